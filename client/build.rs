@@ -1,4 +1,6 @@
 fn main() {
+    // Only the GTK application bundles the icons.
+    #[cfg(feature = "gui")]
     glib_build_tools::compile_resources(
         &["assets"],
         "assets/icons.gresource.xml",
@@ -0,0 +1,44 @@
+//! What the connect view tells assistive technology about connection state,
+//! kept apart from the widgets so it can be tested without a display.
+
+use crate::quic::ConnectPhase;
+
+/// Accessible label of the spinner shown while connecting.
+pub const PROGRESS_LABEL: &str = "Connection progress";
+
+/// Accessible label of the banner that reports a failed connect.
+pub const STATUS_LABEL: &str = "Connection error";
+
+/// The spinner's value text during `phase`: its label without the trailing
+/// ellipsis, which screen readers would otherwise read out.
+pub fn progress_value(phase: ConnectPhase) -> &'static str {
+    phase.label().trim_end_matches('\u{2026}')
+}
+
+/// What the status banner announces when it shows `message`.
+pub fn status_announcement(message: &str) -> String {
+    format!("{STATUS_LABEL}: {message}")
+}
+
+/// The accessible properties a widget is given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessibleText {
+    pub label: String,
+    pub value_text: Option<String>,
+}
+
+/// What the spinner carries during `phase`.
+pub fn spinner_text(phase: ConnectPhase) -> AccessibleText {
+    AccessibleText {
+        label: PROGRESS_LABEL.to_owned(),
+        value_text: Some(progress_value(phase).to_owned()),
+    }
+}
+
+/// What the status banner carries while it shows `message`.
+pub fn banner_text(message: &str) -> AccessibleText {
+    AccessibleText {
+        label: status_announcement(message),
+        value_text: None,
+    }
+}
@@ -0,0 +1,88 @@
+//! Pausing capture while the server's machine can't take input, e.g. with
+//! its screen locked, rather than typing into its lock screen. Servers that
+//! watch for this say so in their welcome and then report every change on a
+//! stream the client keeps open; see [`watch_availability`].
+//!
+//! While paused, capture leaves input to this machine. Keys held when the
+//! pause starts should be released on the server, as their releases won't
+//! be sent.
+
+use std::error::Error;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use quinn::Connection;
+use shared::{ControlRequest, ControlResponse, UnavailableReason};
+
+use crate::quic::{open_bi, read_responses, send_data};
+
+/// What a report changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseChange {
+    /// Stop forwarding input and release what the server holds.
+    Pause(UnavailableReason),
+    Resume,
+}
+
+/// Whether capture is paused for the server, shared between the view that
+/// reads the reports and the capture callback. Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct RemotePause {
+    paused: Arc<AtomicBool>,
+}
+
+impl RemotePause {
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Applies a report from the server, returning what changed. Repeated
+    /// reports of the same state change nothing, so keys are released once
+    /// per pause.
+    pub fn on_response(&self, response: &ControlResponse) -> Option<PauseChange> {
+        match response {
+            ControlResponse::RemoteUnavailable(reason) => {
+                let was_paused = self.paused.swap(true, Ordering::SeqCst);
+                (!was_paused).then_some(PauseChange::Pause(*reason))
+            }
+            ControlResponse::RemoteAvailable => {
+                let was_paused = self.paused.swap(false, Ordering::SeqCst);
+                was_paused.then_some(PauseChange::Resume)
+            }
+            _ => None,
+        }
+    }
+
+    /// Forgets a pause, e.g. once disconnected from the server that asked
+    /// for it.
+    pub fn clear(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Hands each availability report the server pushes to `on_response`.
+/// Returns once the server closes the stream or the connection. Only ask
+/// servers whose welcome set `reports_availability`.
+pub async fn watch_availability<F>(
+    connection: Connection,
+    mut on_response: F,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>>
+where
+    F: FnMut(ControlResponse),
+{
+    let (mut send, mut recv) = open_bi(connection).await?;
+    send_data(
+        &mut send,
+        &rmp_serde::to_vec(&ControlRequest::WatchAvailability)?,
+    )
+    .await?;
+    send.finish()?;
+
+    read_responses(&mut recv, |response| match response {
+        ControlResponse::RemoteAvailable | ControlResponse::RemoteUnavailable(_) => {
+            on_response(response)
+        }
+        other => eprintln!("[client] unexpected availability report: {other:?}"),
+    })
+    .await
+}
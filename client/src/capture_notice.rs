@@ -0,0 +1,57 @@
+//! Warning before capture takes over the keyboard and pointer. Clicking the
+//! input view grabs both, which is alarming the first time and leaves a new
+//! user stuck unless they know the stop chord, so the view first explains
+//! what is about to happen. Shown before the first capture of each run
+//! until the user asks not to see it again, which is kept in
+//! [`ClientSettings::skip_capture_notice`].
+
+use rdev::Key;
+
+use crate::settings::ClientSettings;
+
+/// What stops capture; see the key monitor.
+pub const STOP_CHORD: &str = "Ctrl+Alt+0";
+
+/// Whether capture still needs confirming, for the running client.
+#[derive(Debug, Default)]
+pub struct CaptureNotice {
+    confirmed: bool,
+}
+
+impl CaptureNotice {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to ask before the next capture starts.
+    pub fn needed(&self, settings: &ClientSettings) -> bool {
+        !self.confirmed && !settings.skip_capture_notice
+    }
+
+    /// Records that the user went ahead, not asking again until the client
+    /// restarts, or ever if `dont_show_again`. Returns whether `settings`
+    /// changed and should be saved.
+    pub fn confirm(&mut self, settings: &mut ClientSettings, dont_show_again: bool) -> bool {
+        self.confirmed = true;
+        if dont_show_again && !settings.skip_capture_notice {
+            settings.skip_capture_notice = true;
+            return true;
+        }
+        false
+    }
+}
+
+/// What the notice says, for capture that runs until the stop chord or,
+/// with `hold_key`, only while that key is held.
+pub fn notice_text(hold_key: Option<Key>) -> String {
+    match hold_key {
+        Some(key) => format!(
+            "While capture runs, holding {key:?} sends the keyboard and mouse to the server \
+             instead of this computer. Press {STOP_CHORD} to stop capture."
+        ),
+        None => format!(
+            "Capture sends the keyboard and mouse to the server instead of this computer, \
+             and hides the pointer here. Press {STOP_CHORD} to stop capture and get them back."
+        ),
+    }
+}
@@ -0,0 +1,196 @@
+//! Whether capture can start in this desktop session, and what to tell the
+//! user when it can't.
+//!
+//! On Linux `rdev::grab` reads the devices under `/dev/input` directly and
+//! re-emits what it lets through via `/dev/uinput`, so it works under X11
+//! and Wayland alike as long as this user may open them. It still needs an
+//! X server (XWayland is enough) to name keys and find the pointer. Pointer
+//! warps go through X too, so under Wayland they only move XWayland's
+//! pointer: relative capture then leaves the pointer where it is instead of
+//! recentering it, and absolute pointer mode is the better fit.
+
+use std::{fmt, io};
+
+use rdev::GrabError;
+use shared::session::SessionType;
+
+/// Where `rdev::grab` reads input devices from on Linux.
+pub const INPUT_DEVICES: &str = "/dev/input";
+
+/// Where the README explains the permissions capture needs on each platform.
+pub const PERMISSIONS_HELP_URI: &str = "https://github.com/aellul27/QUICInput#capture-permissions";
+
+/// Opens the Accessibility list in macOS's Privacy & Security settings.
+pub const MACOS_ACCESSIBILITY_URI: &str =
+    "x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility";
+
+const ACCESSIBILITY_HINT: &str =
+    "Allow QUICinput under System Settings → Privacy & Security → Accessibility, then try again.";
+
+const INPUT_GROUP_HINT: &str = "Add this user to the 'input' group (e.g. 'sudo usermod -aG input $USER'), \
+     make sure /dev/uinput is writable by it, then log out and back in.";
+
+#[derive(Debug)]
+pub enum CaptureError {
+    AlreadyRunning,
+    /// `rdev` can't reach an X server in this session.
+    NoXDisplay(SessionType),
+    /// The input devices exist but this user may not read them.
+    NoInputAccess,
+    /// The grab itself failed once started.
+    Grab {
+        session: SessionType,
+        error: GrabError,
+    },
+    /// The grab kept stopping by itself and was given up on.
+    Ended,
+}
+
+/// Broadly why capture didn't start or stopped, so the input view can
+/// respond to each kind differently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureErrorKind {
+    /// Nothing is wrong; capture is running already.
+    AlreadyRunning,
+    /// The OS won't let this user capture input until they change a setting.
+    Permission,
+    /// This session has no display server capture could use.
+    NoDisplay,
+    /// Anything else.
+    Failed,
+}
+
+/// A link to instructions for fixing a capture error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CaptureHelp {
+    pub label: &'static str,
+    pub uri: &'static str,
+}
+
+impl CaptureError {
+    pub fn kind(&self) -> CaptureErrorKind {
+        match self {
+            CaptureError::AlreadyRunning => CaptureErrorKind::AlreadyRunning,
+            CaptureError::NoInputAccess => CaptureErrorKind::Permission,
+            CaptureError::NoXDisplay(_) => CaptureErrorKind::NoDisplay,
+            CaptureError::Grab { error, .. } => match error {
+                GrabError::IoError(io_error)
+                    if io_error.kind() == io::ErrorKind::PermissionDenied =>
+                {
+                    CaptureErrorKind::Permission
+                }
+                // macOS refuses the event tap until the app is trusted for
+                // accessibility.
+                GrabError::EventTapError => CaptureErrorKind::Permission,
+                GrabError::MissingDisplayError | GrabError::KeyboardError => {
+                    CaptureErrorKind::NoDisplay
+                }
+                _ => CaptureErrorKind::Failed,
+            },
+            CaptureError::Ended => CaptureErrorKind::Failed,
+        }
+    }
+
+    /// Where to read how to fix this, for permission errors.
+    pub fn help(&self) -> Option<CaptureHelp> {
+        if self.kind() != CaptureErrorKind::Permission {
+            return None;
+        }
+        Some(match self {
+            CaptureError::Grab {
+                error: GrabError::EventTapError,
+                ..
+            } => CaptureHelp {
+                label: "Open Accessibility settings",
+                uri: MACOS_ACCESSIBILITY_URI,
+            },
+            _ => CaptureHelp {
+                label: "How to give capture access",
+                uri: PERMISSIONS_HELP_URI,
+            },
+        })
+    }
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::AlreadyRunning => f.write_str("Capture is already running."),
+            CaptureError::NoXDisplay(SessionType::Wayland { .. }) => f.write_str(
+                "Capture needs XWayland under Wayland, and DISPLAY isn't set. \
+                 Enable XWayland in your compositor or log in to an X11 session.",
+            ),
+            CaptureError::NoXDisplay(_) => {
+                f.write_str("Capture needs a graphical session, and no display server was found.")
+            }
+            CaptureError::NoInputAccess => write!(
+                f,
+                "Capture can't read the input devices in {INPUT_DEVICES}. {INPUT_GROUP_HINT}"
+            ),
+            CaptureError::Grab { session, error } => {
+                write!(f, "Failed to grab input ({session}): {error:?}.")?;
+                match error {
+                    GrabError::IoError(io_error)
+                        if io_error.kind() == io::ErrorKind::PermissionDenied =>
+                    {
+                        write!(f, " {INPUT_GROUP_HINT}")
+                    }
+                    GrabError::MissingDisplayError | GrabError::KeyboardError => {
+                        f.write_str(" The X server (or XWayland) could not be reached.")
+                    }
+                    GrabError::EventTapError => write!(f, " {ACCESSIBILITY_HINT}"),
+                    _ => Ok(()),
+                }
+            }
+            CaptureError::Ended => {
+                f.write_str("Capture stopped unexpectedly and could not be restarted.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+/// Checks what can be known before grabbing. `input_access` is whether the
+/// input devices could be opened, `None` when there is nothing to check.
+pub fn check_capture(session: SessionType, input_access: Option<bool>) -> Result<(), CaptureError> {
+    if matches!(session, SessionType::Native) {
+        return Ok(());
+    }
+    if !session.has_x_display() {
+        return Err(CaptureError::NoXDisplay(session));
+    }
+    if input_access == Some(false) {
+        return Err(CaptureError::NoInputAccess);
+    }
+    Ok(())
+}
+
+/// Whether any device in [`INPUT_DEVICES`] can be opened for reading, or
+/// `None` off Linux and where the directory can't be listed.
+pub fn input_device_access() -> Option<bool> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let entries = std::fs::read_dir(INPUT_DEVICES).ok()?;
+    let mut seen = false;
+    for entry in entries.flatten() {
+        let is_event = entry.file_name().to_string_lossy().starts_with("event");
+        if !is_event {
+            continue;
+        }
+        seen = true;
+        if std::fs::File::open(entry.path()).is_ok() {
+            return Some(true);
+        }
+    }
+    seen.then_some(false)
+}
+
+/// What capture will do differently in `session`, for logging when it starts.
+pub fn capture_notes(session: SessionType) -> Option<&'static str> {
+    session.is_wayland().then_some(
+        "Wayland won't let the pointer be warped, so it isn't recentered during relative capture; \
+         motion past the edge of the screen is lost. Absolute pointer mode avoids this.",
+    )
+}
@@ -0,0 +1,58 @@
+//! Spotting double-clicks as they are captured, so a [`DoubleClick`] hint
+//! can go ahead of the second press. Off unless `QUICINPUT_DOUBLE_CLICK_MS`
+//! is set, to the longest gap between presses that still counts as a
+//! double-click here: the client's own double-click time is a desktop
+//! setting capture can't read. Servers from before the hint skip it, but
+//! log each one as an unknown payload.
+
+use std::env;
+use std::time::{Duration, Instant};
+
+use rdev::Button;
+use shared::clicks::DoubleClick;
+
+/// Tells which captured presses complete a double-click.
+#[derive(Debug)]
+pub struct ClickTracker {
+    window: Duration,
+    last_press: Option<(Button, Instant)>,
+}
+
+impl ClickTracker {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_press: None,
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Records a press of `button` at `now`, returning the hint to send
+    /// ahead of it when it follows a press of the same button within the
+    /// window. A third quick press starts over rather than hinting again.
+    pub fn on_press(&mut self, button: Button, now: Instant) -> Option<DoubleClick> {
+        let completes = self.last_press.is_some_and(|(last, at)| {
+            last == button && now.saturating_duration_since(at) <= self.window
+        });
+        self.last_press = if completes { None } else { Some((button, now)) };
+        completes.then_some(DoubleClick { button })
+    }
+}
+
+/// The window from `QUICINPUT_DOUBLE_CLICK_MS`, or `None` to send no hints.
+pub fn window_from_env() -> Option<Duration> {
+    let value = env::var("QUICINPUT_DOUBLE_CLICK_MS").ok()?;
+    match value.trim().parse::<u64>() {
+        Ok(0) => None,
+        Ok(ms) => Some(Duration::from_millis(ms)),
+        Err(_) => {
+            eprintln!(
+                "[client] ignoring QUICINPUT_DOUBLE_CLICK_MS={value}: not a number of milliseconds"
+            );
+            None
+        }
+    }
+}
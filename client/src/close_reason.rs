@@ -0,0 +1,75 @@
+//! Why the connection to the server ended, or never started, put into
+//! words for the user.
+
+use quinn::{Connection, ConnectionError, TransportErrorCode};
+use shared::CloseCode;
+
+use crate::quic::ConnectError;
+
+/// What to tell the user about `error`, or `None` when this client closed
+/// the connection itself and already knows why.
+pub fn describe_close(error: &ConnectionError) -> Option<String> {
+    let message = match error {
+        ConnectionError::LocallyClosed => return None,
+        ConnectionError::ApplicationClosed(close) => {
+            let reason = String::from_utf8_lossy(&close.reason);
+            let detail = match CloseCode::try_from(close.error_code) {
+                Ok(code) => code.message().to_string(),
+                Err(unknown) => format!("It gave {unknown}."),
+            };
+            if reason.is_empty() {
+                format!("Server closed the connection. {detail}")
+            } else {
+                format!("Server closed the connection ({reason}). {detail}")
+            }
+        }
+        ConnectionError::TimedOut => {
+            "Connection timed out; the server stopped responding.".to_string()
+        }
+        ConnectionError::Reset => "The server reset the connection.".to_string(),
+        ConnectionError::ConnectionClosed(close)
+            if close.error_code == TransportErrorCode::CONNECTION_REFUSED =>
+        {
+            "The server refused the connection; this computer may not be on its allowlist."
+                .to_string()
+        }
+        error => format!("Connection lost: {error}."),
+    };
+    Some(message)
+}
+
+/// Waits for `connection` to close, then describes why as
+/// [`describe_close`] does.
+pub async fn watch_close(connection: Connection) -> Option<String> {
+    describe_close(&connection.closed().await)
+}
+
+/// What to tell the user when connecting failed with `error`.
+pub fn describe_connect_error(error: &ConnectError) -> String {
+    match error {
+        ConnectError::Socket(error) => {
+            format!("Couldn't open a network socket on this computer: {error}.")
+        }
+        ConnectError::Tls(error) => format!("Couldn't set up encryption: {error}."),
+        ConnectError::InvalidAddress { addr, source } => {
+            format!("{addr} can't be connected to: {source}.")
+        }
+        ConnectError::TimedOut(after) => format!(
+            "The server didn't answer within {} seconds. Check the address and port, \
+             and that the server is running and not blocked by a firewall.",
+            after.as_secs_f64()
+        ),
+        ConnectError::CertificateMismatch { presented } => format!(
+            "The server's certificate doesn't match the pinned one, so the connection \
+             was stopped. It presented {presented}; update the pin only if the \
+             certificate was changed on purpose."
+        ),
+        ConnectError::Refused(error) => describe_close(error)
+            .unwrap_or_else(|| "The server refused the connection.".to_string()),
+        ConnectError::VersionMismatch => {
+            "The server runs a QUIC version this client can't speak.".to_string()
+        }
+        ConnectError::Connection(error) => describe_close(error)
+            .unwrap_or_else(|| "The connection was closed before it was set up.".to_string()),
+    }
+}
@@ -1,19 +1,40 @@
+use gtk4::accessible::Property;
 use gtk4::glib;
 use gtk4::prelude::*;
-use gtk4::{Box, Button, Entry, Image, Label, Orientation, Spinner};
-use quinn::{Connection, Endpoint};
+use gtk4::{
+    Accessible, AccessibleProperty, AccessibleRole, Box, Button, CheckButton, Entry, Label, Orientation, Spinner,
+};
+use libadwaita::Banner;
+#[cfg(feature = "mdns")]
+use gtk4::{ListBox, SelectionMode};
+use shared::SessionToken;
 use std::cell::{Cell, RefCell};
-use std::net::{IpAddr, SocketAddr};
+use std::net::SocketAddr;
 use std::rc::Rc;
-
-use crate::quic::{quic_runtime, run_client};
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::Duration;
+use tokio::task::AbortHandle;
+
+use crate::quic::{
+    congestion_control_from_env, quic_runtime, run_client_with_progress, wire_format_from_env, ClientOptions, ClientSession,
+    ConnectError, ConnectPhase, RetryPolicy,
+};
+use crate::accessibility::{banner_text, spinner_text, AccessibleText};
+use crate::close_reason::describe_connect_error;
+use crate::mirror::{first_to_answer, parse_targets, Attempts};
+use crate::settings::{settings_path, ClientSettings};
+use crate::windowresolution::capture_monitor;
+#[cfg(feature = "mdns")]
+use crate::discovery::{follow, DiscoveredServer, MdnsBrowser};
 
 const OUTER_MARGIN: i32 = 24;
 const COLUMN_SPACING: i32 = 16;
 const INPUT_ROW_SPACING: i32 = 12;
 const STATUS_ROW_SPACING: i32 = 8;
+const PHASE_REFRESH: Duration = Duration::from_millis(50);
 
-type ConnectHandler = dyn Fn(String, u16, Endpoint, Connection);
+// Also handed the further servers named, which input is mirrored to.
+type ConnectHandler = dyn Fn(String, u16, ClientSession, Attempts<ClientSession, ConnectError>);
 
 #[derive(Clone)]
 pub struct ConnectView {
@@ -21,11 +42,17 @@ pub struct ConnectView {
     ip_entry: Entry,
     port_entry: Entry,
     enter_button: Button,
-    status_row: Box,
-    status_label: Label,
+    observe_check: CheckButton,
+    status_banner: Banner,
     spinner_row: Box,
     spinner: Spinner,
+    spinner_label: Label,
+    cancel_button: Button,
     session_id: Rc<Cell<u64>>,
+    // The connect task in flight, so Cancel can abort it.
+    attempt: Rc<RefCell<Option<AbortHandle>>>,
+    // Token from the last server we reached, offered back to it on reconnect.
+    resume: Rc<RefCell<Option<(SocketAddr, SessionToken)>>>,
     on_success: Rc<RefCell<Option<Rc<ConnectHandler>>>>,
 }
 
@@ -37,26 +64,41 @@ impl ConnectView {
         let (input_row, ip_entry, port_entry, enter_button) = build_input_row();
         root.append(&input_row);
 
-        let (spinner_row, spinner) = build_spinner_row();
+        let observe_check = build_observe_check();
+        root.append(&observe_check);
+
+        let (spinner_row, spinner, spinner_label, cancel_button) = build_spinner_row();
         root.append(&spinner_row);
 
-        let (status_row, status_label) = build_status_row();
-        root.append(&status_row);
+        let status_banner = build_status_banner();
+        root.append(&status_banner);
+
+        #[cfg(feature = "mdns")]
+        let (discovery_section, discovery_list) = build_discovery_list();
+        #[cfg(feature = "mdns")]
+        root.append(&discovery_section);
 
         let view = Self {
             root,
             ip_entry,
             port_entry,
             enter_button,
-            status_row,
-            status_label,
+            observe_check,
+            status_banner,
             spinner_row,
             spinner,
+            spinner_label,
+            cancel_button,
             session_id: Rc::new(Cell::new(0)),
+            attempt: Rc::new(RefCell::new(None)),
+            resume: Rc::new(RefCell::new(None)),
             on_success: Rc::new(RefCell::new(None)),
         };
 
         view.wire_enter_button();
+        view.wire_cancel_button();
+        #[cfg(feature = "mdns")]
+        view.wire_discovery(discovery_section, discovery_list);
 
         view
     }
@@ -67,7 +109,7 @@ impl ConnectView {
 
     pub fn set_on_connect<F>(&self, handler: F)
     where
-        F: Fn(String, u16, Endpoint, Connection) + 'static,
+        F: Fn(String, u16, ClientSession, Attempts<ClientSession, ConnectError>) + 'static,
     {
         let handler: Rc<ConnectHandler> = Rc::new(handler);
         self.on_success.borrow_mut().replace(handler);
@@ -75,6 +117,7 @@ impl ConnectView {
 
     pub fn reset(&self) {
         self.bump_session();
+        self.abort_attempt();
         self.hide_status();
         self.hide_spinner();
         self.enter_button.set_sensitive(true);
@@ -89,6 +132,38 @@ impl ConnectView {
         self.ip_entry.grab_focus();
     }
 
+    /// Shows `message` in the status banner, e.g. why the last connection
+    /// ended. What was typed is kept so reconnecting is one click.
+    pub fn show_error(&self, message: &str) {
+        show_status(&self.status_banner, message);
+    }
+
+    /// Abandons the connect in flight and gives the form back, keeping what
+    /// was typed so it can be corrected and retried.
+    pub fn cancel(&self) {
+        self.bump_session();
+        self.abort_attempt();
+        self.hide_spinner();
+        self.enter_button.set_sensitive(true);
+        self.ip_entry.set_sensitive(true);
+        self.port_entry.set_sensitive(true);
+        self.ip_entry.grab_focus();
+    }
+
+    fn wire_cancel_button(&self) {
+        let view = self.clone();
+        self.cancel_button.connect_clicked(move |_button| {
+            view.cancel();
+        });
+    }
+
+    /// Aborting drops the connect future, which closes its endpoint.
+    fn abort_attempt(&self) {
+        if let Some(attempt) = self.attempt.borrow_mut().take() {
+            attempt.abort();
+        }
+    }
+
     fn wire_enter_button(&self) {
         let button_for_ip = self.enter_button.clone();
         self.ip_entry.connect_activate(move |_entry| {
@@ -102,73 +177,112 @@ impl ConnectView {
 
         let ip_entry = self.ip_entry.clone();
         let port_entry = self.port_entry.clone();
-        let status_row = self.status_row.clone();
-        let status_label = self.status_label.clone();
+        let observe_check = self.observe_check.clone();
+        let status_banner = self.status_banner.clone();
         let spinner_row = self.spinner_row.clone();
         let spinner = self.spinner.clone();
+        let spinner_label = self.spinner_label.clone();
         let session_id = self.session_id.clone();
+        let attempt = self.attempt.clone();
+        let resume = self.resume.clone();
         let on_success = self.on_success.clone();
 
         self.enter_button.connect_clicked(move |button| {
-            hide_status(&status_row, &status_label);
-
-            let ip_value = ip_entry.text();
-            let ip = ip_value.trim().to_string();
-            if ip.is_empty() {
-                show_status(&status_row, &status_label, "IP address is required");
-                return;
-            }
+            hide_status(&status_banner);
 
             let port_value = port_entry.text();
             let port = port_value.trim().to_string();
             if port.is_empty() {
-                show_status(&status_row, &status_label, "Port is required");
+                show_status(&status_banner, "Port is required");
                 return;
             }
 
             let portnum = match port.parse::<u16>() {
                 Ok(n) => n,
                 Err(_) => {
-                    show_status(&status_row, &status_label, "Invalid port number");
+                    show_status(&status_banner, "Invalid port number");
                     return;
                 }
             };
 
-            let ip_addr = match ip.parse::<IpAddr>() {
-                Ok(a) => a,
-                Err(_) => {
-                    show_status(&status_row, &status_label, "Invalid IP address");
+            // Several servers may be named; all are tried at once, the first
+            // to answer is connected to here and the rest are mirrored to.
+            let targets = match parse_targets(ip_entry.text().trim(), portnum) {
+                Ok(targets) => targets,
+                Err(err) => {
+                    show_status(&status_banner, &err.to_string());
                     return;
                 }
             };
-            let server_addr = SocketAddr::new(ip_addr, portnum);
+            let observe = observe_check.is_active();
 
+            show_phase(&spinner, &spinner_label, ConnectPhase::Preparing);
             show_spinner(&spinner_row, &spinner);
             button.set_sensitive(false);
             ip_entry.set_sensitive(false);
             port_entry.set_sensitive(false);
 
             let runtime_handle = quic_runtime().handle().clone();
-            let status_row_async = status_row.clone();
-            let status_label_async = status_label.clone();
+            let status_banner_async = status_banner.clone();
             let spinner_row_async = spinner_row.clone();
             let spinner_async = spinner.clone();
             let ip_entry_async = ip_entry.clone();
             let port_entry_async = port_entry.clone();
             let button_async = button.clone();
             let handler_option = on_success.borrow().clone();
-            let ip_for_callback = ip.clone();
             let session_marker = session_id.get();
             let session_id_async = session_id.clone();
+            let last_session = *resume.borrow();
+            let connect_attempts = settings_path()
+                .map_or(RetryPolicy::default().attempts, |path| ClientSettings::load(&path).connect_attempts);
+            let retry = RetryPolicy::from_env_or(connect_attempts);
+            let resume_async = resume.clone();
+
+            // Phases are reported from the QUIC runtime; relay them to the label
+            // until the attempt finishes and drops its sender.
+            let (phase_tx, phase_rx) = mpsc::channel();
+            let phase_spinner = spinner.clone();
+            let phase_label = spinner_label.clone();
+            glib::timeout_add_local(PHASE_REFRESH, move || loop {
+                match phase_rx.try_recv() {
+                    Ok(phase) => show_phase(&phase_spinner, &phase_label, phase),
+                    Err(TryRecvError::Empty) => return glib::ControlFlow::Continue,
+                    Err(TryRecvError::Disconnected) => return glib::ControlFlow::Break,
+                }
+            });
+
+            // Nothing is locked yet; the input view sends the locked
+            // monitor's size once capture starts on it.
+            let display_size = capture_monitor(None).map(|monitor| monitor.size());
+            let task = runtime_handle.spawn(async move {
+                first_to_answer(&targets, |server_addr| {
+                    let mut options = ClientOptions::new(server_addr)
+                        .with_retry(retry)
+                        .with_congestion_control(congestion_control_from_env())
+                        .with_wire_format(wire_format_from_env());
+                    if let Some(display_size) = display_size {
+                        options = options.with_display_size(display_size);
+                    }
+                    let resume_token = last_session
+                        .filter(|(addr, _)| *addr == server_addr)
+                        .map(|(_, token)| token);
+                    let phase_tx = phase_tx.clone();
+                    run_client_with_progress(options, resume_token, observe, move |phase| {
+                        let _ = phase_tx.send(phase);
+                    })
+                })
+                .await
+            });
+            attempt.replace(Some(task.abort_handle()));
+            let attempt_async = attempt.clone();
 
             glib::MainContext::default().spawn_local(async move {
-                let result = runtime_handle
-                    .spawn(async move { run_client(server_addr).await })
-                    .await;
+                let result = task.await;
 
                 if session_id_async.get() != session_marker {
                     return;
                 }
+                attempt_async.replace(None);
 
                 hide_spinner(&spinner_row_async, &spinner_async);
                 button_async.set_sensitive(true);
@@ -176,20 +290,27 @@ impl ConnectView {
                 port_entry_async.set_sensitive(true);
 
                 match result {
-                    Ok(Ok((endpoint, connection))) => {
-                        hide_status(&status_row_async, &status_label_async);
+                    Ok(Ok(answered)) => {
+                        let server_addr = answered.addr;
+                        let session = answered.value;
+                        for (failed_addr, err) in &answered.failed {
+                            println!("Failed to connect to {failed_addr}: {err}");
+                        }
+                        resume_async.replace(session.token.map(|token| (server_addr, token)));
+                        hide_status(&status_banner_async);
                         if let Some(handler) = handler_option {
-                            handler(ip_for_callback, portnum, endpoint, connection);
+                            handler(server_addr.ip().to_string(), server_addr.port(), session, answered.others);
                         }
                     }
-                    Ok(Err(err)) => {
-                        let message = format!("Failed to connect: {err}");
-                        show_status(&status_row_async, &status_label_async, &message);
-                        println!("{message}");
+                    Ok(Err(failures)) => {
+                        for (server_addr, err) in &failures {
+                            println!("Failed to connect to {server_addr}: {err}");
+                        }
+                        show_status(&status_banner_async, &describe_failures(&failures));
                     }
                     Err(join_err) => {
                         let message = format!("Failed to connect: {join_err}");
-                        show_status(&status_row_async, &status_label_async, &message);
+                        show_status(&status_banner_async, &message);
                         println!("{message}");
                     }
                 }
@@ -197,8 +318,65 @@ impl ConnectView {
         });
     }
 
+    #[cfg(feature = "mdns")]
+    fn wire_discovery(&self, section: Box, list: ListBox) {
+        let servers: Rc<RefCell<Vec<DiscoveredServer>>> = Rc::new(RefCell::new(Vec::new()));
+
+        let servers_for_activate = servers.clone();
+        let ip_entry = self.ip_entry.clone();
+        let port_entry = self.port_entry.clone();
+        let enter_button = self.enter_button.clone();
+        list.connect_row_activated(move |_list, row| {
+            if !enter_button.is_sensitive() {
+                return;
+            }
+            let Ok(index) = usize::try_from(row.index()) else {
+                return;
+            };
+            if let Some(server) = servers_for_activate.borrow().get(index) {
+                ip_entry.set_text(&server.addr.ip().to_string());
+                port_entry.set_text(&server.addr.port().to_string());
+                enter_button.emit_clicked();
+            }
+        });
+
+        // Browse only while the view is on screen, so a connected client
+        // isn't chattering on the network.
+        let browse: Rc<RefCell<Option<glib::JoinHandle<()>>>> = Rc::new(RefCell::new(None));
+        let browse_for_map = browse.clone();
+        let section_for_map = section.clone();
+        let list_for_map = list.clone();
+        self.root.connect_map(move |_| {
+            let browser = match MdnsBrowser::start() {
+                Ok(browser) => browser,
+                Err(err) => {
+                    eprintln!("failed to start mDNS discovery: {err}");
+                    return;
+                }
+            };
+            let servers = servers.clone();
+            let section = section_for_map.clone();
+            let list = list_for_map.clone();
+            let task = glib::MainContext::default().spawn_local(follow(browser, move |found| {
+                *servers.borrow_mut() = found.to_vec();
+                show_discovered(&section, &list, found);
+            }));
+            if let Some(previous) = browse_for_map.borrow_mut().replace(task) {
+                previous.abort();
+            }
+        });
+
+        self.root.connect_unmap(move |_| {
+            // Dropping the browser shuts its daemon down.
+            if let Some(task) = browse.borrow_mut().take() {
+                task.abort();
+            }
+            show_discovered(&section, &list, &[]);
+        });
+    }
+
     fn hide_status(&self) {
-        hide_status(&self.status_row, &self.status_label);
+        hide_status(&self.status_banner);
     }
 
     fn hide_spinner(&self) {
@@ -233,6 +411,7 @@ fn build_input_row() -> (Box, Entry, Entry, Button) {
 
     let ip_entry = Entry::new();
     ip_entry.set_placeholder_text(Some("IP address"));
+    ip_entry.set_tooltip_text(Some("Separate several servers with commas to send input to all of them"));
     ip_entry.set_hexpand(true);
 
     let port_entry = Entry::new();
@@ -249,44 +428,115 @@ fn build_input_row() -> (Box, Entry, Entry, Button) {
     (row, ip_entry, port_entry, enter_button)
 }
 
-fn build_status_row() -> (Box, Label) {
+fn build_observe_check() -> CheckButton {
+    let check = CheckButton::with_label("Observe only (watch input without sending any)");
+    check.set_active(false);
+    check
+}
+
+/// An alert, so screen readers announce a failed connect as it appears.
+fn build_status_banner() -> Banner {
+    let banner = Banner::builder()
+        .accessible_role(AccessibleRole::Alert)
+        .revealed(false)
+        .build();
+    banner.add_css_class("error");
+    banner
+}
+
+fn build_spinner_row() -> (Box, Spinner, Label, Button) {
     let row = Box::new(Orientation::Horizontal, STATUS_ROW_SPACING);
     row.set_visible(false);
-    row.add_css_class("error");
 
-    let status_icon = Image::from_icon_name("dialog-error-symbolic");
-    row.append(&status_icon);
+    let spinner = Spinner::builder()
+        .accessible_role(AccessibleRole::ProgressBar)
+        .spinning(false)
+        .build();
+    announce(&spinner, &spinner_text(ConnectPhase::Connecting));
+    row.append(&spinner);
 
-    let label = Label::new(None);
-    label.set_xalign(0.0);
+    // The spinner's value text already says this.
+    let label = Label::builder()
+        .label(ConnectPhase::Connecting.label())
+        .xalign(0.0)
+        .hexpand(true)
+        .accessible_role(AccessibleRole::Presentation)
+        .build();
     row.append(&label);
 
-    (row, label)
+    let cancel_button = Button::with_label("Cancel");
+    row.append(&cancel_button);
+
+    (row, spinner, label, cancel_button)
 }
 
-fn build_spinner_row() -> (Box, Spinner) {
-    let row = Box::new(Orientation::Horizontal, STATUS_ROW_SPACING);
-    row.set_visible(false);
+#[cfg(feature = "mdns")]
+fn build_discovery_list() -> (Box, ListBox) {
+    let section = Box::new(Orientation::Vertical, STATUS_ROW_SPACING);
+    section.set_visible(false);
 
-    let spinner = Spinner::new();
-    spinner.set_spinning(false);
-    row.append(&spinner);
+    let heading = Label::new(Some("Servers on this network"));
+    heading.set_xalign(0.0);
+    heading.add_css_class("heading");
+    section.append(&heading);
 
-    let label = Label::new(Some("Connecting…"));
-    label.set_xalign(0.0);
-    row.append(&label);
+    let list = ListBox::new();
+    list.set_selection_mode(SelectionMode::None);
+    list.add_css_class("boxed-list");
+    section.append(&list);
 
-    (row, spinner)
+    (section, list)
 }
 
-fn hide_status(row: &Box, label: &Label) {
-    label.set_text("");
-    row.set_visible(false);
+#[cfg(feature = "mdns")]
+fn show_discovered(section: &Box, list: &ListBox, servers: &[DiscoveredServer]) {
+    list.remove_all();
+    for server in servers {
+        let label = Label::new(Some(&server.label()));
+        label.set_xalign(0.0);
+        if let Some(fingerprint) = &server.fingerprint {
+            label.set_tooltip_text(Some(&format!("Certificate SHA-256 {fingerprint}")));
+        }
+        list.append(&label);
+    }
+    section.set_visible(!servers.is_empty());
 }
 
-fn show_status(row: &Box, label: &Label, message: &str) {
-    label.set_text(message);
-    row.set_visible(true);
+fn hide_status(banner: &Banner) {
+    banner.set_revealed(false);
+    banner.set_title("");
+    banner.reset_property(AccessibleProperty::Label);
+}
+
+/// What to show when no server answered: the reason alone for a single
+/// server, otherwise each server's reason in turn.
+fn describe_failures(failures: &[(SocketAddr, ConnectError)]) -> String {
+    match failures {
+        [(_, err)] => describe_connect_error(err),
+        failures => failures
+            .iter()
+            .map(|(server_addr, err)| format!("{server_addr}: {}", describe_connect_error(err)))
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+fn show_status(banner: &Banner, message: &str) {
+    banner.set_title(&glib::markup_escape_text(message));
+    announce(banner, &banner_text(message));
+    banner.set_revealed(true);
+}
+
+fn show_phase(spinner: &Spinner, label: &Label, phase: ConnectPhase) {
+    label.set_text(phase.label());
+    announce(spinner, &spinner_text(phase));
+}
+
+fn announce(widget: &impl IsA<Accessible>, text: &AccessibleText) {
+    widget.update_property(&[Property::Label(&text.label)]);
+    if let Some(value_text) = &text.value_text {
+        widget.update_property(&[Property::ValueText(value_text)]);
+    }
 }
 
 fn show_spinner(row: &Box, spinner: &Spinner) {
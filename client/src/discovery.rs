@@ -0,0 +1,190 @@
+//! Servers on the LAN, for the connect view to offer. Servers started with
+//! `--advertise <name>` announce themselves over mDNS; a [`ServerBrowser`]
+//! reports what it hears as [`BrowseEvent`]s, so the view doesn't care how
+//! servers are found and tests can feed it records directly. The real
+//! backend, [`MdnsBrowser`], needs the `mdns` feature.
+
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+
+pub const SERVICE_TYPE: &str = "_quicinput._udp.local.";
+
+/// A service as announced over DNS-SD, before it is made a connect entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServiceRecord {
+    pub fullname: String,
+    pub addresses: Vec<IpAddr>,
+    pub port: u16,
+    /// The server certificate's SHA-256, from the `fingerprint` TXT property.
+    pub fingerprint: Option<String>,
+}
+
+/// A server seen on the LAN, keyed by its mDNS full name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiscoveredServer {
+    pub fullname: String,
+    pub name: String,
+    pub addr: SocketAddr,
+    pub fingerprint: Option<String>,
+}
+
+impl DiscoveredServer {
+    /// The connect entry for `record`, or `None` if it has no address or
+    /// port to connect to.
+    pub fn from_record(record: &ServiceRecord) -> Option<Self> {
+        if record.port == 0 {
+            return None;
+        }
+        // The server binds IPv4 by default, so prefer those addresses.
+        let ip = record
+            .addresses
+            .iter()
+            .find(|ip| ip.is_ipv4())
+            .or_else(|| record.addresses.first())?;
+        let name = record
+            .fullname
+            .strip_suffix(SERVICE_TYPE)
+            .map(|name| name.trim_end_matches('.'))
+            .filter(|name| !name.is_empty())
+            .unwrap_or(&record.fullname)
+            .to_string();
+
+        Some(Self {
+            name,
+            fullname: record.fullname.clone(),
+            addr: SocketAddr::new(*ip, record.port),
+            fingerprint: record.fingerprint.clone(),
+        })
+    }
+
+    pub fn label(&self) -> String {
+        format!("{} — {}", self.name, self.addr)
+    }
+}
+
+/// What a [`ServerBrowser`] hears.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BrowseEvent {
+    Resolved(ServiceRecord),
+    /// The server with this full name went away.
+    Removed(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiscoveryUpdate {
+    Found(DiscoveredServer),
+    Lost(String),
+}
+
+impl DiscoveryUpdate {
+    /// The change `event` makes to the list, if any: records nothing can
+    /// connect to are skipped.
+    pub fn from_event(event: BrowseEvent) -> Option<Self> {
+        match event {
+            BrowseEvent::Resolved(record) => {
+                DiscoveredServer::from_record(&record).map(DiscoveryUpdate::Found)
+            }
+            BrowseEvent::Removed(fullname) => Some(DiscoveryUpdate::Lost(fullname)),
+        }
+    }
+}
+
+/// Applies an update to the list of known servers. Re-announced servers
+/// replace their previous entry so addresses and ports never go stale.
+pub fn apply_update(servers: &mut Vec<DiscoveredServer>, update: DiscoveryUpdate) {
+    match update {
+        DiscoveryUpdate::Found(server) => {
+            match servers
+                .iter_mut()
+                .find(|known| known.fullname == server.fullname)
+            {
+                Some(known) => *known = server,
+                None => servers.push(server),
+            }
+        }
+        DiscoveryUpdate::Lost(fullname) => servers.retain(|known| known.fullname != fullname),
+    }
+}
+
+/// Somewhere servers are found. Browsing runs from creation until the
+/// browser is dropped.
+pub trait ServerBrowser {
+    /// Waits for the next thing heard, or `None` once browsing has stopped.
+    fn next_event(&mut self) -> impl Future<Output = Option<BrowseEvent>>;
+}
+
+/// Browses until `browser` stops, calling `changed` with the servers known
+/// whenever that changes. Drop the future to stop browsing early.
+pub async fn follow<B, F>(mut browser: B, mut changed: F)
+where
+    B: ServerBrowser,
+    F: FnMut(&[DiscoveredServer]),
+{
+    let mut servers = Vec::new();
+    while let Some(event) = browser.next_event().await {
+        if let Some(update) = DiscoveryUpdate::from_event(event) {
+            apply_update(&mut servers, update);
+            changed(&servers);
+        }
+    }
+}
+
+#[cfg(feature = "mdns")]
+pub use mdns::MdnsBrowser;
+
+#[cfg(feature = "mdns")]
+mod mdns {
+    use mdns_sd::{Receiver, ServiceDaemon, ServiceEvent, ServiceInfo};
+
+    use super::{BrowseEvent, SERVICE_TYPE, ServerBrowser, ServiceRecord};
+
+    /// Browses for servers with mdns-sd. The daemon is shut down on drop.
+    pub struct MdnsBrowser {
+        daemon: ServiceDaemon,
+        events: Receiver<ServiceEvent>,
+    }
+
+    impl MdnsBrowser {
+        pub fn start() -> Result<Self, mdns_sd::Error> {
+            let daemon = ServiceDaemon::new()?;
+            let events = daemon.browse(SERVICE_TYPE)?;
+            Ok(Self { daemon, events })
+        }
+    }
+
+    impl ServerBrowser for MdnsBrowser {
+        async fn next_event(&mut self) -> Option<BrowseEvent> {
+            loop {
+                match self.events.recv_async().await.ok()? {
+                    ServiceEvent::ServiceResolved(info) => {
+                        return Some(BrowseEvent::Resolved(record(&info)));
+                    }
+                    ServiceEvent::ServiceRemoved(_, fullname) => {
+                        return Some(BrowseEvent::Removed(fullname));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    impl Drop for MdnsBrowser {
+        fn drop(&mut self) {
+            let _ = self.daemon.shutdown();
+        }
+    }
+
+    fn record(info: &ServiceInfo) -> ServiceRecord {
+        let mut addresses: Vec<_> = info.get_addresses().iter().copied().collect();
+        // A set has no order; sort so the address picked stays the same.
+        addresses.sort();
+        ServiceRecord {
+            fullname: info.get_fullname().to_string(),
+            addresses,
+            port: info.get_port(),
+            fingerprint: info
+                .get_property_val_str("fingerprint")
+                .map(str::to_string),
+        }
+    }
+}
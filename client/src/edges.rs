@@ -0,0 +1,66 @@
+use shared::{DisplayInfo, Edge};
+
+/// Follows where the forwarded pointer sits on the server's display and
+/// reports when it is pushed into one of the edges.
+///
+/// Capture recenters the local cursor after every move, so the position is
+/// rebuilt from the deltas instead of read back from the OS. Each edge
+/// fires once when the pointer moves outward within `band` pixels of it,
+/// then re-arms once the pointer leaves that band again.
+#[derive(Clone, Debug)]
+pub struct EdgeTracker {
+    bounds: DisplayInfo,
+    band: f64,
+    x: f64,
+    y: f64,
+    hit: Vec<Edge>,
+}
+
+impl EdgeTracker {
+    /// Starts at the centre of `bounds`. The server's real pointer may be
+    /// elsewhere at first; both are clamped at the edges, so they line up
+    /// once the pointer has been pushed against one.
+    pub fn new(bounds: DisplayInfo, band: f64) -> Self {
+        let x = f64::from(bounds.x) + f64::from(bounds.width) / 2.0;
+        let y = f64::from(bounds.y) + f64::from(bounds.height) / 2.0;
+        Self {
+            bounds,
+            band: band.max(0.0),
+            x,
+            y,
+            hit: Vec::new(),
+        }
+    }
+
+    pub fn position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    /// Applies one relative move and returns the edges it newly reached.
+    pub fn track(&mut self, dx: f64, dy: f64) -> Vec<Edge> {
+        (self.x, self.y) = self.bounds.clamp(self.x + dx, self.y + dy);
+
+        let left = f64::from(self.bounds.x);
+        let top = f64::from(self.bounds.y);
+        let right = left + f64::from(self.bounds.width.saturating_sub(1));
+        let bottom = top + f64::from(self.bounds.height.saturating_sub(1));
+
+        let edges = [
+            (Edge::Left, self.x - left, dx < 0.0),
+            (Edge::Right, right - self.x, dx > 0.0),
+            (Edge::Top, self.y - top, dy < 0.0),
+            (Edge::Bottom, bottom - self.y, dy > 0.0),
+        ];
+
+        let mut reached = Vec::new();
+        for (edge, distance, outward) in edges {
+            if distance > self.band {
+                self.hit.retain(|hit| *hit != edge);
+            } else if outward && !self.hit.contains(&edge) {
+                self.hit.push(edge);
+                reached.push(edge);
+            }
+        }
+        reached
+    }
+}
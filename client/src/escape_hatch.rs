@@ -0,0 +1,170 @@
+//! Ways out of capture that don't rely on the stop chord. Capture hides the
+//! pointer and holds every key, so if the chord is missed, or the connection
+//! dies and nothing on screen says so, the user is left without a desktop.
+//! The capture callback checks an [`EscapeHatch`] with each event and stops
+//! capture once either
+//!
+//! - Escape has been held for `QUICINPUT_ESCAPE_HOLD_SECS` (default 3), or
+//! - the connection has been closed for `QUICINPUT_DEAD_LINK_SECS`
+//!   (default 10) without a new one taking over.
+//!
+//! 0 turns either off. Both are only noticed when an event arrives; a held
+//! key repeats, and moving the pointer is enough for the dead connection.
+//! The dead connection is timed from when it closed, which a watcher notes
+//! in a [`LinkClosed`] as it happens, so the first event after a long
+//! silence can already find it dead.
+
+use std::env;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rdev::{EventType, Key};
+
+/// The key held to force capture to stop.
+pub const ESCAPE_KEY: Key = Key::Escape;
+
+/// How long [`ESCAPE_KEY`] is held before capture stops, unless overridden.
+pub const DEFAULT_ESCAPE_HOLD: Duration = Duration::from_secs(3);
+
+/// How long capture outlives its connection, unless overridden.
+pub const DEFAULT_DEAD_LINK: Duration = Duration::from_secs(10);
+
+/// Why capture was stopped by an [`EscapeHatch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForcedRelease {
+    /// Escape was held this long.
+    EscapeHeld(Duration),
+    /// The connection had been closed this long.
+    LinkDead(Duration),
+}
+
+impl fmt::Display for ForcedRelease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForcedRelease::EscapeHeld(held) => {
+                write!(f, "Escape held for {:.1}s", held.as_secs_f64())
+            }
+            ForcedRelease::LinkDead(closed) => {
+                write!(f, "Connection closed for {:.1}s", closed.as_secs_f64())
+            }
+        }
+    }
+}
+
+/// Tracks Escape and the connection across the events of one capture.
+#[derive(Debug, Clone, Default)]
+pub struct EscapeHatch {
+    escape_hold: Option<Duration>,
+    dead_link: Option<Duration>,
+    escape_since: Option<Instant>,
+}
+
+impl EscapeHatch {
+    /// `None` turns the matching way out off.
+    pub fn new(escape_hold: Option<Duration>, dead_link: Option<Duration>) -> Self {
+        Self {
+            escape_hold,
+            dead_link,
+            ..Self::default()
+        }
+    }
+
+    /// Timeouts from `QUICINPUT_ESCAPE_HOLD_SECS` and
+    /// `QUICINPUT_DEAD_LINK_SECS`.
+    pub fn from_env() -> Self {
+        Self::new(
+            secs_from_env("QUICINPUT_ESCAPE_HOLD_SECS", DEFAULT_ESCAPE_HOLD),
+            secs_from_env("QUICINPUT_DEAD_LINK_SECS", DEFAULT_DEAD_LINK),
+        )
+    }
+
+    pub fn escape_hold(&self) -> Option<Duration> {
+        self.escape_hold
+    }
+
+    /// Notes `event`, seen at `now` with the connection closed since
+    /// `closed_since` (`None` while it is open), and says why capture should
+    /// stop if it should.
+    pub fn on_event(
+        &mut self,
+        event: &EventType,
+        closed_since: Option<Instant>,
+        now: Instant,
+    ) -> Option<ForcedRelease> {
+        match event {
+            EventType::KeyPress(ESCAPE_KEY) => {
+                self.escape_since.get_or_insert(now);
+            }
+            EventType::KeyRelease(ESCAPE_KEY) => self.escape_since = None,
+            _ => {}
+        }
+
+        let held = self
+            .escape_since
+            .map(|since| now.saturating_duration_since(since));
+        if let (Some(held), Some(limit)) = (held, self.escape_hold)
+            && held >= limit
+        {
+            return Some(ForcedRelease::EscapeHeld(held));
+        }
+        let closed = closed_since.map(|since| now.saturating_duration_since(since));
+        if let (Some(closed), Some(limit)) = (closed, self.dead_link)
+            && closed >= limit
+        {
+            return Some(ForcedRelease::LinkDead(closed));
+        }
+        None
+    }
+}
+
+/// When a connection closed, noted by whatever watches it and read by the
+/// capture callback. Clones share the note.
+#[derive(Debug, Clone, Default)]
+pub struct LinkClosed {
+    // The closed connection's `stable_id`, so a note about one that has
+    // since been replaced is ignored.
+    closed: Arc<Mutex<Option<(usize, Instant)>>>,
+}
+
+impl LinkClosed {
+    /// Notes that the connection `stable_id` closed at `at`.
+    pub fn note(&self, stable_id: usize, at: Instant) {
+        *self.lock() = Some((stable_id, at));
+    }
+
+    /// When the connection `stable_id` closed, if it has been noted.
+    pub fn since(&self, stable_id: usize) -> Option<Instant> {
+        self.lock()
+            .filter(|(closed_id, _)| *closed_id == stable_id)
+            .map(|(_, at)| at)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<(usize, Instant)>> {
+        self.closed.lock().expect("link closed mutex poisoned")
+    }
+}
+
+/// A line telling the user about the Escape hold, or `None` with it off.
+pub fn escape_hint(escape_hold: Option<Duration>) -> Option<String> {
+    escape_hold.map(|hold| {
+        format!(
+            "Holding Escape for {} seconds also stops it.",
+            hold.as_secs_f64()
+        )
+    })
+}
+
+fn secs_from_env(name: &str, default: Duration) -> Option<Duration> {
+    let Ok(value) = env::var(name) else {
+        return Some(default);
+    };
+    match value.trim().parse::<f64>() {
+        Ok(0.0) => None,
+        Ok(secs) if secs.is_finite() && secs > 0.0 => Some(Duration::from_secs_f64(secs)),
+        _ => {
+            eprintln!("[client] ignoring {name}={value}: not a number of seconds");
+            Some(default)
+        }
+    }
+}
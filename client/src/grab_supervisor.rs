@@ -0,0 +1,120 @@
+//! Deciding what to do when the grab behind capture ends. Only the stop
+//! chord is meant to end it; anything else, a grab error, `grab` returning
+//! by itself or the callback panicking, leaves the user without input until
+//! it is noticed. The capture thread reports each ending to a
+//! [`GrabSupervisor`], which restarts the grab a bounded number of times
+//! before giving up and letting the input view reset.
+
+use std::{fmt, time::Duration};
+
+use crate::capture_support::{CaptureError, CaptureErrorKind};
+
+/// How one run of the grab ended.
+#[derive(Debug)]
+pub enum GrabExit {
+    /// Stopped on purpose, by the stop chord.
+    Stopped,
+    /// `grab` failed.
+    Failed(CaptureError),
+    /// `grab` returned without being asked to stop.
+    Ended,
+    /// The grab callback panicked.
+    Panicked,
+}
+
+impl GrabExit {
+    /// Whether running the grab again could help. Errors that will only
+    /// recur, those [`CaptureError::kind`] puts down to permissions or the
+    /// display, give up straight away.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            GrabExit::Stopped => false,
+            GrabExit::Ended | GrabExit::Panicked => true,
+            GrabExit::Failed(error @ CaptureError::Grab { .. }) => {
+                error.kind() == CaptureErrorKind::Failed
+            }
+            GrabExit::Failed(_) => false,
+        }
+    }
+
+    /// What to report to the input view once the supervisor gives up.
+    pub fn into_failure(self) -> Option<CaptureError> {
+        match self {
+            GrabExit::Stopped => None,
+            GrabExit::Failed(error) => Some(error),
+            GrabExit::Ended | GrabExit::Panicked => Some(CaptureError::Ended),
+        }
+    }
+}
+
+impl fmt::Display for GrabExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrabExit::Stopped => f.write_str("stopped"),
+            GrabExit::Failed(error) => write!(f, "{error}"),
+            GrabExit::Ended => f.write_str("the grab ended by itself"),
+            GrabExit::Panicked => f.write_str("the capture callback panicked"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// Restarts allowed in a row before giving up.
+    pub max_restarts: u32,
+    /// Wait before the first restart, doubled for each one after.
+    pub delay: Duration,
+    /// A run lasting this long counts as healthy, so the restarts before
+    /// it no longer count against the limit.
+    pub healthy_after: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 3,
+            delay: Duration::from_millis(250),
+            healthy_after: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SupervisorDecision {
+    /// Run the grab again after `delay`; `attempt` counts from 1.
+    Restart { attempt: u32, delay: Duration },
+    /// Stop for good and reset the input view.
+    GiveUp,
+}
+
+#[derive(Debug, Default)]
+pub struct GrabSupervisor {
+    policy: RestartPolicy,
+    restarts: u32,
+}
+
+impl GrabSupervisor {
+    pub fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy,
+            restarts: 0,
+        }
+    }
+
+    /// Decides what follows a run that lasted `ran_for` and ended with
+    /// `exit`. An intentional stop always ends capture.
+    pub fn on_exit(&mut self, exit: &GrabExit, ran_for: Duration) -> SupervisorDecision {
+        if ran_for >= self.policy.healthy_after {
+            self.restarts = 0;
+        }
+        if !exit.is_retryable() || self.restarts >= self.policy.max_restarts {
+            return SupervisorDecision::GiveUp;
+        }
+        let delay = self.policy.delay.saturating_mul(1 << self.restarts.min(16));
+        self.restarts += 1;
+        SupervisorDecision::Restart {
+            attempt: self.restarts,
+            delay,
+        }
+    }
+}
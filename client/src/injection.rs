@@ -0,0 +1,47 @@
+use std::error::Error;
+
+use quinn::Connection;
+use shared::{ControlRequest, ControlResponse, InjectionStats};
+
+use crate::quic::{open_bi, read_responses, send_data};
+
+/// Hands each injection report the server pushes (about once a second) to
+/// `on_stats`. Returns once the server closes the stream or the connection.
+pub async fn watch_injection<F>(
+    connection: Connection,
+    mut on_stats: F,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>>
+where
+    F: FnMut(InjectionStats),
+{
+    let (mut send, mut recv) = open_bi(connection).await?;
+    send_data(
+        &mut send,
+        &rmp_serde::to_vec(&ControlRequest::WatchInjection)?,
+    )
+    .await?;
+    send.finish()?;
+
+    read_responses(&mut recv, |response| match response {
+        ControlResponse::Injection(stats) => on_stats(stats),
+        other => eprintln!("[client] unexpected injection report: {other:?}"),
+    })
+    .await
+}
+
+/// One-line summary for the input view, e.g.
+/// `Server injected 120 mouse / 8 key events; dropped 3 mouse / 0 key`,
+/// followed by what is still queued when anything is.
+pub fn describe_injection(stats: &InjectionStats) -> String {
+    let mut summary = format!(
+        "Server injected {} mouse / {} key events; dropped {} mouse / {} key",
+        stats.mouse_injected, stats.keys_injected, stats.mouse_dropped, stats.keys_dropped
+    );
+    if stats.mouse_queued > 0 || stats.keys_queued > 0 {
+        summary.push_str(&format!(
+            "; {} mouse / {} key queued",
+            stats.mouse_queued, stats.keys_queued
+        ));
+    }
+    summary
+}
@@ -1,16 +1,64 @@
 use glib::SendWeakRef;
 use gtk4::prelude::*;
-use gtk4::{Align, Box, Button, GestureClick, Label, Orientation};
+use gtk4::{cairo, Align, Box, Button, CheckButton, DrawingArea, DropDown, Entry, EventSequenceState, GestureClick, Label, LinkButton, Orientation, SpinButton, StringList, Switch};
+use libadwaita::prelude::{AdwDialogExt, AlertDialogExt};
+use libadwaita::{AlertDialog, ResponseAppearance};
 use quinn::{Connection, Endpoint};
-use std::cell::RefCell;
+use rdev::Key;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use shared::{script, DisplayInfo, ObservedInput, PointerMode};
+use shared::hex::format_hex;
+use shared::key_combo::{secure_attention_combos, KeyCombo};
+use std::time::Duration;
+use tokio::sync::mpsc as async_mpsc;
 
-use crate::key_monitor::start_global_key_monitor;
+use client::availability::{watch_availability, PauseChange, RemotePause};
+use client::capture_notice::{notice_text, CaptureNotice};
+use client::capture_support::{CaptureError, CaptureErrorKind};
+use client::edges::EdgeTracker;
+use client::escape_hatch::{escape_hint, EscapeHatch};
+use client::injection::{describe_injection, watch_injection};
+use client::key_combo::send_key_combo;
+use client::lock_keys::LockState;
+use client::macros::{load_macro, macros_dir, play, Macro, MacroRecording};
+use client::momentary::CaptureMode;
+use client::observer::watch_observed;
+use client::outbox::OutboxOptions;
+use client::pointer_mode::{request_display_size, request_pointer_mode, PointerModeState};
+use client::raw_debug::send_raw;
+use client::recording::{load_recording, record_path, replay, replay_path};
+use client::release::{release_sweep, send_release_sweep};
+use client::settings::{settings_path, ClientSettings};
+use client::warp::{warp_pointer, Preview};
+
+use crate::key_monitor::{held_keys, start_global_key_monitor, CaptureHandle, CaptureOptions};
+use crate::mirror::Attempts;
+use crate::quic::{
+	congestion_control_from_env, quic_runtime, run_client, wire_format_from_env, ClientOptions, ClientSession,
+	ConnectError, InputLink,
+};
+use crate::quic_helper_thread::{spawn_quic_helper, QuicCommand, SendStats, StreamLayout};
+use crate::windowresolution::{capture_monitor, list_monitors, select_monitor, MonitorChoice, MonitorGeometry};
 
 const OUTER_MARGIN: i32 = 32;
 const INNER_SPACING: i32 = 18;
 const INFO_DEFAULT: &str = "Click here to start capture.";
 const INFO_CAPTURE_ACTIVE: &str = "Type CTRL-ALT-0 to ungrab and stop capture.";
+const INFO_OBSERVING: &str = "Observing. Input the server applies from other clients appears below.";
+const ABSOLUTE_REFUSED: &str = "The server can't position the pointer absolutely; capturing in relative mode.";
+const NO_MONITORS: &str = "No monitors found";
+const EDGE_BAND_DEFAULT: f64 = 2.0;
+const OBSERVED_LINES: usize = 12;
+const OBSERVED_REFRESH: Duration = Duration::from_millis(100);
+const STATS_REFRESH: Duration = Duration::from_secs(1);
+const LOCKS_REFRESH: Duration = Duration::from_millis(100);
+const PREVIEW_WIDTH: i32 = 320;
+const PREVIEW_HEIGHT: i32 = 180;
 
 #[derive(Clone)]
 pub struct InputView {
@@ -20,7 +68,50 @@ pub struct InputView {
 struct InputViewInner {
 	container: Box,
 	info_label: Label,
-	connection: RefCell<Option<(Endpoint, Connection)>>,
+	// Instructions for fixing the last capture error, when there are any.
+	help_link: LinkButton,
+	stats_label: Label,
+	injection_label: Label,
+	// Shown while the server can't take input and capture is paused.
+	remote_label: Label,
+	// Which lock keys are on while capturing, as the keyboard lights can't say.
+	locks_label: Label,
+	// Shown while a macro is being recorded.
+	macro_label: Label,
+	observed_label: Label,
+	monitor_dropdown: DropDown,
+	repeat_switch: Switch,
+	restore_switch: Switch,
+	translate_switch: Switch,
+	edge_switch: Switch,
+	edge_band: SpinButton,
+	ordered_switch: Switch,
+	latency_switch: Switch,
+	mode_label: Label,
+	mirror_label: Label,
+	// What the combo row offers, in the dropdown's order.
+	combos: Vec<(String, KeyCombo)>,
+	// The server's primary display in outline; a click warps its pointer.
+	preview_box: Box,
+	preview: DrawingArea,
+	pointer_mode: RefCell<PointerModeState>,
+	// The capture last started, to hand a new connection to if it still runs.
+	capture: RefCell<Option<CaptureHandle>>,
+	settings: RefCell<ClientSettings>,
+	// Whether capture has been explained this run; see `client::capture_notice`.
+	capture_notice: RefCell<CaptureNotice>,
+	monitors: RefCell<Vec<MonitorGeometry>>,
+	connection: RefCell<Option<(Endpoint, InputLink)>>,
+	// Further servers sent the same input; see `client::mirror`.
+	mirrors: RefCell<Vec<(Endpoint, InputLink)>>,
+	remote_displays: RefCell<Vec<DisplayInfo>>,
+	observing: Cell<bool>,
+	// A recording is being sent in place of capture.
+	replaying: Cell<bool>,
+	// Set while the server reports it can't take input; capture reads it.
+	remote_pause: RemotePause,
+	// Capture feeds it while a macro is recorded; see `client::macros`.
+	macro_recording: MacroRecording,
 }
 
 impl InputView {
@@ -54,16 +145,257 @@ impl InputView {
 
 		container.append(&header_row);
 
+		let monitor_row = Box::new(Orientation::Horizontal, INNER_SPACING);
+		let monitor_label = Label::new(Some("Lock capture to monitor"));
+		monitor_label.set_xalign(0.0);
+		monitor_label.set_hexpand(true);
+		monitor_row.append(&monitor_label);
+
+		let monitor_dropdown = DropDown::from_strings(&[]);
+		monitor_dropdown.set_halign(Align::End);
+		monitor_row.append(&monitor_dropdown);
+
+		container.append(&monitor_row);
+
+		let (repeat_row, repeat_switch) = option_row("Suppress key auto-repeat", true);
+		container.append(&repeat_row);
+
+		let (restore_row, restore_switch) = option_row("Return cursor to start when capture stops", false);
+		container.append(&restore_row);
+
+		let (translate_row, translate_switch) = option_row("Type characters using the server's keyboard layout", false);
+		container.append(&translate_row);
+
+		let (edge_row, edge_switch) = option_row("Report when the pointer reaches a screen edge", false);
+		container.append(&edge_row);
+
+		let band_row = Box::new(Orientation::Horizontal, INNER_SPACING);
+		let band_label = Label::new(Some("Edge trigger band (pixels)"));
+		band_label.set_xalign(0.0);
+		band_label.set_hexpand(true);
+		band_row.append(&band_label);
+
+		let edge_band = SpinButton::with_range(0.0, 200.0, 1.0);
+		edge_band.set_value(EDGE_BAND_DEFAULT);
+		edge_band.set_halign(Align::End);
+		band_row.append(&edge_band);
+
+		edge_switch
+			.bind_property("active", &band_row, "sensitive")
+			.sync_create()
+			.build();
+		container.append(&band_row);
+
+		let (ordered_row, ordered_switch) = option_row("Keep keys and clicks in strict order (single stream)", false);
+		container.append(&ordered_row);
+
+		let (latency_row, latency_switch) = option_row("Measure input latency (logged by the server)", false);
+		container.append(&latency_row);
+
+		let mirror_row = Box::new(Orientation::Horizontal, INNER_SPACING);
+		let mirror_title = Label::new(Some("Mirror input to another server"));
+		mirror_title.set_xalign(0.0);
+		mirror_title.set_hexpand(true);
+		mirror_row.append(&mirror_title);
+
+		let mirror_entry = Entry::new();
+		mirror_entry.set_placeholder_text(Some("ip:port"));
+		mirror_entry.set_halign(Align::End);
+		mirror_row.append(&mirror_entry);
+
+		let mirror_button = Button::with_label("Add");
+		mirror_button.set_halign(Align::End);
+		mirror_row.append(&mirror_button);
+
+		container.append(&mirror_row);
+
+		let mirror_label = Label::new(None);
+		mirror_label.set_xalign(0.0);
+		mirror_label.set_wrap(true);
+		mirror_label.add_css_class("dim-label");
+		mirror_label.set_visible(false);
+		container.append(&mirror_label);
+
+		let settings = settings_path()
+			.map(|path| ClientSettings::load(&path))
+			.unwrap_or_default();
+		let (absolute_row, absolute_switch) = option_row(
+			"Absolute pointer (map this monitor onto the server's screen)",
+			settings.pointer_mode == PointerMode::Absolute,
+		);
+		container.append(&absolute_row);
+
+		let mode_label = Label::new(Some(ABSOLUTE_REFUSED));
+		mode_label.set_xalign(0.0);
+		mode_label.set_wrap(true);
+		mode_label.add_css_class("dim-label");
+		mode_label.set_visible(false);
+		container.append(&mode_label);
+
+		let (hold_row, hold_switch) = option_row(
+			&format!("Capture only while {:?} is held", settings.hold_key),
+			settings.capture_mode == CaptureMode::Hold,
+		);
+		container.append(&hold_row);
+
+		// Shortcuts this machine acts on itself, so capture never sees them.
+		let combos = secure_attention_combos();
+		let combo_row = Box::new(Orientation::Horizontal, INNER_SPACING);
+		let combo_title = Label::new(Some("Send a key combination"));
+		combo_title.set_xalign(0.0);
+		combo_title.set_hexpand(true);
+		combo_row.append(&combo_title);
+
+		let combo_labels: Vec<&str> = combos.iter().map(|(label, _)| label.as_str()).collect();
+		let combo_dropdown = DropDown::from_strings(&combo_labels);
+		combo_dropdown.set_halign(Align::End);
+		combo_row.append(&combo_dropdown);
+
+		let combo_button = Button::with_label("Send");
+		combo_button.set_halign(Align::End);
+		combo_row.append(&combo_button);
+
+		container.append(&combo_row);
+
+		let preview_box = Box::new(Orientation::Vertical, INNER_SPACING / 3);
+		preview_box.set_visible(false);
+		let preview_title = Label::new(Some("Click to move the server's pointer there"));
+		preview_title.set_xalign(0.0);
+		preview_title.add_css_class("dim-label");
+		preview_box.append(&preview_title);
+
+		let preview = DrawingArea::new();
+		preview.set_content_width(PREVIEW_WIDTH);
+		preview.set_content_height(PREVIEW_HEIGHT);
+		preview.set_halign(Align::Start);
+		preview_box.append(&preview);
+		container.append(&preview_box);
+
 		let info_label = Label::new(Some(INFO_DEFAULT));
 		info_label.set_xalign(0.0);
 		info_label.set_wrap(true);
 
+		let help_link = LinkButton::new("about:blank");
+		help_link.set_halign(Align::Start);
+		help_link.set_visible(false);
+
+		let stats_label = Label::new(None);
+		stats_label.set_xalign(0.0);
+		stats_label.add_css_class("dim-label");
+		stats_label.set_visible(false);
+
+		let injection_label = Label::new(None);
+		injection_label.set_xalign(0.0);
+		injection_label.add_css_class("dim-label");
+		injection_label.set_visible(false);
+
+		let remote_label = Label::new(None);
+		remote_label.set_xalign(0.0);
+		remote_label.set_wrap(true);
+		remote_label.add_css_class("warning");
+		remote_label.set_visible(false);
+
+		let locks_label = Label::new(None);
+		locks_label.set_xalign(0.0);
+		locks_label.add_css_class("accent");
+		locks_label.set_visible(false);
+
+		let macro_label = Label::new(Some("Recording a macro. Capture input to add to it."));
+		macro_label.set_xalign(0.0);
+		macro_label.add_css_class("accent");
+		macro_label.set_visible(false);
+
+		let observed_label = Label::new(None);
+		observed_label.set_xalign(0.0);
+		observed_label.add_css_class("monospace");
+		observed_label.set_visible(false);
+
 		let inner = Rc::new(InputViewInner {
 			container: container.clone(),
 			info_label: info_label.clone(),
+			help_link: help_link.clone(),
+			stats_label: stats_label.clone(),
+			injection_label: injection_label.clone(),
+			remote_label: remote_label.clone(),
+			locks_label: locks_label.clone(),
+			macro_label: macro_label.clone(),
+			observed_label: observed_label.clone(),
+			monitor_dropdown,
+			repeat_switch,
+			restore_switch,
+			translate_switch,
+			edge_switch,
+			edge_band,
+			ordered_switch,
+			latency_switch,
+			mode_label,
+			mirror_label,
+			combos,
+			preview_box,
+			preview,
+			pointer_mode: RefCell::new(PointerModeState::new(settings.pointer_mode)),
+			capture: RefCell::new(None),
+			settings: RefCell::new(settings),
+			capture_notice: RefCell::new(CaptureNotice::new()),
+			monitors: RefCell::new(Vec::new()),
 			connection: RefCell::new(None),
+			mirrors: RefCell::new(Vec::new()),
+			remote_displays: RefCell::new(Vec::new()),
+			observing: Cell::new(false),
+			replaying: Cell::new(false),
+			remote_pause: RemotePause::default(),
+			macro_recording: MacroRecording::default(),
+		});
+		inner.refresh_monitors();
+
+		let inner_for_mode = Rc::clone(&inner);
+		absolute_switch.connect_active_notify(move |switch| {
+			let mode = if switch.is_active() {
+				PointerMode::Absolute
+			} else {
+				PointerMode::Relative
+			};
+			inner_for_mode.select_pointer_mode(mode);
+		});
+
+		let inner_for_hold = Rc::clone(&inner);
+		hold_switch.connect_active_notify(move |switch| {
+			let mode = if switch.is_active() {
+				CaptureMode::Hold
+			} else {
+				CaptureMode::Toggle
+			};
+			inner_for_hold.select_capture_mode(mode);
 		});
 
+		let inner_for_combo = Rc::clone(&inner);
+		combo_button.connect_clicked(move |_| {
+			inner_for_combo.send_combo(combo_dropdown.selected());
+		});
+
+		let inner_for_mirror = Rc::clone(&inner);
+		let entry_for_mirror = mirror_entry.clone();
+		mirror_button.connect_clicked(move |_| {
+			inner_for_mirror.add_mirror(&entry_for_mirror);
+		});
+		let inner_for_mirror = Rc::clone(&inner);
+		mirror_entry.connect_activate(move |entry| {
+			inner_for_mirror.add_mirror(entry);
+		});
+
+		let inner_for_draw = Rc::clone(&inner);
+		inner.preview.set_draw_func(move |_, cr, width, height| {
+			inner_for_draw.draw_preview(cr, width, height);
+		});
+		let preview_click = GestureClick::new();
+		let inner_for_warp = Rc::clone(&inner);
+		preview_click.connect_pressed(move |gesture, _, x, y| {
+			// Keeps the click from also starting capture.
+			gesture.set_state(EventSequenceState::Claimed);
+			inner_for_warp.warp_to(x, y);
+		});
+		inner.preview.add_controller(preview_click);
+
 		let clicker = GestureClick::new();
 		let inner_for_click = Rc::clone(&inner);
 		clicker.connect_pressed(move |_, _, _, _| {
@@ -71,6 +403,13 @@ impl InputView {
 		});
 		container.add_controller(clicker);
 		container.append(&info_label);
+		container.append(&help_link);
+		container.append(&stats_label);
+		container.append(&injection_label);
+		container.append(&remote_label);
+		container.append(&locks_label);
+		container.append(&macro_label);
+		container.append(&observed_label);
 
 		Self { inner }
 	}
@@ -79,58 +418,910 @@ impl InputView {
 		self.inner.container.clone()
 	}
 
-	pub fn set_connection(&self, endpoint: Endpoint, connection: Connection) {
+	pub fn set_connection(&self, session: ClientSession) {
+		let input = session.link();
+		if let Some(display) = DisplayInfo::primary(&session.remote_displays) {
+			println!(
+				"Server primary display {} is {}x{}",
+				display.name, display.width, display.height
+			);
+		}
+		self.inner.remote_displays.replace(session.remote_displays);
+		self.inner.show_preview(!session.observing);
+		self.inner.observing.set(session.observing);
+		self.inner.pointer_mode.borrow_mut().reconnected();
+		self.inner.mode_label.set_visible(false);
+		self.inner.remote_pause.clear();
+		self.inner.remote_label.set_visible(false);
+		if session.observing {
+			self.inner.watch_observed(session.connection.clone());
+		} else {
+			self.inner.watch_injection(session.connection.clone());
+			if session.reports_availability {
+				self.inner.watch_availability(input.clone());
+			}
+		}
+		// A capture still running from before the connection dropped keeps
+		// its grab and sends what it queued meanwhile.
+		self.inner.resume_capture(input.clone());
 		self.inner
 			.connection
 			.borrow_mut()
-			.replace((endpoint, connection));
+			.replace((session.endpoint, input));
+		self.inner.refresh_monitors();
 		self.focus();
 	}
 
-	pub fn take_connection(&self) -> Option<(Endpoint, Connection)> {
+	/// Whether the connection in use is the one with this `stable_id`.
+	pub fn is_connected_to(&self, connection_id: usize) -> bool {
+		self.inner
+			.connection
+			.borrow()
+			.as_ref()
+			.is_some_and(|(_, input)| input.connection.stable_id() == connection_id)
+	}
+
+	pub fn take_connection(&self) -> Option<(Endpoint, InputLink)> {
 		self.inner.connection.borrow_mut().take()
 	}
 
+	/// Mirrors input to each server in `attempts` as its connection is made.
+	/// A server that can't be reached is reported without holding up the rest.
+	pub fn add_mirrors(&self, mut attempts: Attempts<ClientSession, ConnectError>) {
+		let inner = Rc::clone(&self.inner);
+		glib::MainContext::default().spawn_local(async move {
+			while let Some(joined) = attempts.join_next().await {
+				match joined {
+					Ok((server_addr, Ok(session))) => inner.add_mirror(server_addr, session),
+					Ok((server_addr, Err(error))) => inner.mirror_failed(server_addr, &error.to_string()),
+					Err(error) => eprintln!("Failed to mirror: {error}"),
+				}
+			}
+		});
+	}
+
+	/// Takes the servers input is mirrored to, for closing.
+	pub fn take_mirrors(&self) -> Vec<(Endpoint, InputLink)> {
+		let mirrors = self.inner.mirrors.take();
+		self.inner.show_mirrors();
+		mirrors
+	}
+
 	pub fn reset(&self) {
 		self.inner.connection.borrow_mut().take();
+		self.inner.mirrors.borrow_mut().clear();
+		self.inner.show_mirrors();
+		self.inner.remote_displays.borrow_mut().clear();
+		self.inner.show_preview(false);
+		self.inner.stats_label.set_visible(false);
+		self.inner.injection_label.set_visible(false);
+		self.inner.injection_label.remove_css_class("error");
+		self.inner.injection_label.add_css_class("dim-label");
+		self.inner.remote_pause.clear();
+		self.inner.remote_label.set_visible(false);
+		self.inner.observing.set(false);
+		self.inner.observed_label.set_label("");
+		self.inner.observed_label.set_visible(false);
 		self.inner.mark_ungrabbed();
 	}
 
+	/// Sends a release for every modifier, button and key capture saw held,
+	/// whether or not capture is running. Does nothing when disconnected.
+	pub fn release_all(&self) {
+		let Some((_, input)) = self.inner.connection.borrow().clone() else {
+			return;
+		};
+		let events = release_sweep(&held_keys());
+		quic_runtime().spawn(async move {
+			match send_release_sweep(input, &events).await {
+				Ok(()) => println!("Sent release for {} keys and buttons", events.len()),
+				Err(error) => eprintln!("Failed to send release sweep: {error}"),
+			}
+		});
+	}
+
+	/// Sends `bytes` untouched on a stream of their own; see
+	/// [`client::raw_debug`]. Returns false when disconnected.
+	pub fn send_raw(&self, bytes: Vec<u8>) -> bool {
+		let Some((_, input)) = self.inner.connection.borrow().clone() else {
+			return false;
+		};
+		quic_runtime().spawn(async move {
+			match send_raw(input, &bytes).await {
+				Ok(()) => println!("Sent {} raw bytes: {}", bytes.len(), format_hex(&bytes)),
+				Err(error) => eprintln!("Failed to send raw bytes: {error}"),
+			}
+		});
+		true
+	}
+
+	/// Records what capture sends from now on into a new macro.
+	pub fn start_macro(&self) {
+		let format = match self.inner.connection.borrow().as_ref() {
+			Some((_, input)) => input.format,
+			None => wire_format_from_env(),
+		};
+		self.inner.macro_recording.start(format);
+		self.inner.macro_label.set_visible(true);
+		println!("Recording a macro");
+	}
+
+	/// Ends the macro being recorded, returning it for saving.
+	pub fn stop_macro(&self) -> Option<Macro> {
+		self.inner.macro_label.set_visible(false);
+		let recorded = self.inner.macro_recording.stop()?;
+		println!("Recorded a macro of {} inputs", recorded.steps.len());
+		Some(recorded)
+	}
+
+	/// Sends the saved macro `name` to the server without capturing. Fails
+	/// with a message to show when it can't start.
+	pub fn play_macro(&self, name: &str) -> Result<(), String> {
+		let Some((_, input)) = self.inner.connection.borrow().clone() else {
+			return Err("Not connected to a server.".to_string());
+		};
+		let dir = macros_dir().ok_or("No config directory to keep macros in.")?;
+		let recorded = load_macro(&dir, name).map_err(|error| format!("Couldn't read macro {name}: {error}"))?;
+		self.inner.play_macro(name.to_string(), recorded, input);
+		Ok(())
+	}
+
 	pub fn focus(&self) {
 		self.inner.container.grab_focus();
 	}
 }
 
 impl InputViewInner {
+	/// Tracks the server's primary display, which is where forwarded moves
+	/// land; unavailable until the server has listed its displays.
+	fn edge_tracker(&self) -> Option<EdgeTracker> {
+		if !self.edge_switch.is_active() {
+			return None;
+		}
+		let displays = self.remote_displays.borrow();
+		let display = DisplayInfo::primary(&displays)?;
+		Some(EdgeTracker::new(display.clone(), self.edge_band.value()))
+	}
+
+	/// Takes effect at the next capture start; remembered for next time.
+	fn select_pointer_mode(&self, mode: PointerMode) {
+		if !self.pointer_mode.borrow_mut().select(mode) {
+			return;
+		}
+		let mut settings = self.settings.borrow_mut();
+		settings.pointer_mode = mode;
+		if let Some(path) = settings_path() {
+			if let Err(error) = settings.save(&path) {
+				eprintln!("Failed to save settings to {}: {error}", path.display());
+			}
+		}
+	}
+
+	/// Takes effect at the next capture start; remembered for next time.
+	fn select_capture_mode(&self, mode: CaptureMode) {
+		let mut settings = self.settings.borrow_mut();
+		settings.capture_mode = mode;
+		if let Some(path) = settings_path() {
+			if let Err(error) = settings.save(&path) {
+				eprintln!("Failed to save settings to {}: {error}", path.display());
+			}
+		}
+	}
+
 	fn start_capture(self: &Rc<Self>) {
+		// Observers only watch; the server would ignore their input anyway.
+		if self.observing.get() {
+			return;
+		}
+		let Some((_, input)) = self.connection.borrow().clone() else {
+			return;
+		};
+		if self.capture_notice.borrow().needed(&self.settings.borrow()) {
+			self.confirm_capture();
+			return;
+		}
+		let (requested, mode) = {
+			let pointer_mode = self.pointer_mode.borrow();
+			let requested = pointer_mode.request_for_capture();
+			(requested, requested.unwrap_or(pointer_mode.in_effect()))
+		};
+		// The hello carried the primary's size; capture may be locked to
+		// another monitor since.
+		let display_size = match mode {
+			PointerMode::Absolute => capture_monitor(self.selected_monitor()).map(|monitor| monitor.size()),
+			PointerMode::Relative => None,
+		};
+		if requested.is_none() && display_size.is_none() {
+			self.begin_capture();
+			return;
+		}
+
+		// Agree the mode with the server first, so it knows which moves to expect.
+		let task = quic_runtime().spawn(async move {
+			if let Some(size) = display_size
+				&& let Err(error) = request_display_size(&input.connection, size).await
+			{
+				eprintln!("Display size request failed: {error}");
+			}
+			match requested {
+				Some(requested) => request_pointer_mode(&input.connection, requested).await.map(Some),
+				None => Ok(None),
+			}
+		});
+		let inner = Rc::clone(self);
+		glib::MainContext::default().spawn_local(async move {
+			let granted = match task.await {
+				Ok(Ok(granted)) => granted,
+				Ok(Err(error)) => {
+					eprintln!("Pointer mode request failed: {error}");
+					None
+				}
+				Err(error) => {
+					eprintln!("Pointer mode request failed: {error}");
+					None
+				}
+			};
+			if let Some(requested) = requested {
+				let refused = {
+					let mut pointer_mode = inner.pointer_mode.borrow_mut();
+					pointer_mode.answered(requested, granted);
+					pointer_mode.absolute_refused()
+				};
+				inner.mode_label.set_visible(refused);
+			}
+			inner.begin_capture();
+		});
+	}
+
+	/// Hands a capture still running to `input`. A new connection starts
+	/// relative, so an absolute capture asks for absolute mode again first.
+	fn resume_capture(self: &Rc<Self>, input: InputLink) {
+		let Some(capture) = self.capture.borrow().clone().filter(CaptureHandle::is_running) else {
+			return;
+		};
+		if capture.pointer_mode() == PointerMode::Relative {
+			if capture.reconnect(input, PointerMode::Relative) {
+				println!("Capture resumed on the new connection");
+			}
+			return;
+		}
+
+		let requested = capture.pointer_mode();
+		let display_size = capture.display_size();
+		let task = quic_runtime().spawn({
+			let connection = input.connection.clone();
+			async move {
+				if let Some(size) = display_size
+					&& let Err(error) = request_display_size(&connection, size).await
+				{
+					eprintln!("Display size request failed: {error}");
+				}
+				request_pointer_mode(&connection, requested).await
+			}
+		});
+		let inner = Rc::clone(self);
+		glib::MainContext::default().spawn_local(async move {
+			let granted = match task.await {
+				Ok(Ok(granted)) => Some(granted),
+				Ok(Err(error)) => {
+					eprintln!("Pointer mode request failed: {error}");
+					None
+				}
+				Err(error) => {
+					eprintln!("Pointer mode request failed: {error}");
+					None
+				}
+			};
+			let (in_effect, refused) = {
+				let mut pointer_mode = inner.pointer_mode.borrow_mut();
+				pointer_mode.answered(requested, granted);
+				(pointer_mode.in_effect(), pointer_mode.absolute_refused())
+			};
+			inner.mode_label.set_visible(refused);
+			if capture.reconnect(input, in_effect) {
+				println!("Capture resumed on the new connection");
+			}
+		});
+	}
+
+	/// Explains what capture does and how to stop it, starting capture only
+	/// once the user goes ahead.
+	fn confirm_capture(self: &Rc<Self>) {
+		let hold_key = self.settings.borrow().hold_trigger();
+		let dont_show = CheckButton::with_label("Don't show this again");
+		let mut text = notice_text(hold_key);
+		if let Some(hint) = escape_hint(EscapeHatch::from_env().escape_hold()) {
+			text.push(' ');
+			text.push_str(&hint);
+		}
+		let dialog = AlertDialog::new(Some("Capture input?"), Some(&text));
+		dialog.set_extra_child(Some(&dont_show));
+		dialog.add_response("cancel", "Cancel");
+		dialog.add_response("capture", "Capture");
+		dialog.set_response_appearance("capture", ResponseAppearance::Suggested);
+		dialog.set_default_response(Some("capture"));
+		dialog.set_close_response("cancel");
+
+		let inner = Rc::clone(self);
+		dialog.connect_response(Some("capture"), move |_, _| {
+			{
+				let mut settings = inner.settings.borrow_mut();
+				let changed = inner.capture_notice.borrow_mut().confirm(&mut settings, dont_show.is_active());
+				if let Some(path) = settings_path().filter(|_| changed) {
+					if let Err(error) = settings.save(&path) {
+						eprintln!("Failed to save settings to {}: {error}", path.display());
+					}
+				}
+			}
+			inner.start_capture();
+		});
+		dialog.present(Some(&self.container));
+	}
+
+	fn begin_capture(self: &Rc<Self>) {
 		let maybe_connection = self.connection.borrow().clone();
-		let Some((endpoint, connection)) = maybe_connection else {
+		let Some((endpoint, input)) = maybe_connection else {
 			return;
 		};
+		if let Some(path) = replay_path() {
+			self.replay(path, input);
+			return;
+		}
 
-		self.mark_grabbed();
+		let (lock_tx, lock_rx) = mpsc::channel();
+		let options = CaptureOptions {
+			monitor: self.selected_monitor(),
+			suppress_repeat: self.repeat_switch.is_active(),
+			restore_cursor: self.restore_switch.is_active(),
+			translate_layout: self.translate_switch.is_active(),
+			edge_tracker: self.edge_tracker(),
+			free_cursor: self.settings.borrow().free_cursor,
+			stream_layout: self.stream_layout(),
+			measure_latency: self.latency_switch.is_active(),
+			pointer_mode: self.pointer_mode.borrow().in_effect(),
+			system_keys: self.settings.borrow().system_keys.clone(),
+			outbox: OutboxOptions::default(),
+			record_to: record_path(),
+			mirrors: self
+				.mirrors
+				.borrow()
+				.iter()
+				.map(|(_, mirror)| mirror.clone())
+				.collect(),
+			hold_key: self.settings.borrow().hold_trigger(),
+			remote_pause: self.remote_pause.clone(),
+			lock_tx: Some(lock_tx),
+			macro_recording: self.macro_recording.clone(),
+		};
+		let (stats_tx, stats_rx) = mpsc::channel();
+		self.mark_grabbed(options.hold_key);
 		let container_weak: SendWeakRef<Box> = self.container.downgrade().into();
 		let label_weak: SendWeakRef<Label> = self.info_label.downgrade().into();
-		let started = start_global_key_monitor(endpoint, connection, move || {
+		let link_weak: SendWeakRef<LinkButton> = self.help_link.downgrade().into();
+		let started = start_global_key_monitor(endpoint, input, options, stats_tx, move |failure| {
 			if let Some(container) = container_weak.upgrade() {
 				container.set_cursor_from_name(None);
 			}
 			if let Some(label) = label_weak.upgrade() {
-				label.set_label(INFO_DEFAULT);
+				label.set_label(&info_after(failure.as_ref()));
+			}
+			if let Some(link) = link_weak.upgrade() {
+				show_help(&link, failure.as_ref());
+			}
+		});
+		match started {
+			Ok(capture) => {
+				self.capture.replace(Some(capture));
+				self.watch_stats(stats_rx);
+				self.watch_locks(lock_rx);
+			}
+			// The click landed while capture was still running; it carries on.
+			Err(error) if error.kind() == CaptureErrorKind::AlreadyRunning => {
+				println!("Capture not started: {error}");
+			}
+			Err(error) => {
+				eprintln!("Capture not started: {error}");
+				self.mark_ungrabbed();
+				self.info_label.set_label(&info_after(Some(&error)));
+				show_help(&self.help_link, Some(&error));
+			}
+		}
+	}
+
+	/// Connects to the server typed in `entry` and mirrors input to it from
+	/// the next capture on.
+	fn add_mirror(self: &Rc<Self>, entry: &Entry) {
+		let text = entry.text();
+		let server_addr: SocketAddr = match text.trim().parse() {
+			Ok(addr) => addr,
+			Err(_) => {
+				self.mirror_label.set_label(&format!("\"{text}\" is not an address such as 192.168.1.20:5000"));
+				self.mirror_label.set_visible(true);
+				return;
+			}
+		};
+		self.connect_mirror(server_addr, Some(entry.clone()));
+	}
+
+	/// Connects to `server_addr` and adds it to the mirrors. `entry`, if it
+	/// named the server, is held while connecting and cleared once it answers.
+	fn connect_mirror(self: &Rc<Self>, server_addr: SocketAddr, entry: Option<Entry>) {
+		let already = self.mirrors.borrow().iter().any(|(_, mirror)| mirror.connection.remote_address() == server_addr);
+		if already {
+			self.show_mirrors();
+			return;
+		}
+
+		if let Some(entry) = &entry {
+			entry.set_sensitive(false);
+		}
+		let options = ClientOptions::new(server_addr)
+			.with_congestion_control(congestion_control_from_env())
+			.with_wire_format(wire_format_from_env());
+		let task = quic_runtime().spawn(async move { run_client(options, None, false).await });
+		let inner = Rc::clone(self);
+		glib::MainContext::default().spawn_local(async move {
+			let result = task.await;
+			if let Some(entry) = &entry {
+				entry.set_sensitive(true);
+			}
+			match result {
+				Ok(Ok(session)) => {
+					if let Some(entry) = &entry {
+						entry.set_text("");
+					}
+					inner.add_mirror(server_addr, session);
+				}
+				Ok(Err(error)) => inner.mirror_failed(server_addr, &error.to_string()),
+				Err(error) => inner.mirror_failed(server_addr, &error.to_string()),
+			}
+		});
+	}
+
+	fn add_mirror(self: &Rc<Self>, server_addr: SocketAddr, session: ClientSession) {
+		println!("Mirroring input to {server_addr}");
+		let connection = session.connection.clone();
+		let mirror = session.link();
+		self.mirrors.borrow_mut().push((session.endpoint, mirror));
+		self.show_mirrors();
+		self.watch_mirror(connection);
+	}
+
+	fn mirror_failed(&self, server_addr: SocketAddr, error: &str) {
+		let message = format!("Failed to mirror to {server_addr}: {error}");
+		eprintln!("{message}");
+		self.mirror_label.set_label(&message);
+		self.mirror_label.set_visible(true);
+	}
+
+	/// Drops `connection` from the list once it closes. A capture already
+	/// mirroring to it drops it by itself.
+	fn watch_mirror(self: &Rc<Self>, connection: Connection) {
+		let id = connection.stable_id();
+		let task = quic_runtime().spawn(async move { connection.closed().await });
+		let inner = Rc::clone(self);
+		glib::MainContext::default().spawn_local(async move {
+			if let Ok(reason) = task.await {
+				println!("Mirror closed: {reason}");
+			}
+			inner.mirrors.borrow_mut().retain(|(_, mirror)| mirror.connection.stable_id() != id);
+			inner.show_mirrors();
+		});
+	}
+
+	fn show_mirrors(&self) {
+		let mirrors = self.mirrors.borrow();
+		let targets: Vec<String> = mirrors
+			.iter()
+			.map(|(_, mirror)| mirror.connection.remote_address().to_string())
+			.collect();
+		self.mirror_label.set_visible(!targets.is_empty());
+		self.mirror_label.set_label(&format!(
+			"Also sending to {} (from the next capture on)",
+			targets.join(", ")
+		));
+	}
+
+	/// Shows the preview if `wanted` and there is a display to preview.
+	fn show_preview(&self, wanted: bool) {
+		let has_display = DisplayInfo::primary(&self.remote_displays.borrow()).is_some();
+		self.preview_box.set_visible(wanted && has_display);
+		self.preview.queue_draw();
+	}
+
+	/// Where the server's primary display sits in the preview as drawn.
+	fn preview_geometry(&self) -> Option<Preview> {
+		let displays = self.remote_displays.borrow();
+		let display = DisplayInfo::primary(&displays)?;
+		Preview::fit(display, f64::from(self.preview.width()), f64::from(self.preview.height()))
+	}
+
+	fn draw_preview(&self, cr: &cairo::Context, width: i32, height: i32) {
+		let displays = self.remote_displays.borrow();
+		let Some(preview) = DisplayInfo::primary(&displays)
+			.and_then(|display| Preview::fit(display, f64::from(width), f64::from(height)))
+		else {
+			return;
+		};
+		cr.rectangle(preview.left, preview.top, preview.width, preview.height);
+		cr.set_source_rgba(0.5, 0.5, 0.5, 0.2);
+		let _ = cr.fill_preserve();
+		cr.set_source_rgba(0.5, 0.5, 0.5, 0.8);
+		cr.set_line_width(1.0);
+		let _ = cr.stroke();
+	}
+
+	/// Moves the server's pointer to the spot clicked in the preview.
+	fn warp_to(&self, x: f64, y: f64) {
+		let Some((_, input)) = self.connection.borrow().clone() else {
+			return;
+		};
+		let Some(preview) = self.preview_geometry() else {
+			return;
+		};
+		let absolute = preview.absolute_at(x, y);
+		quic_runtime().spawn(async move {
+			if let Err(error) = warp_pointer(&input.connection, absolute).await {
+				eprintln!("Failed to move the server's pointer: {error}");
+			}
+		});
+	}
+
+	/// Sends the combo at `index` in the dropdown, whether or not capture
+	/// is running.
+	fn send_combo(&self, index: u32) {
+		if self.observing.get() {
+			return;
+		}
+		let Some((_, input)) = self.connection.borrow().clone() else {
+			return;
+		};
+		let Some((label, combo)) = self.combos.get(index as usize).cloned() else {
+			return;
+		};
+		quic_runtime().spawn(async move {
+			match send_key_combo(input, &combo).await {
+				Ok(()) => println!("Sent {label}"),
+				Err(error) => eprintln!("Failed to send {label}: {error}"),
+			}
+		});
+	}
+
+	fn stream_layout(&self) -> StreamLayout {
+		if self.ordered_switch.is_active() {
+			StreamLayout::Single
+		} else {
+			StreamLayout::Split
+		}
+	}
+
+	/// Sends the recording at `path` at its recorded pace instead of
+	/// starting capture; see [`client::recording`].
+	fn replay(self: &Rc<Self>, path: PathBuf, input: InputLink) {
+		if self.replaying.get() {
+			return;
+		}
+		let recording = match load_recording(&path) {
+			Ok(recording) => recording,
+			Err(error) => {
+				eprintln!("Not replaying {}: {error}", path.display());
+				self.info_label.set_label(&format!("Couldn't read {}: {error}\n{INFO_DEFAULT}", path.display()));
+				return;
+			}
+		};
+		println!("Replaying {} inputs from {}", recording.len(), path.display());
+		self.replaying.set(true);
+		self.info_label.set_label(&format!("Replaying {}…", path.display()));
+
+		let (stats_tx, stats_rx) = mpsc::channel();
+		let sender = spawn_quic_helper(input, stats_tx, self.stream_layout());
+		let task = quic_runtime().spawn(async move {
+			let sent = replay(recording, &sender).await;
+			let _ = sender.send(QuicCommand::Shutdown);
+			sent
+		});
+		self.watch_stats(stats_rx);
+
+		let inner = Rc::clone(self);
+		glib::MainContext::default().spawn_local(async move {
+			let message = match task.await {
+				Ok(sent) => format!("Replayed {sent} inputs.\n{INFO_DEFAULT}"),
+				Err(error) => format!("Replay failed: {error}\n{INFO_DEFAULT}"),
+			};
+			println!("{}", message.lines().next().unwrap_or_default());
+			inner.replaying.set(false);
+			inner.info_label.set_label(&message);
+		});
+	}
+
+	/// Sends `recorded` at its recorded pace through a worker of its own.
+	fn play_macro(self: &Rc<Self>, name: String, recorded: Macro, input: InputLink) {
+		if self.replaying.get() {
+			return;
+		}
+		println!("Playing macro {name}, {} inputs", recorded.steps.len());
+		self.replaying.set(true);
+		self.info_label.set_label(&format!("Playing macro {name}…"));
+
+		let (stats_tx, stats_rx) = mpsc::channel();
+		let format = input.format;
+		let sender = spawn_quic_helper(input, stats_tx, self.stream_layout());
+		let task = quic_runtime().spawn(async move {
+			let sent = play(&recorded, &sender, format).await;
+			let _ = sender.send(QuicCommand::Shutdown);
+			sent
+		});
+		self.watch_stats(stats_rx);
+
+		let inner = Rc::clone(self);
+		glib::MainContext::default().spawn_local(async move {
+			let message = match task.await {
+				Ok(sent) => format!("Played macro {name}, {sent} inputs.\n{INFO_DEFAULT}"),
+				Err(error) => format!("Macro {name} failed: {error}\n{INFO_DEFAULT}"),
+			};
+			println!("{}", message.lines().next().unwrap_or_default());
+			inner.replaying.set(false);
+			inner.info_label.set_label(&message);
+		});
+	}
+
+	/// Polls the QUIC worker's counters once per refresh until it goes away.
+	fn watch_stats(&self, stats_rx: Receiver<SendStats>) {
+		let label = self.stats_label.clone();
+		label.set_label(&format_stats(SendStats::default(), 0.0));
+		label.set_visible(true);
+
+		let mut previous = SendStats::default();
+		glib::timeout_add_local(STATS_REFRESH, move || {
+			let mut latest = previous;
+			loop {
+				match stats_rx.try_recv() {
+					Ok(stats) => latest = stats,
+					Err(TryRecvError::Empty) => break,
+					Err(TryRecvError::Disconnected) => {
+						label.set_label(&format_stats(latest, 0.0));
+						return glib::ControlFlow::Break;
+					}
+				}
+			}
+
+			let events = latest.events.saturating_sub(previous.events);
+			label.set_label(&format_stats(latest, events as f64 / STATS_REFRESH.as_secs_f64()));
+			previous = latest;
+			glib::ControlFlow::Continue
+		});
+	}
+
+	/// Shows which lock keys are on until capture stops, when the sender
+	/// goes away with it.
+	fn watch_locks(&self, lock_rx: Receiver<LockState>) {
+		let label = self.locks_label.clone();
+		glib::timeout_add_local(LOCKS_REFRESH, move || {
+			let mut latest = None;
+			loop {
+				match lock_rx.try_recv() {
+					Ok(locks) => latest = Some(locks),
+					Err(TryRecvError::Empty) => break,
+					Err(TryRecvError::Disconnected) => {
+						label.set_visible(false);
+						return glib::ControlFlow::Break;
+					}
+				}
+			}
+
+			if let Some(locks) = latest {
+				match locks.describe() {
+					Some(text) => {
+						label.set_label(&text);
+						label.set_visible(true);
+					}
+					None => label.set_visible(false),
+				}
+			}
+			glib::ControlFlow::Continue
+		});
+	}
+
+	/// Shows the server's injected and dropped totals as it reports them, so
+	/// input the server can't apply doesn't go unnoticed.
+	fn watch_injection(&self, connection: Connection) {
+		let (stats_tx, mut stats_rx) = async_mpsc::unbounded_channel();
+		quic_runtime().spawn(async move {
+			let result = watch_injection(connection, move |stats| {
+				let _ = stats_tx.send(stats);
+			})
+			.await;
+			if let Err(error) = result {
+				eprintln!("Injection reports ended: {error}");
+			}
+		});
+
+		let label = self.injection_label.clone();
+		glib::MainContext::default().spawn_local(async move {
+			while let Some(stats) = stats_rx.recv().await {
+				label.set_label(&describe_injection(&stats));
+				label.set_visible(true);
+				if stats.mouse_dropped + stats.keys_dropped > 0 {
+					label.remove_css_class("dim-label");
+					label.add_css_class("error");
+				}
+			}
+		});
+	}
+
+	/// Pauses capture while the server reports it can't take input, e.g.
+	/// with its screen locked, releasing what it holds as the pause starts.
+	fn watch_availability(&self, input: InputLink) {
+		let (report_tx, mut report_rx) = async_mpsc::unbounded_channel();
+		let watched = input.connection.clone();
+		quic_runtime().spawn(async move {
+			let result = watch_availability(watched, move |response| {
+				let _ = report_tx.send(response);
+			})
+			.await;
+			if let Err(error) = result {
+				eprintln!("Availability reports ended: {error}");
+			}
+		});
+
+		let pause = self.remote_pause.clone();
+		let label = self.remote_label.clone();
+		glib::MainContext::default().spawn_local(async move {
+			while let Some(response) = report_rx.recv().await {
+				match pause.on_response(&response) {
+					Some(PauseChange::Pause(reason)) => {
+						label.set_label(&format!("Server {reason}; capture paused until it takes input again."));
+						label.set_visible(true);
+						let input = input.clone();
+						let events = release_sweep(&held_keys());
+						quic_runtime().spawn(async move {
+							if let Err(error) = send_release_sweep(input, &events).await {
+								eprintln!("Failed to release keys on pause: {error}");
+							}
+						});
+					}
+					Some(PauseChange::Resume) => label.set_visible(false),
+					None => {}
+				}
 			}
 		});
-		if !started {
-			self.mark_ungrabbed();
+	}
+
+	/// Shows the most recent input the server applied, until it closes the stream.
+	fn watch_observed(&self, connection: Connection) {
+		let (observed_tx, observed_rx) = mpsc::channel();
+		quic_runtime().spawn(async move {
+			let result = watch_observed(connection, move |input| {
+				let _ = observed_tx.send(input);
+			})
+			.await;
+			if let Err(error) = result {
+				eprintln!("Observer stream ended: {error}");
+			}
+		});
+
+		self.info_label.set_label(INFO_OBSERVING);
+		let label = self.observed_label.clone();
+		label.set_visible(true);
+
+		let mut recent = VecDeque::with_capacity(OBSERVED_LINES);
+		glib::timeout_add_local(OBSERVED_REFRESH, move || {
+			loop {
+				match observed_rx.try_recv() {
+					Ok(input) => {
+						if recent.len() == OBSERVED_LINES {
+							recent.pop_front();
+						}
+						recent.push_back(describe_observed(&input));
+					}
+					Err(TryRecvError::Empty) => break,
+					Err(TryRecvError::Disconnected) => return glib::ControlFlow::Break,
+				}
+			}
+
+			let lines: Vec<&str> = recent.iter().map(String::as_str).collect();
+			label.set_label(&lines.join("\n"));
+			glib::ControlFlow::Continue
+		});
+	}
+
+	/// Lists the monitors again, keeping the one picked before if it is
+	/// still there and the primary otherwise.
+	fn refresh_monitors(&self) {
+		let monitors = list_monitors();
+		let choice = match self.selected_monitor() {
+			Some(previous) => MonitorChoice::Named(previous.name),
+			None => MonitorChoice::Primary,
+		};
+		let labels: Vec<String> = if monitors.is_empty() {
+			vec![NO_MONITORS.to_string()]
+		} else {
+			monitors.iter().map(MonitorGeometry::label).collect()
+		};
+		let label_refs: Vec<&str> = labels.iter().map(String::as_str).collect();
+		let model = StringList::new(&label_refs);
+		self.monitor_dropdown.set_model(Some(&model));
+		self.monitor_dropdown.set_sensitive(monitors.len() > 1);
+		if let Some(monitor) = select_monitor(&monitors, &choice) {
+			self.monitor_dropdown.set_selected(monitor.index as u32);
 		}
+		self.monitors.replace(monitors);
 	}
 
-	fn mark_grabbed(&self) {
-		self.container.set_cursor_from_name(Some("none"));
-		self.info_label.set_label(INFO_CAPTURE_ACTIVE);
+	fn selected_monitor(&self) -> Option<MonitorGeometry> {
+		let index = self.monitor_dropdown.selected() as usize;
+		self.monitors.borrow().get(index).cloned()
+	}
+
+	fn mark_grabbed(&self, hold_key: Option<Key>) {
+		match hold_key {
+			// The pointer stays usable here until the key is held.
+			Some(key) => self.info_label.set_label(&format!("Hold {key:?} to send input. {INFO_CAPTURE_ACTIVE}")),
+			// Left visible on purpose; see `ClientSettings::free_cursor`.
+			None if self.settings.borrow().free_cursor => self.info_label.set_label(INFO_CAPTURE_ACTIVE),
+			None => {
+				self.container.set_cursor_from_name(Some("none"));
+				self.info_label.set_label(INFO_CAPTURE_ACTIVE);
+			}
+		}
+		self.help_link.set_visible(false);
 	}
 
 	fn mark_ungrabbed(&self) {
 		self.container.set_cursor_from_name(None);
 		self.info_label.set_label(INFO_DEFAULT);
+		self.help_link.set_visible(false);
 	}
 }
+
+/// What the info label says once capture has stopped, or failed to start.
+fn info_after(failure: Option<&CaptureError>) -> String {
+	match failure {
+		Some(error) => format!("{error}\n{INFO_DEFAULT}"),
+		None => INFO_DEFAULT.to_string(),
+	}
+}
+
+/// Offers instructions for fixing `failure`, if it has any.
+fn show_help(link: &LinkButton, failure: Option<&CaptureError>) {
+	match failure.and_then(CaptureError::help) {
+		Some(help) => {
+			link.set_uri(help.uri);
+			link.set_label(help.label);
+			link.set_visible(true);
+		}
+		None => link.set_visible(false),
+	}
+}
+
+/// A labelled switch for a capture option.
+fn option_row(label: &str, active: bool) -> (Box, Switch) {
+	let row = Box::new(Orientation::Horizontal, INNER_SPACING);
+	let row_label = Label::new(Some(label));
+	row_label.set_xalign(0.0);
+	row_label.set_hexpand(true);
+	row.append(&row_label);
+
+	let switch = Switch::new();
+	switch.set_active(active);
+	switch.set_halign(Align::End);
+	switch.set_valign(Align::Center);
+	row.append(&switch);
+
+	(row, switch)
+}
+
+fn describe_observed(input: &ObservedInput) -> String {
+	match input {
+		ObservedInput::Mouse(mouse_move) => script::format_move(mouse_move),
+		ObservedInput::Event(event) => script::format_event(event),
+	}
+}
+
+fn format_stats(stats: SendStats, events_per_sec: f64) -> String {
+	format!(
+		"Sent {:.1} KiB · {:.0} events/s",
+		stats.bytes as f64 / 1024.0,
+		events_per_sec
+	)
+}
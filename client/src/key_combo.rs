@@ -0,0 +1,25 @@
+//! Sending a [`KeyCombo`] from a button, for shortcuts such as Ctrl+Alt+Del
+//! that this machine's OS acts on itself, so capture never sees them. The
+//! combo goes on a uni stream of its own and the server presses it whole;
+//! see [`shared::key_combo`] for what each target OS makes of it.
+
+use std::error::Error;
+
+use shared::{codec::Codec, key_combo::KeyCombo};
+
+use crate::quic::{send_data, InputLink};
+
+/// Has the server press and release `combo`. Capture needn't be running.
+pub async fn send_key_combo(
+    input: InputLink,
+    combo: &KeyCombo,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    if let Some(problem) = combo.problem() {
+        return Err(problem.into());
+    }
+    let bytes = input.format.encode(combo)?;
+    let mut send = input.open_uni().await?;
+    send_data(&mut send, &bytes).await?;
+    send.finish()?;
+    Ok(())
+}
@@ -0,0 +1,31 @@
+//! Sorts captured key events by what they do to the set of keys held, so
+//! ones that would double-count on the server can be dropped.
+//!
+//! Some platforms deliver a second release for a key already released, or
+//! a release for a key pressed before capture began; neither means
+//! anything to the server. A press for a key already held is usually OS
+//! auto-repeat but can be a spurious duplicate, and the two can't be told
+//! apart, so whether to forward it is left to the caller: see
+//! `CaptureOptions::suppress_repeat`.
+
+use rdev::{EventType, Key};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyVerdict {
+    /// A press of a key not held, or the release of one that is.
+    Forward,
+    /// A press of a key already held.
+    Repeat,
+    /// A release of a key not held.
+    StrayRelease,
+}
+
+/// What `event_type` is given the keys in `pressed`. Anything but a key
+/// event is [`KeyVerdict::Forward`].
+pub fn filter_key_event(pressed: &[Key], event_type: &EventType) -> KeyVerdict {
+    match *event_type {
+        EventType::KeyPress(key) if pressed.contains(&key) => KeyVerdict::Repeat,
+        EventType::KeyRelease(key) if !pressed.contains(&key) => KeyVerdict::StrayRelease,
+        _ => KeyVerdict::Forward,
+    }
+}
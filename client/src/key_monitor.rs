@@ -1,34 +1,186 @@
 use libadwaita::glib;
 use quinn::{Connection, Endpoint};
-use rdev::{grab, simulate, Event, EventType, Key};
+use rdev::{grab, simulate, Button, Event, EventType, Key};
 #[cfg(target_os = "macos")]
 use rdev::set_is_main_thread;
-use shared::MouseMove;
+use shared::{monotonic_micros, CharInput, DisplaySize, EdgeHit, MouseMove, PointerMode, SentAt};
+#[cfg(target_os = "macos")]
+use shared::Gesture;
+use shared::codec::{Codec, WireFormat};
+use shared::layout::{KeyboardLayout, Keystroke};
+use shared::extra_keys::ExtraKeyInput;
+use shared::raw_keys::RawKeyInput;
+use shared::session::SessionType;
+use shared::system_keys::{capture_limitation, SystemKeyFilter};
 use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::thread::{self};
-
-use crate::quic_helper_thread::{spawn_quic_helper, QuicCommand, QuicSender};
+use std::time::{Duration, Instant};
+
+use crate::availability::RemotePause;
+use crate::capture_support::{capture_notes, check_capture, input_device_access, CaptureError};
+use crate::clicks::{self, ClickTracker};
+use crate::edges::EdgeTracker;
+use crate::escape_hatch::{escape_hint, EscapeHatch, LinkClosed};
+use crate::grab_supervisor::{GrabExit, GrabSupervisor, RestartPolicy, SupervisorDecision};
+use crate::key_filter::{filter_key_event, KeyVerdict};
+use crate::lock_keys::LockState;
+use crate::macros::MacroRecording;
+use crate::outbox::{Outbox, OutboxOptions};
+use crate::mirror::spawn_mirrors;
+use crate::momentary::{HoldAction, HoldTrigger};
+use crate::quic::{quic_runtime, InputLink};
+use crate::quic_helper_thread::{recenter_margin, spawn_quic_helper, QuicCommand, SendStats, StreamLayout};
+use crate::recording::Recorder;
+use crate::system_layout::SystemLayout;
+use crate::watchdog::{timeout_from_env, ui_unresponsive_for, UI_HEARTBEAT};
 
 static IGNORE_MOUSE: AtomicBool = AtomicBool::new(false);
 
-use crate::windowresolution::find_window_size;
+use crate::windowresolution::{
+    active_monitor, capture_monitor, clamp_to_desktop, cursor_position, MonitorGeometry,
+};
 
 static MONITOR_RUNNING: AtomicBool = AtomicBool::new(false);
 
-type UngrabCallback = Box<dyn Fn() + Send + 'static>;
+/// Set by the stop chord, so a grab that returns afterwards isn't taken for
+/// one that ended by itself.
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+const MOVE_STAMP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Called once capture ends, with the reason if it failed.
+type UngrabCallback = Box<dyn Fn(Option<CaptureError>) + Send + 'static>;
+
+/// How a capture session behaves, chosen in the input view before it starts.
+#[derive(Clone, Debug)]
+pub struct CaptureOptions {
+    /// Monitor to recenter within; `None` falls back to the primary display.
+    pub monitor: Option<MonitorGeometry>,
+    /// Forward only the first press of a held key and let the server's OS
+    /// generate its own auto-repeat. Off, repeats are forwarded as presses.
+    pub suppress_repeat: bool,
+    /// Put the pointer back where it was before capture once it stops.
+    pub restore_cursor: bool,
+    /// Send the character each press types on this machine's layout, for the
+    /// server to retype on its own, instead of the raw keycode.
+    pub translate_layout: bool,
+    /// Follows the pointer on the server's display and sends an [`EdgeHit`]
+    /// along with any move that pushes it into an edge.
+    pub edge_tracker: Option<EdgeTracker>,
+    /// Leave the local pointer where it is moved instead of recentering it
+    /// after each move; relative moves are still sent.
+    pub free_cursor: bool,
+    /// Whether mouse and keyboard share one stream to keep their order.
+    pub stream_layout: StreamLayout,
+    /// Stamp input with its send time so the server can log end-to-end
+    /// latency. Moves are only stamped every [`MOVE_STAMP_INTERVAL`], as a
+    /// stamp stops the worker merging them.
+    pub measure_latency: bool,
+    /// Agreed with the server before capture starts. In absolute mode the
+    /// local pointer roams the capture monitor and its position there is
+    /// sent instead of deltas.
+    pub pointer_mode: PointerMode,
+    /// Keys the OS may intercept; see [`shared::system_keys`].
+    pub system_keys: Vec<Key>,
+    /// How input is held while the connection is down; see [`crate::outbox`].
+    pub outbox: OutboxOptions,
+    /// Record everything sent to this file; see [`crate::recording`].
+    pub record_to: Option<PathBuf>,
+    /// Other servers to send the same input to; see [`crate::mirror`].
+    pub mirrors: Vec<InputLink>,
+    /// Forward input only while this key is held, leaving it to this
+    /// machine otherwise; see [`crate::momentary`]. `None` forwards
+    /// everything until the stop chord.
+    pub hold_key: Option<Key>,
+    /// Set while the server can't take input; capture then leaves input to
+    /// this machine. See [`crate::availability`].
+    pub remote_pause: RemotePause,
+    /// Told the lock keys' state as capture starts and whenever a forwarded
+    /// press toggles one; see [`crate::lock_keys`].
+    pub lock_tx: Option<Sender<LockState>>,
+    /// Fed everything sent while a macro is being recorded; see
+    /// [`crate::macros`].
+    pub macro_recording: MacroRecording,
+}
+
+/// A connection for a running capture to switch to, picked up with the next
+/// captured event, and the pointer mode the server granted on it.
+struct Reconnect {
+    input: InputLink,
+    pointer_mode: PointerMode,
+}
+
+/// The caller's hold on a capture started by [`start_global_key_monitor`].
+#[derive(Clone)]
+pub struct CaptureHandle {
+    reconnect_tx: Sender<Reconnect>,
+    pointer_mode: PointerMode,
+    display_size: Option<DisplaySize>,
+    running: Arc<AtomicBool>,
+}
 
-pub fn start_global_key_monitor<F>(endpoint: Endpoint, connection: Connection, on_ungrab: F) -> bool
+impl CaptureHandle {
+    /// The pointer mode the capture started in.
+    pub fn pointer_mode(&self) -> PointerMode {
+        self.pointer_mode
+    }
+
+    /// Size of the monitor an absolute capture maps from, to tell a new
+    /// connection; `None` for a relative one.
+    pub fn display_size(&self) -> Option<DisplaySize> {
+        self.display_size
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Hands the capture a new connection after the old one dropped, keeping
+    /// the grab. A new connection starts in relative mode, so an absolute
+    /// capture should ask it for absolute mode first and pass on what was
+    /// granted as `pointer_mode`; a relative one is then sent instead of
+    /// positions the server would ignore. Returns false once capture has
+    /// stopped.
+    pub fn reconnect(&self, input: InputLink, pointer_mode: PointerMode) -> bool {
+        self.is_running()
+            && self
+                .reconnect_tx
+                .send(Reconnect {
+                    input,
+                    pointer_mode,
+                })
+                .is_ok()
+    }
+}
+
+/// Starts capture on its own thread. Fails straight away when capture is
+/// already running or can't work in this session. A grab that stops once
+/// started, other than by the stop chord, is restarted as
+/// [`crate::grab_supervisor`] decides, and reported through `on_ungrab` if
+/// it is given up on.
+pub fn start_global_key_monitor<F>(
+    endpoint: Endpoint,
+    input: InputLink,
+    options: CaptureOptions,
+    stats_tx: Sender<SendStats>,
+    on_ungrab: F,
+) -> Result<CaptureHandle, CaptureError>
 where
-    F: Fn() + Send + 'static,
+    F: Fn(Option<CaptureError>) + Send + 'static,
 {
+    let session = SessionType::detect();
+    check_capture(session, input_device_access())?;
+
     let already_running = MONITOR_RUNNING
         .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
         .is_err();
     if already_running {
         println!("Global key monitor already running");
-        return false;
+        return Err(CaptureError::AlreadyRunning);
     }
 
     {
@@ -38,79 +190,483 @@ where
         *slot = Some(Box::new(on_ungrab));
     }
 
+    let (reconnect_tx, reconnect_rx) = mpsc::channel();
+    let reconnects = Arc::new(Mutex::new(reconnect_rx));
+    let running = Arc::new(AtomicBool::new(true));
+    let handle = CaptureHandle {
+        reconnect_tx,
+        pointer_mode: options.pointer_mode,
+        display_size: match options.pointer_mode {
+            PointerMode::Absolute => capture_monitor(options.monitor.clone()).map(|monitor| monitor.size()),
+            PointerMode::Relative => None,
+        },
+        running: Arc::clone(&running),
+    };
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    // Called from the main loop, so it has just been alive.
+    UI_HEARTBEAT.ping();
+    let ui_timeout = timeout_from_env();
+    let escape_hatch = EscapeHatch::from_env();
+    let link_closed = LinkClosed::default();
+    watch_close(input.connection.clone(), link_closed.clone());
+
     thread::spawn(move || {
-        let endpoint_for_run = endpoint.clone();
-        let connection_for_run = connection.clone();
-        let result = panic::catch_unwind(AssertUnwindSafe(move || {
-            run_key_monitor(endpoint_for_run, connection_for_run);
-        }));
-        MONITOR_RUNNING.store(false, Ordering::SeqCst);
-        notify_ungrab();
-        match result {
-            Ok(()) => println!("Global key monitor stopped"),
-            Err(err) => {
-                if err.downcast_ref::<MonitorStop>().is_some() {
-                    println!("Global key monitor stopped");
-                } else {
-                    panic::resume_unwind(err);
+        // Taken once, so a restart doesn't mistake the recentered pointer
+        // for where it was before capture.
+        let restore_to = if options.restore_cursor && session.can_warp_pointer() {
+            cursor_position()
+        } else {
+            None
+        };
+        let latest = Arc::new(Mutex::new(input));
+        let mut options = options;
+        let mut supervisor = GrabSupervisor::new(RestartPolicy::default());
+        let failure = loop {
+            let started = Instant::now();
+            let run = RunContext {
+                _endpoint: endpoint.clone(),
+                latest: Arc::clone(&latest),
+                reconnects: Arc::clone(&reconnects),
+                restore_to,
+                ui_timeout,
+                escape_hatch: escape_hatch.clone(),
+                link_closed: link_closed.clone(),
+            };
+            let options_for_run = options.clone();
+            let stats_for_run = stats_tx.clone();
+            let result = panic::catch_unwind(AssertUnwindSafe(move || {
+                run_key_monitor(run, options_for_run, stats_for_run, session)
+            }));
+            let exit = match result {
+                Ok(Ok(())) if STOP_REQUESTED.swap(false, Ordering::SeqCst) => GrabExit::Stopped,
+                Ok(Ok(())) => GrabExit::Ended,
+                Ok(Err(error)) => GrabExit::Failed(error),
+                Err(payload) if payload.downcast_ref::<MonitorStop>().is_some() => GrabExit::Stopped,
+                Err(_) => GrabExit::Panicked,
+            };
+            if matches!(exit, GrabExit::Stopped) {
+                break None;
+            }
+            match supervisor.on_exit(&exit, started.elapsed()) {
+                SupervisorDecision::Restart { attempt, delay } => {
+                    eprintln!("Capture interrupted ({exit}); restarting, attempt {attempt}");
+                    if let Some(path) = options.record_to.take() {
+                        println!("Recording to {} stops here; a restart would overwrite it", path.display());
+                    }
+                    thread::sleep(delay);
+                }
+                SupervisorDecision::GiveUp => {
+                    eprintln!("Capture interrupted ({exit}); giving up");
+                    break exit.into_failure();
                 }
             }
-        }
+        };
+        running.store(false, Ordering::SeqCst);
+        MONITOR_RUNNING.store(false, Ordering::SeqCst);
+        notify_ungrab(failure);
+        println!("Global key monitor stopped");
     });
 
-    true
+    Ok(handle)
+}
+
+fn send_data(outbox: &mut Outbox, command: QuicCommand) {
+    let was_connected = outbox.is_connected();
+    outbox.send(command);
+    if was_connected && !outbox.is_connected() {
+        println!("Connection lost; holding input until it is back. Ctrl+Alt+0 still stops capture.");
+    }
+}
+
+/// Tells the send worker when whether anything is held changes, so it keeps
+/// the server from releasing it for want of input; see [`shared::KeepHeld`].
+fn note_holding(outbox: &mut Outbox, holding: &mut bool, held: bool) {
+    if held != *holding {
+        *holding = held;
+        send_data(outbox, QuicCommand::Holding(held));
+    }
+}
+
+/// Sends a [`SentAt`] for the input about to follow on the same stream.
+/// Queued input would arrive late anyway, so it isn't stamped.
+fn send_stamp(outbox: &mut Outbox, format: WireFormat, keyboard: bool) {
+    if !outbox.is_connected() {
+        return;
+    }
+    let buf = format.encode(&SentAt { micros: monotonic_micros() }).expect("failed to serialise");
+    let command = if keyboard {
+        QuicCommand::Keyboard(buf)
+    } else {
+        QuicCommand::Mouse(buf)
+    };
+    send_data(outbox, command);
+}
+
+/// Encodes a key event for the server. Media keys and F13–F24 have only a
+/// platform keycode in rdev, so they go by name as an [`ExtraKeyInput`] for
+/// the server to map to its own. Any other unnamed key goes by its kernel
+/// code as a [`RawKeyInput`] where that is known (Linux only).
+fn encode_key(format: WireFormat, event_type: &EventType) -> Vec<u8> {
+    if let Some(extra) = ExtraKeyInput::from_event(event_type) {
+        return format.encode(&extra).expect("failed to serialise");
+    }
+    match RawKeyInput::from_event(event_type) {
+        Some(raw) => format.encode(&raw).expect("failed to serialise"),
+        None => format.encode(event_type).expect("failed to serialise"),
+    }
 }
 
-fn send_data(quic_sender: &mut Option<QuicSender>, command: QuicCommand) {
-    let send_result = quic_sender
-        .as_ref()
-        .map(|sender| sender.send(command));
-    if matches!(send_result, Some(Err(_))) {
-        *quic_sender = None;
+/// Encodes a wheel event for the server. macOS reports trackpad (and smooth
+/// wheel) scrolling in pixels, so there it goes as a two-finger
+/// [`shared::Gesture::Scroll`] the server turns into notches; elsewhere rdev
+/// already reports whole notches and the wheel event is sent as-is.
+///
+/// rdev reports no pinch, so [`shared::Gesture::Pinch`] isn't captured yet.
+fn encode_wheel(format: WireFormat, event_type: &EventType, delta_x: i64, delta_y: i64) -> Vec<u8> {
+    #[cfg(target_os = "macos")]
+    {
+        let _ = event_type;
+        let gesture = Gesture::Scroll {
+            dx: delta_x as f64,
+            dy: delta_y as f64,
+        };
+        format.encode(&gesture).expect("failed to serialise")
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = (delta_x, delta_y);
+        format.encode(event_type).expect("failed to serialise")
     }
 }
 
 struct MonitorStop;
 
-fn run_key_monitor(_endpoint: Endpoint, connection: Connection) {
+/// What one run of the grab shares with the runs before and after it.
+struct RunContext {
+    _endpoint: Endpoint,
+    /// The connection in use, updated on reconnect so a restart carries on
+    /// with it.
+    latest: Arc<Mutex<InputLink>>,
+    /// New connections from [`CaptureHandle::reconnect`].
+    reconnects: Arc<Mutex<Receiver<Reconnect>>>,
+    /// Where to put the pointer back once capture stops.
+    restore_to: Option<(f64, f64)>,
+    /// Release input once the window has been unresponsive this long; see
+    /// [`crate::watchdog`].
+    ui_timeout: Option<Duration>,
+    /// Stops capture on a held Escape or a dead connection; see
+    /// [`crate::escape_hatch`].
+    escape_hatch: EscapeHatch,
+    /// When the connection in use closed, for the escape hatch.
+    link_closed: LinkClosed,
+}
+
+/// Runs one grab until it stops. A grab that fails is returned for the
+/// supervisor in [`start_global_key_monitor`] to decide on.
+fn run_key_monitor(
+    run: RunContext,
+    options: CaptureOptions,
+    stats_tx: Sender<SendStats>,
+    session: SessionType,
+) -> Result<(), CaptureError> {
     #[cfg(target_os = "macos")]
     set_is_main_thread(false);
 
-    let mut quic_sender = Some(spawn_quic_helper(connection));
+    let input = run.latest.lock().expect("connection mutex poisoned").clone();
+    let mut format = input.format;
+    let sender = spawn_quic_helper(input, stats_tx.clone(), options.stream_layout);
+    let mirror = spawn_mirrors(&options.mirrors, format, options.stream_layout);
+    let mut outbox = Outbox::new(sender, options.outbox)
+        .with_mirror(mirror)
+        .with_macro_recording(options.macro_recording.clone());
+    if let Some(path) = &options.record_to {
+        match Recorder::create(path) {
+            Ok(recorder) => {
+                println!("Recording input to {}", path.display());
+                outbox = outbox.with_recorder(recorder.with_format(format));
+            }
+            Err(err) => println!("Not recording input to {}: {err}", path.display()),
+        }
+    }
 
-    let (middle_y, middle_x) = find_window_size();
-    let _ = simulate(&EventType::MouseMove { x: middle_x, y: middle_y});
+    // Warps only move XWayland's pointer under Wayland, and would then
+    // swallow a real move meant for the server.
+    let can_warp = session.can_warp_pointer();
+    // Recenter within the monitor the user locked capture to, so the pointer
+    // never lands on a neighbouring display between events.
+    // Otherwise recenter on the monitor the pointer is on, wherever it sits
+    // in the desktop. With no monitor at all there is nothing to warp on.
+    let center = options
+        .monitor
+        .clone()
+        .or_else(active_monitor)
+        .map(|monitor| monitor.center());
+    let (middle_x, middle_y) = center.unwrap_or_default();
+    // A free cursor is never warped back to the middle, so its deltas stop
+    // while it is pressed against the edge of the screen.
+    let recenter = can_warp && !options.free_cursor && center.is_some();
+    let restore_to = run.restore_to;
+    let ui_timeout = run.ui_timeout;
+    let mut escape_hatch = run.escape_hatch.clone();
+    if let Some(hint) = escape_hint(escape_hatch.escape_hold()) {
+        println!("{hint}");
+    }
 
-    let modifiers = Arc::new(Mutex::new(ModifierState::default()));
-    let modifier_handle = Arc::clone(&modifiers);
+    let mut absolute_area = match options.pointer_mode {
+        PointerMode::Absolute => {
+            let area = capture_monitor(options.monitor.clone());
+            if area.is_none() {
+                println!("No monitor to map absolute positions from; sending relative moves");
+            }
+            area
+        }
+        PointerMode::Relative => None,
+    };
+
+    let mut hold = options.hold_key.map(HoldTrigger::new);
+    if let Some(hold) = &hold {
+        println!("Hold {:?} to send input to the server. Ctrl+Alt+0 stops capture.", hold.key());
+    }
+    // Where the pointer was when the hold key went down, to put it back.
+    let mut held_from: Option<(f64, f64)> = None;
+    let mut paused_for_remote = false;
+
+    if absolute_area.is_none() {
+        if !can_warp {
+            if let Some(note) = capture_notes(session) {
+                println!("{note}");
+            }
+        } else if options.free_cursor {
+            println!("Free cursor: the pointer stays where it is moved and isn't recentered");
+        } else if center.is_none() {
+            println!("No monitor to recenter the pointer on; leaving it where it is");
+        } else if hold.is_none() {
+            // In hold mode the pointer is only taken over while the key is held.
+            let _ = simulate(&EventType::MouseMove { x: middle_x, y: middle_y});
+        }
+    }
+
+    let layout = if options.translate_layout {
+        let layout = SystemLayout::new();
+        if layout.is_none() {
+            println!("Keyboard layout unavailable; sending raw keycodes");
+        }
+        layout
+    } else {
+        None
+    };
+
+    let mut edge_tracker = options.edge_tracker.clone();
+    // Where the pointer was last seen; on a slow link it is left to drift
+    // within the recenter margin instead of being warped back every event.
+    // Without warps it starts wherever the pointer already is.
+    let mut last_position = if recenter {
+        (middle_x, middle_y)
+    } else {
+        cursor_position().unwrap_or((middle_x, middle_y))
+    };
+    let measure_latency = options.measure_latency;
+    let mut click_tracker = clicks::window_from_env().map(ClickTracker::new);
+    if let Some(tracker) = &click_tracker {
+        println!("Marking double-clicks within {} ms for the server", tracker.window().as_millis());
+    }
+    let mut last_move_stamp: Option<Instant> = None;
+    // Buttons forwarded pressed and not yet released, and whether the send
+    // worker was last told anything is held.
+    let mut held_buttons: Vec<Button> = Vec::new();
+    let mut holding = false;
+
+    for key in &options.system_keys {
+        if let Some(limitation) = capture_limitation(*key) {
+            println!("{key:?} may not be captured: {limitation}");
+        }
+    }
+    let mut system_keys = SystemKeyFilter::new(options.system_keys.clone());
+
+    let modifier_handle = modifier_state();
+    let locks = LockState::from_system().unwrap_or_default();
+    *modifier_handle.lock().expect("modifier mutex poisoned") = ModifierState {
+        locks,
+        ..ModifierState::default()
+    };
+    let lock_tx = options.lock_tx.clone();
+    if let Some(lock_tx) = &lock_tx {
+        let _ = lock_tx.send(locks);
+    }
 
     let callback = move |event: Event| -> Option<Event> {
+        let reconnected = run.reconnects.lock().expect("reconnect mutex poisoned").try_iter().last();
+        if let Some(Reconnect { input, pointer_mode }) = reconnected {
+            if pointer_mode == PointerMode::Relative && absolute_area.take().is_some() {
+                println!("Absolute pointer mode refused after reconnecting; sending relative moves");
+            }
+            watch_close(input.connection.clone(), run.link_closed.clone());
+            *run.latest.lock().expect("connection mutex poisoned") = input.clone();
+            if input.format != format {
+                // The mirrors still read the format capture started with.
+                println!("Reconnected with {} input; mirroring stops", input.format);
+                outbox.stop_mirroring();
+            }
+            format = input.format;
+            let (queued, dropped) = (outbox.queued(), outbox.dropped());
+            let sender = spawn_quic_helper(input, stats_tx.clone(), options.stream_layout);
+            let flushed = outbox.reconnect(sender);
+            println!("Reconnected; sent {flushed} of {queued} queued inputs, {dropped} dropped while queuing");
+        }
+
+        if let Some(silent) = ui_timeout.and_then(ui_unresponsive_for) {
+            let reason = format!("Window unresponsive for {:.1}s", silent.as_secs_f64());
+            return force_stop(reason, &mut outbox, restore_to);
+        }
+
+        // Checked before anything else, so it works whatever the connection
+        // or the hold key is doing.
+        let now = Instant::now();
+        let closed_since = {
+            let latest = run.latest.lock().expect("connection mutex poisoned");
+            let connection = &latest.connection;
+            // The watcher may not have run yet for a connection that has
+            // only just closed.
+            connection
+                .close_reason()
+                .map(|_| run.link_closed.since(connection.stable_id()).unwrap_or(now))
+        };
+        if let Some(release) = escape_hatch.on_event(&event.event_type, closed_since, now) {
+            return force_stop(release, &mut outbox, restore_to);
+        }
+
+        if let Some(hold) = hold.as_mut() {
+            match hold.on_event(&event.event_type, Instant::now()) {
+                HoldAction::Forward => {}
+                HoldAction::Ignore => return None,
+                HoldAction::Engage => {
+                    held_from = cursor_position();
+                    if absolute_area.is_none() {
+                        if recenter {
+                            IGNORE_MOUSE.store(true, Ordering::SeqCst);
+                            let _ = simulate(&EventType::MouseMove { x: middle_x, y: middle_y });
+                            last_position = (middle_x, middle_y);
+                        } else if let Some(position) = held_from {
+                            last_position = position;
+                        }
+                    }
+                    return None;
+                }
+                HoldAction::Disengage { releases, .. } => {
+                    let mut state = modifier_handle
+                        .lock()
+                        .expect("modifier mutex poisoned");
+                    for release in releases {
+                        match release {
+                            EventType::KeyRelease(key) => {
+                                if !state.take_translated(key) {
+                                    send_data(&mut outbox, QuicCommand::Keyboard(encode_key(format, &release)));
+                                }
+                                state.update(key, false);
+                            }
+                            _ => {
+                                if let EventType::ButtonRelease(button) = release {
+                                    held_buttons.retain(|held| *held != button);
+                                }
+                                let buf = format.encode(&release).expect("failed to serialise");
+                                send_data(&mut outbox, QuicCommand::Mouse(buf));
+                            }
+                        }
+                    }
+                    note_holding(&mut outbox, &mut holding, !state.pressed.is_empty() || !held_buttons.is_empty());
+                    if let Some((x, y)) = held_from.take().filter(|_| options.restore_cursor && can_warp) {
+                        restore_cursor(x, y);
+                        // Moves stay here until the next hold; nothing to swallow.
+                        IGNORE_MOUSE.store(false, Ordering::SeqCst);
+                    }
+                    return None;
+                }
+                HoldAction::Pass => return pass_locally(event, modifier_handle, &mut outbox),
+            }
+        }
+
+        let remote_paused = options.remote_pause.is_paused();
+        if remote_paused != paused_for_remote {
+            paused_for_remote = remote_paused;
+            if remote_paused {
+                println!("Server can't take input; leaving it to this machine until it can");
+            } else {
+                println!("Server takes input again");
+                // The pointer roamed while paused; pick up from where it is now.
+                if absolute_area.is_none() {
+                    if recenter {
+                        IGNORE_MOUSE.store(true, Ordering::SeqCst);
+                        let _ = simulate(&EventType::MouseMove { x: middle_x, y: middle_y });
+                        last_position = (middle_x, middle_y);
+                    } else if let Some(position) = cursor_position() {
+                        last_position = position;
+                    }
+                }
+            }
+        }
+        if remote_paused {
+            return pass_locally(event, modifier_handle, &mut outbox);
+        }
+
         match event.event_type {
             EventType::KeyPress(key) => {
-                let buf = rmp_serde::to_vec(&event.event_type).expect("failed to serialise");
-                send_data(&mut quic_sender, QuicCommand::Keyboard(buf));
+                system_keys.missing_press(&event.event_type);
                 let mut state = modifier_handle
                     .lock()
                     .expect("modifier mutex poisoned");
+                let is_repeat = filter_key_event(&state.pressed, &event.event_type) == KeyVerdict::Repeat;
                 state.update(key, true);
+                if !is_repeat && state.locks.toggle(key) && let Some(lock_tx) = &lock_tx {
+                    let _ = lock_tx.send(state.locks);
+                }
 
-                if state.ctrl_alt_active() && matches!(key, Key::Num0 | Key::Kp0) {
-                    println!("Detected Ctrl+Alt+0. Stopping key monitor.");
-                    if let Some(sender) = quic_sender.take() {
-                        let _ = sender.send(QuicCommand::Shutdown);
+                if !(is_repeat && options.suppress_repeat) {
+                    let typed = layout.as_ref().and_then(|layout| state.typed_char(layout, key));
+                    let buf = match typed {
+                        Some(ch) => {
+                            if !state.translated.contains(&key) {
+                                state.translated.push(key);
+                            }
+                            format.encode(&CharInput(ch)).expect("failed to serialise")
+                        }
+                        None => encode_key(format, &event.event_type),
+                    };
+                    if measure_latency {
+                        send_stamp(&mut outbox, format, true);
                     }
-                    request_monitor_stop();
-                    return None;
+                    send_data(&mut outbox, QuicCommand::Keyboard(buf));
+                }
+                note_holding(&mut outbox, &mut holding, true);
+
+                if state.ctrl_alt_active() && matches!(key, Key::Num0 | Key::Kp0) {
+                    return force_stop("Detected Ctrl+Alt+0", &mut outbox, restore_to);
                 }
                 return None
             }
             EventType::KeyRelease(key) => {
-                let buf = rmp_serde::to_vec(&event.event_type).expect("failed to serialise");
-                send_data(&mut quic_sender, QuicCommand::Keyboard(buf));
-                modifier_handle
+                let mut state = modifier_handle
                     .lock()
-                    .expect("modifier mutex poisoned")
-                    .update(key, false);
+                    .expect("modifier mutex poisoned");
+                if let Some(press) = system_keys.missing_press(&event.event_type) {
+                    send_data(&mut outbox, QuicCommand::Keyboard(encode_key(format, &press)));
+                    state.update(key, true);
+                }
+                // A second release, or one for a key held before capture
+                // started, would release something the server never pressed.
+                if filter_key_event(&state.pressed, &event.event_type) == KeyVerdict::StrayRelease {
+                    return None;
+                }
+                // A press sent as a character was typed in full on the server.
+                if !state.take_translated(key) {
+                    let buf = encode_key(format, &event.event_type);
+                    if measure_latency {
+                        send_stamp(&mut outbox, format, true);
+                    }
+                    send_data(&mut outbox, QuicCommand::Keyboard(buf));
+                }
+                state.update(key, false);
+                note_holding(&mut outbox, &mut holding, !state.pressed.is_empty() || !held_buttons.is_empty());
                 return None
             }
             EventType::MouseMove { x, y } => {
@@ -119,24 +675,72 @@ fn run_key_monitor(_endpoint: Endpoint, connection: Connection) {
                     return None; // Swallow simulated event
                 }
 
-                let data = MouseMove {dx: (x - middle_x), dy: (y - middle_y) };
-                let buf = rmp_serde::to_vec(&data).expect("failed to serialise");
-                send_data(&mut quic_sender, QuicCommand::Mouse(buf));
+                // The local pointer moves freely; the server mirrors where it is.
+                if let Some(area) = absolute_area.as_ref() {
+                    let buf = format.encode(&area.fraction_at(x, y)).expect("failed to serialise");
+                    if measure_latency {
+                        send_stamp(&mut outbox, format, false);
+                    }
+                    send_data(&mut outbox, QuicCommand::Mouse(buf));
+                    return Some(event);
+                }
 
-                // Mark next mouse event as simulated
-                IGNORE_MOUSE.store(true, Ordering::SeqCst);
+                let data = MouseMove {dx: (x - last_position.0), dy: (y - last_position.1) };
+                let stamp_due = last_move_stamp.is_none_or(|stamped| stamped.elapsed() >= MOVE_STAMP_INTERVAL);
+                if measure_latency && stamp_due {
+                    send_stamp(&mut outbox, format, false);
+                    last_move_stamp = Some(Instant::now());
+                }
+                send_data(&mut outbox, QuicCommand::Move(data));
+                last_position = (x, y);
 
-                let _ = simulate(&EventType::MouseMove { x: middle_x, y: middle_y });
+                if let Some(tracker) = edge_tracker.as_mut() {
+                    for edge in tracker.track(data.dx, data.dy) {
+                        let buf = format.encode(&EdgeHit(edge)).expect("failed to serialise");
+                        send_data(&mut outbox, QuicCommand::Mouse(buf));
+                    }
+                }
+
+                let margin = recenter_margin();
+                let off_center = (x - middle_x).abs() > margin || (y - middle_y).abs() > margin;
+                if recenter && off_center {
+                    // Mark next mouse event as simulated
+                    IGNORE_MOUSE.store(true, Ordering::SeqCst);
+
+                    let _ = simulate(&EventType::MouseMove { x: middle_x, y: middle_y });
+                    last_position = (middle_x, middle_y);
+                }
             }
             EventType::ButtonPress(..) | EventType::ButtonRelease(..) => {
-                let buf = rmp_serde::to_vec(&event.event_type).expect("failed to serialise");
-                send_data(&mut quic_sender, QuicCommand::Mouse(buf));
+                let buf = format.encode(&event.event_type).expect("failed to serialise");
+                if measure_latency {
+                    send_stamp(&mut outbox, format, false);
+                }
+                let hint = match (event.event_type, click_tracker.as_mut()) {
+                    (EventType::ButtonPress(button), Some(tracker)) => tracker.on_press(button, Instant::now()),
+                    _ => None,
+                };
+                if let Some(hint) = hint {
+                    let hint = format.encode(&hint).expect("failed to serialise");
+                    send_data(&mut outbox, QuicCommand::Mouse(hint));
+                }
+                send_data(&mut outbox, QuicCommand::Mouse(buf));
+                match event.event_type {
+                    EventType::ButtonPress(button) if !held_buttons.contains(&button) => held_buttons.push(button),
+                    EventType::ButtonRelease(button) => held_buttons.retain(|held| *held != button),
+                    _ => {}
+                }
+                let keys_held = !modifier_handle.lock().expect("modifier mutex poisoned").pressed.is_empty();
+                note_holding(&mut outbox, &mut holding, keys_held || !held_buttons.is_empty());
                 return None;
             }
             EventType::Wheel { delta_x, delta_y } => {
                 if delta_x != 0 || delta_y != 0 {
-                    let buf = rmp_serde::to_vec(&event.event_type).expect("failed to serialise");
-                    send_data(&mut quic_sender, QuicCommand::Mouse(buf));
+                    let buf = encode_wheel(format, &event.event_type, delta_x, delta_y);
+                    if measure_latency {
+                        send_stamp(&mut outbox, format, false);
+                    }
+                    send_data(&mut outbox, QuicCommand::Mouse(buf));
                 }
                 return None;
             }
@@ -145,14 +749,66 @@ fn run_key_monitor(_endpoint: Endpoint, connection: Connection) {
         Some(event)
     };
 
-    if let Err(error) = grab(callback) {
-        eprintln!("Failed to grab input events: {error:?}");
-        notify_ungrab();
+    grab(callback).map_err(|error| CaptureError::Grab { session, error })
+}
+
+fn restore_cursor(x: f64, y: f64) {
+    // The layout may have changed while capturing; never warp off-screen.
+    let Some((x, y)) = clamp_to_desktop(x, y) else {
+        return;
+    };
+    // Swallow the move this generates so it is not forwarded to the server.
+    IGNORE_MOUSE.store(true, Ordering::SeqCst);
+    let _ = simulate(&EventType::MouseMove { x, y });
+}
+
+/// Leaves `event` to this machine while nothing is forwarded, keeping the
+/// stop chord working.
+fn pass_locally(event: Event, modifier_handle: &Mutex<ModifierState>, outbox: &mut Outbox) -> Option<Event> {
+    let (key, pressed) = match event.event_type {
+        EventType::KeyPress(key) => (key, true),
+        EventType::KeyRelease(key) => (key, false),
+        _ => return Some(event),
+    };
+    let stop = {
+        let mut state = modifier_handle
+            .lock()
+            .expect("modifier mutex poisoned");
+        state.update(key, pressed);
+        pressed && state.ctrl_alt_active() && matches!(key, Key::Num0 | Key::Kp0)
+    };
+    if stop {
+        // Nothing was taken over, so the pointer stays where it is.
+        return force_stop("Detected Ctrl+Alt+0", outbox, None);
+    }
+    Some(event)
+}
+
+/// Ends capture from the callback: logs `reason`, finishes the streams,
+/// puts the pointer back at `restore_to` and stops the grab. Returns what
+/// the callback should, so the stopping event goes nowhere.
+fn force_stop(reason: impl std::fmt::Display, outbox: &mut Outbox, restore_to: Option<(f64, f64)>) -> Option<Event> {
+    println!("{reason}. Stopping key monitor.");
+    outbox.shutdown();
+    if let Some((x, y)) = restore_to {
+        restore_cursor(x, y);
     }
+    request_monitor_stop();
+    None
+}
+
+/// Notes in `link_closed` when `connection` closes, so a dead connection is
+/// timed from then and not from the next event.
+fn watch_close(connection: Connection, link_closed: LinkClosed) {
+    quic_runtime().spawn(async move {
+        connection.closed().await;
+        link_closed.note(connection.stable_id(), Instant::now());
+    });
 }
 
 fn request_monitor_stop() {
-    notify_ungrab();
+    STOP_REQUESTED.store(true, Ordering::SeqCst);
+    notify_ungrab(None);
     #[cfg(target_os = "macos")]
     macos_run_loop::stop_current();
 
@@ -160,18 +816,33 @@ fn request_monitor_stop() {
     panic::panic_any(MonitorStop);
 }
 
-fn notify_ungrab() {
+fn notify_ungrab(failure: Option<CaptureError>) {
     if let Some(callback) = ungrab_callback_storage()
         .lock()
         .expect("ungrab callback mutex poisoned")
         .take()
     {
         glib::MainContext::default().invoke(move || {
-            callback();
+            callback(failure);
         });
     }
 }
 
+/// Keys the current (or last) capture session saw pressed and not yet
+/// released, for a release sweep to cover.
+pub fn held_keys() -> Vec<Key> {
+    modifier_state()
+        .lock()
+        .expect("modifier mutex poisoned")
+        .pressed
+        .clone()
+}
+
+fn modifier_state() -> &'static Mutex<ModifierState> {
+    static STATE: OnceLock<Mutex<ModifierState>> = OnceLock::new();
+    STATE.get_or_init(|| Mutex::new(ModifierState::default()))
+}
+
 fn ungrab_callback_storage() -> &'static Mutex<Option<UngrabCallback>> {
     static STORAGE: OnceLock<Mutex<Option<UngrabCallback>>> = OnceLock::new();
     STORAGE.get_or_init(|| Mutex::new(None))
@@ -200,22 +871,62 @@ mod macos_run_loop {
 #[cfg(not(target_os = "macos"))]
 mod macos_run_loop {}
 
+/// Keys currently held on this machine, used for the stop chord and to tell
+/// repeated presses and stray releases apart (see [`crate::key_filter`]).
 #[derive(Default)]
 struct ModifierState {
-    ctrl_left: bool,
-    alt_left: bool,
+    pressed: Vec<Key>,
+    /// Held keys whose press went out as a [`CharInput`].
+    translated: Vec<Key>,
+    /// Locks as forwarded presses left them, starting from this machine's.
+    locks: LockState,
 }
 
 impl ModifierState {
     fn update(&mut self, key: Key, pressed: bool) {
-        match key {
-            Key::ControlLeft => self.ctrl_left = pressed,
-            Key::Alt => self.alt_left = pressed,
-            _ => {}
+        if pressed {
+            if !self.is_pressed(key) {
+                self.pressed.push(key);
+            }
+        } else {
+            self.pressed.retain(|held| *held != key);
         }
     }
 
+    fn is_pressed(&self, key: Key) -> bool {
+        self.pressed.contains(&key)
+    }
+
     fn ctrl_alt_active(&self) -> bool {
-        self.ctrl_left && self.alt_left
+        self.is_pressed(Key::ControlLeft) && self.is_pressed(Key::Alt)
+    }
+
+    /// Character `key` types on `layout` with the current modifiers, if it
+    /// should be sent as one. Shortcuts keep their raw keycodes.
+    fn typed_char(&self, layout: &impl KeyboardLayout, key: Key) -> Option<char> {
+        let shortcut = [
+            Key::ControlLeft,
+            Key::ControlRight,
+            Key::Alt,
+            Key::MetaLeft,
+            Key::MetaRight,
+        ]
+        .iter()
+        .any(|modifier| self.is_pressed(*modifier));
+        if shortcut {
+            return None;
+        }
+
+        layout.char_for(Keystroke {
+            key,
+            shift: self.is_pressed(Key::ShiftLeft) || self.is_pressed(Key::ShiftRight),
+            alt_gr: self.is_pressed(Key::AltGr),
+        })
+    }
+
+    fn take_translated(&mut self, key: Key) -> bool {
+        let was_translated = self.translated.contains(&key);
+        self.translated.retain(|translated| *translated != key);
+        was_translated
     }
 }
@@ -0,0 +1,35 @@
+//! Transport side of the QUICinput client, kept free of GTK so it can be
+//! driven from tests.
+
+pub mod accessibility;
+pub mod availability;
+pub mod capture_notice;
+pub mod capture_support;
+pub mod clicks;
+pub mod close_reason;
+pub mod discovery;
+pub mod edges;
+pub mod escape_hatch;
+pub mod grab_supervisor;
+pub mod injection;
+pub mod key_combo;
+pub mod key_filter;
+pub mod lock_keys;
+pub mod macros;
+pub mod mirror;
+pub mod momentary;
+pub mod netsim;
+pub mod observer;
+pub mod outbox;
+pub mod pointer_mode;
+pub mod quic;
+pub mod quic_helper_thread;
+pub mod raw_debug;
+pub mod recording;
+pub mod release;
+pub mod sealed;
+pub mod settings;
+pub mod warp;
+pub mod watchdog;
+#[cfg(feature = "gui")]
+pub mod windowresolution;
@@ -0,0 +1,157 @@
+//! Caps Lock, Num Lock and Scroll Lock while capturing. Capture hides the
+//! pointer and swallows every key, so the keyboard's own lights are the
+//! only hint left that a lock is on, and they show this machine's state.
+//!
+//! Capture starts from this machine's state, read with [`LockState::from_system`],
+//! and flips a lock on each press it forwards. The server's own state isn't
+//! reported, so the two only agree if they matched when capture started.
+
+use std::fs;
+use std::path::Path;
+
+use rdev::Key;
+
+/// Where Linux lists keyboard LEDs, one directory per LED and keyboard.
+pub const LINUX_LEDS: &str = "/sys/class/leds";
+
+/// Which locks are on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockState {
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+
+impl LockState {
+    /// Flips the lock `key` toggles, returning false for any other key.
+    pub fn toggle(&mut self, key: Key) -> bool {
+        let lock = match key {
+            Key::CapsLock => &mut self.caps_lock,
+            Key::NumLock => &mut self.num_lock,
+            Key::ScrollLock => &mut self.scroll_lock,
+            _ => return false,
+        };
+        *lock = !*lock;
+        true
+    }
+
+    /// Names of the locks that are on, in keyboard order.
+    pub fn active(&self) -> Vec<&'static str> {
+        [
+            (self.caps_lock, "Caps Lock"),
+            (self.num_lock, "Num Lock"),
+            (self.scroll_lock, "Scroll Lock"),
+        ]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect()
+    }
+
+    /// A line for the input view, e.g. "Caps Lock, Num Lock on"; `None`
+    /// with every lock off.
+    pub fn describe(&self) -> Option<String> {
+        let active = self.active();
+        (!active.is_empty()).then(|| format!("{} on", active.join(", ")))
+    }
+
+    /// This machine's locks as the OS reports them, or `None` when it
+    /// can't tell. macOS only reports Caps Lock.
+    pub fn from_system() -> Option<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            Self::from_leds(Path::new(LINUX_LEDS))
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            Some(windows_keys::lock_state())
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            Some(macos_flags::lock_state())
+        }
+
+        #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+        {
+            None
+        }
+    }
+
+    /// Reads the lock LEDs under `dir`, laid out like [`LINUX_LEDS`]. A lock
+    /// counts as on if any keyboard lights it. `None` without any lock LED,
+    /// e.g. in a container or with no keyboard attached.
+    pub fn from_leds(dir: &Path) -> Option<Self> {
+        let mut state = Self::default();
+        let mut found = false;
+        for entry in fs::read_dir(dir).ok()?.flatten() {
+            let name = entry.file_name();
+            let Some((_, led)) = name.to_str().and_then(|name| name.rsplit_once("::")) else {
+                continue;
+            };
+            let lock = match led {
+                "capslock" => &mut state.caps_lock,
+                "numlock" => &mut state.num_lock,
+                "scrolllock" => &mut state.scroll_lock,
+                _ => continue,
+            };
+            let Ok(brightness) = fs::read_to_string(entry.path().join("brightness")) else {
+                continue;
+            };
+            found = true;
+            *lock |= brightness
+                .trim()
+                .parse::<u32>()
+                .is_ok_and(|level| level > 0);
+        }
+        found.then_some(state)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows_keys {
+    use super::LockState;
+
+    const VK_CAPITAL: i32 = 0x14;
+    const VK_NUMLOCK: i32 = 0x90;
+    const VK_SCROLL: i32 = 0x91;
+
+    #[link(name = "user32")]
+    unsafe extern "system" {
+        fn GetKeyState(virtual_key: i32) -> i16;
+    }
+
+    fn toggled(virtual_key: i32) -> bool {
+        // The low bit is set while the key's toggle is on.
+        unsafe { GetKeyState(virtual_key) & 1 != 0 }
+    }
+
+    pub fn lock_state() -> LockState {
+        LockState {
+            caps_lock: toggled(VK_CAPITAL),
+            num_lock: toggled(VK_NUMLOCK),
+            scroll_lock: toggled(VK_SCROLL),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos_flags {
+    use super::LockState;
+
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ALPHA_SHIFT_MASK: u64 = 0x0001_0000;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    unsafe extern "C" {
+        fn CGEventSourceFlagsState(state_id: i32) -> u64;
+    }
+
+    pub fn lock_state() -> LockState {
+        let flags = unsafe { CGEventSourceFlagsState(COMBINED_SESSION_STATE) };
+        LockState {
+            caps_lock: flags & ALPHA_SHIFT_MASK != 0,
+            ..LockState::default()
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Named macros: input captured once, kept, and sent again on demand
+//! without capturing. Recording runs alongside capture from when it is
+//! started until it is stopped and named; playing sends the same input
+//! through a worker of its own, as [`crate::recording`]'s replay does,
+//! waiting between steps as long as the user did.
+//!
+//! Each macro is a file in [`macros_dir`], holding the msgpack [`Macro`]:
+//! its steps, each with the time since the step before, and the
+//! [`WireFormat`] their events were encoded in, encrypted as
+//! [`crate::sealed`] says. Playing on a connection that negotiated another
+//! format re-encodes each event first.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use shared::codec::{CodecError, WireFormat};
+use tokio::time::sleep;
+
+use crate::quic_helper_thread::{QuicCommand, QuicSender};
+use crate::recording::RecordedInput;
+use crate::sealed::config_sealer;
+use crate::settings::config_dir;
+
+/// Overrides where macros are kept.
+pub const MACROS_ENV: &str = "QUICINPUT_MACROS_DIR";
+
+const MACRO_EXTENSION: &str = "macro";
+
+/// A recorded macro, ready to play or save.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Macro {
+    /// What the mouse and keyboard steps were encoded in when recorded.
+    pub format: WireFormat,
+    /// Each input, after how long to wait before sending it.
+    pub steps: Vec<(Duration, RecordedInput)>,
+}
+
+impl Macro {
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// How long it takes to play.
+    pub fn duration(&self) -> Duration {
+        self.steps.iter().map(|(gap, _)| *gap).sum()
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        rmp_serde::to_vec(self)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        rmp_serde::from_slice(data)
+    }
+}
+
+/// Builds a [`Macro`] from commands as they are sent. Latency stamps and
+/// shutdowns are left out, as in a recording.
+#[derive(Debug, Default)]
+pub struct MacroRecorder {
+    format: WireFormat,
+    steps: Vec<(Duration, RecordedInput)>,
+    last: Option<Instant>,
+}
+
+impl MacroRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The format the commands it is given were encoded in; MessagePack
+    /// unless set.
+    pub fn with_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Adds `command`, sent at `now`. The first step waits for nothing.
+    pub fn record(&mut self, command: &QuicCommand, now: Instant) {
+        let Some(input) = RecordedInput::from_command(command, self.format) else {
+            return;
+        };
+        let gap = self
+            .last
+            .map_or(Duration::ZERO, |last| now.saturating_duration_since(last));
+        self.last = Some(now);
+        self.steps.push((gap, input));
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn finish(self) -> Macro {
+        Macro {
+            format: self.format,
+            steps: self.steps,
+        }
+    }
+}
+
+/// Whether a macro is being recorded, shared between the menu that starts
+/// and stops it and the capture that feeds it. Clones share the recording.
+#[derive(Debug, Clone, Default)]
+pub struct MacroRecording {
+    recorder: Arc<Mutex<Option<MacroRecorder>>>,
+}
+
+impl MacroRecording {
+    /// Starts a new recording of commands encoded in `format`, discarding
+    /// one still running.
+    pub fn start(&self, format: WireFormat) {
+        *self.lock() = Some(MacroRecorder::new().with_format(format));
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Adds `command` if recording.
+    pub fn record(&self, command: &QuicCommand) {
+        if let Some(recorder) = self.lock().as_mut() {
+            recorder.record(command, Instant::now());
+        }
+    }
+
+    /// Ends the recording, returning what it caught; `None` if none was
+    /// running.
+    pub fn stop(&self) -> Option<Macro> {
+        self.lock().take().map(MacroRecorder::finish)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<MacroRecorder>> {
+        self.recorder
+            .lock()
+            .expect("macro recording mutex poisoned")
+    }
+}
+
+/// Sends each step to the worker behind `sender`, whose connection uses
+/// `format`, sleeping for its gap first. Returns how many were sent, which
+/// falls short if the worker goes away; a step that can't be re-encoded is
+/// skipped.
+pub async fn play(recorded: &Macro, sender: &QuicSender, format: WireFormat) -> usize {
+    let mut sent = 0;
+    for (gap, input) in &recorded.steps {
+        sleep(*gap).await;
+        let input = match transcode(input, recorded.format, format) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("[client] skipping a macro step that can't be sent as {format}: {err}");
+                continue;
+            }
+        };
+        if sender.send(input.into_command()).is_err() {
+            break;
+        }
+        sent += 1;
+    }
+    sent
+}
+
+fn transcode(
+    input: &RecordedInput,
+    from: WireFormat,
+    to: WireFormat,
+) -> Result<RecordedInput, CodecError> {
+    Ok(match input {
+        RecordedInput::Move(mouse_move) => RecordedInput::Move(*mouse_move),
+        RecordedInput::Mouse(buf) => RecordedInput::Mouse(from.transcode(buf, to)?),
+        RecordedInput::Keyboard(buf) => RecordedInput::Keyboard(from.transcode(buf, to)?),
+    })
+}
+
+/// Where macros are kept: [`MACROS_ENV`] if set, else `quicinput/macros` in
+/// the platform's config directory. `None` if there is no such directory.
+pub fn macros_dir() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(MACROS_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    config_dir().map(|dir| dir.join("quicinput").join("macros"))
+}
+
+/// The file `name` is kept in under `dir`. `None` for names that are empty
+/// or would reach outside `dir`.
+pub fn macro_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    let name = name.trim();
+    let plain = !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && !name.chars().any(char::is_control);
+    plain.then(|| dir.join(format!("{name}.{MACRO_EXTENSION}")))
+}
+
+/// Saves `recorded` as `name`, replacing a macro of that name.
+pub fn save_macro(dir: &Path, name: &str, recorded: &Macro) -> io::Result<()> {
+    let path = macro_path(dir, name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{name:?} can't name a macro"),
+        )
+    })?;
+    fs::create_dir_all(dir)?;
+    config_sealer().check_replaceable(&path)?;
+    let data = recorded.to_bytes().map_err(io::Error::other)?;
+    fs::write(path, config_sealer().seal(&data)?)
+}
+
+pub fn load_macro(dir: &Path, name: &str) -> io::Result<Macro> {
+    let path = macro_path(dir, name).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("no macro named {name:?}"))
+    })?;
+    Macro::from_bytes(&config_sealer().open(&fs::read(path)?)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Names of the macros saved in `dir`, sorted. Empty if `dir` doesn't exist.
+pub fn list_macros(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == MACRO_EXTENSION))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names
+}
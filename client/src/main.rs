@@ -2,20 +2,39 @@ mod connect;
 mod input;
 mod key_monitor;
 mod menubar;
-mod windowresolution;
-mod quic;
-mod quic_helper_thread;
+mod system_layout;
 mod about;
 
 use std::rc::Rc;
 
 use libadwaita::gio::SimpleAction;
 use libadwaita::prelude::*;
-use libadwaita::{glib, Application, ApplicationWindow, HeaderBar, ToolbarView};
-use gtk4::{Stack, StackTransitionType};
-use rustls::crypto::aws_lc_rs;
-use rustls::crypto::CryptoProvider;
-use quinn::{Connection, Endpoint};
+use libadwaita::{glib, AlertDialog, Application, ApplicationWindow, HeaderBar, ResponseAppearance, ToolbarView};
+use gtk4::{DropDown, Entry, Stack, StackTransitionType};
+use quinn::Connection;
+use shared::CloseCode;
+use shared::hex::parse_hex;
+use client::accessibility;
+use client::availability;
+use client::capture_support;
+use client::clicks;
+use client::close_reason;
+#[cfg(feature = "mdns")]
+use client::discovery;
+use client::edges;
+use client::escape_hatch;
+use client::grab_supervisor;
+use client::key_filter;
+use client::lock_keys;
+use client::macros;
+use client::mirror::{self, Attempts};
+use client::outbox;
+use client::quic::{self, ClientSession, ConnectError};
+use client::quic_helper_thread;
+use client::raw_debug::take_debug_raw_flag;
+use client::recording;
+use client::watchdog;
+use client::windowresolution;
 
 
 const APP_ID: &str = "com.aellul27.quicinput.client";
@@ -27,26 +46,37 @@ fn main() -> glib::ExitCode {
     gtk4::gio::resources_register(&resource);
     // Create a new application
     let app = Application::builder().application_id(APP_ID).build();
-    CryptoProvider::install_default(aws_lc_rs::default_provider())
-            .expect("Failed to install default crypto provider");
+    let crypto_error = quic::install_crypto_provider().err();
+    let (debug_raw, args) = take_debug_raw_flag(std::env::args().collect());
 
-    app.connect_activate(build_ui);
+    app.connect_activate(move |app| {
+        build_ui(app, debug_raw);
+        if let Some(error) = &crypto_error {
+            show_crypto_error(app, error);
+        }
+    });
 
     // Run the application
-    app.run()
+    app.run_with_args(&args)
 }
 
-fn build_ui(app: &Application) {
+fn build_ui(app: &Application, debug_raw: bool) {
     let toolbar_view = ToolbarView::new();
 
     // Header bar sits in the toolbar view so Adwaita can manage window chrome
     let header = HeaderBar::new();
-    header.pack_end(&menubar::build(app));
+    header.pack_end(&menubar::build(app, debug_raw));
     toolbar_view.add_top_bar(&header);
 
     let controller = AppController::new();
     toolbar_view.set_content(Some(&controller.stack()));
 
+    // Lets capture tell a hung main loop from an idle one.
+    glib::timeout_add_local(watchdog::PING_INTERVAL, || {
+        watchdog::UI_HEARTBEAT.ping();
+        glib::ControlFlow::Continue
+    });
+
     if app.lookup_action("reset").is_none() {
         let controller_for_action = controller.clone();
         let reset_action = SimpleAction::new("reset", None);
@@ -56,12 +86,57 @@ fn build_ui(app: &Application) {
         app.add_action(&reset_action);
     }
 
+    if app.lookup_action("release_all").is_none() {
+        let controller_for_release = controller.clone();
+        let release_action = SimpleAction::new("release_all", None);
+        release_action.connect_activate(move |_, _| {
+            controller_for_release.release_all();
+        });
+        app.add_action(&release_action);
+        app.set_accels_for_action("app.release_all", &["<Primary><Shift>BackSpace"]);
+    }
+
+    if app.lookup_action("macro_record").is_none() {
+        let controller_for_record = controller.clone();
+        let record_action = SimpleAction::new("macro_record", None);
+        record_action.connect_activate(move |_, _| {
+            controller_for_record.start_macro();
+        });
+        app.add_action(&record_action);
+
+        let controller_for_stop = controller.clone();
+        let app_for_stop = app.clone();
+        let stop_action = SimpleAction::new("macro_stop", None);
+        stop_action.connect_activate(move |_, _| {
+            show_save_macro(&app_for_stop, &controller_for_stop);
+        });
+        app.add_action(&stop_action);
+
+        let controller_for_play = controller.clone();
+        let app_for_play = app.clone();
+        let play_action = SimpleAction::new("macro_play", None);
+        play_action.connect_activate(move |_, _| {
+            show_play_macro(&app_for_play, &controller_for_play);
+        });
+        app.add_action(&play_action);
+    }
+
+    if debug_raw && app.lookup_action("send_raw").is_none() {
+        let controller_for_raw = controller.clone();
+        let app_for_raw = app.clone();
+        let send_raw_action = SimpleAction::new("send_raw", None);
+        send_raw_action.connect_activate(move |_, _| {
+            show_send_raw(&app_for_raw, &controller_for_raw);
+        });
+        app.add_action(&send_raw_action);
+    }
+
     if app.lookup_action("quit").is_none() {
         let controller_for_quit = controller.clone();
         let app_for_quit = app.clone();
         let quit_action = SimpleAction::new("quit", None);
         quit_action.connect_activate(move |_, _| {
-            controller_for_quit.shutdown();
+            controller_for_quit.shutdown(CloseCode::UserDisconnect);
             app_for_quit.quit();
         });
         app.add_action(&quit_action);
@@ -87,20 +162,20 @@ fn build_ui(app: &Application) {
     {
         let controller_for_shutdown = controller.clone();
         app.connect_shutdown(move |_app| {
-            controller_for_shutdown.shutdown();
+            controller_for_shutdown.shutdown(CloseCode::UserDisconnect);
         });
     }
 
     gtk4::Window::set_default_icon_name("Icon");
 
-    let (window_height, window_width) = windowresolution::find_window_size();
+    let window_size = windowresolution::find_window_size();
 
-    // Create a window, set the title, and size it relative to the primary display
+    // Create a window, set the title, and size it relative to the active display
     let window = ApplicationWindow::builder()
         .application(app)
         .title("QUICinput")
-        .default_height(window_height as i32)
-        .default_width(window_width as i32)
+        .default_height(window_size.height as i32)
+        .default_width(window_size.width as i32)
         .content(&toolbar_view)
         .build();
 
@@ -108,7 +183,7 @@ fn build_ui(app: &Application) {
         let controller_for_close = controller.clone();
         let app_for_close = app.clone();
         window.connect_close_request(move |_window| {
-            controller_for_close.shutdown();
+            controller_for_close.shutdown(CloseCode::UserDisconnect);
             app_for_close.quit();
             glib::Propagation::Proceed
         });
@@ -118,6 +193,141 @@ fn build_ui(app: &Application) {
     window.present();
 }
 
+/// Tells the user connecting won't work instead of crashing at startup.
+fn show_crypto_error(app: &Application, error: &str) {
+    let dialog = AlertDialog::new(
+        Some("Secure connections unavailable"),
+        Some(&format!("QUICinput could not set up TLS: {error}. Connecting to a server will fail.")),
+    );
+    dialog.add_response("close", "Close");
+    dialog.present(app.active_window().as_ref());
+}
+
+/// Asks for hex to send on a raw stream, for `--debug-raw`. Bad hex or no
+/// connection is reported in a dialog of its own.
+fn show_send_raw(app: &Application, controller: &Rc<AppController>) {
+    let entry = Entry::builder()
+        .placeholder_text("93 a1 41 c3")
+        .activates_default(true)
+        .build();
+    let dialog = AlertDialog::new(
+        Some("Send Raw Bytes"),
+        Some("Bytes in hex, sent as-is on a new stream."),
+    );
+    dialog.set_extra_child(Some(&entry));
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("send", "Send");
+    dialog.set_response_appearance("send", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("send"));
+    dialog.set_close_response("cancel");
+
+    let controller = Rc::clone(controller);
+    let app = app.clone();
+    dialog.connect_response(Some("send"), move |_, _| {
+        let problem = match parse_hex(&entry.text()) {
+            Ok(bytes) => {
+                if controller.send_raw(bytes) {
+                    return;
+                }
+                "Not connected to a server.".to_string()
+            }
+            Err(error) => format!("Invalid hex: {error}"),
+        };
+        let error_dialog = AlertDialog::new(Some("Raw bytes not sent"), Some(&problem));
+        error_dialog.add_response("close", "Close");
+        error_dialog.present(app.active_window().as_ref());
+    });
+    dialog.present(app.active_window().as_ref());
+}
+
+/// Ends the macro being recorded and asks for a name to save it under.
+fn show_save_macro(app: &Application, controller: &Rc<AppController>) {
+    let Some(recorded) = controller.stop_macro() else {
+        show_macro_problem(app, "No macro is being recorded. Start one from the Macros menu first.");
+        return;
+    };
+    if recorded.is_empty() {
+        show_macro_problem(app, "Nothing was captured while recording, so there is no macro to save.");
+        return;
+    }
+    let Some(dir) = macros::macros_dir() else {
+        show_macro_problem(app, "No config directory to keep macros in.");
+        return;
+    };
+
+    let entry = Entry::builder()
+        .placeholder_text("Name")
+        .activates_default(true)
+        .build();
+    let dialog = AlertDialog::new(
+        Some("Save Macro"),
+        Some(&format!(
+            "{} inputs over {:.1}s. Saving under a name already used replaces that macro.",
+            recorded.steps.len(),
+            recorded.duration().as_secs_f64()
+        )),
+    );
+    dialog.set_extra_child(Some(&entry));
+    dialog.add_response("discard", "Discard");
+    dialog.add_response("save", "Save");
+    dialog.set_response_appearance("discard", ResponseAppearance::Destructive);
+    dialog.set_response_appearance("save", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("discard");
+
+    let app = app.clone();
+    dialog.connect_response(Some("save"), move |_, _| {
+        let name = entry.text();
+        match macros::save_macro(&dir, &name, &recorded) {
+            Ok(()) => println!("Saved macro {name}"),
+            Err(error) => show_macro_problem(&app, &format!("Macro not saved: {error}")),
+        }
+    });
+    dialog.present(app.active_window().as_ref());
+}
+
+/// Asks which saved macro to send to the server.
+fn show_play_macro(app: &Application, controller: &Rc<AppController>) {
+    let names = macros::macros_dir()
+        .map(|dir| macros::list_macros(&dir))
+        .unwrap_or_default();
+    if names.is_empty() {
+        show_macro_problem(app, "No macros saved yet. Record one from the Macros menu.");
+        return;
+    }
+
+    let name_refs: Vec<&str> = names.iter().map(String::as_str).collect();
+    let dropdown = DropDown::from_strings(&name_refs);
+    let dialog = AlertDialog::new(
+        Some("Play Macro"),
+        Some("Sent to the server at the pace it was recorded."),
+    );
+    dialog.set_extra_child(Some(&dropdown));
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("play", "Play");
+    dialog.set_response_appearance("play", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("play"));
+    dialog.set_close_response("cancel");
+
+    let controller = Rc::clone(controller);
+    let app = app.clone();
+    dialog.connect_response(Some("play"), move |_, _| {
+        let Some(name) = names.get(dropdown.selected() as usize) else {
+            return;
+        };
+        if let Err(problem) = controller.play_macro(name) {
+            show_macro_problem(&app, &problem);
+        }
+    });
+    dialog.present(app.active_window().as_ref());
+}
+
+fn show_macro_problem(app: &Application, problem: &str) {
+    let dialog = AlertDialog::new(Some("Macros"), Some(problem));
+    dialog.add_response("close", "Close");
+    dialog.present(app.active_window().as_ref());
+}
+
 struct AppController {
     stack: Stack,
     connect_view: connect::ConnectView,
@@ -155,8 +365,8 @@ impl AppController {
 
         self.connect_view.set_on_connect({
             let controller = Rc::clone(self);
-            move |ip, port, endpoint, connection| {
-                controller.handle_connected(ip, port, endpoint, connection);
+            move |ip, port, session, mirrors| {
+                controller.handle_connected(ip, port, session, mirrors);
             }
         });
 
@@ -167,33 +377,83 @@ impl AppController {
         self.stack.clone()
     }
 
-    fn handle_connected(&self, ip: String, port: u16, endpoint: Endpoint, connection: Connection) {
+    fn handle_connected(
+        self: &Rc<Self>,
+        ip: String,
+        port: u16,
+        session: ClientSession,
+        mirrors: Attempts<ClientSession, ConnectError>,
+    ) {
         println!("Connected to {}:{}", ip, port);
-        self.input_view.set_connection(endpoint, connection);
+        self.watch_close(session.connection.clone());
+        self.input_view.set_connection(session);
+        self.input_view.add_mirrors(mirrors);
         self.show_input();
     }
 
+    /// Sends the user back to the connect view, saying why, if the server
+    /// ends this connection while it is still the current one.
+    fn watch_close(self: &Rc<Self>, connection: Connection) {
+        let connection_id = connection.stable_id();
+        let task = quic::quic_runtime().spawn(close_reason::watch_close(connection));
+        let controller = Rc::clone(self);
+        glib::MainContext::default().spawn_local(async move {
+            let Ok(Some(message)) = task.await else {
+                return;
+            };
+            eprintln!("{message}");
+            if controller.input_view.is_connected_to(connection_id) {
+                controller.input_view.take_connection();
+                controller.connect_view.show_error(&message);
+                controller.stack.set_visible_child_name("connect");
+                controller.connect_view.focus();
+            }
+        });
+    }
+
     fn show_input(&self) {
         self.stack.set_visible_child_name("input");
         self.input_view.focus();
     }
 
     fn reset(&self) {
-        self.shutdown();
+        self.shutdown(CloseCode::Reset);
         self.stack.set_visible_child_name("connect");
         self.connect_view.focus();
     }
 
-    fn shutdown(&self) {
-        self.shutdown_connection();
+    /// Unsticks the server's keys and buttons without disconnecting.
+    fn release_all(&self) {
+        self.input_view.release_all();
+    }
+
+    fn send_raw(&self, bytes: Vec<u8>) -> bool {
+        self.input_view.send_raw(bytes)
+    }
+
+    fn start_macro(&self) {
+        self.input_view.start_macro();
+    }
+
+    fn stop_macro(&self) -> Option<macros::Macro> {
+        self.input_view.stop_macro()
+    }
+
+    fn play_macro(&self, name: &str) -> Result<(), String> {
+        self.input_view.play_macro(name)
+    }
+
+    fn shutdown(&self, reason: CloseCode) {
+        self.shutdown_connection(reason);
         self.input_view.reset();
         self.connect_view.reset();
     }
 
-    fn shutdown_connection(&self) {
-        if let Some((endpoint, connection)) = self.input_view.take_connection() {
+    fn shutdown_connection(&self, reason: CloseCode) {
+        let mirrors = self.input_view.take_mirrors();
+        for (endpoint, input) in self.input_view.take_connection().into_iter().chain(mirrors) {
             quic::quic_runtime().spawn(async move {
-                if let Err(error) = quic::close_client(connection, endpoint).await {
+                if let Err(error) = quic::close_client(input, endpoint, reason).await {
                     eprintln!("failed to close client cleanly: {error}");
                 }
             });
@@ -3,15 +3,29 @@ use libadwaita::{gio::Menu, Application};
 
 /// Builds a MenuButton-backed menu so it stays accessible across platforms
 /// while still registering with the application (macOS picks it up globally).
-pub fn build(app: &Application) -> MenuButton {
+/// `debug_raw` adds a Debug menu for sending raw bytes.
+pub fn build(app: &Application, debug_raw: bool) -> MenuButton {
     let menubar = Menu::new();
 
     menubar.append(Some("About"), Some("app.about"));
 
     let connect_menu = Menu::new();
     connect_menu.append(Some("Back to Connect"), Some("app.reset"));
+    connect_menu.append(Some("Release All Keys and Buttons"), Some("app.release_all"));
     menubar.append_submenu(Some("Connect"), &connect_menu);
 
+    let macro_menu = Menu::new();
+    macro_menu.append(Some("Start Recording Macro"), Some("app.macro_record"));
+    macro_menu.append(Some("Stop Recording Macro…"), Some("app.macro_stop"));
+    macro_menu.append(Some("Play Macro…"), Some("app.macro_play"));
+    menubar.append_submenu(Some("Macros"), &macro_menu);
+
+    if debug_raw {
+        let debug_menu = Menu::new();
+        debug_menu.append(Some("Send Raw Bytes…"), Some("app.send_raw"));
+        menubar.append_submenu(Some("Debug"), &debug_menu);
+    }
+
     menubar.append(Some("Quit"), Some("app.quit"));
 
     app.set_menubar(Some(&menubar));
@@ -0,0 +1,204 @@
+//! Driving several servers from one capture, e.g. every screen in a
+//! presentation. Capture's outbox hands each command to the worker for the
+//! server capture started with and to a [`Mirror`], which passes the
+//! already encoded command on to a send worker per further server. Each
+//! server fails on its own: a mirrored server whose worker goes away is
+//! dropped and the rest carry on, and while the first server is down only
+//! its input is queued, for when it reconnects, as the mirrors keep
+//! receiving theirs.
+
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::panic;
+use std::sync::mpsc as std_mpsc;
+
+use shared::codec::WireFormat;
+use tokio::task::JoinSet;
+
+use crate::quic::InputLink;
+use crate::quic_helper_thread::{QuicCommand, QuicSender, StreamLayout, spawn_quic_helper};
+
+/// One server input is mirrored to.
+pub struct MirrorTarget {
+    /// How the server is named in logs, e.g. its address.
+    pub label: String,
+    pub sender: QuicSender,
+}
+
+impl MirrorTarget {
+    pub fn new(label: impl Into<String>, sender: QuicSender) -> Self {
+        Self {
+            label: label.into(),
+            sender,
+        }
+    }
+}
+
+/// Passes every command on to each of its targets.
+pub struct Mirror {
+    targets: Vec<MirrorTarget>,
+}
+
+impl Mirror {
+    pub fn new(targets: Vec<MirrorTarget>) -> Self {
+        Self { targets }
+    }
+
+    /// Hands `command` to every target, dropping those whose worker has
+    /// gone. Returns how many it reached.
+    pub fn send(&mut self, command: &QuicCommand) -> usize {
+        self.targets.retain(|target| {
+            let alive = target.sender.send(command.clone()).is_ok();
+            if !alive {
+                eprintln!("[client] no longer mirroring to {}", target.label);
+            }
+            alive
+        });
+        self.targets.len()
+    }
+
+    /// Labels of the targets still reached.
+    pub fn labels(&self) -> Vec<&str> {
+        self.targets
+            .iter()
+            .map(|target| target.label.as_str())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+}
+
+/// A send worker for each of `mirrors` still open, fed through a [`Mirror`]
+/// for [`crate::outbox::Outbox::with_mirror`]. None of them report to the
+/// stats shown, which are for the server capture started with. Mirrors
+/// that agreed a wire format other than `format` are left out.
+pub fn spawn_mirrors(mirrors: &[InputLink], format: WireFormat, layout: StreamLayout) -> Mirror {
+    let targets = mirrors
+        .iter()
+        .filter(|mirror| mirror.connection.close_reason().is_none())
+        .filter(|mirror| {
+            // Capture encodes once for every server.
+            let matches = mirror.format == format;
+            if !matches {
+                eprintln!(
+                    "[client] not mirroring to {}: it doesn't read {format} input",
+                    mirror.connection.remote_address()
+                );
+            }
+            matches
+        })
+        .map(|mirror| {
+            let (unreported, _) = std_mpsc::channel();
+            let sender = spawn_quic_helper(mirror.clone(), unreported, layout);
+            MirrorTarget::new(mirror.connection.remote_address().to_string(), sender)
+        })
+        .collect();
+    Mirror::new(targets)
+}
+
+/// Why a list of servers to connect to couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetError {
+    /// The list names no server.
+    Empty,
+    /// An entry is neither an IP address nor `ip:port`.
+    Invalid(String),
+}
+
+impl fmt::Display for TargetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetError::Empty => write!(f, "IP address is required"),
+            TargetError::Invalid(entry) => write!(f, "Invalid IP address \"{entry}\""),
+        }
+    }
+}
+
+impl Error for TargetError {}
+
+/// Reads the servers in `list`, separated by commas or spaces, e.g.
+/// `192.168.1.20, 192.168.1.21:5001`. Entries without a port use `port`.
+/// A server named twice is kept once.
+pub fn parse_targets(list: &str, port: u16) -> Result<Vec<SocketAddr>, TargetError> {
+    let mut targets: Vec<SocketAddr> = Vec::new();
+    for entry in list.split([',', ' ']).filter(|entry| !entry.is_empty()) {
+        let target = match entry.parse::<SocketAddr>() {
+            Ok(addr) => addr,
+            Err(_) => entry
+                .parse::<IpAddr>()
+                .map(|ip| SocketAddr::new(ip, port))
+                .map_err(|_| TargetError::Invalid(entry.to_string()))?,
+        };
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    if targets.is_empty() {
+        return Err(TargetError::Empty);
+    }
+    Ok(targets)
+}
+
+/// Connection attempts running on their own, each with the server it is to.
+/// Dropping the set abandons those still in flight.
+pub type Attempts<T, E> = JoinSet<(SocketAddr, Result<T, E>)>;
+
+/// The first of several servers to answer, from [`first_to_answer`].
+#[derive(Debug)]
+pub struct Answered<T, E> {
+    pub addr: SocketAddr,
+    pub value: T,
+    /// Servers that had already failed, in the order they were named.
+    pub failed: Vec<(SocketAddr, E)>,
+    /// The attempts on every other server, left running to mirror to each
+    /// as it answers.
+    pub others: Attempts<T, E>,
+}
+
+/// Tries every one of `targets` at once with `connect`, for the first to
+/// succeed to become the server capture starts with, so one that is down
+/// holds up none of the others. Attempts still in flight then carry on in
+/// [`Answered::others`]. Fails with every target's error, in the order they
+/// were named, only if none succeeded.
+pub async fn first_to_answer<T, E, F, Fut>(
+    targets: &[SocketAddr],
+    connect: F,
+) -> Result<Answered<T, E>, Vec<(SocketAddr, E)>>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+    F: Fn(SocketAddr) -> Fut,
+    Fut: Future<Output = Result<T, E>> + Send + 'static,
+{
+    let mut attempts = JoinSet::new();
+    for &addr in targets {
+        let attempt = connect(addr);
+        attempts.spawn(async move { (addr, attempt.await) });
+    }
+    let mut failures = Vec::new();
+    let by_order = |(addr, _): &(SocketAddr, E)| targets.iter().position(|target| target == addr);
+    while let Some(joined) = attempts.join_next().await {
+        let (addr, result) = match joined {
+            Ok(attempt) => attempt,
+            Err(err) => panic::resume_unwind(err.into_panic()),
+        };
+        match result {
+            Ok(value) => {
+                failures.sort_by_key(by_order);
+                return Ok(Answered {
+                    addr,
+                    value,
+                    failed: failures,
+                    others: attempts,
+                });
+            }
+            Err(err) => failures.push((addr, err)),
+        }
+    }
+    failures.sort_by_key(by_order);
+    Err(failures)
+}
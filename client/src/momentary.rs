@@ -0,0 +1,112 @@
+//! Hold-to-capture, like push-to-talk: the grab runs from when capture is
+//! started, but input only goes to the server while a trigger key is held.
+//! The rest of the time everything is left to this machine. Letting go of
+//! the trigger releases on the server whatever was still held there, so a
+//! key can't stay stuck down between holds.
+
+use std::time::{Duration, Instant};
+
+use rdev::{EventType, Key};
+use serde::{Deserialize, Serialize};
+
+/// Rarely bound to anything, and on most keyboards without a key of its own
+/// to clash with.
+pub const DEFAULT_HOLD_KEY: Key = Key::ScrollLock;
+
+/// When capture forwards input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    /// From a click in the input view until the stop chord.
+    #[default]
+    Toggle,
+    /// Only while the hold key is down.
+    Hold,
+}
+
+/// What the grab callback does with an event in hold mode.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HoldAction {
+    /// The trigger isn't held: leave the event to this machine.
+    Pass,
+    /// The trigger went down: start forwarding.
+    Engage,
+    /// Handle the event as capture always does.
+    Forward,
+    /// The trigger came up after `held_for`: stop forwarding, once
+    /// `releases` are sent for what is still down on the server.
+    Disengage {
+        held_for: Duration,
+        releases: Vec<EventType>,
+    },
+    /// Auto-repeat of the held trigger; swallow it.
+    Ignore,
+}
+
+/// Tracks the trigger and what was pressed on the server while it was held.
+#[derive(Debug, Clone)]
+pub struct HoldTrigger {
+    key: Key,
+    engaged_at: Option<Instant>,
+    /// Releases owed to the server, oldest press first.
+    held: Vec<EventType>,
+}
+
+impl HoldTrigger {
+    pub fn new(key: Key) -> Self {
+        Self {
+            key,
+            engaged_at: None,
+            held: Vec::new(),
+        }
+    }
+
+    pub fn key(&self) -> Key {
+        self.key
+    }
+
+    pub fn is_engaged(&self) -> bool {
+        self.engaged_at.is_some()
+    }
+
+    pub fn on_event(&mut self, event: &EventType, now: Instant) -> HoldAction {
+        match (*event, self.engaged_at) {
+            (EventType::KeyPress(key), None) if key == self.key => {
+                self.engaged_at = Some(now);
+                HoldAction::Engage
+            }
+            (EventType::KeyPress(key), Some(_)) if key == self.key => HoldAction::Ignore,
+            (EventType::KeyRelease(key), Some(engaged_at)) if key == self.key => {
+                self.engaged_at = None;
+                let mut releases = std::mem::take(&mut self.held);
+                // Most recent first, the way fingers usually come up.
+                releases.reverse();
+                HoldAction::Disengage {
+                    held_for: now.saturating_duration_since(engaged_at),
+                    releases,
+                }
+            }
+            (_, None) => HoldAction::Pass,
+            (_, Some(_)) => {
+                self.track(event);
+                HoldAction::Forward
+            }
+        }
+    }
+
+    fn track(&mut self, event: &EventType) {
+        let release = match *event {
+            EventType::KeyPress(key) | EventType::KeyRelease(key) => EventType::KeyRelease(key),
+            EventType::ButtonPress(button) | EventType::ButtonRelease(button) => {
+                EventType::ButtonRelease(button)
+            }
+            _ => return,
+        };
+        let pressed = matches!(event, EventType::KeyPress(_) | EventType::ButtonPress(_));
+        if !pressed {
+            self.held.retain(|held| *held != release);
+        } else if !self.held.contains(&release) {
+            self.held.push(release);
+        }
+    }
+}
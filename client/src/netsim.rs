@@ -0,0 +1,95 @@
+//! Artificial latency and loss on the client's send path, for reproducing
+//! flaky networks in tests without touching the real link.
+//!
+//! Debug builds read it from the environment:
+//!
+//! * `QUICINPUT_SIM_DELAY_MS` – fixed delay before every write
+//! * `QUICINPUT_SIM_JITTER_MS` – extra random delay of up to this much
+//! * `QUICINPUT_SIM_DROP` – probability (0.0–1.0) that a write is skipped
+//! * `QUICINPUT_SIM_SEED` – RNG seed, for repeatable runs
+
+#[cfg(debug_assertions)]
+use std::env;
+use std::time::Duration;
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetSim {
+    pub delay: Duration,
+    pub jitter: Duration,
+    pub drop_rate: f64,
+    pub seed: u64,
+}
+
+impl NetSim {
+    /// Conditions from the `QUICINPUT_SIM_*` variables, or `None` when none
+    /// are set.
+    #[cfg(debug_assertions)]
+    pub fn from_env() -> Option<Self> {
+        let delay = env_number::<u64>("QUICINPUT_SIM_DELAY_MS");
+        let jitter = env_number::<u64>("QUICINPUT_SIM_JITTER_MS");
+        let drop_rate = env_number::<f64>("QUICINPUT_SIM_DROP");
+        if delay.is_none() && jitter.is_none() && drop_rate.is_none() {
+            return None;
+        }
+
+        let sim = Self {
+            delay: Duration::from_millis(delay.unwrap_or(0)),
+            jitter: Duration::from_millis(jitter.unwrap_or(0)),
+            drop_rate: drop_rate.unwrap_or(0.0).clamp(0.0, 1.0),
+            seed: env_number("QUICINPUT_SIM_SEED").unwrap_or_else(rand::random),
+        };
+        eprintln!("[client] simulating network conditions: {sim:?}");
+        Some(sim)
+    }
+
+    /// Release builds never simulate anything.
+    #[cfg(not(debug_assertions))]
+    pub fn from_env() -> Option<Self> {
+        None
+    }
+}
+
+/// Running state for one worker's [`NetSim`].
+pub struct SimulatedLink {
+    sim: NetSim,
+    rng: StdRng,
+}
+
+impl SimulatedLink {
+    pub fn new(sim: NetSim) -> Self {
+        Self {
+            sim,
+            rng: StdRng::seed_from_u64(sim.seed),
+        }
+    }
+
+    /// Waits out the simulated latency, then says whether the write should
+    /// go ahead (`false` means it is dropped).
+    pub async fn before_send(&mut self) -> bool {
+        let jitter = if self.sim.jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            self.sim.jitter.mul_f64(self.rng.random::<f64>())
+        };
+        let delay = self.sim.delay + jitter;
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+
+        !self.rng.random_bool(self.sim.drop_rate)
+    }
+}
+
+#[cfg(debug_assertions)]
+fn env_number<T: std::str::FromStr>(name: &str) -> Option<T> {
+    let value = env::var(name).ok()?;
+    match value.trim().parse() {
+        Ok(number) => Some(number),
+        Err(_) => {
+            eprintln!("[client] ignoring {name}={value}: not a number");
+            None
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::{
+    error::Error,
+    io::{self, Cursor},
+};
+
+use quinn::Connection;
+use rmp_serde::decode;
+use shared::ObservedInput;
+
+const MAX_CHUNK: usize = 64 * 1024;
+
+/// Hands every input the server applies to `on_input`, for a connection that
+/// joined as an observer. Returns once the server closes the stream.
+///
+/// Nothing is injected locally, so watching a server that runs on this very
+/// machine cannot feed its input back into it.
+pub async fn watch_observed<F>(
+    connection: Connection,
+    mut on_input: F,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>>
+where
+    F: FnMut(ObservedInput),
+{
+    let mut recv = connection.accept_uni().await?;
+    let mut buf = Vec::new();
+
+    while let Some(chunk) = recv.read_chunk(MAX_CHUNK, true).await? {
+        buf.extend_from_slice(&chunk.bytes);
+
+        // A chunk may end mid-value or hold several values.
+        loop {
+            let mut cursor = Cursor::new(buf.as_slice());
+            match decode::from_read::<_, ObservedInput>(&mut cursor) {
+                Ok(input) => {
+                    let used = cursor.position() as usize;
+                    buf.drain(..used);
+                    on_input(input);
+                }
+                Err(
+                    decode::Error::InvalidMarkerRead(err) | decode::Error::InvalidDataRead(err),
+                ) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    break;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    Ok(())
+}
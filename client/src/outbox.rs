@@ -0,0 +1,214 @@
+//! Where captured input goes on its way to the send worker. While the
+//! connection is down the worker is gone, so input is held in a bounded
+//! queue instead of being thrown away, and capture carries on: the stop
+//! chord must keep working even with nothing to send its shutdown to.
+//! Once capture is handed a new connection the queue is flushed or
+//! discarded per [`ReconnectPolicy`].
+//!
+//! Only the server capture started with is queued for. Any mirrors (see
+//! [`crate::mirror`]) are handed input as it is sent, whether or not that
+//! server is reached.
+//!
+//! Everything passing through can also be recorded; see [`crate::recording`]
+//! and [`crate::macros`].
+
+use std::collections::VecDeque;
+
+use shared::MouseMove;
+
+use crate::macros::MacroRecording;
+use crate::mirror::Mirror;
+use crate::quic_helper_thread::{QuicCommand, QuicSender};
+use crate::recording::Recorder;
+
+/// Input held while disconnected, unless configured otherwise.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 256;
+
+/// What happens to input queued during a disconnect once capture reconnects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReconnectPolicy {
+    /// Send it, so keys typed during a blip still arrive, late.
+    #[default]
+    Flush,
+    /// Drop it; nothing typed while disconnected reaches the server.
+    Discard,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutboxOptions {
+    /// Commands held while disconnected; the oldest are dropped past this.
+    /// Consecutive moves are merged, so they count once.
+    pub capacity: usize,
+    pub policy: ReconnectPolicy,
+}
+
+impl Default for OutboxOptions {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_QUEUE_CAPACITY,
+            policy: ReconnectPolicy::default(),
+        }
+    }
+}
+
+pub struct Outbox {
+    sender: Option<QuicSender>,
+    queue: VecDeque<QuicCommand>,
+    options: OutboxOptions,
+    dropped: u64,
+    recorder: Option<Recorder>,
+    macro_recording: Option<MacroRecording>,
+    mirror: Option<Mirror>,
+    /// The last [`QuicCommand::Holding`] sent, for a new worker to pick up.
+    holding: bool,
+}
+
+impl Outbox {
+    pub fn new(sender: QuicSender, options: OutboxOptions) -> Self {
+        Self {
+            sender: Some(sender),
+            queue: VecDeque::new(),
+            options,
+            dropped: 0,
+            recorder: None,
+            macro_recording: None,
+            mirror: None,
+            holding: false,
+        }
+    }
+
+    /// Records every command sent from now on, as captured, whether or not
+    /// it reaches the worker straight away.
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Adds every command sent to `recording` while a macro is recorded.
+    pub fn with_macro_recording(mut self, recording: MacroRecording) -> Self {
+        self.macro_recording = Some(recording);
+        self
+    }
+
+    /// Mirrors every command sent from now on to `mirror`'s targets.
+    pub fn with_mirror(mut self, mirror: Mirror) -> Self {
+        self.mirror = Some(mirror);
+        self
+    }
+
+    /// Stops mirroring, shutting down the mirrors' workers.
+    pub fn stop_mirroring(&mut self) {
+        if let Some(mut mirror) = self.mirror.take() {
+            mirror.send(&QuicCommand::Shutdown);
+        }
+    }
+
+    /// Whether the last send reached the worker.
+    pub fn is_connected(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    /// Commands waiting for a connection.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Commands dropped because the queue was full, since the last reconnect.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Hands `command` to the worker, or queues it if there is none, and
+    /// to any mirrors. A failed send means the worker has gone, so the
+    /// outbox switches to queuing from then on.
+    pub fn send(&mut self, command: QuicCommand) {
+        if let QuicCommand::Holding(holding) = command {
+            self.holding = holding;
+        }
+        self.record(&command);
+        if let Some(mirror) = self.mirror.as_mut() {
+            mirror.send(&command);
+        }
+        self.forward(command);
+    }
+
+    /// Asks the worker and any mirrors to finish their streams, and drops
+    /// anything queued. Safe to call while disconnected.
+    pub fn shutdown(&mut self) {
+        self.queue.clear();
+        self.stop_mirroring();
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(QuicCommand::Shutdown);
+        }
+    }
+
+    /// Switches to the worker behind `sender` and flushes or discards the
+    /// queue per the policy. Returns how many commands were flushed.
+    pub fn reconnect(&mut self, sender: QuicSender) -> usize {
+        self.sender = Some(sender);
+        self.dropped = 0;
+        if self.holding {
+            self.forward(QuicCommand::Holding(true));
+        }
+        let queued: Vec<QuicCommand> = self.queue.drain(..).collect();
+        if self.options.policy == ReconnectPolicy::Discard {
+            return 0;
+        }
+        let flushed = queued.len();
+        for command in queued {
+            self.forward(command);
+        }
+        flushed
+    }
+
+    /// Sends an already recorded command.
+    fn forward(&mut self, command: QuicCommand) {
+        let Some(sender) = self.sender.as_ref() else {
+            self.enqueue(command);
+            return;
+        };
+        if let Err(unsent) = sender.send(command) {
+            self.sender = None;
+            self.enqueue(unsent.0);
+        }
+    }
+
+    fn record(&mut self, command: &QuicCommand) {
+        if let Some(recording) = &self.macro_recording {
+            recording.record(command);
+        }
+        let Some(recorder) = self.recorder.as_mut() else {
+            return;
+        };
+        if let Err(err) = recorder.record(command) {
+            eprintln!("[client] recording stopped: {err}");
+            self.recorder = None;
+        }
+    }
+
+    fn enqueue(&mut self, command: QuicCommand) {
+        // Nothing to shut down while disconnected, and whether anything is
+        // held is told to the next worker as it connects.
+        if matches!(command, QuicCommand::Shutdown | QuicCommand::Holding(_)) {
+            return;
+        }
+        if let (QuicCommand::Move(next), Some(QuicCommand::Move(last))) =
+            (&command, self.queue.back_mut())
+        {
+            *last = MouseMove {
+                dx: last.dx + next.dx,
+                dy: last.dy + next.dy,
+            };
+            return;
+        }
+        if self.options.capacity == 0 {
+            self.dropped += 1;
+            return;
+        }
+        if self.queue.len() >= self.options.capacity {
+            self.queue.pop_front();
+            self.dropped += 1;
+        }
+        self.queue.push_back(command);
+    }
+}
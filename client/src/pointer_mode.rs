@@ -0,0 +1,105 @@
+//! Choosing between relative and absolute pointer forwarding.
+//!
+//! The user picks a mode in the input view at any time; it takes effect at
+//! the next capture start, once the server has agreed to it. A server that
+//! can't place the pointer absolutely (or predates absolute mode) answers
+//! with relative, and isn't asked again until the next connection.
+
+use std::error::Error;
+
+use quinn::Connection;
+use shared::{ControlRequest, ControlResponse, DisplaySize, PointerMode};
+
+use crate::quic::control_request;
+
+/// The user's choice against what the server has agreed to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PointerModeState {
+    selected: PointerMode,
+    in_effect: PointerMode,
+    absolute_refused: bool,
+}
+
+impl PointerModeState {
+    /// Starts out relative, as every server does, with `selected` to be
+    /// asked for at the first capture.
+    pub fn new(selected: PointerMode) -> Self {
+        Self {
+            selected,
+            in_effect: PointerMode::Relative,
+            absolute_refused: false,
+        }
+    }
+
+    pub fn selected(&self) -> PointerMode {
+        self.selected
+    }
+
+    /// The mode the next capture runs in.
+    pub fn in_effect(&self) -> PointerMode {
+        self.in_effect
+    }
+
+    /// Whether the server turned absolute mode down on this connection.
+    pub fn absolute_refused(&self) -> bool {
+        self.absolute_refused
+    }
+
+    /// Records the user's choice. Returns whether it changed.
+    pub fn select(&mut self, mode: PointerMode) -> bool {
+        let changed = self.selected != mode;
+        self.selected = mode;
+        changed
+    }
+
+    /// The mode to ask the server for before the next capture, if any: none
+    /// when it is already in effect or the server has refused it.
+    pub fn request_for_capture(&self) -> Option<PointerMode> {
+        if self.selected == self.in_effect {
+            return None;
+        }
+        if self.selected == PointerMode::Absolute && self.absolute_refused {
+            return None;
+        }
+        Some(self.selected)
+    }
+
+    /// Records the server's answer to a request for `requested`; `None` when
+    /// it gave none, as an older server won't.
+    pub fn answered(&mut self, requested: PointerMode, granted: Option<PointerMode>) {
+        let granted = granted.unwrap_or(PointerMode::Relative);
+        if requested == PointerMode::Absolute && granted != PointerMode::Absolute {
+            self.absolute_refused = true;
+        }
+        self.in_effect = granted;
+    }
+
+    /// A new connection starts relative and may be asked again.
+    pub fn reconnected(&mut self) {
+        self.in_effect = PointerMode::Relative;
+        self.absolute_refused = false;
+    }
+}
+
+/// Asks the server to switch to `mode`, returning the mode now in effect.
+pub async fn request_pointer_mode(
+    connection: &Connection,
+    mode: PointerMode,
+) -> Result<PointerMode, Box<dyn Error + Send + Sync + 'static>> {
+    match control_request(connection, &ControlRequest::SetPointerMode(mode)).await? {
+        ControlResponse::PointerMode(granted) => Ok(granted),
+        other => Err(format!("unexpected pointer mode response: {other:?}").into()),
+    }
+}
+
+/// Tells the server the size of the monitor absolute moves now come from,
+/// in place of the one in the hello.
+pub async fn request_display_size(
+    connection: &Connection,
+    size: DisplaySize,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    match control_request(connection, &ControlRequest::SetDisplaySize(size)).await? {
+        ControlResponse::DisplaySize(_) => Ok(()),
+        other => Err(format!("unexpected display size response: {other:?}").into()),
+    }
+}
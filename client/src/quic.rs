@@ -1,17 +1,129 @@
 use std::{
+    env,
     error::Error,
-    net::{IpAddr, Ipv4Addr, SocketAddr},
-    sync::{Arc, OnceLock},
+    fmt,
+    io::{self, Cursor},
+    str::FromStr,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{Arc, Mutex, OnceLock},
     time::Duration,
 };
 
-use quinn::{ClientConfig, Connection, Endpoint, RecvStream, SendStream, TransportConfig};
+use quinn::{ClientConfig, Connection, ConnectionError, Endpoint, RecvStream, SendStream, TransportConfig};
 use quinn::crypto::rustls::QuicClientConfig;
+use rustls::crypto::{CryptoProvider, aws_lc_rs, hash::HashAlgorithm, ring};
+use rmp_serde::decode;
 use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
-use tokio::{runtime::{Builder, Runtime}, time::timeout};
+use shared::{
+    CloseCode, ControlRequest, ControlResponse, DisplayInfo, DisplaySize, SessionToken,
+    codec::WireFormat, congestion::CongestionControl, monotonic_micros,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    runtime::{Builder, Runtime},
+    sync::Notify,
+    time::timeout,
+};
 
 static TOKIO_RUNTIME: OnceLock<Runtime> = OnceLock::new();
 
+/// Largest control response we accept; matches the server's own stream cap.
+pub const MAX_RESPONSE_BYTES: usize = 64 * 1024;
+
+/// Piece size for [`send_stream_chunked`] when the caller has no reason to
+/// pick another.
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+/// How long a control response may take to arrive in full.
+pub const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long one connect attempt may take before it counts as timed out.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often an idle connection is pinged so neither side times it out.
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Name the client asks for during the TLS handshake unless told otherwise.
+pub const DEFAULT_SERVER_NAME: &str = "localhost";
+
+/// How long a graceful close waits for local writers to finish their streams.
+const WRITER_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Uni stream bookkeeping for one live connection, so a graceful close can
+/// ask the server to drain exactly the streams that were opened.
+#[derive(Debug, Default)]
+struct UniStreams {
+    state: Mutex<UniStreamState>,
+    writer_finished: Notify,
+}
+
+#[derive(Debug, Default)]
+struct UniStreamState {
+    opened: Vec<u64>,
+    writers: usize,
+}
+
+impl UniStreams {
+    fn state(&self) -> std::sync::MutexGuard<'_, UniStreamState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A connection as input is sent on it: the wire format agreed in its
+/// handshake and the uni streams opened on it so far. Clones share the
+/// bookkeeping, which goes away with the last of them.
+#[derive(Clone, Debug)]
+pub struct InputLink {
+    pub connection: Connection,
+    pub format: WireFormat,
+    streams: Arc<UniStreams>,
+}
+
+impl InputLink {
+    /// For a connection that never went through [`run_client`], e.g. one a
+    /// test set up itself.
+    pub fn new(connection: Connection, format: WireFormat) -> Self {
+        Self {
+            connection,
+            format,
+            streams: Arc::default(),
+        }
+    }
+
+    /// Opens a uni stream that a graceful close will ask the server to drain.
+    pub async fn open_uni(&self) -> Result<SendStream, Box<dyn Error + Send + Sync + 'static>> {
+        let send = open_uni(self.connection.clone()).await?;
+        self.streams.state().opened.push(u64::from(send.id()));
+        Ok(send)
+    }
+}
+
+/// Held by a task that writes uni streams on a connection; a graceful close
+/// waits for every such task to finish its streams first.
+pub struct StreamWriter {
+    streams: Arc<UniStreams>,
+}
+
+impl StreamWriter {
+    pub fn register(link: &InputLink) -> Self {
+        link.streams.state().writers += 1;
+        Self {
+            streams: Arc::clone(&link.streams),
+        }
+    }
+}
+
+impl Drop for StreamWriter {
+    fn drop(&mut self) {
+        {
+            let mut state = self.streams.state();
+            state.writers = state.writers.saturating_sub(1);
+        }
+        self.streams.writer_finished.notify_waiters();
+    }
+}
+
 pub fn quic_runtime() -> &'static Runtime {
     TOKIO_RUNTIME.get_or_init(|| {
         Builder::new_multi_thread()
@@ -22,45 +134,583 @@ pub fn quic_runtime() -> &'static Runtime {
     })
 }
 
+/// Makes sure rustls has a process-wide crypto provider, preferring aws-lc-rs.
+///
+/// A provider someone else already installed is kept. If aws-lc-rs can't be
+/// installed, ring is tried before giving up.
+pub fn install_crypto_provider() -> Result<(), String> {
+    if CryptoProvider::get_default().is_some() {
+        return Ok(());
+    }
+
+    // Losing an install race still leaves a usable default behind.
+    if aws_lc_rs::default_provider().install_default().is_ok()
+        || CryptoProvider::get_default().is_some()
+    {
+        return Ok(());
+    }
+
+    eprintln!("[client] aws-lc-rs crypto provider unavailable; falling back to ring");
+    if ring::default_provider().install_default().is_ok()
+        || CryptoProvider::get_default().is_some()
+    {
+        return Ok(());
+    }
+
+    Err("no TLS crypto provider could be installed".to_string())
+}
+
+/// A live connection plus what the server told us during the handshake.
+pub struct ClientSession {
+    pub endpoint: Endpoint,
+    pub connection: Connection,
+    pub token: Option<SessionToken>,
+    /// The server's displays, cached once so capture can scale and clamp
+    /// against them without another round trip.
+    pub remote_displays: Vec<DisplayInfo>,
+    /// The server accepted us as an observer; see [`crate::observer`].
+    pub observing: bool,
+    /// How input on this connection is encoded.
+    pub wire_format: WireFormat,
+    /// The server says when its machine can't take input; see
+    /// [`crate::availability`].
+    pub reports_availability: bool,
+    streams: Arc<UniStreams>,
+}
+
+impl ClientSession {
+    /// What input is sent on; every link from one session shares its
+    /// stream bookkeeping.
+    pub fn link(&self) -> InputLink {
+        InputLink {
+            connection: self.connection.clone(),
+            format: self.wire_format,
+            streams: Arc::clone(&self.streams),
+        }
+    }
+}
+
+/// Closes a client endpoint unless the connect that owns it succeeds, so an
+/// attempt that fails or is abandoned mid-way (e.g. its task is aborted)
+/// never leaves a half-open endpoint behind.
+struct EndpointGuard(Option<Endpoint>);
+
+impl EndpointGuard {
+    fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for EndpointGuard {
+    fn drop(&mut self) {
+        if let Some(endpoint) = self.0.take() {
+            endpoint.close(CloseCode::UserDisconnect.into(), b"connect abandoned");
+        }
+    }
+}
+
+/// Why [`run_client`] couldn't connect, for callers to tell the common
+/// failures apart; [`crate::close_reason::describe_connect_error`] puts each
+/// into words for the user.
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectError {
+    /// The local UDP socket couldn't be bound.
+    #[error("failed to bind a local socket: {0}")]
+    Socket(#[source] io::Error),
+    /// TLS couldn't be set up on this machine.
+    #[error("failed to set up TLS: {0}")]
+    Tls(#[source] Box<dyn Error + Send + Sync + 'static>),
+    /// The address or server name was refused before anything was sent.
+    #[error("can't connect to {addr}: {source}")]
+    InvalidAddress {
+        addr: SocketAddr,
+        #[source]
+        source: quinn::ConnectError,
+    },
+    /// Nothing answered in time.
+    #[error("QUIC connect timed out after {}ms", .0.as_millis())]
+    TimedOut(Duration),
+    /// The server presented a certificate other than the pinned one.
+    #[error("server certificate {presented} does not match the pin")]
+    CertificateMismatch { presented: CertPin },
+    /// The server closed the connection as it was being set up, e.g. for
+    /// being off its allowlist or full.
+    #[error("server refused the connection: {0}")]
+    Refused(#[source] ConnectionError),
+    /// The server speaks no QUIC version this client does.
+    #[error("server speaks an incompatible QUIC version")]
+    VersionMismatch,
+    /// Any other way the connection failed.
+    #[error("connection failed: {0}")]
+    Connection(#[source] ConnectionError),
+}
+
+impl ConnectError {
+    /// Whether another attempt might succeed. A server that didn't answer
+    /// within the connect timeout is not tried again, as the wait would
+    /// only grow with each attempt.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ConnectError::Connection(error) => is_retryable(error),
+            _ => false,
+        }
+    }
+
+    fn from_connection(error: ConnectionError, connect_timeout: Duration) -> Self {
+        match error {
+            ConnectionError::TimedOut => ConnectError::TimedOut(connect_timeout),
+            ConnectionError::VersionMismatch => ConnectError::VersionMismatch,
+            ConnectionError::ApplicationClosed(_) => ConnectError::Refused(error),
+            ConnectionError::ConnectionClosed(ref close)
+                if close.error_code == quinn::TransportErrorCode::CONNECTION_REFUSED =>
+            {
+                ConnectError::Refused(error)
+            }
+            error => ConnectError::Connection(error),
+        }
+    }
+}
+
+async fn connect_once(
+    endpoint: &Endpoint,
+    options: &ClientOptions,
+    verification: &ServerVerification,
+) -> Result<Connection, ConnectError> {
+    // A bad address or config is rejected up front and never retried.
+    let connecting = endpoint
+        .connect(options.server_addr, &options.server_name)
+        .map_err(|source| ConnectError::InvalidAddress {
+            addr: options.server_addr,
+            source,
+        })?;
+
+    match timeout(options.connect_timeout, connecting).await {
+        Ok(Ok(connection)) => Ok(connection),
+        Ok(Err(e)) => Err(match verification.mismatched() {
+            // The TLS alert says little; the verifier knows what it refused.
+            Some(presented) => ConnectError::CertificateMismatch { presented },
+            None => ConnectError::from_connection(e, options.connect_timeout),
+        }),
+        Err(_) => Err(ConnectError::TimedOut(options.connect_timeout)),
+    }
+}
+
+/// The provider chosen by [`install_crypto_provider`], so the TLS config and
+/// the certificate verifier never disagree about which backend they use.
+fn installed_crypto_provider() -> Result<Arc<CryptoProvider>, Box<dyn Error + Send + Sync + 'static>> {
+    install_crypto_provider()?;
+    CryptoProvider::get_default()
+        .cloned()
+        .ok_or_else(|| "no TLS crypto provider installed".into())
+}
+
+/// How far [`run_client_with_progress`] has got, for showing to the user
+/// during a connect that can take several seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectPhase {
+    /// Binding the local endpoint and building the TLS config.
+    Preparing,
+    /// Waiting on the QUIC handshake.
+    Connecting,
+    /// Exchanging the session hello.
+    Handshaking,
+    /// Asking for the server's display layout.
+    FetchingDisplays,
+    /// The last attempt failed in a way that may clear up; waiting to try again.
+    Retrying,
+}
+
+impl ConnectPhase {
+    pub const ALL: [ConnectPhase; 5] = [
+        ConnectPhase::Preparing,
+        ConnectPhase::Connecting,
+        ConnectPhase::Handshaking,
+        ConnectPhase::FetchingDisplays,
+        ConnectPhase::Retrying,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ConnectPhase::Preparing => "Preparing connection\u{2026}",
+            ConnectPhase::Connecting => "Connecting\u{2026}",
+            ConnectPhase::Handshaking => "Starting session\u{2026}",
+            ConnectPhase::FetchingDisplays => "Fetching server displays\u{2026}",
+            ConnectPhase::Retrying => "Server not answering yet; retrying\u{2026}",
+        }
+    }
+}
+
+/// How [`run_client_with_progress`] retries a connect that failed in a way
+/// that may clear up on its own, i.e. a reset during the handshake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Connect attempts in total, including the first; at least one is made.
+    pub attempts: u32,
+    /// Wait before the first retry, doubled for each one after.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(250),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The default, with `QUICINPUT_CONNECT_ATTEMPTS` overriding the number
+    /// of attempts.
+    pub fn from_env() -> Self {
+        Self::from_env_or(Self::default().attempts)
+    }
+
+    /// `attempts` in all, e.g. as kept in
+    /// [`crate::settings::ClientSettings::connect_attempts`], unless
+    /// `QUICINPUT_CONNECT_ATTEMPTS` overrides it.
+    pub fn from_env_or(attempts: u32) -> Self {
+        let mut policy = Self {
+            attempts,
+            ..Self::default()
+        };
+        if let Ok(value) = env::var("QUICINPUT_CONNECT_ATTEMPTS") {
+            match value.trim().parse() {
+                Ok(attempts) => policy.attempts = attempts,
+                Err(_) => eprintln!("[client] ignoring QUICINPUT_CONNECT_ATTEMPTS={value}: not a number"),
+            }
+        }
+        policy
+    }
+
+    /// Wait before retry number `retry`, counting from 1.
+    pub fn delay_before(&self, retry: u32) -> Duration {
+        self.backoff
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+    }
+}
+
+/// SHA-256 of the server's leaf certificate, for trusting exactly that
+/// certificate instead of whatever the server presents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CertPin(pub [u8; 32]);
+
+impl CertPin {
+    /// The pin for a DER-encoded certificate.
+    pub fn of(cert_der: &[u8]) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        let provider = installed_crypto_provider()?;
+        sha256(&provider, cert_der)
+            .map(CertPin)
+            .ok_or_else(|| "TLS crypto provider has no SHA-256".into())
+    }
+}
+
+impl fmt::Display for CertPin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, byte) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{byte:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for CertPin {
+    type Err = String;
+
+    /// Accepts 64 hex digits, optionally separated by colons as most tools
+    /// print fingerprints.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let digits: Vec<u8> = value.bytes().filter(|byte| *byte != b':').collect();
+        if digits.len() != 64 {
+            return Err(format!("certificate pin must be 32 bytes of hex, got '{value}'"));
+        }
+        let mut pin = [0u8; 32];
+        for (byte, pair) in pin.iter_mut().zip(digits.chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| format!("invalid certificate pin '{value}'"))?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| format!("invalid certificate pin '{value}'"))?;
+        }
+        Ok(CertPin(pin))
+    }
+}
+
+/// How [`run_client`] reaches the server. [`ClientOptions::new`] connects
+/// the way the client always has; the `with_*` methods change one thing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientOptions {
+    pub server_addr: SocketAddr,
+    /// Name sent for SNI. Only checked by the server, if at all.
+    pub server_name: String,
+    /// Trust only this certificate. Without one any certificate is accepted.
+    pub cert_pin: Option<CertPin>,
+    /// How long one connect attempt may take before it counts as timed out.
+    pub connect_timeout: Duration,
+    /// `None` sends no keep-alives, so an idle connection may time out. They
+    /// also tell the server of a new address after a network change while
+    /// no input is being sent, so it can follow.
+    pub keep_alive: Option<Duration>,
+    /// Local UDP port to bind; 0 lets the OS pick one.
+    pub local_port: u16,
+    pub retry: RetryPolicy,
+    /// Size of the display absolute positions are mapped from, told to the
+    /// server in the handshake so it can keep the aspect ratio.
+    pub display_size: Option<DisplaySize>,
+    /// Controller for what the client sends; the server picks its own.
+    pub congestion_control: CongestionControl,
+    /// Encoding to ask the server to read input in. A server that predates
+    /// the choice reads MessagePack whatever is asked for.
+    pub wire_format: WireFormat,
+}
+
+impl ClientOptions {
+    pub fn new(server_addr: SocketAddr) -> Self {
+        Self {
+            server_addr,
+            server_name: DEFAULT_SERVER_NAME.to_string(),
+            cert_pin: None,
+            connect_timeout: CONNECT_TIMEOUT,
+            keep_alive: Some(KEEP_ALIVE_INTERVAL),
+            local_port: 0,
+            retry: RetryPolicy::default(),
+            display_size: None,
+            congestion_control: CongestionControl::default(),
+            wire_format: WireFormat::default(),
+        }
+    }
+
+    pub fn with_server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = server_name.into();
+        self
+    }
+
+    pub fn with_cert_pin(mut self, pin: CertPin) -> Self {
+        self.cert_pin = Some(pin);
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn with_keep_alive(mut self, keep_alive: Option<Duration>) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    pub fn with_local_port(mut self, local_port: u16) -> Self {
+        self.local_port = local_port;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_display_size(mut self, display_size: DisplaySize) -> Self {
+        self.display_size = Some(display_size);
+        self
+    }
+
+    pub fn with_congestion_control(mut self, congestion_control: CongestionControl) -> Self {
+        self.congestion_control = congestion_control;
+        self
+    }
+
+    pub fn with_wire_format(mut self, wire_format: WireFormat) -> Self {
+        self.wire_format = wire_format;
+        self
+    }
+}
+
+/// The controller named by `QUICINPUT_CONGESTION`, e.g. `bbr`, or the
+/// default.
+pub fn congestion_control_from_env() -> CongestionControl {
+    let Ok(value) = env::var("QUICINPUT_CONGESTION") else {
+        return CongestionControl::default();
+    };
+    value.parse().unwrap_or_else(|err| {
+        eprintln!("[client] ignoring QUICINPUT_CONGESTION: {err}");
+        CongestionControl::default()
+    })
+}
+
+/// The format named by `QUICINPUT_WIRE_FORMAT`, e.g. `json` to read input
+/// in a packet capture, or MessagePack.
+pub fn wire_format_from_env() -> WireFormat {
+    let Ok(value) = env::var("QUICINPUT_WIRE_FORMAT") else {
+        return WireFormat::default();
+    };
+    value.parse().unwrap_or_else(|err| {
+        eprintln!("[client] ignoring QUICINPUT_WIRE_FORMAT: {err}");
+        WireFormat::default()
+    })
+}
+
+/// Whether a failed connect is worth another attempt: only a reset, as
+/// from a server that restarted mid-handshake. The server closing or
+/// refusing us, a protocol mismatch or a timeout will fail the same way
+/// again.
+pub fn is_retryable(error: &ConnectionError) -> bool {
+    matches!(error, ConnectionError::Reset)
+}
+
 pub async fn run_client(
-    server_addr: SocketAddr,
-) -> Result<(Endpoint, Connection), Box<dyn Error + Send + Sync + 'static>> {
+    options: ClientOptions,
+    resume_token: Option<SessionToken>,
+    observe: bool,
+) -> Result<ClientSession, ConnectError> {
+    run_client_with_progress(options, resume_token, observe, |_| {}).await
+}
+
+/// Like [`run_client`], reporting each [`ConnectPhase`] as it starts.
+pub async fn run_client_with_progress<F>(
+    options: ClientOptions,
+    resume_token: Option<SessionToken>,
+    observe: bool,
+    mut on_phase: F,
+) -> Result<ClientSession, ConnectError>
+where
+    F: FnMut(ConnectPhase) + Send,
+{
     println!("Attempting");
-    let mut endpoint = Endpoint::client(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))?;
+    on_phase(ConnectPhase::Preparing);
+    // Bound to the server's address family, so IPv6 servers are reachable.
+    let local_ip = match options.server_addr {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let mut endpoint = Endpoint::client(SocketAddr::new(local_ip, options.local_port))
+        .map_err(ConnectError::Socket)?;
+    let guard = EndpointGuard(Some(endpoint.clone()));
 
-    let rustls_config = rustls::ClientConfig::builder()
+    let provider = installed_crypto_provider().map_err(ConnectError::Tls)?;
+    let verification = ServerVerification::new(provider.clone(), options.cert_pin);
+    let rustls_config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| ConnectError::Tls(Box::new(e)))?
         .dangerous()
-        .with_custom_certificate_verifier(SkipServerVerification::new())
+        .with_custom_certificate_verifier(verification.clone())
         .with_no_client_auth();
 
-    let mut client_config = ClientConfig::new(Arc::new(QuicClientConfig::try_from(rustls_config)?));
+    let quic_config = QuicClientConfig::try_from(rustls_config).map_err(|e| ConnectError::Tls(Box::new(e)))?;
+    let mut client_config = ClientConfig::new(Arc::new(quic_config));
 
     let mut transport_config = TransportConfig::default();
-    transport_config.keep_alive_interval(Some(Duration::from_secs(5)));
+    transport_config.keep_alive_interval(options.keep_alive);
+    options.congestion_control.apply(&mut transport_config);
     client_config.transport_config(Arc::new(transport_config));
 
     endpoint.set_default_client_config(client_config);
     // connect to server
-    let connect_future = endpoint
-        .connect(server_addr, "localhost")
-        .unwrap();
-
-    let connection = timeout(Duration::from_secs(10), connect_future)
-        .await
-        .map_err(|_| {
-            Box::new(std::io::Error::new(
-                std::io::ErrorKind::TimedOut,
-                "QUIC connect timed out after 10s",
-            )) as Box<dyn Error + Send + Sync + 'static>
-        })?
-        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync + 'static>)?;
+    let mut attempt = 1;
+    let connection = loop {
+        on_phase(ConnectPhase::Connecting);
+        let error = match connect_once(&endpoint, &options, &verification).await {
+            Ok(connection) => break connection,
+            Err(error) => error,
+        };
+        if !error.is_retryable() || attempt >= options.retry.attempts {
+            return Err(error);
+        }
+        eprintln!(
+            "[client] connect attempt {attempt}/{} failed: {error}; retrying",
+            options.retry.attempts
+        );
+        on_phase(ConnectPhase::Retrying);
+        tokio::time::sleep(options.retry.delay_before(attempt)).await;
+        attempt += 1;
+    };
     println!("[client] connected: addr={}", connection.remote_address());
-    
 
-    Ok((endpoint, connection))
+    // Servers without session support answer with a plain ack; carry on without a token.
+    on_phase(ConnectPhase::Handshaking);
+    let hello = ControlRequest::Hello {
+        resume_token,
+        observe,
+        clock_micros: Some(monotonic_micros()),
+        display_size: options.display_size,
+        wire_format: options.wire_format,
+    };
+    let (token, observing, wire_format, reports_availability) =
+        match control_request(&connection, &hello).await {
+            Ok(ControlResponse::Welcome {
+                token,
+                resumed,
+                observing,
+                wire_format,
+                reports_availability,
+            }) => {
+                if resumed {
+                    println!("[client] resumed previous session");
+                }
+                (Some(token), observing, wire_format, reports_availability)
+            }
+            Ok(other) => {
+                eprintln!("[client] unexpected handshake response: {other:?}");
+                (None, false, WireFormat::default(), false)
+            }
+            Err(error) => {
+                // A server refusing us may only close the connection once it is up.
+                if let Some(reason) = connection.close_reason() {
+                    return Err(ConnectError::from_connection(reason, options.connect_timeout));
+                }
+                eprintln!("[client] session handshake failed: {error}");
+                (None, false, WireFormat::default(), false)
+            }
+        };
+    if observe && !observing {
+        eprintln!("[client] server did not accept observer mode");
+    }
+    if wire_format != options.wire_format {
+        eprintln!(
+            "[client] server will not read {} input; sending {wire_format}",
+            options.wire_format
+        );
+    }
+
+    on_phase(ConnectPhase::FetchingDisplays);
+    let remote_displays = match control_request(&connection, &ControlRequest::Displays).await {
+        Ok(ControlResponse::Displays(displays)) => displays,
+        Ok(other) => {
+            eprintln!("[client] unexpected displays response: {other:?}");
+            Vec::new()
+        }
+        Err(error) => {
+            eprintln!("[client] failed to query server displays: {error}");
+            Vec::new()
+        }
+    };
+
+    guard.disarm();
+    Ok(ClientSession {
+        endpoint,
+        connection,
+        token,
+        remote_displays,
+        observing,
+        wire_format,
+        reports_availability,
+        streams: Arc::default(),
+    })
+}
+
+/// Sends one control request on a fresh bi stream and waits for its response.
+pub async fn control_request(
+    connection: &Connection,
+    request: &ControlRequest,
+) -> Result<ControlResponse, Box<dyn Error + Send + Sync + 'static>> {
+    let (mut send, recv) = open_bi(connection.clone()).await?;
+    let encoded = rmp_serde::to_vec(request)?;
+    send_stream_chunked(&mut send, encoded.as_slice(), DEFAULT_CHUNK_SIZE, |_| {}).await?;
+    send.finish()?;
+    let response = receive_data(recv, MAX_RESPONSE_BYTES, RESPONSE_TIMEOUT).await?;
+    Ok(rmp_serde::from_slice(&response)?)
 }
 
-#[allow(dead_code)]
 pub async fn open_bi(
     connection: Connection
 ) -> Result<(SendStream, RecvStream), Box<dyn Error + Send + Sync + 'static>> {
@@ -71,6 +721,8 @@ pub async fn open_bi(
     Ok((send, recv))
 }
 
+/// Opens a uni stream that closing won't wait on; input goes through
+/// [`InputLink::open_uni`] instead.
 pub async fn open_uni(
     connection: Connection
 ) -> Result<SendStream, Box<dyn Error + Send + Sync + 'static>> {
@@ -92,46 +744,244 @@ pub async fn send_data(
     Ok(())
 }
 
-#[allow(dead_code)]
-pub async fn recieve_data(
-    mut recv_stream: RecvStream,
-) -> Result<Vec<u8>, Box<dyn Error + Send + Sync + 'static>> {
-    let resp = recv_stream
-        .read_to_end(usize::MAX)
+/// Copies `reader` onto `send_stream` at most `chunk_size` bytes at a time,
+/// calling `on_progress` with the total sent after each piece, so a large
+/// payload reports how far it has got instead of going in one `write_all`.
+/// A `chunk_size` of 0 sends a byte at a time. Returns the total sent; the
+/// stream is left open for the caller to finish.
+pub async fn send_stream_chunked<W, R, F>(
+    send_stream: &mut W,
+    mut reader: R,
+    chunk_size: usize,
+    mut on_progress: F,
+) -> Result<u64, Box<dyn Error + Send + Sync + 'static>>
+where
+    W: AsyncWrite + Unpin,
+    R: AsyncRead + Unpin,
+    F: FnMut(u64),
+{
+    let mut chunk = vec![0; chunk_size.max(1)];
+    let mut sent = 0u64;
+    loop {
+        let read = reader.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(sent);
+        }
+        send_stream.write_all(&chunk[..read]).await?;
+        sent += read as u64;
+        on_progress(sent);
+    }
+}
+
+/// Hands each [`ControlResponse`] the server pushes on `recv` to
+/// `on_response`, in order, until the server finishes the stream. A chunk
+/// may end mid-response or hold several of them.
+pub async fn read_responses<F>(
+    recv: &mut RecvStream,
+    mut on_response: F,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>>
+where
+    F: FnMut(ControlResponse),
+{
+    let mut buf = Vec::new();
+    while let Some(chunk) = recv.read_chunk(MAX_RESPONSE_BYTES, true).await? {
+        buf.extend_from_slice(&chunk.bytes);
+
+        loop {
+            let mut cursor = Cursor::new(buf.as_slice());
+            match decode::from_read::<_, ControlResponse>(&mut cursor) {
+                Ok(response) => {
+                    let used = cursor.position() as usize;
+                    buf.drain(..used);
+                    on_response(response);
+                }
+                Err(
+                    decode::Error::InvalidMarkerRead(err) | decode::Error::InvalidDataRead(err),
+                ) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    break;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Why [`receive_data`] gave up on a stream.
+#[derive(Debug)]
+pub enum ReceiveError {
+    /// The peer sent more than the caller allowed.
+    TooLarge { limit: usize },
+    /// The stream did not finish within the allowed time.
+    TimedOut(Duration),
+    Read(io::Error),
+}
+
+impl fmt::Display for ReceiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReceiveError::TooLarge { limit } => {
+                write!(f, "stream exceeded {limit} bytes")
+            }
+            ReceiveError::TimedOut(after) => {
+                write!(f, "stream did not finish within {}ms", after.as_millis())
+            }
+            ReceiveError::Read(error) => write!(f, "failed to read stream: {error}"),
+        }
+    }
+}
+
+impl Error for ReceiveError {}
+
+/// Reads a stream to its end, giving up past `max_bytes` or `read_timeout`.
+pub async fn receive_data<R: AsyncRead + Unpin>(
+    mut recv_stream: R,
+    max_bytes: usize,
+    read_timeout: Duration,
+) -> Result<Vec<u8>, ReceiveError> {
+    let read = async {
+        let mut resp = Vec::new();
+        // One byte past the cap is enough to tell a full response from an oversized one.
+        (&mut recv_stream)
+            .take(max_bytes as u64 + 1)
+            .read_to_end(&mut resp)
+            .await?;
+        Ok::<_, io::Error>(resp)
+    };
+
+    let resp = timeout(read_timeout, read)
         .await
-        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync + 'static>)?;
+        .map_err(|_| ReceiveError::TimedOut(read_timeout))?
+        .map_err(ReceiveError::Read)?;
+    if resp.len() > max_bytes {
+        return Err(ReceiveError::TooLarge { limit: max_bytes });
+    }
     Ok(resp)
 }
 
+/// Old misspelt name of [`receive_data`], kept so existing callers still build.
+#[deprecated(note = "renamed to `receive_data`")]
+pub async fn recieve_data<R: AsyncRead + Unpin>(
+    recv_stream: R,
+    max_bytes: usize,
+    read_timeout: Duration,
+) -> Result<Vec<u8>, ReceiveError> {
+    receive_data(recv_stream, max_bytes, read_timeout).await
+}
+
+/// Moves `endpoint` to a fresh local port, e.g. once the network it was
+/// sent from is gone. Its connection carries on from the new address once
+/// the server has checked the new path. Returns the new address.
+pub fn rebind_endpoint(endpoint: &Endpoint) -> io::Result<SocketAddr> {
+    let from = endpoint.local_addr()?;
+    // The new socket is registered with the runtime the endpoint runs on.
+    let _runtime = quic_runtime().enter();
+    endpoint.rebind(std::net::UdpSocket::bind(SocketAddr::new(from.ip(), 0))?)?;
+    endpoint.local_addr()
+}
+
 #[allow(dead_code)]
 pub async fn close_client(
-    connection: Connection,
-    endpoint: Endpoint
+    link: InputLink,
+    endpoint: Endpoint,
+    reason: CloseCode,
 ) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    connection.close(0u32.into(), b"done");
+    drain(&link).await;
+    link.connection.close(reason.into(), reason.name().as_bytes());
     // Give the server a fair chance to receive the close packet
     endpoint.wait_idle().await;
     Ok(())
 }
 
+/// Waits for the server to confirm it applied everything sent on this
+/// connection's uni streams, so closing can't cut off the last few events.
+async fn drain(link: &InputLink) {
+    let writers_done = async {
+        loop {
+            // Registered before the check so a writer finishing in between still wakes us.
+            let finished = link.streams.writer_finished.notified();
+            if link.streams.state().writers == 0 {
+                return;
+            }
+            finished.await;
+        }
+    };
+    if timeout(WRITER_DRAIN_TIMEOUT, writers_done).await.is_err() {
+        eprintln!("[client] input writers still busy at close; draining what was opened");
+    }
+
+    let streams = std::mem::take(&mut link.streams.state().opened);
+    if streams.is_empty() || link.connection.close_reason().is_some() {
+        return;
+    }
+
+    match control_request(&link.connection, &ControlRequest::Drain { streams }).await {
+        Ok(ControlResponse::Drained { complete: true }) => {}
+        Ok(ControlResponse::Drained { complete: false }) => {
+            eprintln!("[client] server could not drain every stream; some input may be lost");
+        }
+        Ok(other) => eprintln!("[client] unexpected drain response: {other:?}"),
+        Err(error) => eprintln!("[client] drain before close failed: {error}"),
+    }
+}
+
+/// Accepts any server certificate, or with a pin only the one it names.
+/// Handshake signatures are always checked against the presented certificate.
 #[derive(Debug)]
-struct SkipServerVerification(Arc<rustls::crypto::CryptoProvider>);
+struct ServerVerification {
+    provider: Arc<CryptoProvider>,
+    pin: Option<CertPin>,
+    /// The certificate last refused for not matching `pin`.
+    mismatched: Mutex<Option<CertPin>>,
+}
+
+impl ServerVerification {
+    fn new(provider: Arc<CryptoProvider>, pin: Option<CertPin>) -> Arc<Self> {
+        Arc::new(Self {
+            provider,
+            pin,
+            mismatched: Mutex::new(None),
+        })
+    }
 
-impl SkipServerVerification {
-    fn new() -> Arc<Self> {
-        Arc::new(Self(Arc::new(rustls::crypto::ring::default_provider())))
+    fn mismatched(&self) -> Option<CertPin> {
+        *self.mismatched.lock().expect("certificate pin mutex poisoned")
     }
 }
 
-impl rustls::client::danger::ServerCertVerifier for SkipServerVerification {
+/// SHA-256 through whichever backend `provider` uses, so pinning needs no
+/// hashing crate of its own.
+fn sha256(provider: &CryptoProvider, data: &[u8]) -> Option<[u8; 32]> {
+    let hash = provider
+        .cipher_suites
+        .iter()
+        .filter_map(|suite| suite.tls13())
+        .map(|suite| suite.common.hash_provider)
+        .find(|hash| hash.algorithm() == HashAlgorithm::SHA256)?;
+    hash.hash(data).as_ref().try_into().ok()
+}
+
+impl rustls::client::danger::ServerCertVerifier for ServerVerification {
     fn verify_server_cert(
         &self,
-        _end_entity: &CertificateDer<'_>,
+        end_entity: &CertificateDer<'_>,
         _intermediates: &[CertificateDer<'_>],
         _server_name: &ServerName<'_>,
         _ocsp: &[u8],
         _now: UnixTime,
     ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        if let Some(pin) = self.pin {
+            let presented = sha256(&self.provider, end_entity.as_ref())
+                .ok_or_else(|| rustls::Error::General("no SHA-256 to check the certificate pin".into()))?;
+            if presented != pin.0 {
+                eprintln!("[client] server certificate {} does not match the pin", CertPin(presented));
+                *self.mismatched.lock().expect("certificate pin mutex poisoned") = Some(CertPin(presented));
+                return Err(rustls::Error::InvalidCertificate(
+                    rustls::CertificateError::ApplicationVerificationFailure,
+                ));
+            }
+        }
         Ok(rustls::client::danger::ServerCertVerified::assertion())
     }
 
@@ -145,7 +995,7 @@ impl rustls::client::danger::ServerCertVerifier for SkipServerVerification {
             message,
             cert,
             dss,
-            &self.0.signature_verification_algorithms,
+            &self.provider.signature_verification_algorithms,
         )
     }
 
@@ -159,11 +1009,11 @@ impl rustls::client::danger::ServerCertVerifier for SkipServerVerification {
             message,
             cert,
             dss,
-            &self.0.signature_verification_algorithms,
+            &self.provider.signature_verification_algorithms,
         )
     }
 
     fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
-        self.0.signature_verification_algorithms.supported_schemes()
+        self.provider.signature_verification_algorithms.supported_schemes()
     }
 }
\ No newline at end of file
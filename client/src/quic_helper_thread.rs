@@ -1,28 +1,208 @@
+use std::pin::pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::Sender;
 use std::thread;
+use std::time::{Duration, Instant};
 
+use futures::future::{self, Either};
 use quinn::{Connection, SendStream};
+use shared::{
+    KEEP_HELD_INTERVAL, KeepHeld, MouseMove, Seq, SeqCategory,
+    codec::{Codec, WireFormat},
+};
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::time::{self as tokio_time, Instant as TokioInstant};
 
-use crate::quic::{open_uni, quic_runtime, send_data as send_quic_bytes};
+use crate::netsim::{NetSim, SimulatedLink};
+use crate::quic::{quic_runtime, send_data as send_quic_bytes, InputLink, StreamWriter};
 
+#[derive(Clone)]
 pub enum QuicCommand {
+    /// Relative pointer motion; moves inside the current coalescing window
+    /// are merged.
+    Move(MouseMove),
     Mouse(Vec<u8>),
     Keyboard(Vec<u8>),
+    /// Whether capture holds a key or button down. While it does, the
+    /// worker sends a [`KeepHeld`] once nothing else has gone out for
+    /// [`KEEP_HELD_INTERVAL`].
+    Holding(bool),
     Shutdown,
 }
 
 pub type QuicSender = UnboundedSender<QuicCommand>;
 
-pub fn spawn_quic_helper(connection: Connection) -> QuicSender {
+/// How the worker spreads input over uni streams.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StreamLayout {
+    /// Mouse and keyboard each get their own stream, so a stall on one never
+    /// holds up the other, but their relative order is lost.
+    #[default]
+    Split,
+    /// Everything shares one stream and arrives in the order it was sent,
+    /// e.g. a Shift press always before the click it modifies. A lost packet
+    /// now holds up both kinds of input.
+    Single,
+}
+
+/// Running totals of what the worker has put on the wire.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SendStats {
+    pub bytes: u64,
+    pub events: u64,
+}
+
+// How often the worker publishes a stats snapshot while traffic is flowing.
+const STATS_INTERVAL: Duration = Duration::from_millis(250);
+
+// How often the cadence is re-derived from the connection stats.
+const LINK_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+// RTT at which the link moves up into a regime, and the lower RTT it must
+// drop under to move back down, so a link hovering at a boundary can't flap.
+const MEDIUM_RTT: (Duration, Duration) = (Duration::from_millis(30), Duration::from_millis(20));
+const HIGH_RTT: (Duration, Duration) = (Duration::from_millis(100), Duration::from_millis(80));
+// Upper bound on how long a move may be held back, roughly one 60 Hz frame.
+pub const MAX_COALESCE_WINDOW: Duration = Duration::from_millis(16);
+// Upper bound on how far the pointer may drift before it is recentered.
+pub const MAX_RECENTER_MARGIN: f64 = 24.0;
+
+// Margin for the running key monitor, published by whichever worker sampled
+// the link last. Whole pixels are plenty.
+static RECENTER_MARGIN: AtomicU32 = AtomicU32::new(0);
+
+/// How far the captured pointer may drift from the recenter point before
+/// the key monitor warps it back.
+pub fn recenter_margin() -> f64 {
+    f64::from(RECENTER_MARGIN.load(Ordering::Relaxed))
+}
+
+/// Next [`Seq`] number per category for one worker, i.e. one connection.
+#[derive(Default)]
+struct SeqCounters {
+    mouse: u64,
+    keyboard: u64,
+    format: WireFormat,
+}
+
+impl SeqCounters {
+    /// `buf` behind the next sequence header for `category`. A write that
+    /// is later dropped still uses up its number, so the server sees the gap.
+    fn frame(&mut self, category: SeqCategory, buf: &[u8]) -> Vec<u8> {
+        let counter = match category {
+            SeqCategory::Mouse => &mut self.mouse,
+            SeqCategory::Keyboard => &mut self.keyboard,
+        };
+        let header = Seq {
+            category,
+            seq: *counter,
+        };
+        *counter += 1;
+        let mut framed = self.format.encode(&header).expect("failed to serialise");
+        framed.extend_from_slice(buf);
+        framed
+    }
+}
+
+/// Rough latency band of the link, which sets how much input is batched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LinkRegime {
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+/// Send and recenter cadence for a link.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cadence {
+    /// How long moves may be held back for merging.
+    pub coalesce_window: Duration,
+    /// Pixels the local pointer may wander before it is recentered, so a
+    /// slow link isn't fed a warp after every event.
+    pub recenter_margin: f64,
+}
+
+impl LinkRegime {
+    /// Regime for a new RTT sample, given the one the link was in.
+    pub fn next(self, rtt: Duration) -> Self {
+        let (medium_enter, medium_leave) = MEDIUM_RTT;
+        let (high_enter, high_leave) = HIGH_RTT;
+        match self {
+            LinkRegime::Low if rtt >= high_enter => LinkRegime::High,
+            LinkRegime::Low if rtt >= medium_enter => LinkRegime::Medium,
+            LinkRegime::Low => LinkRegime::Low,
+            LinkRegime::Medium if rtt >= high_enter => LinkRegime::High,
+            LinkRegime::Medium if rtt < medium_leave => LinkRegime::Low,
+            LinkRegime::Medium => LinkRegime::Medium,
+            LinkRegime::High if rtt >= high_leave => LinkRegime::High,
+            LinkRegime::High if rtt >= medium_leave => LinkRegime::Medium,
+            LinkRegime::High => LinkRegime::Low,
+        }
+    }
+
+    /// Cadence for this regime, with a fixed coalescing penalty once loss
+    /// passes 1%. Both values stay within [`MAX_COALESCE_WINDOW`] and
+    /// [`MAX_RECENTER_MARGIN`].
+    pub fn cadence(self, loss_rate: f64) -> Cadence {
+        let (window, margin) = match self {
+            LinkRegime::Low => (Duration::ZERO, 0.0),
+            LinkRegime::Medium => (Duration::from_millis(4), 8.0),
+            LinkRegime::High => (Duration::from_millis(12), MAX_RECENTER_MARGIN),
+        };
+        let from_loss = if loss_rate > 0.01 {
+            MAX_COALESCE_WINDOW / 2
+        } else {
+            Duration::ZERO
+        };
+        Cadence {
+            coalesce_window: (window + from_loss).min(MAX_COALESCE_WINDOW),
+            recenter_margin: margin,
+        }
+    }
+}
+
+pub fn spawn_quic_helper(
+    input: InputLink,
+    stats_tx: Sender<SendStats>,
+    layout: StreamLayout,
+) -> QuicSender {
+    spawn_quic_helper_with_sim(input, stats_tx, layout, NetSim::from_env())
+}
+
+/// Like [`spawn_quic_helper`], with simulated network conditions on every
+/// write. `sim` is ignored in release builds.
+pub fn spawn_quic_helper_with_sim(
+    input: InputLink,
+    stats_tx: Sender<SendStats>,
+    layout: StreamLayout,
+    sim: Option<NetSim>,
+) -> QuicSender {
+    #[cfg(not(debug_assertions))]
+    let sim: Option<NetSim> = {
+        let _ = sim;
+        None
+    };
     let (tx, rx) = mpsc::unbounded_channel();
+    // Registered before the thread starts so a close issued right after
+    // spawning still waits for this worker's streams.
+    let writer = StreamWriter::register(&input);
     // Run QUIC networking on a dedicated worker thread to avoid blocking the input grab callback.
-    let _ = thread::spawn(move || run_quic_worker(connection, rx));
+    let _ = thread::spawn(move || {
+        run_quic_worker(input, rx, stats_tx, layout, sim);
+        drop(writer);
+    });
     tx
 }
 
-fn run_quic_worker(connection: Connection, mut rx: UnboundedReceiver<QuicCommand>) {
+fn run_quic_worker(
+    input: InputLink,
+    mut rx: UnboundedReceiver<QuicCommand>,
+    stats_tx: Sender<SendStats>,
+    layout: StreamLayout,
+    sim: Option<NetSim>,
+) {
     quic_runtime().block_on(async move {
-        let mut mouse_stream = match open_uni(connection.clone()).await {
+        let mut mouse_stream = match input.open_uni().await {
             Ok(stream) => Some(stream),
             Err(error) => {
                 eprintln!("failed to open mouse send stream: {error:?}");
@@ -30,45 +210,271 @@ fn run_quic_worker(connection: Connection, mut rx: UnboundedReceiver<QuicCommand
             }
         };
 
-        let mut keyboard_stream = match open_uni(connection).await {
-            Ok(stream) => Some(stream),
-            Err(error) => {
-                eprintln!("failed to open keyboard send stream: {error:?}");
-                return;
-            }
+        // With a single stream, keyboard input goes out on the mouse one;
+        // every value on the wire is self-describing, so the server tells
+        // them apart without a separate tag.
+        let mut keyboard_stream = match layout {
+            StreamLayout::Single => None,
+            StreamLayout::Split => match input.open_uni().await {
+                Ok(stream) => Some(stream),
+                Err(error) => {
+                    eprintln!("failed to open keyboard send stream: {error:?}");
+                    return;
+                }
+            },
         };
 
-        while let Some(command) = rx.recv().await {
-            match command {
-                QuicCommand::Mouse(buf) => {
-                    if let Some(stream) = mouse_stream.as_mut() {
-                        if let Err(error) = send_quic_bytes(stream, &buf).await {
-                            eprintln!("failed to send mouse data: {error:?}");
-                            mouse_stream = None;
-                        }
+        let mut stats = SendStats::default();
+        let mut last_report = Instant::now();
+        let connection = &input.connection;
+        let mut link = LinkQuality::new(connection);
+        let mut sim = sim.map(SimulatedLink::new);
+        let mut seqs = SeqCounters {
+            format: input.format,
+            ..SeqCounters::default()
+        };
+        // Motion merged so far, and when it must go out at the latest.
+        let mut pending_move: Option<MouseMove> = None;
+        let mut flush_at: Option<TokioInstant> = None;
+        // When a [`KeepHeld`] is due, while capture holds something down.
+        let mut keep_held_at: Option<TokioInstant> = None;
+        // Once the connection is gone the worker stops taking commands, so
+        // senders see it has gone and hold on to input until a reconnect
+        // instead of handing it to a worker that can only drop it.
+        let mut lost = pin!(connection.closed());
+
+        loop {
+            let next = {
+                let deadline = match (flush_at, keep_held_at) {
+                    (Some(flush), Some(keep_held)) => Some(flush.min(keep_held)),
+                    (flush, keep_held) => flush.or(keep_held),
+                };
+                let received = pin!(async {
+                    match deadline {
+                        // A zero window still merges whatever is already
+                        // queued: the receive is polled before the deadline
+                        // is checked.
+                        Some(deadline) => tokio_time::timeout_at(deadline, rx.recv()).await.ok(),
+                        None => Some(rx.recv().await),
                     }
+                });
+                match future::select(lost.as_mut(), received).await {
+                    Either::Left((reason, _)) => Err(reason),
+                    Either::Right((next, _)) => Ok(next),
                 }
-                QuicCommand::Keyboard(buf) => {
-                    if let Some(stream) = keyboard_stream.as_mut() {
-                        if let Err(error) = send_quic_bytes(stream, &buf).await {
-                            eprintln!("failed to send keyboard data: {error:?}");
-                            keyboard_stream = None;
-                        }
+            };
+            let next = match next {
+                Ok(next) => next,
+                Err(reason) => {
+                    eprintln!("connection lost, send worker stopping: {reason}");
+                    rx.close();
+                    break;
+                }
+            };
+            let command = match next {
+                Some(Some(command)) => command,
+                Some(None) => break,
+                None => {
+                    let now = TokioInstant::now();
+                    if keep_held_at.is_some_and(|due| due <= now) {
+                        keep_held_at = Some(now + KEEP_HELD_INTERVAL);
+                        let stream = match layout {
+                            StreamLayout::Split => &mut keyboard_stream,
+                            StreamLayout::Single => &mut mouse_stream,
+                        };
+                        let buf = seqs.format.encode(&KeepHeld).expect("failed to serialise");
+                        send_on(stream, &buf, "keyboard", &mut sim).await;
+                    }
+                    if flush_at.is_some_and(|due| due <= now) {
+                        flush_at = None;
+                        let sent = flush_move(&mut mouse_stream, pending_move.take(), &mut seqs, &mut sim).await;
+                        record_sent(&mut stats, sent, &mut last_report, &stats_tx);
                     }
+                    continue;
+                }
+            };
+
+            // Anything sent shows the server the client is still there.
+            if keep_held_at.is_some() {
+                keep_held_at = Some(TokioInstant::now() + KEEP_HELD_INTERVAL);
+            }
+            if let QuicCommand::Holding(holding) = command {
+                keep_held_at = holding.then(|| TokioInstant::now() + KEEP_HELD_INTERVAL);
+                continue;
+            }
+
+            if let QuicCommand::Move(mouse_move) = command {
+                pending_move = Some(match pending_move.take() {
+                    Some(pending) => MouseMove {
+                        dx: pending.dx + mouse_move.dx,
+                        dy: pending.dy + mouse_move.dy,
+                    },
+                    None => mouse_move,
+                });
+                if flush_at.is_none() {
+                    flush_at = Some(TokioInstant::now() + link.coalesce_window(connection));
+                }
+                continue;
+            }
+
+            // Never merge motion across a button or key: a drag needs its
+            // moves to land between the press and the release.
+            flush_at = None;
+            let sent = flush_move(&mut mouse_stream, pending_move.take(), &mut seqs, &mut sim).await;
+            record_sent(&mut stats, sent, &mut last_report, &stats_tx);
+
+            let sent = match command {
+                QuicCommand::Move(_) => unreachable!("moves are coalesced above"),
+                QuicCommand::Holding(_) => unreachable!("holding is noted above"),
+                QuicCommand::Mouse(buf) => {
+                    let buf = seqs.frame(SeqCategory::Mouse, &buf);
+                    send_on(&mut mouse_stream, &buf, "mouse", &mut sim).await
+                }
+                QuicCommand::Keyboard(buf) => {
+                    let stream = match layout {
+                        StreamLayout::Split => &mut keyboard_stream,
+                        StreamLayout::Single => &mut mouse_stream,
+                    };
+                    let buf = seqs.frame(SeqCategory::Keyboard, &buf);
+                    send_on(stream, &buf, "keyboard", &mut sim).await
                 }
                 QuicCommand::Shutdown => {
                     finish_stream(mouse_stream.take());
                     finish_stream(keyboard_stream.take());
                     break;
                 }
-            }
+            };
+            record_sent(&mut stats, sent, &mut last_report, &stats_tx);
         }
 
+        let sent = flush_move(&mut mouse_stream, pending_move.take(), &mut seqs, &mut sim).await;
+        record_sent(&mut stats, sent, &mut last_report, &stats_tx);
+        let _ = stats_tx.send(stats);
         finish_stream(mouse_stream.take());
         finish_stream(keyboard_stream.take());
     });
 }
 
+/// Tracks RTT and loss on the connection to decide how long mouse moves may
+/// be held back for merging and how far the pointer may drift before it is
+/// recentered: nothing on a good link, more as it degrades.
+struct LinkQuality {
+    regime: LinkRegime,
+    window: Duration,
+    sampled_at: Instant,
+    sent_packets: u64,
+    lost_packets: u64,
+}
+
+impl LinkQuality {
+    fn new(connection: &Connection) -> Self {
+        let path = connection.stats().path;
+        RECENTER_MARGIN.store(0, Ordering::Relaxed);
+        Self {
+            regime: LinkRegime::Low,
+            window: Duration::ZERO,
+            sampled_at: Instant::now(),
+            sent_packets: path.sent_packets,
+            lost_packets: path.lost_packets,
+        }
+    }
+
+    fn coalesce_window(&mut self, connection: &Connection) -> Duration {
+        if self.sampled_at.elapsed() < LINK_SAMPLE_INTERVAL {
+            return self.window;
+        }
+
+        let path = connection.stats().path;
+        let sent = path.sent_packets.saturating_sub(self.sent_packets);
+        let lost = path.lost_packets.saturating_sub(self.lost_packets);
+        let loss_rate = if sent == 0 {
+            0.0
+        } else {
+            lost as f64 / sent as f64
+        };
+
+        let regime = self.regime.next(connection.rtt());
+        let cadence = regime.cadence(loss_rate);
+        if cadence.coalesce_window != self.window || regime != self.regime {
+            println!(
+                "[client] {regime:?} latency link: coalescing {} ms, recenter margin {} px (rtt {} ms, loss {:.1}%)",
+                cadence.coalesce_window.as_millis(),
+                cadence.recenter_margin,
+                connection.rtt().as_millis(),
+                loss_rate * 100.0
+            );
+        }
+
+        RECENTER_MARGIN.store(cadence.recenter_margin as u32, Ordering::Relaxed);
+        self.regime = regime;
+        self.window = cadence.coalesce_window;
+        self.sampled_at = Instant::now();
+        self.sent_packets = path.sent_packets;
+        self.lost_packets = path.lost_packets;
+        self.window
+    }
+}
+
+/// Writes `buf` to the stream, dropping the stream on failure. Returns the
+/// number of bytes sent.
+async fn send_on(
+    stream: &mut Option<SendStream>,
+    buf: &[u8],
+    kind: &str,
+    sim: &mut Option<SimulatedLink>,
+) -> usize {
+    let Some(active) = stream.as_mut() else {
+        return 0;
+    };
+    // A simulated drop skips the write entirely, as if the packet was lost.
+    let dropped = match sim.as_mut() {
+        Some(sim) => !sim.before_send().await,
+        None => false,
+    };
+    if dropped {
+        return 0;
+    }
+    match send_quic_bytes(active, buf).await {
+        Ok(()) => buf.len(),
+        Err(error) => {
+            eprintln!("failed to send {kind} data: {error:?}");
+            *stream = None;
+            0
+        }
+    }
+}
+
+async fn flush_move(
+    stream: &mut Option<SendStream>,
+    pending: Option<MouseMove>,
+    seqs: &mut SeqCounters,
+    sim: &mut Option<SimulatedLink>,
+) -> usize {
+    let Some(mouse_move) = pending else {
+        return 0;
+    };
+    let buf = seqs.format.encode(&mouse_move).expect("failed to serialise");
+    let buf = seqs.frame(SeqCategory::Mouse, &buf);
+    send_on(stream, &buf, "mouse", sim).await
+}
+
+fn record_sent(
+    stats: &mut SendStats,
+    sent: usize,
+    last_report: &mut Instant,
+    stats_tx: &Sender<SendStats>,
+) {
+    if sent == 0 {
+        return;
+    }
+    stats.bytes += sent as u64;
+    stats.events += 1;
+    if last_report.elapsed() >= STATS_INTERVAL {
+        let _ = stats_tx.send(*stats);
+        *last_report = Instant::now();
+    }
+}
+
 fn finish_stream(stream: Option<SendStream>) {
     if let Some(mut stream) = stream {
         let _ = stream.finish();
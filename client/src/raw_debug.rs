@@ -0,0 +1,35 @@
+//! Sending arbitrary bytes to the server, for trying out a message format
+//! before capture knows how to produce it. Only offered when the client is
+//! started with `--debug-raw`; a server started with the same flag logs
+//! what arrives and how it decodes.
+
+use std::error::Error;
+
+
+use crate::quic::{send_data, InputLink};
+
+pub const DEBUG_RAW_FLAG: &str = "--debug-raw";
+
+/// Takes `--debug-raw` out of the command line, so what remains can be
+/// handed to GTK, which rejects options it doesn't know. Returns whether
+/// it was there.
+pub fn take_debug_raw_flag(args: Vec<String>) -> (bool, Vec<String>) {
+    let before = args.len();
+    let rest: Vec<String> = args
+        .into_iter()
+        .filter(|arg| arg != DEBUG_RAW_FLAG)
+        .collect();
+    (rest.len() != before, rest)
+}
+
+/// Sends `bytes` as-is on a uni stream of their own, so nothing capture
+/// sends is split by them.
+pub async fn send_raw(
+    input: InputLink,
+    bytes: &[u8],
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let mut send = input.open_uni().await?;
+    send_data(&mut send, bytes).await?;
+    send.finish()?;
+    Ok(())
+}
@@ -0,0 +1,245 @@
+//! Recording captured input to a file and replaying it later, so a bug
+//! report can come with the exact input that triggered it, e.g. a key that
+//! sticks, and a maintainer can send it to a server again at the same pace:
+//!
+//! * `QUICINPUT_RECORD` – file to record everything capture sends to
+//! * `QUICINPUT_REPLAY` – file to send instead of starting live capture
+//!
+//! A recording is a run of msgpack [`RecordedCommand`]s, each the bytes one
+//! [`QuicCommand`] carried and when, relative to the first. Latency stamps
+//! are left out: replayed, they would carry the recording's send times.
+//!
+//! With `QUICINPUT_ENCRYPT_CONFIG` set (see [`crate::sealed`]) a recording
+//! is sealed too, as it holds whatever was typed. It then starts with
+//! [`MAGIC`] and goes on in frames, each a big-endian `u32` length and that
+//! many bytes. The first frame is a key made for the recording, sealed with
+//! the config's key or passphrase; every other frame is one command sealed
+//! with that key. A passphrase is then only stretched once per file, not
+//! once per command.
+
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use shared::{
+    MouseMove, SentAt,
+    codec::{Codec, WireFormat},
+};
+use tokio::time::{Instant as TokioInstant, sleep_until};
+
+use crate::quic_helper_thread::{QuicCommand, QuicSender};
+use crate::sealed::{MAGIC, Sealer, config_sealer};
+
+pub const RECORD_ENV: &str = "QUICINPUT_RECORD";
+pub const REPLAY_ENV: &str = "QUICINPUT_REPLAY";
+
+/// One [`QuicCommand`] as recorded; shutdowns and holding aren't.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum RecordedInput {
+    Move(MouseMove),
+    Mouse(Vec<u8>),
+    Keyboard(Vec<u8>),
+}
+
+impl RecordedInput {
+    /// `command` as recorded, its bytes read as `format` to pick out
+    /// latency stamps.
+    pub fn from_command(command: &QuicCommand, format: WireFormat) -> Option<Self> {
+        match command {
+            QuicCommand::Move(mouse_move) => Some(RecordedInput::Move(*mouse_move)),
+            QuicCommand::Mouse(buf) if !is_stamp(buf, format) => {
+                Some(RecordedInput::Mouse(buf.clone()))
+            }
+            QuicCommand::Keyboard(buf) if !is_stamp(buf, format) => {
+                Some(RecordedInput::Keyboard(buf.clone()))
+            }
+            QuicCommand::Mouse(_)
+            | QuicCommand::Keyboard(_)
+            | QuicCommand::Holding(_)
+            | QuicCommand::Shutdown => None,
+        }
+    }
+
+    pub fn into_command(self) -> QuicCommand {
+        match self {
+            RecordedInput::Move(mouse_move) => QuicCommand::Move(mouse_move),
+            RecordedInput::Mouse(buf) => QuicCommand::Mouse(buf),
+            RecordedInput::Keyboard(buf) => QuicCommand::Keyboard(buf),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct RecordedCommand {
+    /// Microseconds since the first command of the recording.
+    pub at_micros: u64,
+    pub input: RecordedInput,
+}
+
+/// A [`SentAt`] is the only message that is a one-element array of an
+/// integer in MessagePack, or an object holding only `micros` in JSON, so
+/// nothing else is mistaken for one.
+fn is_stamp(buf: &[u8], format: WireFormat) -> bool {
+    matches!(format.decode_prefix::<SentAt>(buf), Ok((_, used)) if used == buf.len())
+}
+
+/// Appends commands to a recording as they are sent.
+pub struct Recorder<W: Write = BufWriter<File>> {
+    writer: W,
+    format: WireFormat,
+    started: Option<Instant>,
+    // Seals each command of a sealed recording.
+    frame_sealer: Option<Sealer>,
+}
+
+impl Recorder {
+    /// Records to `path`, replacing any file already there, sealed as the
+    /// config is.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::sealed(BufWriter::new(File::create(path)?), config_sealer())
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            format: WireFormat::default(),
+            started: None,
+            frame_sealer: None,
+        }
+    }
+
+    /// A recorder sealing what it writes with a key of its own, kept at the
+    /// start of the recording sealed by `sealer`. The same as
+    /// [`Recorder::new`] for [`Sealer::Plain`].
+    pub fn sealed(mut writer: W, sealer: &Sealer) -> io::Result<Self> {
+        if sealer.is_plain() {
+            return Ok(Self::new(writer));
+        }
+        let key: [u8; 32] = rand::random();
+        writer.write_all(MAGIC)?;
+        write_frame(&mut writer, &sealer.seal(&key)?)?;
+        writer.flush()?;
+        Ok(Self {
+            frame_sealer: Some(Sealer::Key(key)),
+            ..Self::new(writer)
+        })
+    }
+
+    /// The format the commands it is given were encoded in; MessagePack
+    /// unless set.
+    pub fn with_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Writes `command` out, timed from the first one recorded. Flushed
+    /// straight away, as capture may end without the recorder being dropped.
+    pub fn record(&mut self, command: &QuicCommand) -> io::Result<()> {
+        let Some(input) = RecordedInput::from_command(command, self.format) else {
+            return Ok(());
+        };
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let entry = RecordedCommand {
+            at_micros: u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX),
+            input,
+        };
+        let encoded = rmp_serde::to_vec(&entry).map_err(io::Error::other)?;
+        match &self.frame_sealer {
+            Some(sealer) => write_frame(&mut self.writer, &sealer.seal(&encoded)?)?,
+            None => self.writer.write_all(&encoded)?,
+        }
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Parses a whole recording.
+pub fn read_recording(mut data: &[u8]) -> Result<Vec<RecordedCommand>, rmp_serde::decode::Error> {
+    let mut entries = Vec::new();
+    while !data.is_empty() {
+        entries.push(rmp_serde::from_read(&mut data)?);
+    }
+    Ok(entries)
+}
+
+/// Parses a whole recording, opening it with `sealer` if it is sealed.
+pub fn open_recording(data: &[u8], sealer: &Sealer) -> io::Result<Vec<RecordedCommand>> {
+    let invalid = |err: rmp_serde::decode::Error| io::Error::new(io::ErrorKind::InvalidData, err);
+    let Some(mut rest) = data.strip_prefix(MAGIC) else {
+        return read_recording(data).map_err(invalid);
+    };
+    let key = sealer.open(read_frame(&mut rest)?)?;
+    let key: [u8; 32] = key
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "recording key is damaged"))?;
+    let frame_sealer = Sealer::Key(key);
+    let mut entries = Vec::new();
+    while !rest.is_empty() {
+        let entry = frame_sealer.open(read_frame(&mut rest)?)?;
+        entries.push(rmp_serde::from_slice(&entry).map_err(invalid)?);
+    }
+    Ok(entries)
+}
+
+pub fn load_recording(path: &Path) -> io::Result<Vec<RecordedCommand>> {
+    open_recording(&fs::read(path)?, config_sealer())
+}
+
+fn write_frame(writer: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(frame.len()).map_err(io::Error::other)?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(frame)
+}
+
+/// The next frame of a sealed recording, taken off the front of `data`.
+fn read_frame<'a>(data: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+    let cut_short = || io::Error::new(io::ErrorKind::UnexpectedEof, "recording is cut short");
+    let (len, rest) = data.split_first_chunk::<4>().ok_or_else(cut_short)?;
+    let len = u32::from_be_bytes(*len) as usize;
+    if rest.len() < len {
+        return Err(cut_short());
+    }
+    let (frame, rest) = rest.split_at(len);
+    *data = rest;
+    Ok(frame)
+}
+
+/// Hands each recorded command to the worker behind `sender` as far after
+/// the start as it was recorded. Returns how many were sent, which falls
+/// short if the worker goes away.
+pub async fn replay(recording: Vec<RecordedCommand>, sender: &QuicSender) -> usize {
+    let started = TokioInstant::now();
+    let mut sent = 0;
+    for entry in recording {
+        sleep_until(started + Duration::from_micros(entry.at_micros)).await;
+        if sender.send(entry.input.into_command()).is_err() {
+            break;
+        }
+        sent += 1;
+    }
+    sent
+}
+
+/// Where to record capture to, if `QUICINPUT_RECORD` is set.
+pub fn record_path() -> Option<PathBuf> {
+    env::var_os(RECORD_ENV)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Recording to replay instead of capturing, if `QUICINPUT_REPLAY` is set.
+pub fn replay_path() -> Option<PathBuf> {
+    env::var_os(REPLAY_ENV)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
@@ -0,0 +1,61 @@
+use std::error::Error;
+
+use rdev::{Button, EventType, Key};
+use shared::codec::Codec;
+use shared::extra_keys::ExtraKeyInput;
+use shared::raw_keys::RawKeyInput;
+
+use crate::quic::{send_data, InputLink};
+
+/// Modifiers released by every sweep, whether or not they were seen pressed.
+pub const SWEEP_MODIFIERS: [Key; 9] = [
+    Key::ShiftLeft,
+    Key::ShiftRight,
+    Key::ControlLeft,
+    Key::ControlRight,
+    Key::Alt,
+    Key::AltGr,
+    Key::MetaLeft,
+    Key::MetaRight,
+    Key::Function,
+];
+
+/// Mouse buttons released by every sweep.
+pub const SWEEP_BUTTONS: [Button; 3] = [Button::Left, Button::Right, Button::Middle];
+
+/// Releases for everything that could be stuck down on the server: `held`
+/// (keys this client knows it pressed), then every modifier and button.
+/// Each key or button is released once.
+pub fn release_sweep(held: &[Key]) -> Vec<EventType> {
+    let mut keys: Vec<Key> = Vec::with_capacity(held.len() + SWEEP_MODIFIERS.len());
+    for key in held.iter().chain(SWEEP_MODIFIERS.iter()) {
+        if !keys.contains(key) {
+            keys.push(*key);
+        }
+    }
+
+    let keys = keys.into_iter().map(EventType::KeyRelease);
+    let buttons = SWEEP_BUTTONS.into_iter().map(EventType::ButtonRelease);
+    keys.chain(buttons).collect()
+}
+
+/// Sends `events` on a fresh uni stream of their own, so the sweep goes out
+/// whether or not capture is running. Extra and raw keys go as capture
+/// sends them.
+pub async fn send_release_sweep(
+    input: InputLink,
+    events: &[EventType],
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let format = input.format;
+    let mut send = input.open_uni().await?;
+    for event in events {
+        let buf = match (ExtraKeyInput::from_event(event), RawKeyInput::from_event(event)) {
+            (Some(extra), _) => format.encode(&extra)?,
+            (None, Some(raw)) => format.encode(&raw)?,
+            (None, None) => format.encode(event)?,
+        };
+        send_data(&mut send, &buf).await?;
+    }
+    send.finish()?;
+    Ok(())
+}
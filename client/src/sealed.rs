@@ -0,0 +1,236 @@
+//! Encrypting the files the client keeps. Settings say which servers and
+//! keys a user works with, and macros hold whatever was typed while they
+//! were recorded, passwords included, so some users don't want either
+//! readable by anything that can read their home directory. Off unless
+//! `QUICINPUT_ENCRYPT_CONFIG` asks for it:
+//!
+//! - `keyring`: a random key kept in the OS keyring, which needs the
+//!   `keyring` feature. Without a keyring files are saved in plaintext, with
+//!   a warning.
+//! - `passphrase`: a key derived with Argon2 from
+//!   `QUICINPUT_CONFIG_PASSPHRASE`, salted per file.
+//!
+//! A sealed file is [`MAGIC`], a byte saying which kind of key sealed it,
+//! the salt for a passphrase, a nonce and the ChaCha20-Poly1305 ciphertext.
+//! Files without [`MAGIC`] are read as they are, so turning encryption on
+//! keeps reading what was saved before and the next save seals it. A sealed
+//! file that can't be opened is never saved over.
+
+use std::error::Error;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::{env, fmt, fs, io};
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+/// Turns encryption on: `keyring` or `passphrase`.
+pub const ENCRYPT_ENV: &str = "QUICINPUT_ENCRYPT_CONFIG";
+
+/// The passphrase for `QUICINPUT_ENCRYPT_CONFIG=passphrase`.
+pub const PASSPHRASE_ENV: &str = "QUICINPUT_CONFIG_PASSPHRASE";
+
+/// What every sealed file starts with.
+pub const MAGIC: &[u8; 8] = b"QISEALED";
+
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "quicinput";
+#[cfg(feature = "keyring")]
+const KEYRING_USER: &str = "config-key";
+
+const KIND_KEY: u8 = 1;
+const KIND_PASSPHRASE: u8 = 2;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// How files are sealed on save and opened on load.
+#[derive(Clone, Default)]
+pub enum Sealer {
+    /// Saved as they are.
+    #[default]
+    Plain,
+    /// Sealed with a key, such as the one kept in the OS keyring.
+    Key([u8; KEY_LEN]),
+    /// Sealed with a key derived from a passphrase.
+    Passphrase(String),
+}
+
+impl fmt::Debug for Sealer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never the key or passphrase itself.
+        match self {
+            Sealer::Plain => f.write_str("Sealer::Plain"),
+            Sealer::Key(_) => f.write_str("Sealer::Key(..)"),
+            Sealer::Passphrase(_) => f.write_str("Sealer::Passphrase(..)"),
+        }
+    }
+}
+
+impl Sealer {
+    /// The sealer `QUICINPUT_ENCRYPT_CONFIG` asks for, falling back to
+    /// [`Sealer::Plain`] with a warning when it can't be had.
+    pub fn from_env() -> Self {
+        let Ok(value) = env::var(ENCRYPT_ENV) else {
+            return Sealer::Plain;
+        };
+        match value.trim() {
+            "" | "off" => Sealer::Plain,
+            "keyring" => Self::keyring_or_plain(keyring_key()),
+            "passphrase" => match env::var(PASSPHRASE_ENV) {
+                Ok(passphrase) if !passphrase.is_empty() => Sealer::Passphrase(passphrase),
+                _ => {
+                    eprintln!(
+                        "[client] {ENCRYPT_ENV}=passphrase but {PASSPHRASE_ENV} is empty; \
+                         saving config in plaintext"
+                    );
+                    Sealer::Plain
+                }
+            },
+            other => {
+                eprintln!(
+                    "[client] ignoring {ENCRYPT_ENV}={other}: expected keyring or passphrase"
+                );
+                Sealer::Plain
+            }
+        }
+    }
+
+    /// Seals with `key` from the keyring, or saves in plaintext with a
+    /// warning if there was none to be had.
+    pub fn keyring_or_plain(key: Result<[u8; KEY_LEN], Box<dyn Error + Send + Sync>>) -> Self {
+        match key {
+            Ok(key) => Sealer::Key(key),
+            Err(err) => {
+                eprintln!(
+                    "[client] no keyring to encrypt config with ({err}); saving it in plaintext"
+                );
+                Sealer::Plain
+            }
+        }
+    }
+
+    pub fn is_plain(&self) -> bool {
+        matches!(self, Sealer::Plain)
+    }
+
+    /// What to write for `plain`.
+    pub fn seal(&self, plain: &[u8]) -> io::Result<Vec<u8>> {
+        let (kind, salt, key) = match self {
+            Sealer::Plain => return Ok(plain.to_vec()),
+            Sealer::Key(key) => (KIND_KEY, Vec::new(), *key),
+            Sealer::Passphrase(passphrase) => {
+                let salt: [u8; SALT_LEN] = rand::random();
+                (
+                    KIND_PASSPHRASE,
+                    salt.to_vec(),
+                    derive_key(passphrase, &salt)?,
+                )
+            }
+        };
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(Nonce::from_slice(&nonce), plain)
+            .map_err(|_| io::Error::other("encrypting config failed"))?;
+
+        let mut sealed =
+            Vec::with_capacity(MAGIC.len() + 1 + salt.len() + NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.push(kind);
+        sealed.extend_from_slice(&salt);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Fails if `path` holds a sealed file this sealer can't open, e.g. one
+    /// sealed with another passphrase, so saving can't replace what it
+    /// holds with whatever was loaded in its place.
+    pub fn check_replaceable(&self, path: &Path) -> io::Result<()> {
+        match fs::read(path) {
+            Ok(data) if data.starts_with(MAGIC) => self.open(&data).map(drop).map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("not replacing {}: {err}", path.display()),
+                )
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The contents of `data` as read from a file, which is returned as it
+    /// is unless sealed.
+    pub fn open(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let Some(rest) = data.strip_prefix(MAGIC) else {
+            return Ok(data.to_vec());
+        };
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let (&kind, rest) = rest
+            .split_first()
+            .ok_or_else(|| invalid("sealed file is cut short"))?;
+        let (key, rest) = match (kind, self) {
+            (_, Sealer::Plain) => {
+                return Err(invalid(&format!(
+                    "file is encrypted; set {ENCRYPT_ENV} to read it"
+                )));
+            }
+            (KIND_KEY, Sealer::Key(key)) => (*key, rest),
+            (KIND_PASSPHRASE, Sealer::Passphrase(passphrase)) => {
+                if rest.len() < SALT_LEN {
+                    return Err(invalid("sealed file is cut short"));
+                }
+                let (salt, rest) = rest.split_at(SALT_LEN);
+                (derive_key(passphrase, salt)?, rest)
+            }
+            (KIND_KEY | KIND_PASSPHRASE, _) => {
+                return Err(invalid("file was encrypted with another kind of key"));
+            }
+            _ => return Err(invalid("sealed file is of an unknown kind")),
+        };
+        if rest.len() < NONCE_LEN {
+            return Err(invalid("sealed file is cut short"));
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        ChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| invalid("file can't be decrypted: wrong key, or it was damaged"))
+    }
+}
+
+/// The sealer for this run, read from the environment once.
+pub fn config_sealer() -> &'static Sealer {
+    static SEALER: OnceLock<Sealer> = OnceLock::new();
+    SEALER.get_or_init(Sealer::from_env)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> io::Result<[u8; KEY_LEN]> {
+    let mut key = [0u8; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| io::Error::other(format!("deriving config key failed: {err}")))?;
+    Ok(key)
+}
+
+/// The key kept in the OS keyring, made on first use.
+#[cfg(feature = "keyring")]
+fn keyring_key() -> Result<[u8; KEY_LEN], Box<dyn Error + Send + Sync>> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+    match entry.get_secret() {
+        Ok(secret) => secret
+            .try_into()
+            .map_err(|_| "keyring entry isn't a 32-byte key".into()),
+        Err(keyring::Error::NoEntry) => {
+            let key: [u8; KEY_LEN] = rand::random();
+            entry.set_secret(&key)?;
+            Ok(key)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_key() -> Result<[u8; KEY_LEN], Box<dyn Error + Send + Sync>> {
+    Err("built without the keyring feature".into())
+}
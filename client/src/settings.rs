@@ -0,0 +1,126 @@
+//! Choices the client remembers between runs, kept as TOML in the user's
+//! config directory.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use rdev::Key;
+use serde::{Deserialize, Serialize};
+use shared::{PointerMode, system_keys::DEFAULT_SYSTEM_KEYS};
+
+use crate::momentary::{CaptureMode, DEFAULT_HOLD_KEY};
+use crate::quic::RetryPolicy;
+use crate::sealed::{Sealer, config_sealer};
+
+/// Overrides where settings are kept, e.g. for a portable install.
+pub const SETTINGS_ENV: &str = "QUICINPUT_CLIENT_SETTINGS";
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ClientSettings {
+    /// Pointer mode last chosen in the input view.
+    pub pointer_mode: PointerMode,
+    /// Keys the OS may intercept, handled as in [`shared::system_keys`].
+    pub system_keys: Vec<Key>,
+    /// Capture until the stop chord, or only while [`Self::hold_key`] is
+    /// held; see [`crate::momentary`].
+    pub capture_mode: CaptureMode,
+    pub hold_key: Key,
+    /// Start capture without explaining it first; see
+    /// [`crate::capture_notice`].
+    pub skip_capture_notice: bool,
+    /// Leave the pointer visible and where it is moved while capturing,
+    /// rather than hiding it and recentering it after every move.
+    pub free_cursor: bool,
+    /// Connect attempts per server, including the first; see
+    /// [`crate::quic::RetryPolicy`].
+    pub connect_attempts: u32,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        Self {
+            pointer_mode: PointerMode::default(),
+            system_keys: DEFAULT_SYSTEM_KEYS.to_vec(),
+            capture_mode: CaptureMode::default(),
+            hold_key: DEFAULT_HOLD_KEY,
+            skip_capture_notice: false,
+            free_cursor: false,
+            connect_attempts: RetryPolicy::default().attempts,
+        }
+    }
+}
+
+impl ClientSettings {
+    /// The key capture waits for, if it only runs while one is held.
+    pub fn hold_trigger(&self) -> Option<Key> {
+        match self.capture_mode {
+            CaptureMode::Toggle => None,
+            CaptureMode::Hold => Some(self.hold_key),
+        }
+    }
+
+    /// Reads settings from `path`, falling back to the defaults when the
+    /// file is missing or unreadable. Encrypted as [`crate::sealed`] says.
+    pub fn load(path: &Path) -> Self {
+        Self::load_with(path, config_sealer())
+    }
+
+    pub fn load_with(path: &Path, sealer: &Sealer) -> Self {
+        let data = match fs::read(path).and_then(|data| sealer.open(&data)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                eprintln!("[client] failed to read {}: {err}", path.display());
+                return Self::default();
+            }
+        };
+        let data = String::from_utf8_lossy(&data);
+        toml::from_str(&data).unwrap_or_else(|err| {
+            eprintln!(
+                "[client] ignoring invalid settings in {}: {err}",
+                path.display()
+            );
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        self.save_with(path, config_sealer())
+    }
+
+    /// Fails rather than replace a sealed file `sealer` can't open, as this
+    /// was then loaded from the defaults instead.
+    pub fn save_with(&self, path: &Path, sealer: &Sealer) -> io::Result<()> {
+        sealer.check_replaceable(path)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let data = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, sealer.seal(data.as_bytes())?)
+    }
+}
+
+/// Where settings are kept: [`SETTINGS_ENV`] if set, else `quicinput/client.toml`
+/// in the platform's config directory. `None` if there is no such directory.
+pub fn settings_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(SETTINGS_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    config_dir().map(|dir| dir.join("quicinput").join("client.toml"))
+}
+
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        return env::var_os("APPDATA").map(PathBuf::from);
+    }
+    let home = env::var_os("HOME").map(PathBuf::from);
+    if cfg!(target_os = "macos") {
+        return home.map(|home| home.join("Library").join("Application Support"));
+    }
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.map(|home| home.join(".config")))
+}
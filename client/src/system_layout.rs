@@ -0,0 +1,49 @@
+use std::cell::RefCell;
+
+use rdev::{EventType, Key, Keyboard, KeyboardState};
+use shared::layout::{KeyboardLayout, Keystroke};
+
+/// This machine's active keyboard layout, as the OS keymap resolves it.
+///
+/// Used to work out which character a captured press types here before it
+/// is sent for the server to retype on its own layout. Only that direction
+/// is supported; the OS keymap can't be searched by character.
+pub struct SystemLayout {
+    keyboard: RefCell<Keyboard>,
+}
+
+impl SystemLayout {
+    /// `None` when the OS keymap can't be reached, e.g. under Wayland.
+    pub fn new() -> Option<Self> {
+        Keyboard::new().map(|keyboard| Self {
+            keyboard: RefCell::new(keyboard),
+        })
+    }
+}
+
+impl KeyboardLayout for SystemLayout {
+    fn char_for(&self, stroke: Keystroke) -> Option<char> {
+        let mut keyboard = self.keyboard.borrow_mut();
+        keyboard.reset();
+        if stroke.shift {
+            keyboard.add(&EventType::KeyPress(Key::ShiftLeft));
+        }
+        if stroke.alt_gr {
+            keyboard.add(&EventType::KeyPress(Key::AltGr));
+        }
+        let typed = keyboard.add(&EventType::KeyPress(stroke.key));
+        keyboard.reset();
+
+        // Keys that type nothing, or more than one character, stay raw.
+        let typed = typed?;
+        let mut chars = typed.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) if !ch.is_control() => Some(ch),
+            _ => None,
+        }
+    }
+
+    fn keystroke_for(&self, _ch: char) -> Option<Keystroke> {
+        None
+    }
+}
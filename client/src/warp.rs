@@ -0,0 +1,75 @@
+//! Moving the remote pointer by clicking a preview of the server's screen.
+//! There is no picture of the screen, only its outline scaled to fit the
+//! window, so placement is coarse: a click sends the [`AbsoluteMove`] for
+//! the same spot on the server's primary display.
+//!
+//! A warp is a control request rather than captured input, so it leaves the
+//! pointer mode as it was, and the server places it on its own display
+//! without the client display size or `absolute_mapping` that captured
+//! positions go through.
+
+use std::error::Error;
+
+use quinn::Connection;
+use shared::{AbsoluteMove, ControlRequest, ControlResponse, DisplayInfo};
+
+use crate::quic::control_request;
+
+/// Where a server display is drawn within a preview widget: as large as
+/// fits while keeping its aspect ratio, centred.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Preview {
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Preview {
+    /// Fits `display` into a widget `width` by `height`, or `None` if
+    /// either has no area.
+    pub fn fit(display: &DisplayInfo, width: f64, height: f64) -> Option<Self> {
+        if display.width == 0 || display.height == 0 || width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        let scale = (width / f64::from(display.width)).min(height / f64::from(display.height));
+        let (fitted_width, fitted_height) = (
+            f64::from(display.width) * scale,
+            f64::from(display.height) * scale,
+        );
+        Some(Self {
+            left: (width - fitted_width) / 2.0,
+            top: (height - fitted_height) / 2.0,
+            width: fitted_width,
+            height: fitted_height,
+        })
+    }
+
+    /// The position a click at `x`, `y` in the widget stands for. Clicks
+    /// beside the display are clamped to its nearest edge.
+    pub fn absolute_at(&self, x: f64, y: f64) -> AbsoluteMove {
+        let fraction = |offset: f64, span: f64| {
+            if span > 1.0 {
+                (offset / (span - 1.0)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        };
+        AbsoluteMove {
+            x: fraction(x - self.left, self.width),
+            y: fraction(y - self.top, self.height),
+        }
+    }
+}
+
+/// Moves the server's pointer to `absolute` on its primary display.
+pub async fn warp_pointer(
+    connection: &Connection,
+    absolute: AbsoluteMove,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    match control_request(connection, &ControlRequest::Warp(absolute)).await? {
+        ControlResponse::Warped(true) => Ok(()),
+        ControlResponse::Warped(false) => Err("the server can't position the pointer absolutely".into()),
+        other => Err(format!("unexpected warp response: {other:?}").into()),
+    }
+}
@@ -0,0 +1,82 @@
+//! Releasing the grab when the window stops responding. Capture holds all
+//! input, so a hung GTK main loop would otherwise leave the stop chord as
+//! the only way back to the desktop. The main loop pings a [`Heartbeat`]
+//! every [`PING_INTERVAL`]; the capture callback checks it with each event
+//! and stops capture once it has been silent for the timeout.
+//!
+//! `QUICINPUT_UI_TIMEOUT_SECS` sets the timeout (default 5); 0 turns the
+//! watchdog off.
+
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use shared::monotonic_micros;
+
+/// How often the main loop pings.
+pub const PING_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Silence after which capture is released, unless overridden.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The heartbeat of the GTK main loop.
+pub static UI_HEARTBEAT: Heartbeat = Heartbeat::new();
+
+/// When something last showed it was alive, in [`monotonic_micros`].
+#[derive(Debug, Default)]
+pub struct Heartbeat {
+    // Zero until the first ping.
+    last_ping: AtomicU64,
+}
+
+impl Heartbeat {
+    pub const fn new() -> Self {
+        Self {
+            last_ping: AtomicU64::new(0),
+        }
+    }
+
+    pub fn ping(&self) {
+        self.ping_at(monotonic_micros());
+    }
+
+    pub fn ping_at(&self, micros: u64) {
+        self.last_ping.store(micros.max(1), Ordering::Relaxed);
+    }
+
+    /// How long it has been silent as of `now`, or `None` if it has never
+    /// pinged.
+    pub fn silent_for(&self, now: u64) -> Option<Duration> {
+        match self.last_ping.load(Ordering::Relaxed) {
+            0 => None,
+            last => Some(Duration::from_micros(now.saturating_sub(last))),
+        }
+    }
+
+    /// The silence as of `now` if it has lasted longer than `timeout`.
+    pub fn stale_at(&self, now: u64, timeout: Duration) -> Option<Duration> {
+        self.silent_for(now).filter(|silent| *silent > timeout)
+    }
+}
+
+/// The timeout from `QUICINPUT_UI_TIMEOUT_SECS`, or `None` to not watch.
+pub fn timeout_from_env() -> Option<Duration> {
+    let Ok(value) = env::var("QUICINPUT_UI_TIMEOUT_SECS") else {
+        return Some(DEFAULT_TIMEOUT);
+    };
+    match value.trim().parse::<f64>() {
+        Ok(0.0) => None,
+        Ok(secs) if secs.is_finite() && secs > 0.0 => Some(Duration::from_secs_f64(secs)),
+        _ => {
+            eprintln!(
+                "[client] ignoring QUICINPUT_UI_TIMEOUT_SECS={value}: not a number of seconds"
+            );
+            Some(DEFAULT_TIMEOUT)
+        }
+    }
+}
+
+/// How long the main loop has been silent, if longer than `timeout`.
+pub fn ui_unresponsive_for(timeout: Duration) -> Option<Duration> {
+    UI_HEARTBEAT.stale_at(monotonic_micros(), timeout)
+}
@@ -1,19 +1,202 @@
 use display_info::DisplayInfo;
-pub fn get_display_size() -> (u32, u32) {
-	let display_infos = DisplayInfo::all().unwrap();
-    for display_info in display_infos.iter() {
-        if display_info.is_primary {
-            return (display_info.height, display_info.width);
+use mouse_position::mouse_position::Mouse;
+use shared::{AbsoluteMove, DisplaySize};
+
+/// Geometry of a single monitor in global desktop coordinates.
+#[derive(Clone, Debug)]
+pub struct MonitorGeometry {
+    /// Position in [`list_monitors`], counting from 0.
+    pub index: usize,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub is_primary: bool,
+}
+
+impl MonitorGeometry {
+    /// Point the capture loop recenters the pointer onto.
+    pub fn center(&self) -> (f64, f64) {
+        (
+            f64::from(self.x) + f64::from(self.width) / 2.0,
+            f64::from(self.y) + f64::from(self.height) / 2.0,
+        )
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let (left, top) = (f64::from(self.x), f64::from(self.y));
+        x >= left
+            && y >= top
+            && x < left + f64::from(self.width)
+            && y < top + f64::from(self.height)
+    }
+
+    /// Nearest point inside this monitor.
+    pub fn clamp(&self, x: f64, y: f64) -> (f64, f64) {
+        let (left, top) = (f64::from(self.x), f64::from(self.y));
+        let right = left + f64::from(self.width.saturating_sub(1));
+        let bottom = top + f64::from(self.height.saturating_sub(1));
+        (x.clamp(left, right), y.clamp(top, bottom))
+    }
+
+    /// Where `x`, `y` falls on this monitor, as sent in absolute pointer mode.
+    pub fn fraction_at(&self, x: f64, y: f64) -> AbsoluteMove {
+        shared::DisplayInfo {
+            name: self.name.clone(),
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+            is_primary: self.is_primary,
+        }
+        .fraction_at(x, y)
+    }
+
+    /// As told to the server, which keeps its aspect ratio for absolute
+    /// moves.
+    pub fn size(&self) -> DisplaySize {
+        DisplaySize {
+            width: self.width,
+            height: self.height,
         }
     }
-    // fall back to the first display if no primary is flagged
-    display_infos
-        .first()
-        .map(|info| (info.height, info.width))
-        .unwrap_or((0, 0))
+
+    pub fn label(&self) -> String {
+        let primary = if self.is_primary { " (primary)" } else { "" };
+        format!(
+            "{}: {} {}x{}{}",
+            self.index + 1,
+            self.name,
+            self.width,
+            self.height,
+            primary
+        )
+    }
+}
+
+/// Which monitor to capture on. Kept by name rather than by position, as
+/// positions shift when monitors are plugged in or out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MonitorChoice {
+    Primary,
+    Named(String),
+}
+
+/// The monitor `choice` picks from `monitors`, or the primary one if it
+/// picks none that is still there. `None` only when there are no monitors.
+pub fn select_monitor(monitors: &[MonitorGeometry], choice: &MonitorChoice) -> Option<MonitorGeometry> {
+    let chosen = match choice {
+        MonitorChoice::Primary => None,
+        MonitorChoice::Named(name) => monitors.iter().find(|monitor| &monitor.name == name),
+    };
+    chosen
+        .or_else(|| monitors.get(primary_monitor_index(monitors)))
+        .cloned()
+}
+
+/// The monitor absolute pointer mode maps from: `locked`, the one capture
+/// is locked to, or else the primary. `None` only when there are no monitors.
+pub fn capture_monitor(locked: Option<MonitorGeometry>) -> Option<MonitorGeometry> {
+    locked.or_else(|| select_monitor(&list_monitors(), &MonitorChoice::Primary))
+}
+
+/// Default size of the main window, in logical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Used when there is no monitor to size the window from, e.g. headless.
+pub const FALLBACK_WINDOW_SIZE: WindowSize = WindowSize {
+    width: 960.0,
+    height: 540.0,
+};
+
+/// Half the active monitor in each direction, or [`FALLBACK_WINDOW_SIZE`]
+/// without one.
+pub fn find_window_size() -> WindowSize {
+    match active_monitor() {
+        Some(monitor) if monitor.width > 0 && monitor.height > 0 => WindowSize {
+            width: f64::from(monitor.width) / 2.0,
+            height: f64::from(monitor.height) / 2.0,
+        },
+        _ => FALLBACK_WINDOW_SIZE,
+    }
+}
+
+/// The monitor the user is working on: the one under the pointer, else the
+/// primary, else the first listed. `None` when there are no monitors.
+pub fn active_monitor() -> Option<MonitorGeometry> {
+    let monitors = list_monitors();
+    let index = active_monitor_index(&monitors, cursor_position())?;
+    monitors.into_iter().nth(index)
+}
+
+/// Index of the monitor containing `cursor`, falling back as
+/// [`active_monitor`] does.
+pub fn active_monitor_index(
+    monitors: &[MonitorGeometry],
+    cursor: Option<(f64, f64)>,
+) -> Option<usize> {
+    if monitors.is_empty() {
+        return None;
+    }
+    cursor
+        .and_then(|(x, y)| monitors.iter().position(|monitor| monitor.contains(x, y)))
+        .or(Some(primary_monitor_index(monitors)))
+}
+
+/// Every monitor, in the order the OS lists them. Empty when there are
+/// none, e.g. headless, or they can't be listed.
+pub fn list_monitors() -> Vec<MonitorGeometry> {
+    let displays = DisplayInfo::all().unwrap_or_else(|error| {
+        eprintln!("Failed to list monitors: {error}");
+        Vec::new()
+    });
+    displays
+        .into_iter()
+        .enumerate()
+        .map(|(index, info)| MonitorGeometry {
+            index,
+            name: if info.name.is_empty() {
+                format!("Monitor {}", index + 1)
+            } else {
+                info.name
+            },
+            x: info.x,
+            y: info.y,
+            width: info.width,
+            height: info.height,
+            is_primary: info.is_primary,
+        })
+        .collect()
+}
+
+/// Index of the primary monitor, falling back to the first one listed.
+pub fn primary_monitor_index(monitors: &[MonitorGeometry]) -> usize {
+    monitors
+        .iter()
+        .position(|monitor| monitor.is_primary)
+        .unwrap_or(0)
+}
+
+pub fn cursor_position() -> Option<(f64, f64)> {
+    match Mouse::get_mouse_position() {
+        Mouse::Position { x, y } => Some((f64::from(x), f64::from(y))),
+        Mouse::Error => None,
+    }
 }
 
-pub fn find_window_size() -> (f64, f64) {
-    let (height, width) = get_display_size();
-    (f64::from(height) / 2.0, f64::from(width) / 2.0)
-}
\ No newline at end of file
+/// Keeps a point saved earlier on screen after the monitor layout changed,
+/// pulling it onto the primary monitor if its original display is gone.
+pub fn clamp_to_desktop(x: f64, y: f64) -> Option<(f64, f64)> {
+    let monitors = list_monitors();
+    if monitors.iter().any(|monitor| monitor.contains(x, y)) {
+        return Some((x, y));
+    }
+    monitors
+        .get(primary_monitor_index(&monitors))
+        .map(|monitor| monitor.clamp(x, y))
+}
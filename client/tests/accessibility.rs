@@ -0,0 +1,65 @@
+use client::{
+    accessibility::{
+        AccessibleText, PROGRESS_LABEL, STATUS_LABEL, banner_text, progress_value, spinner_text,
+        status_announcement,
+    },
+    quic::ConnectPhase,
+};
+
+#[test]
+fn phase_labels_end_in_a_real_ellipsis() {
+    for phase in ConnectPhase::ALL {
+        let label = phase.label();
+        assert!(label.ends_with('\u{2026}'), "{phase:?}: {label}");
+        // The mojibake UTF-8 ellipsis reads as "â€¦" when decoded as Latin-1.
+        assert!(!label.contains('\u{e2}'), "{phase:?}: {label}");
+    }
+}
+
+#[test]
+fn the_spinner_reads_out_the_phase_without_its_ellipsis() {
+    assert_eq!(progress_value(ConnectPhase::Connecting), "Connecting");
+    assert_eq!(
+        progress_value(ConnectPhase::FetchingDisplays),
+        "Fetching server displays"
+    );
+    for phase in ConnectPhase::ALL {
+        assert!(!progress_value(phase).is_empty(), "{phase:?}");
+    }
+    assert!(!PROGRESS_LABEL.is_empty());
+}
+
+#[test]
+fn the_status_banner_says_it_is_an_error() {
+    assert_eq!(
+        status_announcement("Invalid port number"),
+        format!("{STATUS_LABEL}: Invalid port number")
+    );
+}
+
+#[test]
+fn the_spinner_is_labelled_as_progress_and_valued_by_phase() {
+    assert_eq!(
+        spinner_text(ConnectPhase::Connecting),
+        AccessibleText {
+            label: "Connection progress".to_owned(),
+            value_text: Some("Connecting".to_owned()),
+        }
+    );
+    for phase in ConnectPhase::ALL {
+        let text = spinner_text(phase);
+        assert_eq!(text.label, PROGRESS_LABEL, "{phase:?}");
+        assert_eq!(text.value_text.as_deref(), Some(progress_value(phase)));
+    }
+}
+
+#[test]
+fn the_banner_is_labelled_with_the_error_it_shows() {
+    assert_eq!(
+        banner_text("Port is required"),
+        AccessibleText {
+            label: "Connection error: Port is required".to_owned(),
+            value_text: None,
+        }
+    );
+}
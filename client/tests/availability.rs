@@ -0,0 +1,60 @@
+use client::availability::{PauseChange, RemotePause};
+use shared::{ControlResponse, InjectionStats, UnavailableReason};
+
+const LOCKED: ControlResponse = ControlResponse::RemoteUnavailable(UnavailableReason::ScreenLocked);
+
+#[test]
+fn unavailable_pauses_once() {
+    let pause = RemotePause::default();
+
+    assert_eq!(
+        pause.on_response(&LOCKED),
+        Some(PauseChange::Pause(UnavailableReason::ScreenLocked))
+    );
+    assert!(pause.is_paused());
+    // Keys are released once per pause, not per report.
+    assert_eq!(pause.on_response(&LOCKED), None);
+    assert_eq!(
+        pause.on_response(&ControlResponse::RemoteUnavailable(
+            UnavailableReason::SessionIdle
+        )),
+        None
+    );
+}
+
+#[test]
+fn available_resumes_only_a_paused_capture() {
+    let pause = RemotePause::default();
+
+    assert_eq!(pause.on_response(&ControlResponse::RemoteAvailable), None);
+    pause.on_response(&LOCKED);
+    assert_eq!(
+        pause.on_response(&ControlResponse::RemoteAvailable),
+        Some(PauseChange::Resume)
+    );
+    assert!(!pause.is_paused());
+}
+
+#[test]
+fn clones_share_the_pause() {
+    let pause = RemotePause::default();
+    let capture = pause.clone();
+
+    pause.on_response(&LOCKED);
+    assert!(capture.is_paused());
+
+    pause.clear();
+    assert!(!capture.is_paused());
+}
+
+#[test]
+fn other_responses_change_nothing() {
+    let pause = RemotePause::default();
+    pause.on_response(&LOCKED);
+
+    assert_eq!(
+        pause.on_response(&ControlResponse::Injection(InjectionStats::default())),
+        None
+    );
+    assert!(pause.is_paused());
+}
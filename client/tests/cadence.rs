@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use client::quic_helper_thread::{LinkRegime, MAX_COALESCE_WINDOW, MAX_RECENTER_MARGIN};
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+/// Feeds RTT samples in order from a fresh link.
+fn settle(samples: &[u64]) -> LinkRegime {
+    samples
+        .iter()
+        .fold(LinkRegime::default(), |regime, rtt| regime.next(ms(*rtt)))
+}
+
+#[test]
+fn low_rtt_sends_immediately_and_recenters_every_event() {
+    assert_eq!(settle(&[5, 12, 25]), LinkRegime::Low);
+    let cadence = LinkRegime::Low.cadence(0.0);
+    assert_eq!(cadence.coalesce_window, Duration::ZERO);
+    assert_eq!(cadence.recenter_margin, 0.0);
+}
+
+#[test]
+fn medium_rtt_batches_a_little() {
+    assert_eq!(settle(&[10, 45]), LinkRegime::Medium);
+    let cadence = LinkRegime::Medium.cadence(0.0);
+    assert!(cadence.coalesce_window > Duration::ZERO);
+    assert!(cadence.recenter_margin > 0.0);
+}
+
+#[test]
+fn high_rtt_batches_most_within_bounds() {
+    assert_eq!(settle(&[10, 250]), LinkRegime::High);
+    let medium = LinkRegime::Medium.cadence(0.0);
+    let high = LinkRegime::High.cadence(0.0);
+    assert!(high.coalesce_window > medium.coalesce_window);
+    assert!(high.recenter_margin > medium.recenter_margin);
+
+    // Heavy loss on top still stays bounded.
+    let lossy = LinkRegime::High.cadence(0.5);
+    assert_eq!(lossy.coalesce_window, MAX_COALESCE_WINDOW);
+    assert!(lossy.recenter_margin <= MAX_RECENTER_MARGIN);
+}
+
+#[test]
+fn rtt_near_a_boundary_does_not_flap() {
+    // Hovering either side of the 30 ms step-up stays medium once entered.
+    assert_eq!(settle(&[31, 28, 32, 25, 29]), LinkRegime::Medium);
+    // And the same around the high step-up.
+    assert_eq!(settle(&[120, 95, 105, 85]), LinkRegime::High);
+}
+
+#[test]
+fn regimes_step_down_once_rtt_clearly_improves() {
+    assert_eq!(settle(&[120, 60]), LinkRegime::Medium);
+    assert_eq!(settle(&[120, 10]), LinkRegime::Low);
+    assert_eq!(settle(&[45, 15]), LinkRegime::Low);
+}
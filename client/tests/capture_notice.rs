@@ -0,0 +1,53 @@
+use std::fs;
+
+use client::capture_notice::{CaptureNotice, STOP_CHORD, notice_text};
+use client::settings::ClientSettings;
+use rdev::Key;
+
+#[test]
+fn capture_is_explained_once_per_run() {
+    let mut settings = ClientSettings::default();
+    let mut notice = CaptureNotice::new();
+    assert!(notice.needed(&settings));
+
+    assert!(!notice.confirm(&mut settings, false));
+    assert!(!notice.needed(&settings));
+    assert!(!settings.skip_capture_notice);
+
+    // The next run asks again.
+    assert!(CaptureNotice::new().needed(&settings));
+}
+
+#[test]
+fn dont_show_again_is_kept_in_the_settings() {
+    let path = std::env::temp_dir()
+        .join(format!("quicinput-capture-notice-{}", std::process::id()))
+        .join("client.toml");
+    let _ = fs::remove_file(&path);
+    let mut settings = ClientSettings::load(&path);
+
+    assert!(CaptureNotice::new().confirm(&mut settings, true));
+    settings.save(&path).unwrap();
+
+    let reloaded = ClientSettings::load(&path);
+    assert!(reloaded.skip_capture_notice);
+    assert!(!CaptureNotice::new().needed(&reloaded));
+    // Nothing more to save the next time round.
+    let mut reloaded = reloaded;
+    assert!(!CaptureNotice::new().confirm(&mut reloaded, true));
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn settings_from_before_the_notice_still_show_it() {
+    let settings: ClientSettings = toml::from_str("pointer_mode = \"Relative\"\n").unwrap();
+    assert!(!settings.skip_capture_notice);
+}
+
+#[test]
+fn the_notice_names_the_stop_chord() {
+    assert!(notice_text(None).contains(STOP_CHORD));
+    let held = notice_text(Some(Key::ScrollLock));
+    assert!(held.contains("ScrollLock"));
+    assert!(held.contains(STOP_CHORD));
+}
@@ -0,0 +1,101 @@
+use std::io;
+
+use client::capture_support::{
+    CaptureError, CaptureErrorKind, MACOS_ACCESSIBILITY_URI, PERMISSIONS_HELP_URI, capture_notes,
+    check_capture,
+};
+use rdev::GrabError;
+use shared::session::SessionType;
+
+#[test]
+fn capture_needs_an_x_server_on_linux() {
+    assert!(check_capture(SessionType::X11, Some(true)).is_ok());
+    assert!(check_capture(SessionType::Wayland { xwayland: true }, Some(true)).is_ok());
+    assert!(matches!(
+        check_capture(SessionType::Wayland { xwayland: false }, Some(true)),
+        Err(CaptureError::NoXDisplay(_))
+    ));
+    assert!(matches!(
+        check_capture(SessionType::Headless, None),
+        Err(CaptureError::NoXDisplay(_))
+    ));
+}
+
+#[test]
+fn unreadable_input_devices_are_reported_up_front() {
+    let error = check_capture(SessionType::Wayland { xwayland: true }, Some(false)).unwrap_err();
+    assert!(matches!(error, CaptureError::NoInputAccess));
+    assert!(error.to_string().contains("'input' group"));
+    // Nothing to check is not a failure.
+    assert!(check_capture(SessionType::X11, None).is_ok());
+}
+
+#[test]
+fn other_platforms_are_not_checked() {
+    assert!(check_capture(SessionType::Native, Some(false)).is_ok());
+}
+
+#[test]
+fn grab_failures_say_what_to_do() {
+    let denied = CaptureError::Grab {
+        session: SessionType::Wayland { xwayland: true },
+        error: GrabError::IoError(io::Error::from(io::ErrorKind::PermissionDenied)),
+    };
+    let message = denied.to_string();
+    assert!(message.contains("Wayland (with XWayland)"));
+    assert!(message.contains("'input' group"));
+
+    let no_display = CaptureError::Grab {
+        session: SessionType::X11,
+        error: GrabError::MissingDisplayError,
+    };
+    assert!(no_display.to_string().contains("X server"));
+}
+
+#[test]
+fn only_wayland_changes_how_capture_behaves() {
+    assert!(capture_notes(SessionType::Wayland { xwayland: true }).is_some());
+    assert!(capture_notes(SessionType::X11).is_none());
+}
+
+#[test]
+fn permission_errors_link_to_instructions() {
+    let no_access = CaptureError::NoInputAccess;
+    assert_eq!(no_access.kind(), CaptureErrorKind::Permission);
+    assert_eq!(no_access.help().unwrap().uri, PERMISSIONS_HELP_URI);
+
+    let denied = CaptureError::Grab {
+        session: SessionType::Wayland { xwayland: true },
+        error: GrabError::IoError(io::Error::from(io::ErrorKind::PermissionDenied)),
+    };
+    assert_eq!(denied.kind(), CaptureErrorKind::Permission);
+    assert_eq!(denied.help().unwrap().uri, PERMISSIONS_HELP_URI);
+
+    let untrusted = CaptureError::Grab {
+        session: SessionType::Native,
+        error: GrabError::EventTapError,
+    };
+    assert_eq!(untrusted.kind(), CaptureErrorKind::Permission);
+    assert_eq!(untrusted.help().unwrap().uri, MACOS_ACCESSIBILITY_URI);
+    assert!(untrusted.to_string().contains("Accessibility"));
+}
+
+#[test]
+fn other_errors_are_told_apart_and_offer_no_link() {
+    assert_eq!(
+        CaptureError::AlreadyRunning.kind(),
+        CaptureErrorKind::AlreadyRunning
+    );
+    assert_eq!(
+        CaptureError::NoXDisplay(SessionType::Headless).kind(),
+        CaptureErrorKind::NoDisplay
+    );
+    assert_eq!(CaptureError::Ended.kind(), CaptureErrorKind::Failed);
+    for error in [
+        CaptureError::AlreadyRunning,
+        CaptureError::NoXDisplay(SessionType::Headless),
+        CaptureError::Ended,
+    ] {
+        assert_eq!(error.help(), None);
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::{Duration, Instant};
+
+use client::clicks::ClickTracker;
+use rdev::Button;
+use shared::clicks::DoubleClick;
+
+const WINDOW: Duration = Duration::from_millis(500);
+
+#[test]
+fn second_quick_press_is_marked() {
+    let mut tracker = ClickTracker::new(WINDOW);
+    let start = Instant::now();
+
+    assert_eq!(tracker.on_press(Button::Left, start), None);
+    assert_eq!(
+        tracker.on_press(Button::Left, start + WINDOW),
+        Some(DoubleClick {
+            button: Button::Left
+        })
+    );
+}
+
+#[test]
+fn slow_presses_and_other_buttons_are_not_marked() {
+    let mut tracker = ClickTracker::new(WINDOW);
+    let start = Instant::now();
+
+    tracker.on_press(Button::Left, start);
+    assert_eq!(tracker.on_press(Button::Right, start), None);
+    assert_eq!(tracker.on_press(Button::Left, start), None);
+    assert_eq!(tracker.on_press(Button::Left, start + WINDOW * 2), None);
+}
+
+#[test]
+fn a_third_quick_press_starts_over() {
+    let mut tracker = ClickTracker::new(WINDOW);
+    let start = Instant::now();
+
+    tracker.on_press(Button::Left, start);
+    assert!(tracker.on_press(Button::Left, start).is_some());
+    assert_eq!(tracker.on_press(Button::Left, start), None);
+    assert!(tracker.on_press(Button::Left, start).is_some());
+}
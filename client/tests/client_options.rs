@@ -0,0 +1,38 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use client::quic::{CertPin, ClientOptions, RetryPolicy};
+
+#[test]
+fn defaults_match_the_old_hardcoded_connect() {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 4433));
+    let options = ClientOptions::new(addr);
+    assert_eq!(options.server_addr, addr);
+    assert_eq!(options.server_name, "localhost");
+    assert_eq!(options.cert_pin, None);
+    assert_eq!(options.connect_timeout, Duration::from_secs(10));
+    assert_eq!(options.keep_alive, Some(Duration::from_secs(5)));
+    assert_eq!(options.local_port, 0);
+    assert_eq!(options.retry, RetryPolicy::default());
+}
+
+#[test]
+fn pins_parse_with_or_without_colons() {
+    let bare = "00112233445566778899aabbccddeeff00112233445566778899AABBCCDDEEFF";
+    let pin: CertPin = bare.parse().expect("bare hex pin rejected");
+    assert_eq!(pin.0[1], 0x11);
+    assert_eq!(pin.0[31], 0xFF);
+
+    let printed = pin.to_string();
+    assert_eq!(printed.len(), 64 + 31);
+    assert_eq!(printed.parse::<CertPin>(), Ok(pin));
+}
+
+#[test]
+fn malformed_pins_are_rejected() {
+    assert!("abcd".parse::<CertPin>().is_err());
+    assert!("zz".repeat(32).parse::<CertPin>().is_err());
+    assert!("0".repeat(66).parse::<CertPin>().is_err());
+}
@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use client::close_reason::{describe_close, describe_connect_error};
+use client::quic::{CertPin, ConnectError};
+use quinn::{ApplicationClose, ConnectionClose, ConnectionError, TransportErrorCode, VarInt};
+use shared::CloseCode;
+
+fn closed_by_server(error_code: VarInt, reason: &[u8]) -> ConnectionError {
+    ConnectionError::ApplicationClosed(ApplicationClose {
+        error_code,
+        reason: reason.to_vec().into(),
+    })
+}
+
+#[test]
+fn closing_it_ourselves_needs_no_message() {
+    assert_eq!(describe_close(&ConnectionError::LocallyClosed), None);
+}
+
+#[test]
+fn a_server_close_names_its_reason() {
+    let error = closed_by_server(CloseCode::ServerFull.into(), b"");
+    assert_eq!(
+        describe_close(&error).unwrap(),
+        format!(
+            "Server closed the connection. {}",
+            CloseCode::ServerFull.message()
+        )
+    );
+
+    let error = closed_by_server(CloseCode::Shutdown.into(), b"maintenance");
+    assert_eq!(
+        describe_close(&error).unwrap(),
+        format!(
+            "Server closed the connection (maintenance). {}",
+            CloseCode::Shutdown.message()
+        )
+    );
+}
+
+#[test]
+fn unknown_close_codes_are_shown_with_their_value() {
+    let error = closed_by_server(VarInt::from_u32(4242), b"");
+    assert_eq!(
+        describe_close(&error).unwrap(),
+        "Server closed the connection. It gave unknown close code 4242."
+    );
+}
+
+#[test]
+fn transport_failures_are_described() {
+    assert!(
+        describe_close(&ConnectionError::TimedOut)
+            .unwrap()
+            .contains("timed out")
+    );
+    assert!(
+        describe_close(&ConnectionError::Reset)
+            .unwrap()
+            .contains("reset")
+    );
+    assert!(
+        describe_close(&ConnectionError::VersionMismatch)
+            .unwrap()
+            .starts_with("Connection lost: ")
+    );
+}
+
+#[test]
+fn connect_failures_get_their_own_advice() {
+    let timed_out = describe_connect_error(&ConnectError::TimedOut(Duration::from_secs(5)));
+    assert!(timed_out.contains("within 5 seconds"), "{timed_out}");
+    assert!(timed_out.contains("firewall"), "{timed_out}");
+
+    let mismatch = describe_connect_error(&ConnectError::CertificateMismatch {
+        presented: CertPin([0xab; 32]),
+    });
+    assert!(
+        mismatch.contains("doesn't match the pinned one"),
+        "{mismatch}"
+    );
+    assert!(
+        mismatch.contains(&CertPin([0xab; 32]).to_string()),
+        "{mismatch}"
+    );
+
+    let version = describe_connect_error(&ConnectError::VersionMismatch);
+    assert!(version.contains("QUIC version"), "{version}");
+}
+
+#[test]
+fn a_refused_connection_gives_the_servers_reason() {
+    let error = ConnectError::Refused(closed_by_server(CloseCode::ServerFull.into(), b""));
+    assert_eq!(
+        describe_connect_error(&error),
+        format!(
+            "Server closed the connection. {}",
+            CloseCode::ServerFull.message()
+        )
+    );
+}
+
+#[test]
+fn a_connection_refused_before_its_handshake_mentions_the_allowlist() {
+    let error = ConnectError::Refused(ConnectionError::ConnectionClosed(ConnectionClose {
+        error_code: TransportErrorCode::CONNECTION_REFUSED,
+        frame_type: None,
+        reason: Vec::new().into(),
+    }));
+    assert!(
+        describe_connect_error(&error).contains("allowlist"),
+        "{}",
+        describe_connect_error(&error)
+    );
+}
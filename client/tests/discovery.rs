@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use client::discovery::{
+    BrowseEvent, DiscoveredServer, DiscoveryUpdate, SERVICE_TYPE, ServerBrowser, ServiceRecord,
+    apply_update, follow,
+};
+use futures::executor::block_on;
+
+const LAN_V4: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+const LAN_V6: IpAddr = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1));
+
+/// Hears a fixed list of events, then stops.
+struct FakeBrowser(VecDeque<BrowseEvent>);
+
+impl ServerBrowser for FakeBrowser {
+    async fn next_event(&mut self) -> Option<BrowseEvent> {
+        self.0.pop_front()
+    }
+}
+
+fn record(instance: &str, addresses: Vec<IpAddr>, port: u16) -> ServiceRecord {
+    ServiceRecord {
+        fullname: format!("{instance}.{SERVICE_TYPE}"),
+        addresses,
+        port,
+        fingerprint: None,
+    }
+}
+
+#[test]
+fn a_record_becomes_an_entry_named_after_its_instance() {
+    let mut office = record("Office", vec![LAN_V4], 4433);
+    office.fingerprint = Some("AB:CD".to_string());
+
+    let server = DiscoveredServer::from_record(&office).expect("record should parse");
+    assert_eq!(server.name, "Office");
+    assert_eq!(server.fullname, office.fullname);
+    assert_eq!(server.addr, SocketAddr::new(LAN_V4, 4433));
+    assert_eq!(server.fingerprint.as_deref(), Some("AB:CD"));
+    assert_eq!(server.label(), "Office — 192.168.1.20:4433");
+}
+
+#[test]
+fn ipv4_addresses_are_preferred() {
+    let dual = record("Dual", vec![LAN_V6, LAN_V4], 4433);
+    assert_eq!(
+        DiscoveredServer::from_record(&dual).unwrap().addr.ip(),
+        LAN_V4
+    );
+
+    let v6_only = record("V6", vec![LAN_V6], 4433);
+    assert_eq!(
+        DiscoveredServer::from_record(&v6_only).unwrap().addr.ip(),
+        LAN_V6
+    );
+}
+
+#[test]
+fn records_without_an_address_or_port_are_skipped() {
+    assert_eq!(
+        DiscoveredServer::from_record(&record("Nowhere", Vec::new(), 4433)),
+        None
+    );
+    assert_eq!(
+        DiscoveredServer::from_record(&record("Portless", vec![LAN_V4], 0)),
+        None
+    );
+    let event = BrowseEvent::Resolved(record("Nowhere", Vec::new(), 4433));
+    assert_eq!(DiscoveryUpdate::from_event(event), None);
+}
+
+#[test]
+fn an_unexpected_full_name_is_shown_whole() {
+    let odd = ServiceRecord {
+        fullname: "printer._ipp._tcp.local.".to_string(),
+        addresses: vec![LAN_V4],
+        port: 631,
+        fingerprint: None,
+    };
+    assert_eq!(
+        DiscoveredServer::from_record(&odd).unwrap().name,
+        "printer._ipp._tcp.local."
+    );
+}
+
+#[test]
+fn reannounced_servers_replace_their_entry_and_lost_ones_go() {
+    let mut servers = Vec::new();
+    for event in [
+        BrowseEvent::Resolved(record("Office", vec![LAN_V4], 4433)),
+        BrowseEvent::Resolved(record("Den", vec![LAN_V4], 5000)),
+        BrowseEvent::Resolved(record("Office", vec![LAN_V4], 4434)),
+    ] {
+        apply_update(&mut servers, DiscoveryUpdate::from_event(event).unwrap());
+    }
+    let names: Vec<_> = servers.iter().map(|server| server.name.as_str()).collect();
+    assert_eq!(names, ["Office", "Den"]);
+    assert_eq!(servers[0].addr.port(), 4434);
+
+    apply_update(
+        &mut servers,
+        DiscoveryUpdate::Lost(format!("Office.{SERVICE_TYPE}")),
+    );
+    assert_eq!(servers.len(), 1);
+    assert_eq!(servers[0].name, "Den");
+}
+
+#[test]
+fn following_a_browser_reports_each_change() {
+    let browser = FakeBrowser(VecDeque::from([
+        BrowseEvent::Resolved(record("Office", vec![LAN_V4], 4433)),
+        BrowseEvent::Resolved(record("Nowhere", Vec::new(), 4433)),
+        BrowseEvent::Resolved(record("Den", vec![LAN_V4], 5000)),
+        BrowseEvent::Removed(format!("Office.{SERVICE_TYPE}")),
+    ]));
+
+    let mut seen = Vec::new();
+    block_on(follow(browser, |servers| {
+        seen.push(
+            servers
+                .iter()
+                .map(|server| server.name.clone())
+                .collect::<Vec<_>>(),
+        );
+    }));
+
+    assert_eq!(seen, [vec!["Office"], vec!["Office", "Den"], vec!["Den"]]);
+}
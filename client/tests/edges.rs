@@ -0,0 +1,59 @@
+use client::edges::EdgeTracker;
+use shared::{DisplayInfo, Edge};
+
+fn display() -> DisplayInfo {
+    DisplayInfo {
+        name: "remote".to_owned(),
+        x: 0,
+        y: 0,
+        width: 200,
+        height: 100,
+        is_primary: true,
+    }
+}
+
+#[test]
+fn starts_centred_and_stays_on_the_display() {
+    let mut tracker = EdgeTracker::new(display(), 0.0);
+    assert_eq!(tracker.position(), (100.0, 50.0));
+
+    tracker.track(-500.0, 500.0);
+    assert_eq!(tracker.position(), (0.0, 99.0));
+}
+
+#[test]
+fn each_edge_fires_when_pushed_into() {
+    let mut tracker = EdgeTracker::new(display(), 0.0);
+    assert_eq!(tracker.track(-150.0, 0.0), vec![Edge::Left]);
+    assert_eq!(tracker.track(500.0, 0.0), vec![Edge::Right]);
+    assert_eq!(tracker.track(0.0, -80.0), vec![Edge::Top]);
+    assert_eq!(tracker.track(0.0, 150.0), vec![Edge::Bottom]);
+}
+
+#[test]
+fn corners_report_both_edges() {
+    let mut tracker = EdgeTracker::new(display(), 0.0);
+    assert_eq!(tracker.track(-300.0, -300.0), vec![Edge::Left, Edge::Top]);
+}
+
+#[test]
+fn edge_fires_once_until_the_pointer_leaves_the_band() {
+    let mut tracker = EdgeTracker::new(display(), 5.0);
+    assert_eq!(tracker.track(97.0, 0.0), vec![Edge::Right]);
+    // Still pushing against the edge.
+    assert!(tracker.track(10.0, 0.0).is_empty());
+    // Back off inside the band, then push again.
+    assert!(tracker.track(-3.0, 0.0).is_empty());
+    assert!(tracker.track(3.0, 0.0).is_empty());
+    // Leaving the band re-arms it.
+    assert!(tracker.track(-20.0, 0.0).is_empty());
+    assert_eq!(tracker.track(20.0, 0.0), vec![Edge::Right]);
+}
+
+#[test]
+fn only_the_edge_moved_towards_fires() {
+    // A band this wide puts every edge in range at once.
+    let mut tracker = EdgeTracker::new(display(), 1000.0);
+    assert_eq!(tracker.track(1.0, 0.0), vec![Edge::Right]);
+    assert_eq!(tracker.track(0.0, -1.0), vec![Edge::Top]);
+}
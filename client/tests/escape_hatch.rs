@@ -0,0 +1,109 @@
+use std::time::{Duration, Instant};
+
+use client::escape_hatch::{EscapeHatch, ForcedRelease, LinkClosed, escape_hint};
+use rdev::{EventType, Key};
+
+const HOLD: Duration = Duration::from_secs(3);
+const DEAD: Duration = Duration::from_secs(10);
+
+fn hatch() -> EscapeHatch {
+    EscapeHatch::new(Some(HOLD), Some(DEAD))
+}
+
+fn moved() -> EventType {
+    EventType::MouseMove { x: 1.0, y: 1.0 }
+}
+
+#[test]
+fn holding_escape_long_enough_forces_a_release() {
+    let mut hatch = hatch();
+    let start = Instant::now();
+    let press = EventType::KeyPress(Key::Escape);
+    assert_eq!(hatch.on_event(&press, None, start), None);
+    // Repeats don't restart the hold.
+    assert_eq!(
+        hatch.on_event(&press, None, start + Duration::from_secs(2)),
+        None
+    );
+    assert_eq!(
+        hatch.on_event(&moved(), None, start + HOLD),
+        Some(ForcedRelease::EscapeHeld(HOLD))
+    );
+}
+
+#[test]
+fn releasing_escape_starts_the_hold_over() {
+    let mut hatch = hatch();
+    let start = Instant::now();
+    hatch.on_event(&EventType::KeyPress(Key::Escape), None, start);
+    hatch.on_event(
+        &EventType::KeyRelease(Key::Escape),
+        None,
+        start + Duration::from_secs(2),
+    );
+    let later = start + Duration::from_secs(4);
+    assert_eq!(
+        hatch.on_event(&EventType::KeyPress(Key::Escape), None, later),
+        None
+    );
+    assert_eq!(
+        hatch.on_event(&moved(), None, later + Duration::from_secs(2)),
+        None
+    );
+}
+
+#[test]
+fn a_dead_connection_forces_a_release_unless_it_comes_back() {
+    let mut hatch = hatch();
+    let start = Instant::now();
+    assert_eq!(hatch.on_event(&moved(), Some(start), start), None);
+    assert_eq!(
+        hatch.on_event(&moved(), None, start + Duration::from_secs(5)),
+        None
+    );
+    let closed_again = start + Duration::from_secs(6);
+    assert_eq!(
+        hatch.on_event(&moved(), Some(closed_again), closed_again),
+        None
+    );
+    assert_eq!(
+        hatch.on_event(&moved(), Some(closed_again), closed_again + DEAD),
+        Some(ForcedRelease::LinkDead(DEAD))
+    );
+}
+
+#[test]
+fn a_dead_connection_is_timed_from_its_close_not_the_next_event() {
+    let mut hatch = hatch();
+    let closed = Instant::now();
+    assert_eq!(
+        hatch.on_event(&moved(), Some(closed), closed + DEAD),
+        Some(ForcedRelease::LinkDead(DEAD))
+    );
+}
+
+#[test]
+fn a_close_is_only_noted_for_the_connection_that_closed() {
+    let link_closed = LinkClosed::default();
+    assert_eq!(link_closed.since(1), None);
+    let at = Instant::now();
+    link_closed.clone().note(1, at);
+    assert_eq!(link_closed.since(1), Some(at));
+    assert_eq!(link_closed.since(2), None);
+}
+
+#[test]
+fn either_way_out_can_be_turned_off() {
+    let mut hatch = EscapeHatch::new(None, None);
+    let start = Instant::now();
+    hatch.on_event(&EventType::KeyPress(Key::Escape), Some(start), start);
+    assert_eq!(
+        hatch.on_event(&moved(), Some(start), start + Duration::from_secs(60)),
+        None
+    );
+    assert_eq!(escape_hint(hatch.escape_hold()), None);
+    assert_eq!(
+        escape_hint(Some(HOLD)).as_deref(),
+        Some("Holding Escape for 3 seconds also stops it.")
+    );
+}
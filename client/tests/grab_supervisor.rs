@@ -0,0 +1,102 @@
+use std::{io, time::Duration};
+
+use client::{
+    capture_support::CaptureError,
+    grab_supervisor::{GrabExit, GrabSupervisor, RestartPolicy, SupervisorDecision},
+};
+use rdev::GrabError;
+use shared::session::SessionType;
+
+const BRIEF: Duration = Duration::from_millis(10);
+
+fn policy() -> RestartPolicy {
+    RestartPolicy {
+        max_restarts: 2,
+        delay: Duration::from_millis(100),
+        healthy_after: Duration::from_secs(30),
+    }
+}
+
+fn grab_failed(error: GrabError) -> GrabExit {
+    GrabExit::Failed(CaptureError::Grab {
+        session: SessionType::X11,
+        error,
+    })
+}
+
+#[test]
+fn unexpected_endings_are_restarted_with_growing_delays_then_given_up() {
+    let mut supervisor = GrabSupervisor::new(policy());
+    assert_eq!(
+        supervisor.on_exit(&GrabExit::Ended, BRIEF),
+        SupervisorDecision::Restart {
+            attempt: 1,
+            delay: Duration::from_millis(100),
+        }
+    );
+    assert_eq!(
+        supervisor.on_exit(&GrabExit::Panicked, BRIEF),
+        SupervisorDecision::Restart {
+            attempt: 2,
+            delay: Duration::from_millis(200),
+        }
+    );
+    assert_eq!(
+        supervisor.on_exit(&GrabExit::Ended, BRIEF),
+        SupervisorDecision::GiveUp
+    );
+}
+
+#[test]
+fn a_healthy_run_resets_the_restart_count() {
+    let mut supervisor = GrabSupervisor::new(policy());
+    supervisor.on_exit(&GrabExit::Ended, BRIEF);
+    supervisor.on_exit(&GrabExit::Ended, BRIEF);
+    assert_eq!(
+        supervisor.on_exit(&GrabExit::Ended, Duration::from_secs(60)),
+        SupervisorDecision::Restart {
+            attempt: 1,
+            delay: Duration::from_millis(100),
+        }
+    );
+}
+
+#[test]
+fn an_intentional_stop_is_never_restarted() {
+    let mut supervisor = GrabSupervisor::new(policy());
+    assert_eq!(
+        supervisor.on_exit(&GrabExit::Stopped, BRIEF),
+        SupervisorDecision::GiveUp
+    );
+    assert!(GrabExit::Stopped.into_failure().is_none());
+}
+
+#[test]
+fn errors_that_would_recur_are_given_up_on_straight_away() {
+    let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+    for exit in [
+        grab_failed(GrabError::IoError(denied)),
+        grab_failed(GrabError::MissingDisplayError),
+        // macOS without accessibility access.
+        grab_failed(GrabError::EventTapError),
+        GrabExit::Failed(CaptureError::NoInputAccess),
+    ] {
+        let mut supervisor = GrabSupervisor::new(policy());
+        assert_eq!(supervisor.on_exit(&exit, BRIEF), SupervisorDecision::GiveUp);
+    }
+
+    let lost = io::Error::from(io::ErrorKind::UnexpectedEof);
+    assert!(grab_failed(GrabError::IoError(lost)).is_retryable());
+}
+
+#[test]
+fn giving_up_reports_why() {
+    assert!(matches!(
+        GrabExit::Ended.into_failure(),
+        Some(CaptureError::Ended)
+    ));
+    assert!(matches!(
+        grab_failed(GrabError::KeyboardError).into_failure(),
+        Some(CaptureError::Grab { .. })
+    ));
+}
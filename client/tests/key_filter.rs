@@ -0,0 +1,108 @@
+use client::key_filter::{KeyVerdict, filter_key_event};
+use rdev::{EventType, Key};
+
+/// Runs `events` through the filter, keeping the pressed set as the key
+/// monitor does.
+fn verdicts(events: &[EventType]) -> Vec<KeyVerdict> {
+    let mut pressed = Vec::new();
+    events
+        .iter()
+        .map(|event| {
+            let verdict = filter_key_event(&pressed, event);
+            match *event {
+                EventType::KeyPress(key) if verdict == KeyVerdict::Forward => pressed.push(key),
+                EventType::KeyRelease(key) => pressed.retain(|held| *held != key),
+                _ => {}
+            }
+            verdict
+        })
+        .collect()
+}
+
+#[test]
+fn a_second_press_without_a_release_is_a_repeat() {
+    assert_eq!(
+        verdicts(&[
+            EventType::KeyPress(Key::KeyA),
+            EventType::KeyPress(Key::KeyA),
+            EventType::KeyRelease(Key::KeyA),
+        ]),
+        [KeyVerdict::Forward, KeyVerdict::Repeat, KeyVerdict::Forward]
+    );
+}
+
+#[test]
+fn held_key_auto_repeat_sends_one_press_and_the_release() {
+    let events = [
+        EventType::KeyPress(Key::KeyA),
+        EventType::KeyPress(Key::KeyA),
+        EventType::KeyPress(Key::KeyA),
+        EventType::KeyRelease(Key::KeyA),
+    ];
+    let verdicts = verdicts(&events);
+    assert_eq!(
+        verdicts,
+        [
+            KeyVerdict::Forward,
+            KeyVerdict::Repeat,
+            KeyVerdict::Repeat,
+            KeyVerdict::Forward
+        ]
+    );
+    let forwarded: Vec<EventType> = events
+        .iter()
+        .zip(&verdicts)
+        .filter(|(_, verdict)| **verdict == KeyVerdict::Forward)
+        .map(|(event, _)| *event)
+        .collect();
+    assert_eq!(
+        forwarded,
+        [
+            EventType::KeyPress(Key::KeyA),
+            EventType::KeyRelease(Key::KeyA)
+        ]
+    );
+}
+
+#[test]
+fn a_second_release_is_stray() {
+    assert_eq!(
+        verdicts(&[
+            EventType::KeyPress(Key::ShiftLeft),
+            EventType::KeyRelease(Key::ShiftLeft),
+            EventType::KeyRelease(Key::ShiftLeft),
+        ]),
+        [
+            KeyVerdict::Forward,
+            KeyVerdict::Forward,
+            KeyVerdict::StrayRelease
+        ]
+    );
+    // E.g. a key that was already down when capture started.
+    assert_eq!(
+        filter_key_event(&[], &EventType::KeyRelease(Key::ControlLeft)),
+        KeyVerdict::StrayRelease
+    );
+}
+
+#[test]
+fn alternating_presses_and_releases_all_go_through() {
+    let events = [
+        EventType::KeyPress(Key::ShiftLeft),
+        EventType::KeyPress(Key::KeyA),
+        EventType::KeyRelease(Key::KeyA),
+        EventType::KeyPress(Key::KeyA),
+        EventType::KeyRelease(Key::KeyA),
+        EventType::KeyRelease(Key::ShiftLeft),
+    ];
+    assert_eq!(verdicts(&events), [KeyVerdict::Forward; 6]);
+}
+
+#[test]
+fn other_events_are_not_filtered() {
+    let wheel = EventType::Wheel {
+        delta_x: 0,
+        delta_y: 1,
+    };
+    assert_eq!(filter_key_event(&[], &wheel), KeyVerdict::Forward);
+}
@@ -0,0 +1,68 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use client::lock_keys::LockState;
+use rdev::Key;
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("quicinput-leds-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn add_led(dir: &Path, name: &str, brightness: &str) {
+    let led = dir.join(name);
+    fs::create_dir_all(&led).unwrap();
+    fs::write(led.join("brightness"), brightness).unwrap();
+}
+
+#[test]
+fn lock_keys_toggle_and_others_do_not() {
+    let mut locks = LockState::default();
+    assert!(locks.toggle(Key::CapsLock));
+    assert!(locks.caps_lock);
+    assert!(!locks.toggle(Key::KeyA));
+    assert!(locks.toggle(Key::CapsLock));
+    assert_eq!(locks, LockState::default());
+}
+
+#[test]
+fn only_locks_that_are_on_are_described() {
+    assert_eq!(LockState::default().describe(), None);
+    let locks = LockState {
+        caps_lock: true,
+        num_lock: true,
+        scroll_lock: false,
+    };
+    assert_eq!(locks.describe().as_deref(), Some("Caps Lock, Num Lock on"));
+}
+
+#[test]
+fn leds_lit_on_any_keyboard_count() {
+    let dir = scratch_dir("lit");
+    add_led(&dir, "input3::capslock", "0\n");
+    add_led(&dir, "input7::capslock", "1\n");
+    add_led(&dir, "input3::numlock", "0\n");
+    add_led(&dir, "input3::scrolllock", "0\n");
+    add_led(&dir, "platform::mute", "1\n");
+
+    assert_eq!(
+        LockState::from_leds(&dir),
+        Some(LockState {
+            caps_lock: true,
+            num_lock: false,
+            scroll_lock: false,
+        })
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn no_lock_leds_is_unknown() {
+    let dir = scratch_dir("none");
+    assert_eq!(LockState::from_leds(&dir), None);
+
+    add_led(&dir, "platform::mute", "1\n");
+    assert_eq!(LockState::from_leds(&dir), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
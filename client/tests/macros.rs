@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use client::macros::{
+    Macro, MacroRecorder, MacroRecording, list_macros, load_macro, macro_path, play, save_macro,
+};
+use client::outbox::{Outbox, OutboxOptions};
+use client::quic::quic_runtime;
+use client::quic_helper_thread::QuicCommand;
+use client::recording::RecordedInput;
+use rdev::{EventType, Key};
+use shared::codec::{Codec, WireFormat};
+use shared::{MouseMove, SentAt};
+use tokio::sync::mpsc::unbounded_channel;
+
+fn key(event: EventType) -> Vec<u8> {
+    rmp_serde::to_vec(&event).expect("failed to serialise")
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("quicinput-macros-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn sample_commands() -> Vec<QuicCommand> {
+    vec![
+        QuicCommand::Keyboard(key(EventType::KeyPress(Key::KeyH))),
+        QuicCommand::Keyboard(key(EventType::KeyRelease(Key::KeyH))),
+        QuicCommand::Move(MouseMove { dx: 4.0, dy: -2.0 }),
+    ]
+}
+
+#[test]
+fn recording_keeps_the_gaps_between_inputs() {
+    let start = Instant::now();
+    let mut recorder = MacroRecorder::new();
+    let commands = sample_commands();
+    recorder.record(&commands[0], start);
+    recorder.record(&commands[1], start + Duration::from_millis(30));
+    // Latency stamps would carry the recording's send times.
+    let stamp = rmp_serde::to_vec(&SentAt { micros: 7 }).unwrap();
+    recorder.record(
+        &QuicCommand::Keyboard(stamp),
+        start + Duration::from_millis(40),
+    );
+    recorder.record(&QuicCommand::Shutdown, start + Duration::from_millis(40));
+    recorder.record(&commands[2], start + Duration::from_millis(80));
+
+    let recorded = recorder.finish();
+    let gaps: Vec<Duration> = recorded.steps.iter().map(|(gap, _)| *gap).collect();
+    assert_eq!(
+        gaps,
+        [
+            Duration::ZERO,
+            Duration::from_millis(30),
+            Duration::from_millis(50)
+        ]
+    );
+    assert_eq!(recorded.duration(), Duration::from_millis(80));
+}
+
+#[test]
+fn a_recorded_macro_replays_in_order_and_at_pace() {
+    let start = Instant::now();
+    let mut recorder = MacroRecorder::new();
+    for (index, command) in sample_commands().iter().enumerate() {
+        recorder.record(command, start + Duration::from_millis(40) * index as u32);
+    }
+    // Played from what was saved, as the menu does.
+    let recorded = Macro::from_bytes(&recorder.finish().to_bytes().unwrap()).unwrap();
+
+    let (sender, mut receiver) = unbounded_channel();
+    let started = Instant::now();
+    let sent = quic_runtime().block_on(play(&recorded, &sender, WireFormat::MessagePack));
+    assert_eq!(sent, 3);
+    assert!(started.elapsed() >= Duration::from_millis(80));
+
+    let mut received = Vec::new();
+    while let Ok(command) = receiver.try_recv() {
+        received.push(
+            RecordedInput::from_command(&command, WireFormat::MessagePack)
+                .expect("only input was recorded"),
+        );
+    }
+    let expected: Vec<RecordedInput> = sample_commands()
+        .iter()
+        .filter_map(|command| RecordedInput::from_command(command, WireFormat::MessagePack))
+        .collect();
+    assert_eq!(received, expected);
+}
+
+#[test]
+fn a_macro_recorded_as_json_plays_as_message_pack() {
+    let press = EventType::KeyPress(Key::KeyH);
+    let mut recorder = MacroRecorder::new().with_format(WireFormat::Json);
+    recorder.record(
+        &QuicCommand::Keyboard(WireFormat::Json.encode(&press).unwrap()),
+        Instant::now(),
+    );
+    recorder.record(
+        &QuicCommand::Move(MouseMove { dx: 1.0, dy: 2.0 }),
+        Instant::now(),
+    );
+    let recorded = Macro::from_bytes(&recorder.finish().to_bytes().unwrap()).unwrap();
+    assert_eq!(recorded.format, WireFormat::Json);
+
+    let (sender, mut receiver) = unbounded_channel();
+    let sent = quic_runtime().block_on(play(&recorded, &sender, WireFormat::MessagePack));
+    assert_eq!(sent, 2);
+    match receiver.try_recv() {
+        Ok(QuicCommand::Keyboard(buf)) => assert_eq!(buf, key(press)),
+        _ => panic!("expected the key press first"),
+    }
+    assert!(matches!(
+        receiver.try_recv(),
+        Ok(QuicCommand::Move(MouseMove { dx: 1.0, dy: 2.0 }))
+    ));
+}
+
+#[test]
+fn playing_stops_once_the_worker_is_gone() {
+    let mut recorder = MacroRecorder::new();
+    recorder.record(&sample_commands()[0], Instant::now());
+    let (sender, receiver) = unbounded_channel();
+    drop(receiver);
+    assert_eq!(
+        quic_runtime().block_on(play(&recorder.finish(), &sender, WireFormat::MessagePack)),
+        0
+    );
+}
+
+#[test]
+fn the_outbox_feeds_a_macro_only_while_recording() {
+    let (sender, _receiver) = unbounded_channel();
+    let recording = MacroRecording::default();
+    let mut outbox =
+        Outbox::new(sender, OutboxOptions::default()).with_macro_recording(recording.clone());
+    let commands = sample_commands();
+
+    outbox.send(commands[0].clone());
+    recording.start(WireFormat::Json);
+    assert!(recording.is_recording());
+    outbox.send(commands[1].clone());
+    let recorded = recording.stop().expect("a macro was being recorded");
+    outbox.send(commands[2].clone());
+
+    assert!(!recording.is_recording());
+    assert_eq!(recorded.format, WireFormat::Json);
+    assert_eq!(recorded.steps.len(), 1);
+    assert_eq!(
+        Some(recorded.steps[0].1.clone()),
+        RecordedInput::from_command(&commands[1], WireFormat::MessagePack)
+    );
+    assert_eq!(recording.stop(), None);
+}
+
+#[test]
+fn saved_macros_load_and_are_listed_by_name() {
+    let dir = scratch_dir("saved");
+    let mut recorder = MacroRecorder::new();
+    recorder.record(&sample_commands()[0], Instant::now());
+    let recorded = recorder.finish();
+
+    assert_eq!(list_macros(&dir), Vec::<String>::new());
+    save_macro(&dir, "greet", &recorded).unwrap();
+    save_macro(&dir, "bye now", &Macro::default()).unwrap();
+    fs::write(dir.join("notes.txt"), "not a macro").unwrap();
+
+    assert_eq!(list_macros(&dir), ["bye now", "greet"]);
+    assert_eq!(load_macro(&dir, "greet").unwrap(), recorded);
+    assert!(load_macro(&dir, "missing").is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn names_stay_inside_the_macro_dir() {
+    let dir = Path::new("macros");
+    assert_eq!(macro_path(dir, " greet "), Some(dir.join("greet.macro")));
+    for name in ["", "  ", "../escape", "a/b", "a\\b", ".hidden"] {
+        assert_eq!(macro_path(dir, name), None, "{name:?}");
+    }
+    assert!(save_macro(dir, "../escape", &Macro::default()).is_err());
+}
@@ -0,0 +1,219 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use client::{
+    mirror::{Mirror, MirrorTarget, TargetError, first_to_answer, parse_targets},
+    outbox::{Outbox, OutboxOptions},
+    quic::quic_runtime,
+    quic_helper_thread::QuicCommand,
+};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+
+fn target(label: &str) -> (MirrorTarget, UnboundedReceiver<QuicCommand>) {
+    let (tx, rx) = unbounded_channel();
+    (MirrorTarget::new(label, tx), rx)
+}
+
+fn keyboard(rx: &mut UnboundedReceiver<QuicCommand>) -> Option<Vec<u8>> {
+    match rx.try_recv().ok()? {
+        QuicCommand::Keyboard(bytes) => Some(bytes),
+        _ => None,
+    }
+}
+
+#[test]
+fn one_event_reaches_every_target() {
+    let (a, mut a_rx) = target("a");
+    let (b, mut b_rx) = target("b");
+    let (c, mut c_rx) = target("c");
+    let mut mirror = Mirror::new(vec![a, b, c]);
+
+    assert_eq!(mirror.send(&QuicCommand::Keyboard(vec![1, 2, 3])), 3);
+
+    for rx in [&mut a_rx, &mut b_rx, &mut c_rx] {
+        assert_eq!(keyboard(rx), Some(vec![1, 2, 3]));
+    }
+}
+
+#[test]
+fn a_dead_target_is_dropped_and_the_rest_keep_receiving() {
+    let (a, mut a_rx) = target("a");
+    let (b, b_rx) = target("b");
+    let (c, mut c_rx) = target("c");
+    let mut mirror = Mirror::new(vec![a, b, c]);
+    drop(b_rx);
+
+    assert_eq!(mirror.send(&QuicCommand::Keyboard(vec![1])), 2);
+    assert_eq!(mirror.labels(), ["a", "c"]);
+    assert_eq!(mirror.send(&QuicCommand::Keyboard(vec![2])), 2);
+
+    for rx in [&mut a_rx, &mut c_rx] {
+        assert_eq!(keyboard(rx), Some(vec![1]));
+        assert_eq!(keyboard(rx), Some(vec![2]));
+    }
+}
+
+fn mirrored_outbox(primary: MirrorTarget, mirrors: Vec<MirrorTarget>) -> Outbox {
+    Outbox::new(primary.sender, OutboxOptions::default()).with_mirror(Mirror::new(mirrors))
+}
+
+#[test]
+fn the_outbox_mirrors_until_shutdown() {
+    let (a, mut a_rx) = target("a");
+    let (b, mut b_rx) = target("b");
+    let mut outbox = mirrored_outbox(a, vec![b]);
+
+    outbox.send(QuicCommand::Keyboard(vec![7]));
+    outbox.shutdown();
+
+    for rx in [&mut a_rx, &mut b_rx] {
+        assert_eq!(keyboard(rx), Some(vec![7]));
+        assert!(matches!(rx.try_recv(), Ok(QuicCommand::Shutdown)));
+    }
+}
+
+#[test]
+fn losing_the_first_server_queues_for_it_while_mirrors_keep_receiving() {
+    let (primary, primary_rx) = target("primary");
+    let (mirror, mut mirror_rx) = target("mirror");
+    let mut outbox = mirrored_outbox(primary, vec![mirror]);
+
+    outbox.send(QuicCommand::Keyboard(vec![1]));
+    assert_eq!(keyboard(&mut mirror_rx), Some(vec![1]));
+
+    drop(primary_rx);
+    outbox.send(QuicCommand::Keyboard(vec![2]));
+    outbox.send(QuicCommand::Keyboard(vec![3]));
+    assert!(!outbox.is_connected());
+    assert_eq!(outbox.queued(), 2);
+    assert_eq!(keyboard(&mut mirror_rx), Some(vec![2]));
+    assert_eq!(keyboard(&mut mirror_rx), Some(vec![3]));
+
+    // Only the first server is sent what it missed.
+    let (tx, mut rx) = unbounded_channel();
+    assert_eq!(outbox.reconnect(tx), 2);
+    assert_eq!(keyboard(&mut rx), Some(vec![2]));
+    assert_eq!(keyboard(&mut rx), Some(vec![3]));
+    assert_eq!(keyboard(&mut mirror_rx), None);
+}
+
+#[test]
+fn losing_every_mirror_leaves_the_first_server_connected() {
+    let (primary, mut primary_rx) = target("primary");
+    let (mirror, mirror_rx) = target("mirror");
+    let mut outbox = mirrored_outbox(primary, vec![mirror]);
+    drop(mirror_rx);
+
+    outbox.send(QuicCommand::Keyboard(vec![1]));
+    outbox.send(QuicCommand::Keyboard(vec![2]));
+
+    assert!(outbox.is_connected());
+    assert_eq!(keyboard(&mut primary_rx), Some(vec![1]));
+    assert_eq!(keyboard(&mut primary_rx), Some(vec![2]));
+}
+
+#[test]
+fn stopping_mirroring_shuts_the_mirrors_down_and_keeps_the_first_server() {
+    let (primary, mut primary_rx) = target("primary");
+    let (mirror, mut mirror_rx) = target("mirror");
+    let mut outbox = mirrored_outbox(primary, vec![mirror]);
+
+    outbox.stop_mirroring();
+    outbox.send(QuicCommand::Keyboard(vec![1]));
+
+    assert!(matches!(mirror_rx.try_recv(), Ok(QuicCommand::Shutdown)));
+    assert_eq!(keyboard(&mut mirror_rx), None);
+    assert_eq!(keyboard(&mut primary_rx), Some(vec![1]));
+}
+
+fn addr(text: &str) -> SocketAddr {
+    text.parse().unwrap()
+}
+
+#[test]
+fn targets_without_a_port_use_the_one_given() {
+    assert_eq!(
+        parse_targets("192.168.1.20, 192.168.1.21:5001 ::1", 5000),
+        Ok(vec![
+            addr("192.168.1.20:5000"),
+            addr("192.168.1.21:5001"),
+            addr("[::1]:5000"),
+        ])
+    );
+}
+
+#[test]
+fn a_server_named_twice_is_kept_once() {
+    assert_eq!(
+        parse_targets("10.0.0.1,10.0.0.2, 10.0.0.1:5000", 5000),
+        Ok(vec![addr("10.0.0.1:5000"), addr("10.0.0.2:5000")])
+    );
+}
+
+#[test]
+fn an_empty_or_bad_list_is_refused() {
+    assert_eq!(parse_targets(" , ", 5000), Err(TargetError::Empty));
+    assert_eq!(
+        parse_targets("10.0.0.1, server.local", 5000),
+        Err(TargetError::Invalid("server.local".to_string()))
+    );
+}
+
+#[test]
+fn a_server_that_is_down_leaves_the_next_to_answer_first() {
+    let targets = [
+        addr("10.0.0.1:5000"),
+        addr("10.0.0.2:5000"),
+        addr("10.0.0.3:5000"),
+    ];
+    let answered = quic_runtime()
+        .block_on(first_to_answer(&targets, |server| async move {
+            match server.ip().to_string().as_str() {
+                "10.0.0.1" => Err("unreachable"),
+                "10.0.0.2" => {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    Ok(server.port())
+                }
+                _ => {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    Ok(0)
+                }
+            }
+        }))
+        .expect("the second server answers");
+
+    assert_eq!(answered.addr, targets[1]);
+    assert_eq!(answered.value, 5000);
+    assert_eq!(answered.failed, vec![(targets[0], "unreachable")]);
+
+    // The third is still being connected to, not dropped for redialling.
+    let mut others = answered.others;
+    assert_eq!(others.len(), 1);
+    let (server, result) = quic_runtime()
+        .block_on(others.join_next())
+        .expect("the third attempt was kept")
+        .expect("the third attempt panicked");
+    assert_eq!(server, targets[2]);
+    assert_eq!(result, Ok(0));
+}
+
+#[test]
+fn every_failure_is_kept_in_the_order_named_when_none_answer() {
+    let targets = [addr("10.0.0.1:5000"), addr("10.0.0.2:5000")];
+    let failures = quic_runtime()
+        .block_on(first_to_answer(&targets, |server| async move {
+            if server == addr("10.0.0.1:5000") {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            Err::<(), _>(server.to_string())
+        }))
+        .expect_err("nothing answers");
+
+    assert_eq!(
+        failures,
+        vec![
+            (targets[0], "10.0.0.1:5000".to_string()),
+            (targets[1], "10.0.0.2:5000".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,122 @@
+use std::time::{Duration, Instant};
+
+use client::momentary::{CaptureMode, DEFAULT_HOLD_KEY, HoldAction, HoldTrigger};
+use rdev::{Button, EventType, Key};
+
+const TRIGGER: Key = Key::ScrollLock;
+
+fn press(key: Key) -> EventType {
+    EventType::KeyPress(key)
+}
+
+fn release(key: Key) -> EventType {
+    EventType::KeyRelease(key)
+}
+
+#[test]
+fn toggle_is_the_default_and_scroll_lock_the_trigger() {
+    assert_eq!(CaptureMode::default(), CaptureMode::Toggle);
+    assert_eq!(DEFAULT_HOLD_KEY, Key::ScrollLock);
+}
+
+#[test]
+fn input_stays_here_until_the_trigger_is_held() {
+    let mut hold = HoldTrigger::new(TRIGGER);
+    let now = Instant::now();
+    assert_eq!(hold.on_event(&press(Key::KeyA), now), HoldAction::Pass);
+    assert_eq!(hold.on_event(&release(Key::KeyA), now), HoldAction::Pass);
+    assert_eq!(
+        hold.on_event(&EventType::MouseMove { x: 1.0, y: 2.0 }, now),
+        HoldAction::Pass
+    );
+    assert!(!hold.is_engaged());
+}
+
+#[test]
+fn a_quick_tap_engages_and_disengages_with_nothing_to_release() {
+    let mut hold = HoldTrigger::new(TRIGGER);
+    let start = Instant::now();
+    assert_eq!(hold.on_event(&press(TRIGGER), start), HoldAction::Engage);
+    assert!(hold.is_engaged());
+    assert_eq!(
+        hold.on_event(&release(TRIGGER), start + Duration::from_millis(40)),
+        HoldAction::Disengage {
+            held_for: Duration::from_millis(40),
+            releases: Vec::new(),
+        }
+    );
+    assert!(!hold.is_engaged());
+    assert_eq!(hold.on_event(&press(Key::KeyA), start), HoldAction::Pass);
+}
+
+#[test]
+fn a_long_hold_forwards_everything_and_ignores_trigger_repeats() {
+    let mut hold = HoldTrigger::new(TRIGGER);
+    let start = Instant::now();
+    assert_eq!(hold.on_event(&press(TRIGGER), start), HoldAction::Engage);
+    for second in 1..=5 {
+        let at = start + Duration::from_secs(second);
+        assert_eq!(hold.on_event(&press(TRIGGER), at), HoldAction::Ignore);
+        assert_eq!(hold.on_event(&press(Key::KeyW), at), HoldAction::Forward);
+        assert_eq!(
+            hold.on_event(&EventType::MouseMove { x: 3.0, y: 4.0 }, at),
+            HoldAction::Forward
+        );
+    }
+    assert_eq!(
+        hold.on_event(&release(Key::KeyW), start),
+        HoldAction::Forward
+    );
+
+    assert_eq!(
+        hold.on_event(&release(TRIGGER), start + Duration::from_secs(6)),
+        HoldAction::Disengage {
+            held_for: Duration::from_secs(6),
+            releases: Vec::new(),
+        }
+    );
+}
+
+#[test]
+fn letting_go_releases_what_is_still_down_on_the_server() {
+    let mut hold = HoldTrigger::new(TRIGGER);
+    let now = Instant::now();
+    hold.on_event(&press(TRIGGER), now);
+    hold.on_event(&press(Key::ShiftLeft), now);
+    hold.on_event(&press(Key::KeyA), now);
+    hold.on_event(&release(Key::KeyA), now);
+    hold.on_event(&EventType::ButtonPress(Button::Left), now);
+    hold.on_event(&press(Key::KeyD), now);
+
+    let HoldAction::Disengage { releases, .. } = hold.on_event(&release(TRIGGER), now) else {
+        panic!("releasing the trigger should disengage");
+    };
+    assert_eq!(
+        releases,
+        vec![
+            release(Key::KeyD),
+            EventType::ButtonRelease(Button::Left),
+            release(Key::ShiftLeft),
+        ]
+    );
+
+    // The next hold starts with nothing owed.
+    hold.on_event(&press(TRIGGER), now);
+    assert_eq!(
+        hold.on_event(&release(TRIGGER), now),
+        HoldAction::Disengage {
+            held_for: Duration::ZERO,
+            releases: Vec::new(),
+        }
+    );
+}
+
+#[test]
+fn a_trigger_release_without_its_press_is_left_alone() {
+    // E.g. the key was already down when capture started.
+    let mut hold = HoldTrigger::new(TRIGGER);
+    assert_eq!(
+        hold.on_event(&release(TRIGGER), Instant::now()),
+        HoldAction::Pass
+    );
+}
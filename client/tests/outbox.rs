@@ -0,0 +1,129 @@
+use client::outbox::{Outbox, OutboxOptions, ReconnectPolicy};
+use client::quic_helper_thread::{QuicCommand, QuicSender};
+use shared::MouseMove;
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+
+fn channel() -> (QuicSender, UnboundedReceiver<QuicCommand>) {
+    unbounded_channel()
+}
+
+/// An outbox whose worker has already gone.
+fn disconnected(options: OutboxOptions) -> Outbox {
+    let (sender, receiver) = channel();
+    drop(receiver);
+    let mut outbox = Outbox::new(sender, options);
+    outbox.send(QuicCommand::Keyboard(vec![0]));
+    assert!(!outbox.is_connected());
+    outbox
+}
+
+fn keyboard_bytes(receiver: &mut UnboundedReceiver<QuicCommand>) -> Vec<Vec<u8>> {
+    let mut received = Vec::new();
+    while let Ok(command) = receiver.try_recv() {
+        match command {
+            QuicCommand::Keyboard(buf) => received.push(buf),
+            _ => panic!("expected only keyboard input"),
+        }
+    }
+    received
+}
+
+#[test]
+fn stopping_while_disconnected_drops_the_queue() {
+    let mut outbox = disconnected(OutboxOptions::default());
+    outbox.send(QuicCommand::Keyboard(vec![1]));
+    assert_eq!(outbox.queued(), 2);
+
+    outbox.shutdown();
+    assert_eq!(outbox.queued(), 0);
+    assert!(!outbox.is_connected());
+}
+
+#[test]
+fn stopping_while_connected_tells_the_worker() {
+    let (sender, mut receiver) = channel();
+    let mut outbox = Outbox::new(sender, OutboxOptions::default());
+    outbox.shutdown();
+    assert!(matches!(receiver.try_recv(), Ok(QuicCommand::Shutdown)));
+    assert!(!outbox.is_connected());
+}
+
+#[test]
+fn a_full_queue_drops_the_oldest_input() {
+    let mut outbox = disconnected(OutboxOptions {
+        capacity: 3,
+        policy: ReconnectPolicy::Flush,
+    });
+    for byte in 1..5 {
+        outbox.send(QuicCommand::Keyboard(vec![byte]));
+    }
+    assert_eq!(outbox.queued(), 3);
+    assert_eq!(outbox.dropped(), 2);
+
+    let (sender, mut receiver) = channel();
+    assert_eq!(outbox.reconnect(sender), 3);
+    assert!(outbox.is_connected());
+    assert_eq!(outbox.queued(), 0);
+    assert_eq!(outbox.dropped(), 0);
+    assert_eq!(
+        keyboard_bytes(&mut receiver),
+        vec![vec![2], vec![3], vec![4]]
+    );
+}
+
+#[test]
+fn queued_moves_are_merged() {
+    let mut outbox = disconnected(OutboxOptions {
+        capacity: 2,
+        policy: ReconnectPolicy::Flush,
+    });
+    for _ in 0..10 {
+        outbox.send(QuicCommand::Move(MouseMove { dx: 1.0, dy: -2.0 }));
+    }
+    assert_eq!(outbox.queued(), 2);
+    assert_eq!(outbox.dropped(), 0);
+
+    let (sender, mut receiver) = channel();
+    outbox.reconnect(sender);
+    assert!(matches!(receiver.try_recv(), Ok(QuicCommand::Keyboard(_))));
+    match receiver.try_recv() {
+        Ok(QuicCommand::Move(moved)) => assert_eq!(
+            moved,
+            MouseMove {
+                dx: 10.0,
+                dy: -20.0
+            }
+        ),
+        _ => panic!("expected the merged move"),
+    }
+}
+
+#[test]
+fn the_discard_policy_sends_nothing_queued() {
+    let mut outbox = disconnected(OutboxOptions {
+        capacity: 8,
+        policy: ReconnectPolicy::Discard,
+    });
+    outbox.send(QuicCommand::Keyboard(vec![1]));
+
+    let (sender, mut receiver) = channel();
+    assert_eq!(outbox.reconnect(sender), 0);
+    assert_eq!(outbox.queued(), 0);
+    outbox.send(QuicCommand::Keyboard(vec![2]));
+    assert_eq!(keyboard_bytes(&mut receiver), vec![vec![2]]);
+}
+
+#[test]
+fn a_new_worker_is_told_input_is_still_held() {
+    let mut outbox = disconnected(OutboxOptions::default());
+    outbox.send(QuicCommand::Holding(true));
+    assert_eq!(outbox.queued(), 1);
+
+    let (sender, mut receiver) = channel();
+    assert_eq!(outbox.reconnect(sender), 1);
+    assert!(matches!(
+        receiver.try_recv(),
+        Ok(QuicCommand::Holding(true))
+    ));
+    assert_eq!(keyboard_bytes(&mut receiver), [vec![0]]);
+}
@@ -0,0 +1,66 @@
+use client::pointer_mode::PointerModeState;
+use shared::PointerMode;
+
+#[test]
+fn nothing_is_asked_while_relative_is_selected() {
+    let state = PointerModeState::new(PointerMode::Relative);
+    assert_eq!(state.request_for_capture(), None);
+    assert_eq!(state.in_effect(), PointerMode::Relative);
+}
+
+#[test]
+fn absolute_applies_at_the_capture_after_the_server_grants_it() {
+    let mut state = PointerModeState::new(PointerMode::Relative);
+    assert!(state.select(PointerMode::Absolute));
+    // Selecting alone changes nothing until capture asks the server.
+    assert_eq!(state.in_effect(), PointerMode::Relative);
+    assert_eq!(state.request_for_capture(), Some(PointerMode::Absolute));
+
+    state.answered(PointerMode::Absolute, Some(PointerMode::Absolute));
+    assert_eq!(state.in_effect(), PointerMode::Absolute);
+    assert_eq!(state.request_for_capture(), None);
+}
+
+#[test]
+fn switching_back_to_relative_is_asked_for_too() {
+    let mut state = PointerModeState::new(PointerMode::Absolute);
+    state.answered(PointerMode::Absolute, Some(PointerMode::Absolute));
+    state.select(PointerMode::Relative);
+    assert_eq!(state.request_for_capture(), Some(PointerMode::Relative));
+    state.answered(PointerMode::Relative, Some(PointerMode::Relative));
+    assert_eq!(state.in_effect(), PointerMode::Relative);
+}
+
+#[test]
+fn a_refusal_stays_relative_and_is_not_asked_again() {
+    let mut state = PointerModeState::new(PointerMode::Absolute);
+    state.answered(PointerMode::Absolute, Some(PointerMode::Relative));
+    assert!(state.absolute_refused());
+    assert_eq!(state.in_effect(), PointerMode::Relative);
+    assert_eq!(state.request_for_capture(), None);
+    // The choice itself is kept, for the next server.
+    assert_eq!(state.selected(), PointerMode::Absolute);
+}
+
+#[test]
+fn a_server_without_pointer_modes_counts_as_a_refusal() {
+    let mut state = PointerModeState::new(PointerMode::Absolute);
+    state.answered(PointerMode::Absolute, None);
+    assert!(state.absolute_refused());
+    assert_eq!(state.in_effect(), PointerMode::Relative);
+}
+
+#[test]
+fn reconnecting_asks_again() {
+    let mut state = PointerModeState::new(PointerMode::Absolute);
+    state.answered(PointerMode::Absolute, Some(PointerMode::Relative));
+    state.reconnected();
+    assert!(!state.absolute_refused());
+    assert_eq!(state.request_for_capture(), Some(PointerMode::Absolute));
+}
+
+#[test]
+fn reselecting_the_same_mode_is_not_a_change() {
+    let mut state = PointerModeState::new(PointerMode::Relative);
+    assert!(!state.select(PointerMode::Relative));
+}
@@ -0,0 +1,16 @@
+use client::raw_debug::take_debug_raw_flag;
+
+fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn the_debug_flag_is_taken_out_of_the_command_line() {
+    let (debug_raw, rest) = take_debug_raw_flag(args(&["client", "--debug-raw", "--verbose"]));
+    assert!(debug_raw);
+    assert_eq!(rest, args(&["client", "--verbose"]));
+
+    let (debug_raw, rest) = take_debug_raw_flag(args(&["client"]));
+    assert!(!debug_raw);
+    assert_eq!(rest, args(&["client"]));
+}
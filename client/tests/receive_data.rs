@@ -0,0 +1,110 @@
+//! Exercises `receive_data`'s limits against a stream whose pacing the test
+//! controls, without a server on the other end.
+
+use std::{
+    collections::VecDeque,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use client::quic::{ReceiveError, quic_runtime, receive_data};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Hands out queued chunks one per read, then either ends the stream or
+/// stalls forever like a peer that never finishes.
+struct FakeStream {
+    chunks: VecDeque<Vec<u8>>,
+    finish: bool,
+}
+
+impl FakeStream {
+    fn finished(chunks: &[&[u8]]) -> Self {
+        Self {
+            chunks: chunks.iter().map(|chunk| chunk.to_vec()).collect(),
+            finish: true,
+        }
+    }
+
+    fn stalled(chunks: &[&[u8]]) -> Self {
+        Self {
+            finish: false,
+            ..Self::finished(chunks)
+        }
+    }
+}
+
+impl AsyncRead for FakeStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.chunks.pop_front() {
+            Some(mut chunk) => {
+                let len = chunk.len().min(buf.remaining());
+                buf.put_slice(&chunk[..len]);
+                if len < chunk.len() {
+                    self.chunks.push_front(chunk.split_off(len));
+                }
+                Poll::Ready(Ok(()))
+            }
+            None if self.finish => Poll::Ready(Ok(())),
+            None => Poll::Pending,
+        }
+    }
+}
+
+const LIMIT: usize = 8;
+const WAIT: Duration = Duration::from_millis(100);
+
+#[test]
+fn small_response_is_read_whole() {
+    let stream = FakeStream::finished(&[b"ab", b"cd"]);
+    let data = quic_runtime()
+        .block_on(receive_data(stream, LIMIT, WAIT))
+        .expect("read failed");
+    assert_eq!(data, b"abcd");
+}
+
+#[test]
+fn response_exactly_at_the_cap_is_accepted() {
+    let stream = FakeStream::finished(&[b"12345678"]);
+    let data = quic_runtime()
+        .block_on(receive_data(stream, LIMIT, WAIT))
+        .expect("read failed");
+    assert_eq!(data.len(), LIMIT);
+}
+
+#[test]
+fn oversized_response_is_rejected() {
+    // Stalls after the oversized data, so only the cap can end the read.
+    let stream = FakeStream::stalled(&[b"12345", b"6789"]);
+    let result = quic_runtime().block_on(receive_data(stream, LIMIT, WAIT));
+    assert!(matches!(
+        result,
+        Err(ReceiveError::TooLarge { limit: LIMIT })
+    ));
+}
+
+#[test]
+fn unfinished_response_times_out() {
+    let stream = FakeStream::stalled(&[b"ab"]);
+    let result = quic_runtime().block_on(receive_data(stream, LIMIT, WAIT));
+    assert!(matches!(result, Err(ReceiveError::TimedOut(after)) if after == WAIT));
+}
+
+#[test]
+#[allow(deprecated)]
+fn deprecated_name_behaves_like_the_new_one() {
+    use client::quic::recieve_data;
+
+    let runtime = quic_runtime();
+    let old = runtime.block_on(recieve_data(FakeStream::finished(&[b"ok"]), LIMIT, WAIT));
+    let new = runtime.block_on(receive_data(FakeStream::finished(&[b"ok"]), LIMIT, WAIT));
+    assert_eq!(old.expect("old name failed"), new.expect("new name failed"));
+
+    let old = runtime.block_on(recieve_data(FakeStream::stalled(&[]), LIMIT, WAIT));
+    assert!(matches!(old, Err(ReceiveError::TimedOut(_))));
+}
@@ -0,0 +1,207 @@
+use std::time::{Duration, Instant};
+
+use client::outbox::{Outbox, OutboxOptions};
+use client::quic::quic_runtime;
+use client::quic_helper_thread::QuicCommand;
+use client::recording::{
+    RecordedCommand, RecordedInput, Recorder, open_recording, read_recording, replay,
+};
+use client::sealed::{MAGIC, Sealer};
+use rdev::{EventType, Key};
+use shared::{
+    MouseMove, SentAt,
+    codec::{Codec, WireFormat},
+};
+use tokio::sync::mpsc::unbounded_channel;
+
+fn key(event: EventType) -> Vec<u8> {
+    rmp_serde::to_vec(&event).expect("failed to serialise")
+}
+
+fn stamp() -> Vec<u8> {
+    rmp_serde::to_vec(&SentAt { micros: 42 }).expect("failed to serialise")
+}
+
+#[test]
+fn recorded_input_reads_back_in_order() {
+    let moved = MouseMove { dx: 3.0, dy: -1.5 };
+    let mut recorder = Recorder::new(Vec::new());
+    recorder
+        .record(&QuicCommand::Keyboard(key(EventType::KeyPress(Key::KeyA))))
+        .unwrap();
+    recorder.record(&QuicCommand::Move(moved)).unwrap();
+    recorder
+        .record(&QuicCommand::Keyboard(key(EventType::KeyRelease(
+            Key::KeyA,
+        ))))
+        .unwrap();
+
+    let recording = read_recording(&recorder.into_inner()).expect("recording should parse");
+    let inputs: Vec<_> = recording.iter().map(|entry| entry.input.clone()).collect();
+    assert_eq!(
+        inputs,
+        [
+            RecordedInput::Keyboard(key(EventType::KeyPress(Key::KeyA))),
+            RecordedInput::Move(moved),
+            RecordedInput::Keyboard(key(EventType::KeyRelease(Key::KeyA))),
+        ]
+    );
+    assert_eq!(recording[0].at_micros, 0);
+    assert!(
+        recording
+            .windows(2)
+            .all(|pair| pair[0].at_micros <= pair[1].at_micros)
+    );
+}
+
+#[test]
+fn latency_stamps_and_shutdowns_are_not_recorded() {
+    let mut recorder = Recorder::new(Vec::new());
+    recorder.record(&QuicCommand::Mouse(stamp())).unwrap();
+    recorder.record(&QuicCommand::Keyboard(stamp())).unwrap();
+    recorder.record(&QuicCommand::Shutdown).unwrap();
+    assert!(recorder.into_inner().is_empty());
+
+    let click = key(EventType::ButtonPress(rdev::Button::Left));
+    assert_eq!(
+        RecordedInput::from_command(&QuicCommand::Mouse(click.clone()), WireFormat::MessagePack),
+        Some(RecordedInput::Mouse(click))
+    );
+}
+
+#[test]
+fn latency_stamps_are_recognised_in_json_too() {
+    let stamp = WireFormat::Json.encode(&SentAt { micros: 42 }).unwrap();
+    let press = WireFormat::Json
+        .encode(&EventType::KeyPress(Key::KeyA))
+        .unwrap();
+    let mut recorder = Recorder::new(Vec::new()).with_format(WireFormat::Json);
+    recorder
+        .record(&QuicCommand::Keyboard(stamp.clone()))
+        .unwrap();
+    recorder
+        .record(&QuicCommand::Keyboard(press.clone()))
+        .unwrap();
+
+    let recording = read_recording(&recorder.into_inner()).unwrap();
+    assert_eq!(recording.len(), 1);
+    assert_eq!(recording[0].input, RecordedInput::Keyboard(press));
+    // Read as MessagePack, the JSON stamp is just bytes to keep.
+    assert_eq!(
+        RecordedInput::from_command(
+            &QuicCommand::Keyboard(stamp.clone()),
+            WireFormat::MessagePack
+        ),
+        Some(RecordedInput::Keyboard(stamp))
+    );
+}
+
+#[test]
+fn a_truncated_recording_is_refused() {
+    let mut recorder = Recorder::new(Vec::new());
+    recorder
+        .record(&QuicCommand::Keyboard(key(EventType::KeyPress(Key::KeyA))))
+        .unwrap();
+    let mut data = recorder.into_inner();
+    data.pop();
+    assert!(read_recording(&data).is_err());
+    assert_eq!(read_recording(&[]).unwrap(), Vec::new());
+}
+
+#[test]
+fn a_sealed_recording_opens_with_the_same_passphrase_only() {
+    let sealer = Sealer::Passphrase("correct horse".to_string());
+    let press = key(EventType::KeyPress(Key::KeyA));
+    let mut recorder = Recorder::sealed(Vec::new(), &sealer).unwrap();
+    recorder
+        .record(&QuicCommand::Keyboard(press.clone()))
+        .unwrap();
+    recorder
+        .record(&QuicCommand::Move(MouseMove { dx: 1.0, dy: 0.0 }))
+        .unwrap();
+    let data = recorder.into_inner();
+
+    assert!(data.starts_with(MAGIC));
+    assert!(!data.windows(press.len()).any(|window| window == press));
+    let recording = open_recording(&data, &sealer).expect("recording should open");
+    assert_eq!(recording.len(), 2);
+    assert_eq!(recording[0].input, RecordedInput::Keyboard(press));
+
+    assert!(open_recording(&data, &Sealer::Plain).is_err());
+    assert!(open_recording(&data, &Sealer::Passphrase("battery staple".to_string())).is_err());
+    assert!(open_recording(&data[..data.len() - 1], &sealer).is_err());
+}
+
+#[test]
+fn a_plain_sealer_records_as_before() {
+    let mut recorder = Recorder::sealed(Vec::new(), &Sealer::Plain).unwrap();
+    recorder
+        .record(&QuicCommand::Keyboard(key(EventType::KeyPress(Key::KeyA))))
+        .unwrap();
+    let data = recorder.into_inner();
+    assert_eq!(
+        open_recording(&data, &Sealer::Key([7; 32])).unwrap(),
+        read_recording(&data).unwrap()
+    );
+}
+
+#[test]
+fn replay_keeps_the_recorded_pacing() {
+    let press = key(EventType::KeyPress(Key::KeyB));
+    let release = key(EventType::KeyRelease(Key::KeyB));
+    let recording = vec![
+        RecordedCommand {
+            at_micros: 0,
+            input: RecordedInput::Keyboard(press.clone()),
+        },
+        RecordedCommand {
+            at_micros: 80_000,
+            input: RecordedInput::Keyboard(release.clone()),
+        },
+    ];
+
+    let (sender, mut receiver) = unbounded_channel();
+    let started = Instant::now();
+    let sent = quic_runtime().block_on(replay(recording, &sender));
+    assert_eq!(sent, 2);
+    assert!(started.elapsed() >= Duration::from_millis(80));
+
+    let mut received = Vec::new();
+    while let Ok(command) = receiver.try_recv() {
+        match command {
+            QuicCommand::Keyboard(buf) => received.push(buf),
+            _ => panic!("only key events were recorded"),
+        }
+    }
+    assert_eq!(received, [press, release]);
+}
+
+#[test]
+fn replay_stops_once_the_worker_is_gone() {
+    let recording = vec![RecordedCommand {
+        at_micros: 0,
+        input: RecordedInput::Move(MouseMove { dx: 1.0, dy: 1.0 }),
+    }];
+    let (sender, receiver) = unbounded_channel();
+    drop(receiver);
+    assert_eq!(quic_runtime().block_on(replay(recording, &sender)), 0);
+}
+
+#[test]
+fn the_outbox_records_input_even_while_disconnected() {
+    let (sender, receiver) = unbounded_channel();
+    drop(receiver);
+    let path = std::env::temp_dir().join(format!("quicinput-recording-{}", std::process::id()));
+    let recorder = Recorder::create(&path).expect("failed to create recording");
+    let mut outbox = Outbox::new(sender, OutboxOptions::default()).with_recorder(recorder);
+
+    let press = key(EventType::KeyPress(Key::KeyC));
+    outbox.send(QuicCommand::Keyboard(press.clone()));
+    assert!(!outbox.is_connected());
+    assert_eq!(outbox.queued(), 1);
+
+    let recording = client::recording::load_recording(&path).expect("recording should be readable");
+    std::fs::remove_file(&path).ok();
+    assert_eq!(recording.len(), 1);
+    assert_eq!(recording[0].input, RecordedInput::Keyboard(press));
+}
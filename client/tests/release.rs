@@ -0,0 +1,56 @@
+use client::release::{SWEEP_BUTTONS, SWEEP_MODIFIERS, release_sweep};
+use rdev::{Button, EventType, Key};
+
+#[test]
+fn sweep_covers_standard_modifiers_and_buttons() {
+    let sweep = release_sweep(&[]);
+    for key in [
+        Key::ShiftLeft,
+        Key::ShiftRight,
+        Key::ControlLeft,
+        Key::ControlRight,
+        Key::Alt,
+        Key::AltGr,
+        Key::MetaLeft,
+        Key::MetaRight,
+    ] {
+        assert!(
+            sweep.contains(&EventType::KeyRelease(key)),
+            "{key:?} missing"
+        );
+    }
+    for button in [Button::Left, Button::Right, Button::Middle] {
+        assert!(
+            sweep.contains(&EventType::ButtonRelease(button)),
+            "{button:?} missing"
+        );
+    }
+    assert_eq!(sweep.len(), SWEEP_MODIFIERS.len() + SWEEP_BUTTONS.len());
+}
+
+#[test]
+fn sweep_only_releases() {
+    let sweep = release_sweep(&[Key::KeyW, Key::Space]);
+    assert!(sweep.iter().all(|event| matches!(
+        event,
+        EventType::KeyRelease(_) | EventType::ButtonRelease(_)
+    )));
+}
+
+#[test]
+fn held_keys_are_released_first_and_once() {
+    let sweep = release_sweep(&[Key::KeyW, Key::ShiftLeft, Key::KeyW]);
+    assert_eq!(
+        sweep[..2],
+        [
+            EventType::KeyRelease(Key::KeyW),
+            EventType::KeyRelease(Key::ShiftLeft),
+        ]
+    );
+    let shift_releases = sweep
+        .iter()
+        .filter(|event| **event == EventType::KeyRelease(Key::ShiftLeft))
+        .count();
+    assert_eq!(shift_releases, 1);
+    assert_eq!(sweep.len(), 1 + SWEEP_MODIFIERS.len() + SWEEP_BUTTONS.len());
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use client::quic::{ConnectError, RetryPolicy, is_retryable};
+use quinn::ConnectionError;
+
+#[test]
+fn default_policy_makes_three_attempts() {
+    assert_eq!(RetryPolicy::default().attempts, 3);
+}
+
+#[test]
+fn backoff_doubles_per_retry() {
+    let policy = RetryPolicy {
+        attempts: 4,
+        backoff: Duration::from_millis(100),
+    };
+    assert_eq!(policy.delay_before(1), Duration::from_millis(100));
+    assert_eq!(policy.delay_before(2), Duration::from_millis(200));
+    assert_eq!(policy.delay_before(3), Duration::from_millis(400));
+}
+
+#[test]
+fn only_transient_transport_errors_are_retried() {
+    assert!(!is_retryable(&ConnectionError::TimedOut));
+    assert!(is_retryable(&ConnectionError::Reset));
+    assert!(!is_retryable(&ConnectionError::VersionMismatch));
+    assert!(!is_retryable(&ConnectionError::LocallyClosed));
+    assert!(!is_retryable(&ConnectionError::CidsExhausted));
+}
+
+#[test]
+fn a_connect_that_timed_out_is_not_retried() {
+    assert!(!ConnectError::TimedOut(Duration::from_secs(5)).is_retryable());
+    assert!(ConnectError::Connection(ConnectionError::Reset).is_retryable());
+}
@@ -0,0 +1,108 @@
+use std::fs;
+
+use client::sealed::{MAGIC, Sealer};
+use client::settings::ClientSettings;
+
+const SETTINGS: &[u8] = b"free_cursor = true\n";
+
+#[test]
+fn a_key_seals_and_opens_again() {
+    let sealer = Sealer::Key([7; 32]);
+    let sealed = sealer.seal(SETTINGS).unwrap();
+    assert!(sealed.starts_with(MAGIC));
+    assert!(
+        !sealed
+            .windows(SETTINGS.len())
+            .any(|window| window == SETTINGS)
+    );
+    assert_eq!(sealer.open(&sealed).unwrap(), SETTINGS);
+    // A fresh nonce each time.
+    assert_ne!(sealer.seal(SETTINGS).unwrap(), sealed);
+}
+
+#[test]
+fn a_passphrase_seals_and_opens_again() {
+    let sealer = Sealer::Passphrase("correct horse".to_string());
+    let sealed = sealer.seal(SETTINGS).unwrap();
+    assert_eq!(sealer.open(&sealed).unwrap(), SETTINGS);
+
+    let wrong = Sealer::Passphrase("battery staple".to_string());
+    assert!(wrong.open(&sealed).is_err());
+}
+
+#[test]
+fn sealed_files_are_refused_without_the_right_kind_of_key() {
+    let sealed = Sealer::Key([7; 32]).seal(SETTINGS).unwrap();
+    assert!(Sealer::Plain.open(&sealed).is_err());
+    assert!(Sealer::Key([8; 32]).open(&sealed).is_err());
+    assert!(Sealer::Passphrase("key".to_string()).open(&sealed).is_err());
+    // Cut short, it can't be opened either.
+    assert!(
+        Sealer::Key([7; 32])
+            .open(&sealed[..MAGIC.len() + 4])
+            .is_err()
+    );
+}
+
+#[test]
+fn plaintext_files_are_still_read() {
+    assert_eq!(Sealer::Key([7; 32]).open(SETTINGS).unwrap(), SETTINGS);
+    assert_eq!(Sealer::Plain.seal(SETTINGS).unwrap(), SETTINGS);
+}
+
+#[test]
+fn without_a_keyring_config_is_saved_in_plaintext() {
+    let sealer = Sealer::keyring_or_plain(Err("no keyring in this session".into()));
+    assert!(sealer.is_plain());
+    assert_eq!(sealer.seal(SETTINGS).unwrap(), SETTINGS);
+
+    assert!(!Sealer::keyring_or_plain(Ok([7; 32])).is_plain());
+}
+
+#[test]
+fn settings_load_and_save_through_a_sealer() {
+    let path = std::env::temp_dir()
+        .join(format!("quicinput-sealed-{}", std::process::id()))
+        .join("client.toml");
+    let sealer = Sealer::Key([7; 32]);
+    let settings = ClientSettings {
+        free_cursor: true,
+        ..ClientSettings::default()
+    };
+    settings.save_with(&path, &sealer).unwrap();
+
+    assert!(fs::read(&path).unwrap().starts_with(MAGIC));
+    assert_eq!(ClientSettings::load_with(&path, &sealer), settings);
+    // Without the key the defaults are used, as for any unreadable file.
+    assert_eq!(
+        ClientSettings::load_with(&path, &Sealer::Plain),
+        ClientSettings::default()
+    );
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn a_sealed_file_is_not_saved_over_without_its_key() {
+    let path = std::env::temp_dir()
+        .join(format!("quicinput-sealed-guard-{}", std::process::id()))
+        .join("client.toml");
+    let sealer = Sealer::Key([7; 32]);
+    let settings = ClientSettings {
+        free_cursor: true,
+        ..ClientSettings::default()
+    };
+    settings.save_with(&path, &sealer).unwrap();
+    let saved = fs::read(&path).unwrap();
+
+    for other in [Sealer::Plain, Sealer::Key([8; 32])] {
+        assert!(ClientSettings::default().save_with(&path, &other).is_err());
+        assert_eq!(fs::read(&path).unwrap(), saved);
+    }
+    // The key it was sealed with replaces it as usual.
+    ClientSettings::default().save_with(&path, &sealer).unwrap();
+    assert_eq!(
+        ClientSettings::load_with(&path, &sealer),
+        ClientSettings::default()
+    );
+    let _ = fs::remove_file(&path);
+}
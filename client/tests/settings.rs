@@ -0,0 +1,78 @@
+use std::fs;
+
+use client::{momentary::CaptureMode, settings::ClientSettings};
+use rdev::Key;
+use shared::{PointerMode, system_keys::DEFAULT_SYSTEM_KEYS};
+
+fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("quicinput-settings-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn the_pointer_mode_survives_a_save_and_load() {
+    let path = scratch_dir("roundtrip").join("nested").join("client.toml");
+    let settings = ClientSettings {
+        pointer_mode: PointerMode::Absolute,
+        system_keys: vec![Key::PrintScreen, Key::ScrollLock],
+        capture_mode: CaptureMode::Hold,
+        hold_key: Key::Pause,
+        skip_capture_notice: true,
+        free_cursor: true,
+        connect_attempts: 1,
+    };
+    settings.save(&path).expect("failed to save settings");
+    assert_eq!(ClientSettings::load(&path), settings);
+}
+
+#[test]
+fn system_keys_default_when_left_out() {
+    let path = scratch_dir("system-keys").join("client.toml");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "pointer_mode = \"Absolute\"\n").unwrap();
+    let settings = ClientSettings::load(&path);
+    assert_eq!(settings.pointer_mode, PointerMode::Absolute);
+    assert_eq!(settings.system_keys, DEFAULT_SYSTEM_KEYS.to_vec());
+    assert_eq!(settings.hold_trigger(), None);
+}
+
+#[test]
+fn hold_mode_waits_for_the_configured_key() {
+    let path = scratch_dir("hold").join("client.toml");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "capture_mode = \"hold\"\nhold_key = \"Pause\"\n").unwrap();
+    assert_eq!(ClientSettings::load(&path).hold_trigger(), Some(Key::Pause));
+
+    fs::write(&path, "capture_mode = \"hold\"\n").unwrap();
+    assert_eq!(
+        ClientSettings::load(&path).hold_trigger(),
+        Some(Key::ScrollLock)
+    );
+}
+
+#[test]
+fn free_cursor_is_off_unless_turned_on() {
+    let path = scratch_dir("free-cursor").join("client.toml");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "pointer_mode = \"Relative\"\n").unwrap();
+    assert!(!ClientSettings::load(&path).free_cursor);
+
+    fs::write(&path, "free_cursor = true\n").unwrap();
+    assert!(ClientSettings::load(&path).free_cursor);
+}
+
+#[test]
+fn missing_or_invalid_files_load_the_defaults() {
+    let dir = scratch_dir("defaults");
+    assert_eq!(
+        ClientSettings::load(&dir.join("absent.toml")),
+        ClientSettings::default()
+    );
+
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("client.toml");
+    fs::write(&path, "pointer_mode = 3").unwrap();
+    assert_eq!(ClientSettings::load(&path), ClientSettings::default());
+}
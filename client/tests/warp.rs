@@ -0,0 +1,72 @@
+use client::warp::Preview;
+use shared::{AbsoluteMove, DisplayInfo};
+
+fn display(width: u32, height: u32) -> DisplayInfo {
+    DisplayInfo {
+        name: "remote".to_string(),
+        x: 0,
+        y: 0,
+        width,
+        height,
+        is_primary: true,
+    }
+}
+
+#[test]
+fn a_wide_display_is_centred_vertically_in_a_square_widget() {
+    let preview = Preview::fit(&display(1920, 1080), 320.0, 320.0).unwrap();
+    assert_eq!(
+        preview,
+        Preview {
+            left: 0.0,
+            top: 70.0,
+            width: 320.0,
+            height: 180.0,
+        }
+    );
+}
+
+#[test]
+fn a_tall_display_is_centred_horizontally() {
+    let preview = Preview::fit(&display(1080, 1920), 320.0, 320.0).unwrap();
+    assert_eq!(preview.top, 0.0);
+    assert_eq!(preview.height, 320.0);
+    assert_eq!(preview.width, 180.0);
+    assert_eq!(preview.left, 70.0);
+}
+
+#[test]
+fn nothing_fits_without_area() {
+    assert_eq!(Preview::fit(&display(0, 1080), 320.0, 180.0), None);
+    assert_eq!(Preview::fit(&display(1920, 1080), 0.0, 180.0), None);
+}
+
+#[test]
+fn clicks_map_to_the_same_spot_on_the_display() {
+    let preview = Preview::fit(&display(1920, 1080), 321.0, 181.0).unwrap();
+    assert_eq!(
+        preview.absolute_at(0.0, 0.0),
+        AbsoluteMove { x: 0.0, y: 0.0 }
+    );
+    assert_eq!(
+        preview.absolute_at(160.0, 90.0),
+        AbsoluteMove { x: 0.5, y: 0.5 }
+    );
+    assert_eq!(
+        preview.absolute_at(320.0, 180.0),
+        AbsoluteMove { x: 1.0, y: 1.0 }
+    );
+}
+
+#[test]
+fn clicks_beside_the_display_are_clamped_to_its_edges() {
+    let preview = Preview::fit(&display(1920, 1080), 320.0, 320.0).unwrap();
+    assert_eq!(
+        preview.absolute_at(-5.0, 10.0),
+        AbsoluteMove { x: 0.0, y: 0.0 }
+    );
+    assert_eq!(
+        preview.absolute_at(400.0, 319.0),
+        AbsoluteMove { x: 1.0, y: 1.0 }
+    );
+}
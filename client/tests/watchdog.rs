@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use client::watchdog::Heartbeat;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const SECOND: u64 = 1_000_000;
+
+#[test]
+fn a_heartbeat_that_never_pinged_is_not_stale() {
+    let heartbeat = Heartbeat::new();
+    assert_eq!(heartbeat.silent_for(100 * SECOND), None);
+    assert_eq!(heartbeat.stale_at(100 * SECOND, TIMEOUT), None);
+}
+
+#[test]
+fn silence_up_to_the_timeout_is_tolerated() {
+    let heartbeat = Heartbeat::new();
+    heartbeat.ping_at(10 * SECOND);
+    assert_eq!(heartbeat.stale_at(10 * SECOND, TIMEOUT), None);
+    assert_eq!(heartbeat.stale_at(15 * SECOND, TIMEOUT), None);
+    assert_eq!(
+        heartbeat.stale_at(15 * SECOND + 1, TIMEOUT),
+        Some(Duration::from_micros(5 * SECOND + 1))
+    );
+}
+
+#[test]
+fn a_ping_resets_the_silence() {
+    let heartbeat = Heartbeat::new();
+    heartbeat.ping_at(SECOND);
+    assert!(heartbeat.stale_at(7 * SECOND, TIMEOUT).is_some());
+    heartbeat.ping_at(7 * SECOND);
+    assert_eq!(heartbeat.stale_at(8 * SECOND, TIMEOUT), None);
+    assert_eq!(
+        heartbeat.silent_for(8 * SECOND),
+        Some(Duration::from_secs(1))
+    );
+}
+
+#[test]
+fn a_ping_at_time_zero_still_counts() {
+    let heartbeat = Heartbeat::new();
+    heartbeat.ping_at(0);
+    assert!(heartbeat.silent_for(SECOND).is_some());
+}
+
+#[test]
+fn a_clock_reading_before_the_last_ping_is_no_silence() {
+    let heartbeat = Heartbeat::new();
+    heartbeat.ping_at(10 * SECOND);
+    assert_eq!(heartbeat.silent_for(9 * SECOND), Some(Duration::ZERO));
+}
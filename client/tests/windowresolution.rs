@@ -0,0 +1,107 @@
+#![cfg(feature = "gui")]
+
+use client::windowresolution::{
+    MonitorChoice, MonitorGeometry, active_monitor_index, list_monitors, primary_monitor_index,
+    select_monitor,
+};
+
+fn monitor(index: usize, name: &str, x: i32, is_primary: bool) -> MonitorGeometry {
+    MonitorGeometry {
+        index,
+        name: name.to_string(),
+        x,
+        y: 0,
+        width: 1920,
+        height: 1080,
+        is_primary,
+    }
+}
+
+fn desk() -> Vec<MonitorGeometry> {
+    vec![
+        monitor(0, "DP-1", 0, false),
+        monitor(1, "HDMI-1", 1920, true),
+        monitor(2, "DP-2", 3840, false),
+    ]
+}
+
+#[test]
+fn listed_monitors_are_numbered_in_order() {
+    for (position, monitor) in list_monitors().iter().enumerate() {
+        assert_eq!(monitor.index, position);
+        assert!(!monitor.name.is_empty());
+    }
+}
+
+#[test]
+fn labels_count_from_one_and_mark_the_primary() {
+    let monitors = desk();
+    assert_eq!(monitors[0].label(), "1: DP-1 1920x1080");
+    assert_eq!(monitors[1].label(), "2: HDMI-1 1920x1080 (primary)");
+}
+
+#[test]
+fn the_primary_is_found_wherever_it_is_listed() {
+    assert_eq!(primary_monitor_index(&desk()), 1);
+}
+
+#[test]
+fn without_a_primary_the_first_listed_stands_in() {
+    let monitors = vec![
+        monitor(0, "DP-1", 0, false),
+        monitor(1, "DP-2", 1920, false),
+    ];
+    assert_eq!(primary_monitor_index(&monitors), 0);
+}
+
+#[test]
+fn a_monitor_is_chosen_by_name() {
+    let chosen = select_monitor(&desk(), &MonitorChoice::Named("DP-2".to_string()));
+    assert_eq!(chosen.map(|monitor| monitor.index), Some(2));
+}
+
+#[test]
+fn choosing_the_primary_gets_it() {
+    let chosen = select_monitor(&desk(), &MonitorChoice::Primary);
+    assert_eq!(
+        chosen.map(|monitor| monitor.name),
+        Some("HDMI-1".to_string())
+    );
+}
+
+#[test]
+fn an_unplugged_choice_falls_back_to_the_primary() {
+    let chosen = select_monitor(&desk(), &MonitorChoice::Named("DP-9".to_string()));
+    assert_eq!(
+        chosen.map(|monitor| monitor.name),
+        Some("HDMI-1".to_string())
+    );
+}
+
+#[test]
+fn nothing_is_chosen_without_monitors() {
+    assert!(select_monitor(&[], &MonitorChoice::Primary).is_none());
+    assert!(select_monitor(&[], &MonitorChoice::Named("DP-1".to_string())).is_none());
+}
+
+#[test]
+fn the_active_monitor_is_the_one_under_the_cursor() {
+    assert_eq!(active_monitor_index(&desk(), Some((100.0, 500.0))), Some(0));
+    assert_eq!(active_monitor_index(&desk(), Some((4000.0, 10.0))), Some(2));
+}
+
+#[test]
+fn a_cursor_off_every_monitor_or_unknown_falls_back_to_the_primary() {
+    assert_eq!(active_monitor_index(&desk(), Some((-50.0, 500.0))), Some(1));
+    assert_eq!(
+        active_monitor_index(&desk(), Some((100.0, 2000.0))),
+        Some(1)
+    );
+    assert_eq!(active_monitor_index(&desk(), None), Some(1));
+}
+
+#[test]
+fn there_is_no_active_monitor_without_monitors() {
+    assert_eq!(active_monitor_index(&[], Some((0.0, 0.0))), None);
+    assert_eq!(active_monitor_index(&[], None), None);
+}
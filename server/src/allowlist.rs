@@ -0,0 +1,153 @@
+//! Which addresses may connect. The config's `allowed_peers` lists single
+//! addresses (`192.168.1.20`, `::1`) or whole networks in CIDR notation
+//! (`192.168.1.0/24`, `fd00::/8`); a peer matching none of them is refused
+//! with QUIC's `CONNECTION_REFUSED` before its handshake starts.
+//! The address checked is the one its packets come from, so this narrows
+//! who can reach the server rather than proving who is connecting.
+
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// An address, or every address in a network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PeerRange {
+    /// The network's first address; host bits are cleared on parsing.
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl PeerRange {
+    /// The addresses sharing `address`'s first `prefix` bits, or `None` if
+    /// `prefix` is longer than the address.
+    pub fn new(address: IpAddr, prefix: u8) -> Option<Self> {
+        let address = address.to_canonical();
+        let network = match address {
+            IpAddr::V4(v4) if prefix <= 32 => {
+                IpAddr::V4(Ipv4Addr::from_bits(v4.to_bits() & v4_mask(prefix)))
+            }
+            IpAddr::V6(v6) if prefix <= 128 => {
+                IpAddr::V6(Ipv6Addr::from_bits(v6.to_bits() & v6_mask(prefix)))
+            }
+            _ => return None,
+        };
+        Some(Self { network, prefix })
+    }
+
+    pub fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Whether `peer` is in the range. IPv4 peers reaching a dual-stack
+    /// socket arrive as IPv4-mapped IPv6 addresses and match IPv4 ranges.
+    pub fn contains(&self, peer: IpAddr) -> bool {
+        match (self.network, peer.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(peer)) => {
+                peer.to_bits() & v4_mask(self.prefix) == network.to_bits()
+            }
+            (IpAddr::V6(network), IpAddr::V6(peer)) => {
+                peer.to_bits() & v6_mask(self.prefix) == network.to_bits()
+            }
+            _ => false,
+        }
+    }
+
+    fn is_single_address(&self) -> bool {
+        match self.network {
+            IpAddr::V4(_) => self.prefix == 32,
+            IpAddr::V6(_) => self.prefix == 128,
+        }
+    }
+}
+
+fn v4_mask(prefix: u8) -> u32 {
+    u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0)
+}
+
+fn v6_mask(prefix: u8) -> u128 {
+    u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0)
+}
+
+impl From<IpAddr> for PeerRange {
+    fn from(address: IpAddr) -> Self {
+        let address = address.to_canonical();
+        let prefix = if address.is_ipv4() { 32 } else { 128 };
+        Self {
+            network: address,
+            prefix,
+        }
+    }
+}
+
+impl FromStr for PeerRange {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let Some((address, prefix)) = value.split_once('/') else {
+            return value
+                .parse::<IpAddr>()
+                .map(Self::from)
+                .map_err(|_| format!("'{value}' is not an IP address or CIDR network"));
+        };
+        let address: IpAddr = address
+            .parse()
+            .map_err(|_| format!("'{value}' is not an IP address or CIDR network"))?;
+        let prefix: u8 = prefix
+            .parse()
+            .map_err(|_| format!("'{value}' has no valid prefix length after the '/'"))?;
+        Self::new(address, prefix)
+            .ok_or_else(|| format!("'{value}' has a prefix longer than the address"))
+    }
+}
+
+impl TryFrom<String> for PeerRange {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<PeerRange> for String {
+    fn from(range: PeerRange) -> Self {
+        range.to_string()
+    }
+}
+
+impl fmt::Display for PeerRange {
+    /// Single addresses without a prefix, networks in CIDR notation.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_single_address() {
+            write!(f, "{}", self.network)
+        } else {
+            write!(f, "{}/{}", self.network, self.prefix)
+        }
+    }
+}
+
+/// Whether any of `ranges` contains `peer`.
+pub fn is_allowed(ranges: &[PeerRange], peer: IpAddr) -> bool {
+    ranges.iter().any(|range| range.contains(peer))
+}
+
+/// `ranges` as a comma-separated list, for the startup log.
+pub fn describe(ranges: &[PeerRange]) -> String {
+    if ranges.is_empty() {
+        return "nowhere".to_string();
+    }
+    ranges
+        .iter()
+        .map(PeerRange::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
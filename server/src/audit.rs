@@ -0,0 +1,211 @@
+//! Optional on-disk record of every input the server decodes, for
+//! deployments that need to know who typed what and when.
+//!
+//! One line per input: seconds since the Unix epoch, the client's address,
+//! then the input in the [`shared::script`] line format, e.g.
+//!
+//! ```text
+//! 1760000000.125 192.168.1.20:51234 key press KeyA
+//! ```
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rdev::EventType;
+use shared::{AbsoluteMove, Gesture, extra_keys::ExtraKeyInput, raw_keys::RawKeyInput, script};
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+
+use crate::framing::Frame;
+
+/// Default size a log file may reach before it is rotated.
+pub const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Rotated files kept next to the live one, as `<path>.1` (newest) up to
+/// `<path>.KEPT_FILES`; anything older is deleted.
+pub const KEPT_FILES: usize = 3;
+
+/// Lines that may wait for the writer before new ones are dropped, so a
+/// slow disk can never hold up injection.
+const AUDIT_BACKLOG: usize = 4096;
+
+/// What replaces key presses, releases and typed characters when redacted.
+pub const REDACTED: &str = "[redacted]";
+
+#[derive(Clone, Debug)]
+pub struct AuditOptions {
+    pub path: PathBuf,
+    pub max_bytes: u64,
+    /// Log key events, extra and raw keys and characters as [`REDACTED`] instead of what was typed.
+    pub redact_keys: bool,
+}
+
+/// Handle for appending to the audit log from any stream. Cheap to clone;
+/// the file is written by a background task.
+#[derive(Clone)]
+pub struct AuditLog {
+    sender: Sender<String>,
+    redact_keys: bool,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AuditLog {
+    /// Opens the log and starts its writer. Must be called from within a
+    /// tokio runtime.
+    pub fn start(options: AuditOptions) -> io::Result<Self> {
+        let file = RotatingFile::open(&options.path, options.max_bytes)?;
+        let (sender, receiver) = mpsc::channel(AUDIT_BACKLOG);
+        tokio::task::spawn_blocking(move || write_lines(receiver, file));
+        println!(
+            "[server] auditing input to {}{}",
+            options.path.display(),
+            if options.redact_keys {
+                " with keys redacted"
+            } else {
+                ""
+            }
+        );
+        Ok(Self {
+            sender,
+            redact_keys: options.redact_keys,
+            dropped: Arc::default(),
+        })
+    }
+
+    /// Queues `frame` from `peer` for the log. Never waits: if the writer
+    /// has fallen too far behind, the line is dropped and counted.
+    pub fn record(&self, peer: SocketAddr, frame: &Frame) {
+        let Some(line) = audit_line(SystemTime::now(), peer, frame, self.redact_keys) else {
+            return;
+        };
+        match self.sender.try_send(line) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if dropped == 1 || dropped.is_multiple_of(1000) {
+                    eprintln!("[server] audit log falling behind; {dropped} lines dropped");
+                }
+            }
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+fn write_lines(mut receiver: Receiver<String>, mut file: RotatingFile) {
+    while let Some(line) = receiver.blocking_recv() {
+        if let Err(err) = file.write_line(&line) {
+            eprintln!("[server] failed to write audit log: {err}");
+        }
+    }
+}
+
+/// The log line for `frame`, or `None` for frames that aren't input.
+pub fn audit_line(
+    at: SystemTime,
+    peer: SocketAddr,
+    frame: &Frame,
+    redact_keys: bool,
+) -> Option<String> {
+    let entry = match frame {
+        Frame::Mouse(mouse_move) => script::format_move(mouse_move),
+        Frame::Event(EventType::KeyPress(_) | EventType::KeyRelease(_)) if redact_keys => {
+            REDACTED.to_string()
+        }
+        Frame::Event(event_type) => script::format_event(event_type),
+        Frame::ExtraKey(_) if redact_keys => REDACTED.to_string(),
+        Frame::ExtraKey(ExtraKeyInput { key, pressed }) => {
+            format!("extra-key {} {key:?}", if *pressed { "press" } else { "release" })
+        }
+        Frame::RawKey(_) if redact_keys => REDACTED.to_string(),
+        Frame::RawKey(RawKeyInput { code, pressed }) => {
+            format!("raw-key {} {code}", if *pressed { "press" } else { "release" })
+        }
+        Frame::Char(_) if redact_keys => REDACTED.to_string(),
+        Frame::Char(ch) => format!("char {ch:?}"),
+        Frame::Gesture(Gesture::Scroll { dx, dy }) => format!("gesture scroll {dx} {dy}"),
+        Frame::Gesture(Gesture::Pinch { scale }) => format!("gesture pinch {scale}"),
+        Frame::Absolute(AbsoluteMove { x, y }) => format!("absolute {x} {y}"),
+        Frame::Combo(combo) => format!("combo {}", combo.describe()),
+        Frame::Edge(_)
+        | Frame::SentAt(_)
+        | Frame::Seq(_)
+        | Frame::DoubleClick(_)
+        | Frame::KeepHeld
+        | Frame::Unknown(_) => return None,
+    };
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Some(format!(
+        "{}.{:03} {peer} {entry}",
+        since_epoch.as_secs(),
+        since_epoch.subsec_millis()
+    ))
+}
+
+/// An append-only file that is rotated once it would grow past `max_bytes`.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    file: File,
+    len: u64,
+}
+
+impl RotatingFile {
+    /// Opens `path` for appending, keeping whatever it already holds.
+    pub fn open(path: &Path, max_bytes: u64) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            file,
+            len,
+        })
+    }
+
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let size = line.len() as u64 + 1;
+        // A line longer than the limit still gets a file of its own.
+        if self.len > 0 && self.len + size > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(format!("{line}\n").as_bytes())?;
+        self.len += size;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let oldest = rotated_path(&self.path, KEPT_FILES);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for index in (1..KEPT_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+/// Where the `index`th most recent rotation of `path` is kept.
+pub fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{index}"));
+    PathBuf::from(rotated)
+}
@@ -0,0 +1,133 @@
+//! Telling clients when this machine can't take their input, e.g. because
+//! its screen is locked, so they stop typing into the lock screen. An
+//! [`AvailabilitySource`] is polled every [`AVAILABILITY_POLL_INTERVAL`] and
+//! each change is passed on to every client watching. Off unless enabled,
+//! as how to tell depends on the desktop; only systemd-logind sessions are
+//! understood so far.
+
+use std::{process::Command, sync::Arc, time::Duration};
+
+use shared::{ControlResponse, UnavailableReason};
+use tokio::sync::watch;
+
+/// How often the source is asked.
+pub const AVAILABILITY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Whether this machine can take input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Availability {
+    #[default]
+    Available,
+    Unavailable(UnavailableReason),
+}
+
+impl Availability {
+    /// What a watching client is sent.
+    pub fn response(self) -> ControlResponse {
+        match self {
+            Availability::Available => ControlResponse::RemoteAvailable,
+            Availability::Unavailable(reason) => ControlResponse::RemoteUnavailable(reason),
+        }
+    }
+}
+
+/// Where the server learns whether it can take input.
+pub trait AvailabilitySource: Send + Sync {
+    /// `None` when it can't tell right now; the last answer then stands.
+    fn availability(&self) -> Option<Availability>;
+}
+
+/// The session's lock hint from systemd-logind, read with `loginctl`. Looks at `$XDG_SESSION_ID` when set, otherwise at the
+/// session logind picks for the server's user.
+#[derive(Debug, Default)]
+pub struct LogindAvailability;
+
+impl AvailabilitySource for LogindAvailability {
+    fn availability(&self) -> Option<Availability> {
+        let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+        let output = Command::new("loginctl")
+            .args(["show-session", &session, "-p", "LockedHint"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        parse_session_hints(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// Reads `loginctl show-session` output; `None` without a `LockedHint`.
+///
+/// `IdleHint` is ignored: pausing an idle session would hold back the very
+/// input that makes it active again, so it would never resume.
+pub fn parse_session_hints(output: &str) -> Option<Availability> {
+    output
+        .lines()
+        .find_map(|line| match line.trim().split_once('=') {
+            Some(("LockedHint", value)) => Some(value == "yes"),
+            _ => None,
+        })
+        .map(|locked| {
+            if locked {
+                Availability::Unavailable(UnavailableReason::ScreenLocked)
+            } else {
+                Availability::Available
+            }
+        })
+}
+
+/// A state set by hand, for tests and dry runs.
+#[derive(Debug, Default)]
+pub struct FakeAvailability(std::sync::Mutex<Availability>);
+
+impl FakeAvailability {
+    pub fn set(&self, availability: Availability) {
+        *self.0.lock().expect("availability mutex poisoned") = availability;
+    }
+}
+
+impl AvailabilitySource for FakeAvailability {
+    fn availability(&self) -> Option<Availability> {
+        Some(*self.0.lock().expect("availability mutex poisoned"))
+    }
+}
+
+/// Polls `source` every `interval` from a task of its own, publishing each
+/// change. Starts out available; the task ends once every receiver is gone.
+pub fn watch_availability(
+    source: Arc<dyn AvailabilitySource>,
+    interval: Duration,
+) -> watch::Receiver<Availability> {
+    let (sender, receiver) = watch::channel(Availability::Available);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if sender.is_closed() {
+                return;
+            }
+            // `loginctl` blocks, so it is kept off the runtime's workers.
+            let source = Arc::clone(&source);
+            let Ok(Some(now)) = tokio::task::spawn_blocking(move || source.availability()).await
+            else {
+                continue;
+            };
+            sender.send_if_modified(|current| {
+                if *current == now {
+                    return false;
+                }
+                match now {
+                    Availability::Available => {
+                        println!("[server] available for input again; telling clients")
+                    }
+                    Availability::Unavailable(reason) => {
+                        println!("[server] {reason}; telling clients to pause")
+                    }
+                }
+                *current = now;
+                true
+            });
+        }
+    });
+    receiver
+}
@@ -0,0 +1,115 @@
+//! Keeps injected pointer motion inside a rectangle of the desktop, for
+//! kiosk-style setups (`--pointer-bounds x,y,w,h`).
+//!
+//! Absolute positions are clamped directly. Relative moves are applied to a
+//! position the server tracks itself, as the virtual device on Linux can't
+//! be asked where the pointer is; the move actually emitted is whatever
+//! keeps that position inside. Tracking starts at the centre of the
+//! rectangle, so the bounds are only exact once the pointer has been pushed
+//! into a corner or placed absolutely.
+
+use std::{fmt, str::FromStr};
+
+use shared::MouseMove;
+
+/// A rectangle in desktop coordinates. Both edges are inclusive, so the
+/// pointer can reach `x + width - 1` but not `x + width`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointerBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PointerBounds {
+    /// The nearest point to `x`, `y` inside these bounds.
+    pub fn clamp(&self, x: f64, y: f64) -> (f64, f64) {
+        let (left, top) = (f64::from(self.x), f64::from(self.y));
+        let right = left + f64::from(self.width.saturating_sub(1));
+        let bottom = top + f64::from(self.height.saturating_sub(1));
+        (x.clamp(left, right), y.clamp(top, bottom))
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        (
+            f64::from(self.x) + f64::from(self.width) / 2.0,
+            f64::from(self.y) + f64::from(self.height) / 2.0,
+        )
+    }
+}
+
+impl FromStr for PointerBounds {
+    type Err = String;
+
+    /// Parses `x,y,w,h`, e.g. `0,0,1920,1080`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| format!("invalid pointer bounds '{value}': {reason}");
+        let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+        let [x, y, width, height] = parts[..] else {
+            return Err(invalid("expected x,y,w,h"));
+        };
+        let bounds = PointerBounds {
+            x: x.parse().map_err(|_| invalid("x is not a whole number"))?,
+            y: y.parse().map_err(|_| invalid("y is not a whole number"))?,
+            width: width
+                .parse()
+                .map_err(|_| invalid("w is not a positive whole number"))?,
+            height: height
+                .parse()
+                .map_err(|_| invalid("h is not a positive whole number"))?,
+        };
+        if bounds.width == 0 || bounds.height == 0 {
+            return Err(invalid("w and h must be above zero"));
+        }
+        Ok(bounds)
+    }
+}
+
+impl fmt::Display for PointerBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+/// Where the server believes the pointer is, kept inside [`PointerBounds`].
+#[derive(Debug, Clone)]
+pub struct BoundedPointer {
+    bounds: PointerBounds,
+    position: (f64, f64),
+}
+
+impl BoundedPointer {
+    pub fn new(bounds: PointerBounds) -> Self {
+        Self {
+            bounds,
+            position: bounds.center(),
+        }
+    }
+
+    pub fn bounds(&self) -> PointerBounds {
+        self.bounds
+    }
+
+    pub fn position(&self) -> (f64, f64) {
+        self.position
+    }
+
+    /// Applies `mouse_move` to the tracked position and returns the part
+    /// of it that stays inside the bounds.
+    pub fn move_by(&mut self, mouse_move: MouseMove) -> MouseMove {
+        let (x, y) = self.position;
+        let target = self.bounds.clamp(x + mouse_move.dx, y + mouse_move.dy);
+        self.position = target;
+        MouseMove {
+            dx: target.0 - x,
+            dy: target.1 - y,
+        }
+    }
+
+    /// Clamps an absolute position and tracks it from then on.
+    pub fn move_to(&mut self, x: f64, y: f64) -> (f64, f64) {
+        self.position = self.bounds.clamp(x, y);
+        self.position
+    }
+}
@@ -0,0 +1,68 @@
+//! Reassembling a payload a client sends over a bi stream in pieces, as
+//! `client::quic::send_stream_chunked` does, with a hook told how much has
+//! arrived after each piece so a long transfer can report progress.
+
+use std::{error::Error, fmt, io};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Most bytes taken off the stream per read.
+pub const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Why [`read_chunked`] gave up on a stream.
+#[derive(Debug)]
+pub enum ChunkedReadError {
+    /// The peer sent more than the caller allowed.
+    TooLarge {
+        limit: usize,
+    },
+    Read(io::Error),
+}
+
+impl fmt::Display for ChunkedReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkedReadError::TooLarge { limit } => write!(f, "stream exceeded {limit} bytes"),
+            ChunkedReadError::Read(error) => write!(f, "failed to read stream: {error}"),
+        }
+    }
+}
+
+impl Error for ChunkedReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ChunkedReadError::Read(error) => Some(error),
+            ChunkedReadError::TooLarge { .. } => None,
+        }
+    }
+}
+
+/// Reads `recv` to its end, calling `on_progress` with the total received
+/// after every piece. Gives up as soon as more than `limit` bytes arrive,
+/// without waiting for the rest.
+pub async fn read_chunked<R, F>(
+    mut recv: R,
+    limit: usize,
+    mut on_progress: F,
+) -> Result<Vec<u8>, ChunkedReadError>
+where
+    R: AsyncRead + Unpin,
+    F: FnMut(u64),
+{
+    let mut payload = Vec::new();
+    let mut chunk = vec![0; READ_CHUNK_SIZE];
+    loop {
+        let read = recv
+            .read(&mut chunk)
+            .await
+            .map_err(ChunkedReadError::Read)?;
+        if read == 0 {
+            return Ok(payload);
+        }
+        payload.extend_from_slice(&chunk[..read]);
+        if payload.len() > limit {
+            return Err(ChunkedReadError::TooLarge { limit });
+        }
+        on_progress(payload.len() as u64);
+    }
+}
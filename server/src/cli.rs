@@ -0,0 +1,109 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use crate::{bounds::PointerBounds, control_http};
+
+/// Options given on the command line, layered on top of the config file.
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub config_file: Option<String>,
+    pub binds: Vec<SocketAddr>,
+    /// Log decoded input instead of injecting it.
+    pub dry_run: bool,
+    /// Print every decoded event as a replayable script line.
+    pub verbose_events: bool,
+    /// Log raw uni stream bytes and how they decode.
+    pub debug_raw: bool,
+    /// Append every decoded input to this file, rotating it by size.
+    pub audit_log: Option<PathBuf>,
+    /// Record every decoded frame to this file, with receive times.
+    pub record: Option<PathBuf>,
+    /// Write key events to the audit log as `[redacted]`.
+    pub audit_no_keys: bool,
+    /// Also accept JSON commands over HTTP here; see [`control_http`].
+    pub control_http: Option<SocketAddr>,
+    /// Keep the injected pointer inside this rectangle of the desktop.
+    pub pointer_bounds: Option<PointerBounds>,
+    /// Advertise the server on the LAN over mDNS under this name.
+    pub advertise: Option<String>,
+    /// Only inject while the focused window's title contains this; see
+    /// [`crate::focus`].
+    pub only_when_focused: Option<String>,
+}
+
+pub fn parse_args<I>(args: I) -> Result<CliArgs, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut parsed = CliArgs::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| "--bind requires an address such as 0.0.0.0:4433".to_string())?;
+                let addr = value
+                    .parse::<SocketAddr>()
+                    .map_err(|err| format!("invalid --bind address '{value}': {err}"))?;
+                parsed.binds.push(addr);
+            }
+            "--dry-run" => parsed.dry_run = true,
+            "--verbose-events" => parsed.verbose_events = true,
+            "--debug-raw" => parsed.debug_raw = true,
+            "--audit-log" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| "--audit-log requires a file path".to_string())?;
+                parsed.audit_log = Some(PathBuf::from(value));
+            }
+            "--audit-no-keys" => parsed.audit_no_keys = true,
+            "--record" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| "--record requires a file path".to_string())?;
+                parsed.record = Some(PathBuf::from(value));
+            }
+            "--control-http" => {
+                let value = args.next().ok_or_else(|| {
+                    "--control-http requires a port or an address such as 127.0.0.1:8642"
+                        .to_string()
+                })?;
+                parsed.control_http = Some(control_http::parse_addr(&value)?);
+            }
+            "--pointer-bounds" => {
+                let value = args.next().ok_or_else(|| {
+                    "--pointer-bounds requires a rectangle such as 0,0,1920,1080".to_string()
+                })?;
+                parsed.pointer_bounds = Some(value.parse()?);
+            }
+            "--advertise" => {
+                let value = args
+                    .next()
+                    .filter(|name| !name.trim().is_empty())
+                    .ok_or_else(|| "--advertise requires a name to show to clients".to_string())?;
+                parsed.advertise = Some(value);
+            }
+            "--only-when-focused" => {
+                let value = args
+                    .next()
+                    .filter(|title| !title.trim().is_empty())
+                    .ok_or_else(|| {
+                        "--only-when-focused requires part of a window title".to_string()
+                    })?;
+                parsed.only_when_focused = Some(value);
+            }
+            flag if flag.starts_with("--") => {
+                return Err(format!("unknown option '{flag}'"));
+            }
+            _ if parsed.config_file.is_none() => parsed.config_file = Some(arg),
+            _ => return Err(format!("unexpected argument '{arg}'")),
+        }
+    }
+
+    if parsed.audit_no_keys && parsed.audit_log.is_none() {
+        return Err("--audit-no-keys only makes sense with --audit-log".to_string());
+    }
+
+    Ok(parsed)
+}
@@ -1,20 +1,165 @@
+use crate::{
+    allowlist::PeerRange,
+    audit,
+    mapping::AbsoluteMapping,
+    orientation::Orientation,
+    server::{CertificatePaths, DEFAULT_MAX_STREAMS, DEFAULT_PORT},
+    simulator::LaneLayout,
+    transport::ServerTransportOptions,
+};
 use serde::{Deserialize, Serialize};
-use std::net::{IpAddr, Ipv4Addr};
+use shared::{congestion::CongestionControl, layout::LayoutTable};
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+    time::Duration,
+};
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct QUICInputConfig {
     pub broadcastip: IpAddr,
     pub port: u16,
-    pub max_connections: u8
+    pub max_connections: u8,
+    /// Seconds of silence after which held keys/buttons are released. Clients
+    /// send heartbeats while holding input, so only one that went quiet is
+    /// affected. 0 disables.
+    pub idle_release_secs: u64,
+    /// Seconds a held mouse button is kept while nothing but pointer motion
+    /// arrives, before it is released. 0 disables.
+    pub button_hold_limit_secs: u64,
+    /// Seconds a dropped client may reconnect with its session token and keep
+    /// its held keys. 0 releases immediately on every disconnect.
+    pub resume_grace_secs: u64,
+    /// Layout of this machine, used to type characters from clients in
+    /// layout translation mode. One of `us` or `fr`.
+    pub keyboard_layout: String,
+    /// Input streams, and separately control streams, one connection may
+    /// have open at once. Streams past the limit are stopped.
+    pub max_streams_per_connection: usize,
+    /// Size in bytes at which the `--audit-log` file is rotated.
+    pub audit_log_max_bytes: u64,
+    /// Multiplies relative pointer motion from clients.
+    pub pointer_sensitivity: f64,
+    /// How absolute positions are fitted onto this machine's display when
+    /// the client's has another aspect ratio: `stretch`, `letterbox` or
+    /// `clamp` (pixel for pixel).
+    pub absolute_mapping: AbsoluteMapping,
+    /// Inputs one connection may apply per second. 0 is unlimited.
+    pub max_inputs_per_sec: u32,
+    /// Milliseconds this machine allows between the clicks of a
+    /// double-click. A client's double-click arriving further apart is
+    /// replayed quicker. 0 never replays one.
+    pub double_click_ms: u64,
+    /// Tell clients to pause while this machine's screen is locked or its
+    /// session idle, as systemd-logind reports. Off by default, as other
+    /// desktops aren't understood yet.
+    pub report_availability: bool,
+    /// Seconds queued input may wait without any being simulated before the
+    /// simulator counts as stalled. 0 disables the watchdog.
+    pub simulator_stall_secs: u64,
+    /// Restart a stalled simulator, dropping its backlog, instead of only
+    /// logging the stall.
+    pub simulator_restart: bool,
+    /// `split` gives keys and mouse buttons a simulator each, so neither
+    /// holds up the other; `shared` replays both through one, in order.
+    pub simulator_lanes: LaneLayout,
+    /// Addresses allowed to connect, each a single address or a CIDR
+    /// network such as `192.168.1.0/24`. Empty lets anyone in.
+    pub allowed_peers: Vec<PeerRange>,
+    /// Log every connection refused for being off `allowed_peers`. Turn it
+    /// off if a busy network fills the log with them.
+    pub log_refused_peers: bool,
+    /// PEM certificate chain to present instead of a self-signed one
+    /// generated at startup. Needs `key_path` too.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert_path: Option<PathBuf>,
+    /// PEM private key for `cert_path`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_path: Option<PathBuf>,
+    /// How this machine's display is physically turned, as an
+    /// `[orientation]` table: `rotation` clockwise in degrees (0, 90, 180
+    /// or 270), then `flip_x` and `flip_y` to mirror either axis.
+    pub orientation: Orientation,
+    /// QUIC limits offered to clients, as a `[transport]` table.
+    pub transport: TransportSettings,
+}
+
+/// The `[transport]` table: [`ServerTransportOptions`] in config file units.
+/// The defaults are quinn's own.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TransportSettings {
+    pub max_concurrent_uni_streams: u32,
+    pub max_concurrent_bidi_streams: u32,
+    /// Bytes in flight per stream.
+    pub stream_receive_window: u64,
+    /// Bytes in flight per connection. 0 is unlimited.
+    pub receive_window: u64,
+    /// Seconds before a silent connection is dropped. 0 never drops it.
+    pub max_idle_timeout_secs: u64,
+    /// Seconds between pings to idle clients. 0 sends none.
+    pub keep_alive_secs: u64,
+    /// `cubic` (quinn's default), `new_reno` or `bbr`.
+    pub congestion_control: CongestionControl,
+}
+
+impl Default for TransportSettings {
+    fn default() -> Self {
+        let options = ServerTransportOptions::default();
+        Self {
+            max_concurrent_uni_streams: options.max_concurrent_uni_streams,
+            max_concurrent_bidi_streams: options.max_concurrent_bidi_streams,
+            stream_receive_window: options.stream_receive_window,
+            receive_window: options.receive_window.unwrap_or(0),
+            max_idle_timeout_secs: options.max_idle_timeout.map_or(0, |idle| idle.as_secs()),
+            keep_alive_secs: options.keep_alive_interval.map_or(0, |interval| interval.as_secs()),
+            congestion_control: options.congestion_control,
+        }
+    }
+}
+
+impl TransportSettings {
+    pub fn options(&self) -> ServerTransportOptions {
+        let secs = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+        ServerTransportOptions {
+            max_concurrent_uni_streams: self.max_concurrent_uni_streams,
+            max_concurrent_bidi_streams: self.max_concurrent_bidi_streams,
+            stream_receive_window: self.stream_receive_window,
+            receive_window: (self.receive_window > 0).then_some(self.receive_window),
+            max_idle_timeout: secs(self.max_idle_timeout_secs),
+            keep_alive_interval: secs(self.keep_alive_secs),
+            congestion_control: self.congestion_control,
+        }
+    }
 }
 
 impl Default for QUICInputConfig {
     fn default() -> Self {
         Self {
             broadcastip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
-            port: 4433,
+            port: DEFAULT_PORT,
             max_connections: 1,
+            idle_release_secs: 10,
+            button_hold_limit_secs: 60,
+            resume_grace_secs: 30,
+            keyboard_layout: "us".to_string(),
+            max_streams_per_connection: DEFAULT_MAX_STREAMS,
+            audit_log_max_bytes: audit::DEFAULT_MAX_BYTES,
+            pointer_sensitivity: 1.0,
+            absolute_mapping: AbsoluteMapping::default(),
+            max_inputs_per_sec: 0,
+            double_click_ms: 400,
+            report_availability: false,
+            simulator_stall_secs: 5,
+            simulator_restart: true,
+            simulator_lanes: LaneLayout::default(),
+            allowed_peers: Vec::new(),
+            log_refused_peers: true,
+            cert_path: None,
+            key_path: None,
+            orientation: Orientation::default(),
+            transport: TransportSettings::default(),
         }
     }
 }
@@ -24,6 +169,33 @@ impl QUICInputConfig {
         if self.port == 0 {
             return Err("port must be greater than 0".into());
         }
+        if self.max_streams_per_connection == 0 {
+            return Err("max_streams_per_connection must be greater than 0".into());
+        }
+        if !(self.pointer_sensitivity.is_finite() && self.pointer_sensitivity > 0.0) {
+            return Err("pointer_sensitivity must be a positive number".into());
+        }
+        if self.cert_path.is_some() != self.key_path.is_some() {
+            return Err("cert_path and key_path must be set together".into());
+        }
+        if LayoutTable::by_name(&self.keyboard_layout).is_none() {
+            return Err(format!("unknown keyboard_layout '{}'", self.keyboard_layout));
+        }
+        self.transport
+            .options()
+            .validate()
+            .map_err(|err| format!("transport: {err}"))?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// The certificate files to present, if both are configured.
+    pub fn certificate(&self) -> Option<CertificatePaths> {
+        match (&self.cert_path, &self.key_path) {
+            (Some(cert), Some(key)) => Some(CertificatePaths {
+                cert: cert.clone(),
+                key: key.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! Optional HTTP endpoint that takes input as JSON, for scripting from tools
+//! that can't speak QUIC. Off unless `--control-http` is given, and bound to
+//! loopback unless an address says otherwise.
+//!
+//! Each request is one `POST /input` carrying one command:
+//!
+//! ```text
+//! {"type":"move","dx":5,"dy":0}
+//! {"type":"wheel","delta_x":0,"delta_y":-1}
+//! {"type":"key","key":"KeyA","action":"tap"}
+//! {"type":"button","button":"Left","action":"press"}
+//! ```
+//!
+//! Keys and buttons use their `rdev` names, as in scripts. `action` is
+//! `press`, `release` or `tap` (the default). The answer is `204 No Content`
+//! once the input has been handed to the injector, or `400` with the reason
+//! the command was refused.
+
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr},
+};
+
+use rdev::{Button, EventType, Key};
+use serde::Deserialize;
+use shared::MouseMove;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::inject::Injector;
+
+/// Where `--control-http <port>` listens when given only a port.
+pub const DEFAULT_HOST: Ipv4Addr = Ipv4Addr::LOCALHOST;
+
+/// Largest request head (request line and headers) accepted.
+const MAX_HEAD: usize = 8 * 1024;
+
+/// Largest body accepted; a command is a few dozen bytes.
+const MAX_BODY: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HttpCommand {
+    Move {
+        dx: f64,
+        dy: f64,
+    },
+    Wheel {
+        #[serde(default)]
+        delta_x: i64,
+        #[serde(default)]
+        delta_y: i64,
+    },
+    Key {
+        key: Key,
+        #[serde(default)]
+        action: Action,
+    },
+    Button {
+        button: Button,
+        #[serde(default)]
+        action: Action,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Press,
+    Release,
+    /// A press straight followed by its release.
+    #[default]
+    Tap,
+}
+
+impl Action {
+    fn events(self, press: EventType, release: EventType) -> Vec<EventType> {
+        match self {
+            Action::Press => vec![press],
+            Action::Release => vec![release],
+            Action::Tap => vec![press, release],
+        }
+    }
+}
+
+/// Parses a request body into a command.
+pub fn parse_command(body: &[u8]) -> Result<HttpCommand, serde_json::Error> {
+    serde_json::from_slice(body)
+}
+
+impl HttpCommand {
+    /// Feeds the command through the same injection path as QUIC input.
+    pub fn apply(self, injector: &Injector) {
+        match self {
+            HttpCommand::Move { dx, dy } => injector.mouse_move(MouseMove { dx, dy }),
+            HttpCommand::Wheel { delta_x, delta_y } => {
+                injector.event(EventType::Wheel { delta_x, delta_y })
+            }
+            HttpCommand::Key { key, action } => {
+                for event in action.events(EventType::KeyPress(key), EventType::KeyRelease(key)) {
+                    injector.event(event);
+                }
+            }
+            HttpCommand::Button { button, action } => {
+                let events = action.events(
+                    EventType::ButtonPress(button),
+                    EventType::ButtonRelease(button),
+                );
+                for event in events {
+                    injector.event(event);
+                }
+            }
+        }
+    }
+}
+
+/// Parses the `--control-http` value: a full address, or a port to listen
+/// on at [`DEFAULT_HOST`].
+pub fn parse_addr(value: &str) -> Result<SocketAddr, String> {
+    if let Ok(port) = value.parse::<u16>() {
+        return Ok(SocketAddr::from((DEFAULT_HOST, port)));
+    }
+    value
+        .parse::<SocketAddr>()
+        .map_err(|err| format!("invalid --control-http address '{value}': {err}"))
+}
+
+/// The bound endpoint, ready to [`run`](ControlHttp::run).
+pub struct ControlHttp {
+    listener: TcpListener,
+}
+
+impl ControlHttp {
+    pub async fn bind(addr: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        println!("[server] control endpoint listening on http://{local_addr}/input");
+        if !local_addr.ip().is_loopback() {
+            eprintln!(
+                "[server] control endpoint on {local_addr} is reachable from the network and \
+                 accepts input without authentication"
+            );
+        }
+        Ok(Self { listener })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves requests until the task is dropped, one connection per request.
+    pub async fn run(self, injector: Injector) {
+        loop {
+            let (stream, peer) = match self.listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(err) => {
+                    eprintln!("[server] control endpoint failed to accept: {err}");
+                    continue;
+                }
+            };
+            let injector = injector.clone();
+            tokio::spawn(async move {
+                if let Err(err) = handle(stream, &injector).await {
+                    eprintln!("[server] control request from {peer} failed: {err}");
+                }
+            });
+        }
+    }
+}
+
+async fn handle(mut stream: TcpStream, injector: &Injector) -> io::Result<()> {
+    let (status, body) = match read_request(&mut stream).await? {
+        Ok(request) => route(request, injector),
+        Err(refused) => refused,
+    };
+    respond(&mut stream, status, &body).await
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+type Refused = (u16, String);
+
+fn route(request: Request, injector: &Injector) -> (u16, String) {
+    if request.path != "/input" {
+        return (404, format!("no such endpoint '{}'", request.path));
+    }
+    if request.method != "POST" {
+        return (405, "use POST".to_string());
+    }
+    match parse_command(&request.body) {
+        Ok(command) => {
+            command.apply(injector);
+            (204, String::new())
+        }
+        Err(err) => (400, format!("invalid command: {err}")),
+    }
+}
+
+/// Reads one request. The outer error is the connection failing; the inner
+/// one a request to refuse with that status.
+async fn read_request(stream: &mut TcpStream) -> io::Result<Result<Request, Refused>> {
+    let mut buf = Vec::new();
+    let head_end = loop {
+        if let Some(end) = find_head_end(&buf) {
+            break end;
+        }
+        if buf.len() > MAX_HEAD {
+            return Ok(Err((431, "request head too large".to_string())));
+        }
+        let mut chunk = [0u8; 1024];
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..read]);
+    };
+
+    let mut body = buf.split_off(head_end + 4);
+    let Ok(head) = std::str::from_utf8(&buf[..head_end]) else {
+        return Ok(Err((400, "request head is not UTF-8".to_string())));
+    };
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (Some(method), Some(path)) = (request_line.next(), request_line.next()) else {
+        return Ok(Err((400, "malformed request line".to_string())));
+    };
+    let mut content_length = 0;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            match value.trim().parse::<usize>() {
+                Ok(length) => content_length = length,
+                Err(_) => return Ok(Err((400, "invalid Content-Length".to_string()))),
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Ok(Err((413, format!("body over {MAX_BODY} bytes"))));
+    }
+
+    while body.len() < content_length {
+        let mut chunk = [0u8; 1024];
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+
+    Ok(Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        body,
+    }))
+}
+
+fn find_head_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|window| window == b"\r\n\r\n")
+}
+
+async fn respond(stream: &mut TcpStream, status: u16, body: &str) -> io::Result<()> {
+    let reason = match status {
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "",
+    };
+    let response = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
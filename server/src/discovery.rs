@@ -0,0 +1,45 @@
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use rustls::pki_types::CertificateDer;
+use sha2::{Digest, Sha256};
+
+pub(crate) const SERVICE_TYPE: &str = "_quicinput._udp.local.";
+
+/// Advertises this server over mDNS/DNS-SD as `name`. The returned daemon
+/// must be kept alive for as long as the advertisement should stay up.
+pub(crate) fn advertise(
+    name: &str,
+    port: u16,
+    cert: &CertificateDer<'_>,
+) -> Result<ServiceDaemon, mdns_sd::Error> {
+    let host = hostname::get()
+        .ok()
+        .and_then(|name| name.into_string().ok())
+        .unwrap_or_else(|| "quicinput".into());
+    let host_name = format!("{host}.local.");
+    let fingerprint = cert_fingerprint(cert);
+    let properties = [("fingerprint", fingerprint.as_str())];
+
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        name,
+        &host_name,
+        "",
+        port,
+        &properties[..],
+    )?
+    .enable_addr_auto();
+
+    let daemon = ServiceDaemon::new()?;
+    daemon.register(service)?;
+    println!("[server] advertising '{name}' via mDNS on port {port}");
+    Ok(daemon)
+}
+
+/// SHA-256 of the DER certificate as colon separated hex, as shown to clients.
+pub(crate) fn cert_fingerprint(cert: &CertificateDer<'_>) -> String {
+    Sha256::digest(cert.as_ref())
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
@@ -0,0 +1,49 @@
+use shared::DisplayInfo;
+
+/// Where the server learns about its own displays.
+pub trait DisplaySource: Send + Sync {
+    fn displays(&self) -> Vec<DisplayInfo>;
+}
+
+/// The displays attached to this machine, via the platform's own API.
+#[derive(Debug, Default)]
+pub struct SystemDisplays;
+
+impl DisplaySource for SystemDisplays {
+    fn displays(&self) -> Vec<DisplayInfo> {
+        let infos = match display_info::DisplayInfo::all() {
+            Ok(infos) => infos,
+            Err(err) => {
+                eprintln!("[server] failed to enumerate displays: {err}");
+                return Vec::new();
+            }
+        };
+
+        infos
+            .into_iter()
+            .enumerate()
+            .map(|(index, info)| DisplayInfo {
+                name: if info.name.is_empty() {
+                    format!("Display {}", index + 1)
+                } else {
+                    info.name
+                },
+                x: info.x,
+                y: info.y,
+                width: info.width,
+                height: info.height,
+                is_primary: info.is_primary,
+            })
+            .collect()
+    }
+}
+
+/// A fixed display layout, for tests and dry runs.
+#[derive(Debug, Default)]
+pub struct FakeDisplays(pub Vec<DisplayInfo>);
+
+impl DisplaySource for FakeDisplays {
+    fn displays(&self) -> Vec<DisplayInfo> {
+        self.0.clone()
+    }
+}
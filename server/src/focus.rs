@@ -0,0 +1,230 @@
+//! Only taking input while a chosen window is focused
+//! (`--only-when-focused <title>`), e.g. a game, so a shared server never
+//! types into a banking app that happened to come to the front. A
+//! [`FocusSource`] is asked for the focused window's title every
+//! [`FOCUS_POLL_INTERVAL`] from a thread of its own, which is what moves
+//! go by. A press, click or scroll looks again itself unless the last look
+//! is under [`PRESS_CHECK_MAX_AGE`] old, so the first one after a focus
+//! change doesn't land in the wrong window. Input is dropped while the
+//! title doesn't contain the one asked for; releases always go through, so
+//! nothing is left held in the window that lost focus.
+//!
+//! Input is dropped while the title can't be read, e.g. under Wayland,
+//! which doesn't let clients see other windows.
+
+use std::{
+    process::Command,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the focused window is looked at.
+pub const FOCUS_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How old a look at the focused window a press may go by. Short enough
+/// that a press after any pause looks again, long enough that fast typing
+/// doesn't run `xdotool` on every key.
+pub const PRESS_CHECK_MAX_AGE: Duration = Duration::from_millis(50);
+
+/// Where the server learns which window is focused.
+pub trait FocusSource: Send + Sync {
+    /// The focused window's title, or `None` when it can't tell.
+    fn focused_title(&self) -> Option<String>;
+}
+
+/// The focused window as this OS reports it: through `xdotool` on X11, the
+/// window title on Windows, and the frontmost application's name on macOS.
+#[derive(Debug, Default)]
+pub struct SystemFocus;
+
+impl FocusSource for SystemFocus {
+    fn focused_title(&self) -> Option<String> {
+        #[cfg(target_os = "windows")]
+        {
+            windows_focus::focused_title()
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            command_output(
+                "osascript",
+                &[
+                    "-e",
+                    "tell application \"System Events\" to get name of first application process whose frontmost is true",
+                ],
+            )
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            command_output("xdotool", &["getactivewindow", "getwindowname"])
+        }
+    }
+}
+
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "windows")]
+mod windows_focus {
+    use std::ffi::c_void;
+
+    #[link(name = "user32")]
+    unsafe extern "system" {
+        fn GetForegroundWindow() -> *mut c_void;
+        fn GetWindowTextW(window: *mut c_void, text: *mut u16, max_count: i32) -> i32;
+    }
+
+    pub fn focused_title() -> Option<String> {
+        let mut text = [0u16; 512];
+        unsafe {
+            let window = GetForegroundWindow();
+            if window.is_null() {
+                return None;
+            }
+            let len = GetWindowTextW(window, text.as_mut_ptr(), text.len() as i32);
+            Some(String::from_utf16_lossy(&text[..len.max(0) as usize]))
+        }
+    }
+}
+
+/// A title set by hand, for tests and dry runs.
+#[derive(Debug, Default)]
+pub struct FakeFocus(Mutex<Option<String>>);
+
+impl FakeFocus {
+    pub fn set(&self, title: Option<&str>) {
+        *self.0.lock().expect("focus mutex poisoned") = title.map(str::to_string);
+    }
+}
+
+impl FocusSource for FakeFocus {
+    fn focused_title(&self) -> Option<String> {
+        self.0.lock().expect("focus mutex poisoned").clone()
+    }
+}
+
+/// Whether input may go to the window titled `title` when only windows
+/// whose titles contain `wanted` should get it. Ignores case. Anything goes
+/// without `wanted`; nothing does when the title is unknown.
+pub fn title_matches(title: Option<&str>, wanted: Option<&str>) -> bool {
+    let Some(wanted) = wanted else {
+        return true;
+    };
+    title.is_some_and(|title| title.to_lowercase().contains(&wanted.to_lowercase()))
+}
+
+/// The last answer to whether input may be injected, kept up to date by
+/// [`FocusGuard::start`].
+pub struct FocusGuard {
+    wanted: String,
+    source: Arc<dyn FocusSource>,
+    allowed: AtomicBool,
+    press_max_age: Duration,
+    // When `allowed` was last updated; `None` before the first look.
+    checked_at: Mutex<Option<Instant>>,
+    // The title last seen, to log each change once.
+    last_title: Mutex<Option<Option<String>>>,
+}
+
+impl FocusGuard {
+    /// A guard for windows whose titles contain `wanted`, looked at now and
+    /// then every `interval` until the guard is dropped.
+    pub fn start(
+        wanted: impl Into<String>,
+        source: Arc<dyn FocusSource>,
+        interval: Duration,
+    ) -> Arc<Self> {
+        let guard = Arc::new(Self::new(wanted, source));
+        guard.check();
+        let weak = Arc::downgrade(&guard);
+        thread::Builder::new()
+            .name("focus-guard".into())
+            .spawn(move || poll(weak, interval))
+            .expect("failed to spawn focus guard thread");
+        guard
+    }
+
+    /// A guard that only looks when [`FocusGuard::check`] is called. Input
+    /// is refused until then.
+    pub fn new(wanted: impl Into<String>, source: Arc<dyn FocusSource>) -> Self {
+        Self {
+            wanted: wanted.into(),
+            source,
+            allowed: AtomicBool::new(false),
+            press_max_age: PRESS_CHECK_MAX_AGE,
+            checked_at: Mutex::new(None),
+            last_title: Mutex::new(None),
+        }
+    }
+
+    /// Lets presses go by a look up to `max_age` old instead of
+    /// [`PRESS_CHECK_MAX_AGE`]; zero looks on every press.
+    pub fn with_press_max_age(mut self, max_age: Duration) -> Self {
+        self.press_max_age = max_age;
+        self
+    }
+
+    pub fn wanted(&self) -> &str {
+        &self.wanted
+    }
+
+    /// Whether input may be injected, as of the last look.
+    pub fn allows(&self) -> bool {
+        self.allowed.load(Ordering::Relaxed)
+    }
+
+    /// Whether a press may be injected, looking at the focused window again
+    /// first unless the last look is recent enough.
+    pub fn allows_press(&self) -> bool {
+        let checked_at = *self.checked_at.lock().expect("focus mutex poisoned");
+        match checked_at {
+            Some(at) if at.elapsed() < self.press_max_age => self.allows(),
+            _ => self.check(),
+        }
+    }
+
+    /// Looks at the focused window now, returning whether input may go to it.
+    pub fn check(&self) -> bool {
+        let title = self.source.focused_title();
+        let allowed = title_matches(title.as_deref(), Some(&self.wanted));
+        self.allowed.store(allowed, Ordering::Relaxed);
+        *self.checked_at.lock().expect("focus mutex poisoned") = Some(Instant::now());
+
+        let mut last_title = self.last_title.lock().expect("focus mutex poisoned");
+        if last_title.as_ref() != Some(&title) {
+            match (&title, allowed) {
+                (Some(title), true) => println!("[server] '{title}' is focused; taking input"),
+                (Some(title), false) => println!(
+                    "[server] '{title}' is focused, not a window matching '{}'; dropping input",
+                    self.wanted
+                ),
+                (None, _) => {
+                    println!("[server] can't tell which window is focused; dropping input")
+                }
+            }
+            *last_title = Some(title);
+        }
+        allowed
+    }
+}
+
+fn poll(guard: Weak<FocusGuard>, interval: Duration) {
+    loop {
+        thread::sleep(interval);
+        let Some(guard) = guard.upgrade() else {
+            return;
+        };
+        guard.check();
+    }
+}
@@ -0,0 +1,274 @@
+use std::fmt::Debug;
+
+use rdev::{Button, EventType};
+use serde::{
+    Deserialize, Serialize,
+    de::{DeserializeOwned, IgnoredAny},
+};
+use shared::{
+    AbsoluteMove, CharInput, Edge, EdgeHit, Gesture, KeepHeld, MouseMove, SentAt, Seq, SourceId,
+    Sourced,
+    clicks::DoubleClick,
+    codec::{Codec, CodecError, WireFormat},
+    extra_keys::ExtraKeyInput,
+    key_combo::KeyCombo,
+    raw_keys::RawKeyInput,
+};
+
+/// A complete value pulled off a uni stream.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub enum Frame {
+    Mouse(MouseMove),
+    Event(EventType),
+    /// A character to type through the server's keyboard layout.
+    Char(char),
+    /// The client's pointer was pushed into an edge of our display.
+    Edge(Edge),
+    /// The client's send time for the input that follows.
+    SentAt(u64),
+    /// The sequence number of the message that follows.
+    Seq(Seq),
+    /// A touchpad gesture, injected as wheel events.
+    Gesture(Gesture),
+    /// A key rdev has no name for, named rather than as the client's keycode.
+    ExtraKey(ExtraKeyInput),
+    /// A key by Linux input event code, for keys with no name at all.
+    RawKey(RawKeyInput),
+    /// A pointer position, applied only in absolute mode.
+    Absolute(AbsoluteMove),
+    /// Keys pressed together and released, injected in one go.
+    Combo(KeyCombo),
+    /// The client's next press of this button completes a double-click.
+    DoubleClick(Button),
+    /// The client still holds input down; see [`KeepHeld`].
+    KeepHeld,
+    /// A well-formed value that is neither of the above.
+    Unknown(usize),
+}
+
+/// Reassembles values from a uni stream, in the [`WireFormat`] the client
+/// asked for.
+///
+/// QUIC chunks carry no message boundaries, so a value may arrive split
+/// across reads or several values may share one read. Bytes are buffered
+/// until a whole value is present and only the bytes it used are consumed.
+///
+/// A value still incomplete past the limit can't be skipped, as where it
+/// ends isn't known; reading on would take the rest of it for new values.
+/// The decoder gives the stream up instead: see [`FrameDecoder::overflowed`].
+#[derive(Debug)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    limit: usize,
+    source_id: Option<SourceId>,
+    format: WireFormat,
+    overflowed: bool,
+}
+
+impl FrameDecoder {
+    /// `limit` caps how many bytes a single incomplete value may occupy.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            limit,
+            source_id: None,
+            format: WireFormat::default(),
+            overflowed: false,
+        }
+    }
+
+    /// Reads `format` instead of MessagePack.
+    pub fn with_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn format(&self) -> WireFormat {
+        self.format
+    }
+
+    /// Buffers `bytes`; ignored once the decoder has overflowed.
+    pub fn push(&mut self, bytes: &[u8]) {
+        if !self.overflowed {
+            self.buf.extend_from_slice(bytes);
+        }
+    }
+
+    /// Whether a value grew past the limit. Nothing more is decoded after
+    /// it, and the stream should be stopped.
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Bytes held back waiting for the rest of a value.
+    pub fn pending(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Source tag of the frame most recently returned, if the sender set one.
+    pub fn source_id(&self) -> Option<SourceId> {
+        self.source_id
+    }
+
+    /// Decodes the next complete value, or `None` once more bytes are needed.
+    ///
+    /// Garbage that can never decode is discarded so the stream can carry
+    /// on. A partial value larger than the limit is discarded along with
+    /// everything after it.
+    pub fn next_frame(&mut self) -> Option<Frame> {
+        if self.buf.is_empty() {
+            return None;
+        }
+
+        self.source_id = None;
+
+        let mouse = self.format.decode_prefix::<MouseMove>(&self.buf);
+        if let Ok((mouse_move, used)) = mouse {
+            self.buf.drain(..used);
+            return Some(Frame::Mouse(mouse_move));
+        }
+
+        let event = self.format.decode_prefix::<EventType>(&self.buf);
+        if let Ok((event_type, used)) = event {
+            self.buf.drain(..used);
+            return Some(Frame::Event(event_type));
+        }
+
+        let char_input = self.format.decode_prefix::<CharInput>(&self.buf);
+        if let Ok((CharInput(ch), used)) = char_input {
+            self.buf.drain(..used);
+            return Some(Frame::Char(ch));
+        }
+
+        let edge_hit = self.format.decode_prefix::<EdgeHit>(&self.buf);
+        if let Ok((EdgeHit(edge), used)) = edge_hit {
+            self.buf.drain(..used);
+            return Some(Frame::Edge(edge));
+        }
+
+        let seq = self.format.decode_prefix::<Seq>(&self.buf);
+        if let Ok((seq, used)) = seq {
+            self.buf.drain(..used);
+            return Some(Frame::Seq(seq));
+        }
+
+        let sent_at = self.format.decode_prefix::<SentAt>(&self.buf);
+        if let Ok((SentAt { micros }, used)) = sent_at {
+            self.buf.drain(..used);
+            return Some(Frame::SentAt(micros));
+        }
+
+        let gesture = self.format.decode_prefix::<Gesture>(&self.buf);
+        if let Ok((gesture, used)) = gesture {
+            self.buf.drain(..used);
+            return Some(Frame::Gesture(gesture));
+        }
+
+        let extra_key = self.format.decode_prefix::<ExtraKeyInput>(&self.buf);
+        if let Ok((input, used)) = extra_key {
+            self.buf.drain(..used);
+            return Some(Frame::ExtraKey(input));
+        }
+
+        let raw_key = self.format.decode_prefix::<RawKeyInput>(&self.buf);
+        if let Ok((input, used)) = raw_key {
+            self.buf.drain(..used);
+            return Some(Frame::RawKey(input));
+        }
+
+        let absolute = self.format.decode_prefix::<AbsoluteMove>(&self.buf);
+        if let Ok((absolute, used)) = absolute {
+            self.buf.drain(..used);
+            return Some(Frame::Absolute(absolute));
+        }
+
+        let combo = self.format.decode_prefix::<KeyCombo>(&self.buf);
+        if let Ok((combo, used)) = combo {
+            self.buf.drain(..used);
+            return Some(Frame::Combo(combo));
+        }
+
+        let double_click = self.format.decode_prefix::<DoubleClick>(&self.buf);
+        if let Ok((DoubleClick { button }, used)) = double_click {
+            self.buf.drain(..used);
+            return Some(Frame::DoubleClick(button));
+        }
+
+        let keep_held = self.format.decode_prefix::<KeepHeld>(&self.buf);
+        if let Ok((KeepHeld, used)) = keep_held {
+            self.buf.drain(..used);
+            return Some(Frame::KeepHeld);
+        }
+
+        let sourced_mouse = self.format.decode_prefix::<Sourced<MouseMove>>(&self.buf);
+        if let Ok((sourced, used)) = sourced_mouse {
+            self.buf.drain(..used);
+            self.source_id = Some(sourced.source_id);
+            return Some(Frame::Mouse(sourced.input));
+        }
+
+        let sourced_event = self.format.decode_prefix::<Sourced<EventType>>(&self.buf);
+        if let Ok((sourced, used)) = sourced_event {
+            self.buf.drain(..used);
+            self.source_id = Some(sourced.source_id);
+            return Some(Frame::Event(sourced.input));
+        }
+
+        match self.format.decode_prefix::<IgnoredAny>(&self.buf) {
+            Ok((_, used)) => {
+                self.buf.drain(..used);
+                Some(Frame::Unknown(used))
+            }
+            Err(CodecError::Incomplete) => {
+                if self.buf.len() > self.limit {
+                    eprintln!(
+                        "[server] uni stream value exceeded {} bytes; giving up on the stream",
+                        self.limit
+                    );
+                    self.buf.clear();
+                    self.overflowed = true;
+                }
+                None
+            }
+            Err(err) => {
+                eprintln!(
+                    "[server] discarding {} undecodable bytes: {err}",
+                    self.buf.len()
+                );
+                self.buf.clear();
+                None
+            }
+        }
+    }
+}
+
+/// What `bytes`, encoded in `format`, decode to as each kind of value a uni
+/// stream may carry, one line per kind, for `--debug-raw` to log. Unlike
+/// [`FrameDecoder`], every kind is tried, so a payload that several would
+/// accept shows up as such.
+pub fn attempted_decodes(bytes: &[u8], format: WireFormat) -> Vec<String> {
+    vec![
+        attempt::<MouseMove>("MouseMove", bytes, format),
+        attempt::<EventType>("EventType", bytes, format),
+        attempt::<CharInput>("CharInput", bytes, format),
+        attempt::<EdgeHit>("EdgeHit", bytes, format),
+        attempt::<Seq>("Seq", bytes, format),
+        attempt::<SentAt>("SentAt", bytes, format),
+        attempt::<Gesture>("Gesture", bytes, format),
+        attempt::<ExtraKeyInput>("ExtraKeyInput", bytes, format),
+        attempt::<RawKeyInput>("RawKeyInput", bytes, format),
+        attempt::<AbsoluteMove>("AbsoluteMove", bytes, format),
+        attempt::<KeyCombo>("KeyCombo", bytes, format),
+        attempt::<DoubleClick>("DoubleClick", bytes, format),
+        attempt::<KeepHeld>("KeepHeld", bytes, format),
+        attempt::<Sourced<MouseMove>>("Sourced<MouseMove>", bytes, format),
+        attempt::<Sourced<EventType>>("Sourced<EventType>", bytes, format),
+    ]
+}
+
+fn attempt<T: DeserializeOwned + Debug>(name: &str, bytes: &[u8], format: WireFormat) -> String {
+    match format.decode_prefix::<T>(bytes) {
+        Ok((value, used)) => format!("{name}: {value:?} ({used} of {} bytes)", bytes.len()),
+        Err(err) => format!("{name}: {err}"),
+    }
+}
@@ -0,0 +1,71 @@
+//! Turns touchpad gestures into wheel events, which every platform can
+//! inject: two-finger scrolling becomes wheel notches and a pinch becomes
+//! Ctrl+wheel, the zoom shortcut applications already understand.
+
+use rdev::{EventType, Key};
+use shared::Gesture;
+
+/// Pixels of two-finger scrolling that make up one wheel notch.
+pub const PIXELS_PER_NOTCH: f64 = 16.0;
+
+/// Zoom factor one Ctrl+wheel notch stands for.
+pub const ZOOM_PER_NOTCH: f64 = 1.1;
+
+/// Gesture motion too small for a whole notch yet, per connection, so slow
+/// gestures still add up instead of being rounded away.
+#[derive(Debug, Default)]
+pub struct GestureTranslator {
+    scroll_x: f64,
+    scroll_y: f64,
+    zoom: f64,
+}
+
+impl GestureTranslator {
+    /// Events reproducing `gesture`. With `control_held` the client is
+    /// already holding Control, so a zoom is not wrapped in a press and
+    /// release of its own.
+    pub fn translate(&mut self, gesture: Gesture, control_held: bool) -> Vec<EventType> {
+        match gesture {
+            Gesture::Scroll { dx, dy } => {
+                let delta_x = take_notches(&mut self.scroll_x, dx / PIXELS_PER_NOTCH);
+                let delta_y = take_notches(&mut self.scroll_y, dy / PIXELS_PER_NOTCH);
+                if delta_x == 0 && delta_y == 0 {
+                    return Vec::new();
+                }
+                vec![EventType::Wheel { delta_x, delta_y }]
+            }
+            Gesture::Pinch { scale } => {
+                if !scale.is_finite() || scale <= 0.0 {
+                    return Vec::new();
+                }
+                let delta_y = take_notches(&mut self.zoom, scale.ln() / ZOOM_PER_NOTCH.ln());
+                if delta_y == 0 {
+                    return Vec::new();
+                }
+                let zoom = EventType::Wheel {
+                    delta_x: 0,
+                    delta_y,
+                };
+                if control_held {
+                    return vec![zoom];
+                }
+                vec![
+                    EventType::KeyPress(Key::ControlLeft),
+                    zoom,
+                    EventType::KeyRelease(Key::ControlLeft),
+                ]
+            }
+        }
+    }
+}
+
+/// Adds `amount` to `pending` and takes out the whole notches.
+fn take_notches(pending: &mut f64, amount: f64) -> i64 {
+    if !amount.is_finite() {
+        return 0;
+    }
+    *pending += amount;
+    let notches = pending.trunc();
+    *pending -= notches;
+    notches as i64
+}
@@ -0,0 +1,115 @@
+use rdev::{Button, EventType, Key};
+use shared::raw_keys::RawKeyInput;
+
+/// Keys and mouse buttons a connection currently holds down on this machine.
+///
+/// Fed with every event the server enqueues so that a disconnect (or the idle
+/// watchdog) can release exactly what is still pressed.
+#[derive(Debug, Default)]
+pub struct HeldState {
+    keys: Vec<Key>,
+    buttons: Vec<Button>,
+    /// Codes of [`RawKeyInput`]s held down.
+    raw_keys: Vec<u16>,
+}
+
+impl HeldState {
+    /// Records a press or release; other event types are ignored.
+    pub fn observe(&mut self, event: &EventType) {
+        match *event {
+            EventType::KeyPress(key) => self.add_key(key),
+            EventType::KeyRelease(key) => self.remove_key(key),
+            EventType::ButtonPress(button) => self.add_button(button),
+            EventType::ButtonRelease(button) => self.remove_button(button),
+            _ => {}
+        }
+    }
+
+    /// Records a raw key press or release.
+    pub fn observe_raw(&mut self, input: RawKeyInput) {
+        if !input.pressed {
+            self.raw_keys.retain(|held| *held != input.code);
+        } else if !self.raw_keys.contains(&input.code) {
+            self.raw_keys.push(input.code);
+        }
+    }
+
+    pub fn add_key(&mut self, key: Key) {
+        if !self.keys.contains(&key) {
+            self.keys.push(key);
+        }
+    }
+
+    pub fn remove_key(&mut self, key: Key) {
+        self.keys.retain(|held| *held != key);
+    }
+
+    pub fn add_button(&mut self, button: Button) {
+        if !self.buttons.contains(&button) {
+            self.buttons.push(button);
+        }
+    }
+
+    pub fn remove_button(&mut self, button: Button) {
+        self.buttons.retain(|held| *held != button);
+    }
+
+    /// Folds another connection's held set into this one, e.g. on resume.
+    pub fn merge(&mut self, other: HeldState) {
+        for key in other.keys {
+            self.add_key(key);
+        }
+        for button in other.buttons {
+            self.add_button(button);
+        }
+        for code in other.raw_keys {
+            if !self.raw_keys.contains(&code) {
+                self.raw_keys.push(code);
+            }
+        }
+    }
+
+    pub fn has_buttons(&self) -> bool {
+        !self.buttons.is_empty()
+    }
+
+    /// Keys currently down, in press order.
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.buttons.is_empty() && self.raw_keys.is_empty()
+    }
+
+    /// Empties the set, returning the release events needed to undo it in
+    /// reverse press order.
+    pub fn drain(&mut self) -> Vec<EventType> {
+        let keys = self.keys.drain(..).rev().map(EventType::KeyRelease);
+        let buttons = self.buttons.drain(..).rev().map(EventType::ButtonRelease);
+        keys.chain(buttons).collect()
+    }
+
+    /// Empties the buttons held, returning their releases in reverse press
+    /// order. Keys stay held.
+    pub fn drain_buttons(&mut self) -> Vec<EventType> {
+        self.buttons
+            .drain(..)
+            .rev()
+            .map(EventType::ButtonRelease)
+            .collect()
+    }
+
+    /// Empties the raw keys held, returning their releases in reverse press
+    /// order. Kept apart from [`HeldState::drain`] as they aren't rdev events.
+    pub fn drain_raw(&mut self) -> Vec<RawKeyInput> {
+        self.raw_keys
+            .drain(..)
+            .rev()
+            .map(|code| RawKeyInput {
+                code,
+                pressed: false,
+            })
+            .collect()
+    }
+}
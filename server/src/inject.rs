@@ -0,0 +1,545 @@
+use std::sync::{
+    Arc, Mutex, MutexGuard,
+    atomic::{AtomicU64, Ordering},
+    mpsc::{self, Receiver, Sender},
+};
+
+use rdev::EventType;
+use shared::{InjectionStats, MouseMove, raw_keys::RawKeyInput, session::SessionType};
+
+use crate::{
+    audit::AuditLog,
+    bounds::{BoundedPointer, PointerBounds},
+    focus::FocusGuard,
+    framing::Frame,
+    keymap::for_injection,
+    mousemove::{SubPixelMotion, do_mouse_move, scroll_axes},
+    observers::Observers,
+    recording::InputRecording,
+    simulator::{Lane, SimulatorPool},
+};
+
+#[cfg(target_os = "linux")]
+use crate::{
+    keymap::{linux_keycode, warn_unmapped},
+    mousemove::{do_key, do_scroll},
+    raw_keyboard::RawKeyboard,
+};
+
+pub type Simulators = Arc<SimulatorPool>;
+
+#[cfg(target_os = "linux")]
+pub type DeviceInput = Arc<Mutex<Option<uinput::Device>>>;
+#[cfg(not(target_os = "linux"))]
+pub type DeviceInput = ();
+
+/// Where raw keys go; see [`crate::raw_keyboard`].
+#[cfg(target_os = "linux")]
+pub type RawKeyDevice = Arc<Mutex<Option<RawKeyboard>>>;
+#[cfg(not(target_os = "linux"))]
+pub type RawKeyDevice = ();
+
+/// Where decoded input ends up. Everything applied is also offered to
+/// observer connections, and everything decoded to the audit log and the
+/// recording if any.
+#[derive(Clone)]
+pub struct Injector {
+    target: Target,
+    observers: Observers,
+    audit: Option<AuditLog>,
+    recording: Option<InputRecording>,
+    counters: Arc<Counters>,
+    bounds: Option<Arc<Mutex<BoundedPointer>>>,
+    focus: Option<Arc<FocusGuard>>,
+    motion: Arc<Mutex<SubPixelMotion>>,
+    /// Taken for every key event, and across a whole [`Self::key_sequence`]
+    /// so keys from other streams can't land in the middle of one.
+    key_order: Arc<Mutex<()>>,
+}
+
+/// Running totals behind [`Injector::stats`].
+#[derive(Default)]
+struct Counters {
+    mouse_injected: AtomicU64,
+    mouse_dropped: AtomicU64,
+    keys_injected: AtomicU64,
+    keys_dropped: AtomicU64,
+}
+
+/// How the focus guard treats an input; see [`crate::focus`].
+#[derive(Clone, Copy)]
+enum Guarded {
+    /// Goes by the last poll.
+    Motion,
+    /// Looks again unless the last look is recent.
+    Press,
+    /// Always goes through.
+    Release,
+}
+
+impl Counters {
+    fn count(&self, mouse: bool, injected: bool) {
+        let counter = match (mouse, injected) {
+            (true, true) => &self.mouse_injected,
+            (true, false) => &self.mouse_dropped,
+            (false, true) => &self.keys_injected,
+            (false, false) => &self.keys_dropped,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone)]
+enum Target {
+    /// Replays input on this machine through the simulators and, on Linux,
+    /// the virtual mouse.
+    Live {
+        simulators: Simulators,
+        device_input: DeviceInput,
+        raw_keys: RawKeyDevice,
+    },
+    /// Dry run: decoded input is handed to a channel instead of the OS.
+    Capture(Sender<Frame>),
+}
+
+impl Injector {
+    pub fn live(simulators: Simulators, device_input: DeviceInput, raw_keys: RawKeyDevice) -> Self {
+        Self::new(Target::Live {
+            simulators,
+            device_input,
+            raw_keys,
+        })
+    }
+
+    /// An injector that only records, plus the receiving end of its log.
+    pub fn capture() -> (Self, Receiver<Frame>) {
+        let (sender, receiver) = mpsc::channel();
+        (Self::new(Target::Capture(sender)), receiver)
+    }
+
+    fn new(target: Target) -> Self {
+        Self {
+            target,
+            observers: Observers::default(),
+            audit: None,
+            recording: None,
+            counters: Arc::default(),
+            bounds: None,
+            focus: None,
+            motion: Arc::default(),
+            key_order: Arc::default(),
+        }
+    }
+
+    /// Also records every decoded input, with its sender, to `audit`.
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Also records every decoded frame, with its sender and when it
+    /// arrived, to `recording`.
+    pub fn with_recording(mut self, recording: InputRecording) -> Self {
+        self.recording = Some(recording);
+        self
+    }
+
+    /// Keeps the pointer inside `bounds`; see [`crate::bounds`].
+    pub fn with_pointer_bounds(mut self, bounds: PointerBounds) -> Self {
+        self.bounds = Some(Arc::new(Mutex::new(BoundedPointer::new(bounds))));
+        self
+    }
+
+    pub fn pointer_bounds(&self) -> Option<PointerBounds> {
+        self.bounds.as_ref().map(|pointer| lock_pointer(pointer).bounds())
+    }
+
+    /// Drops input while `guard` refuses it; see [`crate::focus`].
+    pub fn with_focus_guard(mut self, guard: Arc<FocusGuard>) -> Self {
+        self.focus = Some(guard);
+        self
+    }
+
+    pub fn focus_guard(&self) -> Option<&FocusGuard> {
+        self.focus.as_deref()
+    }
+
+    /// Whether the focus guard drops input now. Releases always go through,
+    /// so nothing stays held in a window that lost focus.
+    fn held_back(&self, kind: Guarded) -> bool {
+        let Some(guard) = &self.focus else {
+            return false;
+        };
+        match kind {
+            Guarded::Motion => !guard.allows(),
+            Guarded::Press => !guard.allows_press(),
+            Guarded::Release => false,
+        }
+    }
+
+    pub fn observers(&self) -> &Observers {
+        &self.observers
+    }
+
+    pub fn audit(&self) -> Option<&AuditLog> {
+        self.audit.as_ref()
+    }
+
+    pub fn recording(&self) -> Option<&InputRecording> {
+        self.recording.as_ref()
+    }
+
+    /// Totals since this injector was created. Events the OS later refused
+    /// to simulate count as dropped.
+    pub fn stats(&self) -> InjectionStats {
+        let counters = &self.counters;
+        let mut stats = InjectionStats {
+            mouse_injected: counters.mouse_injected.load(Ordering::Relaxed),
+            mouse_dropped: counters.mouse_dropped.load(Ordering::Relaxed),
+            keys_injected: counters.keys_injected.load(Ordering::Relaxed),
+            keys_dropped: counters.keys_dropped.load(Ordering::Relaxed),
+            ..InjectionStats::default()
+        };
+        if let Target::Live { simulators, .. } = &self.target {
+            // A shared simulator's failures and backlog can't be told
+            // apart by kind, so they all count as keys.
+            let keyboard = simulators.lane(Lane::Keyboard);
+            let (mouse_failed, mouse_queued) = if simulators.is_shared() {
+                (0, 0)
+            } else {
+                let pointer = simulators.lane(Lane::Pointer);
+                (pointer.failed(), pointer.queue_depth())
+            };
+            let keys_failed = keyboard.failed();
+            stats.mouse_injected = stats.mouse_injected.saturating_sub(mouse_failed);
+            stats.mouse_dropped += mouse_failed;
+            stats.keys_injected = stats.keys_injected.saturating_sub(keys_failed);
+            stats.keys_dropped += keys_failed;
+            stats.mouse_queued = mouse_queued;
+            stats.keys_queued = keyboard.queue_depth();
+        }
+        stats
+    }
+
+    pub fn mouse_move(&self, mouse_move: MouseMove) {
+        if self.held_back(Guarded::Motion) {
+            self.counters.count(true, false);
+            return;
+        }
+        let mouse_move = match &self.bounds {
+            Some(pointer) => lock_pointer(pointer).move_by(mouse_move),
+            None => mouse_move,
+        };
+        self.observers.mouse_move(mouse_move);
+        let injected = match &self.target {
+            Target::Live {
+                simulators,
+                device_input,
+                ..
+            } => {
+                #[cfg(target_os = "linux")]
+                {
+                    let _ = simulators;
+                    match device_input.lock() {
+                        Ok(mut maybe_device) => {
+                            if let Some(device) = maybe_device.as_mut() {
+                                let mut motion = lock_motion(&self.motion);
+                                match do_mouse_move(device, &mut motion, mouse_move) {
+                                    Ok(()) => true,
+                                    Err(err) => {
+                                        eprintln!("[server] failed to emit mouse move: {err}");
+                                        false
+                                    }
+                                }
+                            } else {
+                                eprintln!(
+                                    "[server] virtual mouse not available; dropping MouseMove"
+                                );
+                                false
+                            }
+                        }
+                        Err(poisoned) => {
+                            eprintln!("[server] virtual mouse mutex poisoned: {poisoned}");
+                            false
+                        }
+                    }
+                }
+
+                #[cfg(not(target_os = "linux"))]
+                {
+                    let _ = device_input;
+                    let bounds = self.pointer_bounds();
+                    let mut motion = lock_motion(&self.motion);
+                    do_mouse_move(simulators.lane(Lane::Pointer), &mut motion, mouse_move, bounds)
+                }
+            }
+            Target::Capture(sink) => record(sink, Frame::Mouse(mouse_move)),
+        };
+        self.counters.count(true, injected);
+    }
+
+    /// Whether [`Injector::absolute_move`] can place the pointer here. It
+    /// goes through rdev's simulation, which can't move the pointer of a
+    /// Wayland session.
+    pub fn supports_absolute(&self) -> bool {
+        match &self.target {
+            Target::Live { .. } => SessionType::detect().can_warp_pointer(),
+            Target::Capture(_) => true,
+        }
+    }
+
+    /// Puts the pointer at `x`, `y` in desktop coordinates.
+    pub fn absolute_move(&self, x: f64, y: f64) {
+        if self.held_back(Guarded::Motion) {
+            self.counters.count(true, false);
+            return;
+        }
+        let (x, y) = match &self.bounds {
+            Some(pointer) => lock_pointer(pointer).move_to(x, y),
+            None => (x, y),
+        };
+        let event_type = EventType::MouseMove { x, y };
+        self.observers.event(event_type);
+        let injected = match &self.target {
+            Target::Live { simulators, .. } => simulators.lane(Lane::Pointer).enqueue(event_type),
+            Target::Capture(sink) => record(sink, Frame::Event(event_type)),
+        };
+        self.counters.count(true, injected);
+    }
+
+    pub fn event(&self, event_type: EventType) {
+        let is_key = matches!(event_type, EventType::KeyPress(_) | EventType::KeyRelease(_));
+        let _order = is_key.then(|| lock_order(&self.key_order));
+        self.inject(event_type);
+    }
+
+    /// Injects `events` back to back, with no key event from anywhere else
+    /// in between, e.g. for a [`shared::key_combo::KeyCombo`].
+    pub fn key_sequence(&self, events: &[EventType]) {
+        let _order = lock_order(&self.key_order);
+        for event_type in events {
+            self.inject(*event_type);
+        }
+    }
+
+    /// Opens Windows' secure attention screen through `SendSAS`, which
+    /// Ctrl+Alt+Del injected as keys never does. Returns `false` anywhere
+    /// but a live Windows server, where the keys should be sent instead.
+    pub fn secure_attention(&self) -> bool {
+        #[cfg(target_os = "windows")]
+        if let Target::Live { .. } = &self.target {
+            let allowed = !self.held_back(Guarded::Press);
+            if allowed {
+                windows_sas::send();
+            }
+            self.counters.count(false, allowed);
+            return true;
+        }
+        false
+    }
+
+    fn inject(&self, event_type: EventType) {
+        let mouse = Lane::for_event(&event_type) == Lane::Pointer;
+        let kind = match event_type {
+            EventType::KeyRelease(_) | EventType::ButtonRelease(_) => Guarded::Release,
+            EventType::MouseMove { .. } => Guarded::Motion,
+            _ => Guarded::Press,
+        };
+        if self.held_back(kind) {
+            self.counters.count(mouse, false);
+            return;
+        }
+        self.observers.event(event_type);
+        let injected = match &self.target {
+            Target::Live {
+                simulators,
+                device_input,
+                ..
+            } => match event_type {
+                EventType::Wheel { delta_x, delta_y } => {
+                    scroll(simulators, device_input, delta_x, delta_y)
+                }
+                EventType::KeyPress(_) | EventType::KeyRelease(_) => {
+                    key(simulators, device_input, event_type)
+                }
+                _ => simulators.for_event(&event_type).enqueue(event_type),
+            },
+            Target::Capture(sink) => record(sink, Frame::Event(event_type)),
+        };
+        self.counters.count(mouse, injected);
+    }
+
+    /// Emits `input` by its code on the raw key device. There is nothing
+    /// else it could go through, so it is dropped where there is no such
+    /// device. Not offered to observers, which only understand rdev events.
+    pub fn raw_key(&self, input: RawKeyInput) {
+        let kind = match input.pressed {
+            true => Guarded::Press,
+            false => Guarded::Release,
+        };
+        if self.held_back(kind) {
+            self.counters.count(false, false);
+            return;
+        }
+        let injected = match &self.target {
+            Target::Live { raw_keys, .. } => raw_key(raw_keys, input),
+            Target::Capture(sink) => record(sink, Frame::RawKey(input)),
+        };
+        self.counters.count(false, injected);
+    }
+}
+
+fn lock_order(order: &Mutex<()>) -> MutexGuard<'_, ()> {
+    order.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn lock_motion(motion: &Mutex<SubPixelMotion>) -> MutexGuard<'_, SubPixelMotion> {
+    motion.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn lock_pointer(pointer: &Mutex<BoundedPointer>) -> MutexGuard<'_, BoundedPointer> {
+    pointer.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// What won't be injected as expected in `session`, given whether the
+/// virtual input device could be created. That device reaches every
+/// session on Linux; without it input falls back to rdev's simulation,
+/// which only X clients see.
+pub fn session_warnings(session: SessionType, virtual_device: bool) -> Vec<&'static str> {
+    let mut warnings = Vec::new();
+    match session {
+        SessionType::Wayland { xwayland } if !virtual_device => {
+            warnings.push(if xwayland {
+                "without the virtual device, input only reaches XWayland windows; \
+                 give this user write access to /dev/uinput to reach native Wayland ones"
+            } else {
+                "without the virtual device or XWayland nothing can be injected; \
+                 give this user write access to /dev/uinput"
+            });
+        }
+        SessionType::Headless if !virtual_device => {
+            warnings.push(
+                "without the virtual device or a display server nothing can be injected; \
+                 give this user write access to /dev/uinput",
+            );
+        }
+        _ => {}
+    }
+    if session.is_wayland() {
+        warnings.push("Wayland won't let the pointer be placed, so absolute pointer mode is refused");
+    }
+    warnings
+}
+
+/// Emits each wheel axis separately: through the virtual mouse's wheels on
+/// Linux when it is available, through the mouse simulator otherwise.
+/// False if some axis couldn't be queued.
+fn scroll(
+    simulators: &Simulators,
+    device_input: &DeviceInput,
+    delta_x: i64,
+    delta_y: i64,
+) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let mut maybe_device = device_input
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(device) = maybe_device.as_mut() {
+            match do_scroll(device, delta_x, delta_y) {
+                Ok(()) => return true,
+                Err(err) => eprintln!("[server] failed to emit scroll: {err}; simulating it"),
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    let _ = device_input;
+
+    let mut queued = true;
+    for scroll in scroll_axes(delta_x, delta_y) {
+        queued &= simulators.lane(Lane::Pointer).enqueue(scroll.event());
+    }
+    queued
+}
+
+/// Injects a key through the virtual device on Linux when it is available
+/// and the key has a code there (see [`crate::keymap`]), through the
+/// keyboard simulator otherwise.
+fn key(simulators: &Simulators, device_input: &DeviceInput, event_type: EventType) -> bool {
+    let Some(event_type) = for_injection(event_type) else {
+        return false;
+    };
+
+    #[cfg(target_os = "linux")]
+    if let EventType::KeyPress(key) | EventType::KeyRelease(key) = event_type {
+        let pressed = matches!(event_type, EventType::KeyPress(_));
+        let mut maybe_device = device_input
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(device) = maybe_device.as_mut() {
+            match linux_keycode(key) {
+                Some(code) => match do_key(device, code, pressed) {
+                    Ok(()) => return true,
+                    Err(err) => eprintln!("[server] failed to emit {key:?}: {err}; simulating it"),
+                },
+                None => warn_unmapped(key),
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    let _ = device_input;
+
+    simulators.lane(Lane::Keyboard).enqueue(event_type)
+}
+
+#[cfg(target_os = "linux")]
+fn raw_key(raw_keys: &RawKeyDevice, input: RawKeyInput) -> bool {
+    let mut maybe_keyboard = raw_keys
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(keyboard) = maybe_keyboard.as_mut() else {
+        eprintln!("[server] raw key device not available; dropping raw key {}", input.code);
+        return false;
+    };
+    match keyboard.key(input.code, input.pressed) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("[server] failed to emit raw key {}: {err}", input.code);
+            false
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn raw_key(_raw_keys: &RawKeyDevice, input: RawKeyInput) -> bool {
+    eprintln!(
+        "[server] raw key {} needs a Linux virtual device; dropping it",
+        input.code
+    );
+    false
+}
+
+#[cfg(target_os = "windows")]
+mod windows_sas {
+    #[link(name = "sas")]
+    unsafe extern "system" {
+        fn SendSAS(as_user: i32);
+    }
+
+    /// Does nothing unless the `SoftwareSASGeneration` policy lets
+    /// applications generate the sequence and the server has `uiAccess`;
+    /// Windows doesn't say either way.
+    pub fn send() {
+        unsafe { SendSAS(1) }
+    }
+}
+
+fn record(sink: &Sender<Frame>, frame: Frame) -> bool {
+    if sink.send(frame).is_err() {
+        eprintln!("[server] capture log closed; dropping decoded input");
+        return false;
+    }
+    true
+}
@@ -0,0 +1,284 @@
+//! Which Linux input event code (`KEY_*`) each `rdev` key is injected as
+//! through the virtual device. Named keys map by position, as rdev names
+//! them after a US QWERTY layout; `Key::Unknown` only maps when it is one
+//! of this platform's [`ExtraKey`]s, as other codes mean nothing off the
+//! client's OS.
+//!
+//! Keys without a code here are still simulated through rdev, and logged
+//! once so a missing mapping shows up instead of the key silently doing
+//! nothing.
+
+use std::{
+    collections::HashSet,
+    sync::{Mutex, OnceLock},
+};
+
+use rdev::{EventType, Key};
+use shared::{extra_keys::ExtraKey, system_keys::injection_key};
+
+/// Every key rdev names, for checking the table covers them all.
+pub const NAMED_KEYS: [Key; 105] = [
+    Key::Alt,
+    Key::AltGr,
+    Key::Backspace,
+    Key::CapsLock,
+    Key::ControlLeft,
+    Key::ControlRight,
+    Key::Delete,
+    Key::DownArrow,
+    Key::End,
+    Key::Escape,
+    Key::F1,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::Home,
+    Key::LeftArrow,
+    Key::MetaLeft,
+    Key::MetaRight,
+    Key::PageDown,
+    Key::PageUp,
+    Key::Return,
+    Key::RightArrow,
+    Key::ShiftLeft,
+    Key::ShiftRight,
+    Key::Space,
+    Key::Tab,
+    Key::UpArrow,
+    Key::PrintScreen,
+    Key::ScrollLock,
+    Key::Pause,
+    Key::NumLock,
+    Key::BackQuote,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::Num0,
+    Key::Minus,
+    Key::Equal,
+    Key::KeyQ,
+    Key::KeyW,
+    Key::KeyE,
+    Key::KeyR,
+    Key::KeyT,
+    Key::KeyY,
+    Key::KeyU,
+    Key::KeyI,
+    Key::KeyO,
+    Key::KeyP,
+    Key::LeftBracket,
+    Key::RightBracket,
+    Key::KeyA,
+    Key::KeyS,
+    Key::KeyD,
+    Key::KeyF,
+    Key::KeyG,
+    Key::KeyH,
+    Key::KeyJ,
+    Key::KeyK,
+    Key::KeyL,
+    Key::SemiColon,
+    Key::Quote,
+    Key::BackSlash,
+    Key::IntlBackslash,
+    Key::KeyZ,
+    Key::KeyX,
+    Key::KeyC,
+    Key::KeyV,
+    Key::KeyB,
+    Key::KeyN,
+    Key::KeyM,
+    Key::Comma,
+    Key::Dot,
+    Key::Slash,
+    Key::Insert,
+    Key::KpReturn,
+    Key::KpMinus,
+    Key::KpPlus,
+    Key::KpMultiply,
+    Key::KpDivide,
+    Key::Kp0,
+    Key::Kp1,
+    Key::Kp2,
+    Key::Kp3,
+    Key::Kp4,
+    Key::Kp5,
+    Key::Kp6,
+    Key::Kp7,
+    Key::Kp8,
+    Key::Kp9,
+    Key::KpDelete,
+    Key::Function,
+];
+
+/// The Linux input event code for `key`, or `None` if it has none.
+pub fn linux_keycode(key: Key) -> Option<u16> {
+    let code = match key {
+        Key::Escape => 1,
+        Key::Num1 => 2,
+        Key::Num2 => 3,
+        Key::Num3 => 4,
+        Key::Num4 => 5,
+        Key::Num5 => 6,
+        Key::Num6 => 7,
+        Key::Num7 => 8,
+        Key::Num8 => 9,
+        Key::Num9 => 10,
+        Key::Num0 => 11,
+        Key::Minus => 12,
+        Key::Equal => 13,
+        Key::Backspace => 14,
+        Key::Tab => 15,
+        Key::KeyQ => 16,
+        Key::KeyW => 17,
+        Key::KeyE => 18,
+        Key::KeyR => 19,
+        Key::KeyT => 20,
+        Key::KeyY => 21,
+        Key::KeyU => 22,
+        Key::KeyI => 23,
+        Key::KeyO => 24,
+        Key::KeyP => 25,
+        Key::LeftBracket => 26,
+        Key::RightBracket => 27,
+        Key::Return => 28,
+        Key::ControlLeft => 29,
+        Key::KeyA => 30,
+        Key::KeyS => 31,
+        Key::KeyD => 32,
+        Key::KeyF => 33,
+        Key::KeyG => 34,
+        Key::KeyH => 35,
+        Key::KeyJ => 36,
+        Key::KeyK => 37,
+        Key::KeyL => 38,
+        Key::SemiColon => 39,
+        Key::Quote => 40,
+        Key::BackQuote => 41,
+        Key::ShiftLeft => 42,
+        Key::BackSlash => 43,
+        Key::KeyZ => 44,
+        Key::KeyX => 45,
+        Key::KeyC => 46,
+        Key::KeyV => 47,
+        Key::KeyB => 48,
+        Key::KeyN => 49,
+        Key::KeyM => 50,
+        Key::Comma => 51,
+        Key::Dot => 52,
+        Key::Slash => 53,
+        Key::ShiftRight => 54,
+        Key::KpMultiply => 55,
+        Key::Alt => 56,
+        Key::Space => 57,
+        Key::CapsLock => 58,
+        Key::F1 => 59,
+        Key::F2 => 60,
+        Key::F3 => 61,
+        Key::F4 => 62,
+        Key::F5 => 63,
+        Key::F6 => 64,
+        Key::F7 => 65,
+        Key::F8 => 66,
+        Key::F9 => 67,
+        Key::F10 => 68,
+        Key::NumLock => 69,
+        Key::ScrollLock => 70,
+        Key::Kp7 => 71,
+        Key::Kp8 => 72,
+        Key::Kp9 => 73,
+        Key::KpMinus => 74,
+        Key::Kp4 => 75,
+        Key::Kp5 => 76,
+        Key::Kp6 => 77,
+        Key::KpPlus => 78,
+        Key::Kp1 => 79,
+        Key::Kp2 => 80,
+        Key::Kp3 => 81,
+        Key::Kp0 => 82,
+        Key::KpDelete => 83,
+        Key::IntlBackslash => 86,
+        Key::F11 => 87,
+        Key::F12 => 88,
+        Key::KpReturn => 96,
+        Key::ControlRight => 97,
+        Key::KpDivide => 98,
+        Key::PrintScreen => 99,
+        Key::AltGr => 100,
+        Key::Home => 102,
+        Key::UpArrow => 103,
+        Key::PageUp => 104,
+        Key::LeftArrow => 105,
+        Key::RightArrow => 106,
+        Key::End => 107,
+        Key::DownArrow => 108,
+        Key::PageDown => 109,
+        Key::Insert => 110,
+        Key::Delete => 111,
+        Key::Pause => 119,
+        Key::MetaLeft => 125,
+        Key::MetaRight => 126,
+        Key::Function => 464,
+        Key::Unknown(_) => return ExtraKey::from_key(key).map(ExtraKey::linux_code),
+    };
+    Some(code)
+}
+
+/// `event_type` as it should be injected here: system keys may be swapped
+/// for the key that does their job on this platform (see
+/// [`shared::system_keys`]), or dropped when nothing can stand in for
+/// them. Either is logged the first time the key is seen.
+pub fn for_injection(event_type: EventType) -> Option<EventType> {
+    let (key, pressed) = match event_type {
+        EventType::KeyPress(key) => (key, true),
+        EventType::KeyRelease(key) => (key, false),
+        other => return Some(other),
+    };
+    let injected = match injection_key(key) {
+        Ok(injected) => injected,
+        Err(reason) => {
+            warn_once(key, || format!("[server] dropping {key:?}: {reason}"));
+            return None;
+        }
+    };
+    if injected != key {
+        warn_once(key, || format!("[server] injecting {key:?} as {injected:?}"));
+    }
+    Some(if pressed {
+        EventType::KeyPress(injected)
+    } else {
+        EventType::KeyRelease(injected)
+    })
+}
+
+/// Logs that `key` has no mapping, the first time it is seen.
+pub fn warn_unmapped(key: Key) {
+    warn_once(key, || {
+        format!("[server] no keycode for {key:?}; simulating it through rdev")
+    });
+}
+
+fn warn_once(key: Key, message: impl FnOnce() -> String) {
+    static WARNED: OnceLock<Mutex<HashSet<Key>>> = OnceLock::new();
+    let mut warned = WARNED
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if warned.insert(key) {
+        eprintln!("{}", message());
+    }
+}
@@ -0,0 +1,59 @@
+use std::{collections::VecDeque, time::Duration};
+
+/// How many recent samples the rolling average covers.
+pub const LATENCY_WINDOW: usize = 100;
+
+/// End-to-end input latency for one connection, from the client's
+/// [`shared::SentAt`] stamps.
+///
+/// Client and server clocks are unrelated, so nothing is measured until the
+/// hello has given an offset between them.
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    /// Server time minus client time, in microseconds.
+    offset: Option<i64>,
+    samples: VecDeque<Duration>,
+    total: Duration,
+    recorded: u64,
+}
+
+impl LatencyStats {
+    /// Lines the clocks up from a hello the client stamped `client_micros`
+    /// and we read at `server_micros`, after it spent `one_way` in flight.
+    pub fn sync_clock(&mut self, client_micros: u64, server_micros: u64, one_way: Duration) {
+        let one_way = i64::try_from(one_way.as_micros()).unwrap_or(i64::MAX);
+        let offset = i128::from(server_micros) - i128::from(client_micros) - i128::from(one_way);
+        self.offset = i64::try_from(offset).ok();
+    }
+
+    /// Records input the client stamped `sent_micros` and we applied at
+    /// `applied_micros`. `None` until the clocks have been synced.
+    pub fn record(&mut self, sent_micros: u64, applied_micros: u64) -> Option<Duration> {
+        let offset = self.offset?;
+        let sent = i128::from(sent_micros) + i128::from(offset);
+        // Small offset errors can put the send after the receive; call it zero.
+        let micros = u64::try_from(i128::from(applied_micros) - sent).unwrap_or(0);
+        let latency = Duration::from_micros(micros);
+
+        if self.samples.len() == LATENCY_WINDOW {
+            self.total -= self.samples.pop_front().unwrap_or_default();
+        }
+        self.samples.push_back(latency);
+        self.total += latency;
+        self.recorded += 1;
+        Some(latency)
+    }
+
+    /// Mean over the last [`LATENCY_WINDOW`] samples.
+    pub fn average(&self) -> Option<Duration> {
+        let count = u32::try_from(self.samples.len())
+            .ok()
+            .filter(|count| *count > 0)?;
+        Some(self.total / count)
+    }
+
+    /// Samples recorded since the connection started.
+    pub fn recorded(&self) -> u64 {
+        self.recorded
+    }
+}
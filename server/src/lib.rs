@@ -0,0 +1,39 @@
+//! QUICinput server: accepts QUIC connections and replays the input they carry.
+
+pub mod allowlist;
+pub mod audit;
+pub mod availability;
+pub mod bounds;
+pub mod chunked;
+pub mod cli;
+pub mod clicks;
+pub mod config;
+pub mod control_http;
+#[cfg(feature = "mdns")]
+mod discovery;
+pub mod displays;
+pub mod focus;
+pub mod framing;
+pub mod gesture;
+pub mod held;
+pub mod inject;
+pub mod keymap;
+pub mod latency;
+pub mod listen;
+pub mod loadconfig;
+pub mod mapping;
+pub mod migration;
+pub mod mousemove;
+pub mod observers;
+pub mod orientation;
+#[cfg(target_os = "linux")]
+pub mod raw_keyboard;
+pub mod recording;
+pub mod sequence;
+pub mod service;
+pub mod server;
+pub mod sessions;
+pub mod simulator;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod transport;
@@ -0,0 +1,72 @@
+//! Binding the sockets the server listens on. An unspecified IPv4 address
+//! (`0.0.0.0`, the default) means every interface, IPv6 ones included, so
+//! it binds `[::]` with IPv4-mapped addresses allowed, which takes both
+//! families on one socket.
+//!
+//! Not every platform allows that: some (e.g. OpenBSD) refuse to turn
+//! `IPV6_V6ONLY` off, and hosts without IPv6 can't open the socket at all.
+//! Those get a separate IPv4 socket, plus an IPv6-only one where IPv6
+//! exists. Any other address is bound as given.
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+/// A socket ready to hand to quinn.
+#[derive(Debug)]
+pub struct BoundSocket {
+    pub socket: UdpSocket,
+    /// Takes IPv4 as well as IPv6.
+    pub dual_stack: bool,
+}
+
+impl BoundSocket {
+    fn single(socket: UdpSocket) -> Self {
+        Self {
+            socket,
+            dual_stack: false,
+        }
+    }
+}
+
+/// The sockets listening on `addr`: one, or two when every interface was
+/// asked for and one socket can't take both families.
+pub fn bind_sockets(addr: SocketAddr) -> io::Result<Vec<BoundSocket>> {
+    if addr.ip() != IpAddr::V4(Ipv4Addr::UNSPECIFIED) {
+        return Ok(vec![BoundSocket::single(UdpSocket::bind(addr)?)]);
+    }
+
+    let any_v6 = SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), addr.port());
+    match bind_v6(any_v6, false) {
+        Ok(socket) => {
+            return Ok(vec![BoundSocket {
+                socket,
+                dual_stack: true,
+            }]);
+        }
+        Err(err) => eprintln!(
+            "[server] no dual-stack socket on {any_v6} ({err}); binding IPv4 and IPv6 separately"
+        ),
+    }
+
+    let v4 = UdpSocket::bind(addr)?;
+    // With port 0 the OS picked one; IPv6 listens on the same.
+    let port = v4.local_addr()?.port();
+    match bind_v6(SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), port), true) {
+        Ok(v6) => Ok(vec![BoundSocket::single(v4), BoundSocket::single(v6)]),
+        Err(err) => {
+            eprintln!("[server] IPv6 unavailable ({err}); listening on IPv4 only");
+            Ok(vec![BoundSocket::single(v4)])
+        }
+    }
+}
+
+fn bind_v6(addr: SocketAddr, only_v6: bool) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_only_v6(only_v6)?;
+    socket.bind(&addr.into())?;
+    Ok(socket.into())
+}
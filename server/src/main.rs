@@ -2,53 +2,236 @@ use std::{
     env,
     error::Error,
     net::{SocketAddr},
+    process::ExitCode,
     sync::Arc,
+    thread,
+    time::Duration,
 };
 
 #[cfg(target_os = "linux")]
 use std::sync::Mutex;
 
-mod simulator;
-mod mousemove;
-mod server;
-mod loadconfig;
-mod config;
-
-use crate::{config::QUICInputConfig, simulator::EventSimulator};
-use crate::server::{run_server, DeviceInput, Simulators};
+use server::{
+    audit::{AuditLog, AuditOptions},
+    availability::LogindAvailability,
+    cli,
+    config::QUICInputConfig,
+    control_http::ControlHttp,
+    displays::{DisplaySource, FakeDisplays, SystemDisplays},
+    focus::{FOCUS_POLL_INTERVAL, FocusGuard, SystemFocus},
+    inject::{Injector, Simulators, session_warnings},
+    loadconfig,
+    recording::InputRecording,
+    server::{ServerOptions, StreamOptions, run_server_until},
+    service::{self, StartupError},
+    simulator::{LaneLayout, SimulatorPool, WatchdogOptions, spawn_watchdog},
+};
+use shared::{layout::LayoutTable, session::SessionType};
 
+#[cfg(not(target_os = "linux"))]
+use server::inject::{DeviceInput, RawKeyDevice};
 #[cfg(target_os = "linux")]
-use crate::mousemove::create_virtual_mouse;
+use server::{mousemove::create_virtual_mouse, raw_keyboard::RawKeyboard};
 #[cfg(target_os = "linux")]
-use crate::server::ensure_uinput_available;
+use server::server::{ensure_uinput_available, ensure_uinput_writable};
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    let args: Vec<String> = env::args().collect();
-    let quicconfig = if let Some(config_file) = args.get(1) {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("[server] {err}");
+            ExitCode::from(service::exit_code(err.as_ref()))
+        }
+    }
+}
+
+async fn run() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let args = cli::parse_args(env::args().skip(1))?;
+    let quicconfig = if let Some(config_file) = &args.config_file {
         println!("Config File: {}", config_file);
         loadconfig::load_config(config_file)
     } else {
         println!("No config file! Using defaults");
         QUICInputConfig::default()
     };
-    let addr = SocketAddr::new(quicconfig.broadcastip, quicconfig.port);
-    let simulators: Simulators = Arc::new([EventSimulator::new(), EventSimulator::new()]);
+    let addrs = if args.binds.is_empty() {
+        vec![SocketAddr::new(quicconfig.broadcastip, quicconfig.port)]
+    } else {
+        args.binds
+    };
+    let (injector, displays): (Injector, Arc<dyn DisplaySource>) = if args.dry_run {
+        println!("[server] dry run: decoded input is logged, not injected");
+        (dry_run_injector(), Arc::new(FakeDisplays::default()))
+    } else {
+        (
+            live_injector(
+                quicconfig.simulator_lanes,
+                WatchdogOptions {
+                    stall_after: Duration::from_secs(quicconfig.simulator_stall_secs),
+                    restart: quicconfig.simulator_restart,
+                },
+            )?,
+            Arc::new(SystemDisplays),
+        )
+    };
+    let injector = match args.audit_log {
+        Some(path) => injector.with_audit(AuditLog::start(AuditOptions {
+            path,
+            max_bytes: quicconfig.audit_log_max_bytes,
+            redact_keys: args.audit_no_keys,
+        })?),
+        None => injector,
+    };
+    let injector = match args.record {
+        Some(path) => injector.with_recording(InputRecording::start(&path)?),
+        None => injector,
+    };
+    let injector = match args.pointer_bounds {
+        Some(bounds) => {
+            println!("[server] keeping the pointer within {bounds} (x,y,w,h)");
+            injector.with_pointer_bounds(bounds)
+        }
+        None => injector,
+    };
+    let injector = match args.only_when_focused {
+        Some(title) => {
+            println!("[server] only taking input while a window titled '*{title}*' is focused");
+            injector.with_focus_guard(FocusGuard::start(
+                title,
+                Arc::new(SystemFocus),
+                FOCUS_POLL_INTERVAL,
+            ))
+        }
+        None => injector,
+    };
+
+    if args.debug_raw {
+        println!("[server] debug raw: logging uni stream bytes and how they decode");
+    }
+
+    if let Some(addr) = args.control_http {
+        let control = ControlHttp::bind(addr).await?;
+        tokio::spawn(control.run(injector.clone()));
+    }
+
+    let mut options = ServerOptions::new(injector)
+        .with_binds(addrs)
+        .with_max_connections(quicconfig.max_connections)
+        .with_stream_options(StreamOptions {
+            verbose_events: args.verbose_events,
+            debug_raw: args.debug_raw,
+            keyboard_layout: LayoutTable::by_name(&quicconfig.keyboard_layout),
+            max_streams: quicconfig.max_streams_per_connection,
+            ..StreamOptions::default()
+        })
+        .with_idle_release(match quicconfig.idle_release_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        })
+        .with_button_hold_limit(match quicconfig.button_hold_limit_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        })
+        .with_pointer_sensitivity(quicconfig.pointer_sensitivity)
+        .with_absolute_mapping(quicconfig.absolute_mapping)
+        .with_max_inputs_per_sec(match quicconfig.max_inputs_per_sec {
+            0 => None,
+            per_sec => Some(per_sec),
+        })
+        .with_double_click_window(match quicconfig.double_click_ms {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        })
+        .with_resume_grace(Duration::from_secs(quicconfig.resume_grace_secs))
+        .with_transport(quicconfig.transport.options())
+        .with_log_refused_peers(quicconfig.log_refused_peers)
+        .with_displays(displays)
+        .with_on_ready(Arc::new(service::notify_ready));
+    if !quicconfig.orientation.is_identity() {
+        println!("[server] display {}; turning pointer input to match", quicconfig.orientation);
+        options = options.with_orientation(quicconfig.orientation);
+    }
+    if quicconfig.report_availability {
+        if cfg!(target_os = "linux") {
+            println!("[server] telling clients when the screen is locked");
+            options = options.with_availability(Arc::new(LogindAvailability));
+        } else {
+            eprintln!("[server] report_availability needs systemd-logind; ignoring it");
+        }
+    }
+    if !quicconfig.allowed_peers.is_empty() {
+        options = options.with_allowed_peers(quicconfig.allowed_peers.clone());
+    }
+    if let Some(certificate) = quicconfig.certificate() {
+        options = options.with_certificate(certificate);
+    }
+    if let Some(name) = args.advertise {
+        if !cfg!(feature = "mdns") {
+            eprintln!("[server] --advertise needs the mdns feature; not advertising");
+        }
+        options = options.with_advertise(name);
+    }
+
+    let shutdown = service::shutdown_signal()?;
+    run_server_until(options, async {
+        let signal = shutdown.await;
+        println!("[server] received {signal}");
+    })
+    .await
+}
+
+fn live_injector(lanes: LaneLayout, watchdog: WatchdogOptions) -> Result<Injector, StartupError> {
+    let simulators: Simulators = Arc::new(SimulatorPool::new(lanes));
+    if !watchdog.stall_after.is_zero() {
+        spawn_watchdog(Arc::clone(&simulators), watchdog);
+    }
 
     #[cfg(target_os = "linux")]
     let device_input = {
-        ensure_uinput_available();
+        ensure_uinput_available()?;
         match create_virtual_mouse() {
             Ok(device) => Arc::new(Mutex::new(Some(device))),
             Err(err) => {
+                ensure_uinput_writable(std::path::Path::new("/dev/uinput"))?;
                 eprintln!("[server] failed to create virtual mouse: {err}");
                 Arc::new(Mutex::new(None))
             }
         }
     };
 
+    #[cfg(target_os = "linux")]
+    let raw_keys = match RawKeyboard::create() {
+        Ok(keyboard) => Arc::new(Mutex::new(Some(keyboard))),
+        Err(err) => {
+            eprintln!("[server] failed to create raw key device: {err}");
+            Arc::new(Mutex::new(None))
+        }
+    };
+
+    #[cfg(target_os = "linux")]
+    let virtual_device = device_input.lock().is_ok_and(|device| device.is_some());
+    #[cfg(not(target_os = "linux"))]
+    let virtual_device = false;
+
+    let session = SessionType::detect();
+    println!("[server] desktop session: {session}");
+    for warning in session_warnings(session, virtual_device) {
+        eprintln!("[server] {warning}");
+    }
+
     #[cfg(not(target_os = "linux"))]
-    let device_input: DeviceInput = ();
+    let (device_input, raw_keys): (DeviceInput, RawKeyDevice) = ((), ());
+
+    Ok(Injector::live(simulators, device_input, raw_keys))
+}
 
-    run_server(addr, quicconfig.max_connections, simulators, device_input).await
+fn dry_run_injector() -> Injector {
+    let (injector, log) = Injector::capture();
+    thread::spawn(move || {
+        for frame in log {
+            println!("[server] dry run: {frame:?}");
+        }
+    });
+    injector
 }
@@ -0,0 +1,64 @@
+//! Where an [`AbsoluteMove`] lands on the server's display. Positions are
+//! fractions of the client's display, so when the two displays differ in
+//! aspect ratio, plain scaling stretches shapes: a circle traced on the
+//! client comes out an ellipse. [`AbsoluteMapping`] picks how to deal with
+//! that, given the client's size from its hello.
+
+use serde::{Deserialize, Serialize};
+use shared::{AbsoluteMove, DisplayInfo, DisplaySize};
+
+/// How the client's display is laid over the server's in absolute pointer
+/// mode. All three agree when the displays are the same size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AbsoluteMapping {
+    /// Scale each axis on its own so the client's display covers the whole
+    /// server display, distorting it if the aspect ratios differ.
+    #[default]
+    Stretch,
+    /// Scale both axes evenly to the largest centred area with the client's
+    /// aspect ratio. The bars either side of it can't be reached.
+    Letterbox,
+    /// Move pixel for pixel, centred on the server display and clamped to
+    /// its edges where the client's is larger.
+    Clamp,
+}
+
+impl AbsoluteMapping {
+    /// The point on `display` that `absolute` stands for, clamped so it
+    /// always lands on screen. Without the client's size there is no aspect
+    /// ratio to keep, so this stretches whatever the mapping.
+    pub fn place(
+        self,
+        display: &DisplayInfo,
+        client: Option<DisplaySize>,
+        absolute: AbsoluteMove,
+    ) -> (f64, f64) {
+        let client = match client {
+            Some(client) if client.width > 1 && client.height > 1 => client,
+            _ => return display.point_at(absolute),
+        };
+        // Spans, as in `DisplayInfo::point_at`: a fraction of 1.0 is the
+        // last pixel, not one past it.
+        let (display_width, display_height) = span(display.width, display.height);
+        let (client_width, client_height) = span(client.width, client.height);
+        let (width, height) = match self {
+            AbsoluteMapping::Stretch => return display.point_at(absolute),
+            AbsoluteMapping::Letterbox => {
+                let scale = (display_width / client_width).min(display_height / client_height);
+                (client_width * scale, client_height * scale)
+            }
+            AbsoluteMapping::Clamp => (client_width, client_height),
+        };
+        let left = f64::from(display.x) + (display_width - width) / 2.0;
+        let top = f64::from(display.y) + (display_height - height) / 2.0;
+        display.clamp(left + absolute.x * width, top + absolute.y * height)
+    }
+}
+
+fn span(width: u32, height: u32) -> (f64, f64) {
+    (
+        f64::from(width.saturating_sub(1)),
+        f64::from(height.saturating_sub(1)),
+    )
+}
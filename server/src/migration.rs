@@ -0,0 +1,35 @@
+//! Noticing a client that carries its connection over to a new address,
+//! e.g. a laptop moving from Wi-Fi to Ethernet. quinn follows the client by
+//! itself, once the new path answers, but says nothing when it does, so the
+//! address is checked now and then instead.
+
+use std::{net::SocketAddr, time::Duration};
+
+use quinn::Connection;
+
+/// How often the client's address is checked.
+pub const MIGRATION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Calls `on_move` with the old and new address each time the client's
+/// changes, checking every `interval` until the connection closes. Returns
+/// how many moves it saw.
+pub async fn watch_migrations<F>(connection: Connection, interval: Duration, mut on_move: F) -> u32
+where
+    F: FnMut(SocketAddr, SocketAddr),
+{
+    let mut ticker = tokio::time::interval(interval);
+    let mut current = connection.remote_address();
+    let mut moves = 0;
+    loop {
+        tokio::select! {
+            _ = connection.closed() => return moves,
+            _ = ticker.tick() => {}
+        }
+        let now = connection.remote_address();
+        if now != current {
+            on_move(current, now);
+            current = now;
+            moves += 1;
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use rdev::EventType;
 use shared::MouseMove;
 
 #[cfg(target_os = "linux")]
@@ -8,6 +9,8 @@ use uinput::event::controller::Controller::Mouse;
 use uinput::event::controller::Mouse::Left;
 #[cfg(target_os = "linux")]
 use uinput::event::Event::{Controller};
+#[cfg(target_os = "linux")]
+use uinput::event::keyboard::Keyboard;
 
 #[cfg(target_os = "linux")]
 pub fn create_virtual_mouse() -> Result<uinput::Device, uinput::Error> {
@@ -16,34 +19,161 @@ pub fn create_virtual_mouse() -> Result<uinput::Device, uinput::Error> {
         .event(Controller(Mouse(Left))).unwrap()
         .event(relative::Position::X)?
         .event(relative::Position::Y)?
+        .event(relative::Wheel::Vertical)?
+        .event(relative::Wheel::Horizontal)?
+        .event(Keyboard::All)?
         .create()
 }
 
+/// `EV_KEY` from the kernel's input event codes.
+#[cfg(target_os = "linux")]
+const EV_KEY: i32 = 1;
+
+/// Presses or releases the key with Linux input event code `code` (see
+/// [`crate::keymap`]) on the virtual device, which reaches Wayland sessions
+/// and the console as well as X11.
 #[cfg(target_os = "linux")]
-pub fn do_mouse_move(device: &mut uinput::Device, mousemove: MouseMove) -> Result<(), uinput::Error> {
-    device.position(&relative::Position::X, mousemove.dx.ceil() as i32)?;
-    device.position(&relative::Position::Y, mousemove.dy.ceil() as i32)?;
+pub fn do_key(device: &mut uinput::Device, code: u16, pressed: bool) -> Result<(), uinput::Error> {
+    device.write(EV_KEY, i32::from(code), i32::from(pressed))?;
     device.synchronize()?;
     Ok(())
 }
 
+/// Moves by the whole pixels `mousemove` adds to what `motion` has carried
+/// over, if any.
+#[cfg(target_os = "linux")]
+pub fn do_mouse_move(
+    device: &mut uinput::Device,
+    motion: &mut SubPixelMotion,
+    mousemove: MouseMove,
+) -> Result<(), uinput::Error> {
+    let (dx, dy) = motion.take_whole(mousemove);
+    if (dx, dy) == (0, 0) {
+        return Ok(());
+    }
+    device.position(&relative::Position::X, dx)?;
+    device.position(&relative::Position::Y, dy)?;
+    device.synchronize()?;
+    Ok(())
+}
+
+/// Scrolls each axis on its own wheel, so horizontal scroll is never lost
+/// behind (or folded into) the vertical one.
+#[cfg(target_os = "linux")]
+pub fn do_scroll(device: &mut uinput::Device, delta_x: i64, delta_y: i64) -> Result<(), uinput::Error> {
+    for scroll in scroll_axes(delta_x, delta_y) {
+        match scroll {
+            Scroll::Vertical(notches) => device.position(&relative::Wheel::Vertical, clamp_notches(notches))?,
+            Scroll::Horizontal(notches) => device.position(&relative::Wheel::Horizontal, clamp_notches(notches))?,
+        }
+    }
+    device.synchronize()?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn clamp_notches(notches: i64) -> i32 {
+    notches.clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32
+}
+
+/// Motion smaller than a pixel, carried from one move to the next. The
+/// pointer only moves in whole pixels, so a hi-dpi client's fractional
+/// deltas would otherwise be rounded away and the pointer drift from where
+/// the client's went. Kept per injector, as each drives one pointer.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SubPixelMotion {
+    x: f64,
+    y: f64,
+}
+
+impl SubPixelMotion {
+    /// Adds `mousemove` to what is carried over and takes out the whole
+    /// pixels, keeping the fraction left for next time.
+    pub fn take_whole(&mut self, mousemove: MouseMove) -> (i32, i32) {
+        let (dx, x) = whole_pixels(self.x + mousemove.dx);
+        let (dy, y) = whole_pixels(self.y + mousemove.dy);
+        (self.x, self.y) = (x, y);
+        (dx, dy)
+    }
+
+    /// What is carried over, always less than a pixel each way.
+    pub fn remainder(&self) -> MouseMove {
+        MouseMove {
+            dx: self.x,
+            dy: self.y,
+        }
+    }
+}
+
+/// Splits `total` into whole pixels and the fraction left. A delta that
+/// isn't a number moves nothing and drops what was carried over.
+fn whole_pixels(total: f64) -> (i32, f64) {
+    if !total.is_finite() {
+        return (0, 0.0);
+    }
+    let whole = total.trunc();
+    let clamped = whole.clamp(f64::from(i32::MIN), f64::from(i32::MAX));
+    (clamped as i32, total - whole)
+}
+
+/// One axis of a wheel event, in notches. Positive is up or right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scroll {
+    Vertical(i64),
+    Horizontal(i64),
+}
+
+impl Scroll {
+    /// A wheel event that moves only this axis.
+    pub fn event(self) -> EventType {
+        match self {
+            Scroll::Vertical(notches) => EventType::Wheel {
+                delta_x: 0,
+                delta_y: notches,
+            },
+            Scroll::Horizontal(notches) => EventType::Wheel {
+                delta_x: notches,
+                delta_y: 0,
+            },
+        }
+    }
+}
+
+/// Splits a wheel event into one scroll per nonzero axis, vertical first.
+pub fn scroll_axes(delta_x: i64, delta_y: i64) -> Vec<Scroll> {
+    let vertical = (delta_y != 0).then_some(Scroll::Vertical(delta_y));
+    let horizontal = (delta_x != 0).then_some(Scroll::Horizontal(delta_x));
+    vertical.into_iter().chain(horizontal).collect()
+}
+
 #[cfg(not(target_os = "linux"))]
-use crate::simulator::EventSimulator;
+use crate::{bounds::PointerBounds, simulator::EventSimulator};
 #[cfg(not(target_os = "linux"))]
 use mouse_position::mouse_position::Mouse;
-#[cfg(not(target_os = "linux"))]
-use rdev::EventType;
 
+/// Moves from where the OS says the pointer is, by the whole pixels
+/// `mousemove` adds to what `motion` has carried over, kept inside `bounds`
+/// if given.
 #[cfg(not(target_os = "linux"))]
-pub fn do_mouse_move(simulator: &EventSimulator, mousemove: MouseMove) {
+pub fn do_mouse_move(
+    simulator: &EventSimulator,
+    motion: &mut SubPixelMotion,
+    mousemove: MouseMove,
+    bounds: Option<PointerBounds>,
+) -> bool {
     match Mouse::get_mouse_position() {
         Mouse::Position { x, y } => {
-            let event = EventType::MouseMove {
-                x: x as f64 + mousemove.dx,
-                y: y as f64 + mousemove.dy,
+            let (dx, dy) = motion.take_whole(mousemove);
+            let (x, y) = (f64::from(x.saturating_add(dx)), f64::from(y.saturating_add(dy)));
+            let (x, y) = match bounds {
+                Some(bounds) => bounds.clamp(x, y),
+                None => (x, y),
             };
-            simulator.enqueue(event);
+            simulator.enqueue(EventType::MouseMove { x, y })
+        }
+        Mouse::Error => {
+            eprintln!("[server] failed to read mouse position");
+            false
         }
-        Mouse::Error => eprintln!("[server] failed to read mouse position"),
     }
 }
\ No newline at end of file
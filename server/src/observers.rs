@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use quinn::Connection;
+use rdev::EventType;
+use shared::{MouseMove, ObservedInput};
+use tokio::sync::broadcast::{self, Receiver, Sender, error::RecvError};
+
+/// How many applied inputs a slow observer may fall behind before it
+/// starts missing some.
+const OBSERVER_BACKLOG: usize = 1024;
+
+/// Fans the input the server applies out to observer connections.
+///
+/// Observers only ever receive; nothing they send is applied, so what they
+/// are shown can never loop back into the injector.
+#[derive(Clone)]
+pub struct Observers {
+    sender: Sender<Arc<[u8]>>,
+}
+
+impl Default for Observers {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(OBSERVER_BACKLOG);
+        Self { sender }
+    }
+}
+
+impl Observers {
+    pub fn subscribe(&self) -> Receiver<Arc<[u8]>> {
+        self.sender.subscribe()
+    }
+
+    pub fn mouse_move(&self, mouse_move: MouseMove) {
+        self.publish(&ObservedInput::Mouse(mouse_move));
+    }
+
+    pub fn event(&self, event_type: EventType) {
+        self.publish(&ObservedInput::Event(event_type));
+    }
+
+    fn publish(&self, input: &ObservedInput) {
+        // Skip the encoding entirely while nobody is watching.
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        match rmp_serde::to_vec(input) {
+            Ok(bytes) => {
+                let _ = self.sender.send(bytes.into());
+            }
+            Err(err) => eprintln!("[server] failed to encode observed input: {err}"),
+        }
+    }
+}
+
+/// Streams applied input to one observer until it disconnects.
+pub async fn stream_to_observer(connection: Connection, mut inputs: Receiver<Arc<[u8]>>) {
+    let mut send = match connection.open_uni().await {
+        Ok(send) => send,
+        Err(err) => {
+            eprintln!("[server] failed to open observer stream: {err}");
+            return;
+        }
+    };
+
+    loop {
+        match inputs.recv().await {
+            Ok(bytes) => {
+                if let Err(err) = send.write_all(&bytes).await {
+                    println!("[server] observer stream closed: {err}");
+                    return;
+                }
+            }
+            Err(RecvError::Lagged(missed)) => {
+                eprintln!("[server] observer fell behind; skipped {missed} inputs");
+            }
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
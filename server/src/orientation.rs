@@ -0,0 +1,154 @@
+//! Turning pointer input to suit a display that is physically rotated or
+//! mirrored without the OS knowing, e.g. a monitor stood on its side whose
+//! picture the server's desktop doesn't rotate. Without this, moving right
+//! on the client moves the pointer up or down the rotated panel.
+//!
+//! Relative moves and [`AbsoluteMove`] positions are transformed before the
+//! server applies them; positions given in desktop pixels are not, as they
+//! already name a spot on this machine's desktop.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use shared::{AbsoluteMove, DisplaySize};
+
+/// How far the display is turned clockwise, as seen by someone facing it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum Rotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::None,
+        Rotation::Quarter,
+        Rotation::Half,
+        Rotation::ThreeQuarters,
+    ];
+
+    pub fn degrees(self) -> u16 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Quarter => 90,
+            Rotation::Half => 180,
+            Rotation::ThreeQuarters => 270,
+        }
+    }
+
+    /// Whether the display's width and height trade places.
+    pub fn is_sideways(self) -> bool {
+        matches!(self, Rotation::Quarter | Rotation::ThreeQuarters)
+    }
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u16) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|rotation| rotation.degrees() == degrees)
+            .ok_or_else(|| format!("rotation must be 0, 90, 180 or 270, not {degrees}"))
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> Self {
+        rotation.degrees()
+    }
+}
+
+/// A rotation, then optional mirroring of each axis; the `[orientation]`
+/// table of the config file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    /// Mirror left and right, after rotating.
+    pub flip_x: bool,
+    /// Mirror top and bottom, after rotating.
+    pub flip_y: bool,
+}
+
+impl Orientation {
+    pub fn new(rotation: Rotation) -> Self {
+        Self {
+            rotation,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_flips(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// Whether input passes through unchanged.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The client's display as it lands on the server's: width and height
+    /// swapped when turned sideways, so aspect-correct mappings still fit.
+    pub fn display_size(&self, size: DisplaySize) -> DisplaySize {
+        if self.rotation.is_sideways() {
+            DisplaySize {
+                width: size.height,
+                height: size.width,
+            }
+        } else {
+            size
+        }
+    }
+}
+
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rotated {}°", self.rotation.degrees())?;
+        match (self.flip_x, self.flip_y) {
+            (true, true) => f.write_str(", flipped both ways"),
+            (true, false) => f.write_str(", flipped left to right"),
+            (false, true) => f.write_str(", flipped top to bottom"),
+            (false, false) => Ok(()),
+        }
+    }
+}
+
+/// Turns a relative move, as the user sees it, into one on the display's
+/// own pixels. On a panel turned a quarter clockwise the picture's x runs
+/// down and its y runs left, so moving right has to go up its y.
+pub fn transform_delta(dx: f64, dy: f64, orientation: Orientation) -> (f64, f64) {
+    let (dx, dy) = match orientation.rotation {
+        Rotation::None => (dx, dy),
+        Rotation::Quarter => (dy, -dx),
+        Rotation::Half => (-dx, -dy),
+        Rotation::ThreeQuarters => (-dy, dx),
+    };
+    (
+        if orientation.flip_x { -dx } else { dx },
+        if orientation.flip_y { -dy } else { dy },
+    )
+}
+
+/// Turns an absolute position to suit `orientation`, about the middle of
+/// the display, so it agrees with [`transform_delta`]: after a quarter
+/// turn the corner the user sees top left is the picture's bottom left.
+pub fn transform_absolute(absolute: AbsoluteMove, orientation: Orientation) -> AbsoluteMove {
+    let AbsoluteMove { x, y } = absolute;
+    let (x, y) = match orientation.rotation {
+        Rotation::None => (x, y),
+        Rotation::Quarter => (y, 1.0 - x),
+        Rotation::Half => (1.0 - x, 1.0 - y),
+        Rotation::ThreeQuarters => (1.0 - y, x),
+    };
+    AbsoluteMove {
+        x: if orientation.flip_x { 1.0 - x } else { x },
+        y: if orientation.flip_y { 1.0 - y } else { y },
+    }
+}
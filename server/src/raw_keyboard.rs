@@ -0,0 +1,102 @@
+//! The virtual keyboard raw keys (see [`shared::raw_keys`]) are emitted on.
+//!
+//! The kernel drops events for codes a device didn't enable, and the
+//! `uinput` crate only enables the keys it has names for. This device is
+//! set up through `/dev/uinput` directly instead, with every code
+//! [`is_key_code`] accepts enabled.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    mem,
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+    slice,
+};
+
+use shared::raw_keys::{KEY_MAX, is_key_code};
+
+const NAME: &str = "quicinput-raw-keys";
+
+const EV_SYN: u16 = 0;
+const EV_KEY: u16 = 1;
+const SYN_REPORT: u16 = 0;
+const BUS_VIRTUAL: u16 = 0x06;
+
+// Requests from linux/uinput.h, as encoded on x86 and Arm.
+const UI_DEV_CREATE: u32 = 0x5501;
+const UI_DEV_DESTROY: u32 = 0x5502;
+const UI_DEV_SETUP: u32 = 0x405c_5503;
+const UI_SET_EVBIT: u32 = 0x4004_5564;
+const UI_SET_KEYBIT: u32 = 0x4004_5565;
+
+pub struct RawKeyboard {
+    file: File,
+}
+
+impl RawKeyboard {
+    /// Creates the device, with every key code up to [`KEY_MAX`] enabled.
+    pub fn create() -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/uinput")?;
+        let fd = file.as_raw_fd();
+
+        unsafe {
+            check(libc::ioctl(
+                fd,
+                UI_SET_EVBIT as _,
+                libc::c_int::from(EV_KEY),
+            ))?;
+            for code in (1..=KEY_MAX).filter(|&code| is_key_code(code)) {
+                check(libc::ioctl(fd, UI_SET_KEYBIT as _, libc::c_int::from(code)))?;
+            }
+
+            let mut setup: libc::uinput_setup = mem::zeroed();
+            setup.id.bustype = BUS_VIRTUAL;
+            for (slot, byte) in setup.name.iter_mut().zip(NAME.bytes()) {
+                *slot = byte as libc::c_char;
+            }
+            check(libc::ioctl(fd, UI_DEV_SETUP as _, &setup))?;
+            check(libc::ioctl(fd, UI_DEV_CREATE as _))?;
+        }
+        Ok(Self { file })
+    }
+
+    /// Presses or releases the key with Linux input event code `code`.
+    pub fn key(&mut self, code: u16, pressed: bool) -> io::Result<()> {
+        self.emit(EV_KEY, code, i32::from(pressed))?;
+        self.emit(EV_SYN, SYN_REPORT, 0)
+    }
+
+    fn emit(&mut self, kind: u16, code: u16, value: i32) -> io::Result<()> {
+        let mut event: libc::input_event = unsafe { mem::zeroed() };
+        event.type_ = kind;
+        event.code = code;
+        event.value = value;
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                (&event as *const libc::input_event).cast::<u8>(),
+                mem::size_of::<libc::input_event>(),
+            )
+        };
+        self.file.write_all(bytes)
+    }
+}
+
+impl Drop for RawKeyboard {
+    fn drop(&mut self) {
+        // Closing the file removes the device anyway.
+        unsafe {
+            libc::ioctl(self.file.as_raw_fd(), UI_DEV_DESTROY as _);
+        }
+    }
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
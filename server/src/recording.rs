@@ -0,0 +1,133 @@
+//! Recording every frame the server decodes, for telling input that was
+//! sent but never arrived from input that arrived but was never simulated.
+//! Compare with a client recording (`QUICINPUT_RECORD`) of the same session.
+//!
+//! Unlike the [`crate::audit`] log this keeps everything, stamps and
+//! unknown payloads included, unredacted and with microsecond receive times,
+//! as one JSON object per line:
+//!
+//! ```text
+//! {"received_micros":1760000000125000,"peer":"192.168.1.20:51234","frame":{"Event":{"KeyPress":"KeyA"}}}
+//! ```
+//!
+//! Frames are recorded as decoded, before rate limiting or injection, so a
+//! recording is just as complete under `--dry-run`.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    net::SocketAddr,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+
+use crate::framing::Frame;
+
+/// Lines that may wait for the writer before new ones are dropped, so a
+/// slow disk can never hold up the receive loop.
+const RECORDING_BACKLOG: usize = 4096;
+
+/// One line of a recording.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ReceivedFrame {
+    /// When the frame was decoded, in microseconds since the Unix epoch.
+    pub received_micros: u64,
+    /// The client that sent it, if known.
+    pub peer: Option<SocketAddr>,
+    pub frame: Frame,
+}
+
+/// Handle for recording from any stream. Cheap to clone; the file is
+/// written by a background task.
+#[derive(Clone)]
+pub struct InputRecording {
+    sender: Sender<String>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl InputRecording {
+    /// Creates the recording at `path`, replacing any file already there,
+    /// and starts its writer. Must be called from within a tokio runtime.
+    pub fn start(path: &Path) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        let (sender, receiver) = mpsc::channel(RECORDING_BACKLOG);
+        tokio::task::spawn_blocking(move || write_lines(receiver, file));
+        println!("[server] recording received input to {}", path.display());
+        Ok(Self {
+            sender,
+            dropped: Arc::default(),
+        })
+    }
+
+    /// Queues `frame` from `peer`. Never waits: if the writer has fallen too
+    /// far behind, the line is dropped and counted.
+    pub fn record(&self, peer: Option<SocketAddr>, frame: &Frame) {
+        let line = match record_line(SystemTime::now(), peer, frame) {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("[server] failed to record {frame:?}: {err}");
+                return;
+            }
+        };
+        match self.sender.try_send(line) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if dropped == 1 || dropped.is_multiple_of(1000) {
+                    eprintln!("[server] recording falling behind; {dropped} lines dropped");
+                }
+            }
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+/// Writes lines as they come, flushing whenever the queue runs dry so the
+/// file is current even if the server is killed.
+fn write_lines(mut receiver: Receiver<String>, mut file: BufWriter<File>) {
+    while let Some(line) = receiver.blocking_recv() {
+        let mut written = writeln!(file, "{line}");
+        if receiver.is_empty() {
+            written = written.and_then(|()| file.flush());
+        }
+        if let Err(err) = written {
+            eprintln!("[server] failed to write recording: {err}");
+        }
+    }
+}
+
+/// The recording line for `frame`.
+pub fn record_line(
+    at: SystemTime,
+    peer: Option<SocketAddr>,
+    frame: &Frame,
+) -> serde_json::Result<String> {
+    #[derive(Serialize)]
+    struct Line<'a> {
+        received_micros: u64,
+        peer: Option<SocketAddr>,
+        frame: &'a Frame,
+    }
+
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    serde_json::to_string(&Line {
+        received_micros: u64::try_from(since_epoch.as_micros()).unwrap_or(u64::MAX),
+        peer,
+        frame,
+    })
+}
+
+/// Parses a whole recording.
+pub fn read_recording(text: &str) -> serde_json::Result<Vec<ReceivedFrame>> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect()
+}
@@ -0,0 +1,148 @@
+//! Loss and reordering of client input, counted from the [`Seq`] headers
+//! the client puts in front of each message.
+//!
+//! A reliable stream never loses or reorders anything, so on today's
+//! transport these stay at zero unless the client itself dropped input
+//! (e.g. under simulated loss). They matter once input can travel over an
+//! unreliable path.
+
+use std::{collections::VecDeque, fmt, ops::Range};
+
+use shared::{Seq, SeqCategory};
+
+/// Running totals for one [`SeqCategory`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SequenceStats {
+    pub received: u64,
+    /// Numbers skipped over and not (yet) seen.
+    pub lost: u64,
+    /// Messages that arrived after a higher number had.
+    pub reordered: u64,
+}
+
+/// How one header compared with what was expected next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeqOutcome {
+    InOrder,
+    /// This many numbers were skipped to reach this one.
+    Gap(u64),
+    /// A number below the next expected one, filling an earlier gap.
+    Late,
+    /// A number already seen, or skipped too long ago to tell. Left out of
+    /// the totals.
+    Duplicate,
+}
+
+/// Gaps remembered per category so late arrivals can be told from
+/// duplicates. Older ones stay counted as lost.
+const MAX_GAPS: usize = 256;
+
+#[derive(Debug, Default)]
+struct CategoryTracker {
+    next: Option<u64>,
+    /// Numbers skipped over and not seen since, oldest first.
+    gaps: VecDeque<Range<u64>>,
+    stats: SequenceStats,
+}
+
+impl CategoryTracker {
+    fn record(&mut self, seq: u64) -> SeqOutcome {
+        let next = self.next.unwrap_or(seq);
+        if seq < next {
+            if !self.fill(seq) {
+                return SeqOutcome::Duplicate;
+            }
+            self.stats.received += 1;
+            self.stats.reordered += 1;
+            // It was counted lost when it was skipped over.
+            self.stats.lost -= 1;
+            return SeqOutcome::Late;
+        }
+        self.stats.received += 1;
+        self.next = Some(seq + 1);
+        match seq - next {
+            0 => SeqOutcome::InOrder,
+            skipped => {
+                self.stats.lost += skipped;
+                self.gaps.push_back(next..seq);
+                self.forget_oldest_gaps();
+                SeqOutcome::Gap(skipped)
+            }
+        }
+    }
+
+    /// Takes `seq` out of the gap it is in, if any.
+    fn fill(&mut self, seq: u64) -> bool {
+        let Some(index) = self.gaps.iter().position(|gap| gap.contains(&seq)) else {
+            return false;
+        };
+        let gap = self.gaps.remove(index).expect("index is in range");
+        let (before, after) = (gap.start..seq, seq + 1..gap.end);
+        for rest in [after, before] {
+            if !rest.is_empty() {
+                self.gaps.insert(index, rest);
+            }
+        }
+        self.forget_oldest_gaps();
+        true
+    }
+
+    fn forget_oldest_gaps(&mut self) {
+        while self.gaps.len() > MAX_GAPS {
+            self.gaps.pop_front();
+        }
+    }
+}
+
+/// Sequence tracking for one connection.
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    mouse: CategoryTracker,
+    keyboard: CategoryTracker,
+    irregular: u64,
+}
+
+impl SequenceTracker {
+    pub fn record(&mut self, header: Seq) -> SeqOutcome {
+        let outcome = self.tracker(header.category).record(header.seq);
+        if outcome != SeqOutcome::InOrder {
+            self.irregular += 1;
+        }
+        outcome
+    }
+
+    pub fn stats(&self, category: SeqCategory) -> SequenceStats {
+        match category {
+            SeqCategory::Mouse => self.mouse.stats,
+            SeqCategory::Keyboard => self.keyboard.stats,
+        }
+    }
+
+    /// Headers that were not the next one expected, over every category.
+    pub fn irregular(&self) -> u64 {
+        self.irregular
+    }
+
+    fn tracker(&mut self, category: SeqCategory) -> &mut CategoryTracker {
+        match category {
+            SeqCategory::Mouse => &mut self.mouse,
+            SeqCategory::Keyboard => &mut self.keyboard,
+        }
+    }
+}
+
+impl fmt::Display for SequenceTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mouse, keyboard) = (self.mouse.stats, self.keyboard.stats);
+        write!(
+            f,
+            "mouse {} lost, {} reordered of {}; keyboard {} lost, {} reordered of {}",
+            mouse.lost,
+            mouse.reordered,
+            mouse.received,
+            keyboard.lost,
+            keyboard.reordered,
+            keyboard.received
+        )
+    }
+}
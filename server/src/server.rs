@@ -1,35 +1,67 @@
 use std::{
+    collections::HashSet,
     error::Error,
-    net::SocketAddr,
-    sync::Arc,
-    thread,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
 };
 
-use quinn::{Endpoint, Incoming, ServerConfig};
-use rdev::EventType;
-use rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer};
-use shared::MouseMove;
-use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use quinn::{Endpoint, EndpointConfig, Incoming, ServerConfig, default_runtime};
+use rdev::{Button, EventType, Key};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, pem::PemObject};
+use shared::{
+    CloseCode, ControlRequest, ControlResponse, DisplayInfo, DisplaySize, MouseMove, PointerMode,
+    Seq, SessionToken, SourceId,
+    codec::WireFormat,
+    hex::format_hex,
+    key_combo::KeyCombo,
+    layout::{KeyboardLayout, LayoutTable, US_QWERTY},
+    monotonic_micros,
+    raw_keys::is_key_code,
+    script,
+};
+use tokio::{
+    sync::{Notify, OwnedSemaphorePermit, Semaphore, TryAcquireError, watch},
+    time::timeout,
+};
 
 use crate::{
-    mousemove::do_mouse_move,
-    simulator::EventSimulator,
+    allowlist::{self, PeerRange},
+    availability::{AVAILABILITY_POLL_INTERVAL, Availability, AvailabilitySource, watch_availability},
+    chunked::{ChunkedReadError, read_chunked},
+    clicks::{ClickTimer, DEFAULT_DOUBLE_CLICK_WINDOW},
+    displays::{DisplaySource, SystemDisplays},
+    framing::{Frame, FrameDecoder, attempted_decodes},
+    gesture::GestureTranslator,
+    held::HeldState,
+    inject::Injector,
+    latency::{LATENCY_WINDOW, LatencyStats},
+    listen::bind_sockets,
+    mapping::AbsoluteMapping,
+    migration::{MIGRATION_POLL_INTERVAL, watch_migrations},
+    observers::{Observers, stream_to_observer},
+    orientation::{Orientation, transform_absolute, transform_delta},
+    sequence::{SeqOutcome, SequenceTracker},
+    service::StartupError,
+    sessions::SessionStore,
+    transport::ServerTransportOptions,
 };
 
+/// Checks the uinput kernel module is loaded, which the virtual device
+/// needs.
 #[cfg(target_os = "linux")]
-use std::sync::Mutex;
-
-#[cfg(target_os = "linux")]
-pub(crate) fn ensure_uinput_available() {
+pub fn ensure_uinput_available() -> Result<(), StartupError> {
     use std::process::Command;
 
-    let output = match Command::new("lsmod").output() {
-        Ok(output) => output,
-        Err(error) => {
-            eprintln!("[server] failed to run lsmod: {error}");
-            std::process::exit(1);
-        }
-    };
+    let output = Command::new("lsmod").output().map_err(|error| {
+        StartupError::UinputMissing(format!(
+            "failed to run lsmod to look for the uinput module: {error}"
+        ))
+    })?;
 
     let modules = String::from_utf8_lossy(&output.stdout);
     let has_uinput = modules
@@ -37,88 +69,685 @@ pub(crate) fn ensure_uinput_available() {
         .any(|line| line.split_whitespace().next() == Some("uinput"));
 
     if !has_uinput {
-        eprintln!(
-            "[server] kernel module 'uinput' is not loaded. Please enable it (e.g., 'sudo modprobe uinput') and ensure this program has permission to access /dev/uinput."
-        );
-        std::process::exit(1);
+        return Err(StartupError::UinputMissing(
+            "kernel module 'uinput' is not loaded. Please enable it (e.g., 'sudo modprobe uinput') and ensure this program has permission to access /dev/uinput.".to_string(),
+        ));
     }
+    Ok(())
 }
 
-pub(crate) type Simulators = Arc<[EventSimulator; 2]>;
-
+/// Fails with [`StartupError::PermissionDenied`] when the server may not
+/// open the uinput device at `path`, e.g. `/dev/uinput`. Other failures are
+/// left for creating the virtual device to report.
 #[cfg(target_os = "linux")]
-pub(crate) type DeviceInput = Arc<Mutex<Option<uinput::Device>>>;
-#[cfg(not(target_os = "linux"))]
-pub(crate) type DeviceInput = ();
-
-pub(crate) async fn run_server(
-    addr: SocketAddr,
-    max_connections: u8,
-    simulators: Simulators,
-    device_input: DeviceInput,
-) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    let (endpoint, _server_cert) = make_server_endpoint(addr)?;
-    println!(
-        "[server] listening on {} with max {} connections",
-        addr, max_connections
-    );
+pub fn ensure_uinput_writable(path: &std::path::Path) -> Result<(), StartupError> {
+    use std::{fs::OpenOptions, io};
 
-    let connection_limit = Arc::new(Semaphore::new(max_connections.into()));
+    match OpenOptions::new().write(true).open(path) {
+        Err(error) if error.kind() == io::ErrorKind::PermissionDenied => {
+            Err(StartupError::PermissionDenied(format!(
+                "no permission to open {}: {error}. Run the server as a user allowed to write to it, e.g. one in the 'input' group.",
+                path.display()
+            )))
+        }
+        _ => Ok(()),
+    }
+}
 
-    while let Some(incoming) = endpoint.accept().await {
-        let permit = match Arc::clone(&connection_limit).acquire_owned().await {
-            Ok(permit) => permit,
-            Err(_) => {
-                eprintln!("[server] semaphore closed; shutting down accept loop");
-                break;
+/// Input state shared by all uni streams of one connection, so the idle
+/// watchdog and the release sweep see activity on either of them.
+#[derive(Default)]
+struct HeldInput {
+    held: HeldState,
+    last_event: Option<Instant>,
+    /// When input other than pointer motion last arrived; a button still
+    /// held once [`StreamOptions::button_hold_limit`] has passed since then
+    /// is released.
+    last_non_motion: Option<Instant>,
+    latency: LatencyStats,
+    sequence: SequenceTracker,
+    gestures: GestureTranslator,
+    clicks: ClickTimer,
+    /// Where [`Frame::Absolute`] positions land; `None` until the client has
+    /// been granted absolute pointer mode.
+    absolute_display: Option<DisplayInfo>,
+    /// The client's display, from its hello, for [`AbsoluteMapping`].
+    client_display: Option<DisplaySize>,
+    /// How the client encodes its uni streams, from its hello.
+    wire_format: WireFormat,
+    /// Start of the current one-second rate window and inputs admitted in it.
+    rate_window: Option<(Instant, u32)>,
+    rate_dropped: u64,
+}
+
+type SharedHeldInput = Arc<Mutex<HeldInput>>;
+
+/// The connection currently using a session token, so a reconnecting
+/// client can take its held input over.
+struct LiveSession {
+    connection: quinn::Connection,
+    held: SharedHeldInput,
+    slot: ConnectionSlot,
+}
+
+type Sessions = SessionStore<LiveSession>;
+
+/// A connection's place among the server's `max_connections`. Empty while a
+/// connection that arrived with the server full waits for its hello, and
+/// after a reconnecting client took it over with the session.
+type ConnectionSlot = Arc<Mutex<Option<OwnedSemaphorePermit>>>;
+
+impl HeldInput {
+    /// Notes pointer motion, or the client saying input is still held.
+    fn touch(&mut self) {
+        self.last_event = Some(Instant::now());
+    }
+
+    /// Notes any other input: a key, button, wheel, character, gesture or
+    /// combination.
+    fn touch_input(&mut self) {
+        self.touch();
+        self.last_non_motion = self.last_event;
+    }
+
+    fn observe(&mut self, event: &EventType) {
+        self.touch_input();
+        self.held.observe(event);
+    }
+
+    /// Whether a button is held and nothing but pointer motion has arrived
+    /// for `limit`, e.g. because its release was lost mid-drag.
+    fn button_stuck(&self, limit: Duration) -> bool {
+        self.held.has_buttons()
+            && self
+                .last_non_motion
+                .is_some_and(|last| last.elapsed() >= limit)
+    }
+
+    fn idle_for(&self, interval: Duration) -> bool {
+        self.last_event
+            .map(|last| last.elapsed() >= interval)
+            .unwrap_or(true)
+    }
+
+    /// Whether one more input fits under `per_sec` this second; counts and
+    /// now and then logs the ones that don't.
+    fn admit(&mut self, per_sec: u32) -> bool {
+        let now = Instant::now();
+        let (started, admitted) = match self.rate_window {
+            Some((started, admitted)) if now.duration_since(started) < Duration::from_secs(1) => {
+                (started, admitted)
+            }
+            _ => (now, 0),
+        };
+        if admitted < per_sec {
+            self.rate_window = Some((started, admitted + 1));
+            return true;
+        }
+        self.rate_window = Some((started, admitted));
+        self.rate_dropped += 1;
+        if self.rate_dropped == 1 || self.rate_dropped.is_multiple_of(1000) {
+            println!(
+                "[server] client over {per_sec} inputs/s; {} inputs dropped",
+                self.rate_dropped
+            );
+        }
+        false
+    }
+}
+
+/// Uni streams of one connection that have been read to their end, so a
+/// drain request can wait for exactly the streams the client names.
+#[derive(Default)]
+struct FinishedStreams {
+    ids: Mutex<HashSet<u64>>,
+    changed: Notify,
+}
+
+impl FinishedStreams {
+    fn finish(&self, id: u64) {
+        self.ids
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(id);
+        self.changed.notify_waiters();
+    }
+
+    fn all_finished(&self, ids: &[u64]) -> bool {
+        let finished = self.ids.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        ids.iter().all(|id| finished.contains(id))
+    }
+
+    /// Waits up to `limit` for every stream in `ids`; false if some never finished.
+    async fn wait_for(&self, ids: &[u64], limit: Duration) -> bool {
+        let all = async {
+            loop {
+                // Registered before the check so a finish in between still wakes us.
+                let changed = self.changed.notified();
+                if self.all_finished(ids) {
+                    return;
+                }
+                changed.await;
             }
         };
+        timeout(limit, all).await.is_ok()
+    }
+}
+
+/// How long a drain request may wait for the client's streams to finish.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a connection that arrived with the server full has to take over
+/// a session it is resuming before it is refused.
+const ADMISSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long shutdown waits for connections to release their input and end.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a client watching injection is sent the latest totals.
+pub const INJECTION_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default for [`StreamOptions::max_streams`].
+pub const DEFAULT_MAX_STREAMS: usize = 32;
+
+/// Per-stream behaviour chosen at startup.
+#[derive(Clone, Copy, Debug)]
+pub struct StreamOptions {
+    /// Release held input after this long without events; `None` disables it.
+    pub idle_release: Option<Duration>,
+    /// Release a held button once nothing but pointer motion has arrived
+    /// for this long. Catches a lost release mid-drag, which the idle
+    /// release never sees as idle. `None` disables it.
+    pub button_hold_limit: Option<Duration>,
+    /// Print every decoded event in the `shared::script` line format.
+    pub verbose_events: bool,
+    /// Log every chunk read off a uni stream as hex, with what it decodes
+    /// to as each kind of value, for trying out payloads a client doesn't
+    /// send yet.
+    pub debug_raw: bool,
+    /// Layout used to type characters sent in translation mode; `None`
+    /// assumes US QWERTY.
+    pub keyboard_layout: Option<&'static LayoutTable>,
+    /// How many input streams, and separately how many control streams, one
+    /// connection may have in flight. Streams past the limit are stopped.
+    pub max_streams: usize,
+    /// Multiplies relative pointer motion; 1.0 replays it as sent.
+    pub pointer_sensitivity: f64,
+    /// How absolute positions are fitted onto a display whose aspect ratio
+    /// differs from the client's.
+    pub absolute_mapping: AbsoluteMapping,
+    /// Inputs one connection may apply per second; past it they are dropped,
+    /// except releases, so nothing is left held. `None` is unlimited.
+    pub max_inputs_per_sec: Option<u32>,
+    /// How this machine's display is physically turned; pointer input is
+    /// turned to match.
+    pub orientation: Orientation,
+    /// The longest gap this machine counts as a double-click. A client's
+    /// double-click whose presses arrive further apart is replayed; `None`
+    /// leaves them as they arrive.
+    pub double_click_window: Option<Duration>,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            idle_release: None,
+            button_hold_limit: None,
+            verbose_events: false,
+            debug_raw: false,
+            keyboard_layout: None,
+            max_streams: DEFAULT_MAX_STREAMS,
+            pointer_sensitivity: 1.0,
+            absolute_mapping: AbsoluteMapping::default(),
+            max_inputs_per_sec: None,
+            orientation: Orientation::default(),
+            double_click_window: Some(DEFAULT_DOUBLE_CLICK_WINDOW),
+        }
+    }
+}
+
+/// Port the server listens on unless told otherwise.
+pub const DEFAULT_PORT: u16 = 4433;
+
+/// PEM files holding the certificate chain (leaf first) and its private key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertificatePaths {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// Everything [`run_server`] needs, built once in `main` or a test:
+///
+/// ```ignore
+/// let options = ServerOptions::new(injector)
+///     .with_binds(vec![addr])
+///     .with_max_connections(2)
+///     .with_displays(Arc::new(FakeDisplays::default()));
+/// run_server(options).await?;
+/// ```
+#[derive(Clone)]
+pub struct ServerOptions {
+    pub binds: Vec<SocketAddr>,
+    pub max_connections: u8,
+    pub stream_options: StreamOptions,
+    /// How long a dropped client's session is kept for it to resume.
+    pub resume_grace: Duration,
+    /// Peers allowed to connect; `None` lets anyone in. Others are refused
+    /// before their handshake starts.
+    pub allowed_peers: Option<Vec<PeerRange>>,
+    /// Log each connection refused for being off `allowed_peers`.
+    pub log_refused_peers: bool,
+    /// Certificate to present; `None` generates a self-signed one per run.
+    pub certificate: Option<CertificatePaths>,
+    /// QUIC limits offered to clients; quinn's defaults unless changed.
+    pub transport: ServerTransportOptions,
+    /// Name to advertise the server under over mDNS; `None` stays quiet.
+    /// Only acted on with the `mdns` feature.
+    pub advertise: Option<String>,
+    pub displays: Arc<dyn DisplaySource>,
+    pub injector: Injector,
+    /// Called with the bound addresses once every listener is up.
+    pub on_ready: Option<ReadyHook>,
+    /// Tells whether this machine can take input, so clients can pause
+    /// while it can't; `None` reports nothing.
+    pub availability: Option<Arc<dyn AvailabilitySource>>,
+    /// How often `availability` is asked.
+    pub availability_poll: Duration,
+}
+
+/// See [`ServerOptions::on_ready`].
+pub type ReadyHook = Arc<dyn Fn(&[SocketAddr]) + Send + Sync>;
+
+impl ServerOptions {
+    /// One connection on every interface at [`DEFAULT_PORT`], reporting this
+    /// machine's displays and replaying input through `injector`.
+    pub fn new(injector: Injector) -> Self {
+        Self {
+            binds: vec![SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), DEFAULT_PORT)],
+            max_connections: 1,
+            stream_options: StreamOptions::default(),
+            resume_grace: Duration::ZERO,
+            allowed_peers: None,
+            log_refused_peers: true,
+            certificate: None,
+            transport: ServerTransportOptions::default(),
+            advertise: None,
+            displays: Arc::new(SystemDisplays),
+            injector,
+            on_ready: None,
+            availability: None,
+            availability_poll: AVAILABILITY_POLL_INTERVAL,
+        }
+    }
+
+    pub fn with_binds(mut self, binds: Vec<SocketAddr>) -> Self {
+        self.binds = binds;
+        self
+    }
+
+    pub fn with_max_connections(mut self, max_connections: u8) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    pub fn with_stream_options(mut self, stream_options: StreamOptions) -> Self {
+        self.stream_options = stream_options;
+        self
+    }
+
+    pub fn with_idle_release(mut self, idle_release: Option<Duration>) -> Self {
+        self.stream_options.idle_release = idle_release;
+        self
+    }
+
+    pub fn with_button_hold_limit(mut self, limit: Option<Duration>) -> Self {
+        self.stream_options.button_hold_limit = limit;
+        self
+    }
+
+    pub fn with_pointer_sensitivity(mut self, sensitivity: f64) -> Self {
+        self.stream_options.pointer_sensitivity = sensitivity;
+        self
+    }
+
+    pub fn with_absolute_mapping(mut self, mapping: AbsoluteMapping) -> Self {
+        self.stream_options.absolute_mapping = mapping;
+        self
+    }
+
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.stream_options.orientation = orientation;
+        self
+    }
+
+    pub fn with_double_click_window(mut self, window: Option<Duration>) -> Self {
+        self.stream_options.double_click_window = window;
+        self
+    }
+
+    pub fn with_max_inputs_per_sec(mut self, per_sec: Option<u32>) -> Self {
+        self.stream_options.max_inputs_per_sec = per_sec;
+        self
+    }
+
+    pub fn with_resume_grace(mut self, resume_grace: Duration) -> Self {
+        self.resume_grace = resume_grace;
+        self
+    }
+
+    /// Only lets in peers within `peers`, given as addresses or
+    /// [`PeerRange`]s.
+    pub fn with_allowed_peers<P: Into<PeerRange>>(
+        mut self,
+        peers: impl IntoIterator<Item = P>,
+    ) -> Self {
+        self.allowed_peers = Some(peers.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_log_refused_peers(mut self, log: bool) -> Self {
+        self.log_refused_peers = log;
+        self
+    }
+
+    pub fn with_certificate(mut self, certificate: CertificatePaths) -> Self {
+        self.certificate = Some(certificate);
+        self
+    }
+
+    pub fn with_transport(mut self, transport: ServerTransportOptions) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn with_advertise(mut self, name: String) -> Self {
+        self.advertise = Some(name);
+        self
+    }
+
+    pub fn with_displays(mut self, displays: Arc<dyn DisplaySource>) -> Self {
+        self.displays = displays;
+        self
+    }
+
+    pub fn with_on_ready(mut self, on_ready: ReadyHook) -> Self {
+        self.on_ready = Some(on_ready);
+        self
+    }
+
+    pub fn with_availability(mut self, source: Arc<dyn AvailabilitySource>) -> Self {
+        self.availability = Some(source);
+        self
+    }
+
+    fn allows(&self, peer: IpAddr) -> bool {
+        self.allowed_peers
+            .as_ref()
+            .is_none_or(|allowed| allowlist::is_allowed(allowed, peer))
+    }
+}
+
+/// What the control stream needs to issue or resume a connection's session.
+#[derive(Clone)]
+struct SessionContext {
+    connection: quinn::Connection,
+    sessions: Arc<Sessions>,
+    displays: Arc<dyn DisplaySource>,
+    observers: Observers,
+    held: SharedHeldInput,
+    token: Arc<Mutex<Option<SessionToken>>>,
+    /// Set once the client joins as an observer; its input is ignored after.
+    observing: Arc<AtomicBool>,
+    finished_streams: Arc<FinishedStreams>,
+    injector: Injector,
+    /// Whether this machine can take input, when the server watches for it.
+    availability: Option<watch::Receiver<Availability>>,
+    slot: ConnectionSlot,
+    connection_limit: Arc<Semaphore>,
+}
+
+impl SessionContext {
+    /// Whether the connection holds one of the server's slots, or needs none
+    /// as an observer.
+    fn admitted(&self) -> bool {
+        self.observing.load(Ordering::SeqCst) || lock_slot(&self.slot).is_some()
+    }
+
+    /// Gives the connection a slot if it has none: the one `previous` held
+    /// when taking its session over, or else a free one. False when the
+    /// server is full.
+    fn admit(&self, previous: Option<&LiveSession>) -> bool {
+        let mut slot = lock_slot(&self.slot);
+        if slot.is_none()
+            && let Some(previous) = previous
+        {
+            *slot = lock_slot(&previous.slot).take();
+        }
+        if slot.is_none() {
+            *slot = Arc::clone(&self.connection_limit).try_acquire_owned().ok();
+        }
+        slot.is_some()
+    }
+}
+
+pub async fn run_server(options: ServerOptions) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    run_server_until(options, std::future::pending()).await
+}
+
+/// Runs the server until `shutdown` resolves, then closes every connection
+/// with [`CloseCode::Shutdown`], releases what they and any parked sessions
+/// hold, and waits up to [`SHUTDOWN_TIMEOUT`] for them to end.
+pub async fn run_server_until(
+    options: ServerOptions,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let (server_config, _server_cert) =
+        configure_server(options.certificate.as_ref(), &options.transport)?;
+    let sessions = Arc::new(SessionStore::new(options.resume_grace));
 
-        let simulators_for_connection = Arc::clone(&simulators);
-        let device_for_connection = device_input.clone();
+    // One limit shared by every listener, so the cap holds across interfaces.
+    let connection_limit = Arc::new(Semaphore::new(options.max_connections.into()));
+    let availability = options
+        .availability
+        .clone()
+        .map(|source| watch_availability(source, options.availability_poll));
+    let options = Arc::new(options);
+    let mut listeners = Vec::with_capacity(options.binds.len());
+    let mut endpoints = Vec::with_capacity(options.binds.len());
+
+    if let Some(allowed) = &options.allowed_peers {
+        println!(
+            "[server] only accepting connections from {}",
+            allowlist::describe(allowed)
+        );
+    }
+
+    let runtime = default_runtime().ok_or("no async runtime found")?;
+    for addr in &options.binds {
+        let sockets =
+            bind_sockets(*addr).map_err(|error| StartupError::BindFailed { addr: *addr, error })?;
+        for bound in sockets {
+            let local = bound.socket.local_addr()?;
+            let endpoint = Endpoint::new(
+                EndpointConfig::default(),
+                Some(server_config.clone()),
+                bound.socket,
+                Arc::clone(&runtime),
+            )?;
+            let families = if bound.dual_stack { " (IPv4 and IPv6)" } else { "" };
+            println!(
+                "[server] listening on {local}{families} with max {} connections",
+                options.max_connections
+            );
+            endpoints.push((local, endpoint.clone()));
+            listeners.push(tokio::spawn(accept_connections(
+                endpoint,
+                Arc::clone(&connection_limit),
+                Arc::clone(&options),
+                Arc::clone(&sessions),
+                availability.clone(),
+            )));
+        }
+    }
+
+    #[cfg(feature = "mdns")]
+    let _advertisement = match (&options.advertise, options.binds.first()) {
+        (Some(name), Some(addr)) => {
+            match crate::discovery::advertise(name, addr.port(), &_server_cert) {
+                Ok(daemon) => Some(daemon),
+                Err(err) => {
+                    eprintln!("[server] failed to advertise via mDNS: {err}");
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    if let Some(on_ready) = &options.on_ready {
+        let bound: Vec<SocketAddr> = endpoints.iter().map(|(local, _)| *local).collect();
+        on_ready(&bound);
+    }
+
+    let accepting = futures::future::join_all(listeners);
+    tokio::pin!(accepting);
+    tokio::select! {
+        results = &mut accepting => {
+            for result in results {
+                if let Err(err) = result {
+                    eprintln!("[server] accept loop failed: {err}");
+                }
+            }
+            return Ok(());
+        }
+        () = shutdown => {}
+    }
+
+    println!("[server] shutting down");
+    let code = CloseCode::Shutdown;
+    for (_, endpoint) in &endpoints {
+        endpoint.close(code.into(), code.name().as_bytes());
+    }
+    // Clients that dropped earlier won't be back for what they left held.
+    for mut held in sessions.expire_all() {
+        release_held(&mut held, &options.injector);
+    }
+    // Every connection gives its permit back once it has released its input.
+    let all = u32::from(options.max_connections);
+    if timeout(SHUTDOWN_TIMEOUT, connection_limit.acquire_many(all))
+        .await
+        .is_err()
+    {
+        eprintln!(
+            "[server] connections still open after {}s; exiting anyway",
+            SHUTDOWN_TIMEOUT.as_secs()
+        );
+    }
+    let _ = accepting.await;
+    Ok(())
+}
+
+async fn accept_connections(
+    endpoint: Endpoint,
+    connection_limit: Arc<Semaphore>,
+    options: Arc<ServerOptions>,
+    sessions: Arc<Sessions>,
+    availability: Option<watch::Receiver<Availability>>,
+) {
+    while let Some(incoming) = endpoint.accept().await {
+        if !options.allows(incoming.remote_address().ip()) {
+            refuse_connection(incoming, options.log_refused_peers);
+            continue;
+        }
+
+        let connection_limit = Arc::clone(&connection_limit);
+
+        let sessions_for_connection = Arc::clone(&sessions);
+        let displays_for_connection = Arc::clone(&options.displays);
+        let injector_for_connection = options.injector.clone();
+        let availability_for_connection = availability.clone();
+        let stream_options = options.stream_options;
         tokio::spawn(async move {
             handle_connection(
                 incoming,
-                permit,
-                simulators_for_connection,
-                device_for_connection,
+                connection_limit,
+                stream_options,
+                sessions_for_connection,
+                displays_for_connection,
+                injector_for_connection,
+                availability_for_connection,
             )
             .await;
         });
     }
-
-    Ok(())
 }
 
-fn make_server_endpoint(
-    bind_addr: SocketAddr,
-) -> Result<(Endpoint, CertificateDer<'static>), Box<dyn Error + Send + Sync + 'static>> {
-    let (server_config, server_cert) = configure_server()?;
-    let endpoint = Endpoint::server(server_config, bind_addr)?;
-    Ok((endpoint, server_cert))
-}
-
-fn configure_server() -> Result<
-    (ServerConfig, CertificateDer<'static>),
-    Box<dyn Error + Send + Sync + 'static>,
-> {
-    let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
-    let cert_der = CertificateDer::from(cert.cert);
-    let priv_key = PrivatePkcs8KeyDer::from(cert.signing_key.serialize_der());
+/// QUIC server config presenting `certificate`, or a fresh self-signed
+/// certificate when there is none, along with the leaf it presents.
+fn configure_server(
+    certificate: Option<&CertificatePaths>,
+    transport: &ServerTransportOptions,
+) -> Result<(ServerConfig, CertificateDer<'static>), Box<dyn Error + Send + Sync + 'static>> {
+    let (chain, priv_key) = match certificate {
+        Some(paths) => {
+            let chain = CertificateDer::pem_file_iter(&paths.cert)
+                .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+                .map_err(|err| format!("failed to read {}: {err}", paths.cert.display()))?;
+            let priv_key = PrivateKeyDer::from_pem_file(&paths.key)
+                .map_err(|err| format!("failed to read {}: {err}", paths.key.display()))?;
+            println!("[server] using certificate {}", paths.cert.display());
+            (chain, priv_key)
+        }
+        None => {
+            let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+            let cert_der = CertificateDer::from(cert.cert);
+            let priv_key = PrivatePkcs8KeyDer::from(cert.signing_key.serialize_der());
+            (vec![cert_der], priv_key.into())
+        }
+    };
+    let Some(leaf) = chain.first().cloned() else {
+        return Err("certificate file holds no certificates".into());
+    };
 
-    let server_config = ServerConfig::with_single_cert(vec![cert_der.clone()], priv_key.into())?;
+    let mut server_config = ServerConfig::with_single_cert(chain, priv_key)?;
+    transport
+        .apply_to(&mut server_config)
+        .map_err(|err| format!("invalid transport options: {err}"))?;
 
-    Ok((server_config, cert_der))
+    Ok((server_config, leaf))
 }
 
 const MAX_STREAM_DATA: usize = 64 * 1024;
 
+/// Largest control request taken on a bi stream. Unlike input, a request is
+/// only decoded once it has arrived in full, so it may run well past
+/// [`MAX_STREAM_DATA`].
+const MAX_REQUEST_DATA: usize = 8 * 1024 * 1024;
+
+/// Turns `incoming` away before any handshake, so a peer off the allowlist
+/// never gets as far as the TLS exchange.
+fn refuse_connection(incoming: Incoming, log: bool) {
+    if log {
+        println!(
+            "[server] refusing connection from {}: not on the allowlist",
+            incoming.remote_address()
+        );
+    }
+    incoming.refuse();
+}
+
 async fn handle_connection(
     incoming: Incoming,
-    permit: OwnedSemaphorePermit,
-    simulators: Simulators,
-    device_input: DeviceInput,
+    connection_limit: Arc<Semaphore>,
+    stream_options: StreamOptions,
+    sessions: Arc<Sessions>,
+    displays: Arc<dyn DisplaySource>,
+    injector: Injector,
+    availability: Option<watch::Receiver<Availability>>,
 ) {
+    // A full server still hears a client out: it may be reconnecting to take
+    // over the session, and the slot, its old connection holds.
+    let permit = Arc::clone(&connection_limit).try_acquire_owned().ok();
     match incoming.await {
         Ok(connection) => {
             println!(
@@ -126,23 +755,61 @@ async fn handle_connection(
                 connection.remote_address()
             );
 
-            let bi_task = tokio::spawn(listen_bi_streams(connection.clone()));
+            let session = SessionContext {
+                connection: connection.clone(),
+                sessions,
+                displays,
+                observers: injector.observers().clone(),
+                held: SharedHeldInput::default(),
+                token: Arc::new(Mutex::new(None)),
+                observing: Arc::new(AtomicBool::new(false)),
+                finished_streams: Arc::default(),
+                injector: injector.clone(),
+                availability,
+                slot: Arc::new(Mutex::new(permit)),
+                connection_limit,
+            };
+            if !session.admitted() {
+                tokio::spawn(refuse_unless_admitted(session.clone()));
+            }
+            let bi_task = tokio::spawn(listen_bi_streams(
+                connection.clone(),
+                stream_options.max_streams,
+                session.clone(),
+            ));
             let uni_task = tokio::spawn(listen_uni_streams(
                 connection.clone(),
-                Arc::clone(&simulators),
-                device_input,
+                stream_options,
+                session.clone(),
+                injector.clone(),
+            ));
+            let migration_task = tokio::spawn(watch_migrations(
+                connection.clone(),
+                MIGRATION_POLL_INTERVAL,
+                |from, to| println!("[server] client moved from {from} to {to}; connection kept"),
             ));
+            // Resolves to whether the peer went away cleanly.
             let close_task = tokio::spawn(async move {
                 let reason = connection.closed().await;
                 match reason {
-                    quinn::ConnectionError::ApplicationClosed { .. } => {
-                        println!("[server] connection closed by peer");
+                    quinn::ConnectionError::ApplicationClosed(close) => {
+                        match CloseCode::try_from(close.error_code) {
+                            Ok(code) => {
+                                println!("[server] connection closed by peer: {code}");
+                            }
+                            Err(unknown) => {
+                                println!("[server] connection closed by peer with {unknown}")
+                            }
+                        }
+                        true
                     }
                     quinn::ConnectionError::LocallyClosed => {
                         println!("[server] connection closed locally");
+                        true
                     }
                     err => {
                         eprintln!("[server] connection closed with error: {err}");
+                        false
                     }
                 }
             });
@@ -155,27 +822,108 @@ async fn handle_connection(
                 eprintln!("[server] uni stream task failed: {err}");
             }
 
-            if let Err(err) = close_task.await {
-                eprintln!("[server] connection close task failed: {err}");
+            if let Ok(moves @ 1..) = migration_task.await {
+                println!("[server] client address changes over the connection: {moves}");
             }
+
+            let clean = match close_task.await {
+                Ok(clean) => clean,
+                Err(err) => {
+                    eprintln!("[server] connection close task failed: {err}");
+                    false
+                }
+            };
+
+            finish_session(&session, clean, &injector);
         }
         Err(err) => {
             eprintln!("[server] failed to establish connection: {err}");
         }
     }
+}
 
-    drop(permit);
+/// Closes a connection that arrived with the server full unless it has been
+/// admitted within [`ADMISSION_TIMEOUT`].
+async fn refuse_unless_admitted(session: SessionContext) {
+    tokio::time::sleep(ADMISSION_TIMEOUT).await;
+    if !session.admitted() {
+        refuse(&session.connection, CloseCode::ServerFull);
+    }
+}
+
+fn refuse(connection: &quinn::Connection, reason: CloseCode) {
+    println!(
+        "[server] rejecting connection from {}: {reason}",
+        connection.remote_address()
+    );
+    connection.close(reason.into(), reason.name().as_bytes());
 }
 
-async fn listen_bi_streams(connection: quinn::Connection) {
+/// Releases a finished connection's held input, or parks it for the resume
+/// grace window when the client dropped without a clean close.
+fn finish_session(session: &SessionContext, clean: bool, injector: &Injector) {
+    let mut held_input = lock_held(&session.held);
+    if held_input.sequence.irregular() > 0 {
+        println!("[server] input sequence over the connection: {}", held_input.sequence);
+    }
+    let mut held = std::mem::take(&mut held_input.held);
+    drop(held_input);
+    let token = *session
+        .token
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let grace = session.sessions.grace();
+    if let Some(token) = &token {
+        let id = session.connection.stable_id();
+        session
+            .sessions
+            .detach(token, |live| live.connection.stable_id() == id);
+    }
+
+    match token {
+        Some(token) if !clean && !grace.is_zero() => {
+            println!(
+                "[server] keeping session for {}s in case the client reconnects",
+                grace.as_secs()
+            );
+            session.sessions.park(token, held);
+
+            let sessions = Arc::clone(&session.sessions);
+            let injector = injector.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(grace).await;
+                if let Some(mut held) = sessions.expire(&token) {
+                    println!("[server] session expired; releasing held input");
+                    release_held(&mut held, &injector);
+                }
+            });
+        }
+        _ => release_held(&mut held, injector),
+    }
+    // Only now, with its input released or parked, may another client in.
+    lock_slot(&session.slot).take();
+}
+
+async fn listen_bi_streams(
+    connection: quinn::Connection,
+    max_streams: usize,
+    session: SessionContext,
+) {
+    let stream_limit = Arc::new(Semaphore::new(max_streams));
     loop {
         match connection.accept_bi().await {
-            Ok((send, recv)) => {
-                let handle = tokio::runtime::Handle::current();
-                thread::spawn(move || {
-                    handle.block_on(async move {
-                        handle_bi_stream(send, recv).await;
-                    });
+            Ok((mut send, mut recv)) => {
+                let Some(permit) = stream_permit(&stream_limit, "control") else {
+                    let _ = send.reset(CloseCode::StreamLimit.into());
+                    let _ = recv.stop(CloseCode::StreamLimit.into());
+                    continue;
+                };
+                // Control requests are short and only wait on the network,
+                // so a task each is plenty.
+                let session = session.clone();
+                tokio::spawn(async move {
+                    handle_bi_stream(send, recv, session).await;
+                    drop(permit);
                 });
             }
             Err(quinn::ConnectionError::ApplicationClosed { .. })
@@ -192,19 +940,25 @@ async fn listen_bi_streams(connection: quinn::Connection) {
 
 async fn listen_uni_streams(
     connection: quinn::Connection,
-    simulators: Simulators,
-    device_input: DeviceInput,
+    stream_options: StreamOptions,
+    session: SessionContext,
+    injector: Injector,
 ) {
+    let stream_limit = Arc::new(Semaphore::new(stream_options.max_streams));
     loop {
         match connection.accept_uni().await {
-            Ok(recv) => {
-                let handle = tokio::runtime::Handle::current();
-                let simulators = Arc::clone(&simulators);
-                let device_input = device_input.clone();
-                thread::spawn(move || {
-                    handle.block_on(async move {
-                        handle_uni_stream(recv, simulators, device_input).await;
-                    });
+            Ok(mut recv) => {
+                let Some(permit) = stream_permit(&stream_limit, "input") else {
+                    let _ = recv.stop(CloseCode::StreamLimit.into());
+                    // Nothing on it will be applied, so a drain needn't wait for it.
+                    session.finished_streams.finish(u64::from(recv.id()));
+                    continue;
+                };
+                let session = session.clone();
+                let injector = injector.clone();
+                tokio::spawn(async move {
+                    handle_uni_stream(recv, stream_options, session, injector).await;
+                    drop(permit);
                 });
             }
             Err(quinn::ConnectionError::ApplicationClosed { .. })
@@ -219,86 +973,351 @@ async fn listen_uni_streams(
     }
 }
 
-async fn handle_bi_stream(mut send: quinn::SendStream, mut recv: quinn::RecvStream) {
-    let mut total = 0usize;
+/// Takes a slot for one more stream of `kind`, or logs that the connection
+/// already has as many in flight as it may.
+fn stream_permit(limit: &Arc<Semaphore>, kind: &str) -> Option<OwnedSemaphorePermit> {
+    match Arc::clone(limit).try_acquire_owned() {
+        Ok(permit) => Some(permit),
+        Err(TryAcquireError::NoPermits) => {
+            println!("[server] stopping {kind} stream: too many open on this connection");
+            None
+        }
+        Err(TryAcquireError::Closed) => None,
+    }
+}
 
-    loop {
-        match recv.read_chunk(MAX_STREAM_DATA, true).await {
-            Ok(Some(chunk)) => {
-                total += chunk.bytes.len();
-                let message = String::from_utf8_lossy(&chunk.bytes);
-                println!(
-                    "[server] bi stream chunk ({} bytes): {message}",
-                    chunk.bytes.len()
-                );
+async fn handle_bi_stream(
+    mut send: quinn::SendStream,
+    mut recv: quinn::RecvStream,
+    session: SessionContext,
+) {
+    // Nothing here waits on a request's progress.
+    let payload = match read_chunked(&mut recv, MAX_REQUEST_DATA, |_| {}).await {
+        Ok(payload) => payload,
+        Err(ChunkedReadError::TooLarge { limit }) => {
+            eprintln!("[server] bi stream exceeded {limit} bytes; dropping it");
+            return;
+        }
+        Err(ChunkedReadError::Read(err)) => {
+            eprintln!("[server] failed to read bi stream: {err}");
+            return;
+        }
+    };
+
+    let reply = match rmp_serde::from_slice::<ControlRequest>(&payload) {
+        Ok(ControlRequest::WatchInjection) => {
+            report_injection(send, &session.injector).await;
+            return;
+        }
+        Ok(ControlRequest::WatchAvailability) => {
+            report_availability(send, session.availability.clone()).await;
+            return;
+        }
+        Ok(request) => match handle_control(request, &session).await {
+            Ok(response) => match rmp_serde::to_vec(&response) {
+                Ok(reply) => reply,
+                Err(err) => {
+                    eprintln!("[server] failed to encode control response: {err}");
+                    return;
+                }
+            },
+            Err(reason) => {
+                refuse(&session.connection, reason);
+                return;
             }
-            Ok(None) => {
-                println!("[server] bi stream closed after {total} bytes");
-                break;
+        },
+        Err(_) => {
+            let message = String::from_utf8_lossy(&payload);
+            println!(
+                "[server] bi stream closed after {} bytes: {message}",
+                payload.len()
+            );
+            b"ack".to_vec()
+        }
+    };
+
+    if let Err(err) = send_bi_data(&mut send, &reply).await {
+        eprintln!("[server] failed to reply on bi stream: {err}");
+    }
+}
+
+/// Answers a [`ControlRequest::WatchInjection`] with the injector's totals
+/// every [`INJECTION_REPORT_INTERVAL`] until the client stops listening.
+async fn report_injection(mut send: quinn::SendStream, injector: &Injector) {
+    let mut interval = tokio::time::interval(INJECTION_REPORT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let report = match rmp_serde::to_vec(&ControlResponse::Injection(injector.stats())) {
+            Ok(report) => report,
+            Err(err) => {
+                eprintln!("[server] failed to encode injection report: {err}");
+                return;
             }
+        };
+        if let Err(err) = send.write_all(&report).await {
+            println!("[server] injection report stream closed: {err}");
+            return;
+        }
+    }
+}
+
+/// Answers a [`ControlRequest::WatchAvailability`] with whether this machine
+/// can take input, then again on every change until the client stops
+/// listening. Finishes straight away when the server doesn't watch.
+async fn report_availability(
+    mut send: quinn::SendStream,
+    availability: Option<watch::Receiver<Availability>>,
+) {
+    let Some(mut availability) = availability else {
+        let _ = send.finish();
+        return;
+    };
+    loop {
+        let current = *availability.borrow_and_update();
+        let report = match rmp_serde::to_vec(&current.response()) {
+            Ok(report) => report,
             Err(err) => {
-                eprintln!("[server] failed to read bi stream: {err}");
+                eprintln!("[server] failed to encode availability report: {err}");
                 return;
             }
+        };
+        if let Err(err) = send.write_all(&report).await {
+            println!("[server] availability stream closed: {err}");
+            return;
+        }
+        if availability.changed().await.is_err() {
+            let _ = send.finish();
+            return;
         }
     }
+}
 
-    if let Err(err) = send_bi_data(&mut send, b"ack").await {
-        eprintln!("[server] failed to reply on bi stream: {err}");
+/// Hands a session over from the connection still using it to `session`,
+/// closing the old one. Returns what the old connection held.
+fn supersede(previous: LiveSession, session: &SessionContext) -> HeldState {
+    if previous.connection.stable_id() == session.connection.stable_id() {
+        // A repeated hello on the same connection; nothing to hand over.
+        return HeldState::default();
     }
+    println!(
+        "[server] {} took over the session of {}",
+        session.connection.remote_address(),
+        previous.connection.remote_address()
+    );
+    let held = std::mem::take(&mut lock_held(&previous.held).held);
+    // It holds nothing now, so needs no slot either.
+    lock_slot(&previous.slot).take();
+    let code = CloseCode::Superseded;
+    previous.connection.close(code.into(), code.name().as_bytes());
+    held
+}
+
+/// The answer to `request`, or why the connection should be closed instead.
+async fn handle_control(
+    request: ControlRequest,
+    session: &SessionContext,
+) -> Result<ControlResponse, CloseCode> {
+    let response = match request {
+        ControlRequest::Hello {
+            resume_token,
+            observe,
+            clock_micros,
+            display_size,
+            wire_format,
+        } => {
+            if let Some(client_micros) = clock_micros {
+                let one_way = session.connection.rtt() / 2;
+                lock_held(&session.held)
+                    .latency
+                    .sync_clock(client_micros, monotonic_micros(), one_way);
+            }
+            lock_held(&session.held).client_display = display_size;
+            if wire_format != WireFormat::default() {
+                println!(
+                    "[server] {} sends input as {wire_format}",
+                    session.connection.remote_address()
+                );
+            }
+            lock_held(&session.held).wire_format = wire_format;
+
+            let previous = resume_token
+                .and_then(|token| Some((token, session.sessions.take_live(&token)?)));
+            if observe {
+                // Observers inject nothing, so never keep a client out.
+                lock_slot(&session.slot).take();
+            } else if !session.admit(previous.as_ref().map(|(_, previous)| previous)) {
+                if let Some((token, previous)) = previous {
+                    session.sessions.attach(token, previous);
+                }
+                return Err(CloseCode::ServerFull);
+            }
+            let claimed = match previous {
+                Some((token, previous)) => Some((token, supersede(previous, session))),
+                None => resume_token
+                    .and_then(|token| Some((token, session.sessions.resume(&token)?))),
+            };
+            let (token, resumed) = match claimed {
+                Some((token, held)) => {
+                    println!("[server] client resumed its previous session");
+                    lock_held(&session.held).held.merge(held);
+                    (token, true)
+                }
+                None => (session.sessions.issue(), false),
+            };
+            session.sessions.attach(
+                token,
+                LiveSession {
+                    connection: session.connection.clone(),
+                    held: Arc::clone(&session.held),
+                    slot: Arc::clone(&session.slot),
+                },
+            );
+
+            *session
+                .token
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(token);
+
+            let observing = observe && !session.observing.swap(true, Ordering::SeqCst);
+            if observing {
+                println!(
+                    "[server] {} joined as an observer",
+                    session.connection.remote_address()
+                );
+                tokio::spawn(stream_to_observer(
+                    session.connection.clone(),
+                    session.observers.subscribe(),
+                ));
+            }
+            ControlResponse::Welcome {
+                token,
+                resumed,
+                observing,
+                wire_format,
+                reports_availability: session.availability.is_some(),
+            }
+        }
+        ControlRequest::Displays => ControlResponse::Displays(session.displays.displays()),
+        ControlRequest::Drain { streams } => {
+            let complete = session
+                .finished_streams
+                .wait_for(&streams, DRAIN_TIMEOUT)
+                .await;
+            if !complete {
+                eprintln!(
+                    "[server] drain gave up after {}s; some input may be unapplied",
+                    DRAIN_TIMEOUT.as_secs()
+                );
+            }
+            ControlResponse::Drained { complete }
+        }
+        ControlRequest::SetPointerMode(requested) => {
+            let display = match requested {
+                PointerMode::Relative => None,
+                PointerMode::Absolute => absolute_target(
+                    session.injector.supports_absolute(),
+                    &session.displays.displays(),
+                ),
+            };
+            let granted = match display {
+                Some(_) => PointerMode::Absolute,
+                None => PointerMode::Relative,
+            };
+            if granted != requested {
+                println!("[server] absolute pointer mode unavailable here; staying relative");
+            } else {
+                println!("[server] client switched to {granted:?} pointer mode");
+            }
+            lock_held(&session.held).absolute_display = display;
+            ControlResponse::PointerMode(granted)
+        }
+        ControlRequest::SetDisplaySize(size) => {
+            lock_held(&session.held).client_display = Some(size);
+            ControlResponse::DisplaySize(size)
+        }
+        ControlRequest::Warp(absolute) => {
+            let observing = session.observing.load(Ordering::SeqCst);
+            let display = absolute_target(
+                session.injector.supports_absolute(),
+                &session.displays.displays(),
+            )
+            .filter(|_| !observing && session.admitted());
+            // In the server's own space: the client's display and the
+            // absolute mapping only apply to captured positions.
+            if let Some(display) = &display {
+                let (x, y) = display.point_at(absolute);
+                lock_held(&session.held).touch();
+                session.injector.absolute_move(x, y);
+            }
+            ControlResponse::Warped(display.is_some())
+        }
+        // Normally streamed by `report_injection`; one snapshot otherwise.
+        ControlRequest::WatchInjection => ControlResponse::Injection(session.injector.stats()),
+        ControlRequest::WatchAvailability => session
+            .availability
+            .as_ref()
+            .map_or(Availability::Available, |availability| *availability.borrow())
+            .response(),
+    };
+    Ok(response)
+}
+
+/// The display absolute positions are mapped onto, if absolute pointer mode
+/// can be granted: it takes an injector able to place the pointer and a
+/// known display to place it on.
+pub fn absolute_target(absolute_capable: bool, displays: &[DisplayInfo]) -> Option<DisplayInfo> {
+    if !absolute_capable {
+        return None;
+    }
+    DisplayInfo::primary(displays).cloned()
 }
 
 async fn handle_uni_stream(
     mut recv: quinn::RecvStream,
-    simulators: Simulators,
-    device_input: DeviceInput,
+    stream_options: StreamOptions,
+    session: SessionContext,
+    injector: Injector,
 ) {
+    let stream_id = u64::from(recv.id());
+    let held = Arc::clone(&session.held);
     let mut total = 0usize;
+    let mut dispatch = StreamDispatch::new(stream_options, Arc::clone(&held), injector.clone());
+    dispatch.peer = Some(session.connection.remote_address());
 
     loop {
-        match recv.read_chunk(MAX_STREAM_DATA, true).await {
+        let next_chunk = recv.read_chunk(MAX_STREAM_DATA, true);
+        let read = match stream_options.idle_release {
+            Some(interval) => match timeout(interval, next_chunk).await {
+                Ok(read) => read,
+                Err(_elapsed) => {
+                    release_if_idle(&held, interval, &injector);
+                    continue;
+                }
+            },
+            None => next_chunk.await,
+        };
+
+        match read {
+            Ok(Some(_)) if session.observing.load(Ordering::SeqCst) => {
+                // Observers watch only; never let what they send be applied.
+                println!("[server] ignoring input stream from an observer");
+                let _ = recv.stop(CloseCode::ProtocolError.into());
+                break;
+            }
+            Ok(Some(_)) if !session.admitted() => {
+                // Sent before a hello found it room on a full server.
+                println!("[server] ignoring input stream from a connection without a slot");
+                let _ = recv.stop(CloseCode::ServerFull.into());
+                break;
+            }
             Ok(Some(chunk)) => {
                 total += chunk.bytes.len();
-                if let Ok(mouse_move) = rmp_serde::from_slice::<MouseMove>(&chunk.bytes) {
-                    #[cfg(target_os = "linux")]
-                    {
-                        match device_input.lock() {
-                            Ok(mut maybe_device) => {
-                                if let Some(device) = maybe_device.as_mut() {
-                                    if let Err(err) = do_mouse_move(device, mouse_move) {
-                                        eprintln!("[server] failed to emit mouse move: {err}");
-                                    }
-                                } else {
-                                    eprintln!("[server] virtual mouse not available; dropping MouseMove");
-                                }
-                            }
-                            Err(poisoned) => {
-                                eprintln!("[server] virtual mouse mutex poisoned: {poisoned}");
-                            }
-                        }
-                    }
-
-                    #[cfg(not(target_os = "linux"))]
-                    {
-                        let _ = device_input;
-                        do_mouse_move(&simulators[1], mouse_move);
-                    }
-                } else if let Ok(event_type) = rmp_serde::from_slice::<EventType>(&chunk.bytes) {
-                    match event_type {
-                        EventType::ButtonPress(..)
-                        | EventType::ButtonRelease(..)
-                        | EventType::Wheel { .. } => {
-                            simulators[1].enqueue(event_type);
-                        }
-                        _other => {
-                            simulators[0].enqueue(event_type);
-                        }
-                    }
-                } else {
-                    println!(
-                        "[server] uni stream unknown payload ({} bytes)",
-                        chunk.bytes.len()
-                    );
+                dispatch.push(&chunk.bytes);
+                if dispatch.decoder.overflowed() {
+                    // The rest can't be told apart from new values; what
+                    // the stream holds is released as if it had ended.
+                    let _ = recv.stop(CloseCode::TooLarge.into());
+                    break;
                 }
             }
             Ok(None) => {
@@ -306,14 +1325,415 @@ async fn handle_uni_stream(
                 break;
             }
             Err(err) => {
+                // Held input is left for the connection to release or park.
                 eprintln!("[server] failed to read uni stream: {err}");
-                break;
+                session.finished_streams.finish(stream_id);
+                return;
             }
         }
     }
-    simulators[0].enqueue(EventType::KeyRelease(rdev::Key::ControlLeft));
-    simulators[0].enqueue(EventType::KeyRelease(rdev::Key::Alt));
-    simulators[0].enqueue(EventType::KeyRelease(rdev::Key::Num0));
+    dispatch.finish();
+    session.finished_streams.finish(stream_id);
+}
+
+/// Decodes a uni stream's bytes and applies every complete frame: all of
+/// stream handling that doesn't depend on QUIC.
+struct StreamDispatch {
+    decoder: FrameDecoder,
+    // Last source tag seen, so changes are logged once rather than per event.
+    source_id: Option<SourceId>,
+    stream_options: StreamOptions,
+    held: SharedHeldInput,
+    injector: Injector,
+    // Send time of the next input on this stream, if the client stamped it.
+    sent_at: Option<u64>,
+    // Button whose next press completes a double-click on the client.
+    double_click: Option<Button>,
+    // Who sent this stream, for the audit log and recording.
+    peer: Option<SocketAddr>,
+}
+
+impl StreamDispatch {
+    fn new(stream_options: StreamOptions, held: SharedHeldInput, injector: Injector) -> Self {
+        let format = lock_held(&held).wire_format;
+        Self {
+            decoder: FrameDecoder::new(MAX_STREAM_DATA).with_format(format),
+            source_id: None,
+            stream_options,
+            held,
+            injector,
+            sent_at: None,
+            double_click: None,
+            peer: None,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        if self.stream_options.debug_raw {
+            dump_raw(bytes, self.decoder.format());
+        }
+        self.decoder.push(bytes);
+        while let Some(frame) = self.decoder.next_frame() {
+            if self.decoder.source_id() != self.source_id {
+                self.source_id = self.decoder.source_id();
+                match self.source_id {
+                    Some(id) => println!("[server] uni stream input now from source {id}"),
+                    None => println!("[server] uni stream input now untagged"),
+                }
+            }
+            if self.stream_options.verbose_events {
+                dump_frame(&frame);
+            }
+            if let Some(recording) = self.injector.recording() {
+                recording.record(self.peer, &frame);
+            }
+            if let (Some(audit), Some(peer)) = (self.injector.audit(), self.peer) {
+                audit.record(peer, &frame);
+            }
+            if let Frame::SentAt(micros) = frame {
+                self.sent_at = Some(micros);
+                continue;
+            }
+            if let Frame::Seq(header) = frame {
+                self.record_seq(header);
+                continue;
+            }
+            if let Frame::DoubleClick(button) = frame {
+                self.double_click = Some(button);
+                continue;
+            }
+            let over_limit = self.stream_options.max_inputs_per_sec.is_some_and(|per_sec| {
+                counts_as_input(&frame) && !lock_held(&self.held).admit(per_sec)
+            });
+            let double_click = self.double_click.take();
+            if over_limit {
+                self.sent_at = None;
+                continue;
+            }
+            if let Frame::Event(EventType::ButtonPress(button)) = frame {
+                self.time_click(button, double_click == Some(button));
+            }
+            apply_frame(frame, &self.held, &self.injector, &self.stream_options);
+            if let Some(sent) = self.sent_at.take() {
+                self.record_latency(sent);
+            }
+        }
+    }
+
+    /// Slips a click of `button` in ahead of its press when the client
+    /// marked the press as completing a double-click but the first click
+    /// landed here too long ago to count.
+    fn time_click(&self, button: Button, hinted: bool) {
+        let Some(window) = self.stream_options.double_click_window else {
+            return;
+        };
+        let replay = lock_held(&self.held)
+            .clicks
+            .press(button, hinted, Instant::now(), window);
+        if replay {
+            self.injector.event(EventType::ButtonPress(button));
+            self.injector.event(EventType::ButtonRelease(button));
+        }
+    }
+
+    fn record_latency(&self, sent_micros: u64) {
+        let mut held = lock_held(&self.held);
+        let latency = &mut held.latency;
+        if latency.record(sent_micros, monotonic_micros()).is_none() {
+            return;
+        }
+        if !latency.recorded().is_multiple_of(LATENCY_WINDOW as u64) {
+            return;
+        }
+        if let Some(average) = latency.average() {
+            println!(
+                "[server] input latency averaging {:.1} ms over the last {LATENCY_WINDOW} events",
+                average.as_secs_f64() * 1000.0
+            );
+        }
+    }
+
+    fn record_seq(&self, header: Seq) {
+        let mut held = lock_held(&self.held);
+        let sequence = &mut held.sequence;
+        let outcome = sequence.record(header);
+        if outcome == SeqOutcome::InOrder {
+            return;
+        }
+        if sequence.irregular() == 1 || sequence.irregular().is_multiple_of(100) {
+            println!("[server] input arriving out of sequence ({outcome:?}): {sequence}");
+        }
+    }
+
+    /// The stream ended cleanly: drop any partial value and release what
+    /// it still holds.
+    fn finish(self) {
+        if self.decoder.pending() > 0 {
+            println!(
+                "[server] uni stream ended mid-value; dropping {} bytes",
+                self.decoder.pending()
+            );
+        }
+        release_held(&mut lock_held(&self.held).held, &self.injector);
+    }
+}
+
+/// Runs `chunks` through the same decode and dispatch path as a uni stream
+/// that carried them and then ended, without any networking.
+#[cfg(any(test, feature = "testing"))]
+pub(crate) fn dispatch_chunks(
+    chunks: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    stream_options: StreamOptions,
+    injector: Injector,
+) {
+    let mut dispatch = StreamDispatch::new(stream_options, SharedHeldInput::default(), injector);
+    for chunk in chunks {
+        dispatch.push(chunk.as_ref());
+    }
+    dispatch.finish();
+}
+
+/// Whether `frame` counts against [`StreamOptions::max_inputs_per_sec`].
+/// Releases never do, so dropping a flood can't leave anything held.
+fn counts_as_input(frame: &Frame) -> bool {
+    match frame {
+        Frame::Event(EventType::KeyRelease(_) | EventType::ButtonRelease(_)) => false,
+        Frame::ExtraKey(input) => input.pressed,
+        Frame::RawKey(input) => input.pressed,
+        Frame::Mouse(_)
+        | Frame::Event(_)
+        | Frame::Char(_)
+        | Frame::Gesture(_)
+        | Frame::Absolute(_)
+        | Frame::Combo(_) => true,
+        Frame::Edge(_)
+        | Frame::SentAt(_)
+        | Frame::Seq(_)
+        | Frame::DoubleClick(_)
+        | Frame::KeepHeld
+        | Frame::Unknown(_) => false,
+    }
+}
+
+fn apply_frame(
+    frame: Frame,
+    held: &SharedHeldInput,
+    injector: &Injector,
+    stream_options: &StreamOptions,
+) {
+    match frame {
+        Frame::Mouse(mouse_move) => {
+            if let Some(limit) = stream_options.button_hold_limit {
+                release_stuck_buttons(held, limit, injector);
+            }
+            lock_held(held).touch();
+            let sensitivity = stream_options.pointer_sensitivity;
+            let (dx, dy) =
+                transform_delta(mouse_move.dx, mouse_move.dy, stream_options.orientation);
+            injector.mouse_move(MouseMove {
+                dx: dx * sensitivity,
+                dy: dy * sensitivity,
+            });
+        }
+        // An absolute desktop position, unlike the relative `Frame::Mouse`;
+        // it goes where `Frame::Absolute` does, but needs no pointer mode as
+        // it names a pixel rather than a point on the client's display.
+        Frame::Event(EventType::MouseMove { x, y }) => {
+            lock_held(held).touch();
+            injector.absolute_move(x, y);
+        }
+        Frame::Event(event_type) => {
+            lock_held(held).observe(&event_type);
+            injector.event(event_type);
+        }
+        Frame::Char(ch) => {
+            let layout = stream_options.keyboard_layout.unwrap_or(&US_QWERTY);
+            let Some(stroke) = layout.keystroke_for(ch) else {
+                eprintln!("[server] layout '{}' cannot type {ch:?}; dropping it", layout.name);
+                return;
+            };
+            let events = {
+                let mut held = lock_held(held);
+                held.touch_input();
+                stroke.events(held.held.keys())
+            };
+            // Balanced presses and releases, so nothing new is left held.
+            for event in events {
+                injector.event(event);
+            }
+        }
+        Frame::Gesture(gesture) => {
+            let events = {
+                let mut held = lock_held(held);
+                held.touch_input();
+                let control_held = held
+                    .held
+                    .keys()
+                    .iter()
+                    .any(|key| matches!(key, Key::ControlLeft | Key::ControlRight));
+                held.gestures.translate(gesture, control_held)
+            };
+            // Any Control press it adds is released again straight after.
+            for event in events {
+                injector.event(event);
+            }
+        }
+        Frame::ExtraKey(input) => {
+            let Some(event_type) = input.event() else {
+                eprintln!("[server] {:?} cannot be injected here; dropping it", input.key);
+                return;
+            };
+            lock_held(held).observe(&event_type);
+            injector.event(event_type);
+        }
+        Frame::RawKey(input) => {
+            if !is_key_code(input.code) {
+                eprintln!("[server] {} is not a key code; dropping it", input.code);
+                return;
+            }
+            let mut held = lock_held(held);
+            held.touch_input();
+            held.held.observe_raw(input);
+            drop(held);
+            injector.raw_key(input);
+        }
+        Frame::Absolute(absolute) => {
+            let (display, client) = {
+                let held = lock_held(held);
+                (held.absolute_display.clone(), held.client_display)
+            };
+            match display {
+                Some(display) => {
+                    lock_held(held).touch();
+                    let orientation = stream_options.orientation;
+                    let (x, y) = stream_options.absolute_mapping.place(
+                        &display,
+                        client.map(|size| orientation.display_size(size)),
+                        transform_absolute(absolute, orientation),
+                    );
+                    injector.absolute_move(x, y);
+                }
+                None => println!("[server] ignoring absolute move outside absolute pointer mode"),
+            }
+        }
+        Frame::Combo(combo) => {
+            if let Some(problem) = combo.problem() {
+                eprintln!("[server] refusing key combination: {problem}");
+                return;
+            }
+            if combo == KeyCombo::ctrl_alt_del() && injector.secure_attention() {
+                lock_held(held).touch_input();
+                println!("[server] sending the secure attention sequence");
+                return;
+            }
+            let events = {
+                let mut held = lock_held(held);
+                held.touch_input();
+                combo.events(held.held.keys())
+            };
+            println!("[server] sending {}", combo.describe());
+            injector.key_sequence(&events);
+        }
+        Frame::Edge(edge) => {
+            println!("[server] client pointer reached the {edge:?} edge");
+        }
+        Frame::KeepHeld => lock_held(held).touch(),
+        // Consumed by the stream dispatch, which knows what it stamps.
+        Frame::SentAt(_) | Frame::Seq(_) | Frame::DoubleClick(_) => {}
+        Frame::Unknown(len) => {
+            println!("[server] uni stream unknown payload ({len} bytes)");
+        }
+    }
+}
+
+/// Logs a chunk as hex and every way it could be read. A chunk may hold
+/// part of a value or several, so the attempts are only a guide; what was
+/// actually decoded follows in the usual log lines.
+fn dump_raw(bytes: &[u8], format: WireFormat) {
+    if format == WireFormat::Json {
+        let text = String::from_utf8_lossy(bytes);
+        println!("[server] raw {} bytes: {}", bytes.len(), text.trim_end());
+    } else {
+        println!("[server] raw {} bytes: {}", bytes.len(), format_hex(bytes));
+    }
+    for attempt in attempted_decodes(bytes, format) {
+        println!("[server]   as {attempt}");
+    }
+}
+
+/// Prints a decoded frame as a script line, so stdout can be replayed later.
+fn dump_frame(frame: &Frame) {
+    match frame {
+        Frame::Mouse(mouse_move) => println!("{}", script::format_move(mouse_move)),
+        Frame::Event(event_type) => println!("{}", script::format_event(event_type)),
+        // Scripts carry keys, not characters; keep the dump replayable.
+        Frame::Char(ch) => println!("# char {ch:?}"),
+        Frame::Edge(edge) => println!("# edge {edge:?}"),
+        Frame::SentAt(micros) => println!("# sent-at {micros}"),
+        Frame::DoubleClick(button) => println!("# double-click {button:?}"),
+        Frame::KeepHeld => println!("# keep-held"),
+        Frame::Seq(Seq { category, seq }) => println!("# seq {category:?} {seq}"),
+        Frame::Gesture(gesture) => println!("# gesture {gesture:?}"),
+        Frame::Absolute(absolute) => println!("# absolute {} {}", absolute.x, absolute.y),
+        Frame::ExtraKey(input) => match input.event() {
+            Some(event_type) => println!("{}", script::format_event(&event_type)),
+            None => println!("# extra key {input:?}"),
+        },
+        Frame::RawKey(input) => println!("# raw key {input:?}"),
+        Frame::Combo(combo) => println!("# combo {}", combo.describe()),
+        Frame::Unknown(_) => {}
+    }
+}
+
+fn release_held(held: &mut HeldState, injector: &Injector) {
+    for release in held.drain() {
+        injector.event(release);
+    }
+    for release in held.drain_raw() {
+        injector.raw_key(release);
+    }
+}
+
+fn lock_held(held: &SharedHeldInput) -> std::sync::MutexGuard<'_, HeldInput> {
+    held.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn lock_slot(slot: &ConnectionSlot) -> std::sync::MutexGuard<'_, Option<OwnedSemaphorePermit>> {
+    slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Releases everything the connection still holds once it has been silent for
+/// `interval`, so a client that vanished mid-chord doesn't leave keys stuck.
+fn release_if_idle(held: &SharedHeldInput, interval: Duration, injector: &Injector) {
+    let mut held = lock_held(held);
+    if held.held.is_empty() || !held.idle_for(interval) {
+        return;
+    }
+
+    println!(
+        "[server] no input for {}s; releasing held keys and buttons",
+        interval.as_secs()
+    );
+    release_held(&mut held.held, injector);
+}
+
+/// Releases any button held past `limit` (see
+/// [`StreamOptions::button_hold_limit`]) before more motion drags with it.
+fn release_stuck_buttons(held: &SharedHeldInput, limit: Duration, injector: &Injector) {
+    let mut held = lock_held(held);
+    if !held.button_stuck(limit) {
+        return;
+    }
+
+    let releases = held.held.drain_buttons();
+    println!(
+        "[server] {} buttons held through {}s of nothing but motion; releasing them",
+        releases.len(),
+        limit.as_secs()
+    );
+    for release in releases {
+        injector.event(release);
+    }
 }
 
 async fn send_bi_data(
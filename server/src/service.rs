@@ -0,0 +1,170 @@
+//! Running under a service manager such as systemd or launchd. Startup
+//! failures end the process with an exit code saying what went wrong, so a
+//! unit can tell a missing kernel module from a port already in use; the
+//! codes follow `sysexits.h`. Once listening, the server tells systemd it is
+//! ready when started with `Type=notify`, and SIGTERM (or Ctrl+C when run by
+//! hand) closes every connection and releases held input before exiting.
+
+use std::{error::Error, ffi::OsStr, fmt, io, net::SocketAddr};
+
+/// Something went wrong; nothing more specific is known.
+pub const EXIT_FAILURE: u8 = 1;
+/// The uinput kernel module isn't loaded (`EX_UNAVAILABLE`).
+pub const EXIT_UINPUT_MISSING: u8 = 69;
+/// A listening socket couldn't be bound, e.g. the port is taken
+/// (`EX_TEMPFAIL`, as it often is by a previous instance still exiting).
+pub const EXIT_BIND_FAILED: u8 = 75;
+/// The server may not open something it needs: `/dev/uinput`, a
+/// privileged port, a log file (`EX_NOPERM`).
+pub const EXIT_PERMISSION_DENIED: u8 = 77;
+
+/// Why the server couldn't start.
+#[derive(Debug)]
+pub enum StartupError {
+    /// The uinput module isn't available; the message says how to load it.
+    UinputMissing(String),
+    PermissionDenied(String),
+    BindFailed {
+        addr: SocketAddr,
+        error: io::Error,
+    },
+}
+
+impl StartupError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            StartupError::UinputMissing(_) => EXIT_UINPUT_MISSING,
+            StartupError::PermissionDenied(_) => EXIT_PERMISSION_DENIED,
+            StartupError::BindFailed { error, .. }
+                if error.kind() == io::ErrorKind::PermissionDenied =>
+            {
+                EXIT_PERMISSION_DENIED
+            }
+            StartupError::BindFailed { .. } => EXIT_BIND_FAILED,
+        }
+    }
+}
+
+impl fmt::Display for StartupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartupError::UinputMissing(message) | StartupError::PermissionDenied(message) => {
+                f.write_str(message)
+            }
+            StartupError::BindFailed { addr, error } => {
+                write!(f, "failed to listen on {addr}: {error}")
+            }
+        }
+    }
+}
+
+impl Error for StartupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StartupError::BindFailed { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// The exit code for the server failing with `error`. Errors other than a
+/// [`StartupError`] only get a code of their own when they are I/O errors
+/// refusing permission, such as a config or log file it may not open.
+pub fn exit_code(error: &(dyn Error + 'static)) -> u8 {
+    if let Some(startup) = error.downcast_ref::<StartupError>() {
+        return startup.exit_code();
+    }
+    match error.downcast_ref::<io::Error>() {
+        Some(io_error) if io_error.kind() == io::ErrorKind::PermissionDenied => {
+            EXIT_PERMISSION_DENIED
+        }
+        _ => EXIT_FAILURE,
+    }
+}
+
+/// Tells systemd the server is listening on `addrs`, if it asked to be
+/// told. Does nothing when not started by systemd with `Type=notify`.
+pub fn notify_ready(addrs: &[SocketAddr]) {
+    let listening = addrs
+        .iter()
+        .map(SocketAddr::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let state = format!("READY=1\nSTATUS=Listening on {listening}");
+    match notify(std::env::var_os("NOTIFY_SOCKET").as_deref(), &state) {
+        Ok(true) => println!("[server] told the service manager it is ready"),
+        Ok(false) => {}
+        Err(err) => eprintln!("[server] failed to notify the service manager: {err}"),
+    }
+}
+
+/// Sends `state` to the `sd_notify` socket at `socket`. `Ok(false)` when
+/// there is no socket to send to.
+pub fn notify(socket: Option<&OsStr>, state: &str) -> io::Result<bool> {
+    let Some(socket) = socket.filter(|socket| !socket.is_empty()) else {
+        return Ok(false);
+    };
+    send_notify(socket, state)?;
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn send_notify(socket: &OsStr, state: &str) -> io::Result<()> {
+    use std::os::unix::{ffi::OsStrExt, net::UnixDatagram};
+
+    let sender = UnixDatagram::unbound()?;
+    match socket.as_bytes().strip_prefix(b"@") {
+        // An abstract socket, named without a file.
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+            sender.send_to_addr(state.as_bytes(), &SocketAddr::from_abstract_name(name)?)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "abstract notify sockets need Linux",
+            ));
+        }
+        None => {
+            sender.send_to(state.as_bytes(), socket)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_notify(_socket: &OsStr, _state: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "service notification needs a Unix socket",
+    ))
+}
+
+/// Resolves to the name of the first shutdown signal received: SIGTERM, as
+/// service managers send on stop, or SIGINT, as Ctrl+C sends. The handlers
+/// are installed before this returns, so a signal arriving before the
+/// future is first polled still counts.
+#[cfg(unix)]
+pub fn shutdown_signal() -> io::Result<impl Future<Output = &'static str> + Send> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    Ok(async move {
+        tokio::select! {
+            _ = terminate.recv() => "SIGTERM",
+            _ = interrupt.recv() => "SIGINT",
+        }
+    })
+}
+
+/// Resolves on Ctrl+C, the only shutdown signal outside Unix.
+#[cfg(not(unix))]
+pub fn shutdown_signal() -> io::Result<impl Future<Output = &'static str> + Send> {
+    Ok(async {
+        let _ = tokio::signal::ctrl_c().await;
+        "Ctrl+C"
+    })
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use shared::SessionToken;
+
+use crate::held::HeldState;
+
+/// Held input of connections that dropped without a clean close, kept for a
+/// grace window so the same client can reconnect and pick up where it was.
+///
+/// Also tracks which live connection owns each token (as an `L`), since a
+/// client often reconnects before the server has noticed its old
+/// connection is gone.
+pub struct SessionStore<L> {
+    grace: Duration,
+    parked: Mutex<HashMap<SessionToken, Parked>>,
+    live: Mutex<HashMap<SessionToken, L>>,
+}
+
+struct Parked {
+    held: HeldState,
+    expires: Instant,
+}
+
+impl<L> SessionStore<L> {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            grace,
+            parked: Mutex::new(HashMap::new()),
+            live: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn grace(&self) -> Duration {
+        self.grace
+    }
+
+    pub fn issue(&self) -> SessionToken {
+        rand::random()
+    }
+
+    /// Parks `held` under `token` until the grace window runs out.
+    pub fn park(&self, token: SessionToken, held: HeldState) {
+        let expires = Instant::now() + self.grace;
+        self.lock().insert(token, Parked { held, expires });
+    }
+
+    /// Claims a parked session. Expired tokens are rejected and left for
+    /// [`SessionStore::expire`] to release.
+    pub fn resume(&self, token: &SessionToken) -> Option<HeldState> {
+        let mut parked = self.lock();
+        match parked.get(token) {
+            Some(entry) if entry.expires > Instant::now() => {
+                parked.remove(token).map(|entry| entry.held)
+            }
+            _ => None,
+        }
+    }
+
+    /// Removes a session whose grace window has passed, returning whatever it
+    /// still held so the caller can release it.
+    pub fn expire(&self, token: &SessionToken) -> Option<HeldState> {
+        let mut parked = self.lock();
+        match parked.get(token) {
+            Some(entry) if entry.expires <= Instant::now() => {
+                parked.remove(token).map(|entry| entry.held)
+            }
+            _ => None,
+        }
+    }
+
+    /// Removes every parked session, returning what they held, for a server
+    /// shutting down.
+    pub fn expire_all(&self) -> Vec<HeldState> {
+        self.lock().drain().map(|(_, entry)| entry.held).collect()
+    }
+
+    /// Records `live` as the connection currently using `token`.
+    pub fn attach(&self, token: SessionToken, live: L) {
+        self.lock_live().insert(token, live);
+    }
+
+    /// Takes `token` from whichever live connection still holds it, for a
+    /// client that reconnected before its old connection was closed.
+    pub fn take_live(&self, token: &SessionToken) -> Option<L> {
+        self.lock_live().remove(token)
+    }
+
+    /// Forgets the live owner of `token` if `is_owner` says it's the caller;
+    /// a connection that was taken over must not detach its successor.
+    pub fn detach(&self, token: &SessionToken, is_owner: impl FnOnce(&L) -> bool) {
+        let mut live = self.lock_live();
+        if live.get(token).is_some_and(is_owner) {
+            live.remove(token);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<SessionToken, Parked>> {
+        self.parked
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_live(&self) -> std::sync::MutexGuard<'_, HashMap<SessionToken, L>> {
+        self.live
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
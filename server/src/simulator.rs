@@ -1,32 +1,381 @@
-use rdev::{simulate, EventType};
-use std::sync::mpsc::{self, Sender};
+use rdev::{simulate, EventType, SimulateError};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread;
+use std::time::Duration;
 
+use shared::monotonic_micros;
+
+/// What actually replays an event; `rdev::simulate` outside tests.
+pub type SimulateFn = Arc<dyn Fn(&EventType) -> Result<(), SimulateError> + Send + Sync>;
+
+/// How often the watchdog looks at the simulators.
+pub const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Replays events on a worker thread of its own, so a slow `simulate` never
+/// holds up the stream that decoded them.
+///
+/// If `simulate` hangs (e.g. a wedged display driver) the queue only grows;
+/// [`EventSimulator::health`] spots that and [`EventSimulator::restart`]
+/// swaps in a fresh worker, dropping the backlog.
 pub struct EventSimulator {
+    name: &'static str,
+    simulate: SimulateFn,
+    worker: Mutex<Worker>,
+    state: Arc<WorkerState>,
+}
+
+struct Worker {
     sender: Sender<EventType>,
+    generation: u64,
+}
+
+#[derive(Default)]
+struct WorkerState {
+    failed: AtomicU64,
+    enqueued: AtomicU64,
+    /// Events taken off the queue, whether simulated, refused or dropped.
+    done: AtomicU64,
+    /// [`monotonic_micros`] when the worker last finished an event, or was
+    /// (re)started.
+    last_progress: AtomicU64,
+    /// Bumped on restart; a worker that finds it changed stops, so one that
+    /// was stuck never replays its stale backlog once it wakes.
+    generation: AtomicU64,
+}
+
+/// What [`EventSimulator::health`] found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulatorHealth {
+    Idle,
+    /// Events are queued and the worker finished one recently.
+    Draining { depth: u64 },
+    /// Events have been queued for `for_micros` without any being finished.
+    Stalled { depth: u64, for_micros: u64 },
 }
 
 impl EventSimulator {
     pub fn new() -> Self {
-        let (sender, receiver) = mpsc::channel::<EventType>();
-
-        thread::Builder::new()
-            .name("event-simulator".into())
-            .spawn(move || {
-                for event in receiver {
-                    if let Err(error) = simulate(&event) {
-                        eprintln!("[server] failed to simulate event: {error:?}");
-                    }
-                }
-            })
-            .expect("failed to spawn event simulator thread");
+        Self::named("event")
+    }
+
+    /// A simulator replaying through `rdev`, named `name` in logs.
+    pub fn named(name: &'static str) -> Self {
+        Self::with_simulate(name, Arc::new(simulate))
+    }
+
+    /// A simulator that replays through `simulate`, named `name` in logs.
+    pub fn with_simulate(name: &'static str, simulate: SimulateFn) -> Self {
+        let state = Arc::new(WorkerState::default());
+        state.last_progress.store(monotonic_micros(), Ordering::Relaxed);
+        let sender = spawn_worker(name, Arc::clone(&simulate), Arc::clone(&state), 0);
+        Self {
+            name,
+            simulate,
+            worker: Mutex::new(Worker {
+                sender,
+                generation: 0,
+            }),
+            state,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Queues `event` for simulation; false if the simulator thread is gone.
+    pub fn enqueue(&self, event: EventType) -> bool {
+        let worker = self.worker();
+        // An idle worker has nothing to show for the time it sat idle, so
+        // start the stall clock from this event.
+        if self.queue_depth() == 0 {
+            self.state
+                .last_progress
+                .store(monotonic_micros(), Ordering::Relaxed);
+        }
+        match worker.sender.send(event) {
+            Ok(()) => {
+                self.state.enqueued.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(error) => {
+                eprintln!("[server] failed to enqueue event for simulation: {error}");
+                false
+            }
+        }
+    }
+
+    /// Queued events the OS refused to simulate, or that were dropped by a
+    /// [`restart`](EventSimulator::restart).
+    pub fn failed(&self) -> u64 {
+        self.state.failed.load(Ordering::Relaxed)
+    }
+
+    /// Events waiting for the worker, including the one it is on.
+    pub fn queue_depth(&self) -> u64 {
+        let done = self.state.done.load(Ordering::Relaxed);
+        self.state
+            .enqueued
+            .load(Ordering::Relaxed)
+            .saturating_sub(done)
+    }
+
+    /// Whether the worker has kept up, as of `now_micros` on the
+    /// [`monotonic_micros`] clock. Stalled once events have waited
+    /// `stall_after` without any finishing.
+    pub fn health(&self, now_micros: u64, stall_after: Duration) -> SimulatorHealth {
+        let depth = self.queue_depth();
+        if depth == 0 {
+            return SimulatorHealth::Idle;
+        }
+        let last_progress = self.state.last_progress.load(Ordering::Relaxed);
+        let for_micros = now_micros.saturating_sub(last_progress);
+        if u128::from(for_micros) >= stall_after.as_micros() {
+            SimulatorHealth::Stalled { depth, for_micros }
+        } else {
+            SimulatorHealth::Draining { depth }
+        }
+    }
+
+    /// Abandons the current worker and its backlog for a fresh one. The old
+    /// thread can't be killed; if it ever returns from `simulate` it exits
+    /// without replaying anything else. Returns how many events were dropped.
+    pub fn restart(&self) -> u64 {
+        let mut worker = self.worker();
+        let dropped = self.queue_depth();
+        worker.generation += 1;
+        self.state.generation.store(worker.generation, Ordering::Relaxed);
+        // Count the backlog as done now, so the depth starts from zero and
+        // the abandoned worker must not count it again.
+        self.state.done.fetch_add(dropped, Ordering::Relaxed);
+        self.state.failed.fetch_add(dropped, Ordering::Relaxed);
+        self.state
+            .last_progress
+            .store(monotonic_micros(), Ordering::Relaxed);
+        worker.sender = spawn_worker(
+            self.name,
+            Arc::clone(&self.simulate),
+            Arc::clone(&self.state),
+            worker.generation,
+        );
+        dropped
+    }
+
+    fn worker(&self) -> MutexGuard<'_, Worker> {
+        self.worker
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for EventSimulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which simulator of a [`SimulatorPool`] replays an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lane {
+    /// Keys, and anything that isn't pointer input.
+    Keyboard,
+    /// Mouse buttons, the wheel and absolute pointer positions, whether
+    /// placed by the server or sent as rdev `MouseMove`s.
+    Pointer,
+}
+
+impl Lane {
+    pub const ALL: [Lane; 2] = [Lane::Keyboard, Lane::Pointer];
 
-        Self { sender }
+    pub fn for_event(event_type: &EventType) -> Lane {
+        match event_type {
+            EventType::ButtonPress(..)
+            | EventType::ButtonRelease(..)
+            | EventType::Wheel { .. }
+            | EventType::MouseMove { .. } => Lane::Pointer,
+            _other => Lane::Keyboard,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lane::Keyboard => "keyboard",
+            Lane::Pointer => "pointer",
+        }
+    }
+}
+
+/// How many simulators a [`SimulatorPool`]'s lanes get.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LaneLayout {
+    /// One per lane, so a slow key never holds up a click or the other way
+    /// round.
+    #[default]
+    Split,
+    /// One for both, replaying keys and buttons in the order they were
+    /// decoded, e.g. Shift always before the click it modifies.
+    Shared,
+}
+
+/// The simulators live input is replayed through, by [`Lane`].
+pub struct SimulatorPool {
+    simulators: Vec<EventSimulator>,
+    /// Index into `simulators` per lane, in [`Lane::ALL`] order.
+    lanes: [usize; 2],
+}
+
+impl SimulatorPool {
+    /// Simulators replaying through `rdev`, named after their lanes.
+    pub fn new(layout: LaneLayout) -> Self {
+        Self::with_simulate(layout, Arc::new(simulate))
     }
 
-    pub fn enqueue(&self, event: EventType) {
-        if let Err(error) = self.sender.send(event) {
-            eprintln!("[server] failed to enqueue event for simulation: {error}");
+    /// Simulators replaying through `simulate`.
+    pub fn with_simulate(layout: LaneLayout, simulate: SimulateFn) -> Self {
+        match layout {
+            LaneLayout::Split => Self {
+                simulators: Lane::ALL
+                    .iter()
+                    .map(|lane| EventSimulator::with_simulate(lane.name(), Arc::clone(&simulate)))
+                    .collect(),
+                lanes: [0, 1],
+            },
+            LaneLayout::Shared => Self {
+                simulators: vec![EventSimulator::with_simulate("input", simulate)],
+                lanes: [0, 0],
+            },
         }
     }
+
+    pub fn lane(&self, lane: Lane) -> &EventSimulator {
+        let index = match lane {
+            Lane::Keyboard => self.lanes[0],
+            Lane::Pointer => self.lanes[1],
+        };
+        &self.simulators[index]
+    }
+
+    /// The simulator that replays `event_type`.
+    pub fn for_event(&self, event_type: &EventType) -> &EventSimulator {
+        self.lane(Lane::for_event(event_type))
+    }
+
+    /// Whether both lanes go through the one simulator.
+    pub fn is_shared(&self) -> bool {
+        self.simulators.len() == 1
+    }
+
+    pub fn simulators(&self) -> &[EventSimulator] {
+        &self.simulators
+    }
+}
+
+fn spawn_worker(
+    name: &'static str,
+    simulate: SimulateFn,
+    state: Arc<WorkerState>,
+    generation: u64,
+) -> Sender<EventType> {
+    let (sender, receiver) = mpsc::channel::<EventType>();
+    thread::Builder::new()
+        .name(format!("{name}-simulator"))
+        .spawn(move || run_worker(receiver, simulate, state, generation))
+        .expect("failed to spawn event simulator thread");
+    sender
+}
+
+fn run_worker(
+    receiver: Receiver<EventType>,
+    simulate: SimulateFn,
+    state: Arc<WorkerState>,
+    generation: u64,
+) {
+    for event in receiver {
+        if state.generation.load(Ordering::Relaxed) != generation {
+            return;
+        }
+        let result = simulate(&event);
+        if state.generation.load(Ordering::Relaxed) != generation {
+            return;
+        }
+        if let Err(error) = result {
+            state.failed.fetch_add(1, Ordering::Relaxed);
+            eprintln!("[server] failed to simulate event: {error:?}");
+        }
+        state.done.fetch_add(1, Ordering::Relaxed);
+        state
+            .last_progress
+            .store(monotonic_micros(), Ordering::Relaxed);
+    }
+}
+
+/// What the watchdog does about a stalled simulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchdogOptions {
+    /// How long queued events may go without one being simulated.
+    pub stall_after: Duration,
+    /// Replace a stalled worker instead of only logging it.
+    pub restart: bool,
+}
+
+impl Default for WatchdogOptions {
+    fn default() -> Self {
+        Self {
+            stall_after: Duration::from_secs(5),
+            restart: true,
+        }
+    }
+}
+
+/// Checks each simulator every [`WATCHDOG_INTERVAL`], logging once per stall
+/// and restarting the worker if `options.restart` is set. Runs until the
+/// process exits.
+pub fn spawn_watchdog(pool: Arc<SimulatorPool>, options: WatchdogOptions) {
+    thread::Builder::new()
+        .name("simulator-watchdog".into())
+        .spawn(move || {
+            let mut reported = vec![false; pool.simulators().len()];
+            loop {
+                thread::sleep(WATCHDOG_INTERVAL);
+                for (simulator, reported) in pool.simulators().iter().zip(reported.iter_mut()) {
+                    *reported = watch(simulator, options, *reported);
+                }
+            }
+        })
+        .expect("failed to spawn simulator watchdog thread");
+}
+
+/// One watchdog check of `simulator`; returns whether a stall is (still)
+/// being reported.
+fn watch(simulator: &EventSimulator, options: WatchdogOptions, reported: bool) -> bool {
+    let SimulatorHealth::Stalled { depth, for_micros } =
+        simulator.health(monotonic_micros(), options.stall_after)
+    else {
+        if reported {
+            println!("[server] {} simulator is draining again", simulator.name());
+        }
+        return false;
+    };
+    let stalled_secs = Duration::from_micros(for_micros).as_secs_f64();
+    if options.restart {
+        let dropped = simulator.restart();
+        eprintln!(
+            "[server] {} simulator stalled for {stalled_secs:.1}s with {depth} events queued; \
+             restarted it and dropped {dropped} events",
+            simulator.name()
+        );
+        return false;
+    }
+    if !reported {
+        eprintln!(
+            "[server] {} simulator stalled for {stalled_secs:.1}s with {depth} events queued; \
+             injection through it is stuck",
+            simulator.name()
+        );
+    }
+    true
 }
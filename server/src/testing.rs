@@ -0,0 +1,93 @@
+//! Drives the uni stream decode and dispatch path directly, so routing can be
+//! tested without QUIC, a client or uinput. Enabled by the `testing` feature.
+
+use std::{thread, time::Duration};
+
+use rdev::EventType;
+use shared::{CharInput, MouseMove, SourceId, Sourced, raw_keys::RawKeyInput};
+
+use crate::{
+    framing::Frame,
+    inject::Injector,
+    server::{StreamOptions, dispatch_chunks},
+    simulator::Lane,
+};
+
+/// One value as a client would put it on a uni stream.
+#[derive(Debug, Clone)]
+pub enum InputMessage {
+    Mouse(MouseMove),
+    Event(EventType),
+    Char(char),
+    Tagged(SourceId, EventType),
+    /// Bytes sent as-is, e.g. garbage or a value split across chunks.
+    Raw(Vec<u8>),
+    /// Nothing sent for this long, for what depends on time between inputs.
+    Wait(Duration),
+}
+
+impl InputMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let encoded = match self {
+            InputMessage::Mouse(mouse_move) => rmp_serde::to_vec(mouse_move),
+            InputMessage::Event(event_type) => rmp_serde::to_vec(event_type),
+            InputMessage::Char(ch) => rmp_serde::to_vec(&CharInput(*ch)),
+            InputMessage::Tagged(source_id, event_type) => rmp_serde::to_vec(&Sourced {
+                source_id: *source_id,
+                input: *event_type,
+            }),
+            InputMessage::Raw(bytes) => return bytes.clone(),
+            InputMessage::Wait(_) => return Vec::new(),
+        };
+        encoded.expect("failed to serialise test input")
+    }
+}
+
+/// What the server would have handed to the OS, and through which path.
+#[derive(Debug, PartialEq)]
+pub enum Simulated {
+    /// Relative motion, sent to the virtual mouse (or the mouse simulator).
+    Pointer(MouseMove),
+    /// Replayed by the keyboard simulator.
+    Keyboard(EventType),
+    /// Replayed by the mouse simulator.
+    Mouse(EventType),
+    /// Emitted on the virtual device by its code.
+    RawKey(RawKeyInput),
+}
+
+/// Feeds `messages` through a uni stream's decode and dispatch path, one
+/// chunk each, then ends the stream. Returns everything that would have
+/// been simulated, including the releases sent when the stream ends.
+pub fn simulate(messages: &[InputMessage], stream_options: StreamOptions) -> Vec<Simulated> {
+    let chunks = messages.iter().map(|message| {
+        if let InputMessage::Wait(pause) = message {
+            thread::sleep(*pause);
+        }
+        message.encode()
+    });
+    let (injector, log) = Injector::capture();
+    dispatch_chunks(chunks, stream_options, injector);
+
+    log.try_iter()
+        .filter_map(|frame| match frame {
+            Frame::Mouse(mouse_move) => Some(Simulated::Pointer(mouse_move)),
+            Frame::Event(event_type) if Lane::for_event(&event_type) == Lane::Keyboard => {
+                Some(Simulated::Keyboard(event_type))
+            }
+            Frame::Event(event_type) => Some(Simulated::Mouse(event_type)),
+            Frame::RawKey(input) => Some(Simulated::RawKey(input)),
+            Frame::Char(_)
+            | Frame::Edge(_)
+            | Frame::SentAt(_)
+            | Frame::Seq(_)
+            | Frame::Gesture(_)
+            | Frame::ExtraKey(_)
+            | Frame::Absolute(_)
+            | Frame::Combo(_)
+            | Frame::DoubleClick(_)
+            | Frame::KeepHeld
+            | Frame::Unknown(_) => None,
+        })
+        .collect()
+}
@@ -0,0 +1,126 @@
+//! QUIC transport limits the server asks clients to respect. The defaults
+//! are quinn's own, which is what the server used before these were
+//! configurable.
+
+use std::{sync::Arc, time::Duration};
+
+use quinn::{IdleTimeout, ServerConfig, TransportConfig, VarInt};
+use shared::congestion::CongestionControl;
+
+/// quinn's default per-stream receive window: 100 ms at 12.5 MB/s.
+pub const DEFAULT_STREAM_RECEIVE_WINDOW: u64 = 1_250_000;
+
+/// quinn's default for both kinds of stream.
+pub const DEFAULT_MAX_CONCURRENT_STREAMS: u32 = 100;
+
+/// quinn's default, as recommended by RFC 9308.
+pub const DEFAULT_MAX_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerTransportOptions {
+    /// Uni streams a client may have open at once. Input arrives on these.
+    pub max_concurrent_uni_streams: u32,
+    /// Bi streams a client may have open at once. Control requests use these.
+    pub max_concurrent_bidi_streams: u32,
+    /// Bytes a client may have in flight on one stream.
+    pub stream_receive_window: u64,
+    /// Bytes a client may have in flight across all its streams; `None`
+    /// leaves only the per-stream limit.
+    pub receive_window: Option<u64>,
+    /// How long a silent connection is kept; `None` keeps it forever.
+    pub max_idle_timeout: Option<Duration>,
+    /// Ping idle clients this often so their connection isn't timed out.
+    pub keep_alive_interval: Option<Duration>,
+    /// Controller for what the server sends: acknowledgements, control
+    /// replies and observer streams.
+    pub congestion_control: CongestionControl,
+}
+
+impl Default for ServerTransportOptions {
+    fn default() -> Self {
+        Self {
+            max_concurrent_uni_streams: DEFAULT_MAX_CONCURRENT_STREAMS,
+            max_concurrent_bidi_streams: DEFAULT_MAX_CONCURRENT_STREAMS,
+            stream_receive_window: DEFAULT_STREAM_RECEIVE_WINDOW,
+            receive_window: None,
+            max_idle_timeout: Some(DEFAULT_MAX_IDLE_TIMEOUT),
+            keep_alive_interval: None,
+            congestion_control: CongestionControl::default(),
+        }
+    }
+}
+
+impl ServerTransportOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_concurrent_uni_streams == 0 {
+            return Err("max_concurrent_uni_streams must be greater than 0".into());
+        }
+        if self.max_concurrent_bidi_streams == 0 {
+            return Err("max_concurrent_bidi_streams must be greater than 0".into());
+        }
+        if self.stream_receive_window == 0 {
+            return Err("stream_receive_window must be greater than 0".into());
+        }
+        if VarInt::from_u64(self.stream_receive_window).is_err() {
+            return Err("stream_receive_window is too large".into());
+        }
+        match self.receive_window {
+            Some(window) if window < self.stream_receive_window => {
+                return Err("receive_window must be at least stream_receive_window".into());
+            }
+            Some(window) if VarInt::from_u64(window).is_err() => {
+                return Err("receive_window is too large".into());
+            }
+            _ => {}
+        }
+        if let Some(idle) = self.max_idle_timeout {
+            if idle.is_zero() {
+                return Err("max_idle_timeout must be greater than 0".into());
+            }
+            if IdleTimeout::try_from(idle).is_err() {
+                return Err("max_idle_timeout is too large".into());
+            }
+        }
+        match (self.keep_alive_interval, self.max_idle_timeout) {
+            (Some(interval), _) if interval.is_zero() => {
+                return Err("keep_alive_interval must be greater than 0".into());
+            }
+            (Some(interval), Some(idle)) if interval >= idle => {
+                return Err("keep_alive_interval must be shorter than max_idle_timeout".into());
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Sets these limits on `transport`. Assumes [`validate`] passed.
+    ///
+    /// [`validate`]: ServerTransportOptions::validate
+    pub fn apply(&self, transport: &mut TransportConfig) {
+        transport
+            .max_concurrent_uni_streams(self.max_concurrent_uni_streams.into())
+            .max_concurrent_bidi_streams(self.max_concurrent_bidi_streams.into())
+            .stream_receive_window(var_int(self.stream_receive_window))
+            .receive_window(self.receive_window.map_or(VarInt::MAX, var_int))
+            .max_idle_timeout(
+                self.max_idle_timeout
+                    .and_then(|idle| IdleTimeout::try_from(idle).ok()),
+            )
+            .keep_alive_interval(self.keep_alive_interval);
+        self.congestion_control.apply(transport);
+    }
+
+    /// Applies these limits to `server_config`, whose transport must not be
+    /// shared yet.
+    pub fn apply_to(&self, server_config: &mut ServerConfig) -> Result<(), String> {
+        self.validate()?;
+        let transport = Arc::get_mut(&mut server_config.transport)
+            .ok_or("transport config is already shared")?;
+        self.apply(transport);
+        Ok(())
+    }
+}
+
+fn var_int(value: u64) -> VarInt {
+    VarInt::from_u64(value).unwrap_or(VarInt::MAX)
+}
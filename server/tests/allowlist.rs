@@ -0,0 +1,99 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use server::{
+    allowlist::{PeerRange, describe, is_allowed},
+    config::QUICInputConfig,
+};
+
+fn range(value: &str) -> PeerRange {
+    value.parse().expect("failed to parse range")
+}
+
+fn ip(value: &str) -> IpAddr {
+    value.parse().expect("failed to parse address")
+}
+
+#[test]
+fn single_addresses_match_only_themselves() {
+    let single = range("192.168.1.20");
+    assert_eq!(single.prefix(), 32);
+    assert!(single.contains(ip("192.168.1.20")));
+    assert!(!single.contains(ip("192.168.1.21")));
+    assert_eq!(single.to_string(), "192.168.1.20");
+}
+
+#[test]
+fn networks_match_every_address_in_them() {
+    let lan = range("192.168.1.0/24");
+    assert!(lan.contains(ip("192.168.1.0")));
+    assert!(lan.contains(ip("192.168.1.255")));
+    assert!(!lan.contains(ip("192.168.2.1")));
+
+    let ula = range("fd00::/8");
+    assert!(ula.contains(ip("fd12:3456::1")));
+    assert!(!ula.contains(ip("fe80::1")));
+    assert!(!ula.contains(ip("192.168.1.1")));
+
+    let anywhere = range("0.0.0.0/0");
+    assert!(anywhere.contains(ip("203.0.113.9")));
+    assert!(!anywhere.contains(ip("::1")));
+}
+
+#[test]
+fn host_bits_are_cleared() {
+    let lan = range("10.1.2.3/16");
+    assert_eq!(lan.network(), IpAddr::V4(Ipv4Addr::new(10, 1, 0, 0)));
+    assert_eq!(lan.to_string(), "10.1.0.0/16");
+}
+
+#[test]
+fn mapped_ipv4_peers_match_ipv4_ranges() {
+    let lan = range("192.168.1.0/24");
+    let mapped = IpAddr::V6(Ipv4Addr::new(192, 168, 1, 7).to_ipv6_mapped());
+    assert!(lan.contains(mapped));
+    assert!(is_allowed(&[range("::1"), lan], mapped));
+    assert!(!is_allowed(&[range("::1")], mapped));
+    assert!(is_allowed(&[range("::1")], IpAddr::V6(Ipv6Addr::LOCALHOST)));
+}
+
+#[test]
+fn malformed_ranges_are_refused() {
+    for value in [
+        "",
+        "lan",
+        "10.0.0.0/33",
+        "::/129",
+        "10.0.0.0/",
+        "10.0.0.0/x",
+    ] {
+        assert!(value.parse::<PeerRange>().is_err(), "{value:?} parsed");
+    }
+}
+
+#[test]
+fn the_allowlist_is_described_for_the_log() {
+    assert_eq!(
+        describe(&[range("10.0.0.0/8"), range("::1")]),
+        "10.0.0.0/8, ::1"
+    );
+    assert_eq!(describe(&[]), "nowhere");
+}
+
+#[test]
+fn config_takes_addresses_and_networks() {
+    let config: QUICInputConfig = toml::from_str(
+        r#"
+        allowed_peers = ["192.168.1.20", "10.0.0.0/8"]
+        log_refused_peers = false
+        "#,
+    )
+    .expect("failed to parse config");
+    assert_eq!(
+        config.allowed_peers,
+        vec![range("192.168.1.20"), range("10.0.0.0/8")]
+    );
+    assert!(!config.log_refused_peers);
+
+    assert!(toml::from_str::<QUICInputConfig>(r#"allowed_peers = ["10.0.0.0/40"]"#).is_err());
+    assert!(QUICInputConfig::default().log_refused_peers);
+}
@@ -0,0 +1,135 @@
+//! The on-disk audit log: its line format, key redaction and rotation.
+
+use std::{
+    fs,
+    net::SocketAddr,
+    path::PathBuf,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use rdev::{Button, EventType, Key};
+use server::{
+    audit::{KEPT_FILES, REDACTED, RotatingFile, audit_line, rotated_path},
+    framing::Frame,
+};
+use shared::{Edge, MouseMove};
+
+fn peer() -> SocketAddr {
+    "192.168.1.20:51234".parse().unwrap()
+}
+
+fn line(frame: Frame, redact_keys: bool) -> Option<String> {
+    let at = UNIX_EPOCH + Duration::from_millis(1_760_000_000_125);
+    audit_line(at, peer(), &frame, redact_keys)
+}
+
+/// A fresh directory for one test's log files.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("quicinput-audit-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("failed to create scratch dir");
+    dir
+}
+
+#[test]
+fn lines_carry_time_peer_and_script_form() {
+    assert_eq!(
+        line(Frame::Event(EventType::KeyPress(Key::KeyA)), false).as_deref(),
+        Some("1760000000.125 192.168.1.20:51234 key press KeyA")
+    );
+    assert_eq!(
+        line(Frame::Mouse(MouseMove { dx: 2.0, dy: -1.5 }), false).as_deref(),
+        Some("1760000000.125 192.168.1.20:51234 move 2 -1.5")
+    );
+    assert_eq!(line(Frame::Edge(Edge::Left), false), None);
+    assert_eq!(line(Frame::SentAt(7), false), None);
+}
+
+#[test]
+fn redaction_hides_keys_and_characters_only() {
+    let redacted = format!("1760000000.125 192.168.1.20:51234 {REDACTED}");
+    for frame in [
+        Frame::Event(EventType::KeyPress(Key::KeyP)),
+        Frame::Event(EventType::KeyRelease(Key::KeyP)),
+        Frame::Char('p'),
+    ] {
+        assert_eq!(line(frame, true).as_deref(), Some(redacted.as_str()));
+    }
+
+    assert_eq!(
+        line(Frame::Event(EventType::ButtonPress(Button::Left)), true).as_deref(),
+        Some("1760000000.125 192.168.1.20:51234 button press Left")
+    );
+    assert_eq!(
+        line(Frame::Char('p'), false).as_deref(),
+        Some("1760000000.125 192.168.1.20:51234 char 'p'")
+    );
+}
+
+#[test]
+fn file_rotates_once_the_next_line_would_pass_the_limit() {
+    let dir = scratch_dir("rotate");
+    let path = dir.join("audit.log");
+    let mut file = RotatingFile::open(&path, 20).expect("failed to open log");
+
+    file.write_line("first line").unwrap();
+    file.write_line("second").unwrap();
+    assert!(!rotated_path(&path, 1).exists());
+
+    file.write_line("third line").unwrap();
+    assert_eq!(
+        fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+        "first line\nsecond\n"
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "third line\n");
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn only_the_newest_rotations_are_kept() {
+    let dir = scratch_dir("keep");
+    let path = dir.join("audit.log");
+    let mut file = RotatingFile::open(&path, 1).expect("failed to open log");
+
+    for index in 0..KEPT_FILES + 2 {
+        file.write_line(&format!("line {index}")).unwrap();
+    }
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        format!("line {}\n", KEPT_FILES + 1)
+    );
+    assert_eq!(
+        fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+        format!("line {KEPT_FILES}\n")
+    );
+    assert_eq!(
+        fs::read_to_string(rotated_path(&path, KEPT_FILES)).unwrap(),
+        "line 1\n"
+    );
+    assert!(!rotated_path(&path, KEPT_FILES + 1).exists());
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn reopening_appends_to_what_is_there() {
+    let dir = scratch_dir("reopen");
+    let path = dir.join("audit.log");
+    RotatingFile::open(&path, 1024)
+        .unwrap()
+        .write_line("before restart")
+        .unwrap();
+    RotatingFile::open(&path, 1024)
+        .unwrap()
+        .write_line("after restart")
+        .unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "before restart\nafter restart\n"
+    );
+
+    fs::remove_dir_all(dir).ok();
+}
@@ -0,0 +1,122 @@
+//! Telling clients when the server's machine can't take input.
+
+mod common;
+
+use std::{
+    sync::{
+        Arc,
+        mpsc::{self, Receiver},
+    },
+    time::Duration,
+};
+
+use client::{
+    availability::{PauseChange, RemotePause, watch_availability},
+    quic::{ClientSession, quic_runtime},
+};
+use server::availability::{Availability, FakeAvailability, parse_session_hints};
+use shared::{ControlResponse, UnavailableReason};
+
+use common::{TestServer, WAIT, disconnect};
+
+const LOCKED: Availability = Availability::Unavailable(UnavailableReason::ScreenLocked);
+
+fn watch(session: &ClientSession) -> Receiver<ControlResponse> {
+    let (report_tx, report_rx) = mpsc::channel();
+    quic_runtime().spawn(watch_availability(
+        session.connection.clone(),
+        move |response| {
+            let _ = report_tx.send(response);
+        },
+    ));
+    report_rx
+}
+
+#[test]
+fn lock_and_unlock_reach_the_client_and_pause_it() {
+    let source = Arc::new(FakeAvailability::default());
+    let server = TestServer::start_with(|options| {
+        let mut options = options.with_availability(source.clone());
+        options.availability_poll = Duration::from_millis(20);
+        options
+    });
+    let session = server.connect();
+    assert!(session.reports_availability);
+
+    let reports = watch(&session);
+    let pause = RemotePause::default();
+    assert_eq!(
+        reports.recv_timeout(WAIT),
+        Ok(ControlResponse::RemoteAvailable)
+    );
+
+    source.set(LOCKED);
+    let report = reports.recv_timeout(WAIT).expect("no report of the lock");
+    assert_eq!(
+        report,
+        ControlResponse::RemoteUnavailable(UnavailableReason::ScreenLocked)
+    );
+    assert_eq!(
+        pause.on_response(&report),
+        Some(PauseChange::Pause(UnavailableReason::ScreenLocked))
+    );
+    assert!(pause.is_paused());
+
+    source.set(Availability::Available);
+    let report = reports.recv_timeout(WAIT).expect("no report of the unlock");
+    assert_eq!(report, ControlResponse::RemoteAvailable);
+    assert_eq!(pause.on_response(&report), Some(PauseChange::Resume));
+    assert!(!pause.is_paused());
+
+    disconnect(session);
+}
+
+#[test]
+fn server_without_a_source_reports_nothing() {
+    let server = TestServer::start();
+    let session = server.connect();
+    assert!(!session.reports_availability);
+
+    // Asked anyway, the server finishes the stream without a report.
+    let reports = watch(&session);
+    assert_eq!(
+        reports.recv_timeout(WAIT),
+        Err(mpsc::RecvTimeoutError::Disconnected)
+    );
+
+    disconnect(session);
+}
+
+#[test]
+fn logind_hints_are_read() {
+    assert_eq!(
+        parse_session_hints("LockedHint=no\nIdleHint=no\n"),
+        Some(Availability::Available)
+    );
+    assert_eq!(
+        parse_session_hints("LockedHint=yes\nIdleHint=yes\n"),
+        Some(LOCKED)
+    );
+    assert_eq!(parse_session_hints(""), None);
+}
+
+#[test]
+fn an_idle_session_keeps_taking_input() {
+    assert_eq!(
+        parse_session_hints("LockedHint=no\nIdleHint=yes\n"),
+        Some(Availability::Available)
+    );
+    assert_eq!(parse_session_hints("IdleHint=yes\n"), None);
+}
+
+#[test]
+fn availability_is_sent_as_the_matching_response() {
+    assert_eq!(
+        Availability::Available.response(),
+        ControlResponse::RemoteAvailable
+    );
+    assert_eq!(
+        LOCKED.response(),
+        ControlResponse::RemoteUnavailable(UnavailableReason::ScreenLocked)
+    );
+}
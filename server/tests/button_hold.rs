@@ -0,0 +1,140 @@
+//! Buttons whose release never arrived are let go before they drag along.
+
+use std::time::Duration;
+
+use rdev::{Button, EventType, Key};
+use server::{
+    server::{ServerOptions, StreamOptions},
+    testing::{InputMessage, Simulated, simulate},
+};
+use shared::MouseMove;
+
+const MOVED: MouseMove = MouseMove { dx: 4.0, dy: 0.0 };
+
+fn with_limit(limit: Option<Duration>) -> StreamOptions {
+    StreamOptions {
+        button_hold_limit: limit,
+        ..StreamOptions::default()
+    }
+}
+
+#[test]
+fn a_button_held_past_the_limit_is_released_before_the_next_move() {
+    let simulated = simulate(
+        &[
+            InputMessage::Event(EventType::ButtonPress(Button::Left)),
+            InputMessage::Mouse(MOVED),
+        ],
+        with_limit(Some(Duration::ZERO)),
+    );
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Mouse(EventType::ButtonPress(Button::Left)),
+            Simulated::Mouse(EventType::ButtonRelease(Button::Left)),
+            Simulated::Pointer(MOVED),
+        ]
+    );
+}
+
+#[test]
+fn keys_are_left_held_when_a_button_is_released() {
+    let simulated = simulate(
+        &[
+            InputMessage::Event(EventType::KeyPress(Key::ShiftLeft)),
+            InputMessage::Event(EventType::ButtonPress(Button::Left)),
+            InputMessage::Mouse(MOVED),
+        ],
+        with_limit(Some(Duration::ZERO)),
+    );
+    assert_eq!(
+        simulated[2..],
+        [
+            Simulated::Mouse(EventType::ButtonRelease(Button::Left)),
+            Simulated::Pointer(MOVED),
+            // Only once the stream ends.
+            Simulated::Keyboard(EventType::KeyRelease(Key::ShiftLeft)),
+        ]
+    );
+}
+
+#[test]
+fn a_drag_within_the_limit_keeps_its_button() {
+    for limit in [None, Some(Duration::from_secs(60))] {
+        let simulated = simulate(
+            &[
+                InputMessage::Event(EventType::ButtonPress(Button::Left)),
+                InputMessage::Mouse(MOVED),
+                InputMessage::Mouse(MOVED),
+                InputMessage::Event(EventType::ButtonRelease(Button::Left)),
+            ],
+            with_limit(limit),
+        );
+        assert_eq!(
+            simulated,
+            vec![
+                Simulated::Mouse(EventType::ButtonPress(Button::Left)),
+                Simulated::Pointer(MOVED),
+                Simulated::Pointer(MOVED),
+                Simulated::Mouse(EventType::ButtonRelease(Button::Left)),
+            ],
+            "{limit:?}"
+        );
+    }
+}
+
+#[test]
+fn typing_while_a_button_is_held_keeps_it() {
+    // Each wait is under the limit, the two together over it: only the
+    // characters in between keep the button down.
+    let limit = Some(Duration::from_millis(300));
+    let wait = InputMessage::Wait(Duration::from_millis(200));
+    let typed = simulate(
+        &[
+            InputMessage::Event(EventType::ButtonPress(Button::Left)),
+            wait.clone(),
+            InputMessage::Char('a'),
+            wait.clone(),
+            InputMessage::Mouse(MOVED),
+        ],
+        with_limit(limit),
+    );
+    assert_eq!(
+        typed[2..],
+        [
+            Simulated::Keyboard(EventType::KeyRelease(Key::KeyA)),
+            Simulated::Pointer(MOVED),
+            // Only once the stream ends.
+            Simulated::Mouse(EventType::ButtonRelease(Button::Left)),
+        ]
+    );
+
+    let silent = simulate(
+        &[
+            InputMessage::Event(EventType::ButtonPress(Button::Left)),
+            wait.clone(),
+            wait,
+            InputMessage::Mouse(MOVED),
+        ],
+        with_limit(limit),
+    );
+    assert_eq!(
+        silent,
+        vec![
+            Simulated::Mouse(EventType::ButtonPress(Button::Left)),
+            Simulated::Mouse(EventType::ButtonRelease(Button::Left)),
+            Simulated::Pointer(MOVED),
+        ]
+    );
+}
+
+#[test]
+fn the_limit_is_set_through_the_builder() {
+    let (injector, _log) = server::inject::Injector::capture();
+    let options = ServerOptions::new(injector).with_button_hold_limit(Some(Duration::from_secs(5)));
+    assert_eq!(
+        options.stream_options.button_hold_limit,
+        Some(Duration::from_secs(5))
+    );
+    assert_eq!(StreamOptions::default().button_hold_limit, None);
+}
@@ -0,0 +1,124 @@
+//! Large payloads sent in pieces by the client and reassembled by the
+//! server, over an in-memory pipe standing in for a bi stream and over a
+//! real connection.
+
+mod common;
+
+use client::quic::{DEFAULT_CHUNK_SIZE, control_request, quic_runtime, send_stream_chunked};
+use server::chunked::{ChunkedReadError, read_chunked};
+use shared::{ControlRequest, ControlResponse};
+use tokio::io::{AsyncWriteExt, duplex};
+
+use common::{TestServer, disconnect};
+
+fn payload(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+fn assert_increasing(progress: &[u64], total: u64) {
+    assert!(!progress.is_empty());
+    assert!(
+        progress.windows(2).all(|pair| pair[0] < pair[1]),
+        "{progress:?}"
+    );
+    assert_eq!(progress.last(), Some(&total));
+}
+
+#[test]
+fn multi_megabyte_payload_round_trips_with_progress() {
+    let sent = payload(5 * 1024 * 1024 + 17);
+    let (mut writer, reader) = duplex(16 * 1024);
+    let mut sent_progress = Vec::new();
+    let mut received_progress = Vec::new();
+
+    let (total, received) = quic_runtime().block_on(async {
+        let send = async {
+            let total =
+                send_stream_chunked(&mut writer, sent.as_slice(), DEFAULT_CHUNK_SIZE, |bytes| {
+                    sent_progress.push(bytes)
+                })
+                .await
+                .unwrap();
+            writer.shutdown().await.unwrap();
+            total
+        };
+        let receive = read_chunked(reader, 8 * 1024 * 1024, |bytes| {
+            received_progress.push(bytes)
+        });
+        tokio::join!(send, receive)
+    });
+
+    assert_eq!(total, sent.len() as u64);
+    assert_eq!(received.unwrap(), sent);
+    assert_increasing(&sent_progress, total);
+    assert_increasing(&received_progress, total);
+    // Pieces, not one write.
+    assert!(sent_progress.len() >= sent.len() / DEFAULT_CHUNK_SIZE);
+}
+
+#[test]
+fn chunks_are_no_bigger_than_asked() {
+    let sent = payload(10_000);
+    let mut progress = Vec::new();
+
+    let mut written = Vec::new();
+    quic_runtime()
+        .block_on(send_stream_chunked(
+            &mut written,
+            sent.as_slice(),
+            1000,
+            |bytes| progress.push(bytes),
+        ))
+        .unwrap();
+
+    assert_eq!(written, sent);
+    assert_eq!(progress, (1..=10).map(|n| n * 1000).collect::<Vec<u64>>());
+}
+
+#[test]
+fn empty_payload_sends_nothing() {
+    let mut progress = Vec::new();
+    let mut written = Vec::new();
+
+    let total = quic_runtime()
+        .block_on(send_stream_chunked(
+            &mut written,
+            &[][..],
+            DEFAULT_CHUNK_SIZE,
+            |bytes| progress.push(bytes),
+        ))
+        .unwrap();
+
+    assert_eq!(total, 0);
+    assert!(written.is_empty());
+    assert!(progress.is_empty());
+}
+
+#[test]
+fn payload_over_the_limit_is_refused() {
+    let sent = payload(100_000);
+    let result = quic_runtime().block_on(read_chunked(sent.as_slice(), 50_000, |_| {}));
+
+    assert!(matches!(
+        result,
+        Err(ChunkedReadError::TooLarge { limit: 50_000 })
+    ));
+}
+
+#[test]
+fn a_control_request_past_the_input_limit_is_answered() {
+    let server = TestServer::start();
+    let session = server.connect();
+
+    // Ids this large take 9 bytes each, so about 180 KiB in all. None of
+    // them was ever opened, so the drain gives up on them.
+    let request = ControlRequest::Drain {
+        streams: (0..20_000).map(|i| u64::MAX - i).collect(),
+    };
+    let response = quic_runtime()
+        .block_on(control_request(&session.connection, &request))
+        .expect("request failed");
+    assert_eq!(response, ControlResponse::Drained { complete: false });
+
+    disconnect(session);
+}
@@ -0,0 +1,49 @@
+use std::time::{Duration, Instant};
+
+use rdev::Button;
+use server::clicks::{ClickTimer, DEFAULT_DOUBLE_CLICK_WINDOW};
+
+const WINDOW: Duration = DEFAULT_DOUBLE_CLICK_WINDOW;
+
+#[test]
+fn spread_out_double_click_gets_a_click_replayed_within_the_window() {
+    let mut timer = ClickTimer::default();
+    let first = Instant::now();
+    // A resent packet holds the second click up past the window.
+    let second = first + Duration::from_secs(1);
+
+    assert!(!timer.press(Button::Left, false, first, WINDOW));
+    assert!(timer.press(Button::Left, true, second, WINDOW));
+
+    // The replayed click and the press go in back to back at `second`, so
+    // the desktop sees two presses well inside its double-click time, and
+    // a third click soon after still counts as following them.
+    assert!(!timer.press(Button::Left, false, second + WINDOW, WINDOW));
+}
+
+#[test]
+fn double_click_in_time_is_left_alone() {
+    let mut timer = ClickTimer::default();
+    let first = Instant::now();
+
+    timer.press(Button::Left, false, first, WINDOW);
+    assert!(!timer.press(Button::Left, true, first + WINDOW / 2, WINDOW));
+}
+
+#[test]
+fn unhinted_clicks_are_never_replayed() {
+    let mut timer = ClickTimer::default();
+    let first = Instant::now();
+
+    timer.press(Button::Left, false, first, WINDOW);
+    assert!(!timer.press(Button::Left, false, first + Duration::from_secs(5), WINDOW));
+}
+
+#[test]
+fn previous_press_of_another_button_does_not_count() {
+    let mut timer = ClickTimer::default();
+    let first = Instant::now();
+
+    timer.press(Button::Right, false, first, WINDOW);
+    assert!(timer.press(Button::Left, true, first, WINDOW));
+}
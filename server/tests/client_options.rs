@@ -0,0 +1,139 @@
+//! Connection settings carried by the client's `ClientOptions`, against a
+//! real server on loopback.
+
+mod common;
+
+use std::{
+    fs,
+    net::{Ipv4Addr, UdpSocket},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use client::quic::{
+    CertPin, ClientOptions, ConnectError, RetryPolicy, install_crypto_provider, quic_runtime,
+    run_client,
+};
+use server::{
+    displays::FakeDisplays,
+    server::{CertificatePaths, ServerOptions},
+};
+use shared::congestion::CongestionControl;
+
+use common::{TestServer, disconnect, free_loopback_addr};
+
+/// A server presenting a freshly generated certificate, with `configure`
+/// applied to its options, and that certificate's DER bytes.
+fn server_with_certificate(
+    name: &str,
+    configure: impl FnOnce(ServerOptions) -> ServerOptions,
+) -> (TestServer, Vec<u8>) {
+    let dir = std::env::temp_dir().join(format!("quicinput-client-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("failed to create scratch dir");
+    let generated = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+    let paths = CertificatePaths {
+        cert: dir.join("cert.pem"),
+        key: dir.join("key.pem"),
+    };
+    fs::write(&paths.cert, generated.cert.pem()).unwrap();
+    fs::write(&paths.key, generated.signing_key.serialize_pem()).unwrap();
+
+    let server = TestServer::start_with(|options| {
+        configure(
+            options
+                .with_certificate(paths)
+                .with_displays(Arc::new(FakeDisplays::default())),
+        )
+    });
+    (server, generated.cert.der().to_vec())
+}
+
+#[test]
+fn a_matching_pin_connects() {
+    let (server, cert) = server_with_certificate("pin-ok", |options| options);
+
+    let pin = CertPin::of(&cert).expect("failed to hash certificate");
+    let session = server
+        .try_connect_with(|options| options.with_cert_pin(pin))
+        .expect("pinned client failed to connect");
+    disconnect(session);
+}
+
+#[test]
+fn a_different_certificate_is_refused_when_pinned() {
+    let (server, _cert) = server_with_certificate("pin-bad", |options| options);
+
+    let result = server.try_connect_with(|options| options.with_cert_pin(CertPin([0; 32])));
+    match result {
+        Err(ConnectError::CertificateMismatch { presented }) => {
+            assert_ne!(presented, CertPin([0; 32]));
+        }
+        Err(err) => panic!("expected a certificate mismatch, got {err}"),
+        Ok(_) => panic!("connected despite a mismatched pin"),
+    }
+}
+
+#[test]
+fn the_local_port_is_bound_as_asked() {
+    let (server, _cert) = server_with_certificate("port", |options| options);
+
+    let local_port = free_loopback_addr().port();
+    let session = server.connect_with(|options| options.with_local_port(local_port));
+    assert_eq!(
+        session
+            .endpoint
+            .local_addr()
+            .expect("no local address")
+            .port(),
+        local_port
+    );
+    disconnect(session);
+}
+
+#[test]
+fn any_congestion_controller_pairing_connects() {
+    for (server_control, client_control) in [
+        (CongestionControl::Cubic, CongestionControl::Bbr),
+        (CongestionControl::Bbr, CongestionControl::NewReno),
+    ] {
+        let (server, _cert) = server_with_certificate("congestion", |mut options| {
+            options.transport.congestion_control = server_control;
+            options
+        });
+
+        let session = server
+            .try_connect_with(|options| options.with_congestion_control(client_control))
+            .unwrap_or_else(|err| {
+                panic!("{client_control} client failed to reach {server_control} server: {err}")
+            });
+        disconnect(session);
+    }
+}
+
+#[test]
+fn the_connect_timeout_bounds_an_unanswered_attempt() {
+    install_crypto_provider().expect("no crypto provider");
+    let runtime = quic_runtime();
+    // Swallows the handshake without ever answering.
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("failed to bind silent peer");
+    let addr = silent.local_addr().expect("silent peer has no address");
+
+    let started = Instant::now();
+    let result = runtime.block_on(run_client(
+        ClientOptions::new(addr)
+            .with_connect_timeout(Duration::from_millis(300))
+            .with_retry(RetryPolicy {
+                attempts: 1,
+                backoff: Duration::ZERO,
+            }),
+        None,
+        false,
+    ));
+    let err = result.err().expect("connected to a silent peer");
+    assert!(
+        matches!(err, ConnectError::TimedOut(after) if after == Duration::from_millis(300)),
+        "{err}"
+    );
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
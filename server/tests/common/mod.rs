@@ -0,0 +1,118 @@
+//! What the loopback tests share: a dry-run server on a free local port,
+//! recording what it would inject, and clients connected to it. Each test
+//! file uses only some of it.
+
+#![allow(dead_code)]
+
+use std::{
+    error::Error,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::mpsc::Receiver,
+    time::Duration,
+};
+
+use client::quic::{
+    ClientOptions, ClientSession, ConnectError, close_client, install_crypto_provider,
+    quic_runtime, run_client,
+};
+use server::{
+    framing::Frame,
+    inject::Injector,
+    server::{ServerOptions, run_server},
+};
+use shared::CloseCode;
+use tokio::task::JoinHandle;
+
+/// How long a test waits for anything it expects to happen.
+pub const WAIT: Duration = Duration::from_secs(5);
+
+pub type ServerTask = JoinHandle<Result<(), Box<dyn Error + Send + Sync + 'static>>>;
+
+/// A loopback address nothing is listening on, found by binding port 0.
+pub fn free_loopback_addr() -> SocketAddr {
+    let probe = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("failed to bind probe socket");
+    probe.local_addr().expect("probe socket has no address")
+}
+
+/// A dry-run server on a free loopback port, stopped when dropped.
+pub struct TestServer {
+    pub addr: SocketAddr,
+    pub task: ServerTask,
+    pub injector: Injector,
+    /// Everything the server would have injected, in order.
+    pub log: Receiver<Frame>,
+}
+
+impl TestServer {
+    pub fn start() -> Self {
+        Self::start_with(|options| options)
+    }
+
+    /// Starts a server with `configure` applied to its options, which
+    /// already capture what it injects and bind it to [`Self::addr`].
+    pub fn start_with(configure: impl FnOnce(ServerOptions) -> ServerOptions) -> Self {
+        install_crypto_provider().expect("no crypto provider");
+        let addr = free_loopback_addr();
+        let (injector, log) = Injector::capture();
+        let options = configure(ServerOptions::new(injector.clone()).with_binds(vec![addr]));
+        let task = quic_runtime().spawn(run_server(options));
+        Self {
+            addr,
+            task,
+            injector,
+            log,
+        }
+    }
+
+    /// Connects a client with default options.
+    pub fn connect(&self) -> ClientSession {
+        self.connect_with(|options| options)
+    }
+
+    pub fn connect_with(
+        &self,
+        configure: impl FnOnce(ClientOptions) -> ClientOptions,
+    ) -> ClientSession {
+        self.try_connect_with(configure)
+            .expect("client failed to connect")
+    }
+
+    pub fn try_connect(&self) -> Result<ClientSession, ConnectError> {
+        self.try_connect_with(|options| options)
+    }
+
+    pub fn try_connect_with(
+        &self,
+        configure: impl FnOnce(ClientOptions) -> ClientOptions,
+    ) -> Result<ClientSession, ConnectError> {
+        quic_runtime().block_on(run_client(
+            configure(ClientOptions::new(self.addr)),
+            None,
+            false,
+        ))
+    }
+
+    /// The next thing the server would have injected.
+    pub fn next_frame(&self) -> Frame {
+        self.log
+            .recv_timeout(WAIT)
+            .expect("server did not decode anything in time")
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Closes `session` as a user disconnecting would.
+pub fn disconnect(session: ClientSession) {
+    quic_runtime()
+        .block_on(close_client(
+            session.link(),
+            session.endpoint,
+            CloseCode::UserDisconnect,
+        ))
+        .expect("client failed to close");
+}
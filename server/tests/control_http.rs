@@ -0,0 +1,153 @@
+//! JSON commands over the optional HTTP control endpoint.
+
+use std::{
+    io::{Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpStream},
+    time::Duration,
+};
+
+use rdev::{Button, EventType, Key};
+use server::{
+    control_http::{Action, ControlHttp, HttpCommand, parse_addr, parse_command},
+    framing::Frame,
+    inject::Injector,
+};
+use shared::MouseMove;
+
+const WAIT: Duration = Duration::from_secs(5);
+
+#[test]
+fn move_commands_parse() {
+    assert_eq!(
+        parse_command(br#"{"type":"move","dx":5,"dy":0}"#).unwrap(),
+        HttpCommand::Move { dx: 5.0, dy: 0.0 }
+    );
+}
+
+#[test]
+fn key_and_button_actions_default_to_a_tap() {
+    assert_eq!(
+        parse_command(br#"{"type":"key","key":"KeyA"}"#).unwrap(),
+        HttpCommand::Key {
+            key: Key::KeyA,
+            action: Action::Tap
+        }
+    );
+    assert_eq!(
+        parse_command(br#"{"type":"button","button":"Right","action":"release"}"#).unwrap(),
+        HttpCommand::Button {
+            button: Button::Right,
+            action: Action::Release
+        }
+    );
+}
+
+#[test]
+fn wheel_axes_default_to_zero() {
+    assert_eq!(
+        parse_command(br#"{"type":"wheel","delta_y":-2}"#).unwrap(),
+        HttpCommand::Wheel {
+            delta_x: 0,
+            delta_y: -2
+        }
+    );
+}
+
+#[test]
+fn malformed_commands_are_rejected() {
+    for body in [
+        &br#"{"type":"teleport","x":1}"#[..],
+        br#"{"type":"move","dx":"far"}"#,
+        br#"{"type":"key","key":"NoSuchKey"}"#,
+        br#"{"dx":1,"dy":1}"#,
+        b"not json",
+    ] {
+        assert!(
+            parse_command(body).is_err(),
+            "{}",
+            String::from_utf8_lossy(body)
+        );
+    }
+}
+
+#[test]
+fn a_bare_port_listens_on_loopback() {
+    assert_eq!(
+        parse_addr("8642").unwrap(),
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 8642))
+    );
+    assert_eq!(
+        parse_addr("0.0.0.0:8642").unwrap(),
+        SocketAddr::from((Ipv4Addr::UNSPECIFIED, 8642))
+    );
+    assert!(parse_addr("localhost").is_err());
+}
+
+/// Sends one raw HTTP request and returns the status line of the answer.
+fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> String {
+    let mut stream = TcpStream::connect(addr).expect("failed to connect");
+    stream.set_read_timeout(Some(WAIT)).unwrap();
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response.lines().next().unwrap_or_default().to_string()
+}
+
+#[test]
+fn posted_commands_reach_the_injector() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (injector, log) = Injector::capture();
+    let control = runtime
+        .block_on(ControlHttp::bind((Ipv4Addr::LOCALHOST, 0).into()))
+        .expect("failed to bind");
+    let addr = control.local_addr().unwrap();
+    runtime.spawn(control.run(injector));
+
+    let status = request(addr, "POST", "/input", r#"{"type":"move","dx":5,"dy":0}"#);
+    assert_eq!(status, "HTTP/1.1 204 No Content");
+    assert_eq!(
+        log.recv_timeout(WAIT).unwrap(),
+        Frame::Mouse(MouseMove { dx: 5.0, dy: 0.0 })
+    );
+
+    let status = request(addr, "POST", "/input", r#"{"type":"key","key":"KeyB"}"#);
+    assert_eq!(status, "HTTP/1.1 204 No Content");
+    assert_eq!(
+        log.recv_timeout(WAIT).unwrap(),
+        Frame::Event(EventType::KeyPress(Key::KeyB))
+    );
+    assert_eq!(
+        log.recv_timeout(WAIT).unwrap(),
+        Frame::Event(EventType::KeyRelease(Key::KeyB))
+    );
+}
+
+#[test]
+fn bad_requests_are_refused_without_injecting() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (injector, log) = Injector::capture();
+    let control = runtime
+        .block_on(ControlHttp::bind((Ipv4Addr::LOCALHOST, 0).into()))
+        .expect("failed to bind");
+    let addr = control.local_addr().unwrap();
+    runtime.spawn(control.run(injector));
+
+    assert_eq!(
+        request(addr, "POST", "/input", "{"),
+        "HTTP/1.1 400 Bad Request"
+    );
+    assert_eq!(
+        request(addr, "GET", "/input", ""),
+        "HTTP/1.1 405 Method Not Allowed"
+    );
+    assert_eq!(
+        request(addr, "POST", "/elsewhere", "{}"),
+        "HTTP/1.1 404 Not Found"
+    );
+    assert!(log.try_recv().is_err());
+}
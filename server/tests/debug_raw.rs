@@ -0,0 +1,94 @@
+//! The `--debug-raw` path: payloads no client sends yet are logged and
+//! skipped without disturbing the input around them.
+
+use rdev::{EventType, Key};
+use server::{
+    cli::parse_args,
+    framing::{Frame, FrameDecoder, attempted_decodes},
+    server::StreamOptions,
+    testing::{InputMessage, Simulated, simulate},
+};
+use shared::{
+    MouseMove,
+    codec::{Codec, WireFormat},
+    hex::parse_hex,
+};
+
+/// `{"probe": true}`: well-formed MessagePack that no frame type accepts.
+fn unknown_payload() -> Vec<u8> {
+    parse_hex("81 a5 70 72 6f 62 65 c3").unwrap()
+}
+
+#[test]
+fn debug_raw_is_off_unless_asked_for() {
+    assert!(!parse_args(Vec::<String>::new()).unwrap().debug_raw);
+    assert!(parse_args(["--debug-raw".to_string()]).unwrap().debug_raw);
+}
+
+#[test]
+fn an_unknown_payload_is_skipped_whole() {
+    let key = rmp_serde::to_vec(&EventType::KeyPress(Key::KeyA)).unwrap();
+    let mut decoder = FrameDecoder::new(1024);
+    decoder.push(&unknown_payload());
+    decoder.push(&key);
+
+    assert_eq!(
+        decoder.next_frame(),
+        Some(Frame::Unknown(unknown_payload().len()))
+    );
+    assert_eq!(
+        decoder.next_frame(),
+        Some(Frame::Event(EventType::KeyPress(Key::KeyA)))
+    );
+    assert_eq!(decoder.next_frame(), None);
+    assert_eq!(decoder.pending(), 0);
+}
+
+#[test]
+fn input_after_an_unknown_payload_is_still_applied() {
+    let simulated = simulate(
+        &[
+            InputMessage::Raw(unknown_payload()),
+            InputMessage::Event(EventType::KeyPress(Key::KeyQ)),
+        ],
+        StreamOptions {
+            debug_raw: true,
+            ..StreamOptions::default()
+        },
+    );
+
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(EventType::KeyPress(Key::KeyQ)),
+            Simulated::Keyboard(EventType::KeyRelease(Key::KeyQ)),
+        ]
+    );
+}
+
+#[test]
+fn every_frame_type_is_tried() {
+    let attempts = attempted_decodes(&unknown_payload(), WireFormat::MessagePack);
+    assert_eq!(attempts.len(), 15);
+    assert!(attempts.iter().all(|attempt| !attempt.contains(" bytes)")));
+
+    let mouse = rmp_serde::to_vec(&MouseMove { dx: 1.0, dy: 2.0 }).unwrap();
+    let attempts = attempted_decodes(&mouse, WireFormat::MessagePack);
+    assert!(attempts[0].starts_with("MouseMove: MouseMove { dx: 1.0, dy: 2.0 }"));
+}
+
+#[test]
+fn attempts_read_the_connection_s_format() {
+    let mouse = WireFormat::Json
+        .encode(&MouseMove { dx: 1.0, dy: 2.0 })
+        .unwrap();
+    let attempts = attempted_decodes(&mouse, WireFormat::Json);
+    assert_eq!(
+        attempts[0],
+        format!(
+            "MouseMove: MouseMove {{ dx: 1.0, dy: 2.0 }} ({0} of {0} bytes)",
+            mouse.len()
+        )
+    );
+    assert!(!attempted_decodes(&mouse, WireFormat::MessagePack)[0].contains(" bytes)"));
+}
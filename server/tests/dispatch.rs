@@ -0,0 +1,250 @@
+//! Routing tests that feed input straight into the server's dispatch path.
+
+use rdev::{Button, EventType, Key};
+use server::{
+    server::StreamOptions,
+    testing::{InputMessage, Simulated, simulate},
+};
+use shared::{Edge, EdgeHit, MouseMove, SentAt, clicks::DoubleClick, key_combo::KeyCombo};
+
+#[test]
+fn keys_go_to_the_keyboard_simulator_and_buttons_to_the_mouse_one() {
+    let simulated = simulate(
+        &[
+            InputMessage::Event(EventType::KeyPress(Key::KeyH)),
+            InputMessage::Event(EventType::KeyRelease(Key::KeyH)),
+            InputMessage::Event(EventType::ButtonPress(Button::Right)),
+            InputMessage::Event(EventType::ButtonRelease(Button::Right)),
+            InputMessage::Event(EventType::Wheel {
+                delta_x: 0,
+                delta_y: -1,
+            }),
+        ],
+        StreamOptions::default(),
+    );
+
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(EventType::KeyPress(Key::KeyH)),
+            Simulated::Keyboard(EventType::KeyRelease(Key::KeyH)),
+            Simulated::Mouse(EventType::ButtonPress(Button::Right)),
+            Simulated::Mouse(EventType::ButtonRelease(Button::Right)),
+            Simulated::Mouse(EventType::Wheel {
+                delta_x: 0,
+                delta_y: -1,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn relative_motion_goes_to_the_pointer() {
+    let simulated = simulate(
+        &[InputMessage::Mouse(MouseMove { dx: 3.0, dy: -1.0 })],
+        StreamOptions::default(),
+    );
+    assert_eq!(
+        simulated,
+        vec![Simulated::Pointer(MouseMove { dx: 3.0, dy: -1.0 })]
+    );
+}
+
+#[test]
+fn held_input_is_released_when_the_stream_ends() {
+    let simulated = simulate(
+        &[
+            InputMessage::Event(EventType::KeyPress(Key::ShiftLeft)),
+            InputMessage::Event(EventType::ButtonPress(Button::Left)),
+        ],
+        StreamOptions::default(),
+    );
+
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(EventType::KeyPress(Key::ShiftLeft)),
+            Simulated::Mouse(EventType::ButtonPress(Button::Left)),
+            Simulated::Keyboard(EventType::KeyRelease(Key::ShiftLeft)),
+            Simulated::Mouse(EventType::ButtonRelease(Button::Left)),
+        ]
+    );
+}
+
+#[test]
+fn tagged_input_and_split_values_are_decoded() {
+    let press = InputMessage::Tagged(4, EventType::KeyPress(Key::KeyT)).encode();
+    let (head, tail) = press.split_at(press.len() / 2);
+    let simulated = simulate(
+        &[
+            InputMessage::Raw(head.to_vec()),
+            InputMessage::Raw(tail.to_vec()),
+            InputMessage::Tagged(4, EventType::KeyRelease(Key::KeyT)),
+        ],
+        StreamOptions::default(),
+    );
+
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(EventType::KeyPress(Key::KeyT)),
+            Simulated::Keyboard(EventType::KeyRelease(Key::KeyT)),
+        ]
+    );
+}
+
+#[test]
+fn characters_are_typed_on_the_keyboard_simulator() {
+    let simulated = simulate(&[InputMessage::Char('Q')], StreamOptions::default());
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(EventType::KeyPress(Key::ShiftLeft)),
+            Simulated::Keyboard(EventType::KeyPress(Key::KeyQ)),
+            Simulated::Keyboard(EventType::KeyRelease(Key::KeyQ)),
+            Simulated::Keyboard(EventType::KeyRelease(Key::ShiftLeft)),
+        ]
+    );
+}
+
+#[test]
+fn edge_hits_are_not_injected() {
+    let edge_hit = rmp_serde::to_vec(&EdgeHit(Edge::Right)).expect("failed to serialise");
+    let simulated = simulate(
+        &[
+            InputMessage::Raw(edge_hit),
+            InputMessage::Event(EventType::KeyPress(Key::KeyE)),
+        ],
+        StreamOptions::default(),
+    );
+
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(EventType::KeyPress(Key::KeyE)),
+            Simulated::Keyboard(EventType::KeyRelease(Key::KeyE)),
+        ]
+    );
+}
+
+#[test]
+fn send_stamps_are_not_injected() {
+    let stamp = rmp_serde::to_vec(&SentAt { micros: 42 }).expect("failed to serialise");
+    let simulated = simulate(
+        &[
+            InputMessage::Raw(stamp),
+            InputMessage::Mouse(MouseMove { dx: 1.0, dy: 0.0 }),
+        ],
+        StreamOptions::default(),
+    );
+
+    assert_eq!(
+        simulated,
+        vec![Simulated::Pointer(MouseMove { dx: 1.0, dy: 0.0 })]
+    );
+}
+
+#[test]
+fn relative_and_absolute_moves_take_separate_paths() {
+    let simulated = simulate(
+        &[
+            InputMessage::Mouse(MouseMove { dx: 5.0, dy: -2.0 }),
+            InputMessage::Event(EventType::MouseMove { x: 640.0, y: 360.0 }),
+        ],
+        StreamOptions::default(),
+    );
+
+    // The delta goes to the pointer; the rdev position is placed on the
+    // pointer lane, not replayed by the keyboard simulator.
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Pointer(MouseMove { dx: 5.0, dy: -2.0 }),
+            Simulated::Mouse(EventType::MouseMove { x: 640.0, y: 360.0 }),
+        ]
+    );
+}
+
+#[test]
+fn key_combos_leave_keys_already_held_down() {
+    let simulated = simulate(
+        &[
+            InputMessage::Event(EventType::KeyPress(Key::ControlLeft)),
+            InputMessage::Raw(rmp_serde::to_vec(&KeyCombo::ctrl_alt_del()).unwrap()),
+            InputMessage::Event(EventType::KeyRelease(Key::ControlLeft)),
+        ],
+        StreamOptions::default(),
+    );
+
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(EventType::KeyPress(Key::ControlLeft)),
+            Simulated::Keyboard(EventType::KeyPress(Key::Alt)),
+            Simulated::Keyboard(EventType::KeyPress(Key::Delete)),
+            Simulated::Keyboard(EventType::KeyRelease(Key::Delete)),
+            Simulated::Keyboard(EventType::KeyRelease(Key::Alt)),
+            Simulated::Keyboard(EventType::KeyRelease(Key::ControlLeft)),
+        ]
+    );
+}
+
+fn double_click_hint(button: Button) -> InputMessage {
+    InputMessage::Raw(rmp_serde::to_vec(&DoubleClick { button }).unwrap())
+}
+
+#[test]
+fn double_click_whose_first_click_never_arrived_is_pressed_twice() {
+    let simulated = simulate(
+        &[
+            double_click_hint(Button::Left),
+            InputMessage::Event(EventType::ButtonPress(Button::Left)),
+            InputMessage::Event(EventType::ButtonRelease(Button::Left)),
+        ],
+        StreamOptions::default(),
+    );
+
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Mouse(EventType::ButtonPress(Button::Left)),
+            Simulated::Mouse(EventType::ButtonRelease(Button::Left)),
+            Simulated::Mouse(EventType::ButtonPress(Button::Left)),
+            Simulated::Mouse(EventType::ButtonRelease(Button::Left)),
+        ]
+    );
+}
+
+#[test]
+fn double_click_arriving_in_time_is_replayed_as_sent() {
+    let click = [
+        InputMessage::Event(EventType::ButtonPress(Button::Left)),
+        InputMessage::Event(EventType::ButtonRelease(Button::Left)),
+    ];
+    let mut messages = click.to_vec();
+    messages.push(double_click_hint(Button::Left));
+    messages.extend(click.clone());
+
+    let simulated = simulate(&messages, StreamOptions::default());
+
+    assert_eq!(simulated.len(), 4);
+}
+
+#[test]
+fn double_click_hint_for_another_button_is_ignored() {
+    let simulated = simulate(
+        &[
+            double_click_hint(Button::Right),
+            InputMessage::Event(EventType::ButtonPress(Button::Left)),
+        ],
+        StreamOptions::default(),
+    );
+
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Mouse(EventType::ButtonPress(Button::Left)),
+            Simulated::Mouse(EventType::ButtonRelease(Button::Left)),
+        ]
+    );
+}
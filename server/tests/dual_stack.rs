@@ -0,0 +1,104 @@
+//! Listening on every interface takes IPv4 and IPv6 clients alike.
+
+use std::{
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    sync::Arc,
+};
+
+use client::quic::{
+    ClientOptions, close_client, install_crypto_provider, quic_runtime, run_client,
+};
+use server::{
+    displays::FakeDisplays,
+    inject::Injector,
+    listen::bind_sockets,
+    server::{ServerOptions, run_server},
+};
+use shared::CloseCode;
+
+fn ipv6_loopback_available() -> bool {
+    UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).is_ok()
+}
+
+fn free_port() -> u16 {
+    let probe = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).expect("failed to bind probe socket");
+    probe
+        .local_addr()
+        .expect("probe socket has no address")
+        .port()
+}
+
+#[test]
+fn a_specific_address_is_bound_as_given() {
+    let bound = bind_sockets(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).unwrap();
+    assert_eq!(bound.len(), 1);
+    assert!(!bound[0].dual_stack);
+    assert_eq!(
+        bound[0].socket.local_addr().unwrap().ip(),
+        Ipv4Addr::LOCALHOST
+    );
+}
+
+#[test]
+fn every_interface_covers_both_families_on_one_port() {
+    let bound = bind_sockets(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))).unwrap();
+    let ports: Vec<u16> = bound
+        .iter()
+        .map(|bound| bound.socket.local_addr().unwrap().port())
+        .collect();
+    assert!(ports.iter().all(|port| *port == ports[0]));
+
+    let dual_stack = bound.iter().any(|bound| bound.dual_stack);
+    if dual_stack {
+        assert_eq!(bound.len(), 1);
+        assert!(bound[0].socket.local_addr().unwrap().is_ipv6());
+    } else if ipv6_loopback_available() {
+        assert_eq!(bound.len(), 2);
+    }
+}
+
+#[test]
+fn ipv4_and_ipv6_clients_reach_the_same_any_server() {
+    install_crypto_provider().expect("no crypto provider");
+    let runtime = quic_runtime();
+    let port = free_port();
+
+    let (injector, _log) = Injector::capture();
+    let server = runtime.spawn(run_server(
+        ServerOptions::new(injector)
+            .with_binds(vec![SocketAddr::from((Ipv4Addr::UNSPECIFIED, port))])
+            .with_max_connections(2)
+            .with_displays(Arc::new(FakeDisplays::default())),
+    ));
+
+    let mut targets = vec![SocketAddr::from((Ipv4Addr::LOCALHOST, port))];
+    if ipv6_loopback_available() {
+        targets.push(SocketAddr::from((Ipv6Addr::LOCALHOST, port)));
+    } else {
+        eprintln!("no IPv6 loopback here; only checking IPv4");
+    }
+
+    let mut sessions = Vec::new();
+    for target in targets {
+        let session = runtime
+            .block_on(run_client(ClientOptions::new(target), None, false))
+            .unwrap_or_else(|err| panic!("client for {target} failed to connect: {err}"));
+        assert_eq!(
+            session.connection.remote_address().port(),
+            port,
+            "{target} reached another port"
+        );
+        sessions.push(session);
+    }
+
+    for session in sessions {
+        runtime
+            .block_on(close_client(
+                session.link(),
+                session.endpoint,
+                CloseCode::UserDisconnect,
+            ))
+            .unwrap();
+    }
+    server.abort();
+}
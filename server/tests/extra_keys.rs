@@ -0,0 +1,86 @@
+//! Keys rdev has no name for, sent by name, and how the server replays them.
+
+use rdev::{EventType, Key};
+use server::{
+    framing::{Frame, FrameDecoder},
+    server::StreamOptions,
+    testing::{InputMessage, Simulated, simulate},
+};
+use shared::{
+    MouseMove,
+    extra_keys::{ExtraKey, ExtraKeyInput},
+};
+
+fn extra(key: ExtraKey, pressed: bool) -> InputMessage {
+    InputMessage::Raw(rmp_serde::to_vec(&ExtraKeyInput { key, pressed }).unwrap())
+}
+
+#[test]
+fn extra_keys_decode_apart_from_other_frames() {
+    let mut decoder = FrameDecoder::new(1024);
+    let mute = ExtraKeyInput {
+        key: ExtraKey::Mute,
+        pressed: true,
+    };
+    let moved = MouseMove { dx: 1.0, dy: 2.0 };
+    let press = EventType::KeyPress(Key::KeyA);
+    decoder.push(&rmp_serde::to_vec(&mute).unwrap());
+    decoder.push(&rmp_serde::to_vec(&moved).unwrap());
+    decoder.push(&rmp_serde::to_vec(&press).unwrap());
+
+    assert_eq!(decoder.next_frame(), Some(Frame::ExtraKey(mute)));
+    assert_eq!(decoder.next_frame(), Some(Frame::Mouse(moved)));
+    assert_eq!(decoder.next_frame(), Some(Frame::Event(press)));
+    assert_eq!(decoder.next_frame(), None);
+}
+
+#[test]
+fn extra_keys_replay_as_this_platforms_key() {
+    let key = ExtraKey::VolumeUp.key().unwrap();
+    let simulated = simulate(
+        &[
+            extra(ExtraKey::VolumeUp, true),
+            extra(ExtraKey::VolumeUp, false),
+        ],
+        StreamOptions::default(),
+    );
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(EventType::KeyPress(key)),
+            Simulated::Keyboard(EventType::KeyRelease(key)),
+        ]
+    );
+}
+
+#[test]
+fn an_extra_key_left_held_is_released_when_the_stream_ends() {
+    let key = ExtraKey::VolumeDown.key().unwrap();
+    let simulated = simulate(
+        &[extra(ExtraKey::VolumeDown, true)],
+        StreamOptions::default(),
+    );
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(EventType::KeyPress(key)),
+            Simulated::Keyboard(EventType::KeyRelease(key)),
+        ]
+    );
+}
+
+#[test]
+fn high_function_keys_replay_like_media_keys() {
+    let key = ExtraKey::F13.key().unwrap();
+    let simulated = simulate(
+        &[extra(ExtraKey::F13, true), extra(ExtraKey::F13, false)],
+        StreamOptions::default(),
+    );
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(EventType::KeyPress(key)),
+            Simulated::Keyboard(EventType::KeyRelease(key)),
+        ]
+    );
+}
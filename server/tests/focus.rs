@@ -0,0 +1,139 @@
+use std::{sync::Arc, time::Duration};
+
+use rdev::{Button, EventType, Key};
+use server::{
+    cli::parse_args,
+    focus::{FakeFocus, FocusGuard, title_matches},
+    framing::Frame,
+    inject::Injector,
+};
+use shared::{MouseMove, raw_keys::RawKeyInput};
+
+#[test]
+fn a_title_containing_the_wanted_one_matches() {
+    assert!(title_matches(Some("Factorio 2.0"), Some("factorio")));
+    assert!(title_matches(Some("My Game"), Some("My Game")));
+}
+
+#[test]
+fn other_titles_and_unknown_ones_do_not_match() {
+    assert!(!title_matches(
+        Some("Online Banking - Firefox"),
+        Some("factorio")
+    ));
+    assert!(!title_matches(Some(""), Some("factorio")));
+    assert!(!title_matches(None, Some("factorio")));
+}
+
+#[test]
+fn without_a_wanted_title_everything_matches() {
+    assert!(title_matches(Some("Online Banking - Firefox"), None));
+    assert!(title_matches(None, None));
+}
+
+#[test]
+fn the_injector_drops_input_for_other_windows_but_not_releases() {
+    let focus = Arc::new(FakeFocus::default());
+    let guard = Arc::new(FocusGuard::new("factorio", focus.clone()));
+    let (injector, log) = Injector::capture();
+    let injector = injector.with_focus_guard(guard.clone());
+
+    focus.set(Some("Online Banking - Firefox"));
+    assert!(!guard.check());
+    injector.event(EventType::KeyPress(Key::KeyA));
+    injector.event(EventType::ButtonPress(Button::Left));
+    injector.mouse_move(MouseMove { dx: 5.0, dy: 0.0 });
+    injector.absolute_move(10.0, 10.0);
+    injector.raw_key(RawKeyInput {
+        code: 30,
+        pressed: true,
+    });
+    assert!(log.try_recv().is_err());
+
+    injector.event(EventType::KeyRelease(Key::KeyA));
+    injector.event(EventType::ButtonRelease(Button::Left));
+    assert_eq!(
+        log.try_recv(),
+        Ok(Frame::Event(EventType::KeyRelease(Key::KeyA)))
+    );
+    assert_eq!(
+        log.try_recv(),
+        Ok(Frame::Event(EventType::ButtonRelease(Button::Left)))
+    );
+
+    let stats = injector.stats();
+    assert_eq!(stats.keys_dropped, 2);
+    assert_eq!(stats.mouse_dropped, 3);
+
+    focus.set(Some("Factorio 2.0"));
+    assert!(guard.check());
+    injector.event(EventType::KeyPress(Key::KeyA));
+    assert_eq!(
+        log.try_recv(),
+        Ok(Frame::Event(EventType::KeyPress(Key::KeyA)))
+    );
+}
+
+#[test]
+fn a_press_looks_at_the_focused_window_again() {
+    let focus = Arc::new(FakeFocus::default());
+    let guard =
+        Arc::new(FocusGuard::new("factorio", focus.clone()).with_press_max_age(Duration::ZERO));
+    let (injector, log) = Injector::capture();
+    let injector = injector.with_focus_guard(guard.clone());
+
+    focus.set(Some("Factorio 2.0"));
+    assert!(guard.check());
+    // Focus moves on between polls: moves go by the poll, presses don't.
+    focus.set(Some("Online Banking - Firefox"));
+    injector.event(EventType::KeyPress(Key::KeyA));
+    injector.mouse_move(MouseMove { dx: 5.0, dy: 0.0 });
+    assert!(log.try_recv().is_err());
+    assert!(!guard.allows());
+
+    focus.set(Some("Factorio 2.0"));
+    injector.event(EventType::ButtonPress(Button::Left));
+    assert_eq!(
+        log.try_recv(),
+        Ok(Frame::Event(EventType::ButtonPress(Button::Left)))
+    );
+}
+
+#[test]
+fn presses_close_together_share_one_look() {
+    let focus = Arc::new(FakeFocus::default());
+    let guard = Arc::new(
+        FocusGuard::new("factorio", focus.clone()).with_press_max_age(Duration::from_secs(60)),
+    );
+    let (injector, log) = Injector::capture();
+    let injector = injector.with_focus_guard(guard.clone());
+
+    focus.set(Some("Factorio 2.0"));
+    assert!(guard.check());
+    focus.set(None);
+    injector.event(EventType::KeyPress(Key::KeyA));
+    assert_eq!(
+        log.try_recv(),
+        Ok(Frame::Event(EventType::KeyPress(Key::KeyA)))
+    );
+}
+
+#[test]
+fn the_guard_is_off_unless_asked_for() {
+    assert_eq!(
+        parse_args(Vec::<String>::new()).unwrap().only_when_focused,
+        None
+    );
+    let args = parse_args(["--only-when-focused".to_string(), "Factorio".to_string()])
+        .expect("a title should parse");
+    assert_eq!(args.only_when_focused.as_deref(), Some("Factorio"));
+    assert!(parse_args(["--only-when-focused".to_string()]).is_err());
+
+    let (injector, log) = Injector::capture();
+    assert!(injector.focus_guard().is_none());
+    injector.event(EventType::KeyPress(Key::KeyA));
+    assert_eq!(
+        log.try_recv(),
+        Ok(Frame::Event(EventType::KeyPress(Key::KeyA)))
+    );
+}
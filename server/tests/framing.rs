@@ -0,0 +1,71 @@
+use rdev::{EventType, Key};
+use server::framing::{Frame, FrameDecoder};
+use shared::{CharInput, MouseMove};
+
+fn encode(event: &EventType) -> Vec<u8> {
+    rmp_serde::to_vec(event).expect("failed to serialise")
+}
+
+#[test]
+fn a_value_past_the_limit_overflows_the_decoder() {
+    let mut decoder = FrameDecoder::new(1024);
+    decoder.push(&encode(&EventType::KeyPress(Key::KeyA)));
+    assert_eq!(
+        decoder.next_frame(),
+        Some(Frame::Event(EventType::KeyPress(Key::KeyA)))
+    );
+    assert!(!decoder.overflowed());
+
+    let oversized = rmp_serde::to_vec(&vec![0u8; 100 * 1024]).unwrap();
+    for chunk in oversized.chunks(512) {
+        decoder.push(chunk);
+        assert_eq!(decoder.next_frame(), None);
+    }
+    assert!(decoder.overflowed());
+    assert_eq!(decoder.pending(), 0);
+
+    // Whatever follows can't be told apart from the rest of the value.
+    decoder.push(&encode(&EventType::KeyPress(Key::KeyB)));
+    assert_eq!(decoder.next_frame(), None);
+    assert_eq!(decoder.pending(), 0);
+}
+
+#[test]
+fn a_value_within_the_limit_is_decoded_whole() {
+    let mut decoder = FrameDecoder::new(100 * 1024 + 16);
+    let large = rmp_serde::to_vec(&vec![0u8; 100 * 1024]).unwrap();
+    for chunk in large.chunks(4096) {
+        decoder.push(chunk);
+    }
+    assert_eq!(decoder.next_frame(), Some(Frame::Unknown(large.len())));
+    assert!(!decoder.overflowed());
+}
+
+#[test]
+fn values_fed_one_byte_at_a_time_decode_whole() {
+    let mouse = MouseMove { dx: 3.5, dy: -2.0 };
+    let mut bytes = rmp_serde::to_vec(&mouse).unwrap();
+    bytes.extend(encode(&EventType::KeyPress(Key::ShiftLeft)));
+    bytes.extend(rmp_serde::to_vec(&CharInput('é')).unwrap());
+    bytes.extend(encode(&EventType::KeyRelease(Key::ShiftLeft)));
+
+    let mut decoder = FrameDecoder::new(1024);
+    let mut frames = Vec::new();
+    for byte in &bytes {
+        decoder.push(std::slice::from_ref(byte));
+        while let Some(frame) = decoder.next_frame() {
+            frames.push(frame);
+        }
+    }
+    assert_eq!(
+        frames,
+        [
+            Frame::Mouse(mouse),
+            Frame::Event(EventType::KeyPress(Key::ShiftLeft)),
+            Frame::Char('é'),
+            Frame::Event(EventType::KeyRelease(Key::ShiftLeft)),
+        ]
+    );
+    assert_eq!(decoder.pending(), 0);
+    assert!(!decoder.overflowed());
+}
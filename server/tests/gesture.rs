@@ -0,0 +1,117 @@
+//! Touchpad gestures and how the server turns them into wheel events.
+
+use rdev::{EventType, Key};
+use server::{
+    framing::{Frame, FrameDecoder},
+    gesture::{GestureTranslator, PIXELS_PER_NOTCH, ZOOM_PER_NOTCH},
+    server::StreamOptions,
+    testing::{InputMessage, Simulated, simulate},
+};
+use shared::{Gesture, MouseMove};
+
+fn wheel(delta_x: i64, delta_y: i64) -> EventType {
+    EventType::Wheel { delta_x, delta_y }
+}
+
+#[test]
+fn gestures_decode_apart_from_other_frames() {
+    let mut decoder = FrameDecoder::new(1024);
+    let pinch = Gesture::Pinch { scale: 1.25 };
+    let scroll = Gesture::Scroll { dx: -3.0, dy: 40.0 };
+    let moved = MouseMove { dx: 1.0, dy: 2.0 };
+    decoder.push(&rmp_serde::to_vec(&pinch).unwrap());
+    decoder.push(&rmp_serde::to_vec(&moved).unwrap());
+    decoder.push(&rmp_serde::to_vec(&scroll).unwrap());
+
+    assert_eq!(decoder.next_frame(), Some(Frame::Gesture(pinch)));
+    assert_eq!(decoder.next_frame(), Some(Frame::Mouse(moved)));
+    assert_eq!(decoder.next_frame(), Some(Frame::Gesture(scroll)));
+    assert_eq!(decoder.next_frame(), None);
+}
+
+#[test]
+fn two_finger_scroll_becomes_whole_notches() {
+    let mut translator = GestureTranslator::default();
+    let events = translator.translate(
+        Gesture::Scroll {
+            dx: PIXELS_PER_NOTCH,
+            dy: -2.0 * PIXELS_PER_NOTCH,
+        },
+        false,
+    );
+    assert_eq!(events, vec![wheel(1, -2)]);
+}
+
+#[test]
+fn small_scrolls_add_up_instead_of_being_lost() {
+    let mut translator = GestureTranslator::default();
+    let quarter = Gesture::Scroll {
+        dx: 0.0,
+        dy: PIXELS_PER_NOTCH / 4.0,
+    };
+    for _ in 0..3 {
+        assert_eq!(translator.translate(quarter, false), Vec::new());
+    }
+    assert_eq!(translator.translate(quarter, false), vec![wheel(0, 1)]);
+}
+
+#[test]
+fn pinch_zooms_with_control_wheel() {
+    let mut translator = GestureTranslator::default();
+    let events = translator.translate(
+        Gesture::Pinch {
+            scale: ZOOM_PER_NOTCH * ZOOM_PER_NOTCH * 1.01,
+        },
+        false,
+    );
+    assert_eq!(
+        events,
+        vec![
+            EventType::KeyPress(Key::ControlLeft),
+            wheel(0, 2),
+            EventType::KeyRelease(Key::ControlLeft),
+        ]
+    );
+
+    let zoom_out = translator.translate(
+        Gesture::Pinch {
+            scale: 1.0 / ZOOM_PER_NOTCH / 1.05,
+        },
+        true,
+    );
+    assert_eq!(zoom_out, vec![wheel(0, -1)]);
+}
+
+#[test]
+fn nonsense_pinches_are_ignored() {
+    let mut translator = GestureTranslator::default();
+    for scale in [0.0, -2.0, f64::NAN, f64::INFINITY] {
+        assert_eq!(
+            translator.translate(Gesture::Pinch { scale }, false),
+            Vec::new()
+        );
+    }
+}
+
+#[test]
+fn pinch_keeps_a_held_control_down() {
+    let held = EventType::KeyPress(Key::ControlLeft);
+    let encoded = rmp_serde::to_vec(&Gesture::Pinch {
+        scale: ZOOM_PER_NOTCH * 1.01,
+    })
+    .unwrap();
+    let simulated = simulate(
+        &[InputMessage::Event(held), InputMessage::Raw(encoded)],
+        StreamOptions::default(),
+    );
+
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(held),
+            Simulated::Mouse(wheel(0, 1)),
+            // Released only when the stream ends, not by the pinch.
+            Simulated::Keyboard(EventType::KeyRelease(Key::ControlLeft)),
+        ]
+    );
+}
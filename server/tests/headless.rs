@@ -0,0 +1,96 @@
+//! Keeps GTK out of headless builds: the server, and the client library
+//! without its `gui` feature, must not pull any GUI crate in. Reads the
+//! manifests rather than resolving them, so it needs no network or tools.
+
+use toml::{Table, Value};
+
+const GUI_CRATES: [&str; 5] = ["gtk4", "libadwaita", "glib", "gdk4", "gio"];
+
+const SERVER: &str = include_str!("../Cargo.toml");
+const CLIENT: &str = include_str!("../../client/Cargo.toml");
+const SHARED: &str = include_str!("../../shared/Cargo.toml");
+
+fn parse(manifest: &str) -> Table {
+    manifest.parse().expect("manifest isn't valid TOML")
+}
+
+/// Every `[dependencies]` entry, including target-specific ones, by name.
+fn dependencies(manifest: &Table) -> Vec<(String, Value)> {
+    let targets = manifest
+        .get("target")
+        .and_then(Value::as_table)
+        .into_iter()
+        .flat_map(|targets| targets.values().filter_map(Value::as_table));
+    std::iter::once(manifest)
+        .chain(targets)
+        .filter_map(|table| table.get("dependencies").and_then(Value::as_table))
+        .flat_map(|deps| deps.iter().map(|(name, spec)| (name.clone(), spec.clone())))
+        .collect()
+}
+
+fn is_gui(name: &str) -> bool {
+    GUI_CRATES.contains(&name)
+}
+
+fn is_optional(spec: &Value) -> bool {
+    spec.get("optional").and_then(Value::as_bool) == Some(true)
+}
+
+fn features(manifest: &Table) -> Table {
+    manifest
+        .get("features")
+        .and_then(Value::as_table)
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[test]
+fn server_and_shared_depend_on_no_gui_crate() {
+    for (package, manifest) in [("server", SERVER), ("shared", SHARED)] {
+        let gui: Vec<String> = dependencies(&parse(manifest))
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| is_gui(name))
+            .collect();
+        assert!(gui.is_empty(), "{package} depends on {gui:?}");
+    }
+}
+
+#[test]
+fn server_uses_the_client_library_without_its_gui() {
+    let server = parse(SERVER);
+    let client = server
+        .get("dev-dependencies")
+        .and_then(|deps| deps.get("client"))
+        .expect("server no longer depends on the client library");
+    assert_eq!(
+        client.get("default-features").and_then(Value::as_bool),
+        Some(false),
+        "the server's tests would build the client's GUI"
+    );
+}
+
+#[test]
+fn client_gui_crates_are_only_enabled_by_the_gui_feature() {
+    let client = parse(CLIENT);
+    for (name, spec) in dependencies(&client) {
+        if is_gui(&name) {
+            assert!(is_optional(&spec), "client always depends on {name}");
+        }
+    }
+
+    let features = features(&client);
+    for (feature, enables) in &features {
+        let enables = enables.as_array().expect("feature isn't a list");
+        let gui: Vec<&str> = enables
+            .iter()
+            .filter_map(Value::as_str)
+            .filter(|item| is_gui(item.trim_start_matches("dep:")))
+            .collect();
+        if feature == "gui" {
+            assert!(!gui.is_empty(), "the gui feature enables no GUI crate");
+        } else if feature != "default" {
+            assert!(gui.is_empty(), "{feature} enables {gui:?}");
+        }
+    }
+}
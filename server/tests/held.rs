@@ -0,0 +1,136 @@
+//! What a connection holds down, so a disconnect releases exactly that.
+
+use rdev::{Button, EventType, Key};
+use server::held::HeldState;
+use shared::raw_keys::RawKeyInput;
+
+#[test]
+fn presses_are_held_until_released() {
+    let mut held = HeldState::default();
+    held.observe(&EventType::KeyPress(Key::ControlLeft));
+    held.observe(&EventType::KeyPress(Key::KeyC));
+    held.observe(&EventType::ButtonPress(Button::Left));
+    held.observe(&EventType::KeyRelease(Key::KeyC));
+    held.observe(&EventType::MouseMove { x: 1.0, y: 2.0 });
+
+    assert_eq!(held.keys(), [Key::ControlLeft]);
+    assert!(held.has_buttons());
+    assert_eq!(
+        held.drain(),
+        [
+            EventType::KeyRelease(Key::ControlLeft),
+            EventType::ButtonRelease(Button::Left),
+        ]
+    );
+    assert!(held.is_empty());
+}
+
+#[test]
+fn a_repeated_press_is_held_once() {
+    let mut held = HeldState::default();
+    held.add_key(Key::ShiftLeft);
+    held.add_key(Key::ShiftLeft);
+    held.add_button(Button::Right);
+    held.add_button(Button::Right);
+    assert_eq!(
+        held.drain(),
+        [
+            EventType::KeyRelease(Key::ShiftLeft),
+            EventType::ButtonRelease(Button::Right),
+        ]
+    );
+}
+
+#[test]
+fn releasing_what_isnt_held_changes_nothing() {
+    let mut held = HeldState::default();
+    held.remove_key(Key::Alt);
+    held.remove_button(Button::Left);
+    held.observe(&EventType::KeyRelease(Key::Num0));
+    assert!(held.is_empty());
+    assert!(held.drain().is_empty());
+}
+
+#[test]
+fn releases_come_in_reverse_press_order() {
+    let mut held = HeldState::default();
+    for key in [Key::ControlLeft, Key::Alt, Key::Delete] {
+        held.add_key(key);
+    }
+    held.add_button(Button::Left);
+    held.add_button(Button::Right);
+    assert_eq!(
+        held.drain(),
+        [
+            EventType::KeyRelease(Key::Delete),
+            EventType::KeyRelease(Key::Alt),
+            EventType::KeyRelease(Key::ControlLeft),
+            EventType::ButtonRelease(Button::Right),
+            EventType::ButtonRelease(Button::Left),
+        ]
+    );
+}
+
+#[test]
+fn buttons_can_be_let_go_without_the_keys() {
+    let mut held = HeldState::default();
+    held.add_key(Key::ShiftLeft);
+    held.add_button(Button::Left);
+    assert_eq!(
+        held.drain_buttons(),
+        [EventType::ButtonRelease(Button::Left)]
+    );
+    assert!(!held.has_buttons());
+    assert_eq!(held.keys(), [Key::ShiftLeft]);
+}
+
+#[test]
+fn raw_keys_are_held_and_drained_apart() {
+    let mut held = HeldState::default();
+    held.observe_raw(RawKeyInput {
+        code: 248,
+        pressed: true,
+    });
+    held.observe_raw(RawKeyInput {
+        code: 248,
+        pressed: true,
+    });
+    held.observe_raw(RawKeyInput {
+        code: 190,
+        pressed: true,
+    });
+    held.observe_raw(RawKeyInput {
+        code: 190,
+        pressed: false,
+    });
+    assert!(!held.is_empty());
+    assert!(held.drain().is_empty());
+    assert_eq!(
+        held.drain_raw(),
+        [RawKeyInput {
+            code: 248,
+            pressed: false,
+        }]
+    );
+    assert!(held.is_empty());
+}
+
+#[test]
+fn merging_keeps_one_of_each() {
+    let mut held = HeldState::default();
+    held.add_key(Key::ShiftLeft);
+    let mut resumed = HeldState::default();
+    resumed.add_key(Key::ShiftLeft);
+    resumed.add_key(Key::KeyA);
+    resumed.add_button(Button::Middle);
+    held.merge(resumed);
+    assert_eq!(held.keys(), [Key::ShiftLeft, Key::KeyA]);
+    assert_eq!(
+        held.drain(),
+        [
+            EventType::KeyRelease(Key::KeyA),
+            EventType::KeyRelease(Key::ShiftLeft),
+            EventType::ButtonRelease(Button::Middle),
+        ]
+    );
+}
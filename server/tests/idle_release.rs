@@ -0,0 +1,140 @@
+//! A client that goes silent with keys down has them released once the idle
+//! interval passes, while its stream and connection are still open. One
+//! still holding them sends heartbeats, which keep them down.
+
+mod common;
+
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+use client::{
+    quic::{ClientSession, quic_runtime, send_data},
+    quic_helper_thread::{QuicCommand, StreamLayout, spawn_quic_helper},
+};
+use quinn::SendStream;
+use rdev::{EventType, Key};
+use server::framing::Frame;
+use shared::{KEEP_HELD_INTERVAL, KeepHeld, MouseMove};
+
+use common::{TestServer, WAIT};
+
+const IDLE: Duration = Duration::from_millis(400);
+
+/// A dry-run server releasing after `idle`.
+fn start(idle: Duration) -> TestServer {
+    TestServer::start_with(|options| options.with_idle_release(Some(idle)))
+}
+
+/// A client of a server releasing after [`IDLE`], with a uni stream it
+/// never finishes.
+fn connect() -> (TestServer, ClientSession, SendStream) {
+    let server = start(IDLE);
+    let session = server.connect();
+    let send = quic_runtime()
+        .block_on(session.link().open_uni())
+        .expect("failed to open stream");
+    (server, session, send)
+}
+
+fn send(stream: &mut SendStream, value: &impl serde::Serialize) {
+    let bytes = rmp_serde::to_vec(value).unwrap();
+    quic_runtime()
+        .block_on(send_data(stream, &bytes))
+        .expect("failed to send");
+}
+
+#[test]
+fn silence_with_a_key_down_releases_it() {
+    let (server, session, mut stream) = connect();
+    let log = &server.log;
+    send(&mut stream, &EventType::KeyPress(Key::ShiftLeft));
+    assert_eq!(
+        log.recv_timeout(WAIT).unwrap(),
+        Frame::Event(EventType::KeyPress(Key::ShiftLeft))
+    );
+
+    let pressed = Instant::now();
+    assert_eq!(
+        log.recv_timeout(WAIT).unwrap(),
+        Frame::Event(EventType::KeyRelease(Key::ShiftLeft))
+    );
+    assert!(pressed.elapsed() >= IDLE - Duration::from_millis(50));
+    assert!(session.connection.close_reason().is_none());
+}
+
+#[test]
+fn steady_input_keeps_the_key_down() {
+    let (server, _session, mut stream) = connect();
+    let log = &server.log;
+    send(&mut stream, &EventType::KeyPress(Key::ShiftLeft));
+    let moved = MouseMove { dx: 1.0, dy: 0.0 };
+    for _ in 0..8 {
+        thread::sleep(IDLE / 4);
+        send(&mut stream, &moved);
+    }
+
+    let frames: Vec<Frame> = log.try_iter().collect();
+    assert_eq!(frames[0], Frame::Event(EventType::KeyPress(Key::ShiftLeft)));
+    assert!(
+        !frames.contains(&Frame::Event(EventType::KeyRelease(Key::ShiftLeft))),
+        "released while input was still arriving: {frames:?}"
+    );
+    // Then silence lets it go.
+    assert_eq!(next_release(log), Some(Key::ShiftLeft));
+}
+
+/// The next key released, skipping everything else; `None` if none comes.
+fn next_release(log: &Receiver<Frame>) -> Option<Key> {
+    loop {
+        if let Frame::Event(EventType::KeyRelease(key)) = log.recv_timeout(WAIT).ok()? {
+            return Some(key);
+        }
+    }
+}
+
+#[test]
+fn heartbeats_keep_a_held_key_down() {
+    let (server, _session, mut stream) = connect();
+    let log = &server.log;
+    send(&mut stream, &EventType::KeyPress(Key::ShiftLeft));
+    for _ in 0..8 {
+        thread::sleep(IDLE / 4);
+        send(&mut stream, &KeepHeld);
+    }
+
+    let frames: Vec<Frame> = log.try_iter().collect();
+    assert_eq!(frames[0], Frame::Event(EventType::KeyPress(Key::ShiftLeft)));
+    assert!(
+        !frames.contains(&Frame::Event(EventType::KeyRelease(Key::ShiftLeft))),
+        "released while heartbeats were still arriving: {frames:?}"
+    );
+    assert_eq!(next_release(log), Some(Key::ShiftLeft));
+}
+
+#[test]
+fn the_send_worker_sends_heartbeats_while_holding() {
+    let idle = KEEP_HELD_INTERVAL * 2;
+    let server = start(idle);
+    let session = server.connect();
+    let log = &server.log;
+    let (stats_tx, _stats_rx) = mpsc::channel();
+    let sender = spawn_quic_helper(session.link(), stats_tx, StreamLayout::Split);
+    let press = rmp_serde::to_vec(&EventType::KeyPress(Key::ShiftLeft)).unwrap();
+    sender.send(QuicCommand::Keyboard(press)).unwrap();
+    sender.send(QuicCommand::Holding(true)).unwrap();
+
+    thread::sleep(idle * 2);
+    let frames: Vec<Frame> = log.try_iter().collect();
+    assert!(frames.contains(&Frame::Event(EventType::KeyPress(Key::ShiftLeft))));
+    assert!(
+        !frames.contains(&Frame::Event(EventType::KeyRelease(Key::ShiftLeft))),
+        "released while the key was still held: {frames:?}"
+    );
+
+    // With nothing held the heartbeats stop, and silence lets it go.
+    sender.send(QuicCommand::Holding(false)).unwrap();
+    assert_eq!(next_release(log), Some(Key::ShiftLeft));
+}
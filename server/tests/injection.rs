@@ -0,0 +1,76 @@
+//! The injector's injected/dropped totals reported to clients.
+
+use rdev::{Button, EventType, Key};
+use server::inject::Injector;
+use shared::{InjectionStats, MouseMove};
+
+#[test]
+fn captured_input_counts_as_injected_by_kind() {
+    let (injector, _log) = Injector::capture();
+    injector.mouse_move(MouseMove { dx: 1.0, dy: 1.0 });
+    injector.event(EventType::ButtonPress(Button::Left));
+    injector.event(EventType::Wheel {
+        delta_x: 0,
+        delta_y: 1,
+    });
+    injector.event(EventType::KeyPress(Key::KeyA));
+
+    assert_eq!(
+        injector.stats(),
+        InjectionStats {
+            mouse_injected: 3,
+            keys_injected: 1,
+            ..InjectionStats::default()
+        }
+    );
+}
+
+#[test]
+fn input_with_nowhere_to_go_counts_as_dropped() {
+    let (injector, log) = Injector::capture();
+    drop(log);
+    injector.mouse_move(MouseMove { dx: 1.0, dy: 1.0 });
+    injector.event(EventType::KeyRelease(Key::KeyA));
+
+    assert_eq!(
+        injector.stats(),
+        InjectionStats {
+            mouse_dropped: 1,
+            keys_dropped: 1,
+            ..InjectionStats::default()
+        }
+    );
+}
+
+#[test]
+fn clones_share_one_set_of_totals() {
+    let (injector, _log) = Injector::capture();
+    injector.clone().event(EventType::KeyPress(Key::KeyB));
+    assert_eq!(injector.stats().keys_injected, 1);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn moves_are_dropped_while_the_virtual_mouse_is_unavailable() {
+    use std::sync::{Arc, Mutex};
+
+    use server::simulator::{LaneLayout, SimulatorPool};
+
+    let simulators = Arc::new(SimulatorPool::new(LaneLayout::Split));
+    let injector = Injector::live(
+        simulators,
+        Arc::new(Mutex::new(None)),
+        Arc::new(Mutex::new(None)),
+    );
+    for _ in 0..3 {
+        injector.mouse_move(MouseMove { dx: 5.0, dy: 0.0 });
+    }
+
+    assert_eq!(
+        injector.stats(),
+        InjectionStats {
+            mouse_dropped: 3,
+            ..InjectionStats::default()
+        }
+    );
+}
@@ -0,0 +1,71 @@
+//! The table of Linux input event codes keys are injected as.
+
+use std::collections::HashSet;
+
+use rdev::{EventType, Key};
+use server::keymap::{NAMED_KEYS, for_injection, linux_keycode, warn_unmapped};
+use shared::extra_keys::ExtraKey;
+
+#[test]
+fn every_named_key_has_a_distinct_code() {
+    let mut seen = HashSet::new();
+    for key in NAMED_KEYS {
+        let code = linux_keycode(key).unwrap_or_else(|| panic!("{key:?} has no code"));
+        assert!(seen.insert(code), "{key:?} shares code {code}");
+    }
+}
+
+#[test]
+fn function_and_f_keys_map_to_their_linux_codes() {
+    assert_eq!(linux_keycode(Key::Function), Some(464));
+    assert_eq!(linux_keycode(Key::F1), Some(59));
+    assert_eq!(linux_keycode(Key::F12), Some(88));
+}
+
+#[test]
+fn extra_keys_map_through_this_platforms_keycode() {
+    for extra in ExtraKey::ALL {
+        if let Some(key) = extra.key() {
+            assert_eq!(linux_keycode(key), Some(extra.linux_code()), "{extra:?}");
+        }
+    }
+}
+
+#[test]
+fn extra_key_codes_do_not_clash_with_named_keys() {
+    let named: HashSet<u16> = NAMED_KEYS
+        .iter()
+        .filter_map(|key| linux_keycode(*key))
+        .collect();
+    for extra in ExtraKey::ALL {
+        assert!(!named.contains(&extra.linux_code()), "{extra:?}");
+    }
+}
+
+#[test]
+fn unknown_keys_are_left_unmapped_without_panicking() {
+    for code in [0, 1, 255, 0xFFFF, u32::MAX] {
+        let key = Key::Unknown(code);
+        if ExtraKey::from_key(key).is_none() {
+            assert_eq!(linux_keycode(key), None, "{key:?}");
+            warn_unmapped(key);
+            warn_unmapped(key);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn system_keys_are_injected_as_themselves_on_linux() {
+    for key in [Key::PrintScreen, Key::Pause, Key::Function] {
+        assert_eq!(
+            for_injection(EventType::KeyRelease(key)),
+            Some(EventType::KeyRelease(key))
+        );
+    }
+    let wheel = EventType::Wheel {
+        delta_x: 0,
+        delta_y: 1,
+    };
+    assert_eq!(for_injection(wheel), Some(wheel));
+}
@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use server::latency::{LATENCY_WINDOW, LatencyStats};
+
+#[test]
+fn nothing_is_measured_before_the_clocks_are_synced() {
+    let mut stats = LatencyStats::default();
+    assert_eq!(stats.record(1_000, 2_000), None);
+    assert_eq!(stats.average(), None);
+}
+
+#[test]
+fn latency_accounts_for_the_clock_offset_and_flight_time() {
+    let mut stats = LatencyStats::default();
+    // The client's clock reads 1_000 when ours reads 50_500, and the hello
+    // spent 500 µs in flight, so client time t is our time t + 49_000.
+    stats.sync_clock(1_000, 50_500, Duration::from_micros(500));
+
+    assert_eq!(
+        stats.record(2_000, 53_000),
+        Some(Duration::from_micros(2_000))
+    );
+    assert_eq!(
+        stats.record(3_000, 56_000),
+        Some(Duration::from_micros(4_000))
+    );
+    assert_eq!(stats.average(), Some(Duration::from_micros(3_000)));
+}
+
+#[test]
+fn input_apparently_applied_before_it_was_sent_counts_as_zero() {
+    let mut stats = LatencyStats::default();
+    stats.sync_clock(0, 10_000, Duration::ZERO);
+    assert_eq!(stats.record(5_000, 14_000), Some(Duration::ZERO));
+}
+
+#[test]
+fn average_only_covers_the_recent_window() {
+    let mut stats = LatencyStats::default();
+    stats.sync_clock(0, 0, Duration::ZERO);
+    for _ in 0..LATENCY_WINDOW {
+        stats.record(0, 10_000);
+    }
+    for _ in 0..LATENCY_WINDOW {
+        stats.record(0, 2_000);
+    }
+    assert_eq!(stats.average(), Some(Duration::from_micros(2_000)));
+    assert_eq!(stats.recorded(), 2 * LATENCY_WINDOW as u64);
+}
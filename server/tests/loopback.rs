@@ -0,0 +1,784 @@
+//! Drives a real client against a dry-run server over loopback and checks the
+//! server decodes exactly what was sent.
+
+mod common;
+
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::{
+        Arc,
+        mpsc::{self, RecvTimeoutError},
+    },
+    time::{Duration, Instant},
+};
+
+use client::{
+    injection::watch_injection,
+    key_combo::send_key_combo,
+    netsim::NetSim,
+    observer::watch_observed,
+    quic::{
+        ClientOptions, ConnectPhase, InputLink, close_client, control_request,
+        install_crypto_provider, open_uni, quic_runtime, run_client, run_client_with_progress,
+        send_data,
+    },
+    quic_helper_thread::{QuicCommand, QuicSender, StreamLayout, spawn_quic_helper_with_sim},
+};
+use quinn::{Connection, Endpoint};
+use rdev::{Button, EventType, Key};
+use server::{displays::FakeDisplays, framing::Frame, server::StreamOptions};
+use shared::{
+    CharInput, CloseCode, ControlRequest, ControlResponse, DisplayInfo, InjectionStats, MouseMove,
+    ObservedInput, SessionToken,
+    codec::{Codec, WireFormat},
+    key_combo::KeyCombo,
+};
+
+use common::{TestServer, WAIT};
+
+struct Loopback {
+    server: TestServer,
+    endpoint: Endpoint,
+    connection: Connection,
+    input: InputLink,
+    remote_displays: Vec<DisplayInfo>,
+    token: SessionToken,
+    /// Every phase the client reported while connecting, in order.
+    phases: Vec<ConnectPhase>,
+    sender: QuicSender,
+}
+
+impl Loopback {
+    fn start() -> Self {
+        Self::start_with_sim(None)
+    }
+
+    fn start_with_sim(sim: Option<NetSim>) -> Self {
+        Self::start_with(StreamLayout::Split, sim)
+    }
+
+    fn start_with(layout: StreamLayout, sim: Option<NetSim>) -> Self {
+        Self::start_configured(layout, sim, ClientOptions::new)
+    }
+
+    fn start_with_format(format: WireFormat) -> Self {
+        Self::start_configured(StreamLayout::Split, None, |addr| {
+            ClientOptions::new(addr).with_wire_format(format)
+        })
+    }
+
+    fn start_configured(
+        layout: StreamLayout,
+        sim: Option<NetSim>,
+        options: impl FnOnce(SocketAddr) -> ClientOptions,
+    ) -> Self {
+        let server = TestServer::start_with(|options| {
+            options.with_displays(Arc::new(FakeDisplays(vec![sample_display()])))
+        });
+
+        let mut phases = Vec::new();
+        let session = quic_runtime()
+            .block_on(run_client_with_progress(
+                options(server.addr),
+                None,
+                false,
+                |phase| phases.push(phase),
+            ))
+            .expect("client failed to connect");
+        let token = session.token.expect("server did not issue a session token");
+
+        let (stats_tx, _stats_rx) = mpsc::channel();
+        let sender = spawn_quic_helper_with_sim(session.link(), stats_tx, layout, sim);
+
+        Self {
+            server,
+            input: session.link(),
+            endpoint: session.endpoint,
+            connection: session.connection,
+            remote_displays: session.remote_displays,
+            token,
+            phases,
+            sender,
+        }
+    }
+
+    fn send(&self, command: QuicCommand) {
+        self.sender.send(command).expect("quic helper gone");
+    }
+
+    fn next_frame(&self) -> Frame {
+        self.server.next_frame()
+    }
+
+    fn finish(self) {
+        self.send(QuicCommand::Shutdown);
+        quic_runtime()
+            .block_on(close_client(
+                self.input,
+                self.endpoint,
+                CloseCode::UserDisconnect,
+            ))
+            .expect("client failed to close");
+
+        // Everything was released by the client, so the server has nothing left to emit.
+        assert!(matches!(
+            self.server.log.recv_timeout(Duration::from_millis(200)),
+            Err(RecvTimeoutError::Timeout)
+        ));
+    }
+}
+
+fn sample_display() -> DisplayInfo {
+    DisplayInfo {
+        name: "fake-0".to_string(),
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+        is_primary: true,
+    }
+}
+
+fn encode(event: &EventType) -> Vec<u8> {
+    rmp_serde::to_vec(event).expect("failed to serialise")
+}
+
+#[test]
+fn loopback_delivers_input_in_order() {
+    let loopback = Loopback::start();
+
+    // Mouse and keyboard travel on separate streams, so order is only
+    // guaranteed within each of them.
+    loopback.send(QuicCommand::Move(MouseMove { dx: 12.0, dy: -3.5 }));
+    assert_eq!(
+        loopback.next_frame(),
+        Frame::Mouse(MouseMove { dx: 12.0, dy: -3.5 })
+    );
+
+    let keys = [
+        EventType::KeyPress(Key::ShiftLeft),
+        EventType::KeyPress(Key::KeyQ),
+        EventType::KeyRelease(Key::KeyQ),
+        EventType::KeyRelease(Key::ShiftLeft),
+    ];
+    for event in &keys {
+        loopback.send(QuicCommand::Keyboard(encode(event)));
+    }
+    for event in keys {
+        assert_eq!(loopback.next_frame(), Frame::Event(event));
+    }
+
+    loopback.finish();
+}
+
+#[test]
+fn json_input_is_negotiated_and_decoded() {
+    let loopback = Loopback::start_with_format(WireFormat::Json);
+    assert_eq!(loopback.input.format, WireFormat::Json);
+
+    // The worker numbers and encodes moves itself, in the agreed format.
+    loopback.send(QuicCommand::Move(MouseMove { dx: -4.0, dy: 0.5 }));
+    assert_eq!(
+        loopback.next_frame(),
+        Frame::Mouse(MouseMove { dx: -4.0, dy: 0.5 })
+    );
+
+    let keys = [
+        EventType::KeyPress(Key::KeyJ),
+        EventType::KeyRelease(Key::KeyJ),
+    ];
+    for event in &keys {
+        let buf = WireFormat::Json.encode(event).expect("failed to serialise");
+        loopback.send(QuicCommand::Keyboard(buf));
+    }
+    for event in keys {
+        assert_eq!(loopback.next_frame(), Frame::Event(event));
+    }
+
+    loopback.finish();
+}
+
+#[test]
+fn key_combo_is_pressed_whole() {
+    let loopback = Loopback::start();
+
+    quic_runtime()
+        .block_on(send_key_combo(
+            loopback.input.clone(),
+            &KeyCombo::ctrl_alt_del(),
+        ))
+        .expect("failed to send combo");
+    for event in [
+        EventType::KeyPress(Key::ControlLeft),
+        EventType::KeyPress(Key::Alt),
+        EventType::KeyPress(Key::Delete),
+        EventType::KeyRelease(Key::Delete),
+        EventType::KeyRelease(Key::Alt),
+        EventType::KeyRelease(Key::ControlLeft),
+    ] {
+        assert_eq!(loopback.next_frame(), Frame::Event(event));
+    }
+
+    loopback.finish();
+}
+
+#[test]
+fn oversized_key_combo_is_refused() {
+    let loopback = Loopback::start();
+    let combo = KeyCombo::new(vec![
+        Key::ControlLeft,
+        Key::Alt,
+        Key::ShiftLeft,
+        Key::MetaLeft,
+        Key::KeyA,
+        Key::KeyB,
+        Key::KeyC,
+    ]);
+    assert!(
+        quic_runtime()
+            .block_on(send_key_combo(loopback.input.clone(), &combo))
+            .is_err()
+    );
+
+    // Bypassing the client's own check gets no further.
+    quic_runtime().block_on(async {
+        let mut send = open_uni(loopback.connection.clone()).await.unwrap();
+        send_data(&mut send, &rmp_serde::to_vec(&combo).unwrap())
+            .await
+            .unwrap();
+        send.finish().unwrap();
+    });
+
+    // finish() checks that nothing reached the server.
+    loopback.finish();
+}
+
+#[test]
+fn oversized_value_stops_the_stream_and_releases_its_keys() {
+    let loopback = Loopback::start();
+    let press = EventType::KeyPress(Key::KeyA);
+    // Each zero is one byte, so the value is past the 64 KiB a stream may
+    // buffer long before it is complete.
+    let oversized = rmp_serde::to_vec(&vec![0u8; 100 * 1024]).unwrap();
+    assert!(oversized.len() >= 100 * 1024);
+
+    let send = quic_runtime().block_on(async {
+        let mut send = open_uni(loopback.connection.clone()).await.unwrap();
+        send_data(&mut send, &encode(&press)).await.unwrap();
+        // The server may stop the stream before all of it is written.
+        let _ = send_data(&mut send, &oversized).await;
+        let _ = send_data(&mut send, &encode(&EventType::KeyPress(Key::KeyB))).await;
+        send
+    });
+
+    let stopped = quic_runtime()
+        .block_on(async { tokio::time::timeout(WAIT, send.stopped()).await })
+        .expect("server did not stop the stream");
+    assert_eq!(stopped, Ok(Some(CloseCode::TooLarge.into())));
+
+    assert_eq!(loopback.next_frame(), Frame::Event(press));
+    assert_eq!(
+        loopback.next_frame(),
+        Frame::Event(EventType::KeyRelease(Key::KeyA))
+    );
+
+    // finish() checks that nothing after the oversized value was decoded.
+    loopback.finish();
+}
+
+#[test]
+fn single_stream_preserves_order_across_mouse_and_keyboard() {
+    let loopback = Loopback::start_with(StreamLayout::Single, None);
+
+    // A shift-click, interleaved tightly enough that separate streams could
+    // easily reorder it.
+    let sent = [
+        QuicCommand::Keyboard(encode(&EventType::KeyPress(Key::ShiftLeft))),
+        QuicCommand::Mouse(encode(&EventType::ButtonPress(Button::Left))),
+        QuicCommand::Mouse(encode(&EventType::ButtonRelease(Button::Left))),
+        QuicCommand::Keyboard(encode(&EventType::KeyRelease(Key::ShiftLeft))),
+        QuicCommand::Move(MouseMove { dx: 2.0, dy: 3.0 }),
+        QuicCommand::Keyboard(encode(&EventType::KeyPress(Key::KeyA))),
+        QuicCommand::Keyboard(encode(&EventType::KeyRelease(Key::KeyA))),
+        QuicCommand::Mouse(encode(&EventType::Wheel {
+            delta_x: 0,
+            delta_y: 1,
+        })),
+    ];
+    for command in sent {
+        loopback.send(command);
+    }
+
+    let expected = [
+        Frame::Event(EventType::KeyPress(Key::ShiftLeft)),
+        Frame::Event(EventType::ButtonPress(Button::Left)),
+        Frame::Event(EventType::ButtonRelease(Button::Left)),
+        Frame::Event(EventType::KeyRelease(Key::ShiftLeft)),
+        Frame::Mouse(MouseMove { dx: 2.0, dy: 3.0 }),
+        Frame::Event(EventType::KeyPress(Key::KeyA)),
+        Frame::Event(EventType::KeyRelease(Key::KeyA)),
+        Frame::Event(EventType::Wheel {
+            delta_x: 0,
+            delta_y: 1,
+        }),
+    ];
+    for frame in expected {
+        assert_eq!(loopback.next_frame(), frame);
+    }
+
+    loopback.finish();
+}
+
+#[test]
+fn drag_moves_stay_between_press_and_release() {
+    let loopback = Loopback::start();
+
+    // Queued back to back so the worker has every chance to coalesce.
+    loopback.send(QuicCommand::Move(MouseMove { dx: 1.0, dy: 1.0 }));
+    loopback.send(QuicCommand::Mouse(encode(&EventType::ButtonPress(
+        Button::Left,
+    ))));
+    loopback.send(QuicCommand::Move(MouseMove { dx: 5.0, dy: 0.0 }));
+    loopback.send(QuicCommand::Move(MouseMove { dx: 7.0, dy: -2.0 }));
+    loopback.send(QuicCommand::Mouse(encode(&EventType::ButtonRelease(
+        Button::Left,
+    ))));
+    loopback.send(QuicCommand::Move(MouseMove { dx: -3.0, dy: 4.0 }));
+
+    assert_eq!(
+        loopback.next_frame(),
+        Frame::Mouse(MouseMove { dx: 1.0, dy: 1.0 })
+    );
+    assert_eq!(
+        loopback.next_frame(),
+        Frame::Event(EventType::ButtonPress(Button::Left))
+    );
+
+    // The drag may arrive merged or in pieces, but all of it before the release.
+    let (mut dx, mut dy) = (0.0, 0.0);
+    let release = loop {
+        match loopback.next_frame() {
+            Frame::Mouse(mouse_move) => {
+                dx += mouse_move.dx;
+                dy += mouse_move.dy;
+            }
+            other => break other,
+        }
+    };
+    assert_eq!((dx, dy), (12.0, -2.0));
+    assert_eq!(
+        release,
+        Frame::Event(EventType::ButtonRelease(Button::Left))
+    );
+
+    assert_eq!(
+        loopback.next_frame(),
+        Frame::Mouse(MouseMove { dx: -3.0, dy: 4.0 })
+    );
+
+    loopback.finish();
+}
+
+#[test]
+fn client_caches_server_displays_on_connect() {
+    let loopback = Loopback::start();
+    assert_eq!(loopback.remote_displays, vec![sample_display()]);
+    loopback.finish();
+}
+
+#[test]
+fn connect_reports_each_phase_in_order() {
+    let loopback = Loopback::start();
+    assert_eq!(
+        loopback.phases,
+        vec![
+            ConnectPhase::Preparing,
+            ConnectPhase::Connecting,
+            ConnectPhase::Handshaking,
+            ConnectPhase::FetchingDisplays,
+        ]
+    );
+    loopback.finish();
+}
+
+#[test]
+fn handshake_succeeds_with_installed_provider() {
+    let loopback = Loopback::start();
+    assert!(rustls::crypto::CryptoProvider::get_default().is_some());
+    assert!(loopback.connection.close_reason().is_none());
+    loopback.finish();
+}
+
+// Release builds ignore simulated network conditions.
+#[cfg(debug_assertions)]
+#[test]
+fn delayed_events_still_arrive_in_order() {
+    let sim = NetSim {
+        delay: Duration::from_millis(20),
+        jitter: Duration::from_millis(30),
+        seed: 7,
+        ..NetSim::default()
+    };
+    let loopback = Loopback::start_with_sim(Some(sim));
+
+    let keys = [
+        EventType::KeyPress(Key::KeyW),
+        EventType::KeyRelease(Key::KeyW),
+        EventType::KeyPress(Key::KeyS),
+        EventType::KeyRelease(Key::KeyS),
+    ];
+    let started = Instant::now();
+    for event in &keys {
+        loopback.send(QuicCommand::Keyboard(encode(event)));
+    }
+    for event in keys {
+        assert_eq!(loopback.next_frame(), Frame::Event(event));
+    }
+    assert!(started.elapsed() >= sim.delay * keys.len() as u32);
+
+    loopback.finish();
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn drop_injection_discards_writes() {
+    let sim = NetSim {
+        drop_rate: 1.0,
+        ..NetSim::default()
+    };
+    let loopback = Loopback::start_with_sim(Some(sim));
+
+    loopback.send(QuicCommand::Keyboard(encode(&EventType::KeyPress(
+        Key::KeyD,
+    ))));
+    loopback.send(QuicCommand::Move(MouseMove { dx: 4.0, dy: 4.0 }));
+
+    // finish() checks that nothing reached the server.
+    loopback.finish();
+}
+
+#[test]
+fn char_input_is_typed_through_the_server_layout() {
+    let loopback = Loopback::start();
+
+    let buf = rmp_serde::to_vec(&CharInput('?')).expect("failed to serialise");
+    loopback.send(QuicCommand::Keyboard(buf));
+
+    for event in [
+        EventType::KeyPress(Key::ShiftLeft),
+        EventType::KeyPress(Key::Slash),
+        EventType::KeyRelease(Key::Slash),
+        EventType::KeyRelease(Key::ShiftLeft),
+    ] {
+        assert_eq!(loopback.next_frame(), Frame::Event(event));
+    }
+
+    loopback.finish();
+}
+
+#[test]
+fn observer_sees_applied_input_but_cannot_inject() {
+    let loopback = Loopback::start();
+    let runtime = quic_runtime();
+
+    let observer = runtime
+        .block_on(run_client(
+            ClientOptions::new(loopback.server.addr),
+            None,
+            true,
+        ))
+        .expect("observer failed to connect");
+    // With the only connection slot taken by the input client.
+    assert!(observer.observing, "server refused observer mode");
+    // Asking again starts no second stream of observed input.
+    let again = runtime
+        .block_on(control_request(
+            &observer.connection,
+            &ControlRequest::Hello {
+                resume_token: observer.token,
+                observe: true,
+                clock_micros: None,
+                display_size: None,
+                wire_format: WireFormat::default(),
+            },
+        ))
+        .expect("repeated hello failed");
+    assert!(
+        matches!(
+            again,
+            ControlResponse::Welcome {
+                observing: false,
+                ..
+            }
+        ),
+        "{again:?}"
+    );
+
+    let (observed_tx, observed_rx) = mpsc::channel();
+    runtime.spawn(watch_observed(observer.connection.clone(), move |input| {
+        let _ = observed_tx.send(input);
+    }));
+
+    let keys = [
+        EventType::KeyPress(Key::KeyO),
+        EventType::KeyRelease(Key::KeyO),
+    ];
+    for event in &keys {
+        loopback.send(QuicCommand::Keyboard(encode(event)));
+    }
+    for event in keys {
+        assert_eq!(loopback.next_frame(), Frame::Event(event));
+        assert_eq!(
+            observed_rx
+                .recv_timeout(WAIT)
+                .expect("observer saw nothing"),
+            ObservedInput::Event(event)
+        );
+    }
+
+    // Whatever the observer sends is dropped rather than applied.
+    runtime
+        .block_on(async {
+            let mut send = open_uni(observer.connection.clone()).await?;
+            send_data(&mut send, &encode(&EventType::KeyPress(Key::KeyX))).await?;
+            send.finish()?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync + 'static>>(())
+        })
+        .expect("observer failed to send");
+    assert!(matches!(
+        loopback.server.log.recv_timeout(Duration::from_millis(300)),
+        Err(RecvTimeoutError::Timeout)
+    ));
+
+    runtime
+        .block_on(close_client(
+            observer.link(),
+            observer.endpoint,
+            CloseCode::UserDisconnect,
+        ))
+        .expect("observer failed to close");
+    loopback.finish();
+}
+
+#[test]
+fn graceful_close_waits_for_pending_input() {
+    let loopback = Loopback::start();
+
+    let mut expected = Vec::new();
+    for key in [Key::KeyA, Key::KeyB, Key::KeyC, Key::KeyD] {
+        for event in [EventType::KeyPress(key), EventType::KeyRelease(key)] {
+            loopback.send(QuicCommand::Keyboard(encode(&event)));
+            expected.push(Frame::Event(event));
+        }
+    }
+    loopback.send(QuicCommand::Shutdown);
+    quic_runtime()
+        .block_on(close_client(
+            loopback.input,
+            loopback.endpoint,
+            CloseCode::UserDisconnect,
+        ))
+        .expect("client failed to close");
+
+    // The drain handshake means everything was applied before close returned.
+    let applied: Vec<Frame> = loopback.server.log.try_iter().collect();
+    assert_eq!(applied, expected);
+}
+
+#[test]
+fn cancelled_connect_stops_and_a_new_one_works() {
+    install_crypto_provider().expect("no crypto provider");
+    let runtime = quic_runtime();
+
+    // Swallows the handshake without ever answering, like a server that is
+    // still starting.
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("failed to bind silent peer");
+    let addr = silent.local_addr().expect("silent peer has no address");
+    let mut buf = [0u8; 2048];
+
+    let attempt = runtime.spawn(run_client(ClientOptions::new(addr), None, false));
+    silent
+        .set_read_timeout(Some(WAIT))
+        .expect("failed to set read timeout");
+    silent
+        .recv(&mut buf)
+        .expect("client never tried to connect");
+
+    attempt.abort();
+    let outcome = runtime.block_on(attempt);
+    assert!(outcome.is_err_and(|error| error.is_cancelled()));
+
+    // Let anything already in flight land, then expect silence: a lingering
+    // endpoint would keep retransmitting its Initial packet.
+    silent
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .expect("failed to set read timeout");
+    while silent.recv(&mut buf).is_ok() {}
+    silent
+        .set_read_timeout(Some(Duration::from_millis(1500)))
+        .expect("failed to set read timeout");
+    assert!(
+        silent.recv(&mut buf).is_err(),
+        "cancelled connect is still sending"
+    );
+
+    let loopback = Loopback::start();
+    loopback.finish();
+}
+
+#[test]
+fn streams_past_the_limit_are_stopped() {
+    let runtime = quic_runtime();
+    let server = TestServer::start_with(|options| {
+        options
+            .with_stream_options(StreamOptions {
+                max_streams: 2,
+                ..StreamOptions::default()
+            })
+            .with_displays(Arc::new(FakeDisplays(vec![sample_display()])))
+    });
+    let session = server.connect();
+    let log = &server.log;
+
+    let moved = MouseMove { dx: 1.0, dy: 1.0 };
+    let buf = rmp_serde::to_vec(&moved).expect("failed to serialise");
+    let open = |connection: Connection| {
+        let buf = buf.clone();
+        async move {
+            let mut send = open_uni(connection).await.expect("failed to open stream");
+            send_data(&mut send, &buf).await.expect("failed to send");
+            send
+        }
+    };
+
+    // Both slots taken by streams left open.
+    let mut held = Vec::new();
+    for _ in 0..2 {
+        held.push(runtime.block_on(open(session.connection.clone())));
+        assert_eq!(log.recv_timeout(WAIT), Ok(Frame::Mouse(moved)));
+    }
+
+    let extra = runtime.block_on(open(session.connection.clone()));
+    let stopped = runtime
+        .block_on(async { tokio::time::timeout(WAIT, extra.stopped()).await })
+        .expect("server did not stop the extra stream");
+    assert_eq!(stopped, Ok(Some(CloseCode::StreamLimit.into())));
+    assert!(log.recv_timeout(Duration::from_millis(200)).is_err());
+
+    // Finishing one frees its slot for the next stream.
+    let mut first = held.remove(0);
+    first.finish().expect("failed to finish stream");
+    let deadline = Instant::now() + WAIT;
+    loop {
+        let mut next = runtime.block_on(open(session.connection.clone()));
+        if log.recv_timeout(Duration::from_millis(200)).is_ok() {
+            held.push(next);
+            break;
+        }
+        next.finish().ok();
+        assert!(Instant::now() < deadline, "freed slot was never reused");
+    }
+
+    drop(held);
+    runtime
+        .block_on(close_client(
+            session.link(),
+            session.endpoint,
+            CloseCode::UserDisconnect,
+        ))
+        .expect("client failed to close");
+}
+
+#[test]
+fn injection_reports_reach_the_client() {
+    let loopback = Loopback::start();
+    for event in [
+        EventType::KeyPress(Key::KeyQ),
+        EventType::KeyRelease(Key::KeyQ),
+    ] {
+        loopback.send(QuicCommand::Keyboard(encode(&event)));
+        assert_eq!(loopback.next_frame(), Frame::Event(event));
+    }
+
+    let (reports_tx, reports) = mpsc::channel();
+    quic_runtime().spawn(watch_injection(loopback.connection.clone(), move |stats| {
+        let _ = reports_tx.send(stats);
+    }));
+    let report = reports
+        .recv_timeout(WAIT)
+        .expect("server sent no injection report");
+    assert_eq!(
+        report,
+        InjectionStats {
+            keys_injected: 2,
+            ..InjectionStats::default()
+        }
+    );
+
+    loopback.finish();
+}
+
+#[test]
+fn reconnect_takes_over_a_session_still_held_by_the_old_connection() {
+    let loopback = Loopback::start();
+    let press = EventType::KeyPress(Key::ShiftLeft);
+    loopback.send(QuicCommand::Keyboard(encode(&press)));
+    assert_eq!(loopback.next_frame(), Frame::Event(press));
+
+    // The old connection is still open, as when the server has yet to notice
+    // it dropped.
+    let runtime = quic_runtime();
+    let resumed = runtime
+        .block_on(run_client(
+            ClientOptions::new(loopback.server.addr),
+            Some(loopback.token),
+            false,
+        ))
+        .expect("reconnect failed");
+    assert_eq!(resumed.token, Some(loopback.token));
+
+    let closed =
+        runtime.block_on(async { tokio::time::timeout(WAIT, loopback.connection.closed()).await });
+    match closed {
+        Ok(quinn::ConnectionError::ApplicationClosed(close)) => {
+            assert_eq!(
+                CloseCode::try_from(close.error_code),
+                Ok(CloseCode::Superseded)
+            );
+        }
+        other => panic!("old connection was not superseded: {other:?}"),
+    }
+    // Shift moved to the new connection instead of being released.
+    assert!(
+        loopback
+            .server
+            .log
+            .recv_timeout(Duration::from_millis(200))
+            .is_err()
+    );
+
+    let release = EventType::KeyRelease(Key::ShiftLeft);
+    runtime.block_on(async {
+        let mut send = open_uni(resumed.connection.clone()).await.unwrap();
+        send_data(&mut send, &encode(&release)).await.unwrap();
+        send.finish().unwrap();
+    });
+    assert_eq!(loopback.next_frame(), Frame::Event(release));
+
+    // The old connection's worker stops once it sees it was superseded.
+    let _ = loopback.sender.send(QuicCommand::Shutdown);
+    runtime
+        .block_on(close_client(
+            resumed.link(),
+            resumed.endpoint,
+            CloseCode::UserDisconnect,
+        ))
+        .expect("client failed to close");
+    assert!(
+        loopback
+            .server
+            .log
+            .recv_timeout(Duration::from_millis(200))
+            .is_err()
+    );
+}
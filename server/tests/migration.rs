@@ -0,0 +1,63 @@
+//! A client that changes address mid-connection, as a laptop moving from
+//! Wi-Fi to Ethernet does, keeps its session.
+
+mod common;
+
+use std::sync::{Arc, mpsc::Receiver};
+
+use client::quic::{ClientSession, open_uni, quic_runtime, rebind_endpoint, send_data};
+use rdev::{EventType, Key};
+use server::{displays::FakeDisplays, framing::Frame};
+use shared::DisplayInfo;
+
+use common::{TestServer, WAIT, disconnect};
+
+fn start() -> (TestServer, ClientSession) {
+    let server = TestServer::start_with(|options| {
+        options.with_displays(Arc::new(FakeDisplays(vec![DisplayInfo {
+            name: "fake-0".to_string(),
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+            is_primary: true,
+        }])))
+    });
+    let session = server.connect();
+    (server, session)
+}
+
+fn tap(session: &ClientSession, key: Key) {
+    quic_runtime().block_on(async {
+        let mut send = open_uni(session.connection.clone()).await.unwrap();
+        for event in [EventType::KeyPress(key), EventType::KeyRelease(key)] {
+            send_data(&mut send, &rmp_serde::to_vec(&event).unwrap())
+                .await
+                .unwrap();
+        }
+        send.finish().unwrap();
+    });
+}
+
+fn expect_tap(log: &Receiver<Frame>, key: Key) {
+    for event in [EventType::KeyPress(key), EventType::KeyRelease(key)] {
+        assert_eq!(log.recv_timeout(WAIT), Ok(Frame::Event(event)));
+    }
+}
+
+#[test]
+fn the_session_survives_a_new_local_address() {
+    let (server, session) = start();
+    tap(&session, Key::KeyA);
+    expect_tap(&server.log, Key::KeyA);
+
+    let before = session.endpoint.local_addr().unwrap();
+    let after = rebind_endpoint(&session.endpoint).expect("failed to rebind");
+    assert_ne!(before, after);
+
+    tap(&session, Key::KeyB);
+    expect_tap(&server.log, Key::KeyB);
+    assert!(session.connection.close_reason().is_none());
+
+    disconnect(session);
+}
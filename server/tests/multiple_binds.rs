@@ -0,0 +1,85 @@
+//! One server listening on several addresses feeds every connection into
+//! the same injector, under one connection limit.
+
+mod common;
+
+use std::{net::SocketAddr, sync::Arc};
+
+use client::quic::{
+    ClientOptions, ClientSession, ConnectError, quic_runtime, run_client, send_data,
+};
+use rdev::{EventType, Key};
+use server::{displays::FakeDisplays, framing::Frame};
+use shared::CloseCode;
+
+use common::{TestServer, WAIT, free_loopback_addr};
+
+/// A dry-run server bound to two addresses, and those addresses.
+fn start(max_connections: u8) -> (TestServer, [SocketAddr; 2]) {
+    let second = free_loopback_addr();
+    let server = TestServer::start_with(|mut options| {
+        options.binds.push(second);
+        options
+            .with_max_connections(max_connections)
+            .with_displays(Arc::new(FakeDisplays::default()))
+    });
+    assert_ne!(server.addr, second);
+    let binds = [server.addr, second];
+    (server, binds)
+}
+
+fn connect(addr: SocketAddr) -> Result<ClientSession, ConnectError> {
+    quic_runtime().block_on(run_client(ClientOptions::new(addr), None, false))
+}
+
+fn press(session: &ClientSession, key: Key) {
+    let bytes = rmp_serde::to_vec(&EventType::KeyPress(key)).unwrap();
+    quic_runtime()
+        .block_on(async {
+            let mut send = session.link().open_uni().await?;
+            send_data(&mut send, &bytes).await?;
+            send.finish()?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+        })
+        .expect("failed to send a key");
+}
+
+#[test]
+fn both_endpoints_accept_into_the_same_handler() {
+    let (server, binds) = start(2);
+    let first = connect(binds[0]).expect("client on the first address failed");
+    let second = connect(binds[1]).expect("client on the second address failed");
+    assert_eq!(first.connection.remote_address(), binds[0]);
+    assert_eq!(second.connection.remote_address(), binds[1]);
+
+    press(&first, Key::KeyA);
+    press(&second, Key::KeyB);
+    let mut pressed: Vec<Key> = Vec::new();
+    while pressed.len() < 2 {
+        let frame = server
+            .log
+            .recv_timeout(WAIT)
+            .expect("the server dropped a key");
+        if let Frame::Event(EventType::KeyPress(key)) = frame {
+            pressed.push(key);
+        }
+    }
+    pressed.sort_by_key(|key| format!("{key:?}"));
+    assert_eq!(pressed, [Key::KeyA, Key::KeyB]);
+}
+
+#[test]
+fn the_connection_limit_covers_every_endpoint() {
+    let (_server, binds) = start(1);
+    let _first = connect(binds[0]).expect("client on the first address failed");
+    match connect(binds[1]) {
+        Err(ConnectError::Refused(quinn::ConnectionError::ApplicationClosed(close))) => {
+            assert_eq!(
+                CloseCode::try_from(close.error_code),
+                Ok(CloseCode::ServerFull)
+            );
+        }
+        Err(err) => panic!("expected the server to be full, got {err}"),
+        Ok(_) => panic!("the second address had a limit of its own"),
+    }
+}
@@ -0,0 +1,180 @@
+use rdev::{EventType, Key};
+use server::{
+    config::QUICInputConfig,
+    orientation::{Orientation, Rotation, transform_absolute, transform_delta},
+    server::StreamOptions,
+    testing::{InputMessage, Simulated, simulate},
+};
+use shared::{AbsoluteMove, DisplaySize, MouseMove};
+
+const FLIPS: [(bool, bool); 4] = [(false, false), (true, false), (false, true), (true, true)];
+
+/// Where a move one pixel right, and one pixel down, end up.
+fn right_and_down(orientation: Orientation) -> ((f64, f64), (f64, f64)) {
+    (
+        transform_delta(1.0, 0.0, orientation),
+        transform_delta(0.0, 1.0, orientation),
+    )
+}
+
+#[test]
+fn moves_follow_the_panel_as_the_user_sees_it() {
+    // Turned a quarter clockwise, the picture's +x points down at the
+    // user and its +y points to their left.
+    let ((right_x, right_y), (down_x, down_y)) =
+        right_and_down(Orientation::new(Rotation::Quarter));
+    assert_eq!(right_x, 0.0);
+    assert!(right_y < 0.0, "moving right went down the picture's y");
+    assert!(down_x > 0.0, "moving down went back along the picture's x");
+    assert_eq!(down_y, 0.0);
+
+    // Turned a quarter the other way, +x points up and +y to the right.
+    let ((right_x, right_y), (down_x, down_y)) =
+        right_and_down(Orientation::new(Rotation::ThreeQuarters));
+    assert_eq!(right_x, 0.0);
+    assert!(right_y > 0.0, "moving right went up the picture's y");
+    assert!(down_x < 0.0, "moving down went along the picture's x");
+    assert_eq!(down_y, 0.0);
+
+    // Upside down, everything is reversed.
+    assert_eq!(
+        right_and_down(Orientation::new(Rotation::Half)),
+        ((-1.0, 0.0), (0.0, -1.0))
+    );
+}
+
+#[test]
+fn flips_mirror_after_rotating() {
+    for rotation in Rotation::ALL {
+        let (right, down) = right_and_down(Orientation::new(rotation));
+        for (flip_x, flip_y) in FLIPS {
+            let orientation = Orientation::new(rotation).with_flips(flip_x, flip_y);
+            let mirror = |(dx, dy): (f64, f64)| {
+                (if flip_x { -dx } else { dx }, if flip_y { -dy } else { dy })
+            };
+            assert_eq!(
+                right_and_down(orientation),
+                (mirror(right), mirror(down)),
+                "{orientation}"
+            );
+        }
+    }
+}
+
+#[test]
+fn every_orientation_keeps_the_length_of_a_move() {
+    for rotation in Rotation::ALL {
+        for (flip_x, flip_y) in FLIPS {
+            let orientation = Orientation::new(rotation).with_flips(flip_x, flip_y);
+            let (dx, dy) = transform_delta(3.0, -4.0, orientation);
+            assert_eq!(dx.hypot(dy), 5.0, "{orientation}");
+        }
+    }
+}
+
+#[test]
+fn absolute_positions_turn_the_same_way_as_moves() {
+    let start = AbsoluteMove { x: 0.25, y: 0.5 };
+    for rotation in Rotation::ALL {
+        for (flip_x, flip_y) in FLIPS {
+            let orientation = Orientation::new(rotation).with_flips(flip_x, flip_y);
+            let from = transform_absolute(start, orientation);
+            let to = transform_absolute(AbsoluteMove { x: 0.5, y: 0.25 }, orientation);
+            let (dx, dy) = transform_delta(0.25, -0.25, orientation);
+            assert_eq!((to.x - from.x, to.y - from.y), (dx, dy), "{orientation}");
+        }
+    }
+}
+
+#[test]
+fn corners_stay_on_the_display() {
+    let top_left = AbsoluteMove { x: 0.0, y: 0.0 };
+    let cases = [
+        (Orientation::new(Rotation::None), (0.0, 0.0)),
+        (Orientation::new(Rotation::Quarter), (0.0, 1.0)),
+        (Orientation::new(Rotation::Half), (1.0, 1.0)),
+        (Orientation::new(Rotation::ThreeQuarters), (1.0, 0.0)),
+        (Orientation::default().with_flips(true, false), (1.0, 0.0)),
+        (Orientation::default().with_flips(false, true), (0.0, 1.0)),
+        (
+            Orientation::new(Rotation::Quarter).with_flips(true, true),
+            (1.0, 0.0),
+        ),
+    ];
+    for (orientation, (x, y)) in cases {
+        assert_eq!(
+            transform_absolute(top_left, orientation),
+            AbsoluteMove { x, y },
+            "{orientation}"
+        );
+    }
+}
+
+#[test]
+fn sideways_displays_swap_width_and_height() {
+    let size = DisplaySize {
+        width: 1920,
+        height: 1080,
+    };
+    let swapped = DisplaySize {
+        width: 1080,
+        height: 1920,
+    };
+    assert_eq!(
+        Orientation::new(Rotation::Quarter).display_size(size),
+        swapped
+    );
+    assert_eq!(
+        Orientation::new(Rotation::ThreeQuarters).display_size(size),
+        swapped
+    );
+    assert_eq!(Orientation::new(Rotation::Half).display_size(size), size);
+}
+
+#[test]
+fn config_reads_rotation_in_degrees() {
+    let config: QUICInputConfig = toml::from_str(
+        r#"
+        [orientation]
+        rotation = 270
+        flip_y = true
+        "#,
+    )
+    .expect("failed to parse config");
+    assert_eq!(
+        config.orientation,
+        Orientation::new(Rotation::ThreeQuarters).with_flips(false, true)
+    );
+    assert!(QUICInputConfig::default().orientation.is_identity());
+
+    let error = toml::from_str::<QUICInputConfig>("[orientation]\nrotation = 45\n")
+        .expect_err("45 is not a rotation");
+    assert!(error.to_string().contains("0, 90, 180 or 270"));
+
+    let written = toml::to_string(&config).expect("failed to serialise config");
+    assert!(written.contains("rotation = 270"));
+}
+
+#[test]
+fn relative_moves_are_turned_before_scaling() {
+    let simulated = simulate(
+        &[
+            InputMessage::Mouse(MouseMove { dx: 2.0, dy: 1.0 }),
+            InputMessage::Event(EventType::KeyPress(Key::KeyA)),
+            InputMessage::Event(EventType::KeyRelease(Key::KeyA)),
+        ],
+        StreamOptions {
+            pointer_sensitivity: 2.0,
+            orientation: Orientation::new(Rotation::Quarter),
+            ..StreamOptions::default()
+        },
+    );
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Pointer(MouseMove { dx: 2.0, dy: -4.0 }),
+            Simulated::Keyboard(EventType::KeyPress(Key::KeyA)),
+            Simulated::Keyboard(EventType::KeyRelease(Key::KeyA)),
+        ]
+    );
+}
@@ -0,0 +1,121 @@
+use rdev::EventType;
+use server::{
+    bounds::{BoundedPointer, PointerBounds},
+    cli::parse_args,
+    framing::Frame,
+    inject::Injector,
+};
+use shared::MouseMove;
+
+const BOUNDS: PointerBounds = PointerBounds {
+    x: 100,
+    y: 50,
+    width: 201,
+    height: 101,
+};
+
+#[test]
+fn points_inside_are_left_alone() {
+    assert_eq!(BOUNDS.clamp(150.0, 75.0), (150.0, 75.0));
+    assert_eq!(BOUNDS.clamp(100.0, 50.0), (100.0, 50.0));
+    assert_eq!(BOUNDS.clamp(300.0, 150.0), (300.0, 150.0));
+}
+
+#[test]
+fn each_edge_clamps_its_own_axis() {
+    assert_eq!(BOUNDS.clamp(20.0, 75.0), (100.0, 75.0), "left");
+    assert_eq!(BOUNDS.clamp(301.0, 75.0), (300.0, 75.0), "right");
+    assert_eq!(BOUNDS.clamp(150.0, -10.0), (150.0, 50.0), "top");
+    assert_eq!(BOUNDS.clamp(150.0, 151.0), (150.0, 150.0), "bottom");
+}
+
+#[test]
+fn each_corner_clamps_both_axes() {
+    assert_eq!(BOUNDS.clamp(0.0, 0.0), (100.0, 50.0), "top left");
+    assert_eq!(BOUNDS.clamp(900.0, 0.0), (300.0, 50.0), "top right");
+    assert_eq!(BOUNDS.clamp(0.0, 900.0), (100.0, 150.0), "bottom left");
+    assert_eq!(BOUNDS.clamp(900.0, 900.0), (300.0, 150.0), "bottom right");
+}
+
+#[test]
+fn relative_moves_stop_at_the_edges() {
+    let mut pointer = BoundedPointer::new(BOUNDS);
+    assert_eq!(pointer.position(), (200.5, 100.5));
+
+    assert_eq!(
+        pointer.move_by(MouseMove { dx: 500.0, dy: 0.0 }),
+        MouseMove { dx: 99.5, dy: 0.0 }
+    );
+    assert_eq!(pointer.position(), (300.0, 100.5));
+    // Pressed against the edge, only the other axis moves.
+    assert_eq!(
+        pointer.move_by(MouseMove { dx: 5.0, dy: -5.0 }),
+        MouseMove { dx: 0.0, dy: -5.0 }
+    );
+    assert_eq!(
+        pointer.move_by(MouseMove {
+            dx: -1000.0,
+            dy: -1000.0
+        }),
+        MouseMove {
+            dx: -200.0,
+            dy: -45.5
+        }
+    );
+    assert_eq!(pointer.position(), (100.0, 50.0));
+}
+
+#[test]
+fn absolute_positions_are_clamped_and_tracked() {
+    let mut pointer = BoundedPointer::new(BOUNDS);
+    assert_eq!(pointer.move_to(1000.0, 60.0), (300.0, 60.0));
+    assert_eq!(
+        pointer.move_by(MouseMove { dx: -10.0, dy: 0.0 }),
+        MouseMove { dx: -10.0, dy: 0.0 }
+    );
+    assert_eq!(pointer.position(), (290.0, 60.0));
+}
+
+#[test]
+fn the_injector_applies_the_bounds() {
+    let (injector, log) = Injector::capture();
+    let injector = injector.with_pointer_bounds(BOUNDS);
+
+    injector.mouse_move(MouseMove { dx: 0.0, dy: 100.0 });
+    assert_eq!(
+        log.try_recv(),
+        Ok(Frame::Mouse(MouseMove { dx: 0.0, dy: 49.5 }))
+    );
+
+    injector.absolute_move(0.0, 0.0);
+    assert_eq!(
+        log.try_recv(),
+        Ok(Frame::Event(EventType::MouseMove { x: 100.0, y: 50.0 }))
+    );
+}
+
+#[test]
+fn bounds_parse_from_the_command_line() {
+    let args = parse_args([
+        "--pointer-bounds".to_string(),
+        "-1920, 0, 1920, 1080".to_string(),
+    ])
+    .expect("bounds should parse");
+    assert_eq!(
+        args.pointer_bounds,
+        Some(PointerBounds {
+            x: -1920,
+            y: 0,
+            width: 1920,
+            height: 1080
+        })
+    );
+
+    for invalid in ["0,0,1920", "0,0,0,1080", "a,0,10,10", "0,0,-5,10"] {
+        assert!(
+            invalid.parse::<PointerBounds>().is_err(),
+            "{invalid} should be refused"
+        );
+    }
+    assert!(parse_args(["--pointer-bounds".to_string()]).is_err());
+}
@@ -0,0 +1,333 @@
+//! Absolute pointer mode: when the server grants it, and what it does with
+//! absolute positions once granted, including across aspect ratios.
+
+mod common;
+
+use std::sync::Arc;
+
+use client::{
+    pointer_mode::{request_display_size, request_pointer_mode},
+    quic::{open_uni, quic_runtime, send_data},
+    warp::warp_pointer,
+};
+use rdev::EventType;
+use server::{
+    config::QUICInputConfig, displays::FakeDisplays, framing::Frame, mapping::AbsoluteMapping,
+    server::absolute_target,
+};
+use shared::{AbsoluteMove, DisplayInfo, DisplaySize, MouseMove, PointerMode};
+
+use common::{TestServer, WAIT, disconnect};
+
+fn display(name: &str, x: i32, is_primary: bool) -> DisplayInfo {
+    DisplayInfo {
+        name: name.to_string(),
+        x,
+        y: 0,
+        width: 1001,
+        height: 501,
+        is_primary,
+    }
+}
+
+#[test]
+fn absolute_is_granted_only_with_the_capability_and_a_display() {
+    let displays = vec![display("left", 0, false), display("main", 1001, true)];
+    assert_eq!(
+        absolute_target(true, &displays),
+        Some(display("main", 1001, true))
+    );
+    assert_eq!(absolute_target(false, &displays), None);
+    assert_eq!(absolute_target(true, &[]), None);
+}
+
+#[test]
+fn positions_map_onto_the_display_and_back() {
+    let main = display("main", 1001, true);
+    assert_eq!(
+        main.point_at(AbsoluteMove { x: 0.5, y: 1.0 }),
+        (1501.0, 500.0)
+    );
+    assert_eq!(
+        main.fraction_at(1501.0, 500.0),
+        AbsoluteMove { x: 0.5, y: 1.0 }
+    );
+    // Off the display clamps to its edge.
+    assert_eq!(
+        main.fraction_at(0.0, -40.0),
+        AbsoluteMove { x: 0.0, y: 0.0 }
+    );
+}
+
+/// A square client display, next to the 2:1 displays above.
+const SQUARE: DisplaySize = DisplaySize {
+    width: 401,
+    height: 401,
+};
+
+fn at(x: f64, y: f64) -> AbsoluteMove {
+    AbsoluteMove { x, y }
+}
+
+#[test]
+fn stretch_fills_the_display_whatever_the_client() {
+    let main = display("main", 0, true);
+    let stretch = AbsoluteMapping::Stretch;
+    assert_eq!(
+        stretch.place(&main, Some(SQUARE), at(0.5, 0.5)),
+        (500.0, 250.0)
+    );
+    assert_eq!(
+        stretch.place(&main, Some(SQUARE), at(1.0, 1.0)),
+        (1000.0, 500.0)
+    );
+    assert_eq!(AbsoluteMapping::default(), stretch);
+}
+
+#[test]
+fn letterbox_keeps_the_client_aspect_ratio_centred() {
+    let main = display("main", 0, true);
+    let letterbox = AbsoluteMapping::Letterbox;
+    // A 500x500 area with 250 pixel bars either side.
+    assert_eq!(
+        letterbox.place(&main, Some(SQUARE), at(0.0, 0.0)),
+        (250.0, 0.0)
+    );
+    assert_eq!(
+        letterbox.place(&main, Some(SQUARE), at(0.5, 0.25)),
+        (500.0, 125.0)
+    );
+    assert_eq!(
+        letterbox.place(&main, Some(SQUARE), at(1.0, 1.0)),
+        (750.0, 500.0)
+    );
+    // The same on a display away from the origin.
+    let right = display("right", 1001, false);
+    assert_eq!(
+        letterbox.place(&right, Some(SQUARE), at(0.0, 0.0)),
+        (1251.0, 0.0)
+    );
+}
+
+#[test]
+fn clamp_moves_pixel_for_pixel_and_stays_on_screen() {
+    let main = display("main", 0, true);
+    let clamp = AbsoluteMapping::Clamp;
+    // A smaller client is centred: 300 and 50 pixels in.
+    assert_eq!(
+        clamp.place(&main, Some(SQUARE), at(0.0, 0.0)),
+        (300.0, 50.0)
+    );
+    assert_eq!(
+        clamp.place(&main, Some(SQUARE), at(1.0, 1.0)),
+        (700.0, 450.0)
+    );
+
+    // A larger one overhangs every edge and is clamped there.
+    let large = DisplaySize {
+        width: 2001,
+        height: 1001,
+    };
+    assert_eq!(clamp.place(&main, Some(large), at(0.3, 0.3)), (100.0, 50.0));
+    assert_eq!(
+        clamp.place(&main, Some(large), at(0.5, 0.5)),
+        (500.0, 250.0)
+    );
+    assert_eq!(clamp.place(&main, Some(large), at(0.0, 1.0)), (0.0, 500.0));
+}
+
+#[test]
+fn without_the_client_size_every_mapping_stretches() {
+    let main = display("main", 0, true);
+    for mapping in [
+        AbsoluteMapping::Stretch,
+        AbsoluteMapping::Letterbox,
+        AbsoluteMapping::Clamp,
+    ] {
+        assert_eq!(mapping.place(&main, None, at(0.25, 0.5)), (250.0, 250.0));
+    }
+}
+
+#[test]
+fn the_mapping_is_read_from_the_config() {
+    let config: QUICInputConfig =
+        toml::from_str("absolute_mapping = \"letterbox\"").expect("config should parse");
+    assert_eq!(config.absolute_mapping, AbsoluteMapping::Letterbox);
+    assert_eq!(
+        QUICInputConfig::default().absolute_mapping,
+        AbsoluteMapping::Stretch
+    );
+    assert!(toml::from_str::<QUICInputConfig>("absolute_mapping = \"zoom\"").is_err());
+}
+
+#[test]
+fn absolute_moves_apply_only_once_granted() {
+    let runtime = quic_runtime();
+    let server = TestServer::start_with(|options| {
+        options.with_displays(Arc::new(FakeDisplays(vec![display("main", 0, true)])))
+    });
+    let session = server.connect();
+
+    let moved = MouseMove { dx: 1.0, dy: 1.0 };
+    let absolute = AbsoluteMove { x: 0.25, y: 0.5 };
+    let send = |buf: Vec<u8>| {
+        let connection = session.connection.clone();
+        runtime.block_on(async move {
+            let mut stream = open_uni(connection).await.expect("failed to open stream");
+            send_data(&mut stream, &buf).await.expect("failed to send");
+            stream.finish().expect("failed to finish stream");
+        });
+    };
+
+    // Still relative: the position is ignored, the marker move applied.
+    let mut buf = rmp_serde::to_vec(&absolute).expect("failed to serialise");
+    buf.extend(rmp_serde::to_vec(&moved).expect("failed to serialise"));
+    send(buf);
+    assert_eq!(server.log.recv_timeout(WAIT), Ok(Frame::Mouse(moved)));
+
+    let granted = runtime
+        .block_on(request_pointer_mode(
+            &session.connection,
+            PointerMode::Absolute,
+        ))
+        .expect("pointer mode request failed");
+    assert_eq!(granted, PointerMode::Absolute);
+
+    send(rmp_serde::to_vec(&absolute).expect("failed to serialise"));
+    assert_eq!(
+        server.log.recv_timeout(WAIT),
+        Ok(Frame::Event(EventType::MouseMove { x: 250.0, y: 250.0 }))
+    );
+
+    disconnect(session);
+}
+
+#[test]
+fn the_client_size_from_its_hello_shapes_absolute_moves() {
+    let runtime = quic_runtime();
+    let server = TestServer::start_with(|options| {
+        options
+            .with_displays(Arc::new(FakeDisplays(vec![display("main", 0, true)])))
+            .with_absolute_mapping(AbsoluteMapping::Letterbox)
+    });
+    let session = server.connect_with(|options| options.with_display_size(SQUARE));
+
+    let granted = runtime
+        .block_on(request_pointer_mode(
+            &session.connection,
+            PointerMode::Absolute,
+        ))
+        .expect("pointer mode request failed");
+    assert_eq!(granted, PointerMode::Absolute);
+
+    let connection = session.connection.clone();
+    runtime.block_on(async move {
+        let mut stream = open_uni(connection).await.expect("failed to open stream");
+        let buf = rmp_serde::to_vec(&at(0.0, 0.5)).expect("failed to serialise");
+        send_data(&mut stream, &buf).await.expect("failed to send");
+        stream.finish().expect("failed to finish stream");
+    });
+    assert_eq!(
+        server.log.recv_timeout(WAIT),
+        Ok(Frame::Event(EventType::MouseMove { x: 250.0, y: 250.0 }))
+    );
+
+    disconnect(session);
+}
+
+#[test]
+fn a_client_size_sent_later_replaces_the_one_in_its_hello() {
+    let runtime = quic_runtime();
+    let server = TestServer::start_with(|options| {
+        options
+            .with_displays(Arc::new(FakeDisplays(vec![display("main", 0, true)])))
+            .with_absolute_mapping(AbsoluteMapping::Letterbox)
+    });
+    let session = server.connect_with(|options| {
+        options.with_display_size(DisplaySize {
+            width: 1920,
+            height: 1080,
+        })
+    });
+
+    runtime
+        .block_on(request_display_size(&session.connection, SQUARE))
+        .expect("display size request failed");
+    let granted = runtime
+        .block_on(request_pointer_mode(
+            &session.connection,
+            PointerMode::Absolute,
+        ))
+        .expect("pointer mode request failed");
+    assert_eq!(granted, PointerMode::Absolute);
+
+    let connection = session.connection.clone();
+    runtime.block_on(async move {
+        let mut stream = open_uni(connection).await.expect("failed to open stream");
+        let buf = rmp_serde::to_vec(&at(0.0, 0.5)).expect("failed to serialise");
+        send_data(&mut stream, &buf).await.expect("failed to send");
+        stream.finish().expect("failed to finish stream");
+    });
+    assert_eq!(
+        server.log.recv_timeout(WAIT),
+        Ok(Frame::Event(EventType::MouseMove { x: 250.0, y: 250.0 }))
+    );
+
+    disconnect(session);
+}
+
+#[test]
+fn a_warp_lands_on_the_server_display_in_relative_mode() {
+    let runtime = quic_runtime();
+    let server = TestServer::start_with(|options| {
+        options
+            .with_displays(Arc::new(FakeDisplays(vec![display("main", 0, true)])))
+            .with_absolute_mapping(AbsoluteMapping::Letterbox)
+    });
+    let session = server.connect_with(|options| options.with_display_size(SQUARE));
+
+    // The square client would be letterboxed to x = 250 if this were mapped.
+    runtime
+        .block_on(warp_pointer(&session.connection, at(0.0, 0.5)))
+        .expect("warp failed");
+    assert_eq!(
+        server.log.recv_timeout(WAIT),
+        Ok(Frame::Event(EventType::MouseMove { x: 0.0, y: 250.0 }))
+    );
+
+    disconnect(session);
+}
+
+#[test]
+fn a_warp_is_refused_without_a_display() {
+    let runtime = quic_runtime();
+    let server =
+        TestServer::start_with(|options| options.with_displays(Arc::new(FakeDisplays::default())));
+    let session = server.connect();
+
+    assert!(
+        runtime
+            .block_on(warp_pointer(&session.connection, at(0.5, 0.5)))
+            .is_err()
+    );
+
+    disconnect(session);
+}
+
+#[test]
+fn absolute_is_refused_without_a_display() {
+    let runtime = quic_runtime();
+    let server =
+        TestServer::start_with(|options| options.with_displays(Arc::new(FakeDisplays::default())));
+    let session = server.connect();
+
+    let granted = runtime
+        .block_on(request_pointer_mode(
+            &session.connection,
+            PointerMode::Absolute,
+        ))
+        .expect("pointer mode request failed");
+    assert_eq!(granted, PointerMode::Relative);
+
+    disconnect(session);
+}
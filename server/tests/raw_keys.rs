@@ -0,0 +1,88 @@
+//! Keys sent by Linux input event code, bypassing rdev's key names.
+
+use server::{
+    framing::{Frame, FrameDecoder},
+    server::StreamOptions,
+    testing::{InputMessage, Simulated, simulate},
+};
+use shared::{MouseMove, raw_keys::RawKeyInput};
+
+fn raw(code: u16, pressed: bool) -> RawKeyInput {
+    RawKeyInput { code, pressed }
+}
+
+fn message(input: RawKeyInput) -> InputMessage {
+    InputMessage::Raw(rmp_serde::to_vec(&input).unwrap())
+}
+
+#[test]
+fn raw_keys_decode_apart_from_other_frames() {
+    let mut decoder = FrameDecoder::new(1024);
+    let moved = MouseMove { dx: 1.0, dy: 2.0 };
+    decoder.push(&rmp_serde::to_vec(&raw(240, true)).unwrap());
+    decoder.push(&rmp_serde::to_vec(&moved).unwrap());
+
+    assert_eq!(decoder.next_frame(), Some(Frame::RawKey(raw(240, true))));
+    assert_eq!(decoder.next_frame(), Some(Frame::Mouse(moved)));
+    assert_eq!(decoder.next_frame(), None);
+}
+
+#[test]
+fn raw_keys_are_emitted_by_code() {
+    let simulated = simulate(
+        &[message(raw(240, true)), message(raw(240, false))],
+        StreamOptions::default(),
+    );
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::RawKey(raw(240, true)),
+            Simulated::RawKey(raw(240, false)),
+        ]
+    );
+}
+
+#[test]
+fn codes_that_are_not_keys_are_dropped() {
+    let simulated = simulate(
+        &[
+            message(raw(0, true)),
+            message(raw(0x110, true)),
+            message(raw(0x300, true)),
+        ],
+        StreamOptions::default(),
+    );
+    assert_eq!(simulated, vec![]);
+}
+
+#[test]
+fn a_raw_key_left_held_is_released_when_the_stream_ends() {
+    let simulated = simulate(&[message(raw(240, true))], StreamOptions::default());
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::RawKey(raw(240, true)),
+            Simulated::RawKey(raw(240, false)),
+        ]
+    );
+}
+
+#[test]
+fn held_raw_keys_are_released_in_reverse_press_order() {
+    let simulated = simulate(
+        &[
+            message(raw(240, true)),
+            message(raw(241, true)),
+            message(raw(242, true)),
+            message(raw(241, false)),
+        ],
+        StreamOptions::default(),
+    );
+    assert_eq!(
+        simulated[4..],
+        [
+            Simulated::RawKey(raw(242, false)),
+            Simulated::RawKey(raw(240, false)),
+        ]
+    );
+}
@@ -0,0 +1,333 @@
+//! Losing the network mid-session, through a UDP relay that can be cut:
+//! the client holds input while it is gone and resumes its session after,
+//! and the server neither leaves a key held nor releases one twice.
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use client::{
+    outbox::{Outbox, OutboxOptions},
+    quic::{ClientOptions, ClientSession, ConnectError, quic_runtime, run_client},
+    quic_helper_thread::{QuicCommand, QuicSender, StreamLayout, spawn_quic_helper},
+};
+use rdev::{EventType, Key};
+use server::{displays::FakeDisplays, framing::Frame, transport::ServerTransportOptions};
+use shared::{CloseCode, SessionToken};
+use tokio::{net::UdpSocket as AsyncUdpSocket, task::JoinHandle};
+
+use common::{TestServer, WAIT, disconnect};
+
+/// Short, so a cut link is noticed by both ends within the test.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+const KEEP_ALIVE: Duration = Duration::from_millis(200);
+
+/// Relays datagrams between clients and the server until cut. Each client
+/// address gets its own upstream socket, so a reconnect from a new port is
+/// a new flow, as it would be through a NAT.
+struct LossyLink {
+    addr: SocketAddr,
+    cut: Arc<AtomicBool>,
+    task: JoinHandle<()>,
+}
+
+impl LossyLink {
+    fn start(server: SocketAddr) -> Self {
+        let front = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("failed to bind relay");
+        front.set_nonblocking(true).unwrap();
+        let addr = front.local_addr().unwrap();
+        let cut = Arc::new(AtomicBool::new(false));
+        let task = quic_runtime().spawn(relay(front, server, Arc::clone(&cut)));
+        Self { addr, cut, task }
+    }
+
+    /// Drops everything in both directions from now on.
+    fn cut(&self) {
+        self.cut.store(true, Ordering::SeqCst);
+    }
+
+    fn restore(&self) {
+        self.cut.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Drop for LossyLink {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn relay(front: UdpSocket, server: SocketAddr, cut: Arc<AtomicBool>) {
+    let front = Arc::new(AsyncUdpSocket::from_std(front).unwrap());
+    let mut upstreams: HashMap<SocketAddr, Arc<AsyncUdpSocket>> = HashMap::new();
+    let mut buf = vec![0; 65_536];
+    loop {
+        let Ok((len, client)) = front.recv_from(&mut buf).await else {
+            return;
+        };
+        if cut.load(Ordering::SeqCst) {
+            continue;
+        }
+        let upstream = match upstreams.get(&client) {
+            Some(upstream) => Arc::clone(upstream),
+            None => {
+                let upstream = Arc::new(
+                    AsyncUdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+                        .await
+                        .unwrap(),
+                );
+                tokio::spawn(relay_back(
+                    Arc::clone(&upstream),
+                    Arc::clone(&front),
+                    client,
+                    Arc::clone(&cut),
+                ));
+                upstreams.insert(client, Arc::clone(&upstream));
+                upstream
+            }
+        };
+        let _ = upstream.send_to(&buf[..len], server).await;
+    }
+}
+
+async fn relay_back(
+    upstream: Arc<AsyncUdpSocket>,
+    front: Arc<AsyncUdpSocket>,
+    client: SocketAddr,
+    cut: Arc<AtomicBool>,
+) {
+    let mut buf = vec![0; 65_536];
+    while let Ok(len) = upstream.recv(&mut buf).await {
+        if !cut.load(Ordering::SeqCst) {
+            let _ = front.send_to(&buf[..len], client).await;
+        }
+    }
+}
+
+struct Session {
+    server: TestServer,
+    link: LossyLink,
+}
+
+impl Session {
+    fn start(resume_grace: Duration) -> Self {
+        let server = TestServer::start_with(|options| {
+            options
+                .with_resume_grace(resume_grace)
+                .with_transport(ServerTransportOptions {
+                    max_idle_timeout: Some(IDLE_TIMEOUT),
+                    ..ServerTransportOptions::default()
+                })
+                .with_displays(Arc::new(FakeDisplays::default()))
+        });
+        let link = LossyLink::start(server.addr);
+        Self { server, link }
+    }
+
+    fn connect(&self, resume: Option<SessionToken>) -> ClientSession {
+        quic_runtime()
+            .block_on(run_client(
+                ClientOptions::new(self.link.addr).with_keep_alive(Some(KEEP_ALIVE)),
+                resume,
+                false,
+            ))
+            .expect("client failed to connect")
+    }
+
+    fn next_frame(&self) -> Frame {
+        self.server.next_frame()
+    }
+
+    fn assert_quiet(&self) {
+        if let Ok(frame) = self.server.log.recv_timeout(Duration::from_millis(300)) {
+            panic!("unexpected {frame:?}");
+        }
+    }
+}
+
+fn key(event: EventType) -> QuicCommand {
+    QuicCommand::Keyboard(rmp_serde::to_vec(&event).unwrap())
+}
+
+fn wait_until_closed(session: &ClientSession) {
+    let closed = quic_runtime()
+        .block_on(async { tokio::time::timeout(WAIT, session.connection.closed()).await });
+    assert!(closed.is_ok(), "the client never noticed the cut link");
+}
+
+/// Waits for the send worker behind `sender` to stop taking commands, as
+/// it does once its connection has gone.
+fn wait_until_stopped(sender: &QuicSender) {
+    let deadline = Instant::now() + WAIT;
+    while !sender.is_closed() {
+        assert!(
+            Instant::now() < deadline,
+            "the send worker outlived its connection"
+        );
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Keys pressed and not released again, going by what the server applied.
+fn held_keys(frames: &[Frame]) -> Vec<Key> {
+    let mut held = Vec::new();
+    for frame in frames {
+        match frame {
+            Frame::Event(EventType::KeyPress(key)) if !held.contains(key) => held.push(*key),
+            Frame::Event(EventType::KeyRelease(key)) => held.retain(|held| held != key),
+            _ => {}
+        }
+    }
+    held
+}
+
+fn key_events(frames: &[Frame]) -> u64 {
+    frames
+        .iter()
+        .filter(|frame| {
+            matches!(
+                frame,
+                Frame::Event(EventType::KeyPress(_) | EventType::KeyRelease(_))
+            )
+        })
+        .count() as u64
+}
+
+#[test]
+fn input_held_through_an_outage_is_delivered_after_resuming() {
+    let session = Session::start(Duration::from_secs(10));
+    let first = session.connect(None);
+    let token = first.token.expect("server did not issue a session token");
+    let (stats_tx, _stats_rx) = mpsc::channel();
+    let sender = spawn_quic_helper(first.link(), stats_tx.clone(), StreamLayout::Split);
+    let mut outbox = Outbox::new(sender.clone(), OutboxOptions::default());
+
+    let mut frames = Vec::new();
+    for event in [
+        EventType::KeyPress(Key::ShiftLeft),
+        EventType::KeyPress(Key::KeyA),
+    ] {
+        outbox.send(key(event));
+        let frame = session.next_frame();
+        assert_eq!(frame, Frame::Event(event));
+        frames.push(frame);
+    }
+
+    session.link.cut();
+    wait_until_closed(&first);
+    // Released while the link is down: held by the client, and by the
+    // server for the session's grace period.
+    wait_until_stopped(&sender);
+    outbox.send(key(EventType::KeyRelease(Key::KeyA)));
+    outbox.send(key(EventType::KeyRelease(Key::ShiftLeft)));
+    assert!(!outbox.is_connected());
+    assert_eq!(outbox.queued(), 2);
+    session.assert_quiet();
+
+    session.link.restore();
+    let resumed = session.connect(Some(token));
+    assert_eq!(resumed.token, Some(token), "the session was not resumed");
+    let flushed = outbox.reconnect(spawn_quic_helper(
+        resumed.link(),
+        stats_tx,
+        StreamLayout::Split,
+    ));
+    assert_eq!(flushed, 2);
+
+    for expected in [
+        EventType::KeyRelease(Key::KeyA),
+        EventType::KeyRelease(Key::ShiftLeft),
+    ] {
+        let frame = session.next_frame();
+        assert_eq!(frame, Frame::Event(expected));
+        frames.push(frame);
+    }
+
+    outbox.shutdown();
+    disconnect(resumed);
+    // Nothing was left for the server to release on its own.
+    session.assert_quiet();
+    assert_eq!(held_keys(&frames), []);
+    assert_eq!(
+        session.server.injector.stats().keys_injected,
+        key_events(&frames)
+    );
+}
+
+#[test]
+fn keys_held_when_the_client_never_returns_are_released_once() {
+    let session = Session::start(Duration::from_millis(500));
+    let client = session.connect(None);
+    let (stats_tx, _stats_rx) = mpsc::channel();
+    let sender = spawn_quic_helper(client.link(), stats_tx, StreamLayout::Split);
+
+    let press = EventType::KeyPress(Key::ControlLeft);
+    sender.send(key(press)).unwrap();
+    let mut frames = vec![session.next_frame()];
+    assert_eq!(frames[0], Frame::Event(press));
+
+    session.link.cut();
+    wait_until_closed(&client);
+    // Released by the server once the grace period runs out.
+    frames.push(session.next_frame());
+    assert_eq!(
+        frames[1],
+        Frame::Event(EventType::KeyRelease(Key::ControlLeft))
+    );
+
+    session.assert_quiet();
+    assert_eq!(held_keys(&frames), []);
+    assert_eq!(session.server.injector.stats().keys_injected, 2);
+}
+
+#[test]
+fn a_full_server_still_lets_its_client_resume() {
+    // The default of one connection, held by the client that drops.
+    let session = Session::start(Duration::from_secs(10));
+    let first = session.connect(None);
+    let token = first.token.expect("server did not issue a session token");
+
+    let stranger = quic_runtime().block_on(run_client(
+        ClientOptions::new(session.link.addr),
+        None,
+        false,
+    ));
+    match stranger {
+        Err(ConnectError::Refused(quinn::ConnectionError::ApplicationClosed(close))) => {
+            assert_eq!(
+                CloseCode::try_from(close.error_code),
+                Ok(CloseCode::ServerFull)
+            );
+        }
+        Err(err) => panic!("expected the server to be full, got {err}"),
+        Ok(_) => panic!("a second client got in past the limit"),
+    }
+
+    // Reconnecting before the server noticed the old connection dropped
+    // takes its slot over along with the session.
+    let resumed = session.connect(Some(token));
+    assert_eq!(resumed.token, Some(token), "the session was not resumed");
+    wait_until_closed(&first);
+
+    let (stats_tx, _stats_rx) = mpsc::channel();
+    let sender = spawn_quic_helper(resumed.link(), stats_tx, StreamLayout::Split);
+    let press = EventType::KeyPress(Key::KeyA);
+    sender.send(key(press)).unwrap();
+    assert_eq!(session.next_frame(), Frame::Event(press));
+    let release = EventType::KeyRelease(Key::KeyA);
+    sender.send(key(release)).unwrap();
+    assert_eq!(session.next_frame(), Frame::Event(release));
+
+    let _ = sender.send(QuicCommand::Shutdown);
+    disconnect(resumed);
+}
@@ -0,0 +1,110 @@
+//! The received-input recording: its line format, and that it captures
+//! every frame off a stream, whatever happens to it afterwards.
+
+use std::{
+    fs,
+    net::SocketAddr,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+use client::quic::quic_runtime;
+use rdev::{EventType, Key};
+use server::{
+    cli::parse_args,
+    framing::Frame,
+    inject::Injector,
+    recording::{InputRecording, ReceivedFrame, read_recording, record_line},
+};
+use shared::MouseMove;
+
+fn peer() -> SocketAddr {
+    "192.168.1.20:51234".parse().unwrap()
+}
+
+fn scratch_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "quicinput-recording-{name}-{}.jsonl",
+        std::process::id()
+    ))
+}
+
+#[test]
+fn lines_carry_receive_time_peer_and_frame() {
+    let at = UNIX_EPOCH + Duration::from_micros(1_760_000_000_125_000);
+    let frame = Frame::Event(EventType::KeyPress(Key::KeyA));
+    let line = record_line(at, Some(peer()), &frame).unwrap();
+
+    assert_eq!(
+        line,
+        r#"{"received_micros":1760000000125000,"peer":"192.168.1.20:51234","frame":{"Event":{"KeyPress":"KeyA"}}}"#
+    );
+    assert_eq!(
+        read_recording(&line).unwrap(),
+        vec![ReceivedFrame {
+            received_micros: 1_760_000_000_125_000,
+            peer: Some(peer()),
+            frame,
+        }]
+    );
+}
+
+#[test]
+fn frames_that_are_not_input_are_recorded_too() {
+    let at = UNIX_EPOCH;
+    let text = [Frame::SentAt(7), Frame::Unknown(12)]
+        .iter()
+        .map(|frame| record_line(at, None, frame).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let frames: Vec<Frame> = read_recording(&text)
+        .unwrap()
+        .into_iter()
+        .map(|received| received.frame)
+        .collect();
+    assert_eq!(frames, vec![Frame::SentAt(7), Frame::Unknown(12)]);
+}
+
+#[test]
+fn recording_is_off_unless_a_path_is_given() {
+    assert_eq!(parse_args(Vec::<String>::new()).unwrap().record, None);
+    let args = parse_args(["--record".to_string(), "in.jsonl".to_string()]).unwrap();
+    assert_eq!(args.record, Some(PathBuf::from("in.jsonl")));
+    assert!(parse_args(["--record".to_string()]).is_err());
+}
+
+#[test]
+fn recorded_frames_reach_the_file() {
+    let path = scratch_file("file");
+    let recording = quic_runtime()
+        .block_on(async { InputRecording::start(&path) })
+        .expect("failed to start recording");
+    let (injector, _log) = Injector::capture();
+    let injector = injector.with_recording(recording);
+
+    let recording = injector.recording().expect("recording not attached");
+    recording.record(Some(peer()), &Frame::Mouse(MouseMove { dx: 1.0, dy: 2.0 }));
+    recording.record(None, &Frame::Event(EventType::KeyRelease(Key::KeyA)));
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let received = loop {
+        let text = fs::read_to_string(&path).unwrap_or_default();
+        let received = read_recording(&text).unwrap_or_default();
+        if received.len() == 2 || Instant::now() > deadline {
+            break received;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let _ = fs::remove_file(&path);
+
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0].peer, Some(peer()));
+    assert_eq!(
+        received[0].frame,
+        Frame::Mouse(MouseMove { dx: 1.0, dy: 2.0 })
+    );
+    assert_eq!(received[1].peer, None);
+    assert!(received[0].received_micros <= received[1].received_micros);
+}
@@ -0,0 +1,43 @@
+use rdev::EventType;
+use server::mousemove::{Scroll, scroll_axes};
+
+#[test]
+fn vertical_only_scroll_uses_the_vertical_wheel() {
+    assert_eq!(scroll_axes(0, -2), vec![Scroll::Vertical(-2)]);
+}
+
+#[test]
+fn horizontal_only_scroll_uses_the_horizontal_wheel() {
+    assert_eq!(scroll_axes(3, 0), vec![Scroll::Horizontal(3)]);
+}
+
+#[test]
+fn combined_scroll_emits_both_axes() {
+    assert_eq!(
+        scroll_axes(-1, 1),
+        vec![Scroll::Vertical(1), Scroll::Horizontal(-1)]
+    );
+}
+
+#[test]
+fn zero_scroll_emits_nothing() {
+    assert!(scroll_axes(0, 0).is_empty());
+}
+
+#[test]
+fn each_axis_becomes_its_own_wheel_event() {
+    let events: Vec<EventType> = scroll_axes(4, -5).into_iter().map(Scroll::event).collect();
+    assert_eq!(
+        events,
+        vec![
+            EventType::Wheel {
+                delta_x: 0,
+                delta_y: -5,
+            },
+            EventType::Wheel {
+                delta_x: 4,
+                delta_y: 0,
+            },
+        ]
+    );
+}
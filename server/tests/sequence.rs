@@ -0,0 +1,141 @@
+use server::{
+    framing::{Frame, FrameDecoder},
+    sequence::{SeqOutcome, SequenceStats, SequenceTracker},
+    server::StreamOptions,
+    testing::{InputMessage, Simulated, simulate},
+};
+use shared::{MouseMove, Seq, SeqCategory};
+
+fn mouse(seq: u64) -> Seq {
+    Seq {
+        category: SeqCategory::Mouse,
+        seq,
+    }
+}
+
+fn keyboard(seq: u64) -> Seq {
+    Seq {
+        category: SeqCategory::Keyboard,
+        seq,
+    }
+}
+
+#[test]
+fn an_unbroken_sequence_has_no_loss() {
+    let mut tracker = SequenceTracker::default();
+    for seq in 0..5 {
+        assert_eq!(tracker.record(mouse(seq)), SeqOutcome::InOrder);
+    }
+    assert_eq!(
+        tracker.stats(SeqCategory::Mouse),
+        SequenceStats {
+            received: 5,
+            lost: 0,
+            reordered: 0,
+        }
+    );
+    assert_eq!(tracker.irregular(), 0);
+}
+
+#[test]
+fn skipped_numbers_count_as_lost() {
+    let mut tracker = SequenceTracker::default();
+    let outcomes: Vec<_> = [0, 1, 4, 5, 9]
+        .into_iter()
+        .map(|seq| tracker.record(mouse(seq)))
+        .collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            SeqOutcome::InOrder,
+            SeqOutcome::InOrder,
+            SeqOutcome::Gap(2),
+            SeqOutcome::InOrder,
+            SeqOutcome::Gap(3),
+        ]
+    );
+    assert_eq!(tracker.stats(SeqCategory::Mouse).lost, 5);
+    assert_eq!(tracker.irregular(), 2);
+}
+
+#[test]
+fn a_late_arrival_is_reordered_rather_than_lost() {
+    let mut tracker = SequenceTracker::default();
+    for seq in [0, 2, 1, 3] {
+        tracker.record(mouse(seq));
+    }
+    assert_eq!(
+        tracker.stats(SeqCategory::Mouse),
+        SequenceStats {
+            received: 4,
+            lost: 0,
+            reordered: 1,
+        }
+    );
+}
+
+#[test]
+fn a_duplicate_is_not_counted() {
+    let mut tracker = SequenceTracker::default();
+    let outcomes: Vec<_> = [0, 3, 1, 1, 3, 0]
+        .into_iter()
+        .map(|seq| tracker.record(mouse(seq)))
+        .collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            SeqOutcome::InOrder,
+            SeqOutcome::Gap(2),
+            SeqOutcome::Late,
+            SeqOutcome::Duplicate,
+            SeqOutcome::Duplicate,
+            SeqOutcome::Duplicate,
+        ]
+    );
+    // 2 is still missing.
+    assert_eq!(
+        tracker.stats(SeqCategory::Mouse),
+        SequenceStats {
+            received: 3,
+            lost: 1,
+            reordered: 1,
+        }
+    );
+    assert_eq!(tracker.record(mouse(2)), SeqOutcome::Late);
+    assert_eq!(tracker.stats(SeqCategory::Mouse).lost, 0);
+}
+
+#[test]
+fn categories_are_counted_separately() {
+    let mut tracker = SequenceTracker::default();
+    tracker.record(mouse(0));
+    tracker.record(keyboard(0));
+    tracker.record(mouse(1));
+    tracker.record(keyboard(3));
+    assert_eq!(tracker.stats(SeqCategory::Mouse).lost, 0);
+    assert_eq!(tracker.stats(SeqCategory::Keyboard).lost, 2);
+}
+
+#[test]
+fn counting_starts_wherever_the_first_header_does() {
+    // A stream picked up mid-connection shouldn't report everything before it as lost.
+    let mut tracker = SequenceTracker::default();
+    assert_eq!(tracker.record(keyboard(40)), SeqOutcome::InOrder);
+    assert_eq!(tracker.record(keyboard(41)), SeqOutcome::InOrder);
+    assert_eq!(tracker.stats(SeqCategory::Keyboard).lost, 0);
+}
+
+#[test]
+fn headers_decode_on_their_own_and_are_not_injected() {
+    let moved = MouseMove { dx: 1.0, dy: 0.0 };
+    let mut bytes = rmp_serde::to_vec(&mouse(7)).expect("failed to serialise");
+    bytes.extend(rmp_serde::to_vec(&moved).expect("failed to serialise"));
+
+    let mut decoder = FrameDecoder::new(1024);
+    decoder.push(&bytes);
+    assert_eq!(decoder.next_frame(), Some(Frame::Seq(mouse(7))));
+    assert_eq!(decoder.next_frame(), Some(Frame::Mouse(moved)));
+
+    let simulated = simulate(&[InputMessage::Raw(bytes)], StreamOptions::default());
+    assert_eq!(simulated, vec![Simulated::Pointer(moved)]);
+}
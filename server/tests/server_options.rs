@@ -0,0 +1,213 @@
+//! Options carried by `ServerOptions`: who may connect, which certificate
+//! is presented, and how input is scaled and rate limited.
+
+mod common;
+
+use std::{
+    fs,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use client::quic::{ConnectError, quic_runtime};
+use quinn::{ConnectionError, TransportErrorCode};
+use rdev::{EventType, Key};
+use server::{
+    allowlist::PeerRange,
+    cli::parse_args,
+    config::QUICInputConfig,
+    displays::FakeDisplays,
+    inject::Injector,
+    server::{CertificatePaths, ServerOptions, StreamOptions},
+    testing::{InputMessage, Simulated, simulate},
+};
+use shared::MouseMove;
+
+use common::{TestServer, disconnect};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("quicinput-options-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("failed to create scratch dir");
+    dir
+}
+
+fn start(configure: impl FnOnce(ServerOptions) -> ServerOptions) -> TestServer {
+    TestServer::start_with(|options| {
+        configure(options.with_displays(Arc::new(FakeDisplays::default())))
+    })
+}
+
+#[test]
+fn builder_defaults_match_a_fresh_config() {
+    let (injector, _log) = Injector::capture();
+    let options = ServerOptions::new(injector);
+    let config = QUICInputConfig::default();
+    assert_eq!(
+        options.binds,
+        vec![SocketAddr::new(config.broadcastip, config.port)]
+    );
+    assert_eq!(options.max_connections, config.max_connections);
+    assert_eq!(options.allowed_peers, None);
+    assert_eq!(options.certificate, None);
+    assert_eq!(options.stream_options.pointer_sensitivity, 1.0);
+    assert_eq!(options.stream_options.max_inputs_per_sec, None);
+    assert_eq!(options.transport, config.transport.options());
+    assert_eq!(options.advertise, None);
+}
+
+#[test]
+fn advertising_is_opt_in_by_name() {
+    assert_eq!(parse_args(Vec::<String>::new()).unwrap().advertise, None);
+    let args = parse_args(["--advertise".to_string(), "Living room".to_string()])
+        .expect("a name should parse");
+    assert_eq!(args.advertise.as_deref(), Some("Living room"));
+    assert!(parse_args(["--advertise".to_string()]).is_err());
+    assert!(parse_args(["--advertise".to_string(), " ".to_string()]).is_err());
+}
+
+#[test]
+fn peers_outside_the_allowlist_are_refused() {
+    let elsewhere = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let server = start(|options| options.with_allowed_peers(vec![elsewhere]));
+
+    match server.try_connect() {
+        Err(ConnectError::Refused(ConnectionError::ConnectionClosed(close))) => {
+            assert_eq!(close.error_code, TransportErrorCode::CONNECTION_REFUSED);
+        }
+        Err(other) => panic!("failed with {other}"),
+        Ok(_) => panic!("a peer off the allowlist connected"),
+    }
+}
+
+#[test]
+fn allowlisted_peers_connect() {
+    let server = start(|options| options.with_allowed_peers(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]));
+
+    let session = server
+        .try_connect()
+        .expect("allowlisted client failed to connect");
+    disconnect(session);
+}
+
+#[test]
+fn peers_within_an_allowed_network_connect() {
+    let loopback_net: PeerRange = "127.0.0.0/8".parse().unwrap();
+    let server = start(|options| options.with_allowed_peers(vec![loopback_net]));
+
+    let session = server
+        .try_connect()
+        .expect("client in an allowed network failed to connect");
+    disconnect(session);
+}
+
+#[test]
+fn a_certificate_is_loaded_from_pem_files() {
+    let dir = scratch_dir("pem");
+    let generated = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+    let paths = CertificatePaths {
+        cert: dir.join("cert.pem"),
+        key: dir.join("key.pem"),
+    };
+    fs::write(&paths.cert, generated.cert.pem()).unwrap();
+    fs::write(&paths.key, generated.signing_key.serialize_pem()).unwrap();
+
+    let server = start(|options| options.with_certificate(paths));
+    let session = server.connect();
+    let presented = session
+        .connection
+        .peer_identity()
+        .and_then(|identity| {
+            identity
+                .downcast::<Vec<rustls::pki_types::CertificateDer<'static>>>()
+                .ok()
+        })
+        .expect("server presented no certificate");
+    assert_eq!(presented[0].as_ref(), generated.cert.der().as_ref());
+
+    disconnect(session);
+}
+
+#[test]
+fn a_missing_certificate_stops_the_server() {
+    let dir = scratch_dir("missing");
+    let paths = CertificatePaths {
+        cert: dir.join("absent.pem"),
+        key: dir.join("absent.key"),
+    };
+    let mut server = start(|options| options.with_certificate(paths));
+    let result = quic_runtime()
+        .block_on(&mut server.task)
+        .expect("server panicked");
+    let err = result.expect_err("server started without its certificate");
+    assert!(err.to_string().contains("absent.pem"), "{err}");
+}
+
+#[test]
+fn pointer_motion_is_scaled_by_the_sensitivity() {
+    let simulated = simulate(
+        &[InputMessage::Mouse(MouseMove { dx: 4.0, dy: -2.0 })],
+        StreamOptions {
+            pointer_sensitivity: 1.5,
+            ..StreamOptions::default()
+        },
+    );
+    assert_eq!(
+        simulated,
+        vec![Simulated::Pointer(MouseMove { dx: 6.0, dy: -3.0 })]
+    );
+}
+
+#[test]
+fn input_past_the_rate_limit_is_dropped_but_releases_still_apply() {
+    let moved = MouseMove { dx: 1.0, dy: 0.0 };
+    let simulated = simulate(
+        &[
+            InputMessage::Event(EventType::KeyPress(Key::KeyA)),
+            InputMessage::Mouse(moved),
+            InputMessage::Mouse(moved),
+            InputMessage::Mouse(moved),
+            InputMessage::Event(EventType::KeyRelease(Key::KeyA)),
+        ],
+        StreamOptions {
+            max_inputs_per_sec: Some(2),
+            ..StreamOptions::default()
+        },
+    );
+    assert_eq!(
+        simulated,
+        vec![
+            Simulated::Keyboard(EventType::KeyPress(Key::KeyA)),
+            Simulated::Pointer(moved),
+            Simulated::Keyboard(EventType::KeyRelease(Key::KeyA)),
+        ]
+    );
+}
+
+#[test]
+fn config_rejects_bad_sensitivity_and_half_a_certificate() {
+    let config = QUICInputConfig {
+        pointer_sensitivity: 0.0,
+        ..QUICInputConfig::default()
+    };
+    assert!(config.validate().is_err());
+
+    let config = QUICInputConfig {
+        cert_path: Some(PathBuf::from("cert.pem")),
+        ..QUICInputConfig::default()
+    };
+    assert!(config.validate().is_err());
+    assert_eq!(config.certificate(), None);
+}
+
+#[test]
+fn idle_release_is_set_through_the_builder() {
+    let (injector, _log) = Injector::capture();
+    let options = ServerOptions::new(injector).with_idle_release(Some(Duration::from_secs(3)));
+    assert_eq!(
+        options.stream_options.idle_release,
+        Some(Duration::from_secs(3))
+    );
+}
@@ -0,0 +1,232 @@
+//! Behaving as a service: exit codes for startup failures, readiness
+//! notification and shutting down cleanly on a signal.
+#![cfg(unix)]
+
+mod common;
+
+use std::{
+    error::Error,
+    io::{self, BufRead, BufReader},
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    os::unix::{fs::PermissionsExt, net::UnixDatagram},
+    process::{Child, Command, Stdio},
+    sync::{Arc, mpsc},
+    time::{Duration, Instant},
+};
+
+use client::quic::{
+    ClientOptions, install_crypto_provider, open_uni, quic_runtime, run_client, send_data,
+};
+use quinn::ConnectionError;
+use rdev::{EventType, Key};
+#[cfg(target_os = "linux")]
+use server::server::ensure_uinput_writable;
+use server::{
+    displays::FakeDisplays,
+    server::ServerOptions,
+    service::{
+        EXIT_BIND_FAILED, EXIT_FAILURE, EXIT_PERMISSION_DENIED, EXIT_UINPUT_MISSING, StartupError,
+        exit_code, notify,
+    },
+};
+use shared::CloseCode;
+
+use common::{TestServer, WAIT, free_loopback_addr};
+
+fn start(configure: impl FnOnce(ServerOptions) -> ServerOptions) -> TestServer {
+    TestServer::start_with(|options| {
+        configure(options.with_displays(Arc::new(FakeDisplays::default())))
+    })
+}
+
+fn code_for(error: impl Error + Send + Sync + 'static) -> u8 {
+    let boxed: Box<dyn Error + Send + Sync + 'static> = Box::new(error);
+    exit_code(boxed.as_ref())
+}
+
+#[test]
+fn startup_failures_have_their_own_exit_codes() {
+    let addr = free_loopback_addr();
+    assert_eq!(
+        code_for(StartupError::UinputMissing("no uinput".into())),
+        EXIT_UINPUT_MISSING
+    );
+    assert_eq!(
+        code_for(StartupError::PermissionDenied("no access".into())),
+        EXIT_PERMISSION_DENIED
+    );
+    assert_eq!(
+        code_for(StartupError::BindFailed {
+            addr,
+            error: io::ErrorKind::AddrInUse.into(),
+        }),
+        EXIT_BIND_FAILED
+    );
+    // A privileged port is a permission problem, not a busy one.
+    assert_eq!(
+        code_for(StartupError::BindFailed {
+            addr,
+            error: io::ErrorKind::PermissionDenied.into(),
+        }),
+        EXIT_PERMISSION_DENIED
+    );
+}
+
+#[test]
+fn other_errors_exit_with_failure_unless_permission_was_denied() {
+    assert_eq!(
+        code_for(io::Error::from(io::ErrorKind::PermissionDenied)),
+        EXIT_PERMISSION_DENIED
+    );
+    assert_eq!(
+        code_for(io::Error::from(io::ErrorKind::NotFound)),
+        EXIT_FAILURE
+    );
+    let message: Box<dyn Error + Send + Sync + 'static> = "no async runtime found".into();
+    assert_eq!(exit_code(message.as_ref()), EXIT_FAILURE);
+}
+
+#[test]
+fn a_port_in_use_fails_with_the_bind_exit_code() {
+    let taken = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("failed to bind");
+    let addr = taken.local_addr().unwrap();
+
+    let mut server = start(|options| options.with_binds(vec![addr]));
+    let error = quic_runtime()
+        .block_on(&mut server.task)
+        .expect("server panicked")
+        .expect_err("bound a port already in use");
+    assert_eq!(exit_code(error.as_ref()), EXIT_BIND_FAILED);
+    assert!(error.to_string().contains(&addr.to_string()));
+}
+
+#[test]
+fn readiness_is_sent_to_the_notify_socket() {
+    let path = std::env::temp_dir().join(format!("quicinput-notify-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let receiver = UnixDatagram::bind(&path).expect("failed to bind notify socket");
+
+    assert!(notify(Some(path.as_os_str()), "READY=1").unwrap());
+    let mut buf = [0; 64];
+    let len = receiver.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"READY=1");
+
+    // Not started by systemd: nothing to tell.
+    assert!(!notify(None, "READY=1").unwrap());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn the_ready_hook_gets_the_bound_addresses() {
+    let (ready_tx, ready) = mpsc::channel();
+    let server = start(|options| {
+        options.with_on_ready(Arc::new(move |bound: &[SocketAddr]| {
+            let _ = ready_tx.send(bound.to_vec());
+        }))
+    });
+
+    assert_eq!(ready.recv_timeout(WAIT).unwrap(), vec![server.addr]);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn a_uinput_device_the_server_may_not_open_fails_with_permission_denied() {
+    let missing = std::env::temp_dir().join(format!("quicinput-no-uinput-{}", std::process::id()));
+    assert!(ensure_uinput_writable(&missing).is_ok());
+
+    let locked = std::env::temp_dir().join(format!("quicinput-uinput-{}", std::process::id()));
+    std::fs::write(&locked, b"").unwrap();
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+    // Root opens it regardless, so there is nothing to check.
+    if std::fs::OpenOptions::new()
+        .write(true)
+        .open(&locked)
+        .is_err()
+    {
+        let error = ensure_uinput_writable(&locked).expect_err("opened a locked device");
+        assert!(matches!(error, StartupError::PermissionDenied(_)));
+        assert_eq!(code_for(error), EXIT_PERMISSION_DENIED);
+    }
+    let _ = std::fs::remove_file(&locked);
+}
+
+/// Lines the server binary prints, read as they come.
+fn read_lines(child: &mut Child) -> mpsc::Receiver<String> {
+    let stdout = child.stdout.take().expect("server stdout not piped");
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+fn wait_for_line(lines: &mpsc::Receiver<String>, wanted: &str) {
+    let deadline = Instant::now() + WAIT;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match lines.recv_timeout(left) {
+            Ok(line) if line.contains(wanted) => return,
+            Ok(_) => {}
+            Err(_) => panic!("the server never printed '{wanted}'"),
+        }
+    }
+}
+
+#[test]
+fn sigterm_closes_connections_and_releases_held_input() {
+    install_crypto_provider().expect("no crypto provider");
+    let runtime = quic_runtime();
+    let addr = free_loopback_addr();
+    let mut server = Command::new(env!("CARGO_BIN_EXE_server"))
+        .args(["--dry-run", "--bind", &addr.to_string()])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start the server");
+    let lines = read_lines(&mut server);
+    wait_for_line(&lines, "listening on");
+
+    let session = runtime
+        .block_on(run_client(ClientOptions::new(addr), None, false))
+        .expect("client failed to connect");
+    // Left open, so only shutdown can release the key.
+    let _stream = runtime.block_on(async {
+        let mut send = open_uni(session.connection.clone()).await.unwrap();
+        let press = rmp_serde::to_vec(&EventType::KeyPress(Key::ShiftLeft)).unwrap();
+        send_data(&mut send, &press).await.unwrap();
+        send
+    });
+    wait_for_line(&lines, "KeyPress(ShiftLeft)");
+
+    let status = Command::new("kill")
+        .args(["-TERM", &server.id().to_string()])
+        .status()
+        .expect("failed to run kill");
+    assert!(status.success());
+
+    wait_for_line(&lines, "KeyRelease(ShiftLeft)");
+    match runtime.block_on(session.connection.closed()) {
+        ConnectionError::ApplicationClosed(close) => {
+            assert_eq!(
+                CloseCode::try_from(close.error_code),
+                Ok(CloseCode::Shutdown)
+            );
+        }
+        other => panic!("closed with {other:?}"),
+    }
+    let deadline = Instant::now() + WAIT;
+    let exit = loop {
+        if let Some(exit) = server.try_wait().expect("failed to wait for the server") {
+            break exit;
+        }
+        if Instant::now() > deadline {
+            let _ = server.kill();
+            panic!("server did not stop");
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    assert!(exit.success(), "server exited with {exit}");
+}
@@ -0,0 +1,25 @@
+use server::inject::session_warnings;
+use shared::session::SessionType;
+
+#[test]
+fn the_virtual_device_covers_every_session_but_absolute_moves() {
+    assert!(session_warnings(SessionType::X11, true).is_empty());
+    assert!(session_warnings(SessionType::Headless, true).is_empty());
+    assert_eq!(
+        session_warnings(SessionType::Wayland { xwayland: true }, true).len(),
+        1
+    );
+}
+
+#[test]
+fn without_the_virtual_device_only_x11_is_reached() {
+    assert!(session_warnings(SessionType::X11, false).is_empty());
+    assert!(session_warnings(SessionType::Native, false).is_empty());
+
+    let xwayland = session_warnings(SessionType::Wayland { xwayland: true }, false);
+    assert!(xwayland[0].contains("only reaches XWayland windows"));
+    let wayland = session_warnings(SessionType::Wayland { xwayland: false }, false);
+    assert!(wayland[0].contains("nothing can be injected"));
+    let headless = session_warnings(SessionType::Headless, false);
+    assert!(headless[0].contains("nothing can be injected"));
+}
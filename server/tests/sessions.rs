@@ -0,0 +1,78 @@
+//! Parking a dropped connection's held input under its token, for the same
+//! client to resume within the grace window.
+
+use std::{thread, time::Duration};
+
+use rdev::{EventType, Key};
+use server::{held::HeldState, sessions::SessionStore};
+
+fn holding(key: Key) -> HeldState {
+    let mut held = HeldState::default();
+    held.add_key(key);
+    held
+}
+
+#[test]
+fn every_issued_token_is_new() {
+    let store = SessionStore::<()>::new(Duration::from_secs(10));
+    let tokens: Vec<_> = (0..16).map(|_| store.issue()).collect();
+    for (index, token) in tokens.iter().enumerate() {
+        assert!(!tokens[..index].contains(token));
+    }
+    assert_eq!(store.grace(), Duration::from_secs(10));
+}
+
+#[test]
+fn a_parked_session_resumes_once_with_what_it_held() {
+    let store = SessionStore::<()>::new(Duration::from_secs(10));
+    let token = store.issue();
+    store.park(token, holding(Key::ShiftLeft));
+
+    let mut resumed = store.resume(&token).expect("the session was not kept");
+    assert_eq!(resumed.drain(), [EventType::KeyRelease(Key::ShiftLeft)]);
+    assert!(store.resume(&token).is_none(), "resumed twice");
+    assert!(
+        store.resume(&store.issue()).is_none(),
+        "an unknown token resumed"
+    );
+}
+
+#[test]
+fn an_expired_token_is_refused_and_left_to_release() {
+    let store = SessionStore::<()>::new(Duration::from_millis(20));
+    let token = store.issue();
+    store.park(token, holding(Key::Alt));
+    thread::sleep(Duration::from_millis(40));
+
+    assert!(store.resume(&token).is_none());
+    let mut expired = store.expire(&token).expect("nothing left to release");
+    assert_eq!(expired.drain(), [EventType::KeyRelease(Key::Alt)]);
+    assert!(store.expire(&token).is_none());
+}
+
+#[test]
+fn a_session_within_its_grace_window_isnt_expired() {
+    let store = SessionStore::<()>::new(Duration::from_secs(10));
+    let token = store.issue();
+    store.park(token, holding(Key::Alt));
+    assert!(store.expire(&token).is_none());
+    assert_eq!(store.expire_all().len(), 1);
+    assert!(store.resume(&token).is_none());
+}
+
+#[test]
+fn only_the_owning_connection_detaches_its_token() {
+    let store = SessionStore::new(Duration::from_secs(10));
+    let token = store.issue();
+    store.attach(token, 1);
+    // A successor took the token over; the old connection closing later
+    // must leave it be.
+    assert_eq!(store.take_live(&token), Some(1));
+    store.attach(token, 2);
+    store.detach(&token, |live| *live == 1);
+    assert_eq!(store.take_live(&token), Some(2));
+
+    store.attach(token, 3);
+    store.detach(&token, |live| *live == 3);
+    assert_eq!(store.take_live(&token), None);
+}
@@ -0,0 +1,158 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver},
+    },
+    time::Duration,
+};
+
+use rdev::{Button, EventType, Key};
+use server::simulator::{
+    EventSimulator, Lane, LaneLayout, SimulateFn, SimulatorHealth, SimulatorPool,
+};
+use shared::monotonic_micros;
+
+const WAIT: Duration = Duration::from_secs(5);
+const STALL: Duration = Duration::from_secs(5);
+
+/// A simulate function that reports each event, then blocks until released
+/// (dropping the release sender lets every call through).
+fn gated() -> (SimulateFn, Receiver<EventType>, mpsc::Sender<()>) {
+    let (seen_tx, seen) = mpsc::channel();
+    let (release, release_rx) = mpsc::channel::<()>();
+    let release_rx = Mutex::new(release_rx);
+    let simulate: SimulateFn = Arc::new(move |event: &EventType| {
+        let _ = seen_tx.send(*event);
+        let _ = release_rx.lock().unwrap().recv();
+        Ok(())
+    });
+    (simulate, seen, release)
+}
+
+fn press(key: Key) -> EventType {
+    EventType::KeyPress(key)
+}
+
+#[test]
+fn an_idle_simulator_is_healthy() {
+    let simulator = EventSimulator::with_simulate("test", Arc::new(|_: &EventType| Ok(())));
+    assert_eq!(simulator.queue_depth(), 0);
+    let far_future = monotonic_micros() + 3_600_000_000;
+    assert_eq!(simulator.health(far_future, STALL), SimulatorHealth::Idle);
+}
+
+#[test]
+fn a_blocked_worker_shows_as_a_growing_queue_and_then_a_stall() {
+    let (simulate, seen, release) = gated();
+    let simulator = EventSimulator::with_simulate("test", simulate);
+    for key in [Key::KeyA, Key::KeyB, Key::KeyC] {
+        assert!(simulator.enqueue(press(key)));
+    }
+    assert_eq!(seen.recv_timeout(WAIT), Ok(press(Key::KeyA)));
+    assert_eq!(simulator.queue_depth(), 3);
+
+    let now = monotonic_micros();
+    assert_eq!(
+        simulator.health(now, STALL),
+        SimulatorHealth::Draining { depth: 3 }
+    );
+    let later = now + 6_000_000;
+    assert!(matches!(
+        simulator.health(later, STALL),
+        SimulatorHealth::Stalled { depth: 3, for_micros } if for_micros >= 6_000_000
+    ));
+
+    drop(release);
+    for key in [Key::KeyB, Key::KeyC] {
+        assert_eq!(seen.recv_timeout(WAIT), Ok(press(key)));
+    }
+}
+
+#[test]
+fn a_restart_drops_the_backlog_and_keeps_simulating() {
+    let (simulate, seen, release) = gated();
+    let simulator = EventSimulator::with_simulate("test", simulate);
+    for key in [Key::KeyA, Key::KeyB] {
+        simulator.enqueue(press(key));
+    }
+    assert_eq!(seen.recv_timeout(WAIT), Ok(press(Key::KeyA)));
+
+    assert_eq!(simulator.restart(), 2);
+    assert_eq!(simulator.failed(), 2);
+    assert_eq!(simulator.queue_depth(), 0);
+
+    // The fresh worker takes new events; the stuck one, once it wakes,
+    // replays nothing of its stale backlog.
+    simulator.enqueue(press(Key::KeyC));
+    assert_eq!(seen.recv_timeout(WAIT), Ok(press(Key::KeyC)));
+    drop(release);
+    assert!(seen.recv_timeout(Duration::from_millis(200)).is_err());
+}
+
+/// A simulate function that reports each event with the worker thread that
+/// replayed it, named after the simulator.
+fn by_thread() -> (SimulateFn, Receiver<(String, EventType)>) {
+    let (seen_tx, seen) = mpsc::channel();
+    let seen_tx = Mutex::new(seen_tx);
+    let simulate: SimulateFn = Arc::new(move |event: &EventType| {
+        let thread = std::thread::current()
+            .name()
+            .unwrap_or_default()
+            .to_string();
+        let _ = seen_tx.lock().unwrap().send((thread, *event));
+        Ok(())
+    });
+    (simulate, seen)
+}
+
+#[test]
+fn events_route_to_their_named_lane() {
+    let (simulate, seen) = by_thread();
+    let pool = SimulatorPool::with_simulate(LaneLayout::Split, simulate);
+    assert_eq!(pool.lane(Lane::Keyboard).name(), "keyboard");
+    assert_eq!(pool.lane(Lane::Pointer).name(), "pointer");
+    assert!(!pool.is_shared());
+
+    let routed = [
+        (press(Key::KeyA), "keyboard-simulator"),
+        (EventType::KeyRelease(Key::KeyA), "keyboard-simulator"),
+        (EventType::ButtonPress(Button::Left), "pointer-simulator"),
+        (EventType::ButtonRelease(Button::Left), "pointer-simulator"),
+        (
+            EventType::Wheel {
+                delta_x: 0,
+                delta_y: -1,
+            },
+            "pointer-simulator",
+        ),
+        (
+            EventType::MouseMove { x: 10.0, y: 20.0 },
+            "pointer-simulator",
+        ),
+    ];
+    for (event, thread) in routed {
+        assert!(pool.for_event(&event).enqueue(event));
+        assert_eq!(seen.recv_timeout(WAIT), Ok((thread.to_string(), event)));
+    }
+}
+
+#[test]
+fn a_shared_pool_replays_both_lanes_through_one_simulator() {
+    let (simulate, seen) = by_thread();
+    let pool = SimulatorPool::with_simulate(LaneLayout::Shared, simulate);
+    assert!(pool.is_shared());
+    assert_eq!(pool.simulators().len(), 1);
+
+    let shift = press(Key::ShiftLeft);
+    let click = EventType::ButtonPress(Button::Left);
+    pool.for_event(&shift).enqueue(shift);
+    pool.for_event(&click).enqueue(click);
+    assert_eq!(
+        seen.recv_timeout(WAIT),
+        Ok(("input-simulator".to_string(), shift))
+    );
+    assert_eq!(
+        seen.recv_timeout(WAIT),
+        Ok(("input-simulator".to_string(), click))
+    );
+}
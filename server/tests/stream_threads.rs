@@ -0,0 +1,76 @@
+//! Holds many uni streams open at once and checks the server handles them
+//! on its runtime rather than a thread apiece. Kept in its own test binary
+//! so nothing else running changes the process's thread count.
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use std::{fs, sync::Arc};
+
+use client::quic::{open_uni, quic_runtime, send_data};
+use rdev::{EventType, Key};
+use server::{displays::FakeDisplays, framing::Frame, server::StreamOptions};
+use shared::DisplayInfo;
+
+use common::{TestServer, disconnect};
+
+const STREAMS: usize = 64;
+
+fn thread_count() -> usize {
+    let status = fs::read_to_string("/proc/self/status").expect("no /proc/self/status");
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Threads:"))
+        .and_then(|count| count.trim().parse().ok())
+        .expect("no thread count in /proc/self/status")
+}
+
+#[test]
+fn open_streams_do_not_each_take_a_thread() {
+    let runtime = quic_runtime();
+    let server = TestServer::start_with(|options| {
+        options
+            .with_stream_options(StreamOptions {
+                max_streams: STREAMS,
+                ..StreamOptions::default()
+            })
+            .with_displays(Arc::new(FakeDisplays(vec![DisplayInfo {
+                name: "fake-0".to_string(),
+                x: 0,
+                y: 0,
+                width: 1920,
+                height: 1080,
+                is_primary: true,
+            }])))
+    });
+    let session = server.connect();
+
+    let before = thread_count();
+
+    // One event per stream, left unfinished so every handler stays alive.
+    let event = EventType::KeyPress(Key::KeyA);
+    let buf = rmp_serde::to_vec(&event).expect("failed to encode event");
+    let streams = runtime.block_on(async {
+        let mut streams = Vec::with_capacity(STREAMS);
+        for _ in 0..STREAMS {
+            let mut send = open_uni(session.connection.clone())
+                .await
+                .expect("failed to open stream");
+            send_data(&mut send, &buf).await.expect("failed to send");
+            streams.push(send);
+        }
+        streams
+    });
+    for _ in 0..STREAMS {
+        assert_eq!(server.next_frame(), Frame::Event(event));
+    }
+
+    let during = thread_count();
+    assert!(
+        during < before + STREAMS / 4,
+        "{STREAMS} open streams grew the server from {before} to {during} threads"
+    );
+
+    drop(streams);
+    disconnect(session);
+}
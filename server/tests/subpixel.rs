@@ -0,0 +1,57 @@
+//! Sub-pixel motion: fractional deltas add up instead of being rounded
+//! away one move at a time.
+
+use server::mousemove::SubPixelMotion;
+use shared::MouseMove;
+
+#[test]
+fn small_moves_add_up_to_whole_pixels() {
+    let mut motion = SubPixelMotion::default();
+    let step = MouseMove { dx: 0.4, dy: -0.4 };
+
+    let moved: Vec<(i32, i32)> = (0..5).map(|_| motion.take_whole(step)).collect();
+    assert_eq!(moved, vec![(0, 0), (0, 0), (1, -1), (0, 0), (1, -1)]);
+
+    let (mut x, mut y) = (0, 0);
+    for _ in 0..1000 {
+        let (dx, dy) = motion.take_whole(step);
+        (x, y) = (x + dx, y + dy);
+    }
+    // 400 pixels each way, give or take what is still carried over.
+    assert!((399..=400).contains(&x), "moved {x} pixels");
+    assert!((-400..=-399).contains(&y), "moved {y} pixels");
+}
+
+#[test]
+fn whole_pixels_pass_straight_through() {
+    let mut motion = SubPixelMotion::default();
+    assert_eq!(motion.take_whole(MouseMove { dx: 3.0, dy: -7.0 }), (3, -7));
+    assert_eq!(motion.remainder(), MouseMove { dx: 0.0, dy: 0.0 });
+
+    assert_eq!(motion.take_whole(MouseMove { dx: 2.75, dy: 0.0 }), (2, 0));
+    let carried = motion.remainder();
+    assert!((carried.dx - 0.75).abs() < 1e-9);
+}
+
+#[test]
+fn opposite_fractions_cancel_out() {
+    let mut motion = SubPixelMotion::default();
+    assert_eq!(motion.take_whole(MouseMove { dx: 0.6, dy: 0.0 }), (0, 0));
+    assert_eq!(motion.take_whole(MouseMove { dx: -0.6, dy: 0.0 }), (0, 0));
+    assert_eq!(motion.take_whole(MouseMove { dx: 0.6, dy: 0.0 }), (0, 0));
+}
+
+#[test]
+fn a_move_that_is_not_a_number_is_dropped() {
+    let mut motion = SubPixelMotion::default();
+    motion.take_whole(MouseMove { dx: 0.5, dy: 0.5 });
+    assert_eq!(
+        motion.take_whole(MouseMove {
+            dx: f64::NAN,
+            dy: f64::INFINITY,
+        }),
+        (0, 0)
+    );
+    assert_eq!(motion.remainder(), MouseMove { dx: 0.0, dy: 0.0 });
+    assert_eq!(motion.take_whole(MouseMove { dx: 1.0, dy: 1.0 }), (1, 1));
+}
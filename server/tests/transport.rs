@@ -0,0 +1,228 @@
+mod common;
+
+use std::{sync::Arc, thread, time::Duration};
+
+use client::quic::{ClientSession, quic_runtime};
+use quinn::{Connection, ConnectionError, ServerConfig};
+use rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer};
+use server::{
+    config::{QUICInputConfig, TransportSettings},
+    transport::{DEFAULT_MAX_CONCURRENT_STREAMS, ServerTransportOptions},
+};
+use shared::congestion::CongestionControl;
+use tokio::time::timeout;
+
+use common::{TestServer, WAIT};
+
+/// How long an open waits before the stream counts as refused.
+const REFUSED_AFTER: Duration = Duration::from_millis(200);
+
+/// Control requests `run_client` makes. quinn hands the credit for closed
+/// streams back in batches, so theirs may not have come back yet.
+const HANDSHAKE_STREAMS: u32 = 2;
+
+fn server_config() -> ServerConfig {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+    let key = PrivatePkcs8KeyDer::from(cert.signing_key.serialize_der());
+    ServerConfig::with_single_cert(vec![CertificateDer::from(cert.cert)], key.into()).unwrap()
+}
+
+/// A client without keep-alives, of a dry-run server using `transport`.
+fn connect(transport: ServerTransportOptions) -> (TestServer, ClientSession) {
+    let server = TestServer::start_with(|options| options.with_transport(transport));
+    let session = server.connect_with(|options| options.with_keep_alive(None));
+    (server, session)
+}
+
+/// How many uni streams the server lets the client hold open, counting no
+/// further than `up_to`.
+fn uni_streams_allowed(connection: &Connection, up_to: u32) -> u32 {
+    quic_runtime().block_on(async {
+        let mut open = Vec::new();
+        while open.len() < up_to as usize {
+            match timeout(REFUSED_AFTER, connection.open_uni()).await {
+                Ok(stream) => open.push(stream.expect("connection failed")),
+                Err(_) => break,
+            }
+        }
+        open.len() as u32
+    })
+}
+
+/// How many bi streams the server lets the client hold open, counting no
+/// further than `up_to`.
+fn bidi_streams_allowed(connection: &Connection, up_to: u32) -> u32 {
+    quic_runtime().block_on(async {
+        let mut open = Vec::new();
+        while open.len() < up_to as usize {
+            match timeout(REFUSED_AFTER, connection.open_bi()).await {
+                Ok(streams) => open.push(streams.expect("connection failed")),
+                Err(_) => break,
+            }
+        }
+        open.len() as u32
+    })
+}
+
+#[test]
+fn the_defaults_are_quinns_own() {
+    let defaults = ServerTransportOptions::default();
+    let mut config = server_config();
+    defaults
+        .apply_to(&mut config)
+        .expect("defaults should apply");
+
+    let (_server, session) = connect(defaults);
+    let limit = DEFAULT_MAX_CONCURRENT_STREAMS;
+    assert_eq!(uni_streams_allowed(&session.connection, limit + 1), limit);
+    let bidi = bidi_streams_allowed(&session.connection, limit + 1);
+    assert!(
+        (limit - HANDSHAKE_STREAMS..=limit).contains(&bidi),
+        "{bidi}"
+    );
+}
+
+#[test]
+fn configured_limits_are_applied() {
+    let options = ServerTransportOptions {
+        max_concurrent_uni_streams: 8,
+        max_concurrent_bidi_streams: 4,
+        stream_receive_window: 64 * 1024,
+        receive_window: Some(256 * 1024),
+        max_idle_timeout: Some(Duration::from_millis(500)),
+        keep_alive_interval: None,
+        congestion_control: CongestionControl::Bbr,
+    };
+    options
+        .apply_to(&mut server_config())
+        .expect("options should apply");
+
+    let (_server, session) = connect(options);
+    assert_eq!(uni_streams_allowed(&session.connection, 9), 8);
+    let bidi = bidi_streams_allowed(&session.connection, 5);
+    assert!((4 - HANDSHAKE_STREAMS..=4).contains(&bidi), "{bidi}");
+
+    // Neither side sends anything more, so the server's idle timeout ends it.
+    let closed = quic_runtime()
+        .block_on(async { timeout(WAIT, session.connection.closed()).await })
+        .expect("the connection outlived the idle timeout");
+    assert!(matches!(closed, ConnectionError::TimedOut), "{closed}");
+}
+
+#[test]
+fn keep_alives_hold_a_silent_connection_open() {
+    let (_server, session) = connect(ServerTransportOptions {
+        max_idle_timeout: Some(Duration::from_millis(400)),
+        keep_alive_interval: Some(Duration::from_millis(100)),
+        ..ServerTransportOptions::default()
+    });
+    thread::sleep(Duration::from_millis(1200));
+    assert!(session.connection.close_reason().is_none());
+}
+
+#[test]
+fn unusable_limits_are_refused() {
+    let defaults = ServerTransportOptions::default();
+    let invalid = [
+        ServerTransportOptions {
+            max_concurrent_uni_streams: 0,
+            ..defaults
+        },
+        ServerTransportOptions {
+            max_concurrent_bidi_streams: 0,
+            ..defaults
+        },
+        ServerTransportOptions {
+            stream_receive_window: 0,
+            ..defaults
+        },
+        ServerTransportOptions {
+            stream_receive_window: u64::MAX,
+            ..defaults
+        },
+        ServerTransportOptions {
+            receive_window: Some(1024),
+            ..defaults
+        },
+        ServerTransportOptions {
+            max_idle_timeout: Some(Duration::ZERO),
+            ..defaults
+        },
+        ServerTransportOptions {
+            keep_alive_interval: Some(Duration::from_secs(30)),
+            ..defaults
+        },
+    ];
+    for options in invalid {
+        assert!(options.validate().is_err(), "{options:?} should be refused");
+        assert!(options.apply_to(&mut server_config()).is_err());
+    }
+
+    // Without an idle timeout any keep-alive is fine.
+    let options = ServerTransportOptions {
+        max_idle_timeout: None,
+        keep_alive_interval: Some(Duration::from_secs(60)),
+        ..defaults
+    };
+    assert!(options.validate().is_ok());
+}
+
+#[test]
+fn a_shared_transport_is_left_alone() {
+    let mut config = server_config();
+    let _shared = Arc::clone(&config.transport);
+    assert!(
+        ServerTransportOptions::default()
+            .apply_to(&mut config)
+            .is_err()
+    );
+}
+
+#[test]
+fn the_config_file_uses_seconds_and_zero_for_off() {
+    let config: QUICInputConfig = toml::from_str(
+        "[transport]\nmax_concurrent_uni_streams = 4\nmax_idle_timeout_secs = 0\nkeep_alive_secs = 10\n",
+    )
+    .expect("config should parse");
+    assert!(config.validate().is_ok());
+    assert_eq!(
+        config.transport.options(),
+        ServerTransportOptions {
+            max_concurrent_uni_streams: 4,
+            max_idle_timeout: None,
+            keep_alive_interval: Some(Duration::from_secs(10)),
+            ..ServerTransportOptions::default()
+        }
+    );
+    assert_eq!(
+        TransportSettings::default().options(),
+        ServerTransportOptions::default()
+    );
+
+    let config = QUICInputConfig {
+        transport: TransportSettings {
+            keep_alive_secs: 45,
+            ..TransportSettings::default()
+        },
+        ..QUICInputConfig::default()
+    };
+    assert!(config.validate().is_err());
+
+    let written = toml::to_string_pretty(&QUICInputConfig::default()).unwrap();
+    assert!(written.contains("[transport]"));
+    assert!(written.contains("congestion_control = \"cubic\""));
+}
+
+#[test]
+fn the_congestion_controller_is_chosen_by_name() {
+    let config: QUICInputConfig =
+        toml::from_str("[transport]\ncongestion_control = \"new_reno\"\n")
+            .expect("config should parse");
+    assert_eq!(
+        config.transport.options().congestion_control,
+        CongestionControl::NewReno
+    );
+    assert!(
+        toml::from_str::<QUICInputConfig>("[transport]\ncongestion_control = \"vegas\"\n").is_err()
+    );
+}
@@ -0,0 +1,83 @@
+//! Uni streams in the JSON wire format, as a client that asked for it in
+//! its hello sends them.
+
+use rdev::{EventType, Key};
+use server::framing::{Frame, FrameDecoder};
+use shared::{
+    CharInput, Edge, EdgeHit, KeepHeld, MouseMove, SentAt, Seq, SeqCategory,
+    codec::{Codec, WireFormat},
+};
+
+fn json(value: &impl serde::Serialize) -> Vec<u8> {
+    WireFormat::Json.encode(value).unwrap()
+}
+
+#[test]
+fn decoders_read_message_pack_unless_told_otherwise() {
+    assert_eq!(FrameDecoder::new(1024).format(), WireFormat::MessagePack);
+}
+
+#[test]
+fn json_frames_decode_like_message_pack_ones() {
+    let mut decoder = FrameDecoder::new(1024).with_format(WireFormat::Json);
+    let seq = Seq {
+        category: SeqCategory::Keyboard,
+        seq: 3,
+    };
+    let moved = MouseMove { dx: 1.0, dy: -2.5 };
+    decoder.push(&json(&seq));
+    decoder.push(&json(&SentAt { micros: 42 }));
+    decoder.push(&json(&EventType::KeyPress(Key::KeyA)));
+    decoder.push(&json(&moved));
+    decoder.push(&json(&CharInput('é')));
+    decoder.push(&json(&EdgeHit(Edge::Left)));
+    decoder.push(&json(&KeepHeld));
+
+    assert_eq!(decoder.next_frame(), Some(Frame::Seq(seq)));
+    assert_eq!(decoder.next_frame(), Some(Frame::SentAt(42)));
+    assert_eq!(
+        decoder.next_frame(),
+        Some(Frame::Event(EventType::KeyPress(Key::KeyA)))
+    );
+    assert_eq!(decoder.next_frame(), Some(Frame::Mouse(moved)));
+    assert_eq!(decoder.next_frame(), Some(Frame::Char('é')));
+    assert_eq!(decoder.next_frame(), Some(Frame::Edge(Edge::Left)));
+    assert_eq!(decoder.next_frame(), Some(Frame::KeepHeld));
+    assert_eq!(decoder.next_frame(), None);
+    assert_eq!(decoder.pending(), 0);
+}
+
+#[test]
+fn a_json_value_split_across_reads_waits_for_the_rest() {
+    let mut decoder = FrameDecoder::new(1024).with_format(WireFormat::Json);
+    let bytes = json(&EventType::KeyRelease(Key::ShiftLeft));
+    let (head, tail) = bytes.split_at(bytes.len() / 2);
+
+    decoder.push(head);
+    assert_eq!(decoder.next_frame(), None);
+    assert_eq!(decoder.pending(), head.len());
+
+    decoder.push(tail);
+    assert_eq!(
+        decoder.next_frame(),
+        Some(Frame::Event(EventType::KeyRelease(Key::ShiftLeft)))
+    );
+    assert_eq!(decoder.pending(), 0);
+}
+
+#[test]
+fn unknown_json_is_skipped_and_garbage_discarded() {
+    let mut decoder = FrameDecoder::new(1024).with_format(WireFormat::Json);
+    let probe = b"{\"probe\":true}\n";
+    decoder.push(probe);
+    decoder.push(&json(&EventType::KeyPress(Key::KeyZ)));
+    assert_eq!(decoder.next_frame(), Some(Frame::Unknown(probe.len())));
+    assert_eq!(
+        decoder.next_frame(),
+        Some(Frame::Event(EventType::KeyPress(Key::KeyZ)))
+    );
+
+    decoder.push(b"}not json");
+    assert_eq!(decoder.next_frame(), None);
+    assert_eq!(decoder.pending(), 0);
+}
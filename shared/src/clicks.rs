@@ -0,0 +1,39 @@
+//! Double-clicks that survive the network. Two clicks the client's OS would
+//! count as a double-click can reach the server further apart than its own
+//! double-click time, e.g. when a packet is resent, and then land as two
+//! single clicks. The client sends a [`DoubleClick`] just before the press
+//! that completed one, on the same stream, so the server can replay it
+//! close enough together when the clicks came in spread out.
+//!
+//! A server that doesn't know the hint decodes it as an unknown value and
+//! skips it; the press after it still arrives.
+
+use rdev::Button;
+use serde::{Deserialize, Serialize};
+
+/// The press that follows on the same stream is the second click of a
+/// double-click on the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "DoubleClickWire", into = "DoubleClickWire")]
+pub struct DoubleClick {
+    pub button: Button,
+}
+
+/// How [`DoubleClick`] goes on the wire, tagged so no other value can be
+/// mistaken for it.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+enum DoubleClickWire {
+    DoubleClick(Button),
+}
+
+impl From<DoubleClickWire> for DoubleClick {
+    fn from(DoubleClickWire::DoubleClick(button): DoubleClickWire) -> Self {
+        DoubleClick { button }
+    }
+}
+
+impl From<DoubleClick> for DoubleClickWire {
+    fn from(hint: DoubleClick) -> Self {
+        DoubleClickWire::DoubleClick(hint.button)
+    }
+}
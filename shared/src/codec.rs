@@ -0,0 +1,182 @@
+//! How input values are encoded on uni streams. MessagePack is compact and
+//! what QUICinput always sent; JSON can be read straight out of a packet
+//! capture or a `--debug-raw` dump, which helps when chasing a decoding
+//! problem. The client asks for a format in its hello and the server
+//! confirms it in the welcome. Only the input the client sends on uni
+//! streams changes; control streams and what observers are sent stay
+//! MessagePack.
+//!
+//! Values follow one another with no length in front, so decoders read the
+//! value at the start of what has arrived and report how many bytes it used.
+
+use std::{error::Error, fmt, io::Cursor, str::FromStr};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    /// The bytes so far are the start of a value; wait for more.
+    Incomplete,
+    /// The bytes can't be read as the requested value, or the value can't
+    /// be written.
+    Invalid(String),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Incomplete => f.write_str("incomplete value"),
+            CodecError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for CodecError {}
+
+pub trait Codec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError>;
+
+    /// Decodes the value at the start of `bytes`, returning it and how many
+    /// bytes it took.
+    fn decode_prefix<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<(T, usize), CodecError>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessagePack;
+
+impl Codec for MessagePack {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        rmp_serde::to_vec(value).map_err(|err| CodecError::Invalid(err.to_string()))
+    }
+
+    fn decode_prefix<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<(T, usize), CodecError> {
+        let mut cursor = Cursor::new(bytes);
+        match T::deserialize(&mut rmp_serde::Deserializer::new(&mut cursor)) {
+            Ok(value) => Ok((value, cursor.position() as usize)),
+            Err(err) if msgpack_incomplete(&err) => Err(CodecError::Incomplete),
+            Err(err) => Err(CodecError::Invalid(err.to_string())),
+        }
+    }
+}
+
+fn msgpack_incomplete(err: &rmp_serde::decode::Error) -> bool {
+    use rmp_serde::decode::Error;
+    match err {
+        Error::InvalidMarkerRead(io_err) | Error::InvalidDataRead(io_err) => {
+            io_err.kind() == std::io::ErrorKind::UnexpectedEof
+        }
+        _ => false,
+    }
+}
+
+/// One JSON document per value, each followed by a newline so a capture
+/// reads a line at a time. Every value QUICinput sends is an object, array
+/// or string, so a value is never mistaken for the prefix of a longer one
+/// the way a bare number could be.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        let mut bytes =
+            serde_json::to_vec(value).map_err(|err| CodecError::Invalid(err.to_string()))?;
+        bytes.push(b'\n');
+        Ok(bytes)
+    }
+
+    fn decode_prefix<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<(T, usize), CodecError> {
+        let mut values = serde_json::Deserializer::from_slice(bytes).into_iter::<T>();
+        match values.next() {
+            Some(Ok(value)) => {
+                // Take the newline after it too, so nothing is left over
+                // once a stream's last value has been read.
+                let end = values.byte_offset();
+                let trailing = bytes[end..]
+                    .iter()
+                    .take_while(|byte| byte.is_ascii_whitespace())
+                    .count();
+                Ok((value, end + trailing))
+            }
+            Some(Err(err)) if err.is_eof() => Err(CodecError::Incomplete),
+            Some(Err(err)) => Err(CodecError::Invalid(err.to_string())),
+            // Nothing but whitespace so far.
+            None => Err(CodecError::Incomplete),
+        }
+    }
+}
+
+/// The [`Codec`] a connection's uni streams use, as negotiated in the hello.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireFormat {
+    #[default]
+    MessagePack,
+    Json,
+}
+
+impl WireFormat {
+    pub const ALL: [WireFormat; 2] = [WireFormat::MessagePack, WireFormat::Json];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WireFormat::MessagePack => "msgpack",
+            WireFormat::Json => "json",
+        }
+    }
+
+    /// `bytes`, a run of values encoded in this format, encoded in `to`
+    /// instead. Each value goes through a [`serde_json::Value`], which
+    /// holds anything QUICinput sends: MessagePack's structs come out as
+    /// arrays, which both formats read back as structs.
+    pub fn transcode(self, mut bytes: &[u8], to: WireFormat) -> Result<Vec<u8>, CodecError> {
+        if self == to {
+            return Ok(bytes.to_vec());
+        }
+        let mut out = Vec::new();
+        while !bytes.is_empty() {
+            let (value, used) = self.decode_prefix::<serde_json::Value>(bytes)?;
+            out.extend(to.encode(&value)?);
+            bytes = &bytes[used..];
+        }
+        Ok(out)
+    }
+}
+
+impl Codec for WireFormat {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        match self {
+            WireFormat::MessagePack => MessagePack.encode(value),
+            WireFormat::Json => Json.encode(value),
+        }
+    }
+
+    fn decode_prefix<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<(T, usize), CodecError> {
+        match self {
+            WireFormat::MessagePack => MessagePack.decode_prefix(bytes),
+            WireFormat::Json => Json.decode_prefix(bytes),
+        }
+    }
+}
+
+impl fmt::Display for WireFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for WireFormat {
+    type Err = String;
+
+    /// Parses a name as [`WireFormat::name`] gives it, ignoring case;
+    /// `messagepack` works too.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let wanted = value.trim().to_ascii_lowercase();
+        if wanted == "messagepack" {
+            return Ok(WireFormat::MessagePack);
+        }
+        Self::ALL
+            .into_iter()
+            .find(|format| format.name() == wanted)
+            .ok_or_else(|| format!("unknown wire format '{value}'; expected msgpack or json"))
+    }
+}
@@ -0,0 +1,81 @@
+//! Which congestion controller a QUIC connection runs. Input is a trickle
+//! of small packets that rarely fills even the initial window, so the
+//! choice mostly matters after loss: how far the sender backs off, and so
+//! how long input queues behind the lost packet. Both ends send (input one
+//! way, acknowledgements, control replies and observer streams the other),
+//! so client and server each pick theirs.
+
+use std::{fmt, str::FromStr, sync::Arc};
+
+use quinn::{
+    TransportConfig,
+    congestion::{BbrConfig, ControllerFactory, CubicConfig, NewRenoConfig},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CongestionControl {
+    /// quinn's default, and what QUICinput always used before this was
+    /// selectable.
+    #[default]
+    Cubic,
+    /// Halves its window on loss and grows it back linearly.
+    NewReno,
+    /// Paces to the measured bandwidth and round trip instead of treating
+    /// loss as congestion. Experimental in quinn.
+    Bbr,
+}
+
+impl CongestionControl {
+    pub const ALL: [CongestionControl; 3] = [
+        CongestionControl::Cubic,
+        CongestionControl::NewReno,
+        CongestionControl::Bbr,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CongestionControl::Cubic => "cubic",
+            CongestionControl::NewReno => "new_reno",
+            CongestionControl::Bbr => "bbr",
+        }
+    }
+
+    pub fn factory(self) -> Arc<dyn ControllerFactory + Send + Sync + 'static> {
+        match self {
+            CongestionControl::Cubic => Arc::new(CubicConfig::default()),
+            CongestionControl::NewReno => Arc::new(NewRenoConfig::default()),
+            CongestionControl::Bbr => Arc::new(BbrConfig::default()),
+        }
+    }
+
+    pub fn apply(self, transport: &mut TransportConfig) {
+        transport.congestion_controller_factory(self.factory());
+    }
+}
+
+impl fmt::Display for CongestionControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CongestionControl {
+    type Err = String;
+
+    /// Parses a name as [`CongestionControl::name`] gives it, ignoring case;
+    /// `newreno` works too.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let wanted = value.trim().to_ascii_lowercase();
+        if wanted == "newreno" {
+            return Ok(CongestionControl::NewReno);
+        }
+        Self::ALL
+            .into_iter()
+            .find(|control| control.name() == wanted)
+            .ok_or_else(|| {
+                format!("unknown congestion controller '{value}'; expected cubic, new_reno or bbr")
+            })
+    }
+}
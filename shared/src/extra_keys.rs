@@ -0,0 +1,206 @@
+//! Keys `rdev` has no names for: media, volume and brightness keys and
+//! F13–F24. They arrive as `Key::Unknown` holding a platform keycode that
+//! means nothing on another OS, so the client sends them as an
+//! [`ExtraKeyInput`] instead and the server maps that back to its own key.
+//!
+//! What works where:
+//!
+//! | Platform | Captured                          | Injected                                  |
+//! |----------|-----------------------------------|-------------------------------------------|
+//! | Windows  | media, volume, F13–F24            | the same, as virtual-key codes            |
+//! | Linux    | none (rdev's grab drops them)     | all, via the virtual input device, else XTest |
+//! | macOS    | volume, mute, F13–F20             | the same                                  |
+//!
+//! Apple keyboards' media and brightness row is delivered as system-defined
+//! events, which rdev never sees, so on a macOS client only volume keys
+//! that arrive as key codes (e.g. from external keyboards) are forwarded.
+
+use rdev::{EventType, Key};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum ExtraKey {
+    VolumeUp,
+    VolumeDown,
+    Mute,
+    PlayPause,
+    Next,
+    Previous,
+    Stop,
+    BrightnessUp,
+    BrightnessDown,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+}
+
+/// An extra key press or release, sent on the keyboard stream in place of
+/// the raw `Key::Unknown` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ExtraKeyInput {
+    pub key: ExtraKey,
+    pub pressed: bool,
+}
+
+impl ExtraKey {
+    pub const ALL: [ExtraKey; 21] = [
+        ExtraKey::VolumeUp,
+        ExtraKey::VolumeDown,
+        ExtraKey::Mute,
+        ExtraKey::PlayPause,
+        ExtraKey::Next,
+        ExtraKey::Previous,
+        ExtraKey::Stop,
+        ExtraKey::BrightnessUp,
+        ExtraKey::BrightnessDown,
+        ExtraKey::F13,
+        ExtraKey::F14,
+        ExtraKey::F15,
+        ExtraKey::F16,
+        ExtraKey::F17,
+        ExtraKey::F18,
+        ExtraKey::F19,
+        ExtraKey::F20,
+        ExtraKey::F21,
+        ExtraKey::F22,
+        ExtraKey::F23,
+        ExtraKey::F24,
+    ];
+
+    /// The extra key `key` is on this platform, if any.
+    pub fn from_key(key: Key) -> Option<Self> {
+        let Key::Unknown(code) = key else {
+            return None;
+        };
+        ExtraKey::ALL
+            .into_iter()
+            .find(|extra| extra.native_code() == Some(code))
+    }
+
+    /// This key as rdev names it on this platform, or `None` where it has
+    /// no keycode and cannot be simulated.
+    pub fn key(self) -> Option<Key> {
+        self.native_code().map(Key::Unknown)
+    }
+
+    /// The Linux input event code (`KEY_*`) for this key, whatever the
+    /// platform, for injecting through a uinput device.
+    pub fn linux_code(self) -> u16 {
+        match self {
+            ExtraKey::Mute => 113,
+            ExtraKey::VolumeDown => 114,
+            ExtraKey::VolumeUp => 115,
+            ExtraKey::Next => 163,
+            ExtraKey::PlayPause => 164,
+            ExtraKey::Previous => 165,
+            ExtraKey::Stop => 166,
+            ExtraKey::F13 => 183,
+            ExtraKey::F14 => 184,
+            ExtraKey::F15 => 185,
+            ExtraKey::F16 => 186,
+            ExtraKey::F17 => 187,
+            ExtraKey::F18 => 188,
+            ExtraKey::F19 => 189,
+            ExtraKey::F20 => 190,
+            ExtraKey::F21 => 191,
+            ExtraKey::F22 => 192,
+            ExtraKey::F23 => 193,
+            ExtraKey::F24 => 194,
+            ExtraKey::BrightnessDown => 224,
+            ExtraKey::BrightnessUp => 225,
+        }
+    }
+
+    /// Windows virtual-key codes; brightness has none.
+    #[cfg(target_os = "windows")]
+    fn native_code(self) -> Option<u32> {
+        Some(match self {
+            ExtraKey::Mute => 0xAD,
+            ExtraKey::VolumeDown => 0xAE,
+            ExtraKey::VolumeUp => 0xAF,
+            ExtraKey::Next => 0xB0,
+            ExtraKey::Previous => 0xB1,
+            ExtraKey::Stop => 0xB2,
+            ExtraKey::PlayPause => 0xB3,
+            ExtraKey::F13 => 0x7C,
+            ExtraKey::F14 => 0x7D,
+            ExtraKey::F15 => 0x7E,
+            ExtraKey::F16 => 0x7F,
+            ExtraKey::F17 => 0x80,
+            ExtraKey::F18 => 0x81,
+            ExtraKey::F19 => 0x82,
+            ExtraKey::F20 => 0x83,
+            ExtraKey::F21 => 0x84,
+            ExtraKey::F22 => 0x85,
+            ExtraKey::F23 => 0x86,
+            ExtraKey::F24 => 0x87,
+            ExtraKey::BrightnessUp | ExtraKey::BrightnessDown => return None,
+        })
+    }
+
+    /// macOS virtual keycodes (`kVK_*`); there are none for the transport
+    /// and brightness keys or past F20.
+    #[cfg(target_os = "macos")]
+    fn native_code(self) -> Option<u32> {
+        Some(match self {
+            ExtraKey::VolumeUp => 0x48,
+            ExtraKey::VolumeDown => 0x49,
+            ExtraKey::Mute => 0x4A,
+            ExtraKey::F13 => 0x69,
+            ExtraKey::F14 => 0x6B,
+            ExtraKey::F15 => 0x71,
+            ExtraKey::F16 => 0x6A,
+            ExtraKey::F17 => 0x40,
+            ExtraKey::F18 => 0x4F,
+            ExtraKey::F19 => 0x50,
+            ExtraKey::F20 => 0x5A,
+            ExtraKey::PlayPause
+            | ExtraKey::Next
+            | ExtraKey::Previous
+            | ExtraKey::Stop
+            | ExtraKey::BrightnessUp
+            | ExtraKey::BrightnessDown
+            | ExtraKey::F21
+            | ExtraKey::F22
+            | ExtraKey::F23
+            | ExtraKey::F24 => return None,
+        })
+    }
+
+    /// X11 keycodes, i.e. the kernel's `KEY_*` codes plus 8.
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    fn native_code(self) -> Option<u32> {
+        Some(u32::from(self.linux_code()) + 8)
+    }
+}
+
+impl ExtraKeyInput {
+    /// The extra key behind a key event from rdev, if it is one.
+    pub fn from_event(event_type: &EventType) -> Option<Self> {
+        let (key, pressed) = match *event_type {
+            EventType::KeyPress(key) => (key, true),
+            EventType::KeyRelease(key) => (key, false),
+            _ => return None,
+        };
+        ExtraKey::from_key(key).map(|key| ExtraKeyInput { key, pressed })
+    }
+
+    /// The key event that replays this on this platform, if it can be.
+    pub fn event(self) -> Option<EventType> {
+        let key = self.key.key()?;
+        Some(if self.pressed {
+            EventType::KeyPress(key)
+        } else {
+            EventType::KeyRelease(key)
+        })
+    }
+}
@@ -0,0 +1,62 @@
+//! Bytes as hex text, for the raw debug stream: the client sends bytes typed
+//! as hex, and a server started with `--debug-raw` logs what it receives
+//! the same way. Lets a new message format be tried against a running
+//! server before either side knows about it.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HexError(String);
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for HexError {}
+
+/// Parses hex such as `93 a1 41 c3` or `0x93A141C3`. Whitespace, `:` and
+/// `,` are ignored wherever they are, so dumps from most tools paste in
+/// as-is.
+pub fn parse_hex(input: &str) -> Result<Vec<u8>, HexError> {
+    let trimmed = input.trim();
+    let digits = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed);
+
+    let mut nibbles = Vec::new();
+    for ch in digits.chars() {
+        if ch.is_whitespace() || ch == ':' || ch == ',' {
+            continue;
+        }
+        let nibble = ch
+            .to_digit(16)
+            .ok_or_else(|| HexError(format!("'{ch}' is not a hex digit")))?;
+        nibbles.push(nibble as u8);
+    }
+    if nibbles.is_empty() {
+        return Err(HexError("no bytes given".to_string()));
+    }
+    if nibbles.len() % 2 != 0 {
+        return Err(HexError(format!(
+            "{} hex digits is not a whole number of bytes",
+            nibbles.len()
+        )));
+    }
+    Ok(nibbles
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect())
+}
+
+/// `bytes` as lowercase hex pairs separated by spaces, which
+/// [`parse_hex`] reads back.
+pub fn format_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,143 @@
+//! Key combinations sent whole, for shortcuts the client's own OS keeps to
+//! itself and capture never sees, such as Ctrl+Alt+Del. The client sends a
+//! [`KeyCombo`] from a button instead, and the server presses its keys in
+//! order and releases them in reverse, with no other key in between.
+//!
+//! Whether the target acts on it depends on its OS:
+//!
+//! - Windows only opens the secure attention screen for Ctrl+Alt+Del typed
+//!   on real hardware, or sent through `SendSAS` where policy allows
+//!   software to. Injected as ordinary keys it does nothing there, so a
+//!   Windows server calls `SendSAS` for Ctrl+Alt+Del instead.
+//! - Linux switches to virtual console *n* on Ctrl+Alt+F*n* when the kernel
+//!   sees the keys, as it does from the server's virtual device, unless the
+//!   display server turns switching off (e.g. X's `DontVTSwitch`). Console
+//!   switching needs the uinput device; rdev's simulation is only seen by X.
+//!   Ctrl+Alt+Del is an ordinary shortcut to a Linux desktop, but on a text
+//!   console systemd takes it as a request to reboot.
+//! - macOS has no such sequence.
+
+use rdev::{EventType, Key};
+use serde::{Deserialize, Serialize};
+
+/// More than any real shortcut needs; longer combos are refused.
+pub const MAX_COMBO_KEYS: usize = 6;
+
+/// Keys to press together and release again.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "ComboWire", into = "ComboWire")]
+pub struct KeyCombo {
+    keys: Vec<Key>,
+}
+
+/// How [`KeyCombo`] goes on the wire, tagged so no other value can be
+/// mistaken for it.
+#[derive(Clone, Deserialize, Serialize)]
+enum ComboWire {
+    Combo(Vec<Key>),
+}
+
+impl From<ComboWire> for KeyCombo {
+    fn from(ComboWire::Combo(keys): ComboWire) -> Self {
+        KeyCombo { keys }
+    }
+}
+
+impl From<KeyCombo> for ComboWire {
+    fn from(combo: KeyCombo) -> Self {
+        ComboWire::Combo(combo.keys)
+    }
+}
+
+impl KeyCombo {
+    pub fn new(keys: Vec<Key>) -> Self {
+        Self { keys }
+    }
+
+    pub fn ctrl_alt_del() -> Self {
+        Self::new(vec![Key::ControlLeft, Key::Alt, Key::Delete])
+    }
+
+    /// Ctrl+Alt+F`n`, switching Linux to virtual console `n`; `None` unless
+    /// `n` is 1 to 12.
+    pub fn ctrl_alt_f(n: u8) -> Option<Self> {
+        let function_key = match n {
+            1 => Key::F1,
+            2 => Key::F2,
+            3 => Key::F3,
+            4 => Key::F4,
+            5 => Key::F5,
+            6 => Key::F6,
+            7 => Key::F7,
+            8 => Key::F8,
+            9 => Key::F9,
+            10 => Key::F10,
+            11 => Key::F11,
+            12 => Key::F12,
+            _ => return None,
+        };
+        Some(Self::new(vec![Key::ControlLeft, Key::Alt, function_key]))
+    }
+
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    /// Why the server should refuse this combo, if it should: it must have
+    /// keys, no more than [`MAX_COMBO_KEYS`], none of them twice.
+    pub fn problem(&self) -> Option<String> {
+        if self.keys.is_empty() {
+            return Some("a key combination needs at least one key".to_string());
+        }
+        if self.keys.len() > MAX_COMBO_KEYS {
+            return Some(format!(
+                "{} keys is more than a key combination may have ({MAX_COMBO_KEYS})",
+                self.keys.len()
+            ));
+        }
+        let repeated = self
+            .keys
+            .iter()
+            .enumerate()
+            .find(|(index, key)| self.keys[..*index].contains(key));
+        repeated.map(|(_, key)| format!("{key:?} appears twice in a key combination"))
+    }
+
+    /// The presses, in order, then the releases, in reverse. Keys in
+    /// `already_held` are left out both ways, so they stay down afterwards.
+    pub fn events(&self, already_held: &[Key]) -> Vec<EventType> {
+        let keys: Vec<Key> = self
+            .keys
+            .iter()
+            .copied()
+            .filter(|key| !already_held.contains(key))
+            .collect();
+        let presses = keys.iter().map(|key| EventType::KeyPress(*key));
+        let releases = keys.iter().rev().map(|key| EventType::KeyRelease(*key));
+        presses.chain(releases).collect()
+    }
+
+    /// The keys joined with `+`, e.g. `ControlLeft+Alt+Delete`.
+    pub fn describe(&self) -> String {
+        self.keys
+            .iter()
+            .map(|key| format!("{key:?}"))
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+}
+
+/// What the client offers to send, with the label it shows for each:
+/// Ctrl+Alt+Del for Windows targets, then console switches for Linux ones.
+pub fn secure_attention_combos() -> Vec<(String, KeyCombo)> {
+    let mut combos = vec![(
+        "Ctrl+Alt+Del (Windows)".to_string(),
+        KeyCombo::ctrl_alt_del(),
+    )];
+    for n in 1..=7 {
+        if let Some(combo) = KeyCombo::ctrl_alt_f(n) {
+            combos.push((format!("Ctrl+Alt+F{n} (Linux console {n})"), combo));
+        }
+    }
+    combos
+}
@@ -0,0 +1,325 @@
+//! Keyboard layouts for typing by character instead of by keycode.
+//!
+//! Raw keycodes name physical positions, so a QWERTY client driving an
+//! AZERTY server types the wrong characters. In translation mode the client
+//! resolves each press to the character its own layout produces and sends a
+//! [`CharInput`](crate::CharInput); the server looks that character up in
+//! its layout and synthesises whatever key and modifiers type it there.
+
+use rdev::{EventType, Key};
+
+/// A key plus the modifiers that select the character it types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keystroke {
+    pub key: Key,
+    pub shift: bool,
+    pub alt_gr: bool,
+}
+
+impl Keystroke {
+    pub const fn plain(key: Key) -> Self {
+        Self {
+            key,
+            shift: false,
+            alt_gr: false,
+        }
+    }
+
+    pub const fn shifted(key: Key) -> Self {
+        Self {
+            key,
+            shift: true,
+            alt_gr: false,
+        }
+    }
+
+    pub const fn alt_gr(key: Key) -> Self {
+        Self {
+            key,
+            shift: false,
+            alt_gr: true,
+        }
+    }
+
+    /// Events that type this keystroke while `held` keys are already down.
+    ///
+    /// Modifiers the keystroke needs are pressed around it, and held ones it
+    /// must not see are lifted and put back afterwards, so the peer's own
+    /// modifier state is left exactly as it was.
+    pub fn events(&self, held: &[Key]) -> Vec<EventType> {
+        let mut before = Vec::new();
+        adjust_modifier(
+            &mut before,
+            self.shift,
+            Key::ShiftLeft,
+            &[Key::ShiftLeft, Key::ShiftRight],
+            held,
+        );
+        adjust_modifier(&mut before, self.alt_gr, Key::AltGr, &[Key::AltGr], held);
+
+        let after: Vec<EventType> = before.iter().rev().map(invert).collect();
+        let mut events = before;
+        events.push(EventType::KeyPress(self.key));
+        events.push(EventType::KeyRelease(self.key));
+        events.extend(after);
+        events
+    }
+}
+
+fn adjust_modifier(
+    events: &mut Vec<EventType>,
+    wanted: bool,
+    press: Key,
+    variants: &[Key],
+    held: &[Key],
+) {
+    let down: Vec<Key> = variants
+        .iter()
+        .copied()
+        .filter(|key| held.contains(key))
+        .collect();
+    if wanted && down.is_empty() {
+        events.push(EventType::KeyPress(press));
+    } else if !wanted {
+        events.extend(down.into_iter().map(EventType::KeyRelease));
+    }
+}
+
+fn invert(event: &EventType) -> EventType {
+    match *event {
+        EventType::KeyPress(key) => EventType::KeyRelease(key),
+        EventType::KeyRelease(key) => EventType::KeyPress(key),
+        other => other,
+    }
+}
+
+/// Maps between characters and the keystrokes that type them.
+pub trait KeyboardLayout {
+    /// Character typed by `stroke`, if it types one.
+    fn char_for(&self, stroke: Keystroke) -> Option<char>;
+
+    /// Keystroke that types `ch`, if the layout can type it at all.
+    fn keystroke_for(&self, ch: char) -> Option<Keystroke>;
+}
+
+/// A fixed layout described by a table of characters.
+///
+/// Only base-level letters need listing: their shifted form is the upper
+/// case letter on the same key.
+#[derive(Debug)]
+pub struct LayoutTable {
+    pub name: &'static str,
+    entries: &'static [(char, Keystroke)],
+}
+
+impl LayoutTable {
+    /// Looks a built-in layout up by its config name.
+    pub fn by_name(name: &str) -> Option<&'static LayoutTable> {
+        BUILTIN_LAYOUTS
+            .iter()
+            .copied()
+            .find(|layout| layout.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl KeyboardLayout for LayoutTable {
+    fn char_for(&self, stroke: Keystroke) -> Option<char> {
+        let exact = self
+            .entries
+            .iter()
+            .find(|(_, entry)| *entry == stroke)
+            .map(|(ch, _)| *ch);
+        if exact.is_some() || !stroke.shift || stroke.alt_gr {
+            return exact;
+        }
+
+        self.char_for(Keystroke::plain(stroke.key))
+            .filter(char::is_ascii_lowercase)
+            .map(|ch| ch.to_ascii_uppercase())
+    }
+
+    fn keystroke_for(&self, ch: char) -> Option<Keystroke> {
+        let exact = self
+            .entries
+            .iter()
+            .find(|(entry, _)| *entry == ch)
+            .map(|(_, stroke)| *stroke);
+        if exact.is_some() || !ch.is_ascii_uppercase() {
+            return exact;
+        }
+
+        self.keystroke_for(ch.to_ascii_lowercase())
+            .filter(|stroke| *stroke == Keystroke::plain(stroke.key))
+            .map(|stroke| Keystroke::shifted(stroke.key))
+    }
+}
+
+pub const BUILTIN_LAYOUTS: &[&LayoutTable] = &[&US_QWERTY, &FR_AZERTY];
+
+pub static US_QWERTY: LayoutTable = LayoutTable {
+    name: "us",
+    entries: &[
+        (' ', Keystroke::plain(Key::Space)),
+        ('\t', Keystroke::plain(Key::Tab)),
+        ('\n', Keystroke::plain(Key::Return)),
+        ('`', Keystroke::plain(Key::BackQuote)),
+        ('~', Keystroke::shifted(Key::BackQuote)),
+        ('1', Keystroke::plain(Key::Num1)),
+        ('!', Keystroke::shifted(Key::Num1)),
+        ('2', Keystroke::plain(Key::Num2)),
+        ('@', Keystroke::shifted(Key::Num2)),
+        ('3', Keystroke::plain(Key::Num3)),
+        ('#', Keystroke::shifted(Key::Num3)),
+        ('4', Keystroke::plain(Key::Num4)),
+        ('$', Keystroke::shifted(Key::Num4)),
+        ('5', Keystroke::plain(Key::Num5)),
+        ('%', Keystroke::shifted(Key::Num5)),
+        ('6', Keystroke::plain(Key::Num6)),
+        ('^', Keystroke::shifted(Key::Num6)),
+        ('7', Keystroke::plain(Key::Num7)),
+        ('&', Keystroke::shifted(Key::Num7)),
+        ('8', Keystroke::plain(Key::Num8)),
+        ('*', Keystroke::shifted(Key::Num8)),
+        ('9', Keystroke::plain(Key::Num9)),
+        ('(', Keystroke::shifted(Key::Num9)),
+        ('0', Keystroke::plain(Key::Num0)),
+        (')', Keystroke::shifted(Key::Num0)),
+        ('-', Keystroke::plain(Key::Minus)),
+        ('_', Keystroke::shifted(Key::Minus)),
+        ('=', Keystroke::plain(Key::Equal)),
+        ('+', Keystroke::shifted(Key::Equal)),
+        ('q', Keystroke::plain(Key::KeyQ)),
+        ('w', Keystroke::plain(Key::KeyW)),
+        ('e', Keystroke::plain(Key::KeyE)),
+        ('r', Keystroke::plain(Key::KeyR)),
+        ('t', Keystroke::plain(Key::KeyT)),
+        ('y', Keystroke::plain(Key::KeyY)),
+        ('u', Keystroke::plain(Key::KeyU)),
+        ('i', Keystroke::plain(Key::KeyI)),
+        ('o', Keystroke::plain(Key::KeyO)),
+        ('p', Keystroke::plain(Key::KeyP)),
+        ('[', Keystroke::plain(Key::LeftBracket)),
+        ('{', Keystroke::shifted(Key::LeftBracket)),
+        (']', Keystroke::plain(Key::RightBracket)),
+        ('}', Keystroke::shifted(Key::RightBracket)),
+        ('\\', Keystroke::plain(Key::BackSlash)),
+        ('|', Keystroke::shifted(Key::BackSlash)),
+        ('a', Keystroke::plain(Key::KeyA)),
+        ('s', Keystroke::plain(Key::KeyS)),
+        ('d', Keystroke::plain(Key::KeyD)),
+        ('f', Keystroke::plain(Key::KeyF)),
+        ('g', Keystroke::plain(Key::KeyG)),
+        ('h', Keystroke::plain(Key::KeyH)),
+        ('j', Keystroke::plain(Key::KeyJ)),
+        ('k', Keystroke::plain(Key::KeyK)),
+        ('l', Keystroke::plain(Key::KeyL)),
+        (';', Keystroke::plain(Key::SemiColon)),
+        (':', Keystroke::shifted(Key::SemiColon)),
+        ('\'', Keystroke::plain(Key::Quote)),
+        ('"', Keystroke::shifted(Key::Quote)),
+        ('z', Keystroke::plain(Key::KeyZ)),
+        ('x', Keystroke::plain(Key::KeyX)),
+        ('c', Keystroke::plain(Key::KeyC)),
+        ('v', Keystroke::plain(Key::KeyV)),
+        ('b', Keystroke::plain(Key::KeyB)),
+        ('n', Keystroke::plain(Key::KeyN)),
+        ('m', Keystroke::plain(Key::KeyM)),
+        (',', Keystroke::plain(Key::Comma)),
+        ('<', Keystroke::shifted(Key::Comma)),
+        ('.', Keystroke::plain(Key::Dot)),
+        ('>', Keystroke::shifted(Key::Dot)),
+        ('/', Keystroke::plain(Key::Slash)),
+        ('?', Keystroke::shifted(Key::Slash)),
+    ],
+};
+
+/// French AZERTY. Dead keys (`^`, `¨`) are left out since they type
+/// nothing on their own.
+pub static FR_AZERTY: LayoutTable = LayoutTable {
+    name: "fr",
+    entries: &[
+        (' ', Keystroke::plain(Key::Space)),
+        ('\t', Keystroke::plain(Key::Tab)),
+        ('\n', Keystroke::plain(Key::Return)),
+        ('²', Keystroke::plain(Key::BackQuote)),
+        ('&', Keystroke::plain(Key::Num1)),
+        ('1', Keystroke::shifted(Key::Num1)),
+        ('é', Keystroke::plain(Key::Num2)),
+        ('2', Keystroke::shifted(Key::Num2)),
+        ('~', Keystroke::alt_gr(Key::Num2)),
+        ('"', Keystroke::plain(Key::Num3)),
+        ('3', Keystroke::shifted(Key::Num3)),
+        ('#', Keystroke::alt_gr(Key::Num3)),
+        ('\'', Keystroke::plain(Key::Num4)),
+        ('4', Keystroke::shifted(Key::Num4)),
+        ('{', Keystroke::alt_gr(Key::Num4)),
+        ('(', Keystroke::plain(Key::Num5)),
+        ('5', Keystroke::shifted(Key::Num5)),
+        ('[', Keystroke::alt_gr(Key::Num5)),
+        ('-', Keystroke::plain(Key::Num6)),
+        ('6', Keystroke::shifted(Key::Num6)),
+        ('|', Keystroke::alt_gr(Key::Num6)),
+        ('è', Keystroke::plain(Key::Num7)),
+        ('7', Keystroke::shifted(Key::Num7)),
+        ('`', Keystroke::alt_gr(Key::Num7)),
+        ('_', Keystroke::plain(Key::Num8)),
+        ('8', Keystroke::shifted(Key::Num8)),
+        ('\\', Keystroke::alt_gr(Key::Num8)),
+        ('ç', Keystroke::plain(Key::Num9)),
+        ('9', Keystroke::shifted(Key::Num9)),
+        ('^', Keystroke::alt_gr(Key::Num9)),
+        ('à', Keystroke::plain(Key::Num0)),
+        ('0', Keystroke::shifted(Key::Num0)),
+        ('@', Keystroke::alt_gr(Key::Num0)),
+        (')', Keystroke::plain(Key::Minus)),
+        ('°', Keystroke::shifted(Key::Minus)),
+        (']', Keystroke::alt_gr(Key::Minus)),
+        ('=', Keystroke::plain(Key::Equal)),
+        ('+', Keystroke::shifted(Key::Equal)),
+        ('}', Keystroke::alt_gr(Key::Equal)),
+        ('a', Keystroke::plain(Key::KeyQ)),
+        ('z', Keystroke::plain(Key::KeyW)),
+        ('e', Keystroke::plain(Key::KeyE)),
+        ('€', Keystroke::alt_gr(Key::KeyE)),
+        ('r', Keystroke::plain(Key::KeyR)),
+        ('t', Keystroke::plain(Key::KeyT)),
+        ('y', Keystroke::plain(Key::KeyY)),
+        ('u', Keystroke::plain(Key::KeyU)),
+        ('i', Keystroke::plain(Key::KeyI)),
+        ('o', Keystroke::plain(Key::KeyO)),
+        ('p', Keystroke::plain(Key::KeyP)),
+        ('$', Keystroke::plain(Key::RightBracket)),
+        ('£', Keystroke::shifted(Key::RightBracket)),
+        ('¤', Keystroke::alt_gr(Key::RightBracket)),
+        ('q', Keystroke::plain(Key::KeyA)),
+        ('s', Keystroke::plain(Key::KeyS)),
+        ('d', Keystroke::plain(Key::KeyD)),
+        ('f', Keystroke::plain(Key::KeyF)),
+        ('g', Keystroke::plain(Key::KeyG)),
+        ('h', Keystroke::plain(Key::KeyH)),
+        ('j', Keystroke::plain(Key::KeyJ)),
+        ('k', Keystroke::plain(Key::KeyK)),
+        ('l', Keystroke::plain(Key::KeyL)),
+        ('m', Keystroke::plain(Key::SemiColon)),
+        ('ù', Keystroke::plain(Key::Quote)),
+        ('%', Keystroke::shifted(Key::Quote)),
+        ('*', Keystroke::plain(Key::BackSlash)),
+        ('µ', Keystroke::shifted(Key::BackSlash)),
+        ('<', Keystroke::plain(Key::IntlBackslash)),
+        ('>', Keystroke::shifted(Key::IntlBackslash)),
+        ('w', Keystroke::plain(Key::KeyZ)),
+        ('x', Keystroke::plain(Key::KeyX)),
+        ('c', Keystroke::plain(Key::KeyC)),
+        ('v', Keystroke::plain(Key::KeyV)),
+        ('b', Keystroke::plain(Key::KeyB)),
+        ('n', Keystroke::plain(Key::KeyN)),
+        (',', Keystroke::plain(Key::KeyM)),
+        ('?', Keystroke::shifted(Key::KeyM)),
+        (';', Keystroke::plain(Key::Comma)),
+        ('.', Keystroke::shifted(Key::Comma)),
+        (':', Keystroke::plain(Key::Dot)),
+        ('/', Keystroke::shifted(Key::Dot)),
+        ('!', Keystroke::plain(Key::Slash)),
+        ('§', Keystroke::shifted(Key::Slash)),
+    ],
+};
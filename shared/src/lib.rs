@@ -1,6 +1,514 @@
+use codec::WireFormat;
+use quinn::VarInt;
+use rdev::EventType;
 use serde::{Deserialize, Serialize};
-#[derive(Debug, PartialEq, Deserialize, Serialize)]
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+pub mod clicks;
+pub mod codec;
+pub mod congestion;
+pub mod extra_keys;
+pub mod hex;
+pub mod key_combo;
+pub mod layout;
+pub mod raw_keys;
+pub mod script;
+pub mod session;
+pub mod system_keys;
+
+/// Relative pointer motion in pixels, as capture sends it. Not to be confused
+/// with rdev's `EventType::MouseMove`, which is an absolute position on the
+/// desktop; the server accepts that too and moves the pointer straight there.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
 pub struct MouseMove {
     pub dx: f64,
     pub dy: f64,
-}
\ No newline at end of file
+}
+
+/// A pointer position in absolute mode, as fractions of the sender's capture
+/// area: (0, 0) is its top-left corner and (1, 1) its bottom-right. Only
+/// applied once the server has granted [`PointerMode::Absolute`].
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(from = "AbsoluteWire", into = "AbsoluteWire")]
+pub struct AbsoluteMove {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// How [`AbsoluteMove`] goes on the wire. A bare pair of floats would be
+/// read as a [`MouseMove`], so it is tagged.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+enum AbsoluteWire {
+    Absolute(f64, f64),
+}
+
+impl From<AbsoluteWire> for AbsoluteMove {
+    fn from(AbsoluteWire::Absolute(x, y): AbsoluteWire) -> Self {
+        AbsoluteMove { x, y }
+    }
+}
+
+impl From<AbsoluteMove> for AbsoluteWire {
+    fn from(absolute: AbsoluteMove) -> Self {
+        AbsoluteWire::Absolute(absolute.x, absolute.y)
+    }
+}
+
+/// Whether the client forwards pointer motion as [`MouseMove`] deltas or as
+/// [`AbsoluteMove`] positions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PointerMode {
+    #[default]
+    Relative,
+    Absolute,
+}
+
+/// A character to type on the server, sent instead of a raw key press when
+/// the client translates between keyboard layouts. See [`layout`].
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct CharInput(pub char);
+
+/// A side of a display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// Sent on the mouse stream, alongside the move that caused it, when the
+/// pointer is pushed into an edge of the server's display. The building
+/// block for handing control to a neighbouring machine.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct EdgeHit(pub Edge);
+
+/// Touchpad input richer than whole wheel notches, sent on the mouse stream.
+/// Servers turn it into wheel events, so it degrades to plain scrolling
+/// wherever nothing better can be injected.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum Gesture {
+    /// Two-finger scroll by this many pixels; positive `dy` scrolls up and
+    /// positive `dx` right, as with [`EventType::Wheel`].
+    Scroll { dx: f64, dy: f64 },
+    /// Pinch by `scale` relative to the previous pinch event: above 1.0
+    /// zooms in, below zooms out.
+    Pinch { scale: f64 },
+}
+
+/// When the client sent the input that follows it on the same stream, in
+/// [`monotonic_micros`] of the client's clock. Lets the server measure
+/// end-to-end latency once it knows the offset from the hello.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct SentAt {
+    pub micros: u64,
+}
+
+/// How often the client sends [`KeepHeld`] while input is held and nothing
+/// else goes out. Well under the shortest idle release a server can be
+/// configured with, a second.
+pub const KEEP_HELD_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Sent on the keyboard stream while a key or button is held and nothing
+/// else is, so a server releasing held input after a silence doesn't let go
+/// of what the user is still holding down. A server that doesn't know it
+/// skips it as an unknown value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "KeepHeldWire", into = "KeepHeldWire")]
+pub struct KeepHeld;
+
+/// How [`KeepHeld`] goes on the wire, tagged so no other value can be
+/// mistaken for it.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+enum KeepHeldWire {
+    KeepHeld,
+}
+
+impl From<KeepHeldWire> for KeepHeld {
+    fn from(KeepHeldWire::KeepHeld: KeepHeldWire) -> Self {
+        KeepHeld
+    }
+}
+
+impl From<KeepHeld> for KeepHeldWire {
+    fn from(KeepHeld: KeepHeld) -> Self {
+        KeepHeldWire::KeepHeld
+    }
+}
+
+/// Which counter a [`Seq`] comes from. Each kind of input is numbered on
+/// its own, so splitting them over separate streams never looks like loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum SeqCategory {
+    Mouse,
+    Keyboard,
+}
+
+/// Header numbering the message that follows it on the same stream, counting
+/// up from 0 per [`SeqCategory`] for the life of a connection. Lets the
+/// server count lost and reordered input for diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Seq {
+    pub category: SeqCategory,
+    pub seq: u64,
+}
+
+/// Microseconds on a monotonic clock that starts with the process. Only
+/// meaningful within one process; peers compare them via a known offset.
+pub fn monotonic_micros() -> u64 {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    let elapsed = EPOCH.get_or_init(Instant::now).elapsed();
+    u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX)
+}
+
+/// Identifies which local device an input came from, e.g. a foot pedal
+/// alongside the main keyboard.
+pub type SourceId = u32;
+
+/// Input tagged with the device that produced it. Untagged `MouseMove` and
+/// event values remain valid on the wire, so the tag is optional.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct Sourced<T> {
+    pub source_id: SourceId,
+    pub input: T,
+}
+
+/// Opaque handle the server issues so a dropped client can resume its session.
+pub type SessionToken = [u8; 16];
+
+/// Requests a client sends on a control (bi) stream; the server answers each
+/// with exactly one [`ControlResponse`] before finishing the stream, except
+/// [`ControlRequest::WatchInjection`], which is answered until it is closed.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub enum ControlRequest {
+    Hello {
+        resume_token: Option<SessionToken>,
+        /// Join as an observer: watch the input the server applies from other
+        /// clients without being able to inject any.
+        #[serde(default)]
+        observe: bool,
+        /// The client's [`monotonic_micros`] when it sent this hello, so the
+        /// server can line up [`SentAt`] stamps with its own clock.
+        #[serde(default)]
+        clock_micros: Option<u64>,
+        /// Size of the display the client maps absolute positions from, so
+        /// the server can keep its aspect ratio; see [`AbsoluteMove`].
+        #[serde(default)]
+        display_size: Option<DisplaySize>,
+        /// How the client will encode its uni streams.
+        #[serde(default)]
+        wire_format: WireFormat,
+    },
+    /// Asks for the server's displays, answered with [`ControlResponse::Displays`].
+    Displays,
+    /// Sent before a graceful close: the server answers with
+    /// [`ControlResponse::Drained`] once the listed uni streams (by QUIC
+    /// stream id) have been read to their end and applied.
+    Drain { streams: Vec<u64> },
+    /// Asks for a [`ControlResponse::Injection`] every second, for as long as
+    /// the stream stays open.
+    WatchInjection,
+    /// Asks to switch how pointer motion is sent, answered with
+    /// [`ControlResponse::PointerMode`] holding the mode now in effect.
+    SetPointerMode(PointerMode),
+    /// Replaces the display size from the hello, e.g. once capture is
+    /// locked to another monitor. Answered with
+    /// [`ControlResponse::DisplaySize`].
+    SetDisplaySize(DisplaySize),
+    /// Moves the pointer to a spot on the server's primary display, as a
+    /// fraction of that display rather than the client's, whatever the
+    /// pointer mode. Answered with [`ControlResponse::Warped`].
+    Warp(AbsoluteMove),
+    /// Asks to be told whether the server's machine can take input, with a
+    /// [`ControlResponse::RemoteAvailable`] or
+    /// [`ControlResponse::RemoteUnavailable`] now and on every change, for
+    /// as long as the stream stays open. Only answered by servers whose
+    /// welcome set `reports_availability`.
+    WatchAvailability,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub enum ControlResponse {
+    Welcome {
+        token: SessionToken,
+        resumed: bool,
+        /// Set when the server accepted an observer; applied input then
+        /// arrives as [`ObservedInput`] values on a uni stream it opens.
+        #[serde(default)]
+        observing: bool,
+        /// The format the server will read the client's uni streams as;
+        /// servers that predate the choice leave it out, meaning
+        /// MessagePack.
+        #[serde(default)]
+        wire_format: WireFormat,
+        /// Set when the server watches for its screen locking and will
+        /// answer [`ControlRequest::WatchAvailability`].
+        #[serde(default)]
+        reports_availability: bool,
+    },
+    Displays(Vec<DisplayInfo>),
+    /// `complete` is false if the server gave up waiting on some stream.
+    Drained { complete: bool },
+    Injection(InjectionStats),
+    /// Relative when absolute was asked for but can't be injected here.
+    PointerMode(PointerMode),
+    /// The client display size now in use.
+    DisplaySize(DisplaySize),
+    /// Whether a [`ControlRequest::Warp`] moved the pointer: not where it
+    /// can't be placed absolutely, or for an observer.
+    Warped(bool),
+    /// The server's machine takes input again.
+    RemoteAvailable,
+    /// Input sent now would go nowhere useful, e.g. into a lock screen;
+    /// the client should pause capture and release what it holds.
+    RemoteUnavailable(UnavailableReason),
+}
+
+/// Why the server's machine can't take input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum UnavailableReason {
+    ScreenLocked,
+    /// The session has been idle long enough for the desktop to say so.
+    /// Only older servers send it; pausing for it kept back the input that
+    /// would have woken the session.
+    SessionIdle,
+}
+
+impl fmt::Display for UnavailableReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnavailableReason::ScreenLocked => f.write_str("screen is locked"),
+            UnavailableReason::SessionIdle => f.write_str("session is idle"),
+        }
+    }
+}
+
+/// How much input the server has injected, and how much it had to drop
+/// (e.g. because its virtual mouse is unavailable), since it started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct InjectionStats {
+    /// Pointer motion, buttons and the wheel.
+    pub mouse_injected: u64,
+    pub mouse_dropped: u64,
+    pub keys_injected: u64,
+    pub keys_dropped: u64,
+    /// Events waiting for the server's simulator threads right now. Keeps
+    /// growing if one is stuck.
+    #[serde(default)]
+    pub mouse_queued: u64,
+    #[serde(default)]
+    pub keys_queued: u64,
+}
+
+/// Input the server applied on behalf of some client, as streamed to observers.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub enum ObservedInput {
+    Mouse(MouseMove),
+    Event(EventType),
+}
+
+/// Width and height of a display in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DisplaySize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One display of a machine, in its global desktop coordinates.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DisplayInfo {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub is_primary: bool,
+}
+
+impl DisplayInfo {
+    /// The primary display, falling back to the first one listed.
+    pub fn primary(displays: &[DisplayInfo]) -> Option<&DisplayInfo> {
+        displays
+            .iter()
+            .find(|display| display.is_primary)
+            .or_else(|| displays.first())
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let (left, top) = (f64::from(self.x), f64::from(self.y));
+        x >= left
+            && y >= top
+            && x < left + f64::from(self.width)
+            && y < top + f64::from(self.height)
+    }
+
+    /// Nearest point inside this display.
+    pub fn clamp(&self, x: f64, y: f64) -> (f64, f64) {
+        let (left, top) = (f64::from(self.x), f64::from(self.y));
+        let right = left + f64::from(self.width.saturating_sub(1));
+        let bottom = top + f64::from(self.height.saturating_sub(1));
+        (x.clamp(left, right), y.clamp(top, bottom))
+    }
+
+    /// Where `x`, `y` falls on this display as an [`AbsoluteMove`], clamped
+    /// to its edges.
+    pub fn fraction_at(&self, x: f64, y: f64) -> AbsoluteMove {
+        let (x, y) = self.clamp(x, y);
+        AbsoluteMove {
+            x: (x - f64::from(self.x)) / f64::from(self.width.saturating_sub(1).max(1)),
+            y: (y - f64::from(self.y)) / f64::from(self.height.saturating_sub(1).max(1)),
+        }
+    }
+
+    /// The point on this display an [`AbsoluteMove`] stands for, clamped
+    /// so it always lands on screen.
+    pub fn point_at(&self, absolute: AbsoluteMove) -> (f64, f64) {
+        self.clamp(
+            f64::from(self.x) + absolute.x * f64::from(self.width.saturating_sub(1)),
+            f64::from(self.y) + absolute.y * f64::from(self.height.saturating_sub(1)),
+        )
+    }
+
+    /// Maps a point on `source` to the same relative spot on this display,
+    /// clamped so it always lands on screen.
+    pub fn scale_from(&self, source: &DisplayInfo, x: f64, y: f64) -> (f64, f64) {
+        let fraction_x = (x - f64::from(source.x)) / f64::from(source.width.max(1));
+        let fraction_y = (y - f64::from(source.y)) / f64::from(source.height.max(1));
+        self.clamp(
+            f64::from(self.x) + fraction_x * f64::from(self.width),
+            f64::from(self.y) + fraction_y * f64::from(self.height),
+        )
+    }
+}
+
+/// Why a peer closed the connection, carried as the QUIC application error
+/// code. Both sides use these so each can tell the user what happened.
+///
+/// The numeric values are part of the protocol: never renumber a code, only
+/// add new ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseCode {
+    UserDisconnect,
+    Reset,
+    ProtocolError,
+    ServerFull,
+    /// The peer could not prove it is allowed to connect.
+    AuthFailed,
+    /// The peers speak versions of the protocol that can't work together.
+    Incompatible,
+    /// The server is shutting down.
+    Shutdown,
+    /// Stops a stream opened while the connection already had as many in
+    /// flight as the server allows.
+    StreamLimit,
+    /// The same client reconnected and took this connection's session over.
+    Superseded,
+    /// The peer's address is not on the server's allowlist. Only older
+    /// servers send it; newer ones refuse such peers before the handshake.
+    NotAllowed,
+    /// Stops a stream that sent a value larger than the receiver reads.
+    TooLarge,
+}
+
+/// An application close code this build doesn't know, e.g. from a newer peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownCloseCode(pub u64);
+
+impl fmt::Display for UnknownCloseCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown close code {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCloseCode {}
+
+impl CloseCode {
+    pub fn code(self) -> u32 {
+        match self {
+            CloseCode::UserDisconnect => 0,
+            CloseCode::Reset => 1,
+            CloseCode::ProtocolError => 2,
+            CloseCode::ServerFull => 3,
+            CloseCode::AuthFailed => 4,
+            CloseCode::Incompatible => 5,
+            CloseCode::Shutdown => 6,
+            CloseCode::StreamLimit => 7,
+            CloseCode::Superseded => 8,
+            CloseCode::NotAllowed => 9,
+            CloseCode::TooLarge => 10,
+        }
+    }
+
+    pub fn from_code(code: u64) -> Option<Self> {
+        match code {
+            0 => Some(CloseCode::UserDisconnect),
+            1 => Some(CloseCode::Reset),
+            2 => Some(CloseCode::ProtocolError),
+            3 => Some(CloseCode::ServerFull),
+            4 => Some(CloseCode::AuthFailed),
+            5 => Some(CloseCode::Incompatible),
+            6 => Some(CloseCode::Shutdown),
+            7 => Some(CloseCode::StreamLimit),
+            8 => Some(CloseCode::Superseded),
+            9 => Some(CloseCode::NotAllowed),
+            10 => Some(CloseCode::TooLarge),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CloseCode::UserDisconnect => "user-disconnect",
+            CloseCode::Reset => "reset",
+            CloseCode::ProtocolError => "protocol-error",
+            CloseCode::ServerFull => "server-full",
+            CloseCode::AuthFailed => "auth-failed",
+            CloseCode::Incompatible => "incompatible",
+            CloseCode::Shutdown => "shutdown",
+            CloseCode::StreamLimit => "stream-limit",
+            CloseCode::Superseded => "superseded",
+            CloseCode::NotAllowed => "not-allowed",
+            CloseCode::TooLarge => "too-large",
+        }
+    }
+
+    /// What to tell the user when the other side closed with this code.
+    pub fn message(self) -> &'static str {
+        match self {
+            CloseCode::UserDisconnect => "The other side disconnected.",
+            CloseCode::Reset => "The other side reset the connection.",
+            CloseCode::ProtocolError => "The other side sent something it shouldn't have.",
+            CloseCode::ServerFull => "The server has no room for another connection.",
+            CloseCode::AuthFailed => "The server did not accept this client.",
+            CloseCode::Incompatible => "Client and server versions are incompatible.",
+            CloseCode::Shutdown => "The server is shutting down.",
+            CloseCode::StreamLimit => "Too many streams were open at once.",
+            CloseCode::Superseded => "This session was resumed from a newer connection.",
+            CloseCode::NotAllowed => "The server does not accept connections from this address.",
+            CloseCode::TooLarge => "Something sent was too large for the other side to read.",
+        }
+    }
+}
+
+impl From<CloseCode> for VarInt {
+    fn from(code: CloseCode) -> Self {
+        VarInt::from_u32(code.code())
+    }
+}
+
+impl TryFrom<VarInt> for CloseCode {
+    type Error = UnknownCloseCode;
+
+    fn try_from(code: VarInt) -> Result<Self, Self::Error> {
+        let code = code.into_inner();
+        CloseCode::from_code(code).ok_or(UnknownCloseCode(code))
+    }
+}
+
+impl fmt::Display for CloseCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -0,0 +1,87 @@
+//! An escape hatch for keys neither `rdev` nor [`crate::extra_keys`] has a
+//! name for: a [`RawKeyInput`] carries a Linux input event code (`KEY_*`)
+//! that a Linux server emits on its virtual device as-is, so exotic keys
+//! still work without a mapping for each of them.
+//!
+//! A Linux client sends these for any `Key::Unknown` rdev reports that is
+//! not an extra key, as there rdev's keycodes are X11's, which are the
+//! kernel's codes plus 8. Other clients' keycodes can't be turned into
+//! kernel codes, so they don't. Servers on other platforms drop them.
+
+use rdev::{EventType, Key};
+use serde::{Deserialize, Serialize};
+
+use crate::extra_keys::ExtraKey;
+
+/// The highest key code the kernel defines (`KEY_MAX`).
+pub const KEY_MAX: u16 = 0x2ff;
+
+/// Codes below [`KEY_MAX`] that are mouse, joystick and gamepad buttons
+/// rather than keys (`BTN_MISC`–`BTN_GEAR_UP`, the d-pad and
+/// `BTN_TRIGGER_HAPPY*`).
+const BUTTON_CODES: [(u16, u16); 3] = [(0x100, 0x15f), (0x220, 0x223), (0x2c0, 0x2e7)];
+
+/// How far X11 keycodes are offset from the kernel's.
+const X11_OFFSET: u32 = 8;
+
+/// A key press or release by Linux input event code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "RawKeyWire", into = "RawKeyWire")]
+pub struct RawKeyInput {
+    pub code: u16,
+    pub pressed: bool,
+}
+
+/// How [`RawKeyInput`] goes on the wire. A bare code and flag could be read
+/// as other frames, so it is tagged.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+enum RawKeyWire {
+    RawKey(u16, bool),
+}
+
+impl From<RawKeyWire> for RawKeyInput {
+    fn from(RawKeyWire::RawKey(code, pressed): RawKeyWire) -> Self {
+        RawKeyInput { code, pressed }
+    }
+}
+
+impl From<RawKeyInput> for RawKeyWire {
+    fn from(input: RawKeyInput) -> Self {
+        RawKeyWire::RawKey(input.code, input.pressed)
+    }
+}
+
+/// Whether `code` is a key the kernel defines, rather than zero
+/// (`KEY_RESERVED`), a button or out of range.
+pub fn is_key_code(code: u16) -> bool {
+    let is_button = BUTTON_CODES
+        .iter()
+        .any(|&(first, last)| (first..=last).contains(&code));
+    (1..=KEY_MAX).contains(&code) && !is_button
+}
+
+impl RawKeyInput {
+    /// A press or release of `code`, if it is a key code (see
+    /// [`is_key_code`]).
+    pub fn new(code: u16, pressed: bool) -> Option<Self> {
+        is_key_code(code).then_some(RawKeyInput { code, pressed })
+    }
+
+    /// The raw key behind a key event from rdev, on a Linux client, when it
+    /// is neither a named nor an extra key.
+    pub fn from_event(event_type: &EventType) -> Option<Self> {
+        let (key, pressed) = match *event_type {
+            EventType::KeyPress(key) => (key, true),
+            EventType::KeyRelease(key) => (key, false),
+            _ => return None,
+        };
+        let Key::Unknown(keycode) = key else {
+            return None;
+        };
+        if !cfg!(target_os = "linux") || ExtraKey::from_key(key).is_some() {
+            return None;
+        }
+        let code = keycode.checked_sub(X11_OFFSET)?;
+        RawKeyInput::new(u16::try_from(code).ok()?, pressed)
+    }
+}
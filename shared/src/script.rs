@@ -0,0 +1,125 @@
+//! Line-based text form of input events, shared by the server's event dump
+//! and the script client so a recorded session can be replayed as-is.
+//!
+//! One event per line:
+//!
+//! ```text
+//! move <dx> <dy>
+//! pointer <x> <y>
+//! wheel <delta_x> <delta_y>
+//! key press|release <Key>
+//! button press|release <Button>
+//! ```
+//!
+//! Keys and buttons use their `rdev` names (`KeyA`, `ShiftLeft`, `Left`, or
+//! `Unknown(<code>)`). Blank lines and lines starting with `#` are ignored.
+
+use std::fmt;
+
+use rdev::{Button, EventType, Key};
+use serde::{Deserialize, de::IntoDeserializer, de::value::Error as ValueError};
+
+use crate::MouseMove;
+
+/// One parsed script line.
+#[derive(Debug, PartialEq)]
+pub enum ScriptEvent {
+    Move(MouseMove),
+    Input(EventType),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn format_move(mouse_move: &MouseMove) -> String {
+    format!("move {} {}", mouse_move.dx, mouse_move.dy)
+}
+
+pub fn format_event(event: &EventType) -> String {
+    match event {
+        EventType::KeyPress(key) => format!("key press {key:?}"),
+        EventType::KeyRelease(key) => format!("key release {key:?}"),
+        EventType::ButtonPress(button) => format!("button press {button:?}"),
+        EventType::ButtonRelease(button) => format!("button release {button:?}"),
+        EventType::MouseMove { x, y } => format!("pointer {x} {y}"),
+        EventType::Wheel { delta_x, delta_y } => format!("wheel {delta_x} {delta_y}"),
+    }
+}
+
+pub fn format_script_event(event: &ScriptEvent) -> String {
+    match event {
+        ScriptEvent::Move(mouse_move) => format_move(mouse_move),
+        ScriptEvent::Input(event) => format_event(event),
+    }
+}
+
+/// Parses one line, returning `None` for blank lines and comments.
+pub fn parse_line(line: &str) -> Result<Option<ScriptEvent>, ParseError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let event = match words.as_slice() {
+        ["move", dx, dy] => ScriptEvent::Move(MouseMove {
+            dx: parse_number(dx)?,
+            dy: parse_number(dy)?,
+        }),
+        ["pointer", x, y] => ScriptEvent::Input(EventType::MouseMove {
+            x: parse_number(x)?,
+            y: parse_number(y)?,
+        }),
+        ["wheel", delta_x, delta_y] => ScriptEvent::Input(EventType::Wheel {
+            delta_x: parse_number(delta_x)?,
+            delta_y: parse_number(delta_y)?,
+        }),
+        ["key", "press", key] => ScriptEvent::Input(EventType::KeyPress(parse_key(key)?)),
+        ["key", "release", key] => ScriptEvent::Input(EventType::KeyRelease(parse_key(key)?)),
+        ["button", "press", button] => {
+            ScriptEvent::Input(EventType::ButtonPress(parse_button(button)?))
+        }
+        ["button", "release", button] => {
+            ScriptEvent::Input(EventType::ButtonRelease(parse_button(button)?))
+        }
+        _ => return Err(ParseError(format!("unrecognised script line '{line}'"))),
+    };
+
+    Ok(Some(event))
+}
+
+fn parse_number<T: std::str::FromStr>(word: &str) -> Result<T, ParseError> {
+    word.parse()
+        .map_err(|_| ParseError(format!("invalid number '{word}'")))
+}
+
+fn parse_key(word: &str) -> Result<Key, ParseError> {
+    if let Some(code) = unknown_code(word) {
+        return Ok(Key::Unknown(parse_number(code)?));
+    }
+    parse_variant(word).map_err(|_| ParseError(format!("unknown key '{word}'")))
+}
+
+fn parse_button(word: &str) -> Result<Button, ParseError> {
+    if let Some(code) = unknown_code(word) {
+        return Ok(Button::Unknown(parse_number(code)?));
+    }
+    parse_variant(word).map_err(|_| ParseError(format!("unknown button '{word}'")))
+}
+
+/// Looks a unit variant up by name through its serde derive.
+fn parse_variant<'de, T: Deserialize<'de>>(word: &'de str) -> Result<T, ValueError> {
+    T::deserialize(word.into_deserializer())
+}
+
+fn unknown_code(word: &str) -> Option<&str> {
+    word.strip_prefix("Unknown(")?.strip_suffix(')')
+}
@@ -0,0 +1,85 @@
+//! Which display server the desktop session runs on. `rdev` grabs and
+//! simulates input differently under X11 and Wayland, so both sides check
+//! this before promising anything that only works on one of them.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionType {
+    X11,
+    /// `xwayland` is set when X clients can still connect, which `rdev`
+    /// needs to name keys and find the pointer.
+    Wayland {
+        xwayland: bool,
+    },
+    /// A Linux console or an SSH login, with no display server at all.
+    Headless,
+    /// Windows and macOS, where none of this applies.
+    Native,
+}
+
+impl SessionType {
+    /// The session this process is running in.
+    pub fn detect() -> Self {
+        if cfg!(target_os = "linux") {
+            let var = |name| std::env::var(name).ok();
+            SessionType::from_vars(
+                var("XDG_SESSION_TYPE").as_deref(),
+                var("WAYLAND_DISPLAY").as_deref(),
+                var("DISPLAY").as_deref(),
+            )
+        } else {
+            SessionType::Native
+        }
+    }
+
+    /// Works the session out from the usual environment variables on Linux.
+    /// `XDG_SESSION_TYPE` wins when it names X11 or Wayland, as a nested or
+    /// forwarded display can leave the others set.
+    pub fn from_vars(
+        xdg_session_type: Option<&str>,
+        wayland_display: Option<&str>,
+        display: Option<&str>,
+    ) -> Self {
+        let set = |value: Option<&str>| value.is_some_and(|value| !value.is_empty());
+        let xwayland = set(display);
+        match xdg_session_type.map(str::to_ascii_lowercase).as_deref() {
+            Some("wayland") => SessionType::Wayland { xwayland },
+            Some("x11") => SessionType::X11,
+            _ if set(wayland_display) => SessionType::Wayland { xwayland },
+            _ if xwayland => SessionType::X11,
+            _ => SessionType::Headless,
+        }
+    }
+
+    pub fn is_wayland(self) -> bool {
+        matches!(self, SessionType::Wayland { .. })
+    }
+
+    /// Whether `rdev` can reach an X server, which its Linux backend needs
+    /// to name keys and to simulate anything.
+    pub fn has_x_display(self) -> bool {
+        matches!(
+            self,
+            SessionType::X11 | SessionType::Wayland { xwayland: true }
+        )
+    }
+
+    /// Whether pointer warps through `rdev::simulate` move the pointer the
+    /// user sees. Under Wayland they only move XWayland's copy of it.
+    pub fn can_warp_pointer(self) -> bool {
+        matches!(self, SessionType::X11 | SessionType::Native)
+    }
+}
+
+impl fmt::Display for SessionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SessionType::X11 => "X11",
+            SessionType::Wayland { xwayland: true } => "Wayland (with XWayland)",
+            SessionType::Wayland { xwayland: false } => "Wayland",
+            SessionType::Headless => "no display server",
+            SessionType::Native => "native",
+        })
+    }
+}
@@ -0,0 +1,85 @@
+//! Keys the OS acts on itself, so that a capture may never see them whole
+//! or an injection may never reach the desktop: Print Screen, Pause and Fn.
+//!
+//! What happens where:
+//!
+//! | Key          | Captured                                    | Injected                              |
+//! |--------------|---------------------------------------------|---------------------------------------|
+//! | Print Screen | Windows: release only; macOS: no such key   | macOS: as F13, which it is usually bound to |
+//! | Pause        | everywhere                                  | everywhere                            |
+//! | Fn           | macOS only; elsewhere the keyboard eats it  | not on Windows                        |
+//!
+//! Which keys get this handling is configurable (see [`DEFAULT_SYSTEM_KEYS`]),
+//! so one that misbehaves on some setup can be added or taken out.
+
+use std::collections::HashSet;
+
+use rdev::{EventType, Key};
+
+/// The keys handled as system keys unless configured otherwise.
+pub const DEFAULT_SYSTEM_KEYS: [Key; 3] = [Key::PrintScreen, Key::Pause, Key::Function];
+
+/// Why `key` may not be captured as pressed on this platform, if it may not.
+pub fn capture_limitation(key: Key) -> Option<&'static str> {
+    match key {
+        Key::PrintScreen if cfg!(target_os = "windows") => Some(
+            "Windows takes the Print Screen press for itself and only reports the release; \
+             the press is sent along with it",
+        ),
+        Key::PrintScreen if cfg!(target_os = "macos") => {
+            Some("Mac keyboards have no Print Screen key")
+        }
+        Key::Function if !cfg!(target_os = "macos") => {
+            Some("Fn is handled by the keyboard itself and never reaches the OS")
+        }
+        _ => None,
+    }
+}
+
+/// What to inject for `key` on this platform: the key itself, another key
+/// that does its job here, or `Err` with the reason it can't be injected.
+pub fn injection_key(key: Key) -> Result<Key, &'static str> {
+    match key {
+        // kVK_F13; macOS has no Print Screen and screenshot tools bind F13.
+        Key::PrintScreen if cfg!(target_os = "macos") => Ok(Key::Unknown(0x69)),
+        Key::Function if cfg!(target_os = "windows") => Err("Windows has no key code for Fn"),
+        _ => Ok(key),
+    }
+}
+
+/// Repairs system key events from a capture: a release whose press the OS
+/// swallowed should have that press sent just before it, so the other side
+/// sees a whole key stroke.
+#[derive(Debug, Clone, Default)]
+pub struct SystemKeyFilter {
+    keys: Vec<Key>,
+    pressed: HashSet<Key>,
+}
+
+impl SystemKeyFilter {
+    pub fn new(keys: Vec<Key>) -> Self {
+        Self {
+            keys,
+            pressed: HashSet::new(),
+        }
+    }
+
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    /// Notes a captured key event, returning the press to send before it if
+    /// it is the release of a system key that was never seen pressed.
+    pub fn missing_press(&mut self, event_type: &EventType) -> Option<EventType> {
+        match *event_type {
+            EventType::KeyPress(key) if self.keys.contains(&key) => {
+                self.pressed.insert(key);
+                None
+            }
+            EventType::KeyRelease(key) if self.keys.contains(&key) => {
+                (!self.pressed.remove(&key)).then_some(EventType::KeyPress(key))
+            }
+            _ => None,
+        }
+    }
+}
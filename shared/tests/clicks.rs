@@ -0,0 +1,48 @@
+use rdev::{Button, EventType};
+use shared::{
+    MouseMove,
+    clicks::DoubleClick,
+    codec::{Codec, WireFormat},
+};
+
+#[test]
+fn double_click_hints_round_trip_in_every_wire_format() {
+    let hint = DoubleClick {
+        button: Button::Middle,
+    };
+    for format in WireFormat::ALL {
+        let bytes = format.encode(&hint).unwrap();
+        assert_eq!(
+            format.decode_prefix::<DoubleClick>(&bytes).unwrap(),
+            (hint, bytes.len()),
+            "{format}"
+        );
+    }
+}
+
+#[test]
+fn double_click_hints_are_not_mistaken_for_other_input() {
+    for format in WireFormat::ALL {
+        let bytes = format
+            .encode(&DoubleClick {
+                button: Button::Left,
+            })
+            .unwrap();
+        assert!(
+            format.decode_prefix::<EventType>(&bytes).is_err(),
+            "{format}"
+        );
+        assert!(
+            format.decode_prefix::<MouseMove>(&bytes).is_err(),
+            "{format}"
+        );
+
+        let press = format
+            .encode(&EventType::ButtonPress(Button::Left))
+            .unwrap();
+        assert!(
+            format.decode_prefix::<DoubleClick>(&press).is_err(),
+            "{format}"
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use quinn::VarInt;
+use shared::{CloseCode, UnknownCloseCode};
+
+const ALL: [CloseCode; 11] = [
+    CloseCode::UserDisconnect,
+    CloseCode::Reset,
+    CloseCode::ProtocolError,
+    CloseCode::ServerFull,
+    CloseCode::AuthFailed,
+    CloseCode::Incompatible,
+    CloseCode::Shutdown,
+    CloseCode::StreamLimit,
+    CloseCode::Superseded,
+    CloseCode::NotAllowed,
+    CloseCode::TooLarge,
+];
+
+#[test]
+fn every_code_round_trips_through_var_int() {
+    for code in ALL {
+        let wire = VarInt::from(code);
+        assert_eq!(CloseCode::try_from(wire), Ok(code));
+    }
+}
+
+#[test]
+fn numeric_values_are_stable() {
+    let values: Vec<u64> = ALL
+        .iter()
+        .map(|code| VarInt::from(*code).into_inner())
+        .collect();
+    assert_eq!(values, vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+}
+
+#[test]
+fn unknown_codes_are_reported_with_their_value() {
+    let wire = VarInt::from_u32(4242);
+    assert_eq!(CloseCode::try_from(wire), Err(UnknownCloseCode(4242)));
+    assert_eq!(CloseCode::from_code(11), None);
+    assert_eq!(
+        UnknownCloseCode(4242).to_string(),
+        "unknown close code 4242"
+    );
+}
+
+#[test]
+fn every_code_has_a_user_message() {
+    for code in ALL {
+        assert!(!code.message().is_empty(), "{code} has no message");
+    }
+}
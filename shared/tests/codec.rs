@@ -0,0 +1,181 @@
+use rdev::{Button, EventType, Key};
+use serde::{Deserialize, Serialize};
+use shared::{
+    ControlRequest, MouseMove,
+    codec::{Codec, CodecError, Json, MessagePack, WireFormat},
+};
+
+fn events() -> Vec<EventType> {
+    vec![
+        EventType::KeyPress(Key::KeyA),
+        EventType::KeyRelease(Key::ShiftLeft),
+        EventType::ButtonPress(Button::Left),
+        EventType::MouseMove { x: 12.5, y: -3.0 },
+        EventType::Wheel {
+            delta_x: 0,
+            delta_y: -2,
+        },
+    ]
+}
+
+fn round_trips<C: Codec>(codec: C) {
+    let moved = MouseMove { dx: 1.5, dy: -7.25 };
+    let bytes = codec.encode(&moved).unwrap();
+    assert_eq!(codec.decode_prefix(&bytes), Ok((moved, bytes.len())));
+
+    for event in events() {
+        let bytes = codec.encode(&event).unwrap();
+        assert_eq!(codec.decode_prefix(&bytes), Ok((event, bytes.len())));
+    }
+}
+
+#[test]
+fn message_pack_round_trips() {
+    round_trips(MessagePack);
+    round_trips(WireFormat::MessagePack);
+}
+
+#[test]
+fn json_round_trips() {
+    round_trips(Json);
+    round_trips(WireFormat::Json);
+}
+
+#[test]
+fn transcoding_keeps_every_value_in_a_run() {
+    for (from, to) in [
+        (WireFormat::MessagePack, WireFormat::Json),
+        (WireFormat::Json, WireFormat::MessagePack),
+    ] {
+        let mut bytes = Vec::new();
+        for event in events() {
+            bytes.extend(from.encode(&event).unwrap());
+        }
+        let moved = MouseMove { dx: 1.5, dy: -7.25 };
+        bytes.extend(from.encode(&moved).unwrap());
+
+        let mut rest = &from.transcode(&bytes, to).unwrap()[..];
+        for event in events() {
+            let (decoded, used) = to.decode_prefix::<EventType>(rest).unwrap();
+            assert_eq!(decoded, event, "{from} to {to}");
+            rest = &rest[used..];
+        }
+        assert_eq!(to.decode_prefix(rest), Ok((moved, rest.len())));
+    }
+    let bytes = MessagePack.encode(&events()).unwrap();
+    assert_eq!(
+        WireFormat::MessagePack.transcode(&bytes, WireFormat::MessagePack),
+        Ok(bytes)
+    );
+    assert!(matches!(
+        WireFormat::Json.transcode(b"{\"KeyPress\"", WireFormat::MessagePack),
+        Err(CodecError::Incomplete)
+    ));
+}
+
+#[test]
+fn message_pack_is_the_default_and_matches_what_was_always_sent() {
+    assert_eq!(WireFormat::default(), WireFormat::MessagePack);
+    let moved = MouseMove { dx: 2.0, dy: 3.0 };
+    assert_eq!(
+        WireFormat::default().encode(&moved).unwrap(),
+        rmp_serde::to_vec(&moved).unwrap()
+    );
+}
+
+#[test]
+fn json_is_readable_one_value_per_line() {
+    let bytes = Json.encode(&EventType::KeyPress(Key::KeyA)).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes).unwrap(),
+        "{\"KeyPress\":\"KeyA\"}\n"
+    );
+}
+
+fn decodes_back_to_back<C: Codec>(codec: C) {
+    let moved = MouseMove { dx: 4.0, dy: 5.0 };
+    let press = EventType::KeyPress(Key::KeyB);
+    let mut bytes = codec.encode(&moved).unwrap();
+    let first = bytes.len();
+    bytes.extend(codec.encode(&press).unwrap());
+
+    assert_eq!(codec.decode_prefix(&bytes), Ok((moved, first)));
+    assert_eq!(
+        codec.decode_prefix(&bytes[first..]),
+        Ok((press, bytes.len() - first))
+    );
+}
+
+#[test]
+fn only_the_leading_value_is_taken() {
+    decodes_back_to_back(MessagePack);
+    decodes_back_to_back(Json);
+}
+
+fn truncation_is_incomplete<C: Codec>(codec: C) {
+    let bytes = codec.encode(&EventType::KeyPress(Key::KeyC)).unwrap();
+    for end in 0..bytes.len() - 1 {
+        assert_eq!(
+            codec.decode_prefix::<EventType>(&bytes[..end]),
+            Err(CodecError::Incomplete),
+            "{end} bytes"
+        );
+    }
+}
+
+#[test]
+fn a_cut_off_value_asks_for_more() {
+    truncation_is_incomplete(MessagePack);
+    truncation_is_incomplete(Json);
+}
+
+#[test]
+fn the_wrong_type_is_invalid_not_incomplete() {
+    for format in WireFormat::ALL {
+        let bytes = format.encode(&EventType::KeyPress(Key::KeyD)).unwrap();
+        assert!(matches!(
+            format.decode_prefix::<MouseMove>(&bytes),
+            Err(CodecError::Invalid(_))
+        ));
+    }
+}
+
+#[test]
+fn names_round_trip() {
+    for format in WireFormat::ALL {
+        assert_eq!(format.to_string().parse(), Ok(format));
+    }
+    assert_eq!(" JSON ".parse(), Ok(WireFormat::Json));
+    assert_eq!("MessagePack".parse(), Ok(WireFormat::MessagePack));
+    assert!("cbor".parse::<WireFormat>().is_err());
+}
+
+/// A hello as clients sent it before the format could be chosen.
+#[derive(Serialize, Deserialize)]
+enum OldRequest {
+    Hello {
+        resume_token: Option<[u8; 16]>,
+        observe: bool,
+        clock_micros: Option<u64>,
+        display_size: Option<shared::DisplaySize>,
+    },
+}
+
+#[test]
+fn a_hello_without_a_format_asks_for_message_pack() {
+    let old = OldRequest::Hello {
+        resume_token: None,
+        observe: false,
+        clock_micros: Some(7),
+        display_size: None,
+    };
+    let request: ControlRequest = rmp_serde::from_slice(&rmp_serde::to_vec(&old).unwrap()).unwrap();
+    assert!(matches!(
+        request,
+        ControlRequest::Hello {
+            clock_micros: Some(7),
+            wire_format: WireFormat::MessagePack,
+            ..
+        }
+    ));
+}
@@ -0,0 +1,28 @@
+use std::time::Instant;
+
+use quinn::congestion::{Bbr, Cubic, NewReno};
+use shared::congestion::CongestionControl;
+
+#[test]
+fn the_default_is_quinns_cubic() {
+    assert_eq!(CongestionControl::default(), CongestionControl::Cubic);
+}
+
+#[test]
+fn names_round_trip() {
+    for control in CongestionControl::ALL {
+        assert_eq!(control.to_string().parse(), Ok(control));
+    }
+    assert_eq!(" BBR ".parse(), Ok(CongestionControl::Bbr));
+    assert_eq!("NewReno".parse(), Ok(CongestionControl::NewReno));
+    assert!("vegas".parse::<CongestionControl>().is_err());
+}
+
+#[test]
+fn each_choice_builds_its_own_controller() {
+    let build =
+        |control: CongestionControl| control.factory().build(Instant::now(), 1200).into_any();
+    assert!(build(CongestionControl::Cubic).is::<Cubic>());
+    assert!(build(CongestionControl::NewReno).is::<NewReno>());
+    assert!(build(CongestionControl::Bbr).is::<Bbr>());
+}
@@ -0,0 +1,54 @@
+use shared::{ControlResponse, DisplayInfo};
+
+fn display(name: &str, x: i32, y: i32, width: u32, height: u32, is_primary: bool) -> DisplayInfo {
+    DisplayInfo {
+        name: name.to_string(),
+        x,
+        y,
+        width,
+        height,
+        is_primary,
+    }
+}
+
+#[test]
+fn displays_response_round_trips_through_msgpack() {
+    let response = ControlResponse::Displays(vec![
+        display("DP-1", 0, 0, 2560, 1440, true),
+        display("HDMI-1", -1920, 200, 1920, 1080, false),
+    ]);
+
+    let bytes = rmp_serde::to_vec(&response).expect("failed to serialise");
+    let decoded: ControlResponse = rmp_serde::from_slice(&bytes).expect("failed to decode");
+    assert_eq!(decoded, response);
+}
+
+#[test]
+fn primary_falls_back_to_first_display() {
+    let displays = vec![
+        display("left", 0, 0, 1920, 1080, false),
+        display("right", 1920, 0, 1920, 1080, false),
+    ];
+    assert_eq!(DisplayInfo::primary(&displays), Some(&displays[0]));
+    assert_eq!(DisplayInfo::primary(&[]), None);
+}
+
+#[test]
+fn points_scale_to_the_same_relative_spot() {
+    let local = display("laptop", 0, 0, 1280, 800, true);
+    let remote = display("desk", 1920, 0, 2560, 1600, true);
+
+    assert_eq!(remote.scale_from(&local, 640.0, 400.0), (3200.0, 800.0));
+    assert_eq!(remote.scale_from(&local, 0.0, 0.0), (1920.0, 0.0));
+}
+
+#[test]
+fn scaled_points_are_clamped_on_screen() {
+    let local = display("laptop", 0, 0, 1280, 800, true);
+    let remote = display("desk", 0, 0, 1920, 1080, true);
+
+    assert_eq!(remote.scale_from(&local, 1280.0, 800.0), (1919.0, 1079.0));
+    assert_eq!(remote.scale_from(&local, -50.0, -10.0), (0.0, 0.0));
+    assert!(remote.contains(1919.0, 1079.0));
+    assert!(!remote.contains(1920.0, 0.0));
+}
@@ -0,0 +1,77 @@
+use rdev::{EventType, Key};
+use shared::extra_keys::{ExtraKey, ExtraKeyInput};
+
+#[test]
+fn extra_keys_round_trip_through_msgpack() {
+    for key in ExtraKey::ALL {
+        for pressed in [true, false] {
+            let input = ExtraKeyInput { key, pressed };
+            let bytes = rmp_serde::to_vec(&input).unwrap();
+            assert_eq!(
+                rmp_serde::from_slice::<ExtraKeyInput>(&bytes).unwrap(),
+                input
+            );
+        }
+    }
+}
+
+#[test]
+fn native_keycodes_map_back_to_the_same_media_key() {
+    for media in ExtraKey::ALL {
+        if let Some(key) = media.key() {
+            assert!(matches!(key, Key::Unknown(_)), "{media:?} is {key:?}");
+            assert_eq!(ExtraKey::from_key(key), Some(media));
+        }
+    }
+}
+
+#[test]
+fn volume_keys_have_a_keycode_everywhere() {
+    for media in [ExtraKey::VolumeUp, ExtraKey::VolumeDown, ExtraKey::Mute] {
+        assert!(media.key().is_some(), "{media:?} has no keycode");
+    }
+}
+
+#[test]
+fn named_and_unmapped_keys_are_not_extra_keys() {
+    for key in [Key::KeyA, Key::F12, Key::Function, Key::Unknown(0)] {
+        assert_eq!(ExtraKey::from_key(key), None, "{key:?}");
+    }
+    assert_eq!(
+        ExtraKeyInput::from_event(&EventType::Wheel {
+            delta_x: 0,
+            delta_y: 1
+        }),
+        None
+    );
+}
+
+#[test]
+fn key_events_become_extra_key_input_and_back() {
+    let Some(key) = ExtraKey::PlayPause.key() else {
+        return;
+    };
+    let press = EventType::KeyPress(key);
+    let release = EventType::KeyRelease(key);
+    let input = ExtraKeyInput::from_event(&press).unwrap();
+    assert_eq!(
+        input,
+        ExtraKeyInput {
+            key: ExtraKey::PlayPause,
+            pressed: true
+        }
+    );
+    assert_eq!(input.event(), Some(press));
+    assert_eq!(
+        ExtraKeyInput::from_event(&release).unwrap().event(),
+        Some(release)
+    );
+}
+
+#[test]
+fn linux_codes_are_distinct() {
+    let mut codes: Vec<u16> = ExtraKey::ALL.iter().map(|key| key.linux_code()).collect();
+    codes.sort_unstable();
+    codes.dedup();
+    assert_eq!(codes.len(), ExtraKey::ALL.len());
+}
@@ -0,0 +1,29 @@
+use shared::hex::{format_hex, parse_hex};
+
+#[test]
+fn hex_parses_with_or_without_separators() {
+    let expected = vec![0x93, 0xa1, 0x41, 0xc3];
+    assert_eq!(parse_hex("93a141c3").unwrap(), expected);
+    assert_eq!(parse_hex("93 A1 41 C3").unwrap(), expected);
+    assert_eq!(parse_hex("  0x93a1\n41c3 ").unwrap(), expected);
+    assert_eq!(parse_hex("93:a1:41:c3").unwrap(), expected);
+    assert_eq!(parse_hex("93, a1, 41, c3").unwrap(), expected);
+}
+
+#[test]
+fn bad_hex_is_rejected() {
+    assert!(parse_hex("").is_err());
+    assert!(parse_hex("0x").is_err());
+    assert!(parse_hex("9").is_err());
+    assert!(parse_hex("93 a").is_err());
+    let err = parse_hex("93 zz").unwrap_err();
+    assert_eq!(err.to_string(), "'z' is not a hex digit");
+}
+
+#[test]
+fn formatted_hex_parses_back() {
+    let bytes = vec![0x00, 0x0f, 0x10, 0xff];
+    assert_eq!(format_hex(&bytes), "00 0f 10 ff");
+    assert_eq!(parse_hex(&format_hex(&bytes)).unwrap(), bytes);
+    assert_eq!(format_hex(&[]), "");
+}
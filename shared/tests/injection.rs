@@ -0,0 +1,68 @@
+use shared::{ControlRequest, ControlResponse, InjectionStats};
+
+#[test]
+fn injection_reports_round_trip_through_msgpack() {
+    let report = ControlResponse::Injection(InjectionStats {
+        mouse_injected: 1200,
+        mouse_dropped: 3,
+        keys_injected: u64::MAX,
+        keys_dropped: 0,
+        mouse_queued: 7,
+        keys_queued: 0,
+    });
+    let bytes = rmp_serde::to_vec(&report).unwrap();
+    assert_eq!(
+        rmp_serde::from_slice::<ControlResponse>(&bytes).unwrap(),
+        report
+    );
+}
+
+#[test]
+fn reports_without_queue_depths_still_decode() {
+    // As sent by servers from before the queue depths were reported.
+    let bytes = rmp_serde::to_vec(&(1u64, 2u64, 3u64, 4u64)).unwrap();
+    assert_eq!(
+        rmp_serde::from_slice::<InjectionStats>(&bytes).unwrap(),
+        InjectionStats {
+            mouse_injected: 1,
+            mouse_dropped: 2,
+            keys_injected: 3,
+            keys_dropped: 4,
+            ..InjectionStats::default()
+        }
+    );
+}
+
+#[test]
+fn watch_request_round_trips_through_msgpack() {
+    let bytes = rmp_serde::to_vec(&ControlRequest::WatchInjection).unwrap();
+    assert_eq!(
+        rmp_serde::from_slice::<ControlRequest>(&bytes).unwrap(),
+        ControlRequest::WatchInjection
+    );
+}
+
+#[test]
+fn consecutive_reports_decode_one_at_a_time() {
+    let first = ControlResponse::Injection(InjectionStats {
+        keys_injected: 2,
+        ..InjectionStats::default()
+    });
+    let second = ControlResponse::Injection(InjectionStats {
+        keys_injected: 5,
+        mouse_dropped: 1,
+        ..InjectionStats::default()
+    });
+    let mut bytes = rmp_serde::to_vec(&first).unwrap();
+    bytes.extend(rmp_serde::to_vec(&second).unwrap());
+
+    let mut cursor = std::io::Cursor::new(bytes.as_slice());
+    assert_eq!(
+        rmp_serde::decode::from_read::<_, ControlResponse>(&mut cursor).unwrap(),
+        first
+    );
+    assert_eq!(
+        rmp_serde::decode::from_read::<_, ControlResponse>(&mut cursor).unwrap(),
+        second
+    );
+}
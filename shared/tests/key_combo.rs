@@ -0,0 +1,106 @@
+use rdev::{EventType, Key};
+use shared::{
+    CharInput, MouseMove,
+    codec::{Codec, WireFormat},
+    key_combo::{KeyCombo, MAX_COMBO_KEYS, secure_attention_combos},
+};
+
+#[test]
+fn keys_are_pressed_in_order_and_released_in_reverse() {
+    assert_eq!(
+        KeyCombo::ctrl_alt_del().events(&[]),
+        vec![
+            EventType::KeyPress(Key::ControlLeft),
+            EventType::KeyPress(Key::Alt),
+            EventType::KeyPress(Key::Delete),
+            EventType::KeyRelease(Key::Delete),
+            EventType::KeyRelease(Key::Alt),
+            EventType::KeyRelease(Key::ControlLeft),
+        ]
+    );
+}
+
+#[test]
+fn keys_already_held_are_left_alone() {
+    let combo = KeyCombo::ctrl_alt_f(2).unwrap();
+    assert_eq!(
+        combo.events(&[Key::ControlLeft, Key::Alt]),
+        vec![EventType::KeyPress(Key::F2), EventType::KeyRelease(Key::F2)]
+    );
+}
+
+#[test]
+fn console_switches_cover_function_keys_only() {
+    assert_eq!(
+        KeyCombo::ctrl_alt_f(12).unwrap().keys(),
+        &[Key::ControlLeft, Key::Alt, Key::F12]
+    );
+    assert_eq!(KeyCombo::ctrl_alt_f(0), None);
+    assert_eq!(KeyCombo::ctrl_alt_f(13), None);
+}
+
+#[test]
+fn problems_are_reported() {
+    assert_eq!(KeyCombo::ctrl_alt_del().problem(), None);
+    assert!(KeyCombo::new(Vec::new()).problem().is_some());
+    assert!(
+        KeyCombo::new(vec![Key::KeyA; MAX_COMBO_KEYS + 1])
+            .problem()
+            .unwrap()
+            .contains("more than")
+    );
+    assert!(
+        KeyCombo::new(vec![Key::ControlLeft, Key::KeyA, Key::ControlLeft])
+            .problem()
+            .unwrap()
+            .contains("ControlLeft")
+    );
+}
+
+#[test]
+fn offered_combos_are_valid_and_start_with_ctrl_alt_del() {
+    let combos = secure_attention_combos();
+    assert_eq!(combos[0].1, KeyCombo::ctrl_alt_del());
+    assert_eq!(combos[0].1.describe(), "ControlLeft+Alt+Delete");
+    for (label, combo) in &combos {
+        assert_eq!(combo.problem(), None, "{label}");
+    }
+}
+
+#[test]
+fn combos_round_trip_in_every_wire_format() {
+    let combo = KeyCombo::ctrl_alt_f(3).unwrap();
+    for format in WireFormat::ALL {
+        let bytes = format.encode(&combo).unwrap();
+        assert_eq!(
+            format.decode_prefix::<KeyCombo>(&bytes).unwrap(),
+            (combo.clone(), bytes.len()),
+            "{format}"
+        );
+    }
+}
+
+#[test]
+fn combos_are_not_mistaken_for_other_input() {
+    for format in WireFormat::ALL {
+        let bytes = format.encode(&KeyCombo::ctrl_alt_del()).unwrap();
+        assert!(
+            format.decode_prefix::<EventType>(&bytes).is_err(),
+            "{format}"
+        );
+        assert!(
+            format.decode_prefix::<MouseMove>(&bytes).is_err(),
+            "{format}"
+        );
+        assert!(
+            format.decode_prefix::<CharInput>(&bytes).is_err(),
+            "{format}"
+        );
+
+        let event = format.encode(&EventType::KeyPress(Key::Delete)).unwrap();
+        assert!(
+            format.decode_prefix::<KeyCombo>(&event).is_err(),
+            "{format}"
+        );
+    }
+}
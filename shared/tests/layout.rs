@@ -0,0 +1,105 @@
+use rdev::{EventType, Key};
+use shared::{
+    CharInput,
+    layout::{FR_AZERTY, KeyboardLayout, Keystroke, LayoutTable, US_QWERTY},
+};
+
+/// Types `ch` the way a QWERTY client would derive it and an AZERTY server
+/// would retype it.
+fn qwerty_to_azerty(stroke: Keystroke) -> Option<Keystroke> {
+    let ch = US_QWERTY.char_for(stroke)?;
+    FR_AZERTY.keystroke_for(ch)
+}
+
+#[test]
+fn letters_move_to_their_azerty_positions() {
+    assert_eq!(
+        qwerty_to_azerty(Keystroke::plain(Key::KeyA)),
+        Some(Keystroke::plain(Key::KeyQ))
+    );
+    assert_eq!(
+        qwerty_to_azerty(Keystroke::plain(Key::KeyM)),
+        Some(Keystroke::plain(Key::SemiColon))
+    );
+    assert_eq!(
+        qwerty_to_azerty(Keystroke::shifted(Key::KeyZ)),
+        Some(Keystroke::shifted(Key::KeyW))
+    );
+}
+
+#[test]
+fn digits_and_symbols_pick_up_the_right_modifiers() {
+    // Unshifted digits on QWERTY need Shift on AZERTY.
+    assert_eq!(
+        qwerty_to_azerty(Keystroke::plain(Key::Num1)),
+        Some(Keystroke::shifted(Key::Num1))
+    );
+    // Shift+1 is '!', which AZERTY types unshifted on its own key.
+    assert_eq!(
+        qwerty_to_azerty(Keystroke::shifted(Key::Num1)),
+        Some(Keystroke::plain(Key::Slash))
+    );
+    assert_eq!(
+        qwerty_to_azerty(Keystroke::shifted(Key::Num2)),
+        Some(Keystroke::alt_gr(Key::Num0))
+    );
+}
+
+#[test]
+fn characters_outside_the_layout_are_not_typed() {
+    assert_eq!(US_QWERTY.keystroke_for('é'), None);
+    assert_eq!(FR_AZERTY.char_for(Keystroke::alt_gr(Key::KeyQ)), None);
+    // Upper case only follows from plain letters, not from 'é'.
+    assert_eq!(FR_AZERTY.keystroke_for('É'), None);
+}
+
+#[test]
+fn keystroke_events_wrap_needed_modifiers() {
+    assert_eq!(
+        Keystroke::shifted(Key::KeyQ).events(&[]),
+        vec![
+            EventType::KeyPress(Key::ShiftLeft),
+            EventType::KeyPress(Key::KeyQ),
+            EventType::KeyRelease(Key::KeyQ),
+            EventType::KeyRelease(Key::ShiftLeft),
+        ]
+    );
+}
+
+#[test]
+fn keystroke_events_lift_held_modifiers_it_must_not_see() {
+    // The peer holds Shift for '!', but AZERTY types it unshifted.
+    assert_eq!(
+        Keystroke::plain(Key::Slash).events(&[Key::ShiftRight]),
+        vec![
+            EventType::KeyRelease(Key::ShiftRight),
+            EventType::KeyPress(Key::Slash),
+            EventType::KeyRelease(Key::Slash),
+            EventType::KeyPress(Key::ShiftRight),
+        ]
+    );
+    // A modifier that is already down is left alone.
+    assert_eq!(
+        Keystroke::shifted(Key::Num1).events(&[Key::ShiftLeft]),
+        vec![
+            EventType::KeyPress(Key::Num1),
+            EventType::KeyRelease(Key::Num1),
+        ]
+    );
+}
+
+#[test]
+fn layouts_are_found_by_config_name() {
+    assert_eq!(
+        LayoutTable::by_name("FR").map(|layout| layout.name),
+        Some("fr")
+    );
+    assert!(LayoutTable::by_name("dvorak").is_none());
+}
+
+#[test]
+fn char_input_round_trips_through_msgpack() {
+    let bytes = rmp_serde::to_vec(&CharInput('ç')).expect("failed to serialise");
+    let decoded: CharInput = rmp_serde::from_slice(&bytes).expect("failed to deserialise");
+    assert_eq!(decoded, CharInput('ç'));
+}
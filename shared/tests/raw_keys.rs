@@ -0,0 +1,81 @@
+use rdev::{EventType, Key};
+use shared::{
+    MouseMove,
+    extra_keys::ExtraKey,
+    raw_keys::{KEY_MAX, RawKeyInput, is_key_code},
+};
+
+#[test]
+fn raw_keys_round_trip_through_msgpack() {
+    let input = RawKeyInput {
+        code: 240,
+        pressed: true,
+    };
+    let bytes = rmp_serde::to_vec(&input).unwrap();
+    assert_eq!(rmp_serde::from_slice::<RawKeyInput>(&bytes).unwrap(), input);
+    // Tagged, so neither reads as the other.
+    assert!(rmp_serde::from_slice::<MouseMove>(&bytes).is_err());
+    let moved = rmp_serde::to_vec(&MouseMove { dx: 240.0, dy: 1.0 }).unwrap();
+    assert!(rmp_serde::from_slice::<RawKeyInput>(&moved).is_err());
+}
+
+#[test]
+fn only_key_codes_are_valid() {
+    assert!(is_key_code(1));
+    assert!(is_key_code(0xff));
+    assert!(is_key_code(464));
+    assert!(is_key_code(KEY_MAX));
+    assert!(!is_key_code(0));
+    assert!(!is_key_code(KEY_MAX + 1));
+    // BTN_LEFT, BTN_DPAD_UP and BTN_TRIGGER_HAPPY1.
+    for button in [0x110, 0x220, 0x2c0] {
+        assert!(!is_key_code(button), "{button:#x}");
+    }
+    assert_eq!(RawKeyInput::new(0x110, true), None);
+    assert_eq!(
+        RawKeyInput::new(30, false),
+        Some(RawKeyInput {
+            code: 30,
+            pressed: false
+        })
+    );
+}
+
+#[test]
+fn named_and_extra_keys_are_not_raw() {
+    assert_eq!(
+        RawKeyInput::from_event(&EventType::KeyPress(Key::KeyA)),
+        None
+    );
+    if let Some(key) = ExtraKey::Mute.key() {
+        assert_eq!(RawKeyInput::from_event(&EventType::KeyPress(key)), None);
+    }
+    assert_eq!(
+        RawKeyInput::from_event(&EventType::Wheel {
+            delta_x: 0,
+            delta_y: 1
+        }),
+        None
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn linux_keycodes_become_kernel_codes() {
+    // X11 keycode 236 is KEY_KBDILLUMTOGGLE (228) plus 8.
+    assert_eq!(
+        RawKeyInput::from_event(&EventType::KeyRelease(Key::Unknown(236))),
+        Some(RawKeyInput {
+            code: 228,
+            pressed: false
+        })
+    );
+    assert_eq!(
+        RawKeyInput::from_event(&EventType::KeyPress(Key::Unknown(3))),
+        None
+    );
+    assert_eq!(
+        RawKeyInput::from_event(&EventType::KeyPress(Key::Unknown(0x110 + 8))),
+        None
+    );
+}
@@ -0,0 +1,44 @@
+use rdev::{Button, EventType, Key};
+use shared::{
+    MouseMove,
+    script::{ScriptEvent, format_script_event, parse_line},
+};
+
+#[test]
+fn formatted_events_parse_back_unchanged() {
+    let events = [
+        ScriptEvent::Move(MouseMove { dx: 12.0, dy: -3.5 }),
+        ScriptEvent::Move(MouseMove { dx: 0.1, dy: 1e-7 }),
+        ScriptEvent::Input(EventType::MouseMove {
+            x: 640.0,
+            y: 360.25,
+        }),
+        ScriptEvent::Input(EventType::Wheel {
+            delta_x: 0,
+            delta_y: -3,
+        }),
+        ScriptEvent::Input(EventType::KeyPress(Key::KeyA)),
+        ScriptEvent::Input(EventType::KeyRelease(Key::ShiftLeft)),
+        ScriptEvent::Input(EventType::KeyPress(Key::Unknown(172))),
+        ScriptEvent::Input(EventType::ButtonPress(Button::Left)),
+        ScriptEvent::Input(EventType::ButtonRelease(Button::Unknown(8))),
+    ];
+
+    for event in events {
+        let line = format_script_event(&event);
+        assert_eq!(parse_line(&line), Ok(Some(event)), "line: {line}");
+    }
+}
+
+#[test]
+fn blank_lines_and_comments_are_skipped() {
+    assert_eq!(parse_line(""), Ok(None));
+    assert_eq!(parse_line("   # recorded session"), Ok(None));
+}
+
+#[test]
+fn malformed_lines_are_rejected() {
+    assert!(parse_line("key press NotAKey").is_err());
+    assert!(parse_line("move 1").is_err());
+    assert!(parse_line("button hold Left").is_err());
+}
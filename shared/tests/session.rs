@@ -0,0 +1,49 @@
+use shared::session::SessionType;
+
+#[test]
+fn xdg_session_type_decides_when_set() {
+    assert_eq!(
+        SessionType::from_vars(Some("wayland"), Some("wayland-0"), Some(":0")),
+        SessionType::Wayland { xwayland: true }
+    );
+    assert_eq!(
+        SessionType::from_vars(Some("wayland"), None, None),
+        SessionType::Wayland { xwayland: false }
+    );
+    // A display forwarded into an X11 session leaves WAYLAND_DISPLAY behind.
+    assert_eq!(
+        SessionType::from_vars(Some("X11"), Some("wayland-0"), Some(":0")),
+        SessionType::X11
+    );
+}
+
+#[test]
+fn the_display_variables_decide_otherwise() {
+    assert_eq!(
+        SessionType::from_vars(None, Some("wayland-0"), None),
+        SessionType::Wayland { xwayland: false }
+    );
+    assert_eq!(
+        SessionType::from_vars(Some("tty"), None, Some(":1")),
+        SessionType::X11
+    );
+    assert_eq!(
+        SessionType::from_vars(Some("tty"), Some(""), Some("")),
+        SessionType::Headless
+    );
+    assert_eq!(
+        SessionType::from_vars(None, None, None),
+        SessionType::Headless
+    );
+}
+
+#[test]
+fn only_x11_and_native_sessions_warp_the_pointer() {
+    assert!(SessionType::X11.can_warp_pointer());
+    assert!(SessionType::Native.can_warp_pointer());
+    assert!(!SessionType::Wayland { xwayland: true }.can_warp_pointer());
+    assert!(!SessionType::Headless.can_warp_pointer());
+
+    assert!(SessionType::Wayland { xwayland: true }.has_x_display());
+    assert!(!SessionType::Wayland { xwayland: false }.has_x_display());
+}
@@ -0,0 +1,59 @@
+use rdev::{EventType, Key};
+use shared::system_keys::{
+    DEFAULT_SYSTEM_KEYS, SystemKeyFilter, capture_limitation, injection_key,
+};
+
+#[test]
+fn a_release_without_its_press_gets_one() {
+    let mut filter = SystemKeyFilter::new(DEFAULT_SYSTEM_KEYS.to_vec());
+    assert_eq!(
+        filter.missing_press(&EventType::KeyRelease(Key::PrintScreen)),
+        Some(EventType::KeyPress(Key::PrintScreen))
+    );
+}
+
+#[test]
+fn whole_key_strokes_pass_through() {
+    let mut filter = SystemKeyFilter::new(DEFAULT_SYSTEM_KEYS.to_vec());
+    assert_eq!(filter.missing_press(&EventType::KeyPress(Key::Pause)), None);
+    assert_eq!(
+        filter.missing_press(&EventType::KeyRelease(Key::Pause)),
+        None
+    );
+    // The press was used up by the first release.
+    assert_eq!(
+        filter.missing_press(&EventType::KeyRelease(Key::Pause)),
+        Some(EventType::KeyPress(Key::Pause))
+    );
+}
+
+#[test]
+fn only_listed_keys_are_repaired() {
+    let mut filter = SystemKeyFilter::new(vec![Key::Pause]);
+    assert_eq!(filter.keys(), [Key::Pause]);
+    assert_eq!(
+        filter.missing_press(&EventType::KeyRelease(Key::PrintScreen)),
+        None
+    );
+    assert_eq!(
+        filter.missing_press(&EventType::KeyRelease(Key::KeyA)),
+        None
+    );
+}
+
+#[test]
+fn ordinary_keys_have_no_limitations() {
+    assert_eq!(capture_limitation(Key::KeyA), None);
+    assert_eq!(capture_limitation(Key::Pause), None);
+    assert_eq!(injection_key(Key::KeyA), Ok(Key::KeyA));
+    assert_eq!(injection_key(Key::Pause), Ok(Key::Pause));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn linux_captures_print_screen_but_not_fn() {
+    assert_eq!(capture_limitation(Key::PrintScreen), None);
+    assert!(capture_limitation(Key::Function).is_some());
+    assert_eq!(injection_key(Key::PrintScreen), Ok(Key::PrintScreen));
+    assert_eq!(injection_key(Key::Function), Ok(Key::Function));
+}